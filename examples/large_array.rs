@@ -0,0 +1,48 @@
+use std::time::Instant;
+
+use vbap::config::MAX_SPEAKERS;
+use vbap::random_layout::{LayoutShape, RandomLayout};
+use vbap::VBAPanner;
+
+/// Per-direction gain latency we aim for at the largest supported layout.
+const TARGET_LATENCY_US: f64 = 50.0;
+
+fn main() {
+    for n in [64, 128, 256, MAX_SPEAKERS] {
+        let positions = RandomLayout::new(n, LayoutShape::Sphere)
+            .with_min_separation(3.0)
+            .positions()
+            .unwrap();
+
+        let start = Instant::now();
+        let panner = VBAPanner::builder()
+            .add_speakers(&positions)
+            .build()
+            .unwrap();
+        let build = start.elapsed();
+
+        let tuples = panner.config().tuples();
+        let mut gains = vec![0.0; n];
+        let calls = 10_000;
+        let start = Instant::now();
+        for i in 0..calls {
+            let azimuth = (i as f64 * 7.3) % 360.0 - 180.0;
+            let elevation = (i as f64 * 3.1) % 180.0 - 90.0;
+            panner.compute_gains_into(azimuth, elevation, &mut gains);
+        }
+        let latency = start.elapsed().as_secs_f64() * 1e6 / calls as f64;
+
+        println!(
+            "{n:4} speakers: {:4} tuples, {:6.1} KB, build {:7.1} ms, {:5.2} us/direction{}",
+            tuples.len(),
+            tuples.heap_size() as f64 / 1024.0,
+            build.as_secs_f64() * 1e3,
+            latency,
+            if latency > TARGET_LATENCY_US {
+                " (over target)"
+            } else {
+                ""
+            }
+        );
+    }
+}
@@ -0,0 +1,60 @@
+/*
+ * C API for the vbap crate (Vector Base Amplitude Panning).
+ *
+ * Build the library with the `ffi` feature:
+ *
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ *
+ * Angles are in degrees: azimuth 0 = front, 90 = left, -90 = right,
+ * 180 = rear; elevation 0 = horizontal, 90 = above.
+ */
+
+#ifndef VBAP_H
+#define VBAP_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status codes returned by vbap_compute_gains. */
+#define VBAP_OK 0
+#define VBAP_ERROR_NULL_POINTER (-1)
+#define VBAP_ERROR_BUFFER_TOO_SMALL (-2)
+
+/* Opaque panner handle. */
+typedef struct VBAPanner VBAPanner;
+
+/*
+ * Create a panner from `num_speakers` speaker directions.
+ * Returns NULL if the layout cannot be built.
+ */
+VBAPanner *vbap_panner_create(const double *azimuths, const double *elevations,
+                              size_t num_speakers);
+
+/*
+ * Create a panner for a preset layout: "stereo", "stereo-wide", "lcr",
+ * "quad", "5.1", "7.1", "7.1.4", "5.1.4", "hexagon" or "octagon".
+ * Returns NULL for an unknown name.
+ */
+VBAPanner *vbap_panner_create_preset(const char *name);
+
+/* Number of speakers of a panner, 0 for NULL. */
+size_t vbap_panner_num_speakers(const VBAPanner *panner);
+
+/*
+ * Write one gain per speaker to `gains`, which holds `len` values.
+ * Returns VBAP_OK or a negative VBAP_ERROR_* code.
+ */
+int vbap_compute_gains(const VBAPanner *panner, double azimuth, double elevation,
+                       double *gains, size_t len);
+
+/* Release a panner. NULL is ignored. */
+void vbap_panner_destroy(VBAPanner *panner);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* VBAP_H */
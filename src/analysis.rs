@@ -0,0 +1,486 @@
+//! Analysis tools for layouts and scenes.
+
+use glam::DVec3;
+
+use crate::math::spherical_to_cartesian;
+use crate::panner::VBAPanner;
+use crate::util::{db_to_lin, lin_to_db};
+
+/// A source in a scene, for level analysis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SourceLevel {
+    /// Azimuth in degrees.
+    pub azimuth: f64,
+    /// Elevation in degrees.
+    pub elevation: f64,
+    /// Peak level of the source signal in dBFS.
+    pub level_db: f64,
+}
+
+impl SourceLevel {
+    /// Create a source at a position with a peak level in dBFS.
+    pub fn new(azimuth: f64, elevation: f64, level_db: f64) -> Self {
+        Self {
+            azimuth,
+            elevation,
+            level_db,
+        }
+    }
+}
+
+/// Estimated level of one output channel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChannelHeadroom {
+    /// Speaker index.
+    pub speaker: usize,
+    /// Worst-case peak in dBFS, assuming all source peaks line up (coherent sum).
+    pub peak_db: f64,
+    /// Expected level in dBFS for uncorrelated sources (power sum).
+    pub rms_db: f64,
+    /// Remaining headroom in dB before the worst-case peak reaches 0 dBFS.
+    pub headroom_db: f64,
+    /// Whether the worst-case peak leaves less headroom than the requested margin.
+    pub likely_to_clip: bool,
+}
+
+/// Per-speaker gain staging estimate for a scene.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadroomReport {
+    /// One entry per speaker, in speaker order.
+    pub channels: Vec<ChannelHeadroom>,
+}
+
+impl HeadroomReport {
+    /// Channels flagged as likely to clip.
+    pub fn clipping_channels(&self) -> impl Iterator<Item = &ChannelHeadroom> {
+        self.channels.iter().filter(|c| c.likely_to_clip)
+    }
+
+    /// The channel with the least headroom, if any.
+    pub fn worst_channel(&self) -> Option<&ChannelHeadroom> {
+        self.channels
+            .iter()
+            .min_by(|a, b| a.headroom_db.total_cmp(&b.headroom_db))
+    }
+}
+
+/// Estimate per-speaker summed levels for a set of sources.
+///
+/// Each source is panned with `panner`, and its peak level is distributed
+/// over the speakers by its gains. Channels whose worst-case (coherent)
+/// peak leaves less than `margin_db` of headroom are flagged, which lets
+/// system techs pre-validate a show file before it reaches the amplifiers.
+///
+/// # Example
+///
+/// ```
+/// use vbap::analysis::{headroom_report, SourceLevel};
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().stereo().build().unwrap();
+/// let sources = [
+///     SourceLevel::new(30.0, 0.0, -3.0),
+///     SourceLevel::new(30.0, 0.0, -3.0),
+/// ];
+///
+/// let report = headroom_report(&panner, &sources, 1.0);
+/// // Two -3 dBFS peaks on the same speaker can reach +3 dBFS
+/// assert!(report.channels[0].likely_to_clip);
+/// ```
+pub fn headroom_report(
+    panner: &VBAPanner,
+    sources: &[SourceLevel],
+    margin_db: f64,
+) -> HeadroomReport {
+    let n = panner.num_speakers();
+    let mut peak = vec![0.0; n];
+    let mut power = vec![0.0; n];
+    let mut gains = vec![0.0; n];
+
+    for source in sources {
+        let amplitude = db_to_lin(source.level_db);
+        panner.compute_gains_into(source.azimuth, source.elevation, &mut gains);
+        for i in 0..n {
+            let level = gains[i] * amplitude;
+            peak[i] += level;
+            power[i] += level * level;
+        }
+    }
+
+    let channels = (0..n)
+        .map(|speaker| {
+            let peak_db = lin_to_db(peak[speaker]);
+            let headroom_db = -peak_db;
+            ChannelHeadroom {
+                speaker,
+                peak_db,
+                rms_db: lin_to_db(power[speaker].sqrt()),
+                headroom_db,
+                likely_to_clip: headroom_db < margin_db,
+            }
+        })
+        .collect();
+
+    HeadroomReport { channels }
+}
+
+/// Gerzon's localization vectors for one panned direction.
+///
+/// The velocity vector `rV` (gain-weighted mean of the speaker directions)
+/// predicts low-frequency localization, the energy vector `rE` (weighted by
+/// squared gains) high-frequency localization. A source played by a single
+/// speaker has both of length 1; the shorter they get, the more the image
+/// is spread over several speakers.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionMetrics {
+    /// Velocity vector rV, in Cartesian coordinates.
+    pub velocity: DVec3,
+    /// Energy vector rE, in Cartesian coordinates.
+    pub energy: DVec3,
+    /// Angle in degrees between the intended direction and rV.
+    pub velocity_error: f64,
+    /// Angle in degrees between the intended direction and rE.
+    pub angular_error: f64,
+    /// Perceived source width in degrees, `2 * acos(|rE|)`.
+    pub spread: f64,
+}
+
+impl DirectionMetrics {
+    /// Length of the velocity vector, |rV|.
+    #[inline]
+    pub fn velocity_magnitude(&self) -> f64 {
+        self.velocity.length()
+    }
+
+    /// Length of the energy vector, |rE|.
+    #[inline]
+    pub fn energy_magnitude(&self) -> f64 {
+        self.energy.length()
+    }
+}
+
+/// Compute the velocity and energy vectors of a source panned to a
+/// direction, with the spread and localization error they predict.
+///
+/// The direction is given in the panner's convention.
+///
+/// # Example
+///
+/// ```
+/// use vbap::analysis::direction_metrics;
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().stereo().build().unwrap();
+///
+/// // On a speaker: a point source in the right place
+/// let on_speaker = direction_metrics(&panner, 30.0, 0.0);
+/// assert!(on_speaker.spread < 1e-3 && on_speaker.angular_error < 1e-3);
+///
+/// // A phantom center is wider, but still in the center
+/// let phantom = direction_metrics(&panner, 0.0, 0.0);
+/// assert!(phantom.spread > 50.0 && phantom.angular_error < 1e-9);
+/// ```
+pub fn direction_metrics(panner: &VBAPanner, azimuth: f64, elevation: f64) -> DirectionMetrics {
+    let gains = panner.compute_gains(azimuth, elevation);
+    let (azimuth, elevation) = panner.config().convention().to_native(azimuth, elevation);
+    let target = spherical_to_cartesian(azimuth, elevation);
+
+    let mut velocity = DVec3::ZERO;
+    let mut energy = DVec3::ZERO;
+    let (mut amplitude, mut power) = (0.0, 0.0);
+    for (speaker, &gain) in panner.speakers().iter().zip(&gains) {
+        let direction = speaker.cartesian().normalize();
+        velocity += direction * gain;
+        energy += direction * gain * gain;
+        amplitude += gain;
+        power += gain * gain;
+    }
+    if amplitude != 0.0 {
+        velocity /= amplitude;
+    }
+    if power > 0.0 {
+        energy /= power;
+    }
+
+    let error = |v: DVec3| {
+        if v == DVec3::ZERO {
+            180.0
+        } else {
+            v.angle_between(target).to_degrees()
+        }
+    };
+    DirectionMetrics {
+        velocity,
+        energy,
+        velocity_error: error(velocity),
+        angular_error: error(energy),
+        spread: 2.0 * energy.length().min(1.0).acos().to_degrees(),
+    }
+}
+
+/// Speaker gains and localization metrics sampled over a regular
+/// azimuth/elevation grid, see [`sample_sphere`].
+///
+/// Grid points are stored elevation-major: row `e` holds every azimuth at
+/// `elevations()[e]`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GainField {
+    resolution: f64,
+    num_speakers: usize,
+    azimuths: Vec<f64>,
+    elevations: Vec<f64>,
+    /// Gains of every speaker at each grid point.
+    gains: Vec<f64>,
+    metrics: Vec<DirectionMetrics>,
+    covered: Vec<bool>,
+}
+
+impl GainField {
+    /// Get the grid step in degrees.
+    #[inline]
+    pub fn resolution(&self) -> f64 {
+        self.resolution
+    }
+
+    /// Get the number of speakers.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.num_speakers
+    }
+
+    /// Get the grid azimuths in degrees, from -180° up to (not including)
+    /// 180°.
+    #[inline]
+    pub fn azimuths(&self) -> &[f64] {
+        &self.azimuths
+    }
+
+    /// Get the grid elevations in degrees, from -90° up to 90°.
+    #[inline]
+    pub fn elevations(&self) -> &[f64] {
+        &self.elevations
+    }
+
+    /// Get the speaker gains at a grid point.
+    ///
+    /// # Panics
+    /// Panics if an index is out of range.
+    pub fn gains(&self, azimuth_index: usize, elevation_index: usize) -> &[f64] {
+        let point = self.point(azimuth_index, elevation_index);
+        &self.gains[point * self.num_speakers..(point + 1) * self.num_speakers]
+    }
+
+    /// Get the localization metrics at a grid point.
+    ///
+    /// # Panics
+    /// Panics if an index is out of range.
+    pub fn metrics(&self, azimuth_index: usize, elevation_index: usize) -> &DirectionMetrics {
+        &self.metrics[self.point(azimuth_index, elevation_index)]
+    }
+
+    /// Get one speaker's gain at every grid point, elevation-major, ready to
+    /// plot as a coverage map.
+    ///
+    /// # Panics
+    /// Panics if `speaker` is out of range.
+    pub fn speaker_map(&self, speaker: usize) -> Vec<f64> {
+        assert!(speaker < self.num_speakers, "speaker index out of range");
+        self.gains
+            .iter()
+            .skip(speaker)
+            .step_by(self.num_speakers)
+            .copied()
+            .collect()
+    }
+
+    /// Get the gains at every grid point as a `points × speakers` array,
+    /// points elevation-major.
+    #[cfg(feature = "ndarray")]
+    pub fn gain_array(&self) -> ndarray::Array2<f64> {
+        let points = self.gains.len() / self.num_speakers;
+        ndarray::Array2::from_shape_vec((points, self.num_speakers), self.gains.clone())
+            .expect("gains hold one row per grid point")
+    }
+
+    /// Get the grid directions no speaker tuple covers: holes in the
+    /// layout, or the hemisphere a dome does not reach.
+    pub fn holes(&self) -> Vec<(f64, f64)> {
+        self.points()
+            .zip(&self.covered)
+            .filter(|(_, &covered)| !covered)
+            .map(|(direction, _)| direction)
+            .collect()
+    }
+
+    /// Get the covered fraction of the sphere, weighting each grid point by
+    /// the solid angle it stands for.
+    pub fn coverage(&self) -> f64 {
+        let (mut covered, mut total) = (0.0, 0.0);
+        for ((_, elevation), &is_covered) in self.points().zip(&self.covered) {
+            let weight = elevation.to_radians().cos();
+            total += weight;
+            if is_covered {
+                covered += weight;
+            }
+        }
+        if total > 0.0 {
+            covered / total
+        } else {
+            0.0
+        }
+    }
+
+    fn point(&self, azimuth_index: usize, elevation_index: usize) -> usize {
+        assert!(
+            azimuth_index < self.azimuths.len() && elevation_index < self.elevations.len(),
+            "grid index out of range"
+        );
+        elevation_index * self.azimuths.len() + azimuth_index
+    }
+
+    /// Directions of all grid points, in storage order.
+    fn points(&self) -> impl Iterator<Item = (f64, f64)> + '_ {
+        self.elevations.iter().flat_map(move |&elevation| {
+            self.azimuths
+                .iter()
+                .map(move |&azimuth| (azimuth, elevation))
+        })
+    }
+}
+
+/// Sample a panner's gains and localization metrics every `resolution`
+/// degrees in azimuth and elevation.
+///
+/// Directions are in the panner's convention. Plot
+/// [`speaker_map`](GainField::speaker_map) or the spread of the
+/// [`metrics`](GainField::metrics) to see a layout's coverage, and check
+/// [`holes`](GainField::holes) for directions it cannot reproduce.
+///
+/// # Panics
+/// Panics if `resolution` is not positive.
+///
+/// # Example
+///
+/// ```
+/// use vbap::analysis::sample_sphere;
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+/// let field = sample_sphere(&panner, 10.0);
+/// assert_eq!(field.azimuths().len(), 36);
+/// assert_eq!(field.elevations().len(), 19);
+///
+/// // A dome leaves the lower hemisphere uncovered
+/// assert!(field.holes().iter().all(|&(_, elevation)| elevation < 0.0));
+/// assert!(field.coverage() > 0.5 && field.coverage() < 0.7);
+/// ```
+pub fn sample_sphere(panner: &VBAPanner, resolution: f64) -> GainField {
+    assert!(resolution > 0.0, "resolution must be positive");
+    let azimuths: Vec<f64> = (0..)
+        .map(|i| -180.0 + i as f64 * resolution)
+        .take_while(|&azimuth| azimuth < 180.0)
+        .collect();
+    let elevations: Vec<f64> = (0..)
+        .map(|i| -90.0 + i as f64 * resolution)
+        .take_while(|&elevation| elevation <= 90.0)
+        .collect();
+
+    let num_speakers = panner.num_speakers();
+    let num_points = azimuths.len() * elevations.len();
+    let mut gains = vec![0.0; num_points * num_speakers];
+    let mut metrics = Vec::with_capacity(num_points);
+    let mut covered = Vec::with_capacity(num_points);
+    let directions = elevations
+        .iter()
+        .flat_map(|&elevation| azimuths.iter().map(move |&azimuth| (azimuth, elevation)));
+    for ((azimuth, elevation), point) in directions.zip(gains.chunks_exact_mut(num_speakers)) {
+        panner.compute_gains_into(azimuth, elevation, point);
+        metrics.push(direction_metrics(panner, azimuth, elevation));
+        covered.push(panner.covers(azimuth, elevation));
+    }
+
+    GainField {
+        resolution,
+        num_speakers,
+        azimuths,
+        elevations,
+        gains,
+        metrics,
+        covered,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_single_source_on_speaker() {
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let report = headroom_report(&panner, &[SourceLevel::new(0.0, 0.0, -6.0)], 3.0);
+
+        let center = &report.channels[2];
+        assert_relative_eq!(center.peak_db, -6.0, epsilon = 1e-9);
+        assert_relative_eq!(center.headroom_db, 6.0, epsilon = 1e-9);
+        assert!(!center.likely_to_clip);
+        assert_eq!(report.channels[0].peak_db, f64::NEG_INFINITY);
+        assert_eq!(report.clipping_channels().count(), 0);
+    }
+
+    #[test]
+    fn test_direction_metrics_stereo() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+
+        // Equal-power phantom center: rE = cos(30 deg) pointing forward
+        let center = direction_metrics(&panner, 0.0, 0.0);
+        assert_relative_eq!(
+            center.energy_magnitude(),
+            30f64.to_radians().cos(),
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(
+            center.velocity_magnitude(),
+            30f64.to_radians().cos(),
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(center.spread, 60.0, epsilon = 1e-9);
+
+        // VBAP places rV on the target; rE lags towards the nearer speaker
+        let off = direction_metrics(&panner, 10.0, 0.0);
+        assert_relative_eq!(off.velocity_error, 0.0, epsilon = 1e-9);
+        assert!(off.angular_error > 0.0 && off.angular_error < 10.0);
+        let energy_azimuth = off.energy.x.atan2(off.energy.y).to_degrees();
+        assert!(energy_azimuth > 10.0);
+    }
+
+    #[test]
+    fn test_sample_sphere_grid() {
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let field = sample_sphere(&panner, 30.0);
+        assert_eq!(field.azimuths()[0], -180.0);
+        assert_eq!(
+            field.elevations(),
+            [-90.0, -60.0, -30.0, 0.0, 30.0, 60.0, 90.0]
+        );
+
+        // Azimuth 30 on the horizon is the left speaker
+        let (a, e) = (7, 3);
+        assert_eq!((field.azimuths()[a], field.elevations()[e]), (30.0, 0.0));
+        assert_relative_eq!(field.gains(a, e)[0], 1.0, epsilon = 1e-9);
+        assert_relative_eq!(field.speaker_map(0)[e * 12 + a], 1.0, epsilon = 1e-9);
+        assert_relative_eq!(field.metrics(a, e).spread, 0.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_coherent_vs_incoherent_sum() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let sources = [SourceLevel::new(30.0, 0.0, -6.0); 2];
+        let report = headroom_report(&panner, &sources, 0.0);
+
+        let left = &report.channels[0];
+        assert_relative_eq!(left.peak_db, -6.0 + 20.0 * 2f64.log10(), epsilon = 1e-9);
+        assert_relative_eq!(left.rms_db, -6.0 + 10.0 * 2f64.log10(), epsilon = 1e-9);
+        assert_eq!(report.worst_channel().unwrap().speaker, 0);
+    }
+}
@@ -0,0 +1,517 @@
+//! Bass management.
+//!
+//! Most loudspeakers cannot reproduce the lowest octaves. Bass management
+//! high-passes each speaker feed at the speaker's crossover frequency and
+//! routes the removed low-frequency content to the LFE/subwoofer output,
+//! where a level control trims it to match the main speakers.
+//! This stage sits between the VBAP render and the DAC.
+//!
+//! Requires the `render` feature.
+
+use crate::dsp::LinkwitzRiley;
+use crate::error::{Result, VBAPError};
+use crate::math::spherical_to_cartesian;
+use crate::speaker::Speaker;
+use glam::DVec3;
+
+/// How low-frequency content is distributed over a subwoofer array.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LfPanning {
+    /// Send everything to the subwoofer closest to the source direction.
+    Nearest,
+    /// Share the signal equally between all subwoofers.
+    #[default]
+    GainShared,
+    /// Weight each subwoofer by a cardioid pattern `(1 + cos θ) / 2`, where
+    /// `θ` is the angle between the source and the subwoofer.
+    Cardioid,
+}
+
+/// A set of subwoofers with their own positions, panned separately from
+/// the main VBAP layer.
+///
+/// Low frequencies add coherently between subwoofers, so gains are
+/// amplitude-normalized (they sum to 1) rather than power-normalized.
+#[derive(Clone, Debug)]
+pub struct SubwooferArray {
+    subwoofers: Vec<Speaker>,
+    strategy: LfPanning,
+}
+
+impl SubwooferArray {
+    /// Create an array from (azimuth, elevation) positions in degrees.
+    pub fn new(positions: &[(f64, f64)], strategy: LfPanning) -> Result<Self> {
+        if positions.is_empty() {
+            return Err(VBAPError::InsufficientSpeakers {
+                provided: 0,
+                required: 1,
+            });
+        }
+
+        let subwoofers = positions
+            .iter()
+            .enumerate()
+            .map(|(id, &(azi, ele))| Speaker::new(id, azi, ele))
+            .collect();
+        Ok(Self {
+            subwoofers,
+            strategy,
+        })
+    }
+
+    /// Get the subwoofers.
+    #[inline]
+    pub fn subwoofers(&self) -> &[Speaker] {
+        &self.subwoofers
+    }
+
+    /// Get the number of subwoofers.
+    #[inline]
+    pub fn num_subwoofers(&self) -> usize {
+        self.subwoofers.len()
+    }
+
+    /// Get the panning strategy.
+    #[inline]
+    pub fn strategy(&self) -> LfPanning {
+        self.strategy
+    }
+
+    /// Compute subwoofer gains for a source direction.
+    pub fn compute_gains(&self, azimuth: f64, elevation: f64) -> Vec<f64> {
+        let mut gains = vec![0.0; self.subwoofers.len()];
+        self.compute_gains_into(azimuth, elevation, &mut gains);
+        gains
+    }
+
+    /// Compute subwoofer gains into a pre-allocated slice.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_subwoofers()`.
+    pub fn compute_gains_into(&self, azimuth: f64, elevation: f64, gains: &mut [f64]) {
+        self.gains_for_direction(spherical_to_cartesian(azimuth, elevation), gains);
+    }
+
+    /// Compute the routing from each main speaker's low band to the subwoofers.
+    ///
+    /// Row `i` holds the subwoofer gains for the direction of `speakers[i]`;
+    /// pass the result to [`BassManager::process_to_subwoofers`].
+    pub fn routing(&self, speakers: &[Speaker]) -> Vec<Vec<f64>> {
+        speakers
+            .iter()
+            .map(|speaker| {
+                let mut row = vec![0.0; self.subwoofers.len()];
+                self.gains_for_direction(speaker.cartesian(), &mut row);
+                row
+            })
+            .collect()
+    }
+
+    fn gains_for_direction(&self, direction: DVec3, gains: &mut [f64]) {
+        let n = self.subwoofers.len();
+        assert!(
+            gains.len() >= n,
+            "gains slice too small: {} < {}",
+            gains.len(),
+            n
+        );
+        gains.fill(0.0);
+
+        match self.strategy {
+            LfPanning::Nearest => {
+                let nearest = self
+                    .subwoofers
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| {
+                        direction
+                            .dot(a.cartesian())
+                            .total_cmp(&direction.dot(b.cartesian()))
+                    })
+                    .map_or(0, |(i, _)| i);
+                gains[nearest] = 1.0;
+            }
+            LfPanning::GainShared => {
+                gains[..n].fill(1.0 / n as f64);
+            }
+            LfPanning::Cardioid => {
+                for (gain, sub) in gains.iter_mut().zip(&self.subwoofers) {
+                    *gain = 0.5 * (1.0 + direction.dot(sub.cartesian()));
+                }
+                let sum: f64 = gains[..n].iter().sum();
+                if sum > 1e-10 {
+                    gains[..n].iter_mut().for_each(|g| *g /= sum);
+                } else {
+                    gains[..n].fill(1.0 / n as f64);
+                }
+            }
+        }
+    }
+}
+
+/// Per-speaker bass management using Linkwitz-Riley crossovers.
+///
+/// Each speaker either declares a crossover frequency or runs full range.
+/// Speakers with a crossover are high-passed, and their low band is summed
+/// into the LFE output.
+///
+/// # Example
+///
+/// ```
+/// use vbap::bass::BassManager;
+///
+/// // 5 speakers; the surrounds (3, 4) are small and cross over higher
+/// let mut bass = BassManager::new(48000.0, 5);
+/// for speaker in 0..3 {
+///     bass.set_crossover(speaker, Some(80.0)).unwrap();
+/// }
+/// bass.set_crossover(3, Some(120.0)).unwrap();
+/// bass.set_crossover(4, Some(120.0)).unwrap();
+///
+/// let mut feeds = vec![vec![0.0f32; 256]; 5];
+/// let mut lfe = vec![0.0f32; 256];
+/// let mut channels: Vec<&mut [f32]> = feeds.iter_mut().map(|f| f.as_mut_slice()).collect();
+/// bass.process(&mut channels, &mut lfe);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BassManager {
+    sample_rate: f64,
+    /// Crossover filter per speaker, `None` for full-range speakers.
+    crossovers: Vec<Option<LinkwitzRiley>>,
+    /// Linear gain applied to the redirected low band.
+    subwoofer_gain: f64,
+}
+
+impl BassManager {
+    /// Create a bass manager for `num_speakers` full-range speakers.
+    pub fn new(sample_rate: f64, num_speakers: usize) -> Self {
+        Self {
+            sample_rate,
+            crossovers: vec![None; num_speakers],
+            subwoofer_gain: 1.0,
+        }
+    }
+
+    /// Get the sample rate in Hz.
+    #[inline]
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Get the number of speakers.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.crossovers.len()
+    }
+
+    /// Set a speaker's crossover frequency in Hz, or `None` for full range.
+    pub fn set_crossover(&mut self, speaker: usize, frequency: Option<f64>) -> Result<()> {
+        let num_speakers = self.crossovers.len();
+        let slot = self
+            .crossovers
+            .get_mut(speaker)
+            .ok_or(VBAPError::InvalidSpeakerIndex {
+                index: speaker,
+                num_speakers,
+            })?;
+
+        *slot = match frequency {
+            Some(frequency) => {
+                let nyquist = self.sample_rate / 2.0;
+                if !(frequency > 0.0 && frequency < nyquist) {
+                    return Err(VBAPError::InvalidParameter {
+                        parameter: "crossover frequency",
+                        value: frequency,
+                        min: 0.0,
+                        max: nyquist,
+                    });
+                }
+                Some(LinkwitzRiley::new(self.sample_rate, frequency))
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Set the same crossover frequency in Hz for every speaker, or `None`
+    /// to run them all full range.
+    pub fn set_all_crossovers(&mut self, frequency: Option<f64>) -> Result<()> {
+        for speaker in 0..self.crossovers.len() {
+            self.set_crossover(speaker, frequency)?;
+        }
+        Ok(())
+    }
+
+    /// Set the linear gain of the low band sent to the LFE or subwoofers.
+    ///
+    /// Use it to level-match the subwoofers against the main speakers. It
+    /// only scales the redirected bass, not an existing LFE send.
+    pub fn set_subwoofer_gain(&mut self, gain: f64) {
+        self.subwoofer_gain = gain;
+    }
+
+    /// Get the linear gain of the low band sent to the LFE or subwoofers.
+    #[inline]
+    pub fn subwoofer_gain(&self) -> f64 {
+        self.subwoofer_gain
+    }
+
+    /// Get a speaker's crossover frequency in Hz, `None` if it runs full range.
+    pub fn crossover(&self, speaker: usize) -> Option<f64> {
+        self.crossovers
+            .get(speaker)
+            .and_then(|c| c.as_ref().map(LinkwitzRiley::frequency))
+    }
+
+    /// Clear all filter state.
+    pub fn reset(&mut self) {
+        for crossover in self.crossovers.iter_mut().flatten() {
+            crossover.reset();
+        }
+    }
+
+    /// Process one block of speaker feeds in place.
+    ///
+    /// Speakers with a crossover are high-passed, and their low band is
+    /// added to `lfe` at the [subwoofer gain](Self::set_subwoofer_gain)
+    /// (`lfe` is not cleared first, so an existing LFE send is preserved).
+    ///
+    /// # Panics
+    /// Panics if `speakers` does not have one channel per speaker, or a
+    /// channel is not as long as `lfe`.
+    pub fn process(&mut self, speakers: &mut [&mut [f32]], lfe: &mut [f32]) {
+        assert_eq!(
+            speakers.len(),
+            self.crossovers.len(),
+            "expected {} speaker channels",
+            self.crossovers.len()
+        );
+        assert!(
+            speakers.iter().all(|channel| channel.len() == lfe.len()),
+            "expected speaker channels of {} samples, as long as the LFE",
+            lfe.len()
+        );
+
+        let gain = self.subwoofer_gain;
+        for (channel, crossover) in speakers.iter_mut().zip(&mut self.crossovers) {
+            let Some(crossover) = crossover else {
+                continue;
+            };
+            for (sample, lfe_sample) in channel.iter_mut().zip(lfe.iter_mut()) {
+                let (low, high) = crossover.split_sample(*sample as f64);
+                *sample = high as f32;
+                *lfe_sample += (low * gain) as f32;
+            }
+        }
+    }
+
+    /// Process one block, routing each speaker's low band to a subwoofer array.
+    ///
+    /// Like [`process`](Self::process), but the low band of speaker `i` is
+    /// added to each subwoofer `j` with gain `routing[i][j]` (see
+    /// [`SubwooferArray::routing`]), so low frequencies stay on the side of
+    /// the room they came from.
+    ///
+    /// # Panics
+    /// Panics if `speakers` or `routing` does not have one entry per
+    /// speaker, a routing row does not have one gain per subwoofer, or a
+    /// subwoofer buffer is shorter than a speaker channel.
+    pub fn process_to_subwoofers(
+        &mut self,
+        speakers: &mut [&mut [f32]],
+        subwoofers: &mut [&mut [f32]],
+        routing: &[Vec<f64>],
+    ) {
+        assert_eq!(
+            speakers.len(),
+            self.crossovers.len(),
+            "expected {} speaker channels",
+            self.crossovers.len()
+        );
+        assert_eq!(
+            routing.len(),
+            self.crossovers.len(),
+            "expected one routing row per speaker"
+        );
+        assert!(
+            routing.iter().all(|row| row.len() == subwoofers.len()),
+            "expected routing rows of {} gains, one per subwoofer",
+            subwoofers.len()
+        );
+        let block = speakers
+            .iter()
+            .map(|channel| channel.len())
+            .max()
+            .unwrap_or(0);
+        assert!(
+            subwoofers.iter().all(|sub| sub.len() >= block),
+            "expected subwoofer buffers of at least {} samples",
+            block
+        );
+
+        let level = self.subwoofer_gain;
+        for ((channel, crossover), row) in
+            speakers.iter_mut().zip(&mut self.crossovers).zip(routing)
+        {
+            let Some(crossover) = crossover else {
+                continue;
+            };
+            for (n, sample) in channel.iter_mut().enumerate() {
+                let (low, high) = crossover.split_sample(*sample as f64);
+                *sample = high as f32;
+                for (sub, &gain) in subwoofers.iter_mut().zip(row) {
+                    sub[n] += (low * gain * level) as f32;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_full_range_passthrough() {
+        let mut bass = BassManager::new(48000.0, 2);
+        let mut left = vec![0.5f32; 64];
+        let mut right = vec![-0.25f32; 64];
+        let mut lfe = vec![0.0f32; 64];
+
+        bass.process(&mut [&mut left, &mut right], &mut lfe);
+
+        assert!(left.iter().all(|&s| s == 0.5));
+        assert!(right.iter().all(|&s| s == -0.25));
+        assert!(lfe.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "as long as the LFE")]
+    fn test_channel_longer_than_lfe() {
+        let mut bass = BassManager::new(48000.0, 1);
+        bass.set_crossover(0, Some(80.0)).unwrap();
+        let mut channel = vec![1.0f32; 64];
+        let mut lfe = vec![0.0f32; 32];
+        bass.process(&mut [&mut channel], &mut lfe);
+    }
+
+    #[test]
+    fn test_dc_routed_to_lfe() {
+        let mut bass = BassManager::new(48000.0, 2);
+        bass.set_crossover(0, Some(80.0)).unwrap();
+
+        let mut left = vec![1.0f32; 48000];
+        let mut right = vec![0.0f32; 48000];
+        let mut lfe = vec![0.0f32; 48000];
+        bass.process(&mut [&mut left, &mut right], &mut lfe);
+
+        assert!(left[47999].abs() < 1e-3);
+        assert!((lfe[47999] - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_subwoofer_gain() {
+        let mut bass = BassManager::new(48000.0, 2);
+        bass.set_all_crossovers(Some(100.0)).unwrap();
+        assert_eq!(bass.crossover(1), Some(100.0));
+        assert!(bass.set_all_crossovers(Some(30000.0)).is_err());
+        bass.set_subwoofer_gain(0.5);
+
+        let mut left = vec![1.0f32; 48000];
+        let mut right = vec![1.0f32; 48000];
+        let mut lfe = vec![0.0f32; 48000];
+        bass.process(&mut [&mut left, &mut right], &mut lfe);
+        assert!((lfe[47999] - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_subwoofer_nearest() {
+        let array = SubwooferArray::new(
+            &[(45.0, 0.0), (-45.0, 0.0), (180.0, 0.0)],
+            LfPanning::Nearest,
+        )
+        .unwrap();
+        assert_eq!(array.compute_gains(30.0, 0.0), vec![1.0, 0.0, 0.0]);
+        assert_eq!(array.compute_gains(170.0, 0.0), vec![0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_subwoofer_shared_and_cardioid_sum_to_one() {
+        let positions = [(90.0, 0.0), (-90.0, 0.0)];
+        let shared = SubwooferArray::new(&positions, LfPanning::GainShared).unwrap();
+        assert_eq!(shared.compute_gains(90.0, 0.0), vec![0.5, 0.5]);
+
+        let cardioid = SubwooferArray::new(&positions, LfPanning::Cardioid).unwrap();
+        let gains = cardioid.compute_gains(90.0, 0.0);
+        assert_relative_eq!(gains[0], 1.0, epsilon = 1e-9);
+        assert_relative_eq!(gains[1], 0.0, epsilon = 1e-9);
+
+        let gains = cardioid.compute_gains(0.0, 0.0);
+        assert_relative_eq!(gains[0], 0.5, epsilon = 1e-9);
+        assert_relative_eq!(gains[1], 0.5, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_empty_subwoofer_array() {
+        assert!(SubwooferArray::new(&[], LfPanning::Nearest).is_err());
+    }
+
+    #[test]
+    fn test_lows_routed_to_nearest_subwoofer() {
+        let speakers = [Speaker::new(0, 30.0, 0.0), Speaker::new(1, -30.0, 0.0)];
+        let array = SubwooferArray::new(&[(90.0, 0.0), (-90.0, 0.0)], LfPanning::Nearest).unwrap();
+        let routing = array.routing(&speakers);
+
+        let mut bass = BassManager::new(48000.0, 2);
+        bass.set_crossover(0, Some(80.0)).unwrap();
+        bass.set_crossover(1, Some(80.0)).unwrap();
+
+        let mut left = vec![1.0f32; 48000];
+        let mut right = vec![0.0f32; 48000];
+        let mut sub_left = vec![0.0f32; 48000];
+        let mut sub_right = vec![0.0f32; 48000];
+        bass.process_to_subwoofers(
+            &mut [&mut left, &mut right],
+            &mut [&mut sub_left, &mut sub_right],
+            &routing,
+        );
+
+        assert!((sub_left[47999] - 1.0).abs() < 1e-3);
+        assert_eq!(sub_right[47999], 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "subwoofer buffers of at least 64 samples")]
+    fn test_short_subwoofer_buffer() {
+        let mut bass = BassManager::new(48000.0, 1);
+        bass.set_crossover(0, Some(80.0)).unwrap();
+        let mut channel = vec![1.0f32; 64];
+        let mut sub = vec![0.0f32; 32];
+        bass.process_to_subwoofers(&mut [&mut channel], &mut [&mut sub], &[vec![1.0]]);
+    }
+
+    #[test]
+    #[should_panic(expected = "one per subwoofer")]
+    fn test_routing_row_width() {
+        let mut bass = BassManager::new(48000.0, 1);
+        bass.set_crossover(0, Some(80.0)).unwrap();
+        let mut channel = vec![1.0f32; 64];
+        let (mut a, mut b) = (vec![0.0f32; 64], vec![0.0f32; 64]);
+        bass.process_to_subwoofers(&mut [&mut channel], &mut [&mut a, &mut b], &[vec![1.0]]);
+    }
+
+    #[test]
+    fn test_invalid_crossover() {
+        let mut bass = BassManager::new(48000.0, 2);
+        assert!(matches!(
+            bass.set_crossover(0, Some(30000.0)),
+            Err(VBAPError::InvalidParameter { .. })
+        ));
+        assert!(matches!(
+            bass.set_crossover(2, Some(80.0)),
+            Err(VBAPError::InvalidSpeakerIndex { index: 2, .. })
+        ));
+        assert_eq!(bass.crossover(0), None);
+    }
+}
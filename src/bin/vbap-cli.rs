@@ -0,0 +1,356 @@
+//! Command-line tool for inspecting layouts and computing gains.
+//!
+//! ```text
+//! vbap-cli presets
+//! vbap-cli gains --layout 5.1 30,0 -110,0
+//! vbap-cli trajectory --layout venue.json --keyframes flyover.csv --rate 50 -o gains.csv
+//! vbap-cli diagnostics --layout 7.1.4
+//! vbap-cli render --layout 5.1 --keyframes flyover.csv voice.wav voice_5_1.wav
+//! ```
+//!
+//! A layout is a preset name or a file: IEM JSON, SSR ASDF, a Zirkonium
+//! speaker setup or a Max/MSP `define_loudspeakers` message.
+//!
+//! Requires the `cli` feature; `render` also requires `wav`.
+
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand};
+use vbap::analysis::sample_sphere;
+use vbap::config::PRESET_NAMES;
+use vbap::formats::{iem::IemLayout, max, ssr::ReproductionSetup, zirkonium};
+use vbap::trajectory::Trajectory;
+use vbap::util::lin_to_db;
+use vbap::{PanningMode, SpeakerConfigBuilder, VBAPanner};
+
+type CliResult<T> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(
+    name = "vbap-cli",
+    version,
+    about = "Vector Base Amplitude Panning gains and layout analysis"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the preset layout names
+    Presets,
+    /// Print the speaker gains for source directions
+    Gains {
+        /// Preset name or layout file
+        #[arg(short, long)]
+        layout: String,
+        /// Print levels in dB instead of linear gains
+        #[arg(long)]
+        db: bool,
+        /// Source directions as AZIMUTH,ELEVATION in degrees
+        #[arg(required = true, allow_hyphen_values = true)]
+        directions: Vec<String>,
+    },
+    /// Render a keyframe CSV (time,azimuth,elevation) to a CSV of gains
+    Trajectory {
+        /// Preset name or layout file
+        #[arg(short, long)]
+        layout: String,
+        /// Keyframe CSV file
+        #[arg(short, long)]
+        keyframes: PathBuf,
+        /// Output rows per second
+        #[arg(short, long, default_value_t = 100.0)]
+        rate: f64,
+        /// Output file (default: standard output)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Render a mono WAV file along a keyframe CSV to one channel per speaker
+    #[cfg(feature = "wav")]
+    Render {
+        /// Preset name or layout file
+        #[arg(short, long)]
+        layout: String,
+        /// Keyframe CSV file
+        #[arg(short, long)]
+        keyframes: PathBuf,
+        /// Mono input WAV file
+        input: PathBuf,
+        /// Multichannel output WAV file
+        output: PathBuf,
+    },
+    /// Print the speakers, triangulation report and sphere coverage
+    Diagnostics {
+        /// Preset name or layout file
+        #[arg(short, long)]
+        layout: String,
+        /// Grid spacing of the coverage check in degrees
+        #[arg(long, default_value_t = 5.0)]
+        resolution: f64,
+        /// List every rejected candidate tuple and removed connection
+        #[arg(short, long)]
+        verbose: bool,
+    },
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> CliResult<()> {
+    let mut out = io::stdout().lock();
+    match cli.command {
+        Command::Presets => {
+            for name in PRESET_NAMES {
+                writeln!(out, "{name}")?;
+            }
+        }
+        Command::Gains {
+            layout,
+            db,
+            directions,
+        } => {
+            let panner = load_layout(&layout)?;
+            write!(out, "{:>9} {:>9}", "azimuth", "elevation")?;
+            for name in speaker_names(&panner) {
+                write!(out, " {name:>8}")?;
+            }
+            writeln!(out)?;
+            for direction in &directions {
+                let (azimuth, elevation) = parse_direction(direction)?;
+                write!(out, "{azimuth:>9.1} {elevation:>9.1}")?;
+                for gain in panner.compute_gains(azimuth, elevation) {
+                    if db {
+                        write!(out, " {:>8.2}", lin_to_db(gain))?;
+                    } else {
+                        write!(out, " {gain:>8.4}")?;
+                    }
+                }
+                writeln!(out)?;
+            }
+        }
+        Command::Trajectory {
+            layout,
+            keyframes,
+            rate,
+            output,
+        } => {
+            if !(rate.is_finite() && rate > 0.0) {
+                return Err(format!("rate must be positive, got {rate}").into());
+            }
+            let panner = load_layout(&layout)?;
+            let trajectory = Trajectory::from_csv(&read(&keyframes)?)?;
+            match output {
+                Some(path) => {
+                    let mut file = io::BufWriter::new(fs::File::create(&path)?);
+                    write_trajectory(&mut file, &panner, &trajectory, rate)?;
+                    file.flush()?;
+                }
+                None => write_trajectory(&mut out, &panner, &trajectory, rate)?,
+            }
+        }
+        #[cfg(feature = "wav")]
+        Command::Render {
+            layout,
+            keyframes,
+            input,
+            output,
+        } => {
+            let panner = load_layout(&layout)?;
+            let trajectory = Trajectory::from_csv(&read(&keyframes)?)?;
+            vbap::wav::WavRenderer::new(panner).render_file(&input, &output, &trajectory)?;
+        }
+        Command::Diagnostics {
+            layout,
+            resolution,
+            verbose,
+        } => {
+            if !(resolution.is_finite() && resolution > 0.0) {
+                return Err(format!("resolution must be positive, got {resolution}").into());
+            }
+            let builder = load_builder(&layout)?;
+            let (config, report) = builder.build_config_with_report();
+            let panner = VBAPanner::new(config?);
+            let config = panner.config();
+
+            let mode = match config.mode() {
+                PanningMode::TwoD => "2D",
+                PanningMode::ThreeD => "3D",
+            };
+            writeln!(
+                out,
+                "{} speakers, {mode}, {} tuples",
+                config.num_speakers(),
+                config.tuples().len()
+            )?;
+            for (speaker, name) in config.speakers().iter().zip(speaker_names(&panner)) {
+                writeln!(
+                    out,
+                    "  {name:>4} {:>7.1} {:>6.1}",
+                    speaker.azimuth(),
+                    speaker.elevation()
+                )?;
+            }
+            if verbose {
+                write!(out, "{report}")?;
+            } else {
+                for pair in &report.coincident {
+                    writeln!(
+                        out,
+                        "speakers {} and {} are only {:.2}° apart",
+                        pair.speakers[0], pair.speakers[1], pair.angle
+                    )?;
+                }
+                writeln!(
+                    out,
+                    "{} candidate tuples rejected, {} crossing connections removed",
+                    report.rejected.len(),
+                    report.removed_connections.len()
+                )?;
+            }
+            let field = sample_sphere(&panner, resolution);
+            writeln!(
+                out,
+                "coverage: {:.1}% of the sphere ({resolution}° grid)",
+                field.coverage() * 100.0
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Load a layout from a preset name or a file.
+fn load_layout(layout: &str) -> CliResult<VBAPanner> {
+    Ok(load_builder(layout)?.build()?)
+}
+
+/// Read a layout file, detecting the format from its content, or fall back
+/// to a preset name.
+fn load_builder(layout: &str) -> CliResult<SpeakerConfigBuilder> {
+    let path = Path::new(layout);
+    if !path.is_file() {
+        return SpeakerConfigBuilder::new().preset(layout).map_err(|_| {
+            format!(
+                "'{layout}' is neither a layout file nor a preset ({})",
+                PRESET_NAMES.join(", ")
+            )
+            .into()
+        });
+    }
+    let text = read(path)?;
+    let trimmed = text.trim_start();
+    let builder = if trimmed.starts_with('{') {
+        IemLayout::from_json(&text)?.builder()
+    } else if trimmed.starts_with('<') {
+        if text.contains("reproduction_setup") {
+            ReproductionSetup::from_xml(&text)?.builder()
+        } else {
+            zirkonium::parse_speaker_setup(&text)?
+        }
+    } else {
+        max::parse_define_loudspeakers(&text)?
+    };
+    Ok(builder)
+}
+
+fn read(path: &Path) -> CliResult<String> {
+    fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()).into())
+}
+
+/// Column names: channel labels where the layout has them, else indices.
+fn speaker_names(panner: &VBAPanner) -> Vec<String> {
+    panner
+        .config()
+        .speakers()
+        .iter()
+        .map(|speaker| match speaker.label() {
+            Some(label) => label.to_string(),
+            None => speaker.id().to_string(),
+        })
+        .collect()
+}
+
+/// Parse `AZIMUTH,ELEVATION`; the elevation defaults to 0.
+fn parse_direction(text: &str) -> CliResult<(f64, f64)> {
+    let invalid = || format!("invalid direction '{text}', expected AZIMUTH,ELEVATION");
+    let mut fields = text.split(',').map(str::trim);
+    let azimuth = fields
+        .next()
+        .and_then(|f| f.parse().ok())
+        .ok_or_else(invalid)?;
+    let elevation = match fields.next() {
+        Some(field) => field.parse().map_err(|_| invalid())?,
+        None => 0.0,
+    };
+    if fields.next().is_some() {
+        return Err(invalid().into());
+    }
+    Ok((azimuth, elevation))
+}
+
+/// Write one CSV row of gains per output frame over the trajectory.
+fn write_trajectory(
+    out: &mut impl Write,
+    panner: &VBAPanner,
+    trajectory: &Trajectory,
+    rate: f64,
+) -> CliResult<()> {
+    write!(out, "time,azimuth,elevation")?;
+    for name in speaker_names(panner) {
+        write!(out, ",{name}")?;
+    }
+    writeln!(out)?;
+
+    let frames = (trajectory.duration() * rate).floor() as usize + 1;
+    let mut gains = vec![0.0; panner.num_speakers()];
+    for frame in 0..frames {
+        let time = trajectory.start_time() + frame as f64 / rate;
+        let (azimuth, elevation) = trajectory.sample(time);
+        panner.compute_gains_into(azimuth, elevation, &mut gains);
+        write!(out, "{time:.6},{azimuth:.4},{elevation:.4}")?;
+        for gain in &gains {
+            write!(out, ",{gain:.6}")?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_direction() {
+        assert_eq!(parse_direction("-110, 15").unwrap(), (-110.0, 15.0));
+        assert_eq!(parse_direction("30").unwrap(), (30.0, 0.0));
+        assert!(parse_direction("30,0,1").is_err());
+        assert!(parse_direction("left").is_err());
+    }
+
+    #[test]
+    fn test_trajectory_csv() {
+        let panner = load_layout("stereo").unwrap();
+        let trajectory = Trajectory::from_csv("0,30,0\n1,-30,0\n").unwrap();
+        let mut csv = Vec::new();
+        write_trajectory(&mut csv, &panner, &trajectory, 4.0).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "time,azimuth,elevation,L,R");
+        assert!(lines[1].ends_with(",1.000000,0.000000"));
+        assert!(lines[5].starts_with("1.000000,-30.0000"));
+    }
+}
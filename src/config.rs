@@ -3,13 +3,56 @@
 //! This module handles the construction of VBAP speaker configurations,
 //! including the selection of valid speaker pairs (2D) or triplets (3D)
 //! and the computation of inverse matrices for gain calculation.
-
+//!
+//! Triangulation is deterministic: the same speakers, options and
+//! tolerances give the same tuples in the same order on every run and
+//! platform. Candidates are visited in a fixed order with ties broken by
+//! speaker index, and connection lengths are compared on a coarse grid so
+//! that last-bit differences between math libraries cannot reorder them.
+//! [`SpeakerConfig::fingerprint`] identifies the result for caching.
+
+use crate::convention::Convention;
+use crate::diagnostics::{
+    CoincidentSpeakers, RejectedTuple, Rejection, RemovedConnection, TriangulationReport,
+};
 use crate::error::{Result, VBAPError};
-use crate::math::lines_intersect;
+use crate::math::{
+    arcs_intersect, cartesian_to_spherical, rotate_direction, solid_angle, spherical_to_cartesian,
+    wrap_azimuth,
+};
 use crate::panner::VBAPanner;
 use crate::presets;
-use crate::speaker::Speaker;
-use glam::{DMat2, DMat3, DVec2, DVec3};
+use crate::speaker::{ChannelLabel, Speaker};
+use glam::{DMat2, DMat3, DQuat, DVec2, DVec3};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Maximum number of speakers in a layout.
+///
+/// 3D triangulation compares every pair of speaker connections, so its
+/// setup time grows with roughly the fourth power of the speaker count: a
+/// 512-speaker sphere takes about a second to build in release mode. Once
+/// built, computing gains scans all tuples (at most `2n - 4` for `n`
+/// speakers), about 10 µs per direction at 512 speakers. Larger layouts are
+/// rejected with [`VBAPError::TooManySpeakers`] instead of silently taking
+/// minutes to set up.
+///
+/// 2D speaker pairs must be at least 5° apart, so horizontal rings are
+/// further limited to 72 speakers.
+pub const MAX_SPEAKERS: usize = 512;
+
+/// Names accepted by [`SpeakerConfigBuilder::preset`].
+pub const PRESET_NAMES: [&str; 10] = [
+    "stereo",
+    "stereo-wide",
+    "lcr",
+    "quad",
+    "5.1",
+    "7.1",
+    "7.1.4",
+    "5.1.4",
+    "hexagon",
+    "octagon",
+];
 
 /// Minimum angular distance between speakers to form a valid pair/triplet.
 const MIN_PAIR_ANGLE: f64 = 0.0872665; // ~5 degrees in radians
@@ -21,6 +64,98 @@ const MAX_PAIR_ANGLE: f64 = 3.0543; // π - 0.0873 radians
 /// Minimum volume/side ratio for valid 3D triplets.
 const MIN_VOL_P_SIDE_LGTH: f64 = 0.01;
 
+/// Connection lengths are compared on a grid of this many radians, so
+/// that lengths differing only in the last bits (as computed by different
+/// platforms' math libraries) tie and are ordered by speaker index.
+const LENGTH_RESOLUTION: f64 = 1e-9;
+
+/// Number of directions sampled for the coverage check of a report.
+const REPORT_COVERAGE_SAMPLES: usize = 512;
+
+/// How far below zero a raw tuple gain may be for a direction on the
+/// tuple's edge to still count as covered.
+const COVERAGE_TOLERANCE: f64 = 1e-9;
+
+/// Directions sampled when looking for coverage holes (3D).
+const COVERAGE_GRID_3D: usize = 1024;
+
+/// Most virtual speakers [`SpeakerConfig::suggest_virtual_speakers`] proposes.
+const MAX_VIRTUAL_SPEAKERS: usize = 16;
+
+/// Numerical tolerances used when building a layout and computing gains.
+///
+/// The defaults suit layouts up to [`MAX_SPEAKERS`] with speakers at least a
+/// few degrees apart. Tightening them can help extremely dense arrays where
+/// nearly-collinear speakers are misclassified; matching another
+/// implementation's values helps reproduce published results.
+///
+/// # Example
+///
+/// ```
+/// use vbap::{Tolerances, VBAPanner};
+///
+/// let panner = VBAPanner::builder()
+///     .atmos_7_1_4()
+///     .tolerances(Tolerances {
+///         determinant: 1e-12,
+///         ..Tolerances::default()
+///     })
+///     .build()
+///     .unwrap();
+/// assert_eq!(panner.config().tolerances().determinant, 1e-12);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerances {
+    /// Pairs and triplets whose direction matrix has an absolute determinant
+    /// below this are degenerate and not used.
+    pub determinant: f64,
+    /// Angular slack in radians when testing whether a point lies on a
+    /// great-circle arc (see [`point_on_arc`](crate::math::point_on_arc)),
+    /// which decides whether speaker connections cross during triangulation.
+    pub arc: f64,
+    /// Gains whose level (as measured by the normalization) is below this are
+    /// treated as silence instead of being scaled up.
+    pub normalization_floor: f64,
+    /// Speakers closer together than this angle in radians are coincident,
+    /// see [`DuplicateSpeakers`].
+    pub coincidence: f64,
+}
+
+impl Tolerances {
+    /// The default tolerances.
+    pub const DEFAULT: Self = Self {
+        determinant: 1e-10,
+        arc: 1e-6,
+        normalization_floor: 1e-5,
+        coincidence: 0.0174533, // ~1 degree
+    };
+
+    fn validate(&self) -> Result<()> {
+        for (parameter, value) in [
+            ("determinant", self.determinant),
+            ("arc", self.arc),
+            ("normalization_floor", self.normalization_floor),
+            ("coincidence", self.coincidence),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(VBAPError::InvalidParameter {
+                    parameter,
+                    value,
+                    min: 0.0,
+                    max: f64::MAX,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Tolerances {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
 /// Panning mode for VBAP computation.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PanningMode {
@@ -52,12 +187,228 @@ pub enum InverseMatrix {
 }
 
 /// A speaker tuple (pair or triplet) with its precomputed inverse matrix.
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct SpeakerTuple {
+    /// Speaker indices; only the first two are used by pairs.
+    indices: [usize; 3],
+    /// Inverse matrix for gain computation.
+    inverse_matrix: InverseMatrix,
+}
+
+impl SpeakerTuple {
+    /// Placeholder for fixed-size tuple storage.
+    pub(crate) const EMPTY: Self = Self {
+        indices: [0; 3],
+        inverse_matrix: InverseMatrix::ThreeD(DMat3::ZERO),
+    };
+
+    /// Create a tuple from 2 (with a 2D matrix) or 3 (with a 3D matrix)
+    /// speaker indices.
+    fn new(indices: &[usize], inverse_matrix: InverseMatrix) -> Self {
+        let mut tuple = Self {
+            indices: [0; 3],
+            inverse_matrix,
+        };
+        debug_assert_eq!(indices.len(), tuple.speaker_indices().len());
+        tuple.indices[..indices.len()].copy_from_slice(indices);
+        tuple
+    }
+
     /// Indices of speakers in this tuple (2 for 2D, 3 for 3D).
-    pub speaker_indices: Vec<usize>,
+    #[inline]
+    pub fn speaker_indices(&self) -> &[usize] {
+        match self.inverse_matrix {
+            InverseMatrix::TwoD(_) => &self.indices[..2],
+            InverseMatrix::ThreeD(_) => &self.indices,
+        }
+    }
+
     /// Inverse matrix for gain computation.
-    pub inverse_matrix: InverseMatrix,
+    #[inline]
+    pub fn inverse_matrix(&self) -> InverseMatrix {
+        self.inverse_matrix
+    }
+
+    /// Compute the raw (unnormalized) gains of this tuple for a direction.
+    ///
+    /// Returns the gains and how many of them are valid (2 or 3).
+    #[inline]
+    pub(crate) fn raw_gains(&self, direction: DVec3) -> ([f64; 3], usize) {
+        self.inverse_matrix.raw_gains(direction)
+    }
+}
+
+impl InverseMatrix {
+    #[inline]
+    fn raw_gains(&self, direction: DVec3) -> ([f64; 3], usize) {
+        match *self {
+            InverseMatrix::ThreeD(mat) => {
+                let result = mat * direction;
+                ([result.x, result.y, result.z], 3)
+            }
+            InverseMatrix::TwoD(mat) => {
+                let result = mat * DVec2::new(direction.x, direction.y);
+                ([result.x, result.y, 0.0], 2)
+            }
+        }
+    }
+}
+
+/// The speaker tuples of a layout, stored as a structure of arrays.
+///
+/// Every gain computation scans all inverse matrices, so they are kept
+/// contiguous (and at their natural 2x2 or 3x3 size), apart from the speaker
+/// indices, which are only read for the winning tuple.
+#[derive(Clone, Debug)]
+pub struct SpeakerTuples {
+    /// Speaker indices, packed as `u16` (see [`MAX_SPEAKERS`]).
+    indices: Vec<[u16; 3]>,
+    matrices: TupleMatrices,
+}
+
+#[derive(Clone, Debug)]
+enum TupleMatrices {
+    TwoD(Vec<DMat2>),
+    ThreeD(Vec<DMat3>),
+}
+
+impl SpeakerTuples {
+    /// Collect tuples, which must all have the dimension of `mode`.
+    pub(crate) fn from_tuples(mode: PanningMode, tuples: Vec<SpeakerTuple>) -> Self {
+        let mut matrices = match mode {
+            PanningMode::TwoD => TupleMatrices::TwoD(Vec::with_capacity(tuples.len())),
+            PanningMode::ThreeD => TupleMatrices::ThreeD(Vec::with_capacity(tuples.len())),
+        };
+        for tuple in &tuples {
+            match (&mut matrices, tuple.inverse_matrix) {
+                (TupleMatrices::TwoD(m), InverseMatrix::TwoD(mat)) => m.push(mat),
+                (TupleMatrices::ThreeD(m), InverseMatrix::ThreeD(mat)) => m.push(mat),
+                _ => unreachable!("all tuples of a layout have the same dimension"),
+            }
+        }
+
+        Self {
+            indices: tuples
+                .iter()
+                .map(|t| t.indices.map(compact_index))
+                .collect(),
+            matrices,
+        }
+    }
+
+    /// Get the number of tuples.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Check whether there are no tuples.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Get the tuple at `index`.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<SpeakerTuple> {
+        let indices = self.indices.get(index)?.map(usize::from);
+        let inverse_matrix = match &self.matrices {
+            TupleMatrices::TwoD(m) => InverseMatrix::TwoD(m[index]),
+            TupleMatrices::ThreeD(m) => InverseMatrix::ThreeD(m[index]),
+        };
+        Some(SpeakerTuple {
+            indices,
+            inverse_matrix,
+        })
+    }
+
+    /// Get the heap memory used by the tuples in bytes.
+    ///
+    /// Each 3D tuple takes 78 bytes (three `u16` speaker indices plus a 3x3
+    /// matrix), so a full 512-speaker sphere needs about 80 KB.
+    pub fn heap_size(&self) -> usize {
+        let matrices = match &self.matrices {
+            TupleMatrices::TwoD(m) => m.capacity() * std::mem::size_of::<DMat2>(),
+            TupleMatrices::ThreeD(m) => m.capacity() * std::mem::size_of::<DMat3>(),
+        };
+        self.indices.capacity() * std::mem::size_of::<[u16; 3]>() + matrices
+    }
+
+    /// Iterate over all tuples.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = SpeakerTuple> + '_ {
+        (0..self.len()).map(|i| self.get(i).expect("index in range"))
+    }
+
+    /// Indices of the speakers in the tuple at `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of range.
+    #[inline]
+    pub fn speaker_indices(&self, index: usize) -> impl ExactSizeIterator<Item = usize> + '_ {
+        let len = match self.matrices {
+            TupleMatrices::TwoD(_) => 2,
+            TupleMatrices::ThreeD(_) => 3,
+        };
+        self.indices[index][..len].iter().map(|&i| usize::from(i))
+    }
+
+    /// Compute the raw gains of the tuple at `index` for a direction.
+    #[inline]
+    pub(crate) fn raw_gains(&self, index: usize, direction: DVec3) -> ([f64; 3], usize) {
+        match &self.matrices {
+            TupleMatrices::TwoD(m) => InverseMatrix::TwoD(m[index]).raw_gains(direction),
+            TupleMatrices::ThreeD(m) => InverseMatrix::ThreeD(m[index]).raw_gains(direction),
+        }
+    }
+
+    /// Update the inverse matrices for speakers moved by the orthogonal map
+    /// `linear`, so the tuples pan the transformed directions.
+    fn transform(&mut self, linear: DMat3) {
+        // Gains are M⁻¹·d; moving both speakers and directions by T gives
+        // (T·M)⁻¹·(T·d), so the new inverse is M⁻¹·Tᵀ
+        match &mut self.matrices {
+            TupleMatrices::TwoD(m) => {
+                let horizontal =
+                    DMat2::from_cols(linear.x_axis.truncate(), linear.y_axis.truncate());
+                for inverse in m {
+                    *inverse *= horizontal.transpose();
+                }
+            }
+            TupleMatrices::ThreeD(m) => {
+                for inverse in m {
+                    *inverse *= linear.transpose();
+                }
+            }
+        }
+    }
+
+    /// Replace the inverse matrix of the tuple at `index`.
+    fn set_inverse_matrix(&mut self, index: usize, inverse_matrix: InverseMatrix) {
+        match (&mut self.matrices, inverse_matrix) {
+            (TupleMatrices::TwoD(m), InverseMatrix::TwoD(mat)) => m[index] = mat,
+            (TupleMatrices::ThreeD(m), InverseMatrix::ThreeD(mat)) => m[index] = mat,
+            _ => unreachable!("all tuples of a layout have the same dimension"),
+        }
+    }
+}
+
+/// What building a layout does with coincident speakers, those closer
+/// together than [`Tolerances::coincidence`].
+///
+/// Coincident speakers give pairs and triplets too thin to pan with, which
+/// breaks the triangulation around them in confusing ways.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateSpeakers {
+    /// Build as usual and list them in the
+    /// [report](SpeakerConfigBuilder::build_config_with_report).
+    #[default]
+    Warn,
+    /// Fail with [`VBAPError::CoincidentSpeakers`].
+    Error,
+    /// Keep the first speaker of each coincident group and leave the others
+    /// out of the triangulation. They stay in the layout, so gain vectors
+    /// keep their length and channel order, but always get zero gain.
+    Merge,
 }
 
 /// A fully configured speaker setup ready for VBAP computation.
@@ -68,7 +419,20 @@ pub struct SpeakerConfig {
     /// Resolved panning mode.
     mode: PanningMode,
     /// Precomputed speaker tuples with inverse matrices.
-    tuples: Vec<SpeakerTuple>,
+    tuples: SpeakerTuples,
+    /// End speakers of an open 2D arc, `None` for closed rings and 3D.
+    arc_ends: Option<[usize; 2]>,
+    /// Tolerances the layout was built with.
+    tolerances: Tolerances,
+    /// Angle convention of panners built from the layout.
+    convention: Convention,
+    /// Handling of coincident speakers when rebuilding.
+    duplicates: DuplicateSpeakers,
+    /// Whether the tuples were given by the user rather than triangulated.
+    explicit: bool,
+    /// Identifies this layout for caches derived from it. Clones share it;
+    /// any modification produces a new one.
+    revision: u64,
 }
 
 impl SpeakerConfig {
@@ -92,167 +456,1804 @@ impl SpeakerConfig {
 
     /// Get the speaker tuples (pairs for 2D, triplets for 3D).
     #[inline]
-    pub fn tuples(&self) -> &[SpeakerTuple] {
+    pub fn tuples(&self) -> &SpeakerTuples {
         &self.tuples
     }
-}
-
-/// Builder for constructing speaker configurations.
-#[derive(Clone, Debug, Default)]
-pub struct SpeakerConfigBuilder {
-    speakers: Vec<(f64, f64)>, // (azimuth, elevation) pairs
-    dimension: Dimension,
-}
-
-impl SpeakerConfigBuilder {
-    /// Create a new empty builder.
-    pub fn new() -> Self {
-        Self::default()
-    }
 
-    /// Add a speaker at the given position.
+    /// Get the two end speakers of an open 2D arc.
     ///
-    /// # Arguments
-    /// * `azimuth` - Horizontal angle in degrees (0° = front, 90° = left, -90° = right)
-    /// * `elevation` - Vertical angle in degrees (0° = horizontal, 90° = above)
-    pub fn add_speaker(mut self, azimuth: f64, elevation: f64) -> Self {
-        self.speakers.push((azimuth, elevation));
-        self
-    }
-
-    /// Add multiple speakers from an array of (azimuth, elevation) pairs.
-    pub fn add_speakers(mut self, positions: &[(f64, f64)]) -> Self {
-        self.speakers.extend_from_slice(positions);
-        self
-    }
-
-    /// Set the dimension mode.
-    pub fn dimension(mut self, dim: Dimension) -> Self {
-        self.dimension = dim;
-        self
-    }
-
-    // === Preset configurations ===
-
-    /// Configure for standard stereo (L/R at ±30°).
-    pub fn stereo(self) -> Self {
-        self.add_speakers(presets::STEREO)
+    /// Returns `None` unless the configuration was built with
+    /// [`SpeakerConfigBuilder::open_arc`].
+    #[inline]
+    pub fn arc_ends(&self) -> Option<[usize; 2]> {
+        self.arc_ends
     }
 
-    /// Configure for wide stereo (L/R at ±60°).
-    pub fn stereo_wide(self) -> Self {
-        self.add_speakers(presets::STEREO_WIDE)
+    /// Check whether a direction lies inside some speaker pair or triplet.
+    ///
+    /// Directions outside every tuple (below a dome, behind an open arc, or
+    /// in a gap of a sparse layout) are still panned, but only by clamping
+    /// negative gains, so the image collapses onto the nearest speakers.
+    /// Hosts can use this to warn when an object leaves the renderable
+    /// region. Directions on the edge of a tuple count as covered. In 2D
+    /// mode only the azimuth matters.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let dome = VBAPanner::builder().atmos_7_1_4().build_config().unwrap();
+    /// assert!(dome.covers(45.0, 30.0));
+    /// assert!(!dome.covers(45.0, -30.0));
+    ///
+    /// let front = VBAPanner::builder().lcr().open_arc().build_config().unwrap();
+    /// assert!(front.covers(20.0, 0.0));
+    /// assert!(!front.covers(90.0, 0.0));
+    /// ```
+    pub fn covers(&self, azimuth: f64, elevation: f64) -> bool {
+        self.covers_direction(spherical_to_cartesian(azimuth, elevation))
     }
 
-    /// Configure for LCR (Left-Center-Right).
-    pub fn lcr(self) -> Self {
-        self.add_speakers(presets::LCR)
+    /// Sample directions no tuple covers, as native `(azimuth, elevation)`.
+    ///
+    /// 2D layouts are sampled every degree around the horizon, 3D layouts
+    /// on an even spread over the sphere.
+    fn uncovered_directions(&self) -> Vec<(f64, f64)> {
+        let samples: Vec<DVec3> = match self.mode {
+            PanningMode::TwoD => (0..360)
+                .map(|i| spherical_to_cartesian(i as f64 - 180.0, 0.0))
+                .collect(),
+            PanningMode::ThreeD => fibonacci_sphere(REPORT_COVERAGE_SAMPLES),
+        };
+        samples
+            .into_iter()
+            .filter(|&direction| !self.covers_direction(direction))
+            .map(cartesian_to_spherical)
+            .collect()
     }
 
-    /// Configure for quadraphonic (4.0).
-    pub fn quad(self) -> Self {
-        self.add_speakers(presets::QUAD)
+    /// Check whether a unit direction lies inside some tuple.
+    pub(crate) fn covers_direction(&self, direction: DVec3) -> bool {
+        (0..self.tuples.len()).any(|i| {
+            let (raw, len) = self.tuples.raw_gains(i, direction);
+            let raw = &raw[..len];
+            raw.iter().all(|&g| g >= -COVERAGE_TOLERANCE) && raw.iter().any(|&g| g > 0.0)
+        })
     }
 
-    /// Configure for 5.0/5.1 surround.
-    pub fn surround_5_1(self) -> Self {
-        self.add_speakers(presets::SURROUND_5_1)
-    }
+    /// Propose virtual (imaginary) speakers that close the layout's
+    /// coverage holes.
+    ///
+    /// Holes are found by sampling directions with [`covers`](Self::covers).
+    /// For each hole, one speaker is placed at the hole's center (rounded to
+    /// whole degrees), then coverage is checked again, so a dome missing its
+    /// lower half gets a single speaker at the nadir. The greedy search
+    /// keeps the set small but not necessarily minimal, and stops after 16
+    /// speakers. Returns (azimuth, elevation) pairs, empty if the layout
+    /// covers every direction.
+    ///
+    /// Open arcs are uncovered behind the arc by design and get no
+    /// suggestions. Accept the result with
+    /// [`with_virtual_speakers`](Self::with_virtual_speakers), or use
+    /// [`with_suggested_virtual_speakers`](Self::with_suggested_virtual_speakers).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let dome = VBAPanner::builder().atmos_7_1_4().build_config().unwrap();
+    /// let suggested = dome.suggest_virtual_speakers();
+    /// assert_eq!(suggested.len(), 1);
+    /// assert_eq!(suggested[0].1, -90.0);
+    /// ```
+    pub fn suggest_virtual_speakers(&self) -> Vec<(f64, f64)> {
+        if self.arc_ends.is_some() {
+            return Vec::new();
+        }
 
-    /// Configure for 7.0/7.1 surround.
-    pub fn surround_7_1(self) -> Self {
-        self.add_speakers(presets::SURROUND_7_1)
+        let grid: Vec<DVec3> = match self.mode {
+            PanningMode::TwoD => (0..360)
+                .map(|azimuth| spherical_to_cartesian(azimuth as f64 + 0.5, 0.0))
+                .collect(),
+            PanningMode::ThreeD => fibonacci_sphere(COVERAGE_GRID_3D),
+        };
+        // Neighboring grid directions, for grouping uncovered ones into holes
+        let spacing = match self.mode {
+            PanningMode::TwoD => 1f64.to_radians(),
+            PanningMode::ThreeD => (4.0 * std::f64::consts::PI / grid.len() as f64).sqrt(),
+        };
+        let neighbor_cos = (2.0 * spacing).cos();
+
+        let mut suggested: Vec<(f64, f64)> = Vec::new();
+        let mut config = self.clone();
+        while suggested.len() < MAX_VIRTUAL_SPEAKERS {
+            let uncovered: Vec<DVec3> = grid
+                .iter()
+                .copied()
+                .filter(|&d| !config.covers_direction(d))
+                .collect();
+            // Start from the uncovered direction farthest from any speaker
+            let clearance = |d: DVec3| {
+                config
+                    .speakers
+                    .iter()
+                    .map(|s| s.cartesian().dot(d))
+                    .fold(f64::NEG_INFINITY, f64::max)
+            };
+            let Some(&seed) = uncovered
+                .iter()
+                .min_by(|a, b| clearance(**a).total_cmp(&clearance(**b)))
+            else {
+                break;
+            };
+
+            // Grow the hole around it and place the speaker at its center
+            let mut hole = vec![seed];
+            let mut rest = uncovered;
+            let mut next = 0;
+            while next < hole.len() {
+                let d = hole[next];
+                next += 1;
+                let (near, far): (Vec<DVec3>, Vec<DVec3>) =
+                    rest.into_iter().partition(|&p| p.dot(d) >= neighbor_cos);
+                hole.extend(near);
+                rest = far;
+            }
+            let center = hole.iter().copied().sum::<DVec3>().normalize_or_zero();
+            let candidate = if center != DVec3::ZERO && !config.covers_direction(center) {
+                center
+            } else {
+                seed
+            };
+
+            let (azimuth, elevation) = cartesian_to_spherical(candidate);
+            let elevation = match self.mode {
+                PanningMode::TwoD => 0.0,
+                PanningMode::ThreeD => elevation.round(),
+            };
+            suggested.push((wrap_azimuth(azimuth.round()), elevation));
+            match self.with_virtual_speakers(&suggested) {
+                Ok(next) => config = next,
+                Err(_) => {
+                    suggested.pop();
+                    break;
+                }
+            }
+        }
+        suggested
     }
 
-    /// Configure for Dolby Atmos 7.1.4.
-    pub fn atmos_7_1_4(self) -> Self {
-        self.add_speakers(presets::ATMOS_7_1_4)
+    /// Add virtual speakers at the given (azimuth, elevation) directions and
+    /// rebuild the layout.
+    ///
+    /// They get the indices after the existing speakers. A [`VBAPanner`]
+    /// built from the result pans through them but always gives them zero
+    /// gain, so their gain vector entries can be ignored.
+    pub fn with_virtual_speakers(&self, positions: &[(f64, f64)]) -> Result<SpeakerConfig> {
+        let mut builder = self.to_builder();
+        for &(azimuth, elevation) in positions {
+            let id = builder.speakers.len();
+            builder
+                .speakers
+                .push(Speaker::new_virtual(id, azimuth, elevation));
+        }
+        builder.build_config()
     }
 
-    /// Configure for Dolby Atmos 5.1.4.
-    pub fn atmos_5_1_4(self) -> Self {
-        self.add_speakers(presets::ATMOS_5_1_4)
+    /// Add the virtual speakers proposed by
+    /// [`suggest_virtual_speakers`](Self::suggest_virtual_speakers).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let dome = VBAPanner::builder().atmos_7_1_4().build_config().unwrap();
+    /// let closed = dome.with_suggested_virtual_speakers().unwrap();
+    /// assert!(closed.covers(30.0, -45.0));
+    ///
+    /// // The virtual speaker under the listener stays silent
+    /// let panner = VBAPanner::new(closed);
+    /// let gains = panner.compute_gains(30.0, -45.0);
+    /// assert_eq!(gains[11], 0.0);
+    /// ```
+    pub fn with_suggested_virtual_speakers(&self) -> Result<SpeakerConfig> {
+        self.with_virtual_speakers(&self.suggest_virtual_speakers())
     }
 
-    /// Configure for hexagonal (6 speakers in ring).
-    pub fn hexagon(self) -> Self {
-        self.add_speakers(presets::HEXAGON)
+    /// Get the numerical tolerances the layout was built with.
+    #[inline]
+    pub fn tolerances(&self) -> &Tolerances {
+        &self.tolerances
     }
 
-    /// Configure for octagonal (8 speakers in ring).
-    pub fn octagon(self) -> Self {
-        self.add_speakers(presets::OCTAGON)
+    /// Get the angle convention of panners built from this layout.
+    ///
+    /// Only the builder and panners convert angles: speaker angles and the
+    /// methods of `SpeakerConfig` always use the crate's own convention, see
+    /// [`Convention`].
+    #[inline]
+    pub fn convention(&self) -> Convention {
+        self.convention
     }
 
-    /// Build a `VBAPanner` from this configuration.
+    /// Compute a fingerprint of the layout.
     ///
-    /// This is the primary build method and returns a ready-to-use panner.
-    pub fn build(self) -> Result<VBAPanner> {
-        Ok(VBAPanner::new(self.build_config()?))
+    /// Covers everything gains depend on: the speakers, panning mode,
+    /// tuples (their speakers, in order), open arc ends, tolerances and
+    /// convention. Inverse matrices are derived from these and left out.
+    /// The value is stable across runs, platforms and crate builds, so it
+    /// can key caches of renders or derived data.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::SpeakerConfigBuilder;
+    ///
+    /// let a = SpeakerConfigBuilder::new().surround_5_1().build_config().unwrap();
+    /// let b = SpeakerConfigBuilder::new().surround_5_1().build_config().unwrap();
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// assert_ne!(a.fingerprint(), a.rotated(10.0).fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = Fingerprint::new();
+        hash.write(self.speakers.len() as u64);
+        for speaker in &self.speakers {
+            hash.write_f64(speaker.azimuth());
+            hash.write_f64(speaker.elevation());
+            hash.write_f64(speaker.distance());
+            hash.write(speaker.is_virtual() as u64);
+        }
+        hash.write(match self.mode {
+            PanningMode::TwoD => 2,
+            PanningMode::ThreeD => 3,
+        });
+        hash.write(self.tuples.len() as u64);
+        for tuple in 0..self.tuples.len() {
+            for index in self.tuples.speaker_indices(tuple) {
+                hash.write(index as u64);
+            }
+        }
+        match self.arc_ends {
+            Some([first, last]) => {
+                hash.write(1);
+                hash.write(first as u64);
+                hash.write(last as u64);
+            }
+            None => hash.write(0),
+        }
+        hash.write_f64(self.tolerances.determinant);
+        hash.write_f64(self.tolerances.arc);
+        hash.write_f64(self.tolerances.normalization_floor);
+        hash.write_f64(self.tolerances.coincidence);
+        hash.write(convention_code(self.convention).into());
+        hash.finish()
     }
 
-    /// Build only the speaker configuration (without creating a panner).
+    /// Serialize the built layout, inverse matrices included.
     ///
-    /// This validates the configuration, selects valid speaker pairs/triplets,
-    /// and computes the inverse matrices needed for VBAP.
-    pub fn build_config(self) -> Result<SpeakerConfig> {
-        let n = self.speakers.len();
-
-        // Determine effective panning mode
-        let has_elevation = self.speakers.iter().any(|(_, ele)| ele.abs() > 1e-6);
-        let mode = match self.dimension {
-            Dimension::Auto => {
-                if has_elevation {
-                    PanningMode::ThreeD
-                } else {
-                    PanningMode::TwoD
+    /// Restoring with [`from_bytes`](Self::from_bytes) skips the
+    /// triangulation, which takes a noticeable time for arrays of hundreds
+    /// of speakers. Store the bytes under the builder's
+    /// [`layout_hash`](SpeakerConfigBuilder::layout_hash) to cache layouts
+    /// across runs.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use vbap::{SpeakerConfig, SpeakerConfigBuilder};
+    ///
+    /// let mut cache: HashMap<u64, Vec<u8>> = HashMap::new();
+    /// let builder = SpeakerConfigBuilder::new().atmos_7_1_4();
+    /// let key = builder.layout_hash();
+    ///
+    /// let config = match cache.get(&key).map(|bytes| SpeakerConfig::from_bytes(bytes)) {
+    ///     Some(Ok(config)) => config,
+    ///     _ => {
+    ///         let config = builder.build_config().unwrap();
+    ///         cache.insert(key, config.to_bytes());
+    ///         config
+    ///     }
+    /// };
+    ///
+    /// let cached = SpeakerConfig::from_bytes(&cache[&key]).unwrap();
+    /// assert_eq!(cached.fingerprint(), config.fingerprint());
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(64 + 26 * self.speakers.len() + self.tuples.heap_size());
+        out.extend_from_slice(&BYTES_MAGIC);
+        out.extend_from_slice(&BYTES_VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.speakers.len() as u32).to_le_bytes());
+        for speaker in &self.speakers {
+            out.extend_from_slice(&speaker.azimuth().to_le_bytes());
+            out.extend_from_slice(&speaker.elevation().to_le_bytes());
+            out.extend_from_slice(&speaker.distance().to_le_bytes());
+            out.push(speaker.is_virtual() as u8);
+            out.push(label_code(speaker.label()));
+        }
+        out.push(match self.mode {
+            PanningMode::TwoD => 2,
+            PanningMode::ThreeD => 3,
+        });
+        out.extend_from_slice(&(self.tuples.len() as u32).to_le_bytes());
+        for tuple in self.tuples.iter() {
+            for &index in tuple.speaker_indices() {
+                out.extend_from_slice(&compact_index(index).to_le_bytes());
+            }
+            let values = match tuple.inverse_matrix() {
+                InverseMatrix::TwoD(mat) => mat.to_cols_array().to_vec(),
+                InverseMatrix::ThreeD(mat) => mat.to_cols_array().to_vec(),
+            };
+            for value in values {
+                out.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        match self.arc_ends {
+            Some(ends) => {
+                out.push(1);
+                for index in ends {
+                    out.extend_from_slice(&compact_index(index).to_le_bytes());
                 }
             }
-            Dimension::Force2D => PanningMode::TwoD,
-            Dimension::Force3D => PanningMode::ThreeD,
-        };
+            None => out.push(0),
+        }
+        for value in [
+            self.tolerances.determinant,
+            self.tolerances.arc,
+            self.tolerances.normalization_floor,
+            self.tolerances.coincidence,
+        ] {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        out.push(convention_code(self.convention));
+        out.push(duplicates_code(self.duplicates));
+        out.push(self.explicit as u8);
+        let checksum = checksum(&out);
+        out.extend_from_slice(&checksum.to_le_bytes());
+        out
+    }
 
-        // Check minimum speaker count
-        let min_speakers = if mode == PanningMode::ThreeD { 3 } else { 2 };
-        if n < min_speakers {
-            return Err(VBAPError::InsufficientSpeakers {
+    /// Restore a layout serialized with [`to_bytes`](Self::to_bytes).
+    ///
+    /// Every byte is covered by a checksum, and inverse matrix entries must
+    /// be finite. Returns [`VBAPError::Parse`] if the data is truncated,
+    /// corrupted or was written by an incompatible version of the format.
+    pub fn from_bytes(bytes: &[u8]) -> Result<SpeakerConfig> {
+        let payload = bytes.len().saturating_sub(8);
+        let mut reader = ByteReader { bytes };
+        if reader.take(BYTES_MAGIC.len())? != BYTES_MAGIC {
+            return Err(VBAPError::Parse("not a serialized speaker layout".into()));
+        }
+        let version = reader.u32()?;
+        if version != BYTES_VERSION {
+            return Err(VBAPError::Parse(format!(
+                "unsupported layout format version {}",
+                version
+            )));
+        }
+
+        let n = reader.count(26)?;
+        if n > MAX_SPEAKERS {
+            return Err(VBAPError::TooManySpeakers {
                 provided: n,
-                required: min_speakers,
+                max: MAX_SPEAKERS,
+            });
+        }
+        let mut speakers = Vec::with_capacity(n);
+        for id in 0..n {
+            let azimuth = reader.f64()?;
+            let elevation = reader.f64()?;
+            let distance = reader.f64()?;
+            let speaker = Speaker::with_distance(id, azimuth, elevation, distance);
+            let speaker = match reader.u8()? {
+                0 => speaker,
+                1 => Speaker::new_virtual(id, azimuth, elevation).at_distance(distance),
+                flag => return Err(invalid_byte("virtual flag", flag)),
+            };
+            speakers.push(match reader.u8()? {
+                0 => speaker,
+                code => match ChannelLabel::ALL.get(usize::from(code) - 1) {
+                    Some(&label) => speaker.with_label(label),
+                    None => return Err(invalid_byte("channel label", code)),
+                },
             });
         }
 
-        // Create Speaker objects
-        let speakers: Vec<Speaker> = self
-            .speakers
-            .into_iter()
-            .enumerate()
-            .map(|(id, (azi, ele))| Speaker::new(id, azi, ele))
-            .collect();
-
-        // Compute tuples based on mode
-        let tuples = match mode {
-            PanningMode::ThreeD => choose_speaker_triplets(&speakers)?,
-            PanningMode::TwoD => choose_speaker_pairs(&speakers)?,
+        let mode = match reader.u8()? {
+            2 => PanningMode::TwoD,
+            3 => PanningMode::ThreeD,
+            code => return Err(invalid_byte("panning mode", code)),
         };
-
-        if tuples.is_empty() {
-            return Err(VBAPError::InvalidConfiguration(
-                "no valid speaker pairs/triplets could be formed".into(),
-            ));
+        let size = match mode {
+            PanningMode::TwoD => 2,
+            PanningMode::ThreeD => 3,
+        };
+        let num_tuples = reader.count(size * 2 + size * size * 8)?;
+        let mut tuples = Vec::with_capacity(num_tuples);
+        for _ in 0..num_tuples {
+            let mut indices = [0; 3];
+            for index in &mut indices[..size] {
+                *index = reader.index(n)?;
+            }
+            let mut values = [0.0; 9];
+            for value in &mut values[..size * size] {
+                *value = reader.f64()?;
+                if !value.is_finite() {
+                    return Err(VBAPError::Parse(format!(
+                        "non-finite inverse matrix entry {}",
+                        value
+                    )));
+                }
+            }
+            let inverse_matrix = match mode {
+                PanningMode::TwoD => InverseMatrix::TwoD(DMat2::from_cols_slice(&values[..4])),
+                PanningMode::ThreeD => InverseMatrix::ThreeD(DMat3::from_cols_array(&values)),
+            };
+            tuples.push(SpeakerTuple::new(&indices[..size], inverse_matrix));
         }
 
-        Ok(SpeakerConfig {
-            speakers,
-            mode,
-            tuples,
+        let arc_ends = match reader.u8()? {
+            0 => None,
+            1 => Some([reader.index(n)?, reader.index(n)?]),
+            flag => return Err(invalid_byte("arc flag", flag)),
+        };
+        let tolerances = Tolerances {
+            determinant: reader.f64()?,
+            arc: reader.f64()?,
+            normalization_floor: reader.f64()?,
+            coincidence: reader.f64()?,
+        };
+        let convention = match reader.u8()? {
+            0 => Convention::AmbiX,
+            1 => Convention::Max,
+            2 => Convention::Navigational,
+            code => return Err(invalid_byte("convention", code)),
+        };
+        let duplicates = match reader.u8()? {
+            0 => DuplicateSpeakers::Warn,
+            1 => DuplicateSpeakers::Error,
+            2 => DuplicateSpeakers::Merge,
+            code => return Err(invalid_byte("duplicate handling", code)),
+        };
+        let explicit = match reader.u8()? {
+            0 => false,
+            1 => true,
+            flag => return Err(invalid_byte("explicit flag", flag)),
+        };
+        let stored = reader.u64()?;
+        if !reader.bytes.is_empty() {
+            return Err(VBAPError::Parse("trailing data after layout".into()));
+        }
+        if checksum(&bytes[..payload]) != stored {
+            return Err(VBAPError::Parse("layout checksum mismatch".into()));
+        }
+
+        let config = SpeakerConfig {
+            speakers,
+            mode,
+            tuples: SpeakerTuples::from_tuples(mode, tuples),
+            arc_ends,
+            tolerances,
+            convention,
+            duplicates,
+            explicit,
+            revision: next_revision(),
+        };
+        Ok(config)
+    }
+
+    /// Create a builder pre-populated with this configuration's speakers.
+    ///
+    /// The resolved panning mode is carried over as a forced dimension, so
+    /// rebuilding never flips between 2D and 3D.
+    pub fn to_builder(&self) -> SpeakerConfigBuilder {
+        let dimension = match self.mode {
+            PanningMode::TwoD => Dimension::Force2D,
+            PanningMode::ThreeD => Dimension::Force3D,
+        };
+
+        let explicit = self.explicit.then(|| match self.mode {
+            PanningMode::TwoD => ExplicitTuples::Pairs(
+                self.tuples
+                    .iter()
+                    .map(|t| [t.indices[0], t.indices[1]])
+                    .collect(),
+            ),
+            PanningMode::ThreeD => {
+                ExplicitTuples::Triplets(self.tuples.iter().map(|t| t.indices).collect())
+            }
+        });
+
+        SpeakerConfigBuilder {
+            open_arc: self.arc_ends.is_some(),
+            tolerances: self.tolerances,
+            convention: self.convention,
+            duplicates: self.duplicates,
+            explicit,
+            speakers: self.speakers.clone(),
+            ..SpeakerConfigBuilder::new()
+        }
+        .dimension(dimension)
+    }
+
+    /// Move a speaker, approximately, without re-triangulating.
+    ///
+    /// The existing pairs/triplets are kept and only the inverse matrices of
+    /// tuples containing the moved speaker are recomputed. This is cheap enough
+    /// to call on every mouse event while a speaker is dragged in a layout
+    /// editor; call [`moved_speaker`](Self::moved_speaker) once the drag is
+    /// released to get the exact triangulation.
+    ///
+    /// Returns an error if the index is out of range or the move makes one of
+    /// the affected tuples degenerate.
+    pub fn moved_speaker_preview(
+        &self,
+        index: usize,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Result<SpeakerConfig> {
+        self.check_speaker_index(index)?;
+
+        let mut config = self.clone();
+        config.speakers[index] = config.speakers[index].relocated(index, azimuth, elevation);
+        config.revision = next_revision();
+
+        for tuple_idx in 0..config.tuples.len() {
+            let tuple = config.tuples.get(tuple_idx).expect("index in range");
+            let indices = tuple.speaker_indices();
+            if !indices.contains(&index) {
+                continue;
+            }
+            let inverse_matrix =
+                compute_inverse_matrix(&config.speakers, indices, &config.tolerances).ok_or_else(
+                    || {
+                        VBAPError::InvalidConfiguration(format!(
+                            "moving speaker {} makes tuple {:?} degenerate",
+                            index, indices
+                        ))
+                    },
+                )?;
+            config.tuples.set_inverse_matrix(tuple_idx, inverse_matrix);
+        }
+
+        Ok(config)
+    }
+
+    /// Move a speaker and fully rebuild the configuration.
+    pub fn moved_speaker(
+        &self,
+        index: usize,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Result<SpeakerConfig> {
+        self.check_speaker_index(index)?;
+
+        let mut builder = self.to_builder();
+        builder.speakers[index] = self.speakers[index].relocated(index, azimuth, elevation);
+        builder.build_config()
+    }
+
+    /// Rotate the layout around the vertical axis by `yaw` degrees.
+    ///
+    /// Positive yaw turns the layout to the left, so a speaker at 30° moves
+    /// to `30° + yaw`. Useful for stage-relative setups where the audience
+    /// does not face the layout's front. Rotation keeps the geometry, so the
+    /// triangulation is reused and only the inverse matrices are updated.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let config = VBAPanner::builder().quad().build_config().unwrap();
+    /// let turned = config.rotated(45.0);
+    /// assert!((turned.speakers()[0].azimuth() - 90.0).abs() < 1e-9);
+    ///
+    /// let panner = VBAPanner::new(turned);
+    /// assert!((panner.compute_gains(90.0, 0.0)[0] - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn rotated(&self, yaw: f64) -> SpeakerConfig {
+        let (sin, cos) = yaw.to_radians().sin_cos();
+        let linear = DMat3::from_cols(
+            DVec3::new(cos, -sin, 0.0),
+            DVec3::new(sin, cos, 0.0),
+            DVec3::Z,
+        );
+        self.transformed(linear, |azimuth, elevation| {
+            (wrap_azimuth(azimuth + yaw), elevation)
+        })
+    }
+
+    /// Rotate the layout by a quaternion, for example one from
+    /// [`euler_rotation`](crate::math::euler_rotation).
+    ///
+    /// Unlike [`rotated`](Self::rotated) this can tilt the layout. The
+    /// triangulation is reused. Returns an error for a 2D layout if the
+    /// rotation tilts the vertical axis, since its pairs only pan
+    /// horizontally.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::math::euler_rotation;
+    /// use vbap::VBAPanner;
+    ///
+    /// let config = VBAPanner::builder().atmos_7_1_4().build_config().unwrap();
+    /// // A stage tilted 10° towards the audience
+    /// let tilted = config.rotated_by(euler_rotation(0.0, -10.0, 0.0)).unwrap();
+    /// assert!((tilted.speakers()[2].elevation() + 10.0).abs() < 1e-9);
+    /// ```
+    pub fn rotated_by(&self, rotation: DQuat) -> Result<SpeakerConfig> {
+        let rotation = rotation.normalize();
+        if self.mode == PanningMode::TwoD && (rotation * DVec3::Z).z < 1.0 - 1e-9 {
+            return Err(VBAPError::InvalidConfiguration(
+                "a 2D layout can only be rotated around the vertical axis".into(),
+            ));
+        }
+        Ok(
+            self.transformed(DMat3::from_quat(rotation), |azimuth, elevation| {
+                rotate_direction(rotation, azimuth, elevation)
+            }),
+        )
+    }
+
+    /// Mirror the layout left to right (azimuth → -azimuth).
+    ///
+    /// Speaker indices are kept, so the former left channel now plays on the
+    /// right; swap channels downstream if the speakers themselves are to
+    /// stay. The triangulation is reused.
+    pub fn mirrored_lr(&self) -> SpeakerConfig {
+        let linear = DMat3::from_diagonal(DVec3::new(-1.0, 1.0, 1.0));
+        self.transformed(linear, |azimuth, elevation| {
+            (wrap_azimuth(-azimuth), elevation)
+        })
+    }
+
+    /// Mirror the layout front to back (azimuth → 180° - azimuth).
+    ///
+    /// The triangulation is reused.
+    pub fn mirrored_fb(&self) -> SpeakerConfig {
+        let linear = DMat3::from_diagonal(DVec3::new(1.0, -1.0, 1.0));
+        self.transformed(linear, |azimuth, elevation| {
+            (wrap_azimuth(180.0 - azimuth), elevation)
+        })
+    }
+
+    /// Scale all speaker distances by `factor`.
+    ///
+    /// Directions, and therefore gains, are unchanged; only the distances
+    /// used for delay and level compensation change. Returns an error if
+    /// `factor` is not finite and positive.
+    pub fn scaled(&self, factor: f64) -> Result<SpeakerConfig> {
+        if !(factor.is_finite() && factor > 0.0) {
+            return Err(VBAPError::InvalidParameter {
+                parameter: "factor",
+                value: factor,
+                min: 0.0,
+                max: f64::MAX,
+            });
+        }
+
+        let mut config = self.clone();
+        for speaker in &mut config.speakers {
+            *speaker = speaker.at_distance(speaker.distance() * factor);
+        }
+        config.revision = next_revision();
+        Ok(config)
+    }
+
+    /// Move every speaker by the orthogonal map `linear`, whose effect on
+    /// angles is given by `angles`, keeping the triangulation.
+    fn transformed(&self, linear: DMat3, angles: impl Fn(f64, f64) -> (f64, f64)) -> SpeakerConfig {
+        let mut config = self.clone();
+        for speaker in &mut config.speakers {
+            let (azimuth, elevation) = angles(speaker.azimuth(), speaker.elevation());
+            *speaker = speaker.relocated(speaker.id(), azimuth, elevation);
+        }
+        config.tuples.transform(linear);
+        config.revision = next_revision();
+        config
+    }
+
+    /// Re-triangulate the layout without some of its speakers.
+    ///
+    /// The returned configuration keeps all speakers (so gain vectors keep
+    /// their length and channel order), but the excluded ones never appear in
+    /// any tuple and therefore always receive zero gain. A 3D layout whose
+    /// remaining speakers are all horizontal is panned pairwise.
+    ///
+    /// Layouts with [explicit tuples](SpeakerConfigBuilder::with_tuples) are
+    /// not re-triangulated: the tuples using excluded speakers are dropped.
+    pub fn without_speakers(&self, excluded: &[usize]) -> Result<SpeakerConfig> {
+        for &index in excluded {
+            self.check_speaker_index(index)?;
+        }
+
+        if self.explicit {
+            let tuples: Vec<SpeakerTuple> = self
+                .tuples
+                .iter()
+                .filter(|t| !t.speaker_indices().iter().any(|i| excluded.contains(i)))
+                .collect();
+            if tuples.is_empty() {
+                return Err(VBAPError::InvalidConfiguration(
+                    "every explicit tuple uses an excluded speaker".into(),
+                ));
+            }
+            let mut config = self.clone();
+            config.tuples = SpeakerTuples::from_tuples(self.mode, tuples);
+            config.revision = next_revision();
+            return Ok(config);
+        }
+
+        let kept: Vec<usize> = (0..self.speakers.len())
+            .filter(|i| !excluded.contains(i))
+            .collect();
+
+        // Excluding every elevated speaker leaves a ring that cannot form
+        // triplets, so fall back to pairwise panning
+        let mode = if kept.iter().all(|&i| self.speakers[i].is_horizontal()) {
+            PanningMode::TwoD
+        } else {
+            self.mode
+        };
+        let min_speakers = if mode == PanningMode::ThreeD { 3 } else { 2 };
+        if kept.len() < min_speakers {
+            return Err(VBAPError::InsufficientSpeakers {
+                provided: kept.len(),
+                required: min_speakers,
+            });
+        }
+
+        let (tuples, arc_ends) = triangulate_kept(
+            &self.speakers,
+            &kept,
+            mode,
+            self.arc_ends.is_some(),
+            &self.tolerances,
+            None,
+        )?;
+
+        Ok(SpeakerConfig {
+            speakers: self.speakers.clone(),
+            mode,
+            tuples,
+            arc_ends,
+            tolerances: self.tolerances,
+            convention: self.convention,
+            duplicates: self.duplicates,
+            explicit: false,
+            revision: next_revision(),
+        })
+    }
+
+    /// Add a speaker, re-triangulating only the region around it.
+    ///
+    /// For 3D layouts the triplets around the new speaker are replaced by a
+    /// triangulation of their corners plus the new speaker, which is much
+    /// cheaper than a full rebuild on large arrays. The new speaker gets the
+    /// last index. 2D layouts, speakers outside the covered directions, and
+    /// regions the local triangulation cannot tile exactly fall back to a
+    /// full rebuild. The local result covers the same directions as a full
+    /// rebuild but may split the region into different triplets.
+    pub fn with_speaker_added(&self, azimuth: f64, elevation: f64) -> Result<SpeakerConfig> {
+        let n = self.speakers.len();
+        let rebuild = || {
+            let mut builder = self.to_builder();
+            builder.speakers.push(Speaker::new(n, azimuth, elevation));
+            builder.build_config()
+        };
+        // Only a full build applies the duplicate handling and keeps
+        // explicit tuples, which leave the new speaker unused
+        if self.mode != PanningMode::ThreeD
+            || n >= MAX_SPEAKERS
+            || self.duplicates != DuplicateSpeakers::Warn
+            || self.explicit
+        {
+            return rebuild();
+        }
+
+        let mut speakers = self.speakers.clone();
+        speakers.push(Speaker::new(n, azimuth, elevation));
+        let position = speakers[n].cartesian();
+
+        // Triplets containing the new speaker, plus their neighbors so the
+        // patch is not just a fan of thin triangles around it
+        let tuples: Vec<SpeakerTuple> = self.tuples.iter().collect();
+        let containing: Vec<&SpeakerTuple> = tuples
+            .iter()
+            .filter(|t| {
+                let [a, b, c] = t.indices.map(|i| speakers[i].cartesian());
+                is_inside_triangle(position, a, b, c)
+            })
+            .collect();
+        if containing.is_empty() {
+            return rebuild();
+        }
+        let hole: Vec<usize> = (0..tuples.len())
+            .filter(|&i| {
+                containing
+                    .iter()
+                    .any(|t| shared_speakers(&tuples[i], t) >= 2)
+            })
+            .collect();
+
+        let mut local: Vec<usize> = hole.iter().flat_map(|&i| tuples[i].indices).collect();
+        local.push(n);
+        match retriangulate_hole(&speakers, &tuples, &hole, local, &self.tolerances) {
+            Some(tuples) => Ok(SpeakerConfig {
+                speakers,
+                mode: self.mode,
+                tuples: SpeakerTuples::from_tuples(self.mode, tuples),
+                arc_ends: None,
+                tolerances: self.tolerances,
+                convention: self.convention,
+                duplicates: self.duplicates,
+                explicit: false,
+                revision: next_revision(),
+            }),
+            None => rebuild(),
+        }
+    }
+
+    /// Remove a speaker, re-triangulating only the region around it.
+    ///
+    /// Speakers after `index` move down by one. For 3D layouts only the
+    /// triplets using the speaker are replaced, by a triangulation of their
+    /// other corners; otherwise, or if that cannot tile the hole exactly, the
+    /// layout is rebuilt from scratch. To silence a speaker while keeping
+    /// channel order, use [`without_speakers`](Self::without_speakers).
+    ///
+    /// Returns an error if the index is out of range or too few speakers
+    /// remain.
+    pub fn with_speaker_removed(&self, index: usize) -> Result<SpeakerConfig> {
+        self.check_speaker_index(index)?;
+
+        let remaining: Vec<Speaker> = self
+            .speakers
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != index)
+            .enumerate()
+            .map(|(id, (_, s))| s.relocated(id, s.azimuth(), s.elevation()))
+            .collect();
+        let rebuild = || {
+            let mut builder = self.to_builder();
+            builder.speakers.remove(index);
+            if let Some(explicit) = &mut builder.explicit {
+                explicit.remove_speaker(index);
+            }
+            builder.build_config()
+        };
+        if self.mode != PanningMode::ThreeD || remaining.len() < 4 || self.explicit {
+            return rebuild();
+        }
+
+        let tuples: Vec<SpeakerTuple> = self.tuples.iter().collect();
+        let hole: Vec<usize> = (0..tuples.len())
+            .filter(|&i| tuples[i].indices.contains(&index))
+            .collect();
+        let local: Vec<usize> = hole
+            .iter()
+            .flat_map(|&i| tuples[i].indices)
+            .filter(|&i| i != index)
+            .collect();
+
+        match retriangulate_hole(&self.speakers, &tuples, &hole, local, &self.tolerances) {
+            Some(mut tuples) => {
+                for tuple in &mut tuples {
+                    for i in &mut tuple.indices {
+                        if *i > index {
+                            *i -= 1;
+                        }
+                    }
+                }
+                Ok(SpeakerConfig {
+                    speakers: remaining,
+                    mode: self.mode,
+                    tuples: SpeakerTuples::from_tuples(self.mode, tuples),
+                    arc_ends: None,
+                    tolerances: self.tolerances,
+                    convention: self.convention,
+                    duplicates: self.duplicates,
+                    explicit: false,
+                    revision: next_revision(),
+                })
+            }
+            None => rebuild(),
+        }
+    }
+
+    /// Revision number identifying this exact layout.
+    #[inline]
+    pub(crate) fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Return an error if `index` does not refer to a speaker.
+    pub(crate) fn check_speaker_index(&self, index: usize) -> Result<()> {
+        if index >= self.speakers.len() {
+            return Err(VBAPError::InvalidSpeakerIndex {
+                index,
+                num_speakers: self.speakers.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Builder for constructing speaker configurations.
+#[derive(Clone, Debug, Default)]
+pub struct SpeakerConfigBuilder {
+    /// Speakers to build; indices are assigned by `build_config`.
+    speakers: Vec<Speaker>,
+    dimension: Dimension,
+    open_arc: bool,
+    tolerances: Tolerances,
+    convention: Convention,
+    duplicates: DuplicateSpeakers,
+    /// User-supplied tuples replacing the automatic triangulation.
+    explicit: Option<ExplicitTuples>,
+}
+
+/// Speaker tuples given to [`SpeakerConfigBuilder::with_tuples`] or
+/// [`SpeakerConfigBuilder::with_pairs`].
+#[derive(Clone, Debug)]
+enum ExplicitTuples {
+    Pairs(Vec<[usize; 2]>),
+    Triplets(Vec<[usize; 3]>),
+}
+
+impl ExplicitTuples {
+    /// Drop the tuples using a removed speaker and renumber the others.
+    fn remove_speaker(&mut self, index: usize) {
+        fn renumber<const N: usize>(tuples: &mut Vec<[usize; N]>, index: usize) {
+            tuples.retain(|t| !t.contains(&index));
+            for i in tuples.iter_mut().flatten() {
+                if *i > index {
+                    *i -= 1;
+                }
+            }
+        }
+        match self {
+            ExplicitTuples::Pairs(pairs) => renumber(pairs, index),
+            ExplicitTuples::Triplets(triplets) => renumber(triplets, index),
+        }
+    }
+}
+
+impl SpeakerConfigBuilder {
+    /// Create a new empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a speaker at the given position.
+    ///
+    /// # Arguments
+    /// * `azimuth` - Horizontal angle in degrees (0° = front, 90° = left, -90° = right)
+    /// * `elevation` - Vertical angle in degrees (0° = horizontal, 90° = above)
+    ///
+    /// The angles are in the builder's [`convention`](Self::convention).
+    pub fn add_speaker(mut self, azimuth: f64, elevation: f64) -> Self {
+        let (azimuth, elevation) = self.convention.to_native(azimuth, elevation);
+        self.speakers
+            .push(Speaker::new(self.speakers.len(), azimuth, elevation));
+        self
+    }
+
+    /// Add a speaker with a channel label.
+    ///
+    /// Like [`add_speaker`](Self::add_speaker); the label lets gains be
+    /// looked up by channel name (see [`Gains::by_label`](crate::gains::Gains::by_label)).
+    pub fn add_labeled_speaker(
+        mut self,
+        label: ChannelLabel,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Self {
+        let (azimuth, elevation) = self.convention.to_native(azimuth, elevation);
+        let speaker = Speaker::new(self.speakers.len(), azimuth, elevation);
+        self.speakers.push(speaker.with_label(label));
+        self
+    }
+
+    /// Add a speaker at a physical position in meters, relative to the
+    /// listening position.
+    ///
+    /// Uses the panner's Cartesian frame: `x` to the left, `y` to the front
+    /// and `z` up. The position is converted to azimuth and elevation, and
+    /// the distance is kept on the [`Speaker`] for delay and level
+    /// compensation (see
+    /// [`ListenerCompensation::from_speaker_distances`](crate::listener::ListenerCompensation::from_speaker_distances)).
+    /// A speaker at the listening position makes the build fail.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder()
+    ///     .add_speaker_xyz(1.5, 2.6, 0.0) // front left
+    ///     .add_speaker_xyz(-1.5, 2.6, 0.0) // front right
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let left = &panner.speakers()[0];
+    /// assert!((left.azimuth() - 30.0).abs() < 0.1);
+    /// assert!((left.distance() - 3.0).abs() < 0.01);
+    /// ```
+    pub fn add_speaker_xyz(mut self, x: f64, y: f64, z: f64) -> Self {
+        let position = DVec3::new(x, y, z);
+        let (azimuth, elevation) = cartesian_to_spherical(position);
+        self.speakers.push(Speaker::with_distance(
+            self.speakers.len(),
+            azimuth,
+            elevation,
+            position.length(),
+        ));
+        self
+    }
+
+    /// Add multiple speakers from an array of (azimuth, elevation) pairs.
+    pub fn add_speakers(mut self, positions: &[(f64, f64)]) -> Self {
+        for &(azimuth, elevation) in positions {
+            self = self.add_speaker(azimuth, elevation);
+        }
+        self
+    }
+
+    /// Add a virtual (imaginary) speaker.
+    ///
+    /// Virtual speakers close gaps in the triangulation, e.g. below a dome,
+    /// but are never fed: their gain is discarded (see
+    /// [`Speaker::new_virtual`]). They still get an index, so add them after
+    /// the physical speakers to keep channel order.
+    pub fn add_virtual_speaker(mut self, azimuth: f64, elevation: f64) -> Self {
+        let (azimuth, elevation) = self.convention.to_native(azimuth, elevation);
+        self.speakers.push(Speaker::new_virtual(
+            self.speakers.len(),
+            azimuth,
+            elevation,
+        ));
+        self
+    }
+
+    /// Add a prepared speaker, keeping its distance and virtual flag.
+    ///
+    /// Its index is reassigned by `build_config`.
+    #[cfg_attr(not(feature = "io"), allow(dead_code))]
+    pub(crate) fn add(mut self, speaker: Speaker) -> Self {
+        self.speakers.push(speaker);
+        self
+    }
+
+    /// Set the dimension mode.
+    pub fn dimension(mut self, dim: Dimension) -> Self {
+        self.dimension = dim;
+        self
+    }
+
+    /// Treat a 2D layout as an open arc instead of a closed ring.
+    ///
+    /// By default the speaker with the largest azimuth is paired with the one
+    /// with the smallest, closing the ring. For frontal arrays (e.g. an LCR
+    /// array in a theater) this wrap-around pair is wrong: with `open_arc`,
+    /// the largest gap between adjacent speakers is left unpaired, and
+    /// sources outside the arc are clamped to the nearest end speaker.
+    ///
+    /// Has no effect in 3D mode.
+    pub fn open_arc(mut self) -> Self {
+        self.open_arc = true;
+        self
+    }
+
+    /// Override the numerical tolerances, see [`Tolerances`].
+    pub fn tolerances(mut self, tolerances: Tolerances) -> Self {
+        self.tolerances = tolerances;
+        self
+    }
+
+    /// Choose what happens to speakers closer together than
+    /// [`Tolerances::coincidence`], see [`DuplicateSpeakers`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::{DuplicateSpeakers, VBAPError, VBAPanner};
+    ///
+    /// // The left speaker was entered twice
+    /// let builder = VBAPanner::builder().surround_5_1().add_speaker(30.0, 0.0);
+    /// let result = builder.clone().duplicate_speakers(DuplicateSpeakers::Error).build();
+    /// assert!(matches!(result, Err(VBAPError::CoincidentSpeakers { first: 0, second: 5, .. })));
+    ///
+    /// let panner = builder.duplicate_speakers(DuplicateSpeakers::Merge).build().unwrap();
+    /// let gains = panner.compute_gains(30.0, 0.0);
+    /// assert!((gains[0] - 1.0).abs() < 1e-9 && gains[5] == 0.0);
+    /// ```
+    pub fn duplicate_speakers(mut self, handling: DuplicateSpeakers) -> Self {
+        self.duplicates = handling;
+        self
+    }
+
+    /// Use these speaker triplets instead of the automatic triangulation.
+    ///
+    /// Indices refer to speakers in the order they were added. The layout
+    /// is panned in 3D with exactly these triplets; building fails if an
+    /// index is out of range or a triplet is degenerate. This is for
+    /// hand-tuned triangulations, e.g. ported from an existing Max/MSP or
+    /// SuperCollider setup; directions outside every triplet are not
+    /// covered.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder()
+    ///     .add_speakers(&[(45.0, 0.0), (-45.0, 0.0), (0.0, 60.0), (180.0, 0.0)])
+    ///     .with_tuples(&[[0, 1, 2], [0, 2, 3], [1, 2, 3]])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(panner.config().tuples().len(), 3);
+    /// ```
+    pub fn with_tuples(mut self, triplets: &[[usize; 3]]) -> Self {
+        self.explicit = Some(ExplicitTuples::Triplets(triplets.to_vec()));
+        self
+    }
+
+    /// Use these speaker pairs instead of the automatic pair selection.
+    ///
+    /// Like [`with_tuples`](Self::with_tuples), for 2D layouts.
+    pub fn with_pairs(mut self, pairs: &[[usize; 2]]) -> Self {
+        self.explicit = Some(ExplicitTuples::Pairs(pairs.to_vec()));
+        self
+    }
+
+    /// Set the angle convention, see [`Convention`].
+    ///
+    /// Applies to speakers added by angle after this call
+    /// ([`add_speaker`](Self::add_speaker), [`add_speakers`](Self::add_speakers),
+    /// [`add_virtual_speaker`](Self::add_virtual_speaker)) and to source
+    /// directions passed to the built panner. Presets and
+    /// [`add_speaker_xyz`](Self::add_speaker_xyz) are unaffected, and
+    /// [`Speaker`] angles are always reported in the crate's own convention.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::convention::Convention;
+    /// use vbap::VBAPanner;
+    ///
+    /// // A stereo pair copied from a Max patch: left is at -30°
+    /// let panner = VBAPanner::builder()
+    ///     .convention(Convention::Max)
+    ///     .add_speakers(&[(-30.0, 0.0), (30.0, 0.0)])
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(panner.speakers()[0].azimuth(), 30.0);
+    /// // Sources use the same convention
+    /// assert!(panner.compute_gains(-30.0, 0.0)[0] > 0.99);
+    /// ```
+    pub fn convention(mut self, convention: Convention) -> Self {
+        self.convention = convention;
+        self
+    }
+
+    // === Preset configurations ===
+
+    /// Configure a preset by name, one of [`PRESET_NAMES`].
+    ///
+    /// For tools that take the layout as text, such as command lines and
+    /// language bindings. Names are case-insensitive. Returns
+    /// [`VBAPError::InvalidConfiguration`] for an unknown name.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::SpeakerConfigBuilder;
+    ///
+    /// let config = SpeakerConfigBuilder::new().preset("7.1.4").unwrap().build_config().unwrap();
+    /// assert_eq!(config.num_speakers(), 11);
+    /// ```
+    pub fn preset(self, name: &str) -> Result<Self> {
+        Ok(match name.trim().to_ascii_lowercase().as_str() {
+            "stereo" => self.stereo(),
+            "stereo-wide" => self.stereo_wide(),
+            "lcr" => self.lcr(),
+            "quad" => self.quad(),
+            "5.1" => self.surround_5_1(),
+            "7.1" => self.surround_7_1(),
+            "7.1.4" => self.atmos_7_1_4(),
+            "5.1.4" => self.atmos_5_1_4(),
+            "hexagon" => self.hexagon(),
+            "octagon" => self.octagon(),
+            _ => {
+                return Err(VBAPError::InvalidConfiguration(format!(
+                    "unknown preset '{}', expected one of {}",
+                    name,
+                    PRESET_NAMES.join(", ")
+                )))
+            }
+        })
+    }
+
+    /// Configure for standard stereo (L/R at ±30°).
+    pub fn stereo(self) -> Self {
+        self.add_preset(presets::STEREO, presets::STEREO_LABELS)
+    }
+
+    /// Configure for wide stereo (L/R at ±60°).
+    pub fn stereo_wide(self) -> Self {
+        self.add_preset(presets::STEREO_WIDE, presets::STEREO_LABELS)
+    }
+
+    /// Configure for LCR (Left-Center-Right).
+    pub fn lcr(self) -> Self {
+        self.add_preset(presets::LCR, presets::LCR_LABELS)
+    }
+
+    /// Configure for quadraphonic (4.0).
+    pub fn quad(self) -> Self {
+        self.add_preset(presets::QUAD, presets::QUAD_LABELS)
+    }
+
+    /// Configure for 5.0/5.1 surround.
+    pub fn surround_5_1(self) -> Self {
+        self.add_preset(presets::SURROUND_5_1, presets::SURROUND_5_0_LABELS)
+    }
+
+    /// Configure for 7.0/7.1 surround.
+    pub fn surround_7_1(self) -> Self {
+        self.add_preset(presets::SURROUND_7_1, presets::SURROUND_7_0_LABELS)
+    }
+
+    /// Configure for Dolby Atmos 7.1.4.
+    pub fn atmos_7_1_4(self) -> Self {
+        self.add_preset(presets::ATMOS_7_1_4, presets::ATMOS_7_1_4_LABELS)
+    }
+
+    /// Configure for Dolby Atmos 5.1.4.
+    pub fn atmos_5_1_4(self) -> Self {
+        self.add_preset(presets::ATMOS_5_1_4, presets::ATMOS_5_1_4_LABELS)
+    }
+
+    /// Configure for hexagonal (6 speakers in ring).
+    pub fn hexagon(self) -> Self {
+        self.add_preset(presets::HEXAGON, &[])
+    }
+
+    /// Configure for octagonal (8 speakers in ring).
+    pub fn octagon(self) -> Self {
+        self.add_preset(presets::OCTAGON, &[])
+    }
+
+    /// Add preset speakers, which are in the crate's own convention.
+    fn add_preset(mut self, positions: &[(f64, f64)], labels: &[ChannelLabel]) -> Self {
+        let first = self.speakers.len();
+        let convention = std::mem::take(&mut self.convention);
+        self = self.add_speakers(positions);
+        self.convention = convention;
+        for (speaker, &label) in self.speakers[first..].iter_mut().zip(labels) {
+            *speaker = speaker.clone().with_label(label);
+        }
+        self
+    }
+
+    /// Build a `VBAPanner` from this configuration.
+    ///
+    /// This is the primary build method and returns a ready-to-use panner.
+    pub fn build(self) -> Result<VBAPanner> {
+        Ok(VBAPanner::new(self.build_config()?))
+    }
+
+    /// Build only the speaker configuration (without creating a panner).
+    ///
+    /// This validates the configuration, selects valid speaker pairs/triplets,
+    /// and computes the inverse matrices needed for VBAP. The result is
+    /// deterministic, see the [module documentation](self).
+    pub fn build_config(self) -> Result<SpeakerConfig> {
+        self.build_config_reporting(None)
+    }
+
+    /// Build the speaker configuration and report how it was triangulated.
+    ///
+    /// The report lists rejected candidate tuples, removed crossing
+    /// connections, near-coincident speakers and uncovered directions (see
+    /// [`TriangulationReport`]). It is returned even when the build fails,
+    /// since that is when it is needed most.
+    pub fn build_config_with_report(self) -> (Result<SpeakerConfig>, TriangulationReport) {
+        let mut report = TriangulationReport::default();
+        let config = self.build_config_reporting(Some(&mut report));
+        if let Ok(config) = &config {
+            report.uncovered = config.uncovered_directions();
+        }
+        (config, report)
+    }
+
+    /// Compute a hash of everything the built layout depends on.
+    ///
+    /// Use it to key a cache of [`SpeakerConfig::to_bytes`] output: equal
+    /// builders hash alike on every run and platform, so the triangulation
+    /// only has to run once. The crate version is part of the hash, so
+    /// cached layouts are rebuilt after an upgrade that might triangulate
+    /// differently.
+    pub fn layout_hash(&self) -> u64 {
+        let mut hash = Fingerprint::new();
+        for byte in env!("CARGO_PKG_VERSION").bytes() {
+            hash.write(byte.into());
+        }
+        hash.write(self.speakers.len() as u64);
+        for speaker in &self.speakers {
+            hash.write_f64(speaker.azimuth());
+            hash.write_f64(speaker.elevation());
+            hash.write_f64(speaker.distance());
+            hash.write(speaker.is_virtual() as u64);
+            hash.write(label_code(speaker.label()).into());
+        }
+        hash.write(match self.dimension {
+            Dimension::Auto => 0,
+            Dimension::Force2D => 2,
+            Dimension::Force3D => 3,
+        });
+        hash.write(self.open_arc as u64);
+        hash.write_f64(self.tolerances.determinant);
+        hash.write_f64(self.tolerances.arc);
+        hash.write_f64(self.tolerances.normalization_floor);
+        hash.write_f64(self.tolerances.coincidence);
+        hash.write(convention_code(self.convention).into());
+        hash.write(duplicates_code(self.duplicates).into());
+        match &self.explicit {
+            None => hash.write(0),
+            Some(ExplicitTuples::Pairs(pairs)) => {
+                hash.write(2);
+                hash.write(pairs.len() as u64);
+                for &index in pairs.iter().flatten() {
+                    hash.write(index as u64);
+                }
+            }
+            Some(ExplicitTuples::Triplets(triplets)) => {
+                hash.write(3);
+                hash.write(triplets.len() as u64);
+                for &index in triplets.iter().flatten() {
+                    hash.write(index as u64);
+                }
+            }
+        }
+        hash.finish()
+    }
+
+    fn build_config_reporting(
+        self,
+        mut report: Option<&mut TriangulationReport>,
+    ) -> Result<SpeakerConfig> {
+        let n = self.speakers.len();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("build_config", speakers = n).entered();
+        self.tolerances.validate()?;
+
+        // Determine effective panning mode
+        let has_elevation = self.speakers.iter().any(|s| !s.is_horizontal());
+        let mode = match (&self.explicit, self.dimension) {
+            (Some(ExplicitTuples::Pairs(_)), _) => PanningMode::TwoD,
+            (Some(ExplicitTuples::Triplets(_)), _) => PanningMode::ThreeD,
+            (None, Dimension::Auto) => {
+                if has_elevation {
+                    PanningMode::ThreeD
+                } else {
+                    PanningMode::TwoD
+                }
+            }
+            (None, Dimension::Force2D) => PanningMode::TwoD,
+            (None, Dimension::Force3D) => PanningMode::ThreeD,
+        };
+
+        // Check minimum speaker count
+        let min_speakers = if mode == PanningMode::ThreeD { 3 } else { 2 };
+        if n < min_speakers {
+            return Err(VBAPError::InsufficientSpeakers {
+                provided: n,
+                required: min_speakers,
+            });
+        }
+        if n > MAX_SPEAKERS {
+            return Err(VBAPError::TooManySpeakers {
+                provided: n,
+                max: MAX_SPEAKERS,
+            });
+        }
+
+        if let Some(speaker) = self
+            .speakers
+            .iter()
+            .find(|s| !(s.distance().is_finite() && s.distance() > 0.0))
+        {
+            return Err(VBAPError::InvalidParameter {
+                parameter: "distance",
+                value: speaker.distance(),
+                min: 0.0,
+                max: f64::MAX,
+            });
+        }
+
+        // Number the speakers in order
+        let speakers: Vec<Speaker> = self
+            .speakers
+            .iter()
+            .enumerate()
+            .map(|(id, s)| s.relocated(id, s.azimuth(), s.elevation()))
+            .collect();
+
+        let coincident = if report.is_some() || self.duplicates != DuplicateSpeakers::Warn {
+            coincident_speakers(&speakers, self.tolerances.coincidence)
+        } else {
+            Vec::new()
+        };
+        let merged: Vec<usize> = match (self.duplicates, coincident.first()) {
+            (DuplicateSpeakers::Error, Some(pair)) => {
+                return Err(VBAPError::CoincidentSpeakers {
+                    first: pair.speakers[0],
+                    second: pair.speakers[1],
+                    angle: pair.angle,
+                });
+            }
+            (DuplicateSpeakers::Merge, _) => {
+                coincident.iter().map(|pair| pair.speakers[1]).collect()
+            }
+            _ => Vec::new(),
+        };
+        if let Some(report) = report.as_deref_mut() {
+            report.coincident = coincident;
+        }
+
+        let (tuples, arc_ends) = match &self.explicit {
+            Some(explicit) => (
+                explicit_tuples(&speakers, explicit, &self.tolerances)?,
+                None,
+            ),
+            None => {
+                let kept: Vec<usize> = (0..n).filter(|i| !merged.contains(i)).collect();
+                triangulate_kept(
+                    &speakers,
+                    &kept,
+                    mode,
+                    self.open_arc,
+                    &self.tolerances,
+                    report,
+                )?
+            }
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!(?mode, tuples = tuples.len(), "triangulated layout");
+
+        Ok(SpeakerConfig {
+            speakers,
+            mode,
+            tuples,
+            arc_ends,
+            tolerances: self.tolerances,
+            convention: self.convention,
+            duplicates: self.duplicates,
+            explicit: self.explicit.is_some(),
+            revision: next_revision(),
+        })
+    }
+}
+
+/// Validate user-supplied tuples and compute their inverse matrices.
+fn explicit_tuples(
+    speakers: &[Speaker],
+    explicit: &ExplicitTuples,
+    tolerances: &Tolerances,
+) -> Result<SpeakerTuples> {
+    let (mode, list): (PanningMode, Vec<&[usize]>) = match explicit {
+        ExplicitTuples::Pairs(pairs) => (PanningMode::TwoD, pairs.iter().map(|t| &t[..]).collect()),
+        ExplicitTuples::Triplets(triplets) => (
+            PanningMode::ThreeD,
+            triplets.iter().map(|t| &t[..]).collect(),
+        ),
+    };
+    if list.is_empty() {
+        return Err(VBAPError::InvalidConfiguration(
+            "no explicit speaker tuples given".into(),
+        ));
+    }
+
+    let tuples = list
+        .into_iter()
+        .map(|indices| {
+            if let Some(&index) = indices.iter().find(|&&i| i >= speakers.len()) {
+                return Err(VBAPError::InvalidSpeakerIndex {
+                    index,
+                    num_speakers: speakers.len(),
+                });
+            }
+            let inverse_matrix =
+                compute_inverse_matrix(speakers, indices, tolerances).ok_or_else(|| {
+                    VBAPError::InvalidConfiguration(format!(
+                        "speaker tuple {:?} is degenerate",
+                        indices
+                    ))
+                })?;
+            Ok(SpeakerTuple::new(indices, inverse_matrix))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(SpeakerTuples::from_tuples(mode, tuples))
+}
+
+/// Triangulate only the `kept` speakers of a layout.
+///
+/// Tuple indices, arc ends and report entries refer to the full `speakers`
+/// list.
+fn triangulate_kept(
+    speakers: &[Speaker],
+    kept: &[usize],
+    mode: PanningMode,
+    open_arc: bool,
+    tolerances: &Tolerances,
+    report: Option<&mut TriangulationReport>,
+) -> Result<(SpeakerTuples, Option<[usize; 2]>)> {
+    let subset: Vec<Speaker> = kept.iter().map(|&i| speakers[i].clone()).collect();
+    let mut local_report = report.is_some().then(TriangulationReport::default);
+    let result = triangulate(&subset, mode, open_arc, tolerances, local_report.as_mut());
+
+    if let (Some(report), Some(local)) = (report, local_report) {
+        report
+            .rejected
+            .extend(local.rejected.into_iter().map(|mut tuple| {
+                for i in &mut tuple.speakers {
+                    *i = kept[*i];
+                }
+                if let Rejection::InteriorSpeaker(i) = &mut tuple.reason {
+                    *i = kept[*i];
+                }
+                tuple
+            }));
+        report
+            .removed_connections
+            .extend(
+                local
+                    .removed_connections
+                    .into_iter()
+                    .map(|connection| RemovedConnection {
+                        speakers: connection.speakers.map(|i| kept[i]),
+                        crossed: connection.crossed.map(|i| kept[i]),
+                    }),
+            );
+    }
+
+    // Map subset indices back to the full layout
+    let (mut tuples, arc_ends) = result?;
+    for indices in &mut tuples.indices {
+        for index in indices {
+            *index = compact_index(kept[usize::from(*index)]);
+        }
+    }
+    Ok((tuples, arc_ends.map(|ends| ends.map(|i| kept[i]))))
+}
+
+/// Compute tuples based on mode.
+///
+/// Rejected candidates and removed connections are recorded in `report`.
+fn triangulate(
+    speakers: &[Speaker],
+    mode: PanningMode,
+    open_arc: bool,
+    tolerances: &Tolerances,
+    report: Option<&mut TriangulationReport>,
+) -> Result<(SpeakerTuples, Option<[usize; 2]>)> {
+    let (tuples, arc_ends) = match mode {
+        PanningMode::ThreeD => (choose_speaker_triplets(speakers, tolerances, report)?, None),
+        PanningMode::TwoD => choose_speaker_pairs(speakers, open_arc, tolerances, report)?,
+    };
+
+    if tuples.is_empty() {
+        return Err(VBAPError::InvalidConfiguration(
+            "no valid speaker pairs/triplets could be formed".into(),
+        ));
+    }
+
+    Ok((SpeakerTuples::from_tuples(mode, tuples), arc_ends))
+}
+
+// Speaker indices are stored as u16.
+const _: () = assert!(MAX_SPEAKERS <= u16::MAX as usize + 1);
+
+/// Narrow a speaker index for compact tuple storage.
+fn compact_index(index: usize) -> u16 {
+    u16::try_from(index).expect("layouts are limited to MAX_SPEAKERS")
+}
+
+/// Find pairs of speakers less than `threshold` radians apart.
+fn coincident_speakers(speakers: &[Speaker], threshold: f64) -> Vec<CoincidentSpeakers> {
+    let mut coincident = Vec::new();
+    for (i, a) in speakers.iter().enumerate() {
+        for (j, b) in speakers.iter().enumerate().skip(i + 1) {
+            let angle = a.cartesian().angle_between(b.cartesian());
+            if angle < threshold {
+                coincident.push(CoincidentSpeakers {
+                    speakers: [i, j],
+                    angle: angle.to_degrees(),
+                });
+            }
+        }
+    }
+    coincident
+}
+
+/// Evenly spread unit directions (Fibonacci sphere).
+pub(crate) fn fibonacci_sphere(n: usize) -> Vec<DVec3> {
+    let golden_angle = 180.0 * (3.0 - 5f64.sqrt());
+    (0..n)
+        .map(|i| {
+            let z = 1.0 - (2 * i + 1) as f64 / n as f64;
+            spherical_to_cartesian(i as f64 * golden_angle, z.asin().to_degrees())
         })
+        .collect()
+}
+
+/// Leading bytes of [`SpeakerConfig::to_bytes`] output.
+const BYTES_MAGIC: [u8; 8] = *b"VBAPCFG\0";
+
+/// Version of the [`SpeakerConfig::to_bytes`] format.
+///
+/// Version 2 checksums every byte instead of the layout fingerprint.
+const BYTES_VERSION: u32 = 2;
+
+/// Checksum of serialized layout bytes.
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut hash = Fingerprint::new();
+    hash.write_bytes(bytes);
+    hash.finish()
+}
+
+/// Reads little-endian values from the front of a byte slice.
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.bytes.len() < len {
+            return Err(VBAPError::Parse("serialized layout is truncated".into()));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    /// Read an element count, checking that the remaining data can hold
+    /// that many elements of at least `size` bytes before anything is
+    /// allocated for them.
+    fn count(&mut self, size: usize) -> Result<usize> {
+        let count = self.u32()? as usize;
+        if count.saturating_mul(size) > self.bytes.len() {
+            return Err(VBAPError::Parse("serialized layout is truncated".into()));
+        }
+        Ok(count)
+    }
+
+    /// Read a speaker index of a layout with `n` speakers.
+    fn index(&mut self, n: usize) -> Result<usize> {
+        let index = usize::from(u16::from_le_bytes(self.array()?));
+        if index >= n {
+            return Err(VBAPError::Parse(format!(
+                "speaker index {} out of range",
+                index
+            )));
+        }
+        Ok(index)
+    }
+}
+
+/// Error for an unknown code in serialized data.
+fn invalid_byte(field: &str, value: u8) -> VBAPError {
+    VBAPError::Parse(format!("invalid {} {}", field, value))
+}
+
+/// Stable code of a convention for hashing and serialization.
+fn convention_code(convention: Convention) -> u8 {
+    match convention {
+        Convention::AmbiX => 0,
+        Convention::Max => 1,
+        Convention::Navigational => 2,
+    }
+}
+
+/// Stable code of a channel label for hashing and serialization, 0 for
+/// none.
+fn label_code(label: Option<ChannelLabel>) -> u8 {
+    label.map_or(0, |label| {
+        let index = ChannelLabel::ALL.iter().position(|&l| l == label);
+        index.map_or(0, |i| i as u8 + 1)
+    })
+}
+
+/// Stable code of a duplicate handling for hashing and serialization.
+fn duplicates_code(duplicates: DuplicateSpeakers) -> u8 {
+    match duplicates {
+        DuplicateSpeakers::Warn => 0,
+        DuplicateSpeakers::Error => 1,
+        DuplicateSpeakers::Merge => 2,
+    }
+}
+
+/// 64-bit FNV-1a hash over little-endian words.
+///
+/// Unlike `std`'s hashers its output is specified, so fingerprints can be
+/// stored and compared across builds.
+struct Fingerprint(u64);
+
+impl Fingerprint {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    /// Hash a float by its bits, with `-0.0` and `0.0` hashing alike.
+    fn write_f64(&mut self, value: f64) {
+        self.write((value + 0.0).to_bits());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
     }
 }
 
+/// Allocate a new configuration revision number.
+fn next_revision() -> u64 {
+    static REVISION: AtomicU64 = AtomicU64::new(0);
+    REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
 /// Choose valid speaker pairs for 2D VBAP and compute their inverse matrices.
 ///
 /// Based on Ardour's `choose_speaker_pairs()` in vbap_speakers.cc.
-fn choose_speaker_pairs(speakers: &[Speaker]) -> Result<Vec<SpeakerTuple>> {
+///
+/// If `open_arc` is set, the pair spanning the largest azimuth gap is left out
+/// and the two speakers bordering that gap are returned as the arc ends.
+fn choose_speaker_pairs(
+    speakers: &[Speaker],
+    open_arc: bool,
+    tolerances: &Tolerances,
+    mut report: Option<&mut TriangulationReport>,
+) -> Result<(Vec<SpeakerTuple>, Option<[usize; 2]>)> {
     let n = speakers.len();
     if n < 2 {
         return Err(VBAPError::InsufficientSpeakers {
@@ -261,12 +2262,30 @@ fn choose_speaker_pairs(speakers: &[Speaker]) -> Result<Vec<SpeakerTuple>> {
         });
     }
 
-    // Sort speakers by azimuth
+    // Sort speakers by azimuth, ties by index
     let mut sorted_indices: Vec<usize> = (0..n).collect();
-    sorted_indices.sort_by(|&a, &b| speakers[a].azimuth().total_cmp(&speakers[b].azimuth()));
+    sorted_indices.sort_by(|&a, &b| {
+        speakers[a]
+            .azimuth()
+            .total_cmp(&speakers[b].azimuth())
+            .then(a.cmp(&b))
+    });
+
+    // For an open arc, find the largest gap between adjacent speakers
+    let gap = open_arc.then(|| {
+        (0..n)
+            .map(|i| {
+                let azi1 = speakers[sorted_indices[i]].azimuth();
+                let azi2 = speakers[sorted_indices[(i + 1) % n]].azimuth();
+                (i, (azi2 - azi1).rem_euclid(360.0))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(0, |(i, _)| i)
+    });
 
     // Create pairs from adjacent speakers (in sorted order)
     let tuples = (0..n)
+        .filter(|&i| Some(i) != gap)
         .filter_map(|i| {
             let idx1 = sorted_indices[i];
             let idx2 = sorted_indices[(i + 1) % n];
@@ -274,41 +2293,62 @@ fn choose_speaker_pairs(speakers: &[Speaker]) -> Result<Vec<SpeakerTuple>> {
             let s1 = &speakers[idx1];
             let s2 = &speakers[idx2];
 
+            let indices = [idx1, idx2];
+            let mut reject = |reason| {
+                if let Some(report) = report.as_deref_mut() {
+                    report.rejected.push(RejectedTuple {
+                        speakers: indices.to_vec(),
+                        reason,
+                    });
+                }
+                None
+            };
+
             // Skip pairs that are too close or too far apart
             let angle = s1.cartesian().angle_between(s2.cartesian());
-            if !(MIN_PAIR_ANGLE..=MAX_PAIR_ANGLE).contains(&angle) {
-                return None;
+            if angle < MIN_PAIR_ANGLE {
+                return reject(Rejection::TooClose);
             }
-
-            // Compute 2x2 inverse matrix for this pair
-            // Matrix columns are speaker direction vectors (sin/cos of azimuth)
-            let azi1_rad = s1.azimuth().to_radians();
-            let azi2_rad = s2.azimuth().to_radians();
-
-            let mat = DMat2::from_cols(
-                DVec2::new(azi1_rad.sin(), azi1_rad.cos()),
-                DVec2::new(azi2_rad.sin(), azi2_rad.cos()),
-            );
-
-            if mat.determinant().abs() < 1e-10 {
-                return None;
+            if angle > MAX_PAIR_ANGLE {
+                return reject(Rejection::TooWide);
             }
 
-            Some(SpeakerTuple {
-                speaker_indices: vec![idx1, idx2],
-                inverse_matrix: InverseMatrix::TwoD(mat.inverse()),
-            })
+            let Some(inverse_matrix) = compute_inverse_matrix(speakers, &indices, tolerances)
+            else {
+                return reject(Rejection::Singular);
+            };
+
+            Some(SpeakerTuple::new(&indices, inverse_matrix))
         })
         .collect();
 
-    Ok(tuples)
+    let arc_ends = gap.map(|i| [sorted_indices[(i + 1) % n], sorted_indices[i]]);
+
+    Ok((tuples, arc_ends))
 }
 
 /// Choose valid speaker triplets for 3D VBAP and compute their inverse matrices.
 ///
 /// Based on Ardour's `choose_speaker_triplets()` in vbap_speakers.cc.
 /// This implements a convex hull-like algorithm to find valid triangular facets.
-fn choose_speaker_triplets(speakers: &[Speaker]) -> Result<Vec<SpeakerTuple>> {
+fn choose_speaker_triplets(
+    speakers: &[Speaker],
+    tolerances: &Tolerances,
+    report: Option<&mut TriangulationReport>,
+) -> Result<Vec<SpeakerTuple>> {
+    choose_speaker_triplets_with_edges(speakers, &[], tolerances, report)
+}
+
+/// Choose speaker triplets, keeping the `fixed` connections.
+///
+/// Fixed connections are kept before all others, so any connection crossing
+/// one is dropped regardless of length.
+fn choose_speaker_triplets_with_edges(
+    speakers: &[Speaker],
+    fixed: &[(usize, usize)],
+    tolerances: &Tolerances,
+    mut report: Option<&mut TriangulationReport>,
+) -> Result<Vec<SpeakerTuple>> {
     let n = speakers.len();
     if n < 3 {
         return Err(VBAPError::InsufficientSpeakers {
@@ -317,141 +2357,437 @@ fn choose_speaker_triplets(speakers: &[Speaker]) -> Result<Vec<SpeakerTuple>> {
         });
     }
 
-    // Connection matrix: connections[i*n + j] = true if speakers i and j are connected
-    let mut connections = vec![true; n * n];
+    // Build table of all speaker pairs, sorted by length (shortest first).
+    // Equal lengths are ordered by speaker index, so the result does not
+    // depend on sort internals or rounding.
+    let mut pending: Vec<Connection> = (0..n)
+        .flat_map(|i| ((i + 1)..n).map(move |j| Connection::new(speakers, i, j)))
+        .collect();
+    for connection in &mut pending {
+        connection.fixed = fixed.contains(&(connection.a, connection.b));
+    }
+    pending.sort_unstable_by_key(|c| (!c.fixed, c.rank, c.a, c.b));
+
+    // Remove crossing connections (later lines that cross earlier ones).
+    // The first pending connection can no longer be removed, so it is kept
+    // and every later pending connection crossing it is dropped. Dropping
+    // them as we go keeps the scan short for large layouts.
+    let mut connections = vec![false; n * n];
+    let mut next = 0;
+    while next < pending.len() {
+        let ab = pending[next];
+        next += 1;
+        connections[ab.a * n + ab.b] = true;
+        connections[ab.b * n + ab.a] = true;
+
+        let mut write = next;
+        for read in next..pending.len() {
+            let cd = pending[read];
+            let crosses = !cd.fixed
+                && ab.may_cross(&cd)
+                && !ab.shares_speaker(&cd)
+                && arcs_intersect(ab.start, ab.end, cd.start, cd.end, tolerances.arc);
+            if !crosses {
+                pending[write] = cd;
+                write += 1;
+            } else if let Some(report) = report.as_deref_mut() {
+                report.removed_connections.push(RemovedConnection {
+                    speakers: [cd.a, cd.b],
+                    crossed: [ab.a, ab.b],
+                });
+            }
+        }
+        pending.truncate(write);
+    }
+
+    // Remaining connections of each speaker to higher-indexed speakers, sorted
+    let neighbors: Vec<Vec<usize>> = (0..n)
+        .map(|i| ((i + 1)..n).filter(|&j| connections[i * n + j]).collect())
+        .collect();
 
-    // First pass: find all potentially valid triplets
-    let mut candidates: Vec<(usize, usize, usize, f64)> = Vec::new();
+    // Form triplets from connected triangles
+    let mut tuples = Vec::new();
 
     for i in 0..n {
-        for j in (i + 1)..n {
-            for k in (j + 1)..n {
+        for (pos, &j) in neighbors[i].iter().enumerate() {
+            for &k in &neighbors[i][pos + 1..] {
+                if !connections[j * n + k] {
+                    continue;
+                }
+
+                let indices = [i, j, k];
+                let mut reject = |reason| {
+                    if let Some(report) = report.as_deref_mut() {
+                        report.rejected.push(RejectedTuple {
+                            speakers: indices.to_vec(),
+                            reason,
+                        });
+                    }
+                };
+
                 let v1 = speakers[i].cartesian();
                 let v2 = speakers[j].cartesian();
                 let v3 = speakers[k].cartesian();
 
                 // Calculate volume-to-perimeter ratio (filters degenerate triplets)
-                let cross = v1.cross(v2);
-                let vol = cross.dot(v3).abs();
+                let vol = v1.cross(v2).dot(v3).abs();
                 let side_sum = v1.angle_between(v2) + v1.angle_between(v3) + v2.angle_between(v3);
-
-                if side_sum < 1e-10 {
+                if side_sum < 1e-10 || vol / side_sum <= MIN_VOL_P_SIDE_LGTH {
+                    reject(Rejection::TooThin);
                     continue;
                 }
 
-                let vol_p_side = vol / side_sum;
+                // Check if any other speaker is "inside" this triplet
+                let interior_speaker = speakers.iter().enumerate().position(|(m, speaker)| {
+                    m != i
+                        && m != j
+                        && m != k
+                        && is_inside_triangle(speaker.cartesian(), v1, v2, v3)
+                });
 
-                if vol_p_side > MIN_VOL_P_SIDE_LGTH {
-                    candidates.push((i, j, k, vol_p_side));
+                if let Some(m) = interior_speaker {
+                    reject(Rejection::InteriorSpeaker(m));
+                    continue;
                 }
+
+                let Some(inverse_matrix) = compute_inverse_matrix(speakers, &indices, tolerances)
+                else {
+                    reject(Rejection::Singular);
+                    continue;
+                };
+
+                tuples.push(SpeakerTuple::new(&indices, inverse_matrix));
             }
         }
     }
 
-    // Build distance table for all speaker pairs, sorted by distance (shortest first)
-    let mut distances: Vec<(usize, usize, f64)> = (0..n)
-        .flat_map(|i| {
-            ((i + 1)..n).map(move |j| {
-                let dist = speakers[i]
-                    .cartesian()
-                    .angle_between(speakers[j].cartesian());
-                (i, j, dist)
-            })
-        })
-        .collect();
-    distances.sort_by(|a, b| a.2.total_cmp(&b.2));
+    Ok(tuples)
+}
 
-    // Remove crossing connections (longer lines that cross shorter ones)
-    for (a, b, _) in &distances {
-        let va = speakers[*a].cartesian();
-        let vb = speakers[*b].cartesian();
+/// Great-circle connection between two speakers.
+#[derive(Clone, Copy)]
+struct Connection {
+    a: usize,
+    b: usize,
+    start: DVec3,
+    end: DVec3,
+    /// Arc length in units of [`LENGTH_RESOLUTION`].
+    rank: u64,
+    /// Arc midpoint, zero for antipodal speakers.
+    midpoint: DVec3,
+    /// Cosine and sine of half the arc length.
+    half_cos: f64,
+    half_sin: f64,
+    /// Kept no matter which connections cross it.
+    fixed: bool,
+}
 
-        // Check all other connections
-        for (c, d, _) in &distances {
-            if a == c || a == d || b == c || b == d {
-                continue;
-            }
+impl Connection {
+    fn new(speakers: &[Speaker], a: usize, b: usize) -> Self {
+        let start = speakers[a].cartesian();
+        let end = speakers[b].cartesian();
+        let length = start.angle_between(end);
+        Self {
+            a,
+            b,
+            start,
+            end,
+            rank: (length / LENGTH_RESOLUTION).round() as u64,
+            midpoint: (start + end).normalize_or_zero(),
+            half_cos: (0.5 * length).cos(),
+            half_sin: (0.5 * length).sin(),
+            fixed: false,
+        }
+    }
 
-            if !connections[*c * n + *d] {
-                continue;
-            }
+    /// Check whether two connections share a speaker.
+    fn shares_speaker(&self, other: &Connection) -> bool {
+        self.a == other.a || self.a == other.b || self.b == other.a || self.b == other.b
+    }
 
-            let vc = speakers[*c].cartesian();
-            let vd = speakers[*d].cartesian();
+    /// Cheap bounding-cap test: arcs can only cross if the caps around their
+    /// midpoints overlap, i.e. the midpoints are at most the sum of the half
+    /// lengths apart.
+    fn may_cross(&self, other: &Connection) -> bool {
+        let reach_cos = self.half_cos * other.half_cos - self.half_sin * other.half_sin;
+        self.midpoint.dot(other.midpoint) >= reach_cos - 1e-9
+    }
+}
 
-            if lines_intersect(va, vb, vc, vd) {
-                // Remove the longer connection
-                let dist_ab = va.angle_between(vb);
-                let dist_cd = vc.angle_between(vd);
+/// Number of speakers two triplets have in common.
+fn shared_speakers(a: &SpeakerTuple, b: &SpeakerTuple) -> usize {
+    a.indices.iter().filter(|i| b.indices.contains(i)).count()
+}
 
-                if dist_cd > dist_ab {
-                    connections[*c * n + *d] = false;
-                    connections[*d * n + *c] = false;
-                }
-            }
-        }
-    }
+/// Replace the triplets in `hole` by a triangulation of the `local` speakers.
+///
+/// Only new triplets lying inside the hole are kept. Returns `None` unless
+/// they tile the hole exactly: every boundary edge of the hole is used once
+/// and the solid angles add up.
+fn retriangulate_hole(
+    speakers: &[Speaker],
+    tuples: &[SpeakerTuple],
+    hole: &[usize],
+    mut local: Vec<usize>,
+    tolerances: &Tolerances,
+) -> Option<Vec<SpeakerTuple>> {
+    local.sort_unstable();
+    local.dedup();
+
+    let corners = |t: &SpeakerTuple| t.indices.map(|i| speakers[i].cartesian());
+    let area = |t: &SpeakerTuple| {
+        let [a, b, c] = corners(t);
+        solid_angle(a, b, c)
+    };
+    let inside_hole = |p: DVec3| {
+        hole.iter().any(|&i| {
+            let [a, b, c] = corners(&tuples[i]);
+            is_inside_triangle(p, a, b, c)
+        })
+    };
+
+    // Edges on the hole boundary belong to exactly one removed triplet
+    let edges = |t: &SpeakerTuple| {
+        let [a, b, c] = t.indices;
+        [(a, b), (b, c), (c, a)].map(|(x, y)| (x.min(y), x.max(y)))
+    };
+    let uses =
+        |list: &[SpeakerTuple], edge| list.iter().filter(|t| edges(t).contains(&edge)).count();
+    let removed: Vec<SpeakerTuple> = hole.iter().map(|&i| tuples[i]).collect();
+    let boundary: Vec<(usize, usize)> = removed
+        .iter()
+        .flat_map(edges)
+        .filter(|&edge| uses(&removed, edge) == 1)
+        .collect();
 
-    // Filter triplets based on remaining connections
-    let mut tuples = Vec::new();
+    // Keep the boundary so the patch cannot cut across it
+    let local_index = |i: usize| local.binary_search(&i).ok();
+    let fixed: Vec<(usize, usize)> = boundary
+        .iter()
+        .filter_map(|&(a, b)| Some((local_index(a)?, local_index(b)?)))
+        .collect();
 
-    for (i, j, k, _) in candidates {
-        // Check if all three sides are still connected
-        if !connections[i * n + j] || !connections[i * n + k] || !connections[j * n + k] {
-            continue;
-        }
+    let subset: Vec<Speaker> = local.iter().map(|&i| speakers[i].clone()).collect();
+    let patch: Vec<SpeakerTuple> =
+        choose_speaker_triplets_with_edges(&subset, &fixed, tolerances, None)
+            .ok()?
+            .into_iter()
+            .map(|mut t| {
+                t.indices = t.indices.map(|i| local[i]);
+                t
+            })
+            .filter(|t| {
+                let [a, b, c] = corners(t);
+                inside_hole((a + b + c).normalize())
+            })
+            .collect();
 
-        // Check if any other speaker is "inside" this triplet
-        let v1 = speakers[i].cartesian();
-        let v2 = speakers[j].cartesian();
-        let v3 = speakers[k].cartesian();
+    if boundary.iter().any(|&edge| uses(&patch, edge) != 1) {
+        return None;
+    }
 
-        let has_interior_speaker = speakers.iter().enumerate().any(|(m, speaker)| {
-            m != i && m != j && m != k && is_inside_triangle(speaker.cartesian(), v1, v2, v3)
-        });
+    let hole_area: f64 = removed.iter().map(area).sum();
+    let patch_area: f64 = patch.iter().map(area).sum();
+    if (hole_area - patch_area).abs() > 1e-9 * hole_area.max(1.0) {
+        return None;
+    }
 
-        if has_interior_speaker {
-            continue;
-        }
+    let mut result: Vec<SpeakerTuple> = tuples
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !hole.contains(i))
+        .map(|(_, t)| *t)
+        .collect();
+    result.extend(patch);
+    Some(result)
+}
+
+/// Compute the inverse gain matrix for a pair or triplet of speakers.
+///
+/// Pairs use the horizontal (sin/cos of azimuth) direction of each speaker,
+/// triplets use the full Cartesian unit vectors. Returns `None` if the
+/// speakers are (nearly) linearly dependent.
+fn compute_inverse_matrix(
+    speakers: &[Speaker],
+    indices: &[usize],
+    tolerances: &Tolerances,
+) -> Option<InverseMatrix> {
+    match *indices {
+        [a, b] => {
+            // Matrix columns are speaker direction vectors (sin/cos of azimuth)
+            let azi1_rad = speakers[a].azimuth().to_radians();
+            let azi2_rad = speakers[b].azimuth().to_radians();
+
+            let mat = DMat2::from_cols(
+                DVec2::new(azi1_rad.sin(), azi1_rad.cos()),
+                DVec2::new(azi2_rad.sin(), azi2_rad.cos()),
+            );
 
-        // Compute 3x3 inverse matrix using glam
-        // Matrix columns are the speaker direction vectors
-        let mat = DMat3::from_cols(v1, v2, v3);
+            if mat.determinant().abs() < tolerances.determinant {
+                return None;
+            }
 
-        if mat.determinant().abs() < 1e-10 {
-            continue;
+            Some(InverseMatrix::TwoD(mat.inverse()))
         }
+        [a, b, c] => {
+            // Matrix columns are the speaker direction vectors
+            let mat = DMat3::from_cols(
+                speakers[a].cartesian(),
+                speakers[b].cartesian(),
+                speakers[c].cartesian(),
+            );
 
-        tuples.push(SpeakerTuple {
-            speaker_indices: vec![i, j, k],
-            inverse_matrix: InverseMatrix::ThreeD(mat.inverse()),
-        });
-    }
+            if mat.determinant().abs() < tolerances.determinant {
+                return None;
+            }
 
-    Ok(tuples)
+            Some(InverseMatrix::ThreeD(mat.inverse()))
+        }
+        _ => None,
+    }
 }
 
 /// Check if point p is inside the spherical triangle defined by v1, v2, v3.
 fn is_inside_triangle(p: DVec3, v1: DVec3, v2: DVec3, v3: DVec3) -> bool {
-    // Use barycentric-like approach on the sphere
-    // Point is inside if it's on the same side of all three edges
-
-    let n1 = v1.cross(v2);
-    let n2 = v2.cross(v3);
-    let n3 = v3.cross(v1);
+    // Point is inside if it's on the inner side of all three edges. The
+    // sides are taken relative to the triangle's winding so the antipodal
+    // triangle does not count as inside.
+    let orientation = v1.dot(v2.cross(v3)).signum();
 
-    let d1 = p.dot(n1);
-    let d2 = p.dot(n2);
-    let d3 = p.dot(n3);
+    let d1 = p.dot(v1.cross(v2)) * orientation;
+    let d2 = p.dot(v2.cross(v3)) * orientation;
+    let d3 = p.dot(v3.cross(v1)) * orientation;
 
-    // All same sign means inside (or on edge)
-    (d1 >= 0.0 && d2 >= 0.0 && d3 >= 0.0) || (d1 <= 0.0 && d2 <= 0.0 && d3 <= 0.0)
+    // Points on an edge count as inside
+    d1 >= -1e-9 && d2 >= -1e-9 && d3 >= -1e-9
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_speaker_limit() {
+        let positions: Vec<(f64, f64)> = (0..=MAX_SPEAKERS)
+            .map(|i| (i as f64, (i % 90) as f64))
+            .collect();
+        let err = SpeakerConfigBuilder::new()
+            .add_speakers(&positions)
+            .build_config()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            VBAPError::TooManySpeakers {
+                provided: MAX_SPEAKERS + 1,
+                max: MAX_SPEAKERS
+            }
+        );
+    }
+
+    #[test]
+    fn test_large_sphere() {
+        // Fibonacci sphere: evenly spread, no four speakers on a circle
+        let n = MAX_SPEAKERS;
+        let golden_angle = 180.0 * (3.0 - 5f64.sqrt());
+        let positions: Vec<(f64, f64)> = (0..n)
+            .map(|i| {
+                let z = 1.0 - (2 * i + 1) as f64 / n as f64;
+                (i as f64 * golden_angle, z.asin().to_degrees())
+            })
+            .collect();
+        let config = SpeakerConfigBuilder::new()
+            .add_speakers(&positions)
+            .build_config()
+            .unwrap();
+
+        let tuples = config.tuples();
+        assert!(tuples.len() > n && tuples.len() <= 2 * n - 4);
+        assert!(tuples.heap_size() <= (2 * n - 4) * 78);
+
+        let panner = VBAPanner::new(config);
+        for i in 0..100 {
+            let gains = panner.compute_gains(i as f64 * 37.0, (i % 19) as f64 * 10.0 - 90.0);
+            let power: f64 = gains.iter().map(|g| g * g).sum();
+            assert!((power - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_incremental_updates() {
+        use crate::random_layout::{LayoutShape, RandomLayout};
+
+        let config = RandomLayout::new(60, LayoutShape::Sphere)
+            .with_min_separation(10.0)
+            .with_seed(5)
+            .build_config()
+            .unwrap();
+        let n = config.num_speakers();
+        let same_tuples = |a: &SpeakerConfig, b: &SpeakerConfig, shift: &dyn Fn(usize) -> usize| {
+            let old: Vec<Vec<usize>> = a
+                .tuples()
+                .iter()
+                .map(|t| t.speaker_indices().iter().map(|&i| shift(i)).collect())
+                .collect();
+            b.tuples()
+                .iter()
+                .filter(|t| old.contains(&t.speaker_indices().to_vec()))
+                .count()
+        };
+        let assert_covers = |config: &SpeakerConfig| {
+            let panner = VBAPanner::new(config.clone());
+            for i in 0..200 {
+                let gains = panner.compute_gains(i as f64 * 37.0, (i % 37) as f64 * 5.0 - 90.0);
+                let power: f64 = gains.iter().map(|g| g * g).sum();
+                assert!((power - 1.0).abs() < 1e-9);
+            }
+        };
+
+        // A gap between speakers, so the new one lands inside a triplet
+        let added = config.with_speaker_added(12.0, 34.0).unwrap();
+        assert_eq!(added.num_speakers(), n + 1);
+        assert_eq!(added.tuples().len(), 2 * (n + 1) - 4);
+        assert!(same_tuples(&config, &added, &|i| i) >= config.tuples().len() - 12);
+        assert_covers(&added);
+
+        let removed = config.with_speaker_removed(7).unwrap();
+        assert_eq!(removed.num_speakers(), n - 1);
+        assert_eq!(removed.speakers()[7].id(), 7);
+        assert_eq!(removed.tuples().len(), 2 * (n - 1) - 4);
+        let shift = |i: usize| if i > 7 { i - 1 } else { i };
+        assert!(same_tuples(&config, &removed, &shift) >= config.tuples().len() - 12);
+        assert_covers(&removed);
+
+        assert!(config.with_speaker_removed(n).is_err());
+
+        // 2D layouts are rebuilt
+        let ring = SpeakerConfigBuilder::new()
+            .octagon()
+            .build_config()
+            .unwrap();
+        assert_eq!(ring.with_speaker_removed(0).unwrap().tuples().len(), 7);
+        assert_eq!(
+            ring.with_speaker_added(10.0, 0.0).unwrap().tuples().len(),
+            9
+        );
+    }
+
+    #[test]
+    fn test_octahedron_fully_triangulated() {
+        // Speakers opposite a triplet are not inside it
+        let config = SpeakerConfigBuilder::new()
+            .add_speakers(&[
+                (0.0, 0.0),
+                (90.0, 0.0),
+                (180.0, 0.0),
+                (-90.0, 0.0),
+                (0.0, 90.0),
+                (0.0, -90.0),
+            ])
+            .build_config()
+            .unwrap();
+        assert_eq!(config.tuples().len(), 8);
+    }
+
     #[test]
     fn test_build_stereo() {
         let config = SpeakerConfigBuilder::new().stereo().build_config().unwrap();
@@ -483,6 +2819,28 @@ mod tests {
         assert_eq!(config.mode(), PanningMode::ThreeD); // Auto-detected from elevation
     }
 
+    #[test]
+    fn test_preset_by_name() {
+        for name in PRESET_NAMES {
+            let config = SpeakerConfigBuilder::new()
+                .preset(name)
+                .unwrap()
+                .build_config()
+                .unwrap();
+            assert!(config.num_speakers() >= 2, "{name}");
+        }
+        let config = SpeakerConfigBuilder::new()
+            .preset(" Hexagon ")
+            .unwrap()
+            .build_config()
+            .unwrap();
+        assert_eq!(config.num_speakers(), 6);
+        assert!(matches!(
+            SpeakerConfigBuilder::new().preset("9.1.6"),
+            Err(VBAPError::InvalidConfiguration(_))
+        ));
+    }
+
     #[test]
     fn test_force_2d() {
         let config = SpeakerConfigBuilder::new()
@@ -517,4 +2875,600 @@ mod tests {
 
         assert_eq!(config.num_speakers(), 3);
     }
+
+    #[test]
+    fn test_open_arc_drops_wrap_pair() {
+        let closed = SpeakerConfigBuilder::new().lcr().build_config().unwrap();
+        let open = SpeakerConfigBuilder::new()
+            .lcr()
+            .open_arc()
+            .build_config()
+            .unwrap();
+
+        assert_eq!(closed.tuples().len(), 3);
+        assert_eq!(open.tuples().len(), 2);
+        assert_eq!(closed.arc_ends(), None);
+        // Ends are R (-30°) and L (30°)
+        assert_eq!(open.arc_ends(), Some([2, 0]));
+    }
+
+    #[test]
+    fn test_open_arc_across_rear() {
+        // Rear arc spanning ±180°: the gap is at the front
+        let config = SpeakerConfigBuilder::new()
+            .add_speakers(&[(150.0, 0.0), (180.0, 0.0), (-150.0, 0.0)])
+            .open_arc()
+            .build_config()
+            .unwrap();
+
+        assert_eq!(config.tuples().len(), 2);
+        assert_eq!(config.arc_ends(), Some([0, 2]));
+    }
+
+    #[test]
+    fn test_custom_tolerances() {
+        let strict = Tolerances {
+            determinant: 1e-14,
+            arc: 1e-9,
+            normalization_floor: 1e-8,
+            coincidence: 1e-3,
+        };
+        let config = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .tolerances(strict)
+            .build_config()
+            .unwrap();
+        assert_eq!(*config.tolerances(), strict);
+        // Derived layouts keep them
+        assert_eq!(*config.without_speakers(&[0]).unwrap().tolerances(), strict);
+        assert_eq!(
+            *config.moved_speaker(0, 35.0, 0.0).unwrap().tolerances(),
+            strict
+        );
+
+        // A determinant threshold above every stereo pair rejects the layout
+        let result = SpeakerConfigBuilder::new()
+            .stereo()
+            .tolerances(Tolerances {
+                determinant: 1.0,
+                ..Tolerances::default()
+            })
+            .build_config();
+        assert!(matches!(result, Err(VBAPError::InvalidConfiguration(_))));
+
+        let result = SpeakerConfigBuilder::new()
+            .stereo()
+            .tolerances(Tolerances {
+                arc: f64::NAN,
+                ..Tolerances::default()
+            })
+            .build_config();
+        assert!(matches!(
+            result,
+            Err(VBAPError::InvalidParameter {
+                parameter: "arc",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_transforms() {
+        // Panning a transformed source on the transformed layout gives the
+        // original gains
+        let check =
+            |config: &SpeakerConfig, transformed: SpeakerConfig, direction: &dyn Fn(f64) -> f64| {
+                let original = VBAPanner::new(config.clone());
+                let transformed = VBAPanner::new(transformed);
+                for i in 0..72 {
+                    // Off the grid, so no source sits exactly on a tie
+                    let (azi, ele) = (i as f64 * 5.0 - 177.5, (i % 4) as f64 * 20.0 + 1.0);
+                    let expected = original.compute_gains(azi, ele);
+                    let gains = transformed.compute_gains(direction(azi), ele);
+                    for (a, b) in gains.iter().zip(&expected) {
+                        assert!((a - b).abs() < 1e-9, "{} {}: {} != {}", azi, ele, a, b);
+                    }
+                }
+            };
+
+        for builder in [
+            SpeakerConfigBuilder::new().surround_7_1(),
+            SpeakerConfigBuilder::new().atmos_7_1_4(),
+            SpeakerConfigBuilder::new().lcr().open_arc(),
+        ] {
+            let config = builder.build_config().unwrap();
+            check(&config, config.rotated(37.0), &|azi| azi + 37.0);
+            check(&config, config.rotated(-180.0), &|azi| azi - 180.0);
+            check(&config, config.mirrored_lr(), &|azi| -azi);
+            check(&config, config.mirrored_fb(), &|azi| 180.0 - azi);
+        }
+
+        let config = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .build_config()
+            .unwrap();
+        let mirrored = config.mirrored_lr();
+        assert_eq!(mirrored.speakers()[0].azimuth(), -30.0);
+        assert_eq!(mirrored.speakers()[1].azimuth(), 30.0);
+
+        let scaled = config.scaled(2.5).unwrap();
+        assert_eq!(scaled.speakers()[0].distance(), 2.5);
+        assert!(config.scaled(0.0).is_err());
+    }
+
+    #[test]
+    fn test_rotated_by() {
+        use crate::math::{euler_rotation, rotate_direction};
+
+        let config = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .build_config()
+            .unwrap();
+        let rotation = euler_rotation(25.0, -15.0, 5.0);
+        let original = VBAPanner::new(config.clone());
+        let rotated = VBAPanner::new(config.rotated_by(rotation).unwrap());
+        for i in 0..36 {
+            let (azi, ele) = (i as f64 * 10.0 - 177.5, (i % 3) as f64 * 25.0 + 1.0);
+            let (r_azi, r_ele) = rotate_direction(rotation, azi, ele);
+            let expected = original.compute_gains(azi, ele);
+            for (a, b) in rotated.compute_gains(r_azi, r_ele).iter().zip(&expected) {
+                assert!((a - b).abs() < 1e-9, "{} {}: {} != {}", azi, ele, a, b);
+            }
+        }
+
+        // 2D layouts turn but do not tilt
+        let ring = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .build_config()
+            .unwrap();
+        let turned = ring.rotated_by(euler_rotation(30.0, 0.0, 0.0)).unwrap();
+        assert!((turned.speakers()[0].azimuth() - 60.0).abs() < 1e-9);
+        assert!(ring.rotated_by(euler_rotation(0.0, 10.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn test_covers() {
+        let ring = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .build_config()
+            .unwrap();
+        assert!((-180..180).all(|azi| ring.covers(azi as f64, 0.0)));
+        // On a speaker and on the wrap-around pair
+        assert!(ring.covers(30.0, 0.0) && ring.covers(180.0, 0.0));
+
+        let dome = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .build_config()
+            .unwrap();
+        assert!(dome.covers(0.0, 0.0) && dome.covers(0.0, 90.0));
+        assert!(!dome.covers(0.0, -10.0));
+
+        // Removing the front speakers opens a gap in the ring
+        let gap = ring.without_speakers(&[0, 1, 2]).unwrap();
+        assert!(!gap.covers(0.0, 0.0));
+        assert!(gap.covers(180.0, 0.0));
+    }
+
+    #[test]
+    fn test_suggest_virtual_speakers() {
+        let dome = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .build_config()
+            .unwrap();
+        let closed = dome.with_suggested_virtual_speakers().unwrap();
+        assert_eq!(closed.num_speakers(), dome.num_speakers() + 1);
+        assert!(closed.speakers()[11].is_virtual());
+        assert_eq!(closed.speakers()[11].elevation(), -90.0);
+        assert!(fibonacci_sphere(500)
+            .into_iter()
+            .all(|d| closed.covers_direction(d)));
+        assert!(closed.suggest_virtual_speakers().is_empty());
+
+        // Virtual speakers survive rebuilds
+        let moved = closed.moved_speaker(0, 35.0, 0.0).unwrap();
+        assert!(moved.speakers()[11].is_virtual());
+
+        // A ring with a gap on the right
+        let ring = SpeakerConfigBuilder::new()
+            .add_speakers(&[(10.0, 0.0), (100.0, 0.0), (-170.0, 0.0)])
+            .build_config()
+            .unwrap();
+        assert!(!ring.covers(-80.0, 0.0));
+        let suggested = ring.suggest_virtual_speakers();
+        assert_eq!(suggested, vec![(-80.0, 0.0)]);
+
+        let arc = SpeakerConfigBuilder::new()
+            .lcr()
+            .open_arc()
+            .build_config()
+            .unwrap();
+        assert!(arc.suggest_virtual_speakers().is_empty());
+    }
+
+    #[test]
+    fn test_without_speakers() {
+        let config = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .build_config()
+            .unwrap();
+        let subset = config.without_speakers(&[2]).unwrap();
+
+        assert_eq!(subset.num_speakers(), 5);
+        assert!(subset
+            .tuples()
+            .iter()
+            .all(|t| !t.speaker_indices().contains(&2)));
+        assert!(matches!(
+            config.without_speakers(&[7]),
+            Err(VBAPError::InvalidSpeakerIndex { index: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn test_tuple_layout() {
+        let pairs = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .build_config()
+            .unwrap();
+        let triplets = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .build_config()
+            .unwrap();
+
+        for (config, len) in [(&pairs, 2), (&triplets, 3)] {
+            let tuples = config.tuples();
+            assert_eq!(tuples.iter().len(), tuples.len());
+            for (i, tuple) in tuples.iter().enumerate() {
+                assert_eq!(tuple.speaker_indices().len(), len);
+                assert!(tuple
+                    .speaker_indices()
+                    .iter()
+                    .copied()
+                    .eq(tuples.speaker_indices(i)));
+            }
+            assert!(tuples.get(tuples.len()).is_none());
+        }
+    }
+
+    #[test]
+    fn test_moved_speaker_preview_keeps_tuples() {
+        let config = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .build_config()
+            .unwrap();
+        let preview = config.moved_speaker_preview(7, 40.0, 50.0).unwrap();
+
+        assert_eq!(preview.tuples().len(), config.tuples().len());
+        assert_eq!(preview.speakers()[7].azimuth(), 40.0);
+        assert_eq!(preview.speakers()[7].elevation(), 50.0);
+    }
+
+    #[test]
+    fn test_moved_speaker_rebuilds() {
+        let config = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .build_config()
+            .unwrap();
+        let moved = config.moved_speaker(3, 100.0, 0.0).unwrap();
+
+        assert_eq!(moved.mode(), PanningMode::TwoD);
+        assert_eq!(moved.speakers()[3].azimuth(), 100.0);
+    }
+
+    #[test]
+    fn test_moved_speaker_invalid_index() {
+        let config = SpeakerConfigBuilder::new().stereo().build_config().unwrap();
+
+        assert!(matches!(
+            config.moved_speaker_preview(5, 0.0, 0.0),
+            Err(VBAPError::InvalidSpeakerIndex { index: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn test_report_2d_close_pair() {
+        let (config, report) = SpeakerConfigBuilder::new()
+            .add_speakers(&[(0.0, 0.0), (3.0, 0.0), (120.0, 0.0), (-120.0, 0.0)])
+            .build_config_with_report();
+        assert!(config.is_ok());
+        assert_eq!(
+            report.rejected,
+            [RejectedTuple {
+                speakers: vec![0, 1],
+                reason: Rejection::TooClose
+            }]
+        );
+        assert!(report.coincident.is_empty());
+        // Nothing pans between the two front speakers
+        assert_eq!(report.uncovered, [(1.0, 0.0), (2.0, 0.0)]);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_report_3d() {
+        let (config, report) = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .add_speaker(30.5, 0.0)
+            .build_config_with_report();
+        assert!(config.is_ok());
+        assert_eq!(report.coincident.len(), 1);
+        assert_eq!(report.coincident[0].speakers, [0, 11]);
+        assert!(!report.removed_connections.is_empty());
+        assert!(report.to_string().contains("only 0.50° apart"));
+
+        // A ring forced to 3D has no usable triplets, and the report says why
+        let (config, report) = SpeakerConfigBuilder::new()
+            .add_speakers(&[(0.0, 0.0), (120.0, 0.0), (-120.0, 0.0)])
+            .dimension(Dimension::Force3D)
+            .build_config_with_report();
+        assert!(config.is_err());
+        assert_eq!(report.rejected[0].reason, Rejection::TooThin);
+    }
+
+    #[test]
+    fn test_explicit_tuples() {
+        let positions = [(45.0, 0.0), (-45.0, 0.0), (0.0, 60.0), (180.0, 0.0)];
+        let config = SpeakerConfigBuilder::new()
+            .add_speakers(&positions)
+            .with_tuples(&[[0, 1, 2], [0, 2, 3], [1, 2, 3]])
+            .build_config()
+            .unwrap();
+        assert_eq!(config.mode(), PanningMode::ThreeD);
+        let panner = VBAPanner::new(config.clone());
+        let gains = panner.compute_gains(0.0, 20.0);
+        assert!(gains[0] > 0.0 && gains[1] > 0.0 && gains[2] > 0.0);
+        assert_eq!(gains[3], 0.0);
+
+        // Rebuilding and silencing keep the user's tuples
+        let moved = config.moved_speaker(0, 50.0, 0.0).unwrap();
+        assert_eq!(moved.tuples().len(), 3);
+        let silenced = config.without_speakers(&[3]).unwrap();
+        assert_eq!(silenced.tuples().len(), 1);
+        let removed = config.with_speaker_removed(0).unwrap();
+        let indices: Vec<Vec<usize>> = removed
+            .tuples()
+            .iter()
+            .map(|t| t.speaker_indices().to_vec())
+            .collect();
+        assert_eq!(indices, [[0, 1, 2]]);
+
+        let result = SpeakerConfigBuilder::new()
+            .add_speakers(&positions)
+            .with_tuples(&[[0, 1, 4]])
+            .build_config();
+        assert!(matches!(
+            result,
+            Err(VBAPError::InvalidSpeakerIndex { index: 4, .. })
+        ));
+        let result = SpeakerConfigBuilder::new()
+            .add_speakers(&positions)
+            .with_tuples(&[[0, 0, 2]])
+            .build_config();
+        assert!(matches!(result, Err(VBAPError::InvalidConfiguration(_))));
+    }
+
+    #[test]
+    fn test_explicit_pairs() {
+        // Without the rear pair nothing covers the back
+        let config = SpeakerConfigBuilder::new()
+            .quad()
+            .with_pairs(&[[0, 1], [1, 3], [0, 2]])
+            .build_config()
+            .unwrap();
+        assert_eq!(config.mode(), PanningMode::TwoD);
+        assert_eq!(config.tuples().len(), 3);
+        assert!(!config.covers(180.0, 0.0));
+    }
+
+    #[test]
+    fn test_merge_duplicate_speakers() {
+        let builder = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .add_speaker(30.5, 0.0)
+            .add_speaker(30.0, 0.2)
+            .duplicate_speakers(DuplicateSpeakers::Merge);
+        let (config, report) = builder.clone().build_config_with_report();
+        let config = config.unwrap();
+        assert_eq!(config.num_speakers(), 13);
+        assert_eq!(report.coincident.len(), 3);
+        // Only the first of the three is used
+        assert!(config
+            .tuples()
+            .iter()
+            .all(|t| !t.speaker_indices().contains(&11) && !t.speaker_indices().contains(&12)));
+        assert!(report
+            .rejected
+            .iter()
+            .all(|t| !t.speakers.contains(&11) && !t.speakers.contains(&12)));
+        // Rebuilding keeps the handling
+        let moved = config.moved_speaker(4, 5.0, 0.0).unwrap();
+        assert!(moved
+            .tuples()
+            .iter()
+            .all(|t| !t.speaker_indices().contains(&11)));
+
+        // A larger tolerance catches speakers a few degrees apart
+        let result = SpeakerConfigBuilder::new()
+            .add_speakers(&[(0.0, 0.0), (3.0, 0.0), (120.0, 0.0), (-120.0, 0.0)])
+            .tolerances(Tolerances {
+                coincidence: 5f64.to_radians(),
+                ..Tolerances::default()
+            })
+            .duplicate_speakers(DuplicateSpeakers::Error)
+            .build_config();
+        assert!(matches!(
+            result,
+            Err(VBAPError::CoincidentSpeakers {
+                first: 0,
+                second: 1,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_symmetric_layout_ties() {
+        // All edges of a cube are equally long, as are the crossing face
+        // diagonals; exactly one diagonal per face must survive
+        let elevation = (1.0f64 / 3.0).sqrt().asin().to_degrees();
+        let mut builder = SpeakerConfigBuilder::new();
+        for azimuth in [45.0, 135.0, -135.0, -45.0] {
+            builder = builder
+                .add_speaker(azimuth, elevation)
+                .add_speaker(azimuth, -elevation);
+        }
+        let config = builder.clone().build_config().unwrap();
+        assert_eq!(config.tuples().len(), 12);
+
+        let again = builder.build_config().unwrap();
+        let indices = |config: &SpeakerConfig| -> Vec<Vec<usize>> {
+            config
+                .tuples()
+                .iter()
+                .map(|t| t.speaker_indices().to_vec())
+                .collect()
+        };
+        assert_eq!(indices(&config), indices(&again));
+        assert_eq!(config.fingerprint(), again.fingerprint());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let config = SpeakerConfigBuilder::new().stereo().build_config().unwrap();
+        // Pinned so that changes to the hash or the triangulation are noticed
+        assert_eq!(config.fingerprint(), 0xd97f_bb92_fc94_0a6e);
+
+        let zero = SpeakerConfigBuilder::new()
+            .add_speaker(0.0, 0.0)
+            .add_speaker(90.0, 0.0)
+            .add_speaker(-90.0, 0.0)
+            .build_config()
+            .unwrap();
+        let negative_zero = SpeakerConfigBuilder::new()
+            .add_speaker(-0.0, 0.0)
+            .add_speaker(90.0, -0.0)
+            .add_speaker(-90.0, 0.0)
+            .build_config()
+            .unwrap();
+        assert_eq!(zero.fingerprint(), negative_zero.fingerprint());
+
+        let max = zero
+            .to_builder()
+            .convention(Convention::Max)
+            .build_config()
+            .unwrap();
+        assert_ne!(zero.fingerprint(), max.fingerprint());
+        let open = zero.to_builder().open_arc().build_config().unwrap();
+        assert_ne!(zero.fingerprint(), open.fingerprint());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let config = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .convention(Convention::Max)
+            .build_config()
+            .unwrap()
+            .with_suggested_virtual_speakers()
+            .unwrap()
+            .scaled(2.0)
+            .unwrap();
+        let restored = SpeakerConfig::from_bytes(&config.to_bytes()).unwrap();
+        assert_eq!(restored.fingerprint(), config.fingerprint());
+        assert_ne!(restored.revision(), config.revision());
+        for (a, b) in restored.speakers().iter().zip(config.speakers()) {
+            assert_eq!(a.is_virtual(), b.is_virtual());
+            assert_eq!(a.distance(), b.distance());
+        }
+        let panner = VBAPanner::new(config);
+        let restored = VBAPanner::new(restored);
+        for (azimuth, elevation) in [(0.0, 0.0), (70.0, 20.0), (-150.0, 45.0)] {
+            assert_eq!(
+                restored.compute_gains(azimuth, elevation),
+                panner.compute_gains(azimuth, elevation)
+            );
+        }
+
+        let arc = SpeakerConfigBuilder::new()
+            .lcr()
+            .open_arc()
+            .build_config()
+            .unwrap();
+        let restored = SpeakerConfig::from_bytes(&arc.to_bytes()).unwrap();
+        assert_eq!(restored.arc_ends(), arc.arc_ends());
+        assert_eq!(restored.mode(), PanningMode::TwoD);
+    }
+
+    #[test]
+    fn test_bytes_rejects_corruption() {
+        let bytes = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .build_config()
+            .unwrap()
+            .to_bytes();
+        let parse =
+            |bytes: &[u8]| matches!(SpeakerConfig::from_bytes(bytes), Err(VBAPError::Parse(_)));
+
+        assert!(parse(&bytes[..bytes.len() - 1]));
+        assert!(parse(&[bytes.as_slice(), &[0]].concat()));
+        let mut version = bytes.clone();
+        version[8..12].copy_from_slice(&(BYTES_VERSION + 1).to_le_bytes());
+        assert!(parse(&version));
+        // Blobs of the previous format, checksummed by fingerprint
+        version[8..12].copy_from_slice(&1u32.to_le_bytes());
+        assert!(parse(&version));
+        // A speaker azimuth
+        let mut moved = bytes.clone();
+        moved[12 + 7] ^= 0x01;
+        assert!(parse(&moved));
+        // An inverse matrix entry, which the layout fingerprint leaves out:
+        // magic, version and count, five speakers, the mode, the tuple count
+        // and the first pair's indices come first
+        let matrix = 16 + 5 * 26 + 1 + 4 + 2 * 2;
+        let mut skewed = bytes.clone();
+        skewed[matrix + 3] ^= 0x01;
+        assert!(parse(&skewed));
+        // A NaN entry with a matching checksum
+        let mut nan = bytes.clone();
+        nan[matrix..matrix + 8].copy_from_slice(&f64::NAN.to_le_bytes());
+        let end = nan.len() - 8;
+        let sum = checksum(&nan[..end]);
+        nan[end..].copy_from_slice(&sum.to_le_bytes());
+        assert!(matches!(
+            SpeakerConfig::from_bytes(&nan),
+            Err(VBAPError::Parse(message)) if message.contains("non-finite")
+        ));
+        // The explicit flag
+        let mut flag = bytes.clone();
+        flag[end - 1] ^= 0x01;
+        assert!(parse(&flag));
+        // A huge speaker count must not allocate
+        let mut count = bytes;
+        count[12..16].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse(&count));
+    }
+
+    #[test]
+    fn test_layout_hash() {
+        let builder = SpeakerConfigBuilder::new().surround_5_1();
+        assert_eq!(
+            builder.layout_hash(),
+            SpeakerConfigBuilder::new().surround_5_1().layout_hash()
+        );
+        assert_ne!(
+            builder.layout_hash(),
+            builder.clone().open_arc().layout_hash()
+        );
+        assert_ne!(
+            builder.layout_hash(),
+            builder.clone().add_speaker(90.0, 0.0).layout_hash()
+        );
+        assert_ne!(
+            builder.layout_hash(),
+            builder
+                .clone()
+                .duplicate_speakers(DuplicateSpeakers::Merge)
+                .layout_hash()
+        );
+    }
 }
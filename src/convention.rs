@@ -0,0 +1,125 @@
+//! Angle conventions of other spatial audio tools.
+//!
+//! Every ecosystem picks its own sign and zero for azimuth, so a layout
+//! copied verbatim from another tool can come out mirrored. [`Convention`]
+//! converts between those conventions and the one used throughout this
+//! crate (azimuth 0° = front, counter-clockwise seen from above, so 90° =
+//! left; elevation 90° = up).
+//!
+//! Set it on the builder with
+//! [`SpeakerConfigBuilder::convention`](crate::SpeakerConfigBuilder::convention)
+//! to enter speakers and query sources in the foreign convention.
+
+use crate::math::wrap_azimuth;
+
+/// Azimuth convention for speaker positions and source directions.
+///
+/// All conventions measure elevation from the horizontal plane, positive
+/// upwards; they differ in the azimuth's direction and range.
+///
+/// # Example
+///
+/// ```
+/// use vbap::convention::Convention;
+///
+/// // 30° to the right in Max/MSP is -30° here
+/// assert_eq!(Convention::Max.to_native(30.0, 0.0), (-30.0, 0.0));
+/// assert_eq!(Convention::Navigational.from_native(-30.0, 10.0), (30.0, 10.0));
+/// assert_eq!(Convention::Navigational.from_native(30.0, 0.0), (330.0, 0.0));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Convention {
+    /// Counter-clockwise azimuth in `(-180, 180]`, 0° = front, 90° = left.
+    ///
+    /// Used by ambiX, the ISO spherical coordinate system and this crate.
+    #[default]
+    AmbiX,
+    /// Clockwise azimuth in `(-180, 180]`, 0° = front, 90° = right, as in
+    /// the Max/MSP and Pd `vbap` objects.
+    Max,
+    /// Clockwise compass bearing in `[0, 360)`, 0° = front, 90° = right.
+    Navigational,
+}
+
+impl Convention {
+    /// Convert a direction in this convention to the crate's convention.
+    ///
+    /// Angles are in degrees. [`AmbiX`](Self::AmbiX) passes them through
+    /// unchanged; the others return an azimuth in `(-180, 180]`.
+    #[inline]
+    pub fn to_native(self, azimuth: f64, elevation: f64) -> (f64, f64) {
+        match self {
+            Convention::AmbiX => (azimuth, elevation),
+            Convention::Max | Convention::Navigational => (wrap_azimuth(-azimuth), elevation),
+        }
+    }
+
+    /// Convert a direction in the crate's convention to this convention.
+    ///
+    /// Angles are in degrees. The azimuth is wrapped into this convention's
+    /// range.
+    #[inline]
+    pub fn from_native(self, azimuth: f64, elevation: f64) -> (f64, f64) {
+        match self {
+            Convention::AmbiX => (wrap_azimuth(azimuth), elevation),
+            Convention::Max => (wrap_azimuth(-azimuth), elevation),
+            Convention::Navigational => (wrap_bearing(-azimuth), elevation),
+        }
+    }
+
+    /// Convert a direction from this convention to `target`.
+    #[inline]
+    pub fn convert_to(self, target: Convention, azimuth: f64, elevation: f64) -> (f64, f64) {
+        let (azimuth, elevation) = self.to_native(azimuth, elevation);
+        target.from_native(azimuth, elevation)
+    }
+}
+
+/// Wrap an azimuth in degrees into the range `[0, 360)`.
+#[inline]
+fn wrap_bearing(azimuth: f64) -> f64 {
+    let wrapped = azimuth.rem_euclid(360.0);
+    // rem_euclid rounds tiny negative angles up to 360 and keeps -0
+    if wrapped >= 360.0 || wrapped == 0.0 {
+        0.0
+    } else {
+        wrapped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    const ALL: [Convention; 3] = [Convention::AmbiX, Convention::Max, Convention::Navigational];
+
+    #[test]
+    fn test_round_trip() {
+        for convention in ALL {
+            for azi in (-180..=180).step_by(15) {
+                let (native_azi, native_ele) = convention.to_native(azi as f64, 20.0);
+                let (back_azi, back_ele) = convention.from_native(native_azi, native_ele);
+                assert_relative_eq!(back_ele, 20.0);
+                assert_relative_eq!(wrap_azimuth(back_azi), wrap_azimuth(azi as f64));
+            }
+        }
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(Convention::Max.to_native(90.0, 0.0), (-90.0, 0.0));
+        assert_eq!(Convention::Navigational.to_native(270.0, 5.0), (90.0, 5.0));
+        assert_eq!(
+            Convention::Navigational.from_native(90.0, 0.0),
+            (270.0, 0.0)
+        );
+        assert_eq!(Convention::Navigational.from_native(-0.0, 0.0), (0.0, 0.0));
+        assert_eq!(Convention::Navigational.from_native(1e-20, 0.0), (0.0, 0.0));
+        assert_eq!(Convention::AmbiX.from_native(-180.0, 0.0), (180.0, 0.0));
+        assert_eq!(
+            Convention::Max.convert_to(Convention::Navigational, -30.0, 0.0),
+            (330.0, 0.0)
+        );
+    }
+}
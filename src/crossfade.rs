@@ -0,0 +1,278 @@
+//! Glitch-free switching between two layouts.
+//!
+//! When a venue reconfigures mid-show, or a layout is recalibrated,
+//! swapping panners makes every source jump at once. [`ConfigCrossfade`]
+//! computes gains on both the old and the new layout and fades from one to
+//! the other over a set time instead.
+
+use std::f64::consts::FRAC_PI_2;
+
+use crate::error::{Result, VBAPError};
+use crate::panner::VBAPanner;
+
+/// How the gains of the two layouts are weighted during a crossfade.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CrossfadeLaw {
+    /// Weights `cos` and `sin` of the progress, keeping the power constant
+    /// when the two layouts drive different speakers.
+    #[default]
+    EqualPower,
+    /// Weights `1 - t` and `t`, keeping the amplitude constant when the two
+    /// layouts drive mostly the same speakers, as with two calibrations of
+    /// one layout.
+    Linear,
+}
+
+/// Crossfade from the gains of one panner to those of another.
+///
+/// Output channels are shared by index: speaker `i` of both layouts feeds
+/// channel `i`, and a layout with fewer speakers leaves the remaining
+/// channels silent. Gains are computed without allocating into buffers the
+/// caller provides, so [`compute_gains_into`](Self::compute_gains_into)
+/// can run on the audio thread while [`advance`](Self::advance) is called
+/// once per block.
+///
+/// # Example
+///
+/// ```
+/// use vbap::crossfade::ConfigCrossfade;
+/// use vbap::VBAPanner;
+///
+/// let from = VBAPanner::builder().surround_5_1().build().unwrap();
+/// let to = VBAPanner::builder().surround_7_1().build().unwrap();
+/// let mut fade = ConfigCrossfade::new(from, to, 2.0).unwrap();
+///
+/// let (mut gains, mut scratch) = ([0.0; 7], [0.0; 7]);
+/// while !fade.is_finished() {
+///     fade.compute_gains_into(120.0, 0.0, &mut gains, &mut scratch);
+///     fade.advance(512.0 / 48000.0);
+/// }
+/// let panner = fade.finish();
+/// assert_eq!(panner.num_speakers(), 7);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConfigCrossfade {
+    from: VBAPanner,
+    to: VBAPanner,
+    /// Fade length in seconds.
+    duration: f64,
+    /// Time since the start of the fade in seconds.
+    elapsed: f64,
+    law: CrossfadeLaw,
+}
+
+impl ConfigCrossfade {
+    /// Start a crossfade from `from` to `to` lasting `duration` seconds.
+    ///
+    /// A duration of 0 switches to `to` immediately.
+    pub fn new(from: VBAPanner, to: VBAPanner, duration: f64) -> Result<Self> {
+        if !(duration.is_finite() && duration >= 0.0) {
+            return Err(VBAPError::InvalidParameter {
+                parameter: "duration",
+                value: duration,
+                min: 0.0,
+                max: f64::INFINITY,
+            });
+        }
+        Ok(Self {
+            from,
+            to,
+            duration,
+            elapsed: 0.0,
+            law: CrossfadeLaw::default(),
+        })
+    }
+
+    /// Set the crossfade law (default [`CrossfadeLaw::EqualPower`]).
+    pub fn with_law(mut self, law: CrossfadeLaw) -> Self {
+        self.law = law;
+        self
+    }
+
+    /// Get the crossfade law.
+    #[inline]
+    pub fn law(&self) -> CrossfadeLaw {
+        self.law
+    }
+
+    /// Get the panner being faded out.
+    #[inline]
+    pub fn from(&self) -> &VBAPanner {
+        &self.from
+    }
+
+    /// Get the panner being faded in.
+    #[inline]
+    pub fn to(&self) -> &VBAPanner {
+        &self.to
+    }
+
+    /// Get the number of output channels, the larger of the two layouts.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.from.num_speakers().max(self.to.num_speakers())
+    }
+
+    /// Get the progress of the fade, from 0 (only `from`) to 1 (only `to`).
+    pub fn progress(&self) -> f64 {
+        if self.elapsed >= self.duration {
+            1.0
+        } else {
+            self.elapsed / self.duration
+        }
+    }
+
+    /// Check whether the fade has reached the new layout.
+    #[inline]
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    /// Move the fade forward by `seconds`.
+    pub fn advance(&mut self, seconds: f64) {
+        if seconds > 0.0 {
+            self.elapsed = (self.elapsed + seconds).min(self.duration);
+        }
+    }
+
+    /// End the fade and keep the new panner.
+    pub fn finish(self) -> VBAPanner {
+        self.to
+    }
+
+    /// Compute crossfaded gains for a source direction.
+    pub fn compute_gains(&self, azimuth: f64, elevation: f64) -> Vec<f64> {
+        let mut gains = vec![0.0; self.num_speakers()];
+        let mut scratch = vec![0.0; self.num_speakers()];
+        self.compute_gains_into(azimuth, elevation, &mut gains, &mut scratch);
+        gains
+    }
+
+    /// Compute crossfaded gains into a caller-provided buffer, without
+    /// allocating.
+    ///
+    /// `scratch` holds the new layout's gains while they are mixed in; its
+    /// contents are overwritten.
+    ///
+    /// # Panics
+    /// Panics if `gains.len()` or `scratch.len()` is less than
+    /// `self.num_speakers()`.
+    pub fn compute_gains_into(
+        &self,
+        azimuth: f64,
+        elevation: f64,
+        gains: &mut [f64],
+        scratch: &mut [f64],
+    ) {
+        let n = self.num_speakers();
+        assert!(
+            gains.len() >= n,
+            "gains slice too small: {} < {}",
+            gains.len(),
+            n
+        );
+        assert!(
+            scratch.len() >= n,
+            "scratch slice too small: {} < {}",
+            scratch.len(),
+            n
+        );
+        gains[..n].fill(0.0);
+
+        let t = self.progress();
+        let (from_weight, to_weight) = match self.law {
+            CrossfadeLaw::EqualPower => {
+                let (sin, cos) = (t * FRAC_PI_2).sin_cos();
+                (cos, sin)
+            }
+            CrossfadeLaw::Linear => (1.0 - t, t),
+        };
+
+        if from_weight > 0.0 {
+            self.from.compute_gains_into(azimuth, elevation, gains);
+            for gain in &mut gains[..n] {
+                *gain *= from_weight;
+            }
+        }
+        if to_weight > 0.0 {
+            scratch[..n].fill(0.0);
+            self.to.compute_gains_into(azimuth, elevation, scratch);
+            for (gain, &target) in gains[..n].iter_mut().zip(&scratch[..n]) {
+                *gain += to_weight * target;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn fade(duration: f64) -> ConfigCrossfade {
+        let from = VBAPanner::builder().stereo().build().unwrap();
+        let to = VBAPanner::builder()
+            .add_speaker(30.0, 0.0)
+            .add_speaker(-30.0, 0.0)
+            .add_speaker(0.0, 0.0)
+            .open_arc()
+            .build()
+            .unwrap();
+        ConfigCrossfade::new(from, to, duration).unwrap()
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut fade = fade(1.0);
+        assert_eq!(fade.num_speakers(), 3);
+        assert_eq!(fade.progress(), 0.0);
+        fade.advance(0.25);
+        assert_relative_eq!(fade.progress(), 0.25);
+        assert!(!fade.is_finished());
+        fade.advance(2.0);
+        assert_eq!(fade.progress(), 1.0);
+        assert!(fade.is_finished());
+
+        let instant = self::fade(0.0);
+        assert!(instant.is_finished());
+        assert_relative_eq!(instant.compute_gains(0.0, 0.0)[2], 1.0, epsilon = 1e-9);
+
+        assert!(ConfigCrossfade::new(fade.from().clone(), fade.to().clone(), -1.0).is_err());
+    }
+
+    #[test]
+    fn test_equal_power() {
+        let mut fade = fade(1.0);
+        let start = fade.compute_gains(0.0, 0.0);
+        assert_relative_eq!(start[0], start[1], epsilon = 1e-9);
+        assert_eq!(start[2], 0.0);
+
+        // Phantom centre fading into the real one keeps its power
+        fade.advance(0.5);
+        let middle = fade.compute_gains(0.0, 0.0);
+        let power: f64 = middle.iter().map(|g| g * g).sum();
+        assert_relative_eq!(power, 1.0, epsilon = 1e-9);
+
+        fade.advance(0.5);
+        let end = fade.compute_gains(0.0, 0.0);
+        assert_relative_eq!(end[2], 1.0, epsilon = 1e-9);
+        assert_eq!(fade.finish().num_speakers(), 3);
+    }
+
+    #[test]
+    fn test_linear_law() {
+        let from = VBAPanner::builder().stereo().build().unwrap();
+        let mut fade = ConfigCrossfade::new(from.clone(), from, 1.0)
+            .unwrap()
+            .with_law(CrossfadeLaw::Linear);
+        assert_eq!(fade.law(), CrossfadeLaw::Linear);
+
+        // Identical layouts come through unchanged at any point
+        fade.advance(0.3);
+        let gains = fade.compute_gains(20.0, 0.0);
+        let expected = fade.to().compute_gains(20.0, 0.0);
+        for (gain, expected) in gains.iter().zip(&expected) {
+            assert_relative_eq!(gain, expected, epsilon = 1e-9);
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Reports on how a layout was triangulated.
+//!
+//! [`SpeakerConfigBuilder::build_config_with_report`] returns a
+//! [`TriangulationReport`] next to the built configuration: the candidate
+//! pairs and triplets that were rejected and why, the connections dropped
+//! for crossing shorter ones, speakers so close together they confuse the
+//! triangulation, and directions no tuple covers.
+//!
+//! [`SpeakerConfigBuilder::build_config_with_report`]: crate::config::SpeakerConfigBuilder::build_config_with_report
+
+use std::fmt;
+
+/// Why a candidate pair or triplet was not used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// 2D pair less than 5° apart.
+    TooClose,
+    /// 2D pair more than 175° apart.
+    TooWide,
+    /// Triplet too thin (nearly on one great circle) to pan stably.
+    TooThin,
+    /// Another speaker, the given index, lies inside the triplet.
+    InteriorSpeaker(usize),
+    /// The speaker directions are linearly dependent.
+    Singular,
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::TooClose => write!(f, "speakers less than 5° apart"),
+            Rejection::TooWide => write!(f, "speakers more than 175° apart"),
+            Rejection::TooThin => write!(f, "too thin"),
+            Rejection::InteriorSpeaker(index) => write!(f, "speaker {index} lies inside"),
+            Rejection::Singular => write!(f, "singular direction matrix"),
+        }
+    }
+}
+
+/// A candidate pair or triplet that was rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RejectedTuple {
+    /// Speaker indices (2 for pairs, 3 for triplets).
+    pub speakers: Vec<usize>,
+    /// Why it was rejected.
+    pub reason: Rejection,
+}
+
+/// A 3D connection dropped because it crosses a shorter one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RemovedConnection {
+    /// Speakers of the dropped connection.
+    pub speakers: [usize; 2],
+    /// Speakers of the connection it crosses.
+    pub crossed: [usize; 2],
+}
+
+/// Two speakers closer together than the coincidence tolerance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoincidentSpeakers {
+    /// Speaker indices.
+    pub speakers: [usize; 2],
+    /// Angle between them in degrees.
+    pub angle: f64,
+}
+
+/// Diagnostics collected while building a layout.
+///
+/// # Example
+///
+/// ```
+/// use vbap::SpeakerConfigBuilder;
+///
+/// let (config, report) = SpeakerConfigBuilder::new()
+///     .atmos_7_1_4()
+///     .build_config_with_report();
+/// assert!(config.is_ok());
+/// // A dome leaves everything below the horizon uncovered
+/// assert!(report.uncovered.iter().all(|&(_, elevation)| elevation < 0.0));
+/// println!("{report}");
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TriangulationReport {
+    /// Candidate tuples that were rejected.
+    pub rejected: Vec<RejectedTuple>,
+    /// Connections dropped for crossing shorter ones.
+    pub removed_connections: Vec<RemovedConnection>,
+    /// Speakers closer together than the
+    /// [coincidence tolerance](crate::config::Tolerances::coincidence).
+    pub coincident: Vec<CoincidentSpeakers>,
+    /// Sampled directions, `(azimuth, elevation)` in degrees, that no tuple
+    /// covers. Empty if the build failed.
+    pub uncovered: Vec<(f64, f64)>,
+}
+
+impl TriangulationReport {
+    /// Check whether the report has no coincident speakers and no coverage
+    /// gaps.
+    ///
+    /// Rejected candidates and removed connections are a normal part of
+    /// triangulation and do not count.
+    pub fn is_clean(&self) -> bool {
+        self.coincident.is_empty() && self.uncovered.is_empty()
+    }
+}
+
+impl fmt::Display for TriangulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for pair in &self.coincident {
+            writeln!(
+                f,
+                "speakers {} and {} are only {:.2}° apart",
+                pair.speakers[0], pair.speakers[1], pair.angle
+            )?;
+        }
+        for tuple in &self.rejected {
+            writeln!(f, "rejected {:?}: {}", tuple.speakers, tuple.reason)?;
+        }
+        for connection in &self.removed_connections {
+            writeln!(
+                f,
+                "removed connection {:?}: crosses {:?}",
+                connection.speakers, connection.crossed
+            )?;
+        }
+        if !self.uncovered.is_empty() {
+            writeln!(
+                f,
+                "{} sampled directions are not covered",
+                self.uncovered.len()
+            )?;
+        }
+        Ok(())
+    }
+}
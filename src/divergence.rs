@@ -0,0 +1,115 @@
+//! Center-channel divergence.
+//!
+//! On film layouts a frontal source can either play from the physical
+//! center speaker or as a phantom image between left and right. Mixers
+//! control the balance with a divergence (center bias) parameter: at 0 the
+//! source pans with plain VBAP, so a source at 0° is fed only to the center
+//! speaker; at 1 the center is taken out of the panning and the left/right
+//! pair carries a phantom center.
+
+use crate::config::SpeakerConfig;
+use crate::error::{Result, VBAPError};
+
+/// Per-source center divergence.
+///
+/// The phantom layout (the layout re-triangulated without the center
+/// speaker) is built lazily on first use and cached until the panner's
+/// layout changes. Away from the front both layouts select the same tuples,
+/// so divergence only affects sources panned between the front speakers.
+///
+/// # Example
+///
+/// ```
+/// use vbap::{CenterDivergence, VBAPanner};
+///
+/// let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+/// let mut dialog = CenterDivergence::new(2).with_divergence(0.5).unwrap();
+/// let mut gains = vec![0.0; panner.num_speakers()];
+///
+/// panner
+///     .compute_gains_diverged(0.0, 0.0, &mut dialog, &mut gains)
+///     .unwrap();
+/// assert!(gains[0] > 0.0 && gains[1] > 0.0 && gains[2] > 0.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CenterDivergence {
+    center: usize,
+    divergence: f64,
+    /// Phantom layout and the revision of the layout it was built from.
+    cache: Option<(u64, SpeakerConfig)>,
+}
+
+impl CenterDivergence {
+    /// Create a divergence control for the center speaker at `center`.
+    ///
+    /// Divergence starts at 0 (plain VBAP).
+    pub fn new(center: usize) -> Self {
+        Self {
+            center,
+            divergence: 0.0,
+            cache: None,
+        }
+    }
+
+    /// Set the divergence, see [`set_divergence`](Self::set_divergence).
+    pub fn with_divergence(mut self, divergence: f64) -> Result<Self> {
+        self.set_divergence(divergence)?;
+        Ok(self)
+    }
+
+    /// Set the divergence: 0 feeds the physical center, 1 a phantom center.
+    ///
+    /// Returns an error if `divergence` is outside `[0, 1]`.
+    pub fn set_divergence(&mut self, divergence: f64) -> Result<()> {
+        if !(0.0..=1.0).contains(&divergence) {
+            return Err(VBAPError::InvalidParameter {
+                parameter: "divergence",
+                value: divergence,
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+        self.divergence = divergence;
+        Ok(())
+    }
+
+    /// Get the divergence.
+    #[inline]
+    pub fn divergence(&self) -> f64 {
+        self.divergence
+    }
+
+    /// Get the index of the center speaker.
+    #[inline]
+    pub fn center(&self) -> usize {
+        self.center
+    }
+
+    /// Get the phantom layout for `parent`, building it if needed.
+    pub(crate) fn phantom_config_for(&mut self, parent: &SpeakerConfig) -> Result<&SpeakerConfig> {
+        let stale = match &self.cache {
+            Some((revision, _)) => *revision != parent.revision(),
+            None => true,
+        };
+        if stale {
+            let phantom = parent.without_speakers(&[self.center])?;
+            self.cache = Some((parent.revision(), phantom));
+        }
+
+        Ok(self.cache.as_ref().map(|(_, config)| config).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_divergence_range() {
+        let mut divergence = CenterDivergence::new(2);
+        assert!(divergence.set_divergence(1.5).is_err());
+        assert!(divergence.set_divergence(f64::NAN).is_err());
+        divergence.set_divergence(0.25).unwrap();
+        assert_eq!(divergence.divergence(), 0.25);
+    }
+}
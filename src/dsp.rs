@@ -0,0 +1,504 @@
+//! Small DSP building blocks used by the processing modules.
+//!
+//! Filter coefficients are computed in `f64`; audio buffers are `f32`.
+//!
+//! Requires the `render` feature.
+
+use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+/// Normalized coefficients of a second-order IIR filter.
+///
+/// Designed with the formulas from Robert Bristow-Johnson's
+/// "Audio EQ Cookbook".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiquadCoefficients {
+    /// Feed-forward coefficient b0.
+    pub b0: f64,
+    /// Feed-forward coefficient b1.
+    pub b1: f64,
+    /// Feed-forward coefficient b2.
+    pub b2: f64,
+    /// Feedback coefficient a1 (a0 normalized to 1).
+    pub a1: f64,
+    /// Feedback coefficient a2 (a0 normalized to 1).
+    pub a2: f64,
+}
+
+impl BiquadCoefficients {
+    /// Pass-through filter.
+    pub const IDENTITY: Self = Self {
+        b0: 1.0,
+        b1: 0.0,
+        b2: 0.0,
+        a1: 0.0,
+        a2: 0.0,
+    };
+
+    /// Second-order low-pass.
+    pub fn lowpass(sample_rate: f64, frequency: f64, q: f64) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(sample_rate, frequency, q);
+        let b1 = 1.0 - cos_w0;
+        Self::normalized(b1 / 2.0, b1, b1 / 2.0, cos_w0, alpha)
+    }
+
+    /// Second-order high-pass.
+    pub fn highpass(sample_rate: f64, frequency: f64, q: f64) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(sample_rate, frequency, q);
+        let b1 = -(1.0 + cos_w0);
+        Self::normalized(-b1 / 2.0, b1, -b1 / 2.0, cos_w0, alpha)
+    }
+
+    /// Second-order high shelf with `gain_db` above `frequency`.
+    pub fn high_shelf(sample_rate: f64, frequency: f64, gain_db: f64, q: f64) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(sample_rate, frequency, q);
+        let a = 10f64.powf(gain_db / 40.0);
+        let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+        Self::from_raw(
+            [
+                a * ((a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0),
+                a * ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha),
+            ],
+            [
+                (a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos_w0),
+                (a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha,
+            ],
+        )
+    }
+
+    /// Second-order peaking (bell) filter with `gain_db` at `frequency`.
+    pub fn peaking(sample_rate: f64, frequency: f64, gain_db: f64, q: f64) -> Self {
+        let (cos_w0, alpha) = Self::prewarp(sample_rate, frequency, q);
+        let a = 10f64.powf(gain_db / 40.0);
+        Self::from_raw(
+            [1.0 + alpha * a, -2.0 * cos_w0, 1.0 - alpha * a],
+            [1.0 + alpha / a, -2.0 * cos_w0, 1.0 - alpha / a],
+        )
+    }
+
+    fn prewarp(sample_rate: f64, frequency: f64, q: f64) -> (f64, f64) {
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    fn from_raw(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+        }
+    }
+
+    fn normalized(b0: f64, b1: f64, b2: f64, cos_w0: f64, alpha: f64) -> Self {
+        let a0 = 1.0 + alpha;
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: -2.0 * cos_w0 / a0,
+            a2: (1.0 - alpha) / a0,
+        }
+    }
+}
+
+/// A second-order IIR filter (transposed direct form II).
+#[derive(Clone, Debug)]
+pub struct Biquad {
+    coeffs: BiquadCoefficients,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    /// Create a filter with cleared state.
+    pub fn new(coeffs: BiquadCoefficients) -> Self {
+        Self {
+            coeffs,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+
+    /// Get the filter coefficients.
+    #[inline]
+    pub fn coefficients(&self) -> BiquadCoefficients {
+        self.coeffs
+    }
+
+    /// Replace the coefficients, keeping the filter state.
+    pub fn set_coefficients(&mut self, coeffs: BiquadCoefficients) {
+        self.coeffs = coeffs;
+    }
+
+    /// Clear the filter state.
+    pub fn reset(&mut self) {
+        self.z1 = 0.0;
+        self.z2 = 0.0;
+    }
+
+    /// Filter a single sample.
+    #[inline]
+    pub fn process_sample(&mut self, x: f64) -> f64 {
+        let c = &self.coeffs;
+        let y = c.b0 * x + self.z1;
+        self.z1 = c.b1 * x - c.a1 * y + self.z2;
+        self.z2 = c.b2 * x - c.a2 * y;
+        y
+    }
+
+    /// Filter a buffer in place.
+    pub fn process(&mut self, buffer: &mut [f32]) {
+        for sample in buffer {
+            *sample = self.process_sample(*sample as f64) as f32;
+        }
+    }
+}
+
+/// A 4th-order Linkwitz-Riley crossover (two cascaded Butterworth sections per band).
+///
+/// The low and high outputs sum to an all-pass response, so splitting a
+/// signal and recombining the bands leaves its magnitude unchanged.
+#[derive(Clone, Debug)]
+pub struct LinkwitzRiley {
+    frequency: f64,
+    low: [Biquad; 2],
+    high: [Biquad; 2],
+}
+
+impl LinkwitzRiley {
+    /// Create a crossover at `frequency` Hz.
+    pub fn new(sample_rate: f64, frequency: f64) -> Self {
+        let lp = Biquad::new(BiquadCoefficients::lowpass(
+            sample_rate,
+            frequency,
+            FRAC_1_SQRT_2,
+        ));
+        let hp = Biquad::new(BiquadCoefficients::highpass(
+            sample_rate,
+            frequency,
+            FRAC_1_SQRT_2,
+        ));
+        Self {
+            frequency,
+            low: [lp.clone(), lp],
+            high: [hp.clone(), hp],
+        }
+    }
+
+    /// Get the crossover frequency in Hz.
+    #[inline]
+    pub fn frequency(&self) -> f64 {
+        self.frequency
+    }
+
+    /// Clear the filter state.
+    pub fn reset(&mut self) {
+        for filter in self.low.iter_mut().chain(&mut self.high) {
+            filter.reset();
+        }
+    }
+
+    /// Split one sample into its (low, high) bands.
+    #[inline]
+    pub fn split_sample(&mut self, x: f64) -> (f64, f64) {
+        let low = self.low[0].process_sample(x);
+        let high = self.high[0].process_sample(x);
+        (
+            self.low[1].process_sample(low),
+            self.high[1].process_sample(high),
+        )
+    }
+
+    /// Split a buffer into low and high bands.
+    ///
+    /// # Panics
+    /// Panics if the output buffers are shorter than `input`.
+    pub fn split(&mut self, input: &[f32], low: &mut [f32], high: &mut [f32]) {
+        assert!(low.len() >= input.len() && high.len() >= input.len());
+        for (i, &x) in input.iter().enumerate() {
+            let (l, h) = self.split_sample(x as f64);
+            low[i] = l as f32;
+            high[i] = h as f32;
+        }
+    }
+}
+
+/// A one-pole low-pass filter with unity gain at DC.
+///
+/// Cheap enough to run per source and smooth under coefficient changes, so
+/// the cutoff can follow a moving parameter block by block.
+#[derive(Clone, Debug, Default)]
+pub struct OnePole {
+    pole: f64,
+    state: f64,
+}
+
+impl OnePole {
+    /// Create a transparent filter (pole at 0).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the pole, in `[0, 1)`; 0 passes the signal unchanged.
+    #[inline]
+    pub fn pole(&self) -> f64 {
+        self.pole
+    }
+
+    /// Place the pole so that the filter's gain at `frequency` Hz is
+    /// `gain` (clamped to `(0, 1]`), keeping the filter state.
+    pub fn set_gain_at(&mut self, sample_rate: f64, frequency: f64, gain: f64) {
+        let g2 = gain.clamp(1e-6, 1.0).powi(2);
+        let cos = (2.0 * PI * frequency / sample_rate).cos();
+        // |H|² = (1 - p)² / (1 - 2p cos w + p²) = g² solved for p
+        let a = 1.0 - g2;
+        let b = 1.0 - g2 * cos;
+        self.pole = if a > 0.0 {
+            (b - (b * b - a * a).max(0.0).sqrt()) / a
+        } else {
+            0.0
+        };
+    }
+
+    /// Clear the filter state.
+    pub fn reset(&mut self) {
+        self.state = 0.0;
+    }
+
+    /// Filter a single sample.
+    #[inline]
+    pub fn process_sample(&mut self, x: f64) -> f64 {
+        self.state = x + (self.state - x) * self.pole;
+        self.state
+    }
+
+    /// Filter a buffer in place.
+    pub fn process(&mut self, buffer: &mut [f32]) {
+        for sample in buffer {
+            *sample = self.process_sample(*sample as f64) as f32;
+        }
+    }
+}
+
+/// A direct-form FIR filter, e.g. for convolving with an impulse response.
+#[derive(Clone, Debug)]
+pub struct Fir {
+    taps: Vec<f32>,
+    /// Past inputs, newest at `position`.
+    history: Vec<f32>,
+    position: usize,
+}
+
+impl Fir {
+    /// Create a filter with the given impulse response and cleared state.
+    pub fn new(taps: Vec<f32>) -> Self {
+        Self {
+            history: vec![0.0; taps.len().max(1)],
+            taps,
+            position: 0,
+        }
+    }
+
+    /// Get the impulse response.
+    #[inline]
+    pub fn taps(&self) -> &[f32] {
+        &self.taps
+    }
+
+    /// Clear the filter state.
+    pub fn reset(&mut self) {
+        self.history.fill(0.0);
+    }
+
+    /// Filter a single sample.
+    #[inline]
+    pub fn process_sample(&mut self, x: f32) -> f32 {
+        let len = self.history.len();
+        self.position = (self.position + 1) % len;
+        self.history[self.position] = x;
+
+        // Newest to oldest: back from `position`, then wrapping around
+        let (recent, wrapped) = self.history.split_at(self.position + 1);
+        recent
+            .iter()
+            .rev()
+            .chain(wrapped.iter().rev())
+            .zip(&self.taps)
+            .map(|(x, h)| x * h)
+            .sum()
+    }
+}
+
+/// A delay line read at fractional delays with linear interpolation.
+///
+/// Varying the delay while reading resamples the signal, which is how the
+/// mixer renders Doppler shifts.
+#[derive(Clone, Debug)]
+pub struct DelayLine {
+    buffer: Vec<f32>,
+    write: usize,
+}
+
+impl DelayLine {
+    /// Create a silent delay line holding up to `max_delay` samples.
+    pub fn new(max_delay: usize) -> Self {
+        Self {
+            buffer: vec![0.0; max_delay + 2],
+            write: 0,
+        }
+    }
+
+    /// Get the longest delay in samples.
+    #[inline]
+    pub fn max_delay(&self) -> usize {
+        self.buffer.len() - 2
+    }
+
+    /// Clear the stored signal.
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write = 0;
+    }
+
+    /// Push one sample and read the signal `delay` samples back, clamped to
+    /// `[0, max_delay]`. A delay of 0 returns `x` itself.
+    #[inline]
+    pub fn process_sample(&mut self, x: f32, delay: f64) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[self.write] = x;
+
+        let delay = delay.clamp(0.0, self.max_delay() as f64);
+        let whole = delay.floor();
+        let frac = (delay - whole) as f32;
+        let newer = (self.write + len - whole as usize) % len;
+        let older = (newer + len - 1) % len;
+        let y = self.buffer[newer] + (self.buffer[older] - self.buffer[newer]) * frac;
+
+        self.write = (self.write + 1) % len;
+        y
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn sine(sample_rate: f64, frequency: f64, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|n| (2.0 * PI * frequency * n as f64 / sample_rate).sin() as f32)
+            .collect()
+    }
+
+    fn peak(buffer: &[f32]) -> f32 {
+        buffer.iter().fold(0.0, |m, x| m.max(x.abs()))
+    }
+
+    #[test]
+    fn test_one_pole_gain() {
+        let mut filter = OnePole::new();
+        filter.set_gain_at(48000.0, 4000.0, 0.5);
+        assert!(filter.pole() > 0.0 && filter.pole() < 1.0);
+
+        let mut buffer = sine(48000.0, 4000.0, 48000);
+        filter.process(&mut buffer);
+        // Sampled peaks miss the crest at 12 samples per period; use RMS
+        let tail = &buffer[24000..];
+        let rms =
+            (tail.iter().map(|&x| (x as f64).powi(2)).sum::<f64>() / tail.len() as f64).sqrt();
+        assert_relative_eq!(rms * 2f64.sqrt(), 0.5, epsilon = 1e-3);
+
+        let mut dc = vec![1.0f32; 4800];
+        filter.process(&mut dc);
+        assert_relative_eq!(dc[4799], 1.0, epsilon = 1e-4);
+
+        filter.set_gain_at(48000.0, 4000.0, 1.0);
+        assert_eq!(filter.pole(), 0.0);
+    }
+
+    #[test]
+    fn test_fir_convolves() {
+        let mut fir = Fir::new(vec![0.5, 0.25, -1.0]);
+        let out: Vec<f32> = [1.0, 0.0, 0.0, 2.0, 0.0]
+            .iter()
+            .map(|&x| fir.process_sample(x))
+            .collect();
+        assert_eq!(out, [0.5, 0.25, -1.0, 1.0, 0.5]);
+    }
+
+    #[test]
+    fn test_delay_line() {
+        let mut delay = DelayLine::new(4);
+        let out: Vec<f32> = [1.0, 2.0, 3.0, 4.0, 5.0]
+            .iter()
+            .map(|&x| delay.process_sample(x, 1.5))
+            .collect();
+        assert_eq!(out, [0.0, 0.5, 1.5, 2.5, 3.5]);
+        // Delays beyond the maximum are clamped
+        assert_eq!(delay.process_sample(6.0, 10.0), 2.0);
+    }
+
+    #[test]
+    fn test_lowpass_passes_dc() {
+        let mut filter = Biquad::new(BiquadCoefficients::lowpass(48000.0, 100.0, FRAC_1_SQRT_2));
+        let mut buffer = vec![1.0f32; 48000];
+        filter.process(&mut buffer);
+        assert_relative_eq!(buffer[47999], 1.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_highpass_blocks_dc() {
+        let mut filter = Biquad::new(BiquadCoefficients::highpass(48000.0, 100.0, FRAC_1_SQRT_2));
+        let mut buffer = vec![1.0f32; 48000];
+        filter.process(&mut buffer);
+        assert_relative_eq!(buffer[47999], 0.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_shelf_and_peaking_gain() {
+        let db = |g: f64| 20.0 * g.abs().log10();
+        let dc = |c: BiquadCoefficients| db((c.b0 + c.b1 + c.b2) / (1.0 + c.a1 + c.a2));
+        let nyquist = |c: BiquadCoefficients| db((c.b0 - c.b1 + c.b2) / (1.0 - c.a1 + c.a2));
+
+        let shelf = BiquadCoefficients::high_shelf(48000.0, 4000.0, 6.0, FRAC_1_SQRT_2);
+        assert_relative_eq!(dc(shelf), 0.0, epsilon = 1e-9);
+        assert_relative_eq!(nyquist(shelf), 6.0, epsilon = 1e-9);
+
+        let bell = BiquadCoefficients::peaking(48000.0, 1000.0, -9.0, 1.0);
+        assert_relative_eq!(dc(bell), 0.0, epsilon = 1e-9);
+        let mut buffer = sine(48000.0, 1000.0, 48000);
+        Biquad::new(bell).process(&mut buffer);
+        assert_relative_eq!(db(peak(&buffer[24000..]) as f64), -9.0, epsilon = 0.05);
+    }
+
+    #[test]
+    fn test_linkwitz_riley_sums_flat() {
+        for frequency in [40.0, 80.0, 160.0, 1000.0] {
+            let mut crossover = LinkwitzRiley::new(48000.0, 80.0);
+            let input = sine(48000.0, frequency, 48000);
+            let mut low = vec![0.0; input.len()];
+            let mut high = vec![0.0; input.len()];
+            crossover.split(&input, &mut low, &mut high);
+
+            let sum: Vec<f32> = low.iter().zip(&high).map(|(l, h)| l + h).collect();
+            // Skip the transient
+            assert_relative_eq!(peak(&sum[24000..]), 1.0, epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_linkwitz_riley_crossover_point() {
+        let mut crossover = LinkwitzRiley::new(48000.0, 80.0);
+        let input = sine(48000.0, 80.0, 48000);
+        let mut low = vec![0.0; input.len()];
+        let mut high = vec![0.0; input.len()];
+        crossover.split(&input, &mut low, &mut high);
+
+        // Both bands are -6 dB at the crossover frequency
+        assert_relative_eq!(peak(&low[24000..]), 0.5, epsilon = 1e-3);
+        assert_relative_eq!(peak(&high[24000..]), 0.5, epsilon = 1e-3);
+    }
+}
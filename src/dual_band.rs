@@ -0,0 +1,208 @@
+//! Dual-band VBAP processing.
+//!
+//! Below a few hundred hertz the signals of neighbouring speakers add
+//! coherently at the listener, so VBAP gains should sum to one (amplitude
+//! normalization); above, they add in power, so squared gains should sum to
+//! one (energy normalization). [`DualBandPanner`] splits a source at a
+//! crossover and pans each band with the matching normalization.
+
+use crate::dsp::LinkwitzRiley;
+use crate::error::{Result, VBAPError};
+use crate::panner::{Normalization, VBAPanner};
+
+/// Default crossover between the amplitude- and energy-normalized bands.
+pub const DEFAULT_CROSSOVER: f64 = 700.0;
+
+/// Pans one source with frequency-dependent gain normalization.
+///
+/// The input is split by a Linkwitz-Riley crossover (whose bands sum back
+/// to a flat response) into a low band panned with
+/// [`Normalization::Amplitude`] and a high band panned with
+/// [`Normalization::Power`]. Like the [`Mixer`](crate::mixer::Mixer),
+/// position changes take effect at the next block and gains are ramped
+/// across it.
+///
+/// Requires the `dual-band` feature.
+///
+/// # Example
+///
+/// ```
+/// use vbap::dual_band::{DualBandPanner, DEFAULT_CROSSOVER};
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+/// let mut dual = DualBandPanner::new(&panner, 48000.0, DEFAULT_CROSSOVER).unwrap();
+/// dual.set_position(15.0, 0.0);
+///
+/// let input = vec![0.5f32; 256];
+/// let mut buffers = vec![vec![0.0f32; 256]; panner.num_speakers()];
+/// let mut outputs: Vec<&mut [f32]> = buffers.iter_mut().map(|b| b.as_mut_slice()).collect();
+/// dual.process(&input, &mut outputs);
+/// ```
+#[derive(Clone, Debug)]
+pub struct DualBandPanner {
+    low: VBAPanner,
+    high: VBAPanner,
+    crossover: LinkwitzRiley,
+    azimuth: f64,
+    elevation: f64,
+    /// Low- and high-band gains at the end of the previous block.
+    current: [Vec<f64>; 2],
+    /// Low- and high-band gains to reach by the end of the current block.
+    target: [Vec<f64>; 2],
+}
+
+impl DualBandPanner {
+    /// Create a dual-band panner for the layout of `panner`.
+    ///
+    /// Other panner settings (frozen speakers, renormalization) carry over
+    /// to both bands. Returns an error unless
+    /// `0 < crossover < sample_rate / 2`.
+    pub fn new(panner: &VBAPanner, sample_rate: f64, crossover: f64) -> Result<Self> {
+        let nyquist = sample_rate / 2.0;
+        if !(crossover > 0.0 && crossover < nyquist) {
+            return Err(VBAPError::InvalidParameter {
+                parameter: "crossover frequency",
+                value: crossover,
+                min: 0.0,
+                max: nyquist,
+            });
+        }
+
+        let n = panner.num_speakers();
+        Ok(Self {
+            low: panner.clone().with_normalization(Normalization::Amplitude),
+            high: panner.clone().with_normalization(Normalization::Power),
+            crossover: LinkwitzRiley::new(sample_rate, crossover),
+            azimuth: 0.0,
+            elevation: 0.0,
+            current: [vec![0.0; n], vec![0.0; n]],
+            target: [vec![0.0; n], vec![0.0; n]],
+        })
+    }
+
+    /// Get the crossover frequency in Hz.
+    #[inline]
+    pub fn crossover(&self) -> f64 {
+        self.crossover.frequency()
+    }
+
+    /// Get the number of output channels (one per speaker).
+    #[inline]
+    pub fn num_outputs(&self) -> usize {
+        self.high.num_speakers()
+    }
+
+    /// Set the source direction in degrees. Takes effect at the next block.
+    pub fn set_position(&mut self, azimuth: f64, elevation: f64) {
+        self.azimuth = azimuth;
+        self.elevation = elevation;
+    }
+
+    /// Current (low band, high band) target gains.
+    pub fn gains(&self) -> (&[f64], &[f64]) {
+        (&self.target[0], &self.target[1])
+    }
+
+    /// Clear the filter state and gain ramps.
+    pub fn reset(&mut self) {
+        self.crossover.reset();
+        for gains in self.current.iter_mut().chain(&mut self.target) {
+            gains.fill(0.0);
+        }
+    }
+
+    /// Render one block of the mono `input` into one output per speaker.
+    ///
+    /// Outputs are overwritten.
+    ///
+    /// # Panics
+    /// Panics if `outputs` does not have one channel per speaker, or a
+    /// channel is shorter than `input`.
+    pub fn process(&mut self, input: &[f32], outputs: &mut [&mut [f32]]) {
+        assert_eq!(
+            outputs.len(),
+            self.num_outputs(),
+            "expected one output per speaker"
+        );
+
+        let (azimuth, elevation) = (self.azimuth, self.elevation);
+        self.low
+            .compute_gains_into(azimuth, elevation, &mut self.target[0]);
+        self.high
+            .compute_gains_into(azimuth, elevation, &mut self.target[1]);
+
+        for output in outputs.iter_mut() {
+            output[..input.len()].fill(0.0);
+        }
+
+        let len = input.len() as f64;
+        for (n, &x) in input.iter().enumerate() {
+            let (low, high) = self.crossover.split_sample(x as f64);
+            let ramp = (n + 1) as f64 / len;
+
+            for (channel, output) in outputs.iter_mut().enumerate() {
+                let gain = |band: usize| {
+                    let start = self.current[band][channel];
+                    start + (self.target[band][channel] - start) * ramp
+                };
+                let (low_gain, high_gain) = (gain(0), gain(1));
+                if low_gain != 0.0 || high_gain != 0.0 {
+                    output[n] = (low * low_gain + high * high_gain) as f32;
+                }
+            }
+        }
+
+        for (current, target) in self.current.iter_mut().zip(&self.target) {
+            current.copy_from_slice(target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn render(dual: &mut DualBandPanner, input: &[f32]) -> Vec<Vec<f32>> {
+        let mut buffers = vec![vec![0.0f32; input.len()]; dual.num_outputs()];
+        let mut outputs: Vec<&mut [f32]> = buffers.iter_mut().map(|b| b.as_mut_slice()).collect();
+        dual.process(input, &mut outputs);
+        buffers
+    }
+
+    #[test]
+    fn test_invalid_crossover() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        assert!(DualBandPanner::new(&panner, 48000.0, 0.0).is_err());
+        assert!(DualBandPanner::new(&panner, 48000.0, 24000.0).is_err());
+    }
+
+    #[test]
+    fn test_band_normalizations() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut dual = DualBandPanner::new(&panner, 48000.0, DEFAULT_CROSSOVER).unwrap();
+        dual.set_position(0.0, 0.0);
+        render(&mut dual, &[0.0; 16]);
+
+        let (low, high) = dual.gains();
+        assert_relative_eq!(low[0] + low[1], 1.0, epsilon = 1e-9);
+        assert_relative_eq!(high[0].powi(2) + high[1].powi(2), 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_low_frequency_uses_amplitude_gains() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut dual = DualBandPanner::new(&panner, 48000.0, DEFAULT_CROSSOVER).unwrap();
+        dual.set_position(0.0, 0.0);
+        render(&mut dual, &[0.0; 64]); // settle the gain ramp
+
+        // 50 Hz sine, well below the crossover
+        let input: Vec<f32> = (0..48000)
+            .map(|n| (std::f64::consts::TAU * 50.0 * n as f64 / 48000.0).sin() as f32)
+            .collect();
+        let out = render(&mut dual, &input);
+        let peak = out[0][24000..].iter().fold(0.0f32, |m, x| m.max(x.abs()));
+        assert_relative_eq!(peak, 0.5, epsilon = 0.02);
+    }
+}
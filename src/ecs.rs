@@ -0,0 +1,268 @@
+//! Bevy integration.
+//!
+//! [`VbapPlugin`] positions the sources of a [`Mixer`] from entity
+//! transforms. Mark the camera or player with [`SpatialListener`] and give
+//! each sounding entity a [`SpatialEmitter`] naming its mixer source. After
+//! transforms are propagated each frame, the plugin computes every
+//! emitter's azimuth, elevation and distance relative to the listener and
+//! stores them in the [`SpatialPositions`] resource.
+//!
+//! The mixer itself usually runs on the audio thread, for example in a
+//! `playback::Playback` callback (`cpal` feature). Clone the resource into
+//! that callback and call [`SpatialPositions::apply`] before mixing each
+//! block; it never blocks the audio thread.
+//!
+//! Bevy's axes are converted to the crate's: Bevy's forward (-Z) is
+//! azimuth 0, its +X is to the right (azimuth -90°) and +Y is up.
+//!
+//! Requires the `bevy` feature.
+
+use std::sync::{Arc, Mutex};
+
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::ecs::prelude::*;
+use bevy::math::Vec3;
+use bevy::transform::components::GlobalTransform;
+use bevy::transform::TransformSystems;
+use glam::DVec3;
+
+use crate::math::cartesian_to_spherical;
+use crate::mixer::{Mixer, SourceId};
+
+/// Marks the entity whose transform is the listening position.
+///
+/// With several listeners, none is used.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct SpatialListener;
+
+/// Positions a mixer source at the entity.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpatialEmitter {
+    /// Source of the mixer this entity drives.
+    pub source: SourceId,
+}
+
+impl SpatialEmitter {
+    /// Drive mixer source `source`.
+    pub fn new(source: SourceId) -> Self {
+        Self { source }
+    }
+}
+
+/// Position of an emitter relative to the listener.
+///
+/// Angles are in the crate's convention, whatever the layout's.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EmitterPosition {
+    /// Azimuth in degrees.
+    pub azimuth: f64,
+    /// Elevation in degrees.
+    pub elevation: f64,
+    /// Distance in world units.
+    pub distance: f64,
+}
+
+impl EmitterPosition {
+    /// Get the position of `emitter`, a world-space point, relative to a
+    /// listener.
+    ///
+    /// The listener's scale is ignored. An emitter at the listener is in
+    /// front.
+    pub fn relative_to(listener: &GlobalTransform, emitter: Vec3) -> Self {
+        let (_, rotation, translation) = listener.to_scale_rotation_translation();
+        let local = rotation.inverse() * (emitter - translation);
+        let direction = DVec3::new(-local.x as f64, -local.z as f64, local.y as f64);
+        let (azimuth, elevation) = cartesian_to_spherical(direction);
+        Self {
+            azimuth,
+            elevation,
+            distance: direction.length(),
+        }
+    }
+}
+
+/// Emitter positions shared between the ECS and the audio thread, indexed
+/// by mixer source.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct SpatialPositions {
+    positions: Arc<Mutex<Vec<Option<EmitterPosition>>>>,
+}
+
+impl SpatialPositions {
+    /// Get the last position of a source, or `None` if no emitter has
+    /// driven it yet.
+    pub fn get(&self, source: SourceId) -> Option<EmitterPosition> {
+        let positions = self.positions.lock().ok()?;
+        positions.get(source).copied().flatten()
+    }
+
+    /// Move the mixer's sources to the last emitter positions, converted
+    /// to the layout's [`convention`](crate::SpeakerConfig::convention).
+    ///
+    /// Returns `false`, leaving the mixer unchanged, if the ECS is writing
+    /// positions at that moment; the next block picks them up. Positions of
+    /// sources the mixer does not have are skipped.
+    pub fn apply(&self, mixer: &mut Mixer) -> bool {
+        let Ok(positions) = self.positions.try_lock() else {
+            return false;
+        };
+        let convention = mixer.panner().config().convention();
+        let num_sources = mixer.sources().len();
+        for (source, position) in positions.iter().enumerate().take(num_sources) {
+            if let Some(position) = position {
+                let (azimuth, elevation) =
+                    convention.from_native(position.azimuth, position.elevation);
+                mixer.set_position(source, azimuth, elevation);
+                mixer.set_distance(source, position.distance);
+            }
+        }
+        true
+    }
+
+    fn set(&self, positions: impl IntoIterator<Item = (SourceId, EmitterPosition)>) {
+        let Ok(mut slots) = self.positions.lock() else {
+            return;
+        };
+        for (source, position) in positions {
+            if source >= slots.len() {
+                slots.resize(source + 1, None);
+            }
+            slots[source] = Some(position);
+        }
+    }
+}
+
+/// Store the position of every emitter relative to the listener.
+pub fn update_emitters(
+    listener: Query<&GlobalTransform, With<SpatialListener>>,
+    emitters: Query<(&SpatialEmitter, &GlobalTransform)>,
+    positions: Res<SpatialPositions>,
+) {
+    let Ok(listener) = listener.single() else {
+        return;
+    };
+    positions.set(emitters.iter().map(|(emitter, transform)| {
+        (
+            emitter.source,
+            EmitterPosition::relative_to(listener, transform.translation()),
+        )
+    }));
+}
+
+/// Adds [`SpatialPositions`] and runs [`update_emitters`] after transform
+/// propagation.
+///
+/// # Example
+///
+/// ```
+/// use bevy::prelude::*;
+/// use vbap::ecs::{SpatialEmitter, SpatialListener, SpatialPositions, VbapPlugin};
+/// use vbap::mixer::Mixer;
+/// use vbap::VBAPanner;
+///
+/// let mut mixer = Mixer::new(VBAPanner::builder().surround_5_1().build().unwrap());
+/// let source = mixer.add_source();
+///
+/// let mut app = App::new();
+/// app.add_plugins((TransformPlugin, VbapPlugin));
+/// app.world_mut().spawn((SpatialListener, Transform::default()));
+/// // 3 units to the listener's left
+/// app.world_mut()
+///     .spawn((SpatialEmitter::new(source), Transform::from_xyz(-3.0, 0.0, 0.0)));
+/// app.update();
+///
+/// // Normally in the audio callback
+/// let positions = app.world().resource::<SpatialPositions>().clone();
+/// positions.apply(&mut mixer);
+/// assert!((mixer.sources()[source].azimuth() - 90.0).abs() < 1e-4);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VbapPlugin;
+
+impl Plugin for VbapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialPositions>().add_systems(
+            PostUpdate,
+            update_emitters.after(TransformSystems::Propagate),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::panner::VBAPanner;
+    use approx::assert_relative_eq;
+    use bevy::math::Quat;
+    use bevy::transform::components::Transform;
+
+    #[test]
+    fn test_relative_position() {
+        let listener = GlobalTransform::IDENTITY;
+        let front = EmitterPosition::relative_to(&listener, Vec3::new(0.0, 0.0, -2.0));
+        assert_relative_eq!(front.azimuth, 0.0, epsilon = 1e-9);
+        assert_relative_eq!(front.distance, 2.0, epsilon = 1e-9);
+        let right = EmitterPosition::relative_to(&listener, Vec3::X);
+        assert_relative_eq!(right.azimuth, -90.0, epsilon = 1e-5);
+        let above = EmitterPosition::relative_to(&listener, Vec3::Y);
+        assert_relative_eq!(above.elevation, 90.0, epsilon = 1e-5);
+
+        // Turned 90° to the left, the world's -Z is now on the right
+        let turned = GlobalTransform::from(
+            Transform::from_xyz(0.0, 0.0, 5.0)
+                .with_rotation(Quat::from_rotation_y(90f32.to_radians())),
+        );
+        let position = EmitterPosition::relative_to(&turned, Vec3::ZERO);
+        assert_relative_eq!(position.azimuth, -90.0, epsilon = 1e-4);
+        assert_relative_eq!(position.distance, 5.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_plugin_drives_mixer() {
+        let mut mixer = Mixer::new(VBAPanner::builder().surround_5_1().build().unwrap());
+        let first = mixer.add_source();
+        let second = mixer.add_source();
+
+        let mut app = App::new();
+        app.add_plugins(VbapPlugin);
+        app.world_mut().spawn((
+            SpatialListener,
+            GlobalTransform::from_translation(Vec3::new(0.0, 0.0, 1.0)),
+        ));
+        let emitter = app
+            .world_mut()
+            .spawn((
+                SpatialEmitter::new(second),
+                GlobalTransform::from_translation(Vec3::new(0.0, 0.0, 4.0)),
+            ))
+            .id();
+        app.update();
+
+        let positions = app.world().resource::<SpatialPositions>().clone();
+        assert_eq!(positions.get(first), None);
+        assert!(positions.apply(&mut mixer));
+        let source = &mixer.sources()[second];
+        assert_relative_eq!(source.azimuth().abs(), 180.0, epsilon = 1e-9);
+        assert_relative_eq!(source.distance(), 3.0, epsilon = 1e-9);
+
+        // Emitters move with their transforms
+        app.world_mut()
+            .entity_mut(emitter)
+            .insert(GlobalTransform::from_translation(Vec3::new(-1.0, 0.0, 1.0)));
+        app.update();
+        positions.apply(&mut mixer);
+        assert_relative_eq!(mixer.sources()[second].azimuth(), 90.0, epsilon = 1e-9);
+
+        // Layouts in another convention get their own angles
+        let panner = VBAPanner::builder()
+            .convention(crate::Convention::Max)
+            .surround_5_1()
+            .build()
+            .unwrap();
+        let mut mixer = Mixer::new(panner);
+        mixer.add_source();
+        mixer.add_source();
+        positions.apply(&mut mixer);
+        assert_relative_eq!(mixer.sources()[second].azimuth(), -90.0, epsilon = 1e-9);
+    }
+}
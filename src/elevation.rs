@@ -0,0 +1,198 @@
+//! Virtual elevation cues for horizontal-only layouts.
+//!
+//! A 2D layout cannot place sources above the listener, but part of height
+//! perception comes from spectral cues: sources above the head are heard
+//! with more energy around 7–8 kHz (Blauert's "overhead" directional band)
+//! and a brighter top end. [`ElevationCues`] turns a source elevation into
+//! simple EQ hints that hosts can apply to the speaker feeds.
+//!
+//! Requires the `render` feature.
+
+use crate::config::PanningMode;
+use crate::dsp::BiquadCoefficients;
+use crate::math::clamp_elevation;
+use crate::panner::VBAPanner;
+
+/// Type of a [`CueFilter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CueFilterKind {
+    /// Second-order high shelf.
+    HighShelf,
+    /// Second-order peaking (bell) filter.
+    Peaking,
+}
+
+/// An EQ filter specification.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CueFilter {
+    /// Filter type.
+    pub kind: CueFilterKind,
+    /// Corner (shelf) or center (peaking) frequency in Hz.
+    pub frequency: f64,
+    /// Gain in dB.
+    pub gain_db: f64,
+    /// Quality factor.
+    pub q: f64,
+}
+
+impl CueFilter {
+    /// Biquad coefficients realizing this filter at `sample_rate`.
+    pub fn coefficients(&self, sample_rate: f64) -> BiquadCoefficients {
+        match self.kind {
+            CueFilterKind::HighShelf => {
+                BiquadCoefficients::high_shelf(sample_rate, self.frequency, self.gain_db, self.q)
+            }
+            CueFilterKind::Peaking => {
+                BiquadCoefficients::peaking(sample_rate, self.frequency, self.gain_db, self.q)
+            }
+        }
+    }
+}
+
+/// EQ hints for one speaker: apply both filters in series.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElevationCue {
+    /// Overall brightness shelf.
+    pub shelf: CueFilter,
+    /// Overhead directional-band boost (or cut below the horizon).
+    pub band: CueFilter,
+}
+
+/// Maps source elevation to spectral height cues.
+///
+/// Cue strength follows `sin(elevation)`: full at the zenith, none on the
+/// horizon, and inverted (darker) below it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ElevationCues {
+    /// Shelf corner frequency in Hz.
+    pub shelf_frequency: f64,
+    /// Shelf gain at the zenith in dB.
+    pub shelf_gain_db: f64,
+    /// Directional band center frequency in Hz.
+    pub band_frequency: f64,
+    /// Directional band gain at the zenith in dB.
+    pub band_gain_db: f64,
+    /// Directional band quality factor.
+    pub band_q: f64,
+}
+
+impl Default for ElevationCues {
+    fn default() -> Self {
+        Self {
+            shelf_frequency: 6000.0,
+            shelf_gain_db: 3.0,
+            band_frequency: 7500.0,
+            band_gain_db: 6.0,
+            band_q: 1.4,
+        }
+    }
+}
+
+impl ElevationCues {
+    /// EQ hint for a source at `elevation` degrees, `None` on the horizon.
+    pub fn cue(&self, elevation: f64) -> Option<ElevationCue> {
+        let strength = clamp_elevation(elevation).to_radians().sin();
+        if strength.abs() < 1e-6 {
+            return None;
+        }
+
+        Some(ElevationCue {
+            shelf: CueFilter {
+                kind: CueFilterKind::HighShelf,
+                frequency: self.shelf_frequency,
+                gain_db: self.shelf_gain_db * strength,
+                q: std::f64::consts::FRAC_1_SQRT_2,
+            },
+            band: CueFilter {
+                kind: CueFilterKind::Peaking,
+                frequency: self.band_frequency,
+                gain_db: self.band_gain_db * strength,
+                q: self.band_q,
+            },
+        })
+    }
+
+    /// Compute gains plus per-speaker EQ hints for an elevated source.
+    ///
+    /// `cues[i]` is the hint for speaker `i`; it is `None` for silent
+    /// speakers, for sources on the horizon, and for 3D layouts, which have
+    /// real height speakers.
+    ///
+    /// # Panics
+    /// Panics if `gains` or `cues` is shorter than `panner.num_speakers()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::elevation::ElevationCues;
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder().surround_7_1().build().unwrap();
+    /// let mut gains = vec![0.0; panner.num_speakers()];
+    /// let mut cues = vec![None; panner.num_speakers()];
+    ///
+    /// ElevationCues::default().compute(&panner, 30.0, 60.0, &mut gains, &mut cues);
+    /// assert!(cues[0].is_some()); // front left carries the source
+    /// ```
+    pub fn compute(
+        &self,
+        panner: &VBAPanner,
+        azimuth: f64,
+        elevation: f64,
+        gains: &mut [f64],
+        cues: &mut [Option<ElevationCue>],
+    ) {
+        let n = panner.num_speakers();
+        assert!(
+            cues.len() >= n,
+            "cues slice too small: {} < {}",
+            cues.len(),
+            n
+        );
+        panner.compute_gains_into(azimuth, elevation, gains);
+
+        let cue = match panner.mode() {
+            PanningMode::TwoD => self.cue(elevation),
+            PanningMode::ThreeD => None,
+        };
+        for (slot, &gain) in cues.iter_mut().zip(&gains[..n]) {
+            *slot = if gain > 0.0 { cue } else { None };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_cue_strength() {
+        let cues = ElevationCues::default();
+        assert!(cues.cue(0.0).is_none());
+
+        let zenith = cues.cue(90.0).unwrap();
+        assert_relative_eq!(zenith.band.gain_db, 6.0, epsilon = 1e-9);
+        assert_relative_eq!(cues.cue(30.0).unwrap().shelf.gain_db, 1.5, epsilon = 1e-9);
+        assert!(cues.cue(-45.0).unwrap().band.gain_db < 0.0);
+    }
+
+    #[test]
+    fn test_compute_hints_only_active_2d_speakers() {
+        let cues = ElevationCues::default();
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let mut gains = vec![0.0; panner.num_speakers()];
+        let mut hints = vec![None; panner.num_speakers()];
+
+        cues.compute(&panner, 15.0, 45.0, &mut gains, &mut hints);
+        for (gain, hint) in gains.iter().zip(&hints) {
+            assert_eq!(*gain > 0.0, hint.is_some());
+        }
+
+        let atmos = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let mut gains = vec![0.0; atmos.num_speakers()];
+        let mut hints = vec![None; atmos.num_speakers()];
+        cues.compute(&atmos, 15.0, 45.0, &mut gains, &mut hints);
+        assert!(hints.iter().all(Option::is_none));
+    }
+}
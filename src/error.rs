@@ -13,6 +13,14 @@ pub enum VBAPError {
         required: usize,
     },
 
+    /// More speakers than [`MAX_SPEAKERS`](crate::config::MAX_SPEAKERS).
+    TooManySpeakers {
+        /// Number of speakers provided.
+        provided: usize,
+        /// Maximum supported number of speakers.
+        max: usize,
+    },
+
     /// Cannot form valid speaker pairs (2D) or triplets (3D).
     /// This can happen if speakers are too close together or all collinear.
     InvalidConfiguration(String),
@@ -28,6 +36,64 @@ pub enum VBAPError {
         /// Maximum valid value.
         max: f64,
     },
+
+    /// Trajectory keyframes are missing or invalid.
+    InvalidTrajectory(String),
+
+    /// A user motion script failed to compile or run.
+    Script(String),
+
+    /// A layout description in another tool's format could not be parsed.
+    Parse(String),
+
+    /// An audio file could not be read or written, or an audio device
+    /// failed.
+    Audio(String),
+
+    /// A network socket could not be opened or failed.
+    Network(String),
+
+    /// A numeric parameter is out of its valid range.
+    InvalidParameter {
+        /// Name of the parameter.
+        parameter: &'static str,
+        /// The invalid value provided.
+        value: f64,
+        /// Minimum valid value.
+        min: f64,
+        /// Maximum valid value.
+        max: f64,
+    },
+
+    /// No speaker tuple covers a source direction, so the best tuple still
+    /// has a negative gain that would be clamped to zero.
+    UncoveredDirection {
+        /// Source azimuth in degrees.
+        azimuth: f64,
+        /// Source elevation in degrees.
+        elevation: f64,
+        /// Smallest power-normalized gain of the best tuple.
+        min_gain: f64,
+    },
+
+    /// Two speakers are closer together than the coincidence tolerance, see
+    /// [`DuplicateSpeakers::Error`](crate::config::DuplicateSpeakers::Error).
+    CoincidentSpeakers {
+        /// Index of the first speaker.
+        first: usize,
+        /// Index of the second speaker.
+        second: usize,
+        /// Angle between them in degrees.
+        angle: f64,
+    },
+
+    /// A speaker index does not refer to a speaker in the configuration.
+    InvalidSpeakerIndex {
+        /// The index that was provided.
+        index: usize,
+        /// Number of speakers in the configuration.
+        num_speakers: usize,
+    },
 }
 
 impl fmt::Display for VBAPError {
@@ -40,6 +106,13 @@ impl fmt::Display for VBAPError {
                     provided, required
                 )
             }
+            VBAPError::TooManySpeakers { provided, max } => {
+                write!(
+                    f,
+                    "too many speakers: {} provided, at most {}",
+                    provided, max
+                )
+            }
             VBAPError::InvalidConfiguration(msg) => {
                 write!(f, "invalid speaker configuration: {}", msg)
             }
@@ -55,6 +128,57 @@ impl fmt::Display for VBAPError {
                     parameter, value, min, max
                 )
             }
+            VBAPError::InvalidTrajectory(msg) => {
+                write!(f, "invalid trajectory: {}", msg)
+            }
+            VBAPError::Script(msg) => write!(f, "script error: {}", msg),
+            VBAPError::Parse(msg) => write!(f, "parse error: {}", msg),
+            VBAPError::Audio(msg) => write!(f, "audio error: {}", msg),
+            VBAPError::Network(msg) => write!(f, "network error: {}", msg),
+            VBAPError::InvalidParameter {
+                parameter,
+                value,
+                min,
+                max,
+            } => {
+                write!(
+                    f,
+                    "invalid {}: {} (must be between {} and {})",
+                    parameter, value, min, max
+                )
+            }
+            VBAPError::UncoveredDirection {
+                azimuth,
+                elevation,
+                min_gain,
+            } => {
+                write!(
+                    f,
+                    "direction ({}, {}) is not covered by the layout (gain {})",
+                    azimuth, elevation, min_gain
+                )
+            }
+            VBAPError::CoincidentSpeakers {
+                first,
+                second,
+                angle,
+            } => {
+                write!(
+                    f,
+                    "speakers {} and {} are only {:.2}° apart",
+                    first, second, angle
+                )
+            }
+            VBAPError::InvalidSpeakerIndex {
+                index,
+                num_speakers,
+            } => {
+                write!(
+                    f,
+                    "invalid speaker index: {} (configuration has {} speakers)",
+                    index, num_speakers
+                )
+            }
         }
     }
 }
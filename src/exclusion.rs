@@ -0,0 +1,119 @@
+//! Per-source speaker exclusion lists.
+//!
+//! Some sources must never reach certain speakers (e.g. keeping a vocal off
+//! the ceiling). Rather than zeroing those gains after panning, which breaks
+//! normalization, the layout is re-triangulated without the excluded speakers.
+
+use crate::config::SpeakerConfig;
+use crate::error::Result;
+use crate::mask::SpeakerMask;
+
+/// Speakers a single source must never be sent to.
+///
+/// The re-triangulated layout is built lazily on first use and cached until
+/// the panner's layout changes.
+///
+/// # Example
+///
+/// ```
+/// use vbap::{SpeakerExclusions, VBAPanner};
+///
+/// let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+/// let mut vocal = SpeakerExclusions::new(&[7, 8, 9, 10]);
+/// let mut gains = vec![0.0; panner.num_speakers()];
+///
+/// panner
+///     .compute_gains_excluding(30.0, 40.0, &mut vocal, &mut gains)
+///     .unwrap();
+/// assert!(gains[7..].iter().all(|&g| g == 0.0));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SpeakerExclusions {
+    excluded: Vec<usize>,
+    /// Re-triangulated layout and the revision of the layout it was built from.
+    cache: Option<(u64, SpeakerConfig)>,
+}
+
+impl SpeakerExclusions {
+    /// Create an exclusion list from speaker indices.
+    pub fn new(excluded: &[usize]) -> Self {
+        let mut excluded = excluded.to_vec();
+        excluded.sort_unstable();
+        excluded.dedup();
+        Self {
+            excluded,
+            cache: None,
+        }
+    }
+
+    /// Create an exclusion list keeping a source inside a zone.
+    ///
+    /// Every speaker not in `zone` is excluded.
+    pub fn from_mask(zone: &SpeakerMask) -> Self {
+        Self {
+            excluded: zone.inverted().iter().collect(),
+            cache: None,
+        }
+    }
+
+    /// Get the excluded speaker indices (sorted).
+    #[inline]
+    pub fn excluded(&self) -> &[usize] {
+        &self.excluded
+    }
+
+    /// Check whether a speaker is excluded.
+    #[inline]
+    pub fn is_excluded(&self, index: usize) -> bool {
+        self.excluded.binary_search(&index).is_ok()
+    }
+
+    /// Get the layout to pan with for `parent`, building it if needed.
+    pub(crate) fn config_for(&mut self, parent: &SpeakerConfig) -> Result<&SpeakerConfig> {
+        let stale = match &self.cache {
+            Some((revision, _)) => *revision != parent.revision(),
+            None => true,
+        };
+        if stale {
+            let subset = parent.without_speakers(&self.excluded)?;
+            self.cache = Some((parent.revision(), subset));
+        }
+
+        Ok(self.cache.as_ref().map(|(_, config)| config).unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpeakerConfigBuilder;
+
+    #[test]
+    fn test_exclusions_sorted_and_deduped() {
+        let exclusions = SpeakerExclusions::new(&[3, 1, 3]);
+        assert_eq!(exclusions.excluded(), &[1, 3]);
+        assert!(exclusions.is_excluded(1));
+        assert!(!exclusions.is_excluded(2));
+    }
+
+    #[test]
+    fn test_from_mask() {
+        let zone = SpeakerMask::from_indices(5, &[0, 1, 2]).unwrap();
+        assert_eq!(SpeakerExclusions::from_mask(&zone).excluded(), &[3, 4]);
+    }
+
+    #[test]
+    fn test_cache_rebuilt_on_layout_change() {
+        let config = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .build_config()
+            .unwrap();
+        let mut exclusions = SpeakerExclusions::new(&[2]);
+
+        let first = exclusions.config_for(&config).unwrap().revision();
+        assert_eq!(exclusions.config_for(&config).unwrap().revision(), first);
+
+        let moved = config.moved_speaker(3, 100.0, 0.0).unwrap();
+        assert_ne!(exclusions.config_for(&moved).unwrap().revision(), first);
+    }
+}
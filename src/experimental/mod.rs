@@ -0,0 +1,19 @@
+//! Algorithms that are not yet part of the stable API.
+//!
+//! New panning methods (SPCAP, n-wise panning, hybrid HOA decoding, ...)
+//! ship here first so they can be tried on real rigs while their API
+//! settles. Anything in this module may change or disappear in a minor
+//! release; the rest of the crate follows semver.
+//!
+//! When an item is stabilized it moves into the core modules and a
+//! deprecated re-export stays here for one minor release, so code that
+//! opted in keeps compiling with a warning that points at the new path:
+//!
+//! ```ignore
+//! #[deprecated(since = "0.3.0", note = "stabilized as `vbap::spcap::SpcapPanner`")]
+//! pub use crate::spcap::SpcapPanner;
+//! ```
+//!
+//! Requires the `experimental` feature.
+
+pub mod spcap;
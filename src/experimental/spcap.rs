@@ -0,0 +1,165 @@
+//! Speaker-Placement Correction Amplitude Panning (SPCAP).
+//!
+//! SPCAP feeds every speaker in front of the source with a cardioid gain
+//! `(1 + cos θ) / 2` of its angle θ to the source, then divides each gain
+//! by the speaker's "effective count", the sum of the same cardioid over
+//! all speakers. Clustered speakers share their weight, so irregular
+//! layouts do not pull sources towards dense regions. Unlike VBAP it needs
+//! no triangulation and never has gaps, at the price of wider phantom
+//! sources.
+
+use glam::DVec3;
+
+use crate::config::SpeakerConfig;
+use crate::convention::Convention;
+use crate::math::spherical_to_cartesian;
+
+/// SPCAP panner for a speaker layout.
+///
+/// Gains are power-normalized. Virtual speakers get no gain and are left
+/// out of the effective counts.
+///
+/// # Example
+///
+/// ```
+/// use vbap::experimental::spcap::SpcapPanner;
+/// use vbap::SpeakerConfigBuilder;
+///
+/// let config = SpeakerConfigBuilder::new().surround_5_1().build_config().unwrap();
+/// let panner = SpcapPanner::new(&config);
+///
+/// let gains = panner.compute_gains(0.0, 0.0);
+/// let loudest = (0..gains.len()).max_by(|&a, &b| gains[a].total_cmp(&gains[b]));
+/// assert_eq!(loudest, Some(2)); // center
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpcapPanner {
+    /// Speaker directions, `None` for virtual speakers.
+    directions: Vec<Option<DVec3>>,
+    /// Reciprocal effective speaker count of each speaker.
+    weights: Vec<f64>,
+    convention: Convention,
+}
+
+impl SpcapPanner {
+    /// Prepare a panner for the speakers of `config`.
+    ///
+    /// Only the speaker directions and the angle convention are used, not
+    /// the triangulation.
+    pub fn new(config: &SpeakerConfig) -> Self {
+        let directions: Vec<Option<DVec3>> = config
+            .speakers()
+            .iter()
+            .map(|s| (!s.is_virtual()).then(|| s.cartesian()))
+            .collect();
+        let weights = directions
+            .iter()
+            .map(|direction| match direction {
+                Some(d) => {
+                    let count: f64 = directions.iter().flatten().map(|o| cardioid(*d, *o)).sum();
+                    1.0 / count
+                }
+                None => 0.0,
+            })
+            .collect();
+
+        Self {
+            directions,
+            weights,
+            convention: config.convention(),
+        }
+    }
+
+    /// Get the number of speakers.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.directions.len()
+    }
+
+    /// Compute speaker gains for a source direction.
+    pub fn compute_gains(&self, azimuth: f64, elevation: f64) -> Vec<f64> {
+        let mut gains = vec![0.0; self.num_speakers()];
+        self.compute_gains_into(azimuth, elevation, &mut gains);
+        gains
+    }
+
+    /// Compute speaker gains into a pre-allocated slice.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_speakers()`.
+    pub fn compute_gains_into(&self, azimuth: f64, elevation: f64, gains: &mut [f64]) {
+        let n = self.num_speakers();
+        assert!(
+            gains.len() >= n,
+            "gains slice too small: {} < {}",
+            gains.len(),
+            n
+        );
+
+        let (azimuth, elevation) = self.convention.to_native(azimuth, elevation);
+        let source = spherical_to_cartesian(azimuth, elevation);
+
+        let mut power = 0.0;
+        for ((gain, direction), weight) in gains.iter_mut().zip(&self.directions).zip(&self.weights)
+        {
+            *gain = direction.map_or(0.0, |d| cardioid(source, d) * weight);
+            power += *gain * *gain;
+        }
+        if power > 0.0 {
+            let scale = power.sqrt().recip();
+            gains[..n].iter_mut().for_each(|g| *g *= scale);
+        }
+    }
+}
+
+/// Cardioid weight `(1 + cos θ) / 2` between two unit vectors.
+#[inline]
+fn cardioid(a: DVec3, b: DVec3) -> f64 {
+    0.5 * (1.0 + a.dot(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpeakerConfigBuilder;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_power_and_symmetry() {
+        let config = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .build_config()
+            .unwrap();
+        let panner = SpcapPanner::new(&config);
+
+        for (azi, ele) in [(0.0, 0.0), (75.0, 30.0), (-160.0, -40.0)] {
+            let gains = panner.compute_gains(azi, ele);
+            assert_relative_eq!(
+                gains.iter().map(|g| g * g).sum::<f64>(),
+                1.0,
+                epsilon = 1e-12
+            );
+            assert!(gains.iter().all(|&g| g >= 0.0));
+
+            // The layout is left/right symmetric, speakers come in L/R pairs
+            let mirrored = panner.compute_gains(-azi, ele);
+            assert_relative_eq!(gains[0], mirrored[1], epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_virtual_speakers_are_silent() {
+        let config = SpeakerConfigBuilder::new()
+            .atmos_5_1_4()
+            .add_virtual_speaker(0.0, -90.0)
+            .build_config()
+            .unwrap();
+        let gains = SpcapPanner::new(&config).compute_gains(0.0, -90.0);
+        assert_eq!(gains[9], 0.0);
+        assert_relative_eq!(
+            gains.iter().map(|g| g * g).sum::<f64>(),
+            1.0,
+            epsilon = 1e-12
+        );
+    }
+}
@@ -0,0 +1,159 @@
+//! Source extent (width and height).
+//!
+//! Object audio formats such as ADM give each object a size as well as a
+//! position. [`VBAPanner::compute_gains_with_extent`] renders an [`Extent`]
+//! as a grid of virtual point sources covering the area around the source
+//! direction, sums their power per speaker and normalizes the result, so a
+//! wide source is as loud as a point source.
+//!
+//! [`VBAPanner::compute_gains_with_extent`]: crate::VBAPanner::compute_gains_with_extent
+
+use glam::DVec3;
+
+use crate::error::{Result, VBAPError};
+
+/// Largest spacing, in degrees, between the virtual sources of an extent.
+const EXTENT_STEP: f64 = 5.0;
+
+/// Angular size of a source in degrees.
+///
+/// `width` is measured along the horizontal arc through the source and
+/// `height` along the vertical one, both centered on the source direction.
+/// The default is a point source.
+///
+/// # Example
+///
+/// ```
+/// use vbap::extent::Extent;
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+/// let mut gains = [0.0; 5];
+///
+/// // A 120° wide source in front reaches beyond the front speakers
+/// let wide = Extent::new(120.0, 0.0).unwrap();
+/// panner.compute_gains_with_extent(0.0, 0.0, wide, &mut gains);
+/// assert!(gains[3] > 0.0 && gains[4] > 0.0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Extent {
+    width: f64,
+    height: f64,
+}
+
+impl Extent {
+    /// Create an extent `width` degrees wide and `height` degrees high.
+    ///
+    /// Returns an error unless `width` is within `[0, 360]` and `height`
+    /// within `[0, 180]`.
+    pub fn new(width: f64, height: f64) -> Result<Self> {
+        for (parameter, value, max) in [("width", width, 360.0), ("height", height, 180.0)] {
+            if !(0.0..=max).contains(&value) {
+                return Err(VBAPError::InvalidParameter {
+                    parameter,
+                    value,
+                    min: 0.0,
+                    max,
+                });
+            }
+        }
+        Ok(Self { width, height })
+    }
+
+    /// Get the width in degrees.
+    #[inline]
+    pub fn width(&self) -> f64 {
+        self.width
+    }
+
+    /// Get the height in degrees.
+    #[inline]
+    pub fn height(&self) -> f64 {
+        self.height
+    }
+
+    /// Check whether the extent is a point.
+    #[inline]
+    pub fn is_point(&self) -> bool {
+        self.width == 0.0 && self.height == 0.0
+    }
+
+    /// Call `f` with the directions of the virtual sources covering the
+    /// extent around `center`, a unit vector.
+    ///
+    /// Sources sit at the midpoints of a grid at most
+    /// [`EXTENT_STEP`] degrees apart, so a full-circle width has no
+    /// duplicate at the seam.
+    pub(crate) fn for_each_direction(&self, center: DVec3, mut f: impl FnMut(DVec3)) {
+        let cross = center.cross(DVec3::Z);
+        // At the poles any horizontal direction will do
+        let left = if cross.length_squared() > 1e-12 {
+            cross.normalize()
+        } else {
+            DVec3::X
+        };
+        let up = left.cross(center);
+
+        let offsets = |size: f64| {
+            let count = (size / EXTENT_STEP).ceil().max(1.0) as usize;
+            (0..count).map(move |i| (size * ((i as f64 + 0.5) / count as f64 - 0.5)).to_radians())
+        };
+        for vertical in offsets(self.height) {
+            let (v_sin, v_cos) = vertical.sin_cos();
+            for horizontal in offsets(self.width) {
+                let (h_sin, h_cos) = horizontal.sin_cos();
+                f(v_cos * (h_cos * center + h_sin * left) + v_sin * up);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_extent_range() {
+        assert!(Extent::new(360.0, 180.0).is_ok());
+        assert!(Extent::new(-1.0, 0.0).is_err());
+        assert!(Extent::new(0.0, 181.0).is_err());
+        assert!(Extent::new(f64::NAN, 0.0).is_err());
+        assert!(Extent::default().is_point());
+    }
+
+    #[test]
+    fn test_directions() {
+        let front = DVec3::Y;
+        let mut directions = Vec::new();
+        Extent::default().for_each_direction(front, |d| directions.push(d));
+        assert_eq!(directions, [front]);
+
+        // 90° wide: 18 sources from 42.5° right to 42.5° left
+        directions.clear();
+        let extent = Extent::new(90.0, 0.0).unwrap();
+        extent.for_each_direction(front, |d| directions.push(d));
+        assert_eq!(directions.len(), 18);
+        assert_relative_eq!(
+            directions[0].x,
+            -42.5f64.to_radians().sin(),
+            epsilon = 1e-12
+        );
+        assert_relative_eq!(
+            directions[17].x,
+            42.5f64.to_radians().sin(),
+            epsilon = 1e-12
+        );
+        assert!(directions.iter().all(|d| d.z.abs() < 1e-12));
+
+        // Above the listener the grid still spans the extent
+        directions.clear();
+        let extent = Extent::new(20.0, 20.0).unwrap();
+        extent.for_each_direction(DVec3::Z, |d| directions.push(d));
+        assert_eq!(directions.len(), 16);
+        for d in directions {
+            assert_relative_eq!(d.length(), 1.0, epsilon = 1e-12);
+            assert!(d.z > 15f64.to_radians().cos());
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! C API.
+//!
+//! A minimal set of `extern "C"` functions for using the panner from C, C++
+//! or any language with a C FFI. The declarations are in `include/vbap.h`.
+//! Build a shared library with
+//!
+//! ```text
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! or a static one with `--crate-type staticlib`.
+//!
+//! A panner is an opaque pointer from [`vbap_panner_create`] or
+//! [`vbap_panner_create_preset`], released with [`vbap_panner_destroy`].
+//! Directions use the crate's angle convention (degrees, azimuth 0 = front,
+//! 90 = left). Functions that can fail return a status code instead of
+//! panicking across the boundary.
+//!
+//! Requires the `ffi` feature.
+
+use std::ffi::{c_char, c_int, CStr};
+use std::ptr;
+use std::slice;
+
+use crate::config::SpeakerConfigBuilder;
+use crate::panner::VBAPanner;
+
+/// The call succeeded.
+pub const VBAP_OK: c_int = 0;
+/// A required pointer was null.
+pub const VBAP_ERROR_NULL_POINTER: c_int = -1;
+/// The gains buffer holds fewer values than the panner has speakers.
+pub const VBAP_ERROR_BUFFER_TOO_SMALL: c_int = -2;
+
+/// Create a panner from speaker directions in degrees.
+///
+/// Returns null if a pointer is null or the layout cannot be built (too
+/// few speakers, degenerate directions, ...).
+///
+/// # Safety
+/// `azimuths` and `elevations` must each point to `num_speakers` readable
+/// values.
+#[no_mangle]
+pub unsafe extern "C" fn vbap_panner_create(
+    azimuths: *const f64,
+    elevations: *const f64,
+    num_speakers: usize,
+) -> *mut VBAPanner {
+    if azimuths.is_null() || elevations.is_null() {
+        return ptr::null_mut();
+    }
+    let azimuths = slice::from_raw_parts(azimuths, num_speakers);
+    let elevations = slice::from_raw_parts(elevations, num_speakers);
+    let builder = azimuths
+        .iter()
+        .zip(elevations)
+        .fold(VBAPanner::builder(), |builder, (&azimuth, &elevation)| {
+            builder.add_speaker(azimuth, elevation)
+        });
+    match builder.build() {
+        Ok(panner) => Box::into_raw(Box::new(panner)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Create a panner for a preset layout by name, such as `"stereo"`,
+/// `"5.1"` or `"7.1.4"` (see [`PRESET_NAMES`](crate::config::PRESET_NAMES)).
+///
+/// Returns null if `name` is null, not UTF-8 or not a preset.
+///
+/// # Safety
+/// `name` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn vbap_panner_create_preset(name: *const c_char) -> *mut VBAPanner {
+    if name.is_null() {
+        return ptr::null_mut();
+    }
+    let Ok(name) = CStr::from_ptr(name).to_str() else {
+        return ptr::null_mut();
+    };
+    match SpeakerConfigBuilder::new()
+        .preset(name)
+        .and_then(SpeakerConfigBuilder::build)
+    {
+        Ok(panner) => Box::into_raw(Box::new(panner)),
+        Err(_) => ptr::null_mut(),
+    }
+}
+
+/// Get the number of speakers of a panner, 0 if `panner` is null.
+///
+/// # Safety
+/// `panner` must be null or a live pointer from one of the create functions.
+#[no_mangle]
+pub unsafe extern "C" fn vbap_panner_num_speakers(panner: *const VBAPanner) -> usize {
+    panner.as_ref().map_or(0, VBAPanner::num_speakers)
+}
+
+/// Compute speaker gains for a source direction in degrees.
+///
+/// Writes one gain per speaker to the start of `gains` and returns
+/// [`VBAP_OK`], or returns a negative `VBAP_ERROR_*` code and leaves
+/// `gains` untouched.
+///
+/// # Safety
+/// `panner` must be null or a live pointer from one of the create functions,
+/// and `gains` must be null or point to `len` writable values.
+#[no_mangle]
+pub unsafe extern "C" fn vbap_compute_gains(
+    panner: *const VBAPanner,
+    azimuth: f64,
+    elevation: f64,
+    gains: *mut f64,
+    len: usize,
+) -> c_int {
+    let Some(panner) = panner.as_ref() else {
+        return VBAP_ERROR_NULL_POINTER;
+    };
+    if gains.is_null() {
+        return VBAP_ERROR_NULL_POINTER;
+    }
+    if len < panner.num_speakers() {
+        return VBAP_ERROR_BUFFER_TOO_SMALL;
+    }
+    let gains = slice::from_raw_parts_mut(gains, len);
+    panner.compute_gains_into(azimuth, elevation, gains);
+    VBAP_OK
+}
+
+/// Release a panner. Null is ignored.
+///
+/// # Safety
+/// `panner` must be null or a pointer from one of the create functions that
+/// has not been destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn vbap_panner_destroy(panner: *mut VBAPanner) {
+    if !panner.is_null() {
+        drop(Box::from_raw(panner));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_and_pan() {
+        let azimuths = [30.0, -30.0, 0.0, 110.0, -110.0];
+        let elevations = [0.0; 5];
+        unsafe {
+            let panner = vbap_panner_create(azimuths.as_ptr(), elevations.as_ptr(), 5);
+            assert!(!panner.is_null());
+            assert_eq!(vbap_panner_num_speakers(panner), 5);
+
+            let mut gains = [0.0; 5];
+            let status = vbap_compute_gains(panner, 0.0, 0.0, gains.as_mut_ptr(), gains.len());
+            assert_eq!(status, VBAP_OK);
+            assert!((gains[2] - 1.0).abs() < 1e-9);
+
+            let status = vbap_compute_gains(panner, 0.0, 0.0, gains.as_mut_ptr(), 4);
+            assert_eq!(status, VBAP_ERROR_BUFFER_TOO_SMALL);
+            let status = vbap_compute_gains(panner, 0.0, 0.0, ptr::null_mut(), 5);
+            assert_eq!(status, VBAP_ERROR_NULL_POINTER);
+            vbap_panner_destroy(panner);
+
+            // One speaker is not a layout
+            assert!(vbap_panner_create(azimuths.as_ptr(), elevations.as_ptr(), 1).is_null());
+            assert!(vbap_panner_create(ptr::null(), elevations.as_ptr(), 5).is_null());
+        }
+    }
+
+    #[test]
+    fn test_presets_and_null_panner() {
+        unsafe {
+            let panner = vbap_panner_create_preset(b"7.1.4\0".as_ptr().cast());
+            assert_eq!(vbap_panner_num_speakers(panner), 11);
+            vbap_panner_destroy(panner);
+
+            assert!(vbap_panner_create_preset(b"22.2\0".as_ptr().cast()).is_null());
+            assert!(vbap_panner_create_preset(ptr::null()).is_null());
+
+            let mut gains = [0.0; 2];
+            let status = vbap_compute_gains(ptr::null(), 0.0, 0.0, gains.as_mut_ptr(), 2);
+            assert_eq!(status, VBAP_ERROR_NULL_POINTER);
+            assert_eq!(vbap_panner_num_speakers(ptr::null()), 0);
+            vbap_panner_destroy(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,206 @@
+//! Fixed-capacity panner for real-time and embedded use.
+//!
+//! [`FixedPanner`] stores its tuples and gains in arrays sized by const
+//! generics, so computing gains never allocates. Building one from a
+//! [`SpeakerConfig`] runs the usual triangulation once up front.
+
+use glam::{DVec2, DVec3};
+
+use crate::config::{SpeakerConfig, SpeakerTuple};
+use crate::convention::Convention;
+use crate::error::{Result, VBAPError};
+use crate::math::spherical_to_cartesian;
+use crate::panner::{ARC_EDGE_TOLERANCE, TIE_TOLERANCE};
+
+/// Allocation-free VBAP panner with a compile-time capacity.
+///
+/// `MAX_SPK` bounds the number of speakers and `MAX_TUPLES` the number of
+/// speaker pairs/triplets. A 2D ring of `n` speakers needs `n` tuples; a 3D
+/// layout of `n` speakers needs at most `2n - 4`.
+///
+/// This is the core algorithm only: speaker freezing, renormalization and
+/// tie-break policies other than the lowest tuple index are available on
+/// [`VBAPanner`](crate::VBAPanner).
+///
+/// # Example
+///
+/// ```
+/// use vbap::{FixedPanner, SpeakerConfigBuilder};
+///
+/// let config = SpeakerConfigBuilder::new().surround_5_1().build_config().unwrap();
+/// let panner = FixedPanner::<8, 8>::from_config(&config).unwrap();
+///
+/// let gains: [f64; 8] = panner.compute_gains(0.0, 0.0);
+/// assert!((gains[2] - 1.0).abs() < 1e-9); // center
+/// ```
+#[derive(Clone, Debug)]
+pub struct FixedPanner<const MAX_SPK: usize, const MAX_TUPLES: usize> {
+    num_speakers: usize,
+    tuples: [SpeakerTuple; MAX_TUPLES],
+    num_tuples: usize,
+    normalization_floor: f64,
+    convention: Convention,
+    /// Speakers whose gain is discarded.
+    virtual_speakers: [bool; MAX_SPK],
+    /// End speakers of an open arc with their horizontal directions.
+    arc_ends: Option<[(usize, DVec2); 2]>,
+}
+
+impl<const MAX_SPK: usize, const MAX_TUPLES: usize> FixedPanner<MAX_SPK, MAX_TUPLES> {
+    /// Copy a speaker configuration into fixed-capacity storage.
+    ///
+    /// Returns an error if the layout has more speakers or tuples than the
+    /// capacity allows.
+    pub fn from_config(config: &SpeakerConfig) -> Result<Self> {
+        if config.num_speakers() > MAX_SPK {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "layout has {} speakers, capacity is {}",
+                config.num_speakers(),
+                MAX_SPK
+            )));
+        }
+        if config.tuples().len() > MAX_TUPLES {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "layout has {} speaker tuples, capacity is {}",
+                config.tuples().len(),
+                MAX_TUPLES
+            )));
+        }
+
+        let mut tuples = [SpeakerTuple::EMPTY; MAX_TUPLES];
+        for (fixed, tuple) in tuples.iter_mut().zip(config.tuples().iter()) {
+            *fixed = tuple;
+        }
+
+        let speakers = config.speakers();
+        let mut virtual_speakers = [false; MAX_SPK];
+        for (flag, speaker) in virtual_speakers.iter_mut().zip(speakers) {
+            *flag = speaker.is_virtual();
+        }
+        let arc_ends = config
+            .arc_ends()
+            .map(|ends| ends.map(|i| (i, horizontal(speakers[i].cartesian()))));
+
+        Ok(Self {
+            num_speakers: config.num_speakers(),
+            tuples,
+            num_tuples: config.tuples().len(),
+            normalization_floor: config.tolerances().normalization_floor,
+            convention: config.convention(),
+            virtual_speakers,
+            arc_ends,
+        })
+    }
+
+    /// Get the number of speakers in use (at most `MAX_SPK`).
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.num_speakers
+    }
+
+    /// Get the number of speaker tuples in use (at most `MAX_TUPLES`).
+    #[inline]
+    pub fn num_tuples(&self) -> usize {
+        self.num_tuples
+    }
+
+    /// Compute speaker gains for a source direction, in the layout's
+    /// [`convention`](SpeakerConfig::convention).
+    ///
+    /// Entries past [`num_speakers`](Self::num_speakers) and of virtual
+    /// speakers are zero. Gains are
+    /// power-normalized as with [`VBAPanner::compute_gains`](crate::VBAPanner::compute_gains).
+    pub fn compute_gains(&self, azimuth: f64, elevation: f64) -> [f64; MAX_SPK] {
+        let mut gains = [0.0; MAX_SPK];
+        let (azimuth, elevation) = self.convention.to_native(azimuth, elevation);
+        let direction = spherical_to_cartesian(azimuth, elevation);
+
+        let mut best: Option<(usize, [f64; 3], f64)> = None;
+        for (tuple_idx, tuple) in self.tuples[..self.num_tuples].iter().enumerate() {
+            let (raw, len) = tuple.raw_gains(direction);
+            let min = raw[..len].iter().copied().fold(f64::INFINITY, f64::min);
+            if best.map_or(true, |(_, _, best_min)| min > best_min + TIE_TOLERANCE) {
+                best = Some((tuple_idx, raw, min));
+            }
+        }
+
+        let Some((tuple_idx, raw, min)) = best else {
+            return gains;
+        };
+
+        if let Some(ends) = self.arc_ends {
+            if min < -ARC_EDGE_TOLERANCE {
+                let dir = horizontal(direction);
+                let nearest = if dir.dot(ends[0].1) >= dir.dot(ends[1].1) {
+                    ends[0].0
+                } else {
+                    ends[1].0
+                };
+                if !self.virtual_speakers[nearest] {
+                    gains[nearest] = 1.0;
+                }
+                return gains;
+            }
+        }
+
+        let speaker_indices = self.tuples[tuple_idx].speaker_indices();
+        let raw = &raw[..speaker_indices.len()];
+        let level = raw.iter().map(|g| g * g).sum::<f64>().sqrt();
+        let norm = if level > self.normalization_floor {
+            1.0 / level
+        } else {
+            0.0
+        };
+        for (&speaker_idx, &gain) in speaker_indices.iter().zip(raw) {
+            if !self.virtual_speakers[speaker_idx] {
+                gains[speaker_idx] = (gain * norm).max(0.0);
+            }
+        }
+        gains
+    }
+}
+
+fn horizontal(v: DVec3) -> DVec2 {
+    DVec2::new(v.x, v.y).normalize_or_zero()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpeakerConfigBuilder;
+    use crate::VBAPanner;
+    use approx::assert_relative_eq;
+
+    fn assert_matches_panner<const N: usize, const T: usize>(builder: SpeakerConfigBuilder) {
+        let config = builder.build_config().unwrap();
+        let fixed = FixedPanner::<N, T>::from_config(&config).unwrap();
+        let panner = VBAPanner::new(config);
+
+        for i in 0..72 {
+            let (azi, ele) = (i as f64 * 5.0 - 180.0, (i % 5) as f64 * 15.0);
+            let gains = fixed.compute_gains(azi, ele);
+            let expected = panner.compute_gains(azi, ele);
+            for (a, b) in gains.iter().zip(&expected) {
+                assert_relative_eq!(a, b, epsilon = 1e-12);
+            }
+            assert!(gains[expected.len()..].iter().all(|&g| g == 0.0));
+        }
+    }
+
+    #[test]
+    fn test_matches_panner() {
+        assert_matches_panner::<8, 8>(SpeakerConfigBuilder::new().surround_7_1());
+        assert_matches_panner::<16, 32>(SpeakerConfigBuilder::new().atmos_7_1_4());
+        assert_matches_panner::<4, 4>(SpeakerConfigBuilder::new().lcr().open_arc());
+    }
+
+    #[test]
+    fn test_capacity_exceeded() {
+        let config = SpeakerConfigBuilder::new()
+            .surround_7_1()
+            .build_config()
+            .unwrap();
+        assert!(FixedPanner::<4, 8>::from_config(&config).is_err());
+        assert!(FixedPanner::<8, 4>::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,409 @@
+//! Audio Definition Model (ADM, ITU-R BS.2076) object rendering.
+//!
+//! ADM describes a moving object as a sequence of `audioBlockFormat`s inside
+//! an `audioChannelFormat` of type `Objects`. Each block starts at `rtime`,
+//! lasts `duration`, and holds the position the object moves to:
+//!
+//! ```text
+//! <audioChannelFormat audioChannelFormatID="AC_00031001" typeDefinition="Objects">
+//!   <audioBlockFormat rtime="00:00:00.00000" duration="00:00:01.00000">
+//!     <position coordinate="azimuth">30.0</position>
+//!     <position coordinate="elevation">0.0</position>
+//!   </audioBlockFormat>
+//!   ...
+//! </audioChannelFormat>
+//! ```
+//!
+//! As in the ITU-R BS.2127 renderer, gains are computed for each block's
+//! position and interpolated linearly from the previous block's gains over
+//! the block (or over `interpolationLength` when `jumpPosition` is set).
+//! ADM polar coordinates use the crate's convention; Cartesian positions
+//! (x right, y front, z up) are converted. Block `gain`s are applied, and
+//! the `width` and `height` of polar blocks are rendered as an
+//! [`Extent`]; Cartesian extents, `depth`, divergence and screen locking
+//! are ignored.
+
+use roxmltree::{Document, Node};
+
+use crate::error::{Result, VBAPError};
+use crate::extent::Extent;
+use crate::math::cartesian_to_spherical;
+use crate::panner::VBAPanner;
+use glam::DVec3;
+
+/// One `audioBlockFormat` of an object.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdmBlock {
+    /// Start time in seconds.
+    pub start: f64,
+    /// Duration in seconds, `None` for a static block lasting forever.
+    pub duration: Option<f64>,
+    /// Target azimuth in degrees.
+    pub azimuth: f64,
+    /// Target elevation in degrees.
+    pub elevation: f64,
+    /// Target distance, normalized (1 = speaker distance).
+    pub distance: f64,
+    /// Linear gain.
+    pub gain: f64,
+    /// Width and height, a point unless the block gives them.
+    pub extent: Extent,
+    /// Time in seconds to move from the previous position; the block's
+    /// duration unless `jumpPosition` is set.
+    pub interpolation: f64,
+}
+
+/// Position metadata of one ADM object channel.
+///
+/// # Example
+///
+/// ```
+/// use vbap::formats::adm::AdmObject;
+/// use vbap::VBAPanner;
+///
+/// let xml = r#"<audioFormatExtended>
+///   <audioChannelFormat audioChannelFormatID="AC_00031001" typeDefinition="Objects">
+///     <audioBlockFormat rtime="00:00:00.00000" duration="00:00:01.00000">
+///       <position coordinate="azimuth">30.0</position>
+///       <position coordinate="elevation">0.0</position>
+///     </audioBlockFormat>
+///     <audioBlockFormat rtime="00:00:01.00000" duration="00:00:02.00000">
+///       <position coordinate="azimuth">-30.0</position>
+///       <position coordinate="elevation">0.0</position>
+///     </audioBlockFormat>
+///   </audioChannelFormat>
+/// </audioFormatExtended>"#;
+///
+/// let objects = AdmObject::parse_all(xml).unwrap();
+/// let panner = VBAPanner::builder().stereo().build().unwrap();
+/// let mut gains = [0.0; 2];
+///
+/// objects[0].gains_at(&panner, 0.5, &mut gains);
+/// assert_eq!(gains, [1.0, 0.0]);
+/// // Halfway through the second block: halfway between left and right
+/// objects[0].gains_at(&panner, 2.0, &mut gains);
+/// assert!((gains[0] - 0.5).abs() < 1e-9 && (gains[1] - 0.5).abs() < 1e-9);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdmObject {
+    /// The `audioChannelFormatID`.
+    pub id: String,
+    /// The `audioChannelFormatName`.
+    pub name: String,
+    /// Blocks sorted by start time.
+    pub blocks: Vec<AdmBlock>,
+}
+
+impl AdmObject {
+    /// Parse every object channel format of an ADM document.
+    ///
+    /// Accepts a full BWF `axml` chunk or any fragment containing
+    /// `audioChannelFormat` elements; those of other types are skipped.
+    /// Returns [`VBAPError::Parse`] if the XML is malformed, an object has
+    /// no blocks, or a block has an invalid time or position.
+    pub fn parse_all(xml: &str) -> Result<Vec<AdmObject>> {
+        let document = Document::parse(xml).map_err(|e| VBAPError::Parse(e.to_string()))?;
+        document
+            .descendants()
+            .filter(|n| {
+                n.has_tag_name("audioChannelFormat")
+                    && n.attribute("typeDefinition")
+                        .map_or(true, |t| t == "Objects")
+                    && n.attribute("typeLabel").map_or(true, |t| t == "0003")
+            })
+            .map(parse_object)
+            .collect()
+    }
+
+    /// Get the time the last block ends, `None` if it never does.
+    pub fn end_time(&self) -> Option<f64> {
+        let last = self.blocks.last()?;
+        last.duration.map(|duration| last.start + duration)
+    }
+
+    /// Compute the object's speaker gains at `time` seconds.
+    ///
+    /// Before the first block the first block's gains apply, after the last
+    /// its gains are held.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < panner.num_speakers()`.
+    pub fn gains_at(&self, panner: &VBAPanner, time: f64, gains: &mut [f64]) {
+        let n = panner.num_speakers();
+        let index = self
+            .blocks
+            .partition_point(|block| block.start <= time)
+            .saturating_sub(1);
+        let block = &self.blocks[index];
+        block_gains(panner, block, gains);
+
+        let progress = if block.interpolation > 0.0 {
+            ((time - block.start) / block.interpolation).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        if index == 0 || progress >= 1.0 {
+            return;
+        }
+
+        let mut previous = vec![0.0; n];
+        block_gains(panner, &self.blocks[index - 1], &mut previous);
+        for (gain, from) in gains[..n].iter_mut().zip(previous) {
+            *gain = from + (*gain - from) * progress;
+        }
+    }
+
+    /// Render gain automation at `rate` points per second from `start` to
+    /// `end` seconds.
+    ///
+    /// Returns one row of `panner.num_speakers()` gains per point, row-major,
+    /// for times `start + i / rate` before `end`.
+    pub fn render(&self, panner: &VBAPanner, start: f64, end: f64, rate: f64) -> Vec<f64> {
+        let n = panner.num_speakers();
+        let points = ((end - start) * rate).ceil().max(0.0) as usize;
+        let mut automation = vec![0.0; points * n];
+        for (i, row) in automation.chunks_exact_mut(n).enumerate() {
+            self.gains_at(panner, start + i as f64 / rate, row);
+        }
+        automation
+    }
+}
+
+/// Gains of a block's target position.
+fn block_gains(panner: &VBAPanner, block: &AdmBlock, gains: &mut [f64]) {
+    panner.compute_gains_with_extent(block.azimuth, block.elevation, block.extent, gains);
+    if block.gain != 1.0 {
+        for gain in &mut gains[..panner.num_speakers()] {
+            *gain *= block.gain;
+        }
+    }
+}
+
+fn parse_object(channel: Node) -> Result<AdmObject> {
+    let id = channel
+        .attribute("audioChannelFormatID")
+        .unwrap_or_default()
+        .to_owned();
+    let mut blocks = channel
+        .children()
+        .filter(|n| n.has_tag_name("audioBlockFormat"))
+        .map(parse_block)
+        .collect::<Result<Vec<_>>>()?;
+    if blocks.is_empty() {
+        return Err(VBAPError::Parse(format!("object {} has no blocks", id)));
+    }
+    blocks.sort_by(|a, b| a.start.total_cmp(&b.start));
+
+    Ok(AdmObject {
+        name: channel
+            .attribute("audioChannelFormatName")
+            .unwrap_or_default()
+            .to_owned(),
+        id,
+        blocks,
+    })
+}
+
+fn parse_block(block: Node) -> Result<AdmBlock> {
+    let start = block.attribute("rtime").map(parse_time).transpose()?;
+    let duration = block.attribute("duration").map(parse_time).transpose()?;
+    let child = |name: &str| block.children().find(|n| n.has_tag_name(name));
+    let text_number = |node: Node| -> Result<f64> {
+        let text = node.text().unwrap_or_default().trim();
+        text.parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| {
+                VBAPError::Parse(format!(
+                    "invalid {} value '{}'",
+                    node.tag_name().name(),
+                    text
+                ))
+            })
+    };
+    let coordinate = |name: &str| -> Result<Option<f64>> {
+        block
+            .children()
+            .find(|n| n.has_tag_name("position") && n.attribute("coordinate") == Some(name))
+            .map(text_number)
+            .transpose()
+    };
+
+    let cartesian = match child("cartesian") {
+        Some(node) => text_number(node)? != 0.0,
+        None => false,
+    };
+    let (azimuth, elevation, distance) = if cartesian {
+        let (x, y, z) = (
+            coordinate("X")?.unwrap_or(0.0),
+            coordinate("Y")?.unwrap_or(0.0),
+            coordinate("Z")?.unwrap_or(0.0),
+        );
+        // ADM x points right, the crate's x left
+        let position = DVec3::new(-x, y, z);
+        let (azimuth, elevation) = cartesian_to_spherical(position);
+        (azimuth, elevation, position.length())
+    } else {
+        let missing = |name: &str| VBAPError::Parse(format!("block has no {}", name));
+        (
+            coordinate("azimuth")?.ok_or_else(|| missing("azimuth"))?,
+            coordinate("elevation")?.ok_or_else(|| missing("elevation"))?,
+            coordinate("distance")?.unwrap_or(1.0),
+        )
+    };
+
+    // Out-of-range sizes are clamped rather than rejected
+    let extent = if cartesian {
+        Extent::default()
+    } else {
+        let size = |name: &str, max: f64| -> Result<f64> {
+            Ok(child(name)
+                .map(text_number)
+                .transpose()?
+                .unwrap_or(0.0)
+                .clamp(0.0, max))
+        };
+        Extent::new(size("width", 360.0)?, size("height", 180.0)?)?
+    };
+
+    let jump = child("jumpPosition");
+    let interpolation = match jump {
+        Some(node) if text_number(node)? != 0.0 => node
+            .attribute("interpolationLength")
+            .map(parse_time)
+            .transpose()?
+            .unwrap_or(0.0),
+        _ => duration.unwrap_or(0.0),
+    };
+
+    Ok(AdmBlock {
+        start: start.unwrap_or(0.0),
+        duration,
+        azimuth,
+        elevation,
+        distance,
+        gain: child("gain").map(text_number).transpose()?.unwrap_or(1.0),
+        extent,
+        interpolation,
+    })
+}
+
+/// Parse an ADM time, `hh:mm:ss.fffff` or `hh:mm:ss.sssssSrate` (samples at
+/// a sample rate), into seconds. Plain seconds are accepted too.
+fn parse_time(text: &str) -> Result<f64> {
+    let invalid = || VBAPError::Parse(format!("invalid time '{}'", text));
+    let number = |s: &str| s.parse::<f64>().ok().filter(|v| v.is_finite() && *v >= 0.0);
+
+    let (clock, samples) = match text.trim().split_once('S') {
+        Some((clock, rate)) => {
+            let (whole, samples) = clock.rsplit_once('.').ok_or_else(invalid)?;
+            let rate = number(rate).filter(|&r| r > 0.0).ok_or_else(invalid)?;
+            (whole, number(samples).ok_or_else(invalid)? / rate)
+        }
+        None => (text.trim(), 0.0),
+    };
+
+    let mut seconds = 0.0;
+    for field in clock.split(':') {
+        seconds = seconds * 60.0 + number(field).ok_or_else(invalid)?;
+    }
+    Ok(seconds + samples)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_times() {
+        assert_relative_eq!(parse_time("00:00:01.50000").unwrap(), 1.5);
+        assert_relative_eq!(parse_time("01:02:03.25").unwrap(), 3723.25);
+        assert_relative_eq!(parse_time("00:00:02.24000S48000").unwrap(), 2.5);
+        assert_relative_eq!(parse_time("4.5").unwrap(), 4.5);
+        assert!(parse_time("00:aa:01.0").is_err());
+        assert!(parse_time("00:00:01S48000").is_err());
+    }
+
+    #[test]
+    fn test_jump_and_cartesian_blocks() {
+        let xml = r#"<audioFormatExtended>
+          <audioChannelFormat audioChannelFormatID="AC_00011001" typeDefinition="DirectSpeakers"/>
+          <audioChannelFormat audioChannelFormatID="AC_00031002" audioChannelFormatName="Fly"
+                              typeDefinition="Objects">
+            <audioBlockFormat rtime="00:00:02.00000" duration="00:00:02.00000">
+              <jumpPosition interpolationLength="0.5">1</jumpPosition>
+              <position coordinate="azimuth">-110.0</position>
+              <position coordinate="elevation">0.0</position>
+              <gain>0.5</gain>
+            </audioBlockFormat>
+            <audioBlockFormat rtime="00:00:00.00000" duration="00:00:02.00000">
+              <cartesian>1</cartesian>
+              <position coordinate="X">-1.0</position>
+              <position coordinate="Y">1.0</position>
+              <position coordinate="Z">0.0</position>
+            </audioBlockFormat>
+          </audioChannelFormat>
+        </audioFormatExtended>"#;
+        let objects = AdmObject::parse_all(xml).unwrap();
+        assert_eq!(objects.len(), 1);
+        let object = &objects[0];
+        assert_eq!(object.name, "Fly");
+        assert_eq!(object.end_time(), Some(4.0));
+
+        // Sorted; Cartesian front left is 45° to the left
+        assert_relative_eq!(object.blocks[0].azimuth, 45.0, epsilon = 1e-9);
+        assert_relative_eq!(object.blocks[0].distance, 2f64.sqrt(), epsilon = 1e-9);
+        assert_eq!(object.blocks[1].interpolation, 0.5);
+
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let automation = object.render(&panner, 0.0, 5.0, 4.0);
+        assert_eq!(automation.len(), 20 * 5);
+        let row = |i: usize| &automation[i * 5..(i + 1) * 5];
+        assert_eq!(row(0), panner.compute_gains(45.0, 0.0).as_slice());
+        // Within the jump: halfway between the two positions' gains
+        let expected = 0.5 * panner.compute_gains(45.0, 0.0)[0];
+        assert_relative_eq!(row(9)[0], expected, epsilon = 1e-9);
+        // After the jump and past the end: Rs at half gain
+        assert_relative_eq!(row(12)[4], 0.5, epsilon = 1e-9);
+        assert_relative_eq!(row(19)[4], 0.5, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_extent_blocks() {
+        let xml = r#"<audioChannelFormat typeDefinition="Objects">
+            <audioBlockFormat>
+              <position coordinate="azimuth">0.0</position>
+              <position coordinate="elevation">0.0</position>
+              <width>400</width>
+              <height>20</height>
+            </audioBlockFormat>
+          </audioChannelFormat>"#;
+        let object = &AdmObject::parse_all(xml).unwrap()[0];
+        assert_eq!(object.blocks[0].extent, Extent::new(360.0, 20.0).unwrap());
+
+        // A full-circle source plays from every speaker
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let mut gains = [0.0; 5];
+        object.gains_at(&panner, 0.0, &mut gains);
+        assert!(gains.iter().all(|&g| g > 0.0));
+    }
+
+    #[test]
+    fn test_malformed_objects() {
+        for xml in [
+            "<audioChannelFormat typeDefinition=\"Objects\"/>",
+            r#"<audioChannelFormat typeDefinition="Objects"><audioBlockFormat>
+                <position coordinate="azimuth">0</position></audioBlockFormat></audioChannelFormat>"#,
+            r#"<audioChannelFormat typeDefinition="Objects"><audioBlockFormat rtime="soon">
+                <position coordinate="azimuth">0</position>
+                <position coordinate="elevation">0</position></audioBlockFormat></audioChannelFormat>"#,
+        ] {
+            assert!(
+                matches!(AdmObject::parse_all(xml), Err(VBAPError::Parse(_))),
+                "{}",
+                xml
+            );
+        }
+    }
+}
@@ -0,0 +1,323 @@
+//! IEM Plug-in Suite loudspeaker layouts.
+//!
+//! The AllRADecoder, SimpleDecoder and EnergyVisualizer of the IEM Plug-in
+//! Suite exchange layouts as JSON:
+//!
+//! ```text
+//! {
+//!   "Name": "Studio",
+//!   "Description": "...",
+//!   "LoudspeakerLayout": {
+//!     "Name": "Studio",
+//!     "Loudspeakers": [
+//!       { "Azimuth": 30.0, "Elevation": 0.0, "Radius": 1.0,
+//!         "IsImaginary": false, "Channel": 1, "Gain": 1.0 },
+//!       ...
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! IEM uses the crate's own angle convention. Imaginary loudspeakers become
+//! virtual speakers, and `Channel` (1-based) routes each real loudspeaker to
+//! an output channel.
+
+use serde_json::{json, Map, Value};
+
+use crate::config::{SpeakerConfig, SpeakerConfigBuilder};
+use crate::error::{Result, VBAPError};
+use crate::speaker::Speaker;
+
+/// One loudspeaker of an [`IemLayout`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct IemLoudspeaker {
+    /// Azimuth in degrees.
+    pub azimuth: f64,
+    /// Elevation in degrees.
+    pub elevation: f64,
+    /// Distance in meters.
+    pub radius: f64,
+    /// Imaginary loudspeaker that only helps the triangulation.
+    pub imaginary: bool,
+    /// Output channel, 1-based. Ignored for imaginary loudspeakers.
+    pub channel: usize,
+    /// Linear gain trim.
+    pub gain: f64,
+}
+
+/// A loudspeaker layout in the IEM Plug-in Suite's JSON format.
+///
+/// # Example
+///
+/// ```
+/// use vbap::formats::iem::IemLayout;
+/// use vbap::VBAPanner;
+///
+/// let json = r#"{
+///     "Name": "Quad",
+///     "LoudspeakerLayout": {
+///         "Loudspeakers": [
+///             { "Azimuth": 45, "Elevation": 0, "IsImaginary": false, "Channel": 2 },
+///             { "Azimuth": -45, "Elevation": 0, "IsImaginary": false, "Channel": 1 },
+///             { "Azimuth": 135, "Elevation": 0, "IsImaginary": false, "Channel": 4 },
+///             { "Azimuth": -135, "Elevation": 0, "IsImaginary": false, "Channel": 3 }
+///         ]
+///     }
+/// }"#;
+/// let layout = IemLayout::from_json(json).unwrap();
+/// let panner = layout.builder().build().unwrap();
+///
+/// // Speaker 0 (front left) feeds output 1 (0-based)
+/// assert_eq!(layout.channels(), [Some(1), Some(0), Some(3), Some(2)]);
+/// assert!(panner.compute_gains(45.0, 0.0)[0] > 0.99);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IemLayout {
+    /// Layout name.
+    pub name: String,
+    /// Free-form description.
+    pub description: String,
+    /// Loudspeakers in speaker index order.
+    pub loudspeakers: Vec<IemLoudspeaker>,
+}
+
+impl IemLayout {
+    /// Parse an IEM layout JSON document.
+    ///
+    /// `Radius`, `Gain` and `IsImaginary` are optional; a missing `Channel`
+    /// defaults to the loudspeaker's position in the list. Returns
+    /// [`VBAPError::Parse`] for malformed documents, non-numeric angles, or
+    /// two real loudspeakers on the same channel.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let root: Value =
+            serde_json::from_str(json).map_err(|e| VBAPError::Parse(e.to_string()))?;
+        let layout = root
+            .get("LoudspeakerLayout")
+            .ok_or_else(|| VBAPError::Parse("missing LoudspeakerLayout".into()))?;
+        let entries = layout
+            .get("Loudspeakers")
+            .and_then(Value::as_array)
+            .ok_or_else(|| VBAPError::Parse("missing Loudspeakers array".into()))?;
+
+        let loudspeakers = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| parse_loudspeaker(index, entry))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut used = Vec::new();
+        for speaker in loudspeakers.iter().filter(|s| !s.imaginary) {
+            if used.contains(&speaker.channel) {
+                return Err(VBAPError::Parse(format!(
+                    "channel {} is used by more than one loudspeaker",
+                    speaker.channel
+                )));
+            }
+            used.push(speaker.channel);
+        }
+
+        let text = |value: Option<&Value>| value.and_then(Value::as_str).map(str::to_owned);
+        Ok(Self {
+            name: text(layout.get("Name"))
+                .or_else(|| text(root.get("Name")))
+                .unwrap_or_default(),
+            description: text(root.get("Description")).unwrap_or_default(),
+            loudspeakers,
+        })
+    }
+
+    /// Describe a layout for export.
+    ///
+    /// Speaker `i` is routed to channel `i + 1` and virtual speakers are
+    /// marked imaginary. Gains are 1.
+    pub fn from_config(config: &SpeakerConfig, name: &str) -> Self {
+        let loudspeakers = config
+            .speakers()
+            .iter()
+            .enumerate()
+            .map(|(index, speaker)| IemLoudspeaker {
+                azimuth: speaker.azimuth(),
+                elevation: speaker.elevation(),
+                radius: speaker.distance(),
+                imaginary: speaker.is_virtual(),
+                channel: index + 1,
+                gain: 1.0,
+            })
+            .collect();
+        Self {
+            name: name.to_owned(),
+            description: String::new(),
+            loudspeakers,
+        }
+    }
+
+    /// Write the layout as IEM JSON.
+    pub fn to_json(&self) -> String {
+        let loudspeakers: Vec<Value> = self
+            .loudspeakers
+            .iter()
+            .map(|s| {
+                json!({
+                    "Azimuth": s.azimuth,
+                    "Elevation": s.elevation,
+                    "Radius": s.radius,
+                    "IsImaginary": s.imaginary,
+                    "Channel": s.channel,
+                    "Gain": s.gain,
+                })
+            })
+            .collect();
+
+        let mut layout = Map::new();
+        layout.insert("Name".into(), Value::from(self.name.as_str()));
+        layout.insert("Loudspeakers".into(), Value::from(loudspeakers));
+        let root = json!({
+            "Name": self.name,
+            "Description": self.description,
+            "LoudspeakerLayout": layout,
+        });
+        serde_json::to_string_pretty(&root).expect("layout JSON is always serializable")
+    }
+
+    /// Create a builder with the layout's speakers, in list order.
+    ///
+    /// Imaginary loudspeakers become virtual speakers and radii become
+    /// speaker distances.
+    pub fn builder(&self) -> SpeakerConfigBuilder {
+        self.loudspeakers
+            .iter()
+            .fold(SpeakerConfigBuilder::new(), |builder, s| {
+                let speaker = if s.imaginary {
+                    Speaker::new_virtual(0, s.azimuth, s.elevation).at_distance(s.radius)
+                } else {
+                    Speaker::with_distance(0, s.azimuth, s.elevation, s.radius)
+                };
+                builder.add(speaker)
+            })
+    }
+
+    /// Get the 0-based output channel of each speaker, `None` for imaginary
+    /// loudspeakers.
+    pub fn channels(&self) -> Vec<Option<usize>> {
+        self.loudspeakers
+            .iter()
+            .map(|s| (!s.imaginary).then(|| s.channel - 1))
+            .collect()
+    }
+
+    /// Get the number of output channels the routing needs.
+    pub fn num_channels(&self) -> usize {
+        self.loudspeakers
+            .iter()
+            .filter(|s| !s.imaginary)
+            .map(|s| s.channel)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+fn parse_loudspeaker(index: usize, entry: &Value) -> Result<IemLoudspeaker> {
+    let number = |key: &str| -> Result<Option<f64>> {
+        match entry.get(key) {
+            None => Ok(None),
+            Some(value) => value
+                .as_f64()
+                .filter(|v| v.is_finite())
+                .map(Some)
+                .ok_or_else(|| {
+                    VBAPError::Parse(format!("loudspeaker {}: {} is not a number", index, key))
+                }),
+        }
+    };
+    let required = |key: &str| {
+        number(key)?
+            .ok_or_else(|| VBAPError::Parse(format!("loudspeaker {}: missing {}", index, key)))
+    };
+
+    let imaginary = match entry.get("IsImaginary") {
+        None => false,
+        Some(value) => value.as_bool().ok_or_else(|| {
+            VBAPError::Parse(format!(
+                "loudspeaker {}: IsImaginary is not a boolean",
+                index
+            ))
+        })?,
+    };
+    let channel = match entry.get("Channel") {
+        None => index + 1,
+        Some(value) => value
+            .as_u64()
+            .filter(|&c| c >= 1)
+            .map(|c| c as usize)
+            .ok_or_else(|| {
+                VBAPError::Parse(format!(
+                    "loudspeaker {}: Channel must be a positive integer",
+                    index
+                ))
+            })?,
+    };
+
+    Ok(IemLoudspeaker {
+        azimuth: required("Azimuth")?,
+        elevation: required("Elevation")?,
+        radius: number("Radius")?.unwrap_or(1.0),
+        imaginary,
+        channel,
+        gain: number("Gain")?.unwrap_or(1.0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VBAPanner;
+
+    #[test]
+    fn test_round_trip_with_imaginary_speaker() {
+        let config = SpeakerConfigBuilder::new()
+            .atmos_5_1_4()
+            .add_speaker_xyz(0.0, -2.0, 0.0)
+            .build_config()
+            .unwrap()
+            .with_suggested_virtual_speakers()
+            .unwrap();
+        let json = IemLayout::from_config(&config, "Dome").to_json();
+        let layout = IemLayout::from_json(&json).unwrap();
+        assert_eq!(layout.name, "Dome");
+        assert_eq!(layout, IemLayout::from_config(&config, "Dome"));
+
+        let rebuilt = layout.builder().build_config().unwrap();
+        for (a, b) in rebuilt.speakers().iter().zip(config.speakers()) {
+            assert_eq!(a.azimuth(), b.azimuth());
+            assert_eq!(a.elevation(), b.elevation());
+            assert_eq!(a.distance(), b.distance());
+            assert_eq!(a.is_virtual(), b.is_virtual());
+        }
+
+        let channels = layout.channels();
+        assert_eq!(channels.last(), Some(&None));
+        assert_eq!(layout.num_channels(), config.num_speakers() - 1);
+        let panner = VBAPanner::new(rebuilt);
+        assert_eq!(panner.compute_gains(0.0, -90.0).last(), Some(&0.0));
+    }
+
+    #[test]
+    fn test_malformed_layouts() {
+        for json in [
+            "not json",
+            r#"{"Name": "x"}"#,
+            r#"{"LoudspeakerLayout": {"Loudspeakers": [{"Azimuth": 0}]}}"#,
+            r#"{"LoudspeakerLayout": {"Loudspeakers": [{"Azimuth": "left", "Elevation": 0}]}}"#,
+            r#"{"LoudspeakerLayout": {"Loudspeakers": [{"Azimuth": 0, "Elevation": 0, "Channel": 0}]}}"#,
+            r#"{"LoudspeakerLayout": {"Loudspeakers": [
+                {"Azimuth": 30, "Elevation": 0, "Channel": 1},
+                {"Azimuth": -30, "Elevation": 0, "Channel": 1}]}}"#,
+        ] {
+            assert!(
+                matches!(IemLayout::from_json(json), Err(VBAPError::Parse(_))),
+                "{}",
+                json
+            );
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Max/MSP and Pd `define_loudspeakers` messages.
+//!
+//! The `vbap` externals for Max/MSP and Pd take their layout as a message:
+//! the dimension (2 or 3) followed by the speaker angles, one azimuth per
+//! speaker in 2D and azimuth/elevation pairs in 3D:
+//!
+//! ```text
+//! define_loudspeakers 2 -30 30 -110 110
+//! define_loudspeakers 3 -45 0 45 0 -135 0 135 0 0 90
+//! ```
+//!
+//! Azimuths are in the [`Max`](Convention::Max) convention (clockwise) and
+//! are converted on import and export.
+
+use crate::config::{Dimension, PanningMode, SpeakerConfig, SpeakerConfigBuilder};
+use crate::convention::Convention;
+use crate::error::{Result, VBAPError};
+
+/// Selector of the message.
+const SELECTOR: &str = "define_loudspeakers";
+
+/// Parse a `define_loudspeakers` message into a builder.
+///
+/// The selector is optional, and a trailing `;` (as in Pd message boxes)
+/// is ignored. The builder's dimension is forced to the message's, and its
+/// speakers are converted to the crate's convention; call
+/// [`convention`](SpeakerConfigBuilder::convention) on the result to also
+/// pan sources with Max azimuths.
+///
+/// Returns [`VBAPError::Parse`] if the message is malformed.
+///
+/// # Example
+///
+/// ```
+/// use vbap::formats::max::parse_define_loudspeakers;
+///
+/// let panner = parse_define_loudspeakers("define_loudspeakers 2 -30 30 -110 110")
+///     .unwrap()
+///     .build()
+///     .unwrap();
+/// // Max azimuths are clockwise: -30 is front left
+/// assert_eq!(panner.speakers()[0].azimuth(), 30.0);
+/// ```
+pub fn parse_define_loudspeakers(message: &str) -> Result<SpeakerConfigBuilder> {
+    let message = message.trim();
+    let message = message.strip_suffix(';').unwrap_or(message);
+    let mut tokens = message.split_whitespace().peekable();
+    if tokens.peek() == Some(&SELECTOR) {
+        tokens.next();
+    }
+
+    let dimension = match tokens.next() {
+        Some("2") => Dimension::Force2D,
+        Some("3") => Dimension::Force3D,
+        Some(other) => {
+            return Err(VBAPError::Parse(format!(
+                "dimension must be 2 or 3, got '{}'",
+                other
+            )))
+        }
+        None => return Err(VBAPError::Parse("empty message".into())),
+    };
+
+    let angles = tokens
+        .map(|token| match token.parse::<f64>() {
+            Ok(angle) if angle.is_finite() => Ok(angle),
+            _ => Err(VBAPError::Parse(format!("invalid angle '{}'", token))),
+        })
+        .collect::<Result<Vec<f64>>>()?;
+
+    let positions: Vec<(f64, f64)> = match dimension {
+        Dimension::Force3D => {
+            if angles.len() % 2 != 0 {
+                return Err(VBAPError::Parse(format!(
+                    "3D layouts need azimuth/elevation pairs, got {} angles",
+                    angles.len()
+                )));
+            }
+            angles
+                .chunks_exact(2)
+                .map(|pair| Convention::Max.to_native(pair[0], pair[1]))
+                .collect()
+        }
+        _ => angles
+            .iter()
+            .map(|&azimuth| Convention::Max.to_native(azimuth, 0.0))
+            .collect(),
+    };
+
+    Ok(SpeakerConfigBuilder::new()
+        .dimension(dimension)
+        .add_speakers(&positions))
+}
+
+/// Write a layout as a `define_loudspeakers` message.
+///
+/// 2D layouts list azimuths only, 3D layouts azimuth/elevation pairs, all
+/// in the Max convention. The Max and Pd objects have no virtual speakers,
+/// so layouts containing them are rejected; export before adding them.
+///
+/// # Example
+///
+/// ```
+/// use vbap::formats::max::to_define_loudspeakers;
+/// use vbap::VBAPanner;
+///
+/// let config = VBAPanner::builder().quad().build_config().unwrap();
+/// assert_eq!(
+///     to_define_loudspeakers(&config).unwrap(),
+///     "define_loudspeakers 2 -45 45 -135 135"
+/// );
+/// ```
+pub fn to_define_loudspeakers(config: &SpeakerConfig) -> Result<String> {
+    if let Some(speaker) = config.speakers().iter().find(|s| s.is_virtual()) {
+        return Err(VBAPError::InvalidConfiguration(format!(
+            "speaker {} is virtual and cannot be exported",
+            speaker.id()
+        )));
+    }
+
+    let mut message = String::from(SELECTOR);
+    match config.mode() {
+        PanningMode::TwoD => {
+            message.push_str(" 2");
+            for speaker in config.speakers() {
+                let (azimuth, _) = Convention::Max.from_native(speaker.azimuth(), 0.0);
+                message.push_str(&format!(" {}", azimuth));
+            }
+        }
+        PanningMode::ThreeD => {
+            message.push_str(" 3");
+            for speaker in config.speakers() {
+                let (azimuth, elevation) =
+                    Convention::Max.from_native(speaker.azimuth(), speaker.elevation());
+                message.push_str(&format!(" {} {}", azimuth, elevation));
+            }
+        }
+    }
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for builder in [
+            SpeakerConfigBuilder::new().surround_7_1(),
+            SpeakerConfigBuilder::new().atmos_7_1_4(),
+        ] {
+            let config = builder.build_config().unwrap();
+            let message = to_define_loudspeakers(&config).unwrap();
+            let parsed = parse_define_loudspeakers(&message)
+                .unwrap()
+                .build_config()
+                .unwrap();
+            assert_eq!(parsed.mode(), config.mode());
+            for (a, b) in parsed.speakers().iter().zip(config.speakers()) {
+                assert_eq!(a.azimuth(), b.azimuth());
+                assert_eq!(a.elevation(), b.elevation());
+            }
+        }
+
+        // Pd syntax, selector omitted
+        let config = parse_define_loudspeakers("3 -45 0 45 0 -135 0 135 0 0 90;")
+            .unwrap()
+            .build_config()
+            .unwrap();
+        assert_eq!(config.num_speakers(), 5);
+        assert_eq!(config.mode(), PanningMode::ThreeD);
+        assert_eq!(config.speakers()[1].azimuth(), -45.0);
+    }
+
+    #[test]
+    fn test_malformed_messages() {
+        for message in [
+            "",
+            "define_loudspeakers",
+            "define_loudspeakers 4 0 90 180",
+            "define_loudspeakers 2 0 ninety 180",
+            "define_loudspeakers 3 0 0 90",
+            "define_loudspeakers 2 0 inf",
+        ] {
+            assert!(
+                matches!(parse_define_loudspeakers(message), Err(VBAPError::Parse(_))),
+                "{:?}",
+                message
+            );
+        }
+
+        let config = SpeakerConfigBuilder::new()
+            .atmos_5_1_4()
+            .build_config()
+            .unwrap()
+            .with_suggested_virtual_speakers()
+            .unwrap();
+        assert!(to_define_loudspeakers(&config).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+//! Import and export of speaker layouts in other tools' formats.
+//!
+//! - [`max`]: the `define_loudspeakers` message of the Max/MSP and Pd
+//!   `vbap` objects.
+//! - [`iem`]: the loudspeaker layout JSON of the IEM Plug-in Suite.
+//! - [`ssr`]: SoundScape Renderer ASDF reproduction setups.
+//! - [`zirkonium`]: ZKM Zirkonium speaker setups.
+//! - [`adm`]: gain automation for Audio Definition Model objects.
+//! - [`spatdif`]: SpatDIF scenes of moving sources.
+//!
+//! Requires the `io` feature.
+
+pub mod adm;
+pub mod iem;
+pub mod max;
+pub mod spatdif;
+pub mod ssr;
+pub mod zirkonium;
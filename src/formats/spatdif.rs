@@ -0,0 +1,572 @@
+//! SpatDIF scene descriptions.
+//!
+//! The Spatial Sound Description Interchange Format stores source positions
+//! over time. In its XML form, `time` elements set the time of the `source`
+//! entries that follow them:
+//!
+//! ```text
+//! <spatdif version="0.4">
+//!   <meta>
+//!     <extensions>interpolation</extensions>
+//!     <ordering>time</ordering>
+//!   </meta>
+//!   <time>0.0</time>
+//!   <source><name>voice</name><position>0.0 1.0 0.0</position></source>
+//!   <time>2.0</time>
+//!   <source><name>voice</name><position units="aed">90.0 0.0 1.0</position></source>
+//! </spatdif>
+//! ```
+//!
+//! Both orderings are read: `time` (times at the top level) and `track`
+//! (`time` and `position` pairs inside each `source`). A position in the
+//! `meta` section is the source's initial position at time 0.
+//!
+//! Positions are Cartesian by default, x to the right, y to the front and z
+//! up, or azimuth, elevation and distance with `units="aed"` (or a trailing
+//! `aed` token). SpatDIF azimuths are clockwise, 90° to the right, and are
+//! converted to the crate's convention. Other descriptors (orientation,
+//! media, ...) are ignored.
+//!
+//! Without the `interpolation` extension, a source jumps to each position
+//! at its time; with it, positions are interpolated linearly between
+//! events.
+
+use std::fmt::Write;
+
+use glam::DVec3;
+use roxmltree::{Document, Node};
+
+use crate::convention::Convention;
+use crate::error::{Result, VBAPError};
+use crate::math::{cartesian_to_spherical, lerp_azimuth, spherical_to_cartesian};
+use crate::panner::VBAPanner;
+#[cfg(feature = "trajectory")]
+use crate::trajectory::{Keyframe, Trajectory};
+
+/// A source position at a point in time, in the crate's convention.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpatdifEvent {
+    /// Time in seconds.
+    pub time: f64,
+    /// Azimuth in degrees.
+    pub azimuth: f64,
+    /// Elevation in degrees.
+    pub elevation: f64,
+    /// Distance in meters.
+    pub distance: f64,
+}
+
+impl SpatdifEvent {
+    /// Create an event.
+    pub fn new(time: f64, azimuth: f64, elevation: f64, distance: f64) -> Self {
+        Self {
+            time,
+            azimuth,
+            elevation,
+            distance,
+        }
+    }
+}
+
+/// A named source and its positions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpatdifSource {
+    /// Name, unique within the scene.
+    pub name: String,
+    /// Events sorted by time.
+    pub events: Vec<SpatdifEvent>,
+}
+
+impl SpatdifSource {
+    /// Create a source without events.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Create a source following a trajectory's keyframes at distance 1.
+    ///
+    /// Only the keyframes are kept; export with
+    /// [`interpolation`](SpatdifScene::interpolation) set so that other
+    /// tools move the source linearly between them.
+    #[cfg(feature = "trajectory")]
+    pub fn from_trajectory(name: impl Into<String>, trajectory: &Trajectory) -> Self {
+        Self {
+            name: name.into(),
+            events: trajectory
+                .keyframes()
+                .iter()
+                .map(|k| SpatdifEvent::new(k.time, k.azimuth, k.elevation, 1.0))
+                .collect(),
+        }
+    }
+}
+
+/// Sources of a SpatDIF scene.
+///
+/// # Example
+///
+/// ```
+/// use vbap::formats::spatdif::SpatdifScene;
+/// use vbap::VBAPanner;
+///
+/// let xml = r#"<spatdif version="0.4">
+///   <meta><ordering>time</ordering></meta>
+///   <time>0.0</time>
+///   <source><name>voice</name><position units="aed">-30.0 0.0 1.0</position></source>
+///   <time>1.0</time>
+///   <source><name>voice</name><position>1.0 1.7320508 0.0</position></source>
+/// </spatdif>"#;
+///
+/// let scene = SpatdifScene::from_xml(xml).unwrap();
+/// let panner = VBAPanner::builder().stereo().build().unwrap();
+/// let mut gains = [0.0; 2];
+///
+/// // SpatDIF's -30° is to the left
+/// scene.gains_at(&panner, 0, 0.5, &mut gains);
+/// assert!((gains[0] - 1.0).abs() < 1e-9);
+/// // 30° to the right from one second
+/// scene.gains_at(&panner, 0, 1.0, &mut gains);
+/// assert!((gains[1] - 1.0).abs() < 1e-6);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SpatdifScene {
+    /// Sources in order of first appearance.
+    pub sources: Vec<SpatdifSource>,
+    /// Whether positions are interpolated between events (the
+    /// `interpolation` extension) instead of jumping.
+    pub interpolation: bool,
+}
+
+impl SpatdifScene {
+    /// Create an empty scene.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set whether positions are interpolated between events.
+    pub fn with_interpolation(mut self, interpolation: bool) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Add a source.
+    pub fn with_source(mut self, source: SpatdifSource) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Parse a SpatDIF XML scene.
+    ///
+    /// Returns [`VBAPError::Parse`] if the XML is malformed, the root is not
+    /// `spatdif`, or a time, name or position is invalid.
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let document = Document::parse(xml).map_err(|e| VBAPError::Parse(e.to_string()))?;
+        let root = document.root_element();
+        if !root.has_tag_name("spatdif") {
+            return Err(VBAPError::Parse(format!(
+                "expected a <spatdif> root, found <{}>",
+                root.tag_name().name()
+            )));
+        }
+
+        let mut scene = SpatdifScene::new();
+        let mut time = 0.0;
+        for node in root.children().filter(Node::is_element) {
+            match node.tag_name().name() {
+                "meta" => {
+                    scene.interpolation = node
+                        .children()
+                        .filter(|n| n.has_tag_name("extensions"))
+                        .flat_map(|n| n.text().unwrap_or_default().split_whitespace())
+                        .any(|extension| extension == "interpolation");
+                    for source in node.children().filter(|n| n.has_tag_name("source")) {
+                        scene.read_source(source, 0.0)?;
+                    }
+                }
+                "time" => time = parse_time(node)?,
+                "source" => scene.read_source(node, time)?,
+                _ => {}
+            }
+        }
+        for source in &mut scene.sources {
+            // Stable, so later events at the same time win
+            source.events.sort_by(|a, b| a.time.total_cmp(&b.time));
+        }
+        Ok(scene)
+    }
+
+    /// Write the scene as SpatDIF 0.4 XML in time ordering, with Cartesian
+    /// positions.
+    pub fn to_xml(&self) -> String {
+        let mut events: Vec<(&SpatdifSource, &SpatdifEvent)> = self
+            .sources
+            .iter()
+            .flat_map(|source| source.events.iter().map(move |event| (source, event)))
+            .collect();
+        events.sort_by(|a, b| a.1.time.total_cmp(&b.1.time));
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<spatdif version=\"0.4\">\n  <meta>\n");
+        if self.interpolation {
+            xml.push_str("    <extensions>interpolation</extensions>\n");
+        }
+        xml.push_str("    <ordering>time</ordering>\n  </meta>\n");
+        let mut time = None;
+        for (source, event) in events {
+            if time != Some(event.time) {
+                let _ = writeln!(xml, "  <time>{}</time>", event.time);
+                time = Some(event.time);
+            }
+            // SpatDIF's x points right, the crate's to the left
+            let position = spherical_to_cartesian(event.azimuth, event.elevation) * event.distance;
+            let _ = writeln!(
+                xml,
+                "  <source><name>{}</name><position>{} {} {}</position></source>",
+                escape(&source.name),
+                -position.x,
+                position.y,
+                position.z
+            );
+        }
+        xml.push_str("</spatdif>\n");
+        xml
+    }
+
+    /// Get a source by name.
+    pub fn source(&self, name: &str) -> Option<&SpatdifSource> {
+        self.sources.iter().find(|source| source.name == name)
+    }
+
+    /// Get the time of the last event in seconds, 0 without events.
+    pub fn end_time(&self) -> f64 {
+        self.sources
+            .iter()
+            .filter_map(|source| source.events.last())
+            .map(|event| event.time)
+            .fold(0.0, f64::max)
+    }
+
+    /// Get the position of source `source` at `time` seconds, `None` if it
+    /// has no events.
+    ///
+    /// Before the first event the source is at its first position, after
+    /// the last it stays at its last.
+    ///
+    /// # Panics
+    /// Panics if `source` is out of range.
+    pub fn position_at(&self, source: usize, time: f64) -> Option<SpatdifEvent> {
+        let events = &self.sources[source].events;
+        let next = events.partition_point(|event| event.time <= time);
+        if next == 0 {
+            return events.first().copied();
+        }
+        let (a, b) = (events[next - 1], events.get(next));
+        match b {
+            Some(b) if self.interpolation && b.time > a.time => {
+                let frac = (time - a.time) / (b.time - a.time);
+                Some(SpatdifEvent {
+                    time,
+                    azimuth: lerp_azimuth(a.azimuth, b.azimuth, frac),
+                    elevation: a.elevation + (b.elevation - a.elevation) * frac,
+                    distance: a.distance + (b.distance - a.distance) * frac,
+                })
+            }
+            _ => Some(SpatdifEvent { time, ..a }),
+        }
+    }
+
+    /// Compute the speaker gains of source `source` at `time` seconds.
+    ///
+    /// A source without events is silent.
+    ///
+    /// # Panics
+    /// Panics if `source` is out of range or
+    /// `gains.len() < panner.num_speakers()`.
+    pub fn gains_at(&self, panner: &VBAPanner, source: usize, time: f64, gains: &mut [f64]) {
+        match self.position_at(source, time) {
+            Some(event) => panner.compute_gains_into(event.azimuth, event.elevation, gains),
+            None => gains[..panner.num_speakers()].fill(0.0),
+        }
+    }
+
+    /// Get the movement of source `source` as a trajectory, `None` if it
+    /// has no events.
+    ///
+    /// Without [`interpolation`](Self::interpolation), each event is
+    /// preceded by a keyframe holding the previous position, so the
+    /// trajectory jumps.
+    ///
+    /// # Panics
+    /// Panics if `source` is out of range.
+    #[cfg(feature = "trajectory")]
+    pub fn trajectory(&self, source: usize) -> Option<Trajectory> {
+        let events = &self.sources[source].events;
+        let mut keyframes: Vec<Keyframe> = Vec::with_capacity(events.len() * 2);
+        for event in events {
+            if let Some(&previous) = keyframes.last().filter(|_| !self.interpolation) {
+                keyframes.push(Keyframe {
+                    time: event.time,
+                    ..previous
+                });
+            }
+            keyframes.push(Keyframe::new(event.time, event.azimuth, event.elevation));
+        }
+        Trajectory::new(keyframes).ok()
+    }
+
+    /// Create a mixer playing the scene through a layout, with one source
+    /// per scene source following its [`trajectory`](Self::trajectory),
+    /// converted to the layout's convention.
+    ///
+    /// Render it with `Mixer::render`, one input per scene source. Sources
+    /// stay at their first event's distance.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::formats::spatdif::{SpatdifEvent, SpatdifScene, SpatdifSource};
+    /// use vbap::VBAPanner;
+    ///
+    /// let mut voice = SpatdifSource::new("voice");
+    /// voice.events.push(SpatdifEvent::new(0.0, 30.0, 0.0, 1.0));
+    /// let scene = SpatdifScene::new().with_source(voice);
+    ///
+    /// let panner = VBAPanner::builder().stereo().build().unwrap();
+    /// let mut mixer = scene.mixer(panner);
+    /// let outputs = mixer.render(&[&[1.0; 512]], 48000.0, 256);
+    /// assert_eq!(outputs[0][511], 1.0);
+    /// ```
+    #[cfg(all(feature = "render", feature = "trajectory"))]
+    pub fn mixer(&self, panner: VBAPanner) -> crate::mixer::Mixer {
+        let convention = panner.config().convention();
+        let mut mixer = crate::mixer::Mixer::new(panner);
+        for (index, source) in self.sources.iter().enumerate() {
+            let id = mixer.add_source();
+            let Some(trajectory) = self.trajectory(index) else {
+                continue;
+            };
+            let keyframes = trajectory
+                .keyframes()
+                .iter()
+                .map(|k| {
+                    let (azimuth, elevation) = convention.from_native(k.azimuth, k.elevation);
+                    Keyframe::new(k.time, azimuth, elevation)
+                })
+                .collect();
+            if let Ok(trajectory) = Trajectory::new(keyframes) {
+                mixer.set_trajectory(id, trajectory);
+            }
+            mixer.set_distance(id, source.events[0].distance);
+        }
+        mixer
+    }
+
+    /// Record the positions of a `source` element at `time`.
+    fn read_source(&mut self, node: Node, mut time: f64) -> Result<()> {
+        let name = node
+            .children()
+            .find(|n| n.has_tag_name("name"))
+            .and_then(|n| n.text())
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| VBAPError::Parse("source has no name".into()))?;
+        let index = match self.sources.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                self.sources.push(SpatdifSource::new(name));
+                self.sources.len() - 1
+            }
+        };
+        for child in node.children().filter(Node::is_element) {
+            match child.tag_name().name() {
+                "time" => time = parse_time(child)?,
+                "position" => {
+                    let (azimuth, elevation, distance) = parse_position(child)?;
+                    self.sources[index]
+                        .events
+                        .push(SpatdifEvent::new(time, azimuth, elevation, distance));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_time(node: Node) -> Result<f64> {
+    let text = node.text().unwrap_or_default().trim();
+    text.parse::<f64>()
+        .ok()
+        .filter(|t| t.is_finite())
+        .ok_or_else(|| VBAPError::Parse(format!("invalid time '{}'", text)))
+}
+
+/// Parse a position into the crate's azimuth, elevation and distance.
+fn parse_position(node: Node) -> Result<(f64, f64, f64)> {
+    let text = node.text().unwrap_or_default();
+    let invalid = || VBAPError::Parse(format!("invalid position '{}'", text.trim()));
+    let mut fields: Vec<&str> = text.split_whitespace().collect();
+    let mut units = node.attribute("units").unwrap_or("xyz");
+    if let Some(&last) = fields.last().filter(|f| f.parse::<f64>().is_err()) {
+        units = last;
+        fields.pop();
+    }
+    let values: Vec<f64> = fields
+        .iter()
+        .map(|f| f.parse::<f64>().ok().filter(|v| v.is_finite()))
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    match (units, values.as_slice()) {
+        ("xyz", &[x, y, z]) => {
+            let position = DVec3::new(-x, y, z);
+            let (azimuth, elevation) = cartesian_to_spherical(position);
+            Ok((azimuth, elevation, position.length()))
+        }
+        ("aed", &[azimuth, elevation, distance]) => {
+            let (azimuth, elevation) = Convention::Navigational.to_native(azimuth, elevation);
+            Ok((azimuth, elevation, distance))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_track_ordering_and_meta() {
+        let xml = r#"<spatdif version="0.4">
+          <meta>
+            <extensions>media interpolation</extensions>
+            <source><name>bird</name><position>0 2 0</position></source>
+            <ordering>track</ordering>
+          </meta>
+          <source>
+            <name>bird</name>
+            <time>2.0</time><position>-2 0 0 xyz</position>
+            <time>4.0</time><position units="aed">180 0 3</position>
+          </source>
+          <source><name>a &amp; b</name><position units="aed">0 90 1</position></source>
+        </spatdif>"#;
+        let scene = SpatdifScene::from_xml(xml).unwrap();
+        assert!(scene.interpolation);
+        assert_eq!(scene.end_time(), 4.0);
+        let bird = scene.source("bird").unwrap();
+        assert_eq!(bird.events.len(), 3);
+        assert_relative_eq!(bird.events[1].azimuth, 90.0, epsilon = 1e-9);
+        assert_relative_eq!(bird.events[2].azimuth, 180.0, epsilon = 1e-9);
+        assert_eq!(scene.sources[1].name, "a & b");
+        assert_relative_eq!(scene.sources[1].events[0].elevation, 90.0);
+
+        // Interpolated: halfway from front to left
+        let position = scene.position_at(0, 1.0).unwrap();
+        assert_relative_eq!(position.azimuth, 45.0, epsilon = 1e-9);
+        assert_relative_eq!(position.distance, 2.0, epsilon = 1e-9);
+        assert_relative_eq!(scene.position_at(0, 3.0).unwrap().distance, 2.5);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut voice = SpatdifSource::new("voice <1>");
+        voice.events = vec![
+            SpatdifEvent::new(0.0, 30.0, 10.0, 2.0),
+            SpatdifEvent::new(1.5, -110.0, 0.0, 1.0),
+        ];
+        let mut drone = SpatdifSource::new("drone");
+        drone.events = vec![SpatdifEvent::new(1.5, 180.0, 45.0, 5.0)];
+        let scene = SpatdifScene::new().with_source(voice).with_source(drone);
+
+        let xml = scene.to_xml();
+        assert_eq!(xml.matches("<time>1.5</time>").count(), 1);
+        let read = SpatdifScene::from_xml(&xml).unwrap();
+        assert!(!read.interpolation);
+        assert_eq!(read.sources.len(), 2);
+        for (read, source) in read.sources.iter().zip(&scene.sources) {
+            assert_eq!(read.name, source.name);
+            for (a, b) in read.events.iter().zip(&source.events) {
+                assert_eq!(a.time, b.time);
+                assert_relative_eq!(a.azimuth, b.azimuth, epsilon = 1e-9);
+                assert_relative_eq!(a.elevation, b.elevation, epsilon = 1e-9);
+                assert_relative_eq!(a.distance, b.distance, epsilon = 1e-9);
+            }
+        }
+
+        // Without interpolation, positions jump
+        assert_relative_eq!(
+            read.position_at(0, 1.4).unwrap().azimuth,
+            30.0,
+            epsilon = 1e-9
+        );
+    }
+
+    #[test]
+    fn test_malformed_scenes() {
+        for xml in [
+            "<scene/>",
+            "<spatdif><time>soon</time></spatdif>",
+            "<spatdif><source><position>0 1 0</position></source></spatdif>",
+            "<spatdif><source><name>a</name><position>0 1</position></source></spatdif>",
+            "<spatdif><source><name>a</name><position>0 1 0 polar</position></source></spatdif>",
+        ] {
+            assert!(
+                matches!(SpatdifScene::from_xml(xml), Err(VBAPError::Parse(_))),
+                "{}",
+                xml
+            );
+        }
+    }
+
+    #[cfg(feature = "trajectory")]
+    #[test]
+    fn test_trajectory() {
+        let mut source = SpatdifSource::new("a");
+        source.events = vec![
+            SpatdifEvent::new(0.0, 0.0, 0.0, 1.0),
+            SpatdifEvent::new(1.0, 90.0, 0.0, 1.0),
+        ];
+        let scene = SpatdifScene::new().with_source(source);
+        let jumping = scene.trajectory(0).unwrap();
+        assert_eq!(jumping.sample(0.5), (0.0, 0.0));
+        assert_eq!(jumping.sample(1.0), (90.0, 0.0));
+
+        let scene = scene.with_interpolation(true);
+        assert_relative_eq!(scene.trajectory(0).unwrap().sample(0.5).0, 45.0);
+        assert!(SpatdifScene::new()
+            .with_source(SpatdifSource::new("empty"))
+            .trajectory(0)
+            .is_none());
+
+        let exported = SpatdifSource::from_trajectory("b", &scene.trajectory(0).unwrap());
+        assert_eq!(exported.events, scene.sources[0].events);
+    }
+
+    #[cfg(all(feature = "render", feature = "trajectory"))]
+    #[test]
+    fn test_mixer_uses_layout_convention() {
+        let mut source = SpatdifSource::new("a");
+        source.events = vec![SpatdifEvent::new(0.0, 30.0, 0.0, 2.0)];
+        let scene = SpatdifScene::new().with_source(source);
+        let panner = VBAPanner::builder()
+            .convention(Convention::Max)
+            .stereo()
+            .build()
+            .unwrap();
+        let mut mixer = scene.mixer(panner);
+        let outputs = mixer.render(&[&[1.0; 64]], 48000.0, 16);
+        // Max's stereo has the left speaker first too
+        assert_eq!((outputs[0][63], outputs[1][63]), (1.0, 0.0));
+        assert_eq!(mixer.sources()[0].azimuth(), -30.0);
+        assert_eq!(mixer.sources()[0].distance(), 2.0);
+    }
+}
@@ -0,0 +1,316 @@
+//! SoundScape Renderer (SSR) ASDF reproduction setups.
+//!
+//! The SSR describes loudspeaker arrays in the `reproduction_setup` part of
+//! its Audio Scene Description Format:
+//!
+//! ```text
+//! <reproduction_setup>
+//!   <circular_array number="8">
+//!     <first><position x="0" y="2"/></first>
+//!   </circular_array>
+//!   <skip number="2"/>
+//!   <loudspeaker model="subwoofer"><position x="0" y="0"/></loudspeaker>
+//! </reproduction_setup>
+//! ```
+//!
+//! Elements are read in order, and each loudspeaker takes the next output
+//! channel. Supported are single `loudspeaker`s, `circular_array`s (evenly
+//! around the circle from `first`, or spread from `first` to `last`),
+//! `linear_array`s (from `first` in steps of `second`, or spread to
+//! `last`) and `skip` for unused channels, each of at most
+//! [`MAX_SPEAKERS`] channels. Orientations are ignored.
+//!
+//! Positions are in meters in the SSR's frame: x to the right, y to the
+//! front (the default reference orientation of 90°) and z up.
+
+use glam::DVec3;
+use roxmltree::{Document, Node};
+
+use crate::config::{SpeakerConfigBuilder, MAX_SPEAKERS};
+use crate::error::{Result, VBAPError};
+
+/// Loudspeakers of an SSR reproduction setup.
+///
+/// # Example
+///
+/// ```
+/// use vbap::formats::ssr::ReproductionSetup;
+///
+/// let xml = r#"<asdf version="0.1"><reproduction_setup>
+///     <circular_array number="8">
+///         <first><position x="0" y="1.5"/></first>
+///     </circular_array>
+///     <loudspeaker model="subwoofer"><position x="0" y="0.5"/></loudspeaker>
+/// </reproduction_setup></asdf>"#;
+///
+/// let setup = ReproductionSetup::from_xml(xml).unwrap();
+/// assert_eq!(setup.subwoofer_channels(), [8]);
+///
+/// let panner = setup.builder().build().unwrap();
+/// assert_eq!(panner.num_speakers(), 8);
+/// // The array turns counter-clockwise from the front
+/// assert!((panner.speakers()[2].azimuth() - 90.0).abs() < 1e-9);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReproductionSetup {
+    /// Panning speaker positions in the crate's frame, in meters.
+    positions: Vec<DVec3>,
+    /// Output channel of each panning speaker, 0-based.
+    channels: Vec<usize>,
+    /// Output channels of subwoofers, 0-based.
+    subwoofers: Vec<usize>,
+}
+
+impl ReproductionSetup {
+    /// Parse an ASDF document or a bare `reproduction_setup` element.
+    ///
+    /// Returns [`VBAPError::Parse`] if the XML is malformed, has no
+    /// reproduction setup, or an element lacks a required attribute.
+    pub fn from_xml(xml: &str) -> Result<Self> {
+        let document = Document::parse(xml).map_err(|e| VBAPError::Parse(e.to_string()))?;
+        let setup = document
+            .descendants()
+            .find(|n| n.has_tag_name("reproduction_setup"))
+            .ok_or_else(|| VBAPError::Parse("missing reproduction_setup".into()))?;
+
+        let mut parsed = Self::default();
+        let mut channel = 0;
+        for element in setup.children().filter(Node::is_element) {
+            let subwoofer = element.attribute("model") == Some("subwoofer");
+            let positions = match element.tag_name().name() {
+                "loudspeaker" => vec![position_of(element)?],
+                "circular_array" => circular_array(element)?,
+                "linear_array" => linear_array(element)?,
+                "skip" => {
+                    channel += count(element)?;
+                    continue;
+                }
+                _ => continue,
+            };
+            for position in positions {
+                if subwoofer {
+                    parsed.subwoofers.push(channel);
+                } else {
+                    // SSR x points right, the crate's x left
+                    parsed
+                        .positions
+                        .push(DVec3::new(-position.x, position.y, position.z));
+                    parsed.channels.push(channel);
+                }
+                channel += 1;
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Create a builder with the panning speakers, keeping their distances.
+    ///
+    /// Subwoofers are left out; see
+    /// [`subwoofer_channels`](Self::subwoofer_channels).
+    pub fn builder(&self) -> SpeakerConfigBuilder {
+        self.positions
+            .iter()
+            .fold(SpeakerConfigBuilder::new(), |builder, p| {
+                builder.add_speaker_xyz(p.x, p.y, p.z)
+            })
+    }
+
+    /// Get the panning speaker positions in meters, in the crate's frame
+    /// (x left, y front, z up).
+    #[inline]
+    pub fn positions(&self) -> &[DVec3] {
+        &self.positions
+    }
+
+    /// Get the 0-based output channel of each panning speaker.
+    #[inline]
+    pub fn channels(&self) -> &[usize] {
+        &self.channels
+    }
+
+    /// Get the 0-based output channels of the subwoofers.
+    #[inline]
+    pub fn subwoofer_channels(&self) -> &[usize] {
+        &self.subwoofers
+    }
+}
+
+/// Read a required numeric attribute.
+fn number(node: Node, name: &str) -> Result<f64> {
+    let value = node.attribute(name).ok_or_else(|| {
+        VBAPError::Parse(format!(
+            "<{}> is missing the {} attribute",
+            node.tag_name().name(),
+            name
+        ))
+    })?;
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| VBAPError::Parse(format!("invalid {} '{}'", name, value)))
+}
+
+/// Read the `number` attribute of an array or skip, at most
+/// [`MAX_SPEAKERS`].
+fn count(node: Node) -> Result<usize> {
+    let value = number(node, "number")?;
+    if value < 0.0 || value.fract() != 0.0 {
+        return Err(VBAPError::Parse(format!(
+            "<{}> number must be a non-negative integer, got {}",
+            node.tag_name().name(),
+            value
+        )));
+    }
+    if value > MAX_SPEAKERS as f64 {
+        return Err(VBAPError::Parse(format!(
+            "<{}> number {} exceeds the maximum of {}",
+            node.tag_name().name(),
+            value,
+            MAX_SPEAKERS
+        )));
+    }
+    Ok(value as usize)
+}
+
+/// Read the `position` child of `node` in SSR coordinates.
+fn position_of(node: Node) -> Result<DVec3> {
+    let position = node
+        .children()
+        .find(|n| n.has_tag_name("position"))
+        .ok_or_else(|| VBAPError::Parse(format!("<{}> has no position", node.tag_name().name())))?;
+    let z = match position.attribute("z") {
+        Some(_) => number(position, "z")?,
+        None => 0.0,
+    };
+    Ok(DVec3::new(
+        number(position, "x")?,
+        number(position, "y")?,
+        z,
+    ))
+}
+
+/// Read the position of the child element `name`, if present.
+fn array_point(array: Node, name: &str) -> Result<Option<DVec3>> {
+    array
+        .children()
+        .find(|n| n.has_tag_name(name))
+        .map(position_of)
+        .transpose()
+}
+
+fn required_point(array: Node, name: &str) -> Result<DVec3> {
+    array_point(array, name)?
+        .ok_or_else(|| VBAPError::Parse(format!("<{}> has no <{}>", array.tag_name().name(), name)))
+}
+
+fn circular_array(array: Node) -> Result<Vec<DVec3>> {
+    let number = count(array)?;
+    let first = required_point(array, "first")?;
+    let start = first.y.atan2(first.x);
+    let step = match array_point(array, "last")? {
+        Some(last) if number > 1 => (last.y.atan2(last.x) - start) / (number - 1) as f64,
+        _ => std::f64::consts::TAU / number.max(1) as f64,
+    };
+
+    let radius = first.truncate().length();
+    Ok((0..number)
+        .map(|i| {
+            let (sin, cos) = (start + step * i as f64).sin_cos();
+            DVec3::new(radius * cos, radius * sin, first.z)
+        })
+        .collect())
+}
+
+fn linear_array(array: Node) -> Result<Vec<DVec3>> {
+    let number = count(array)?;
+    let first = required_point(array, "first")?;
+    let step = match (array_point(array, "second")?, array_point(array, "last")?) {
+        (Some(second), _) => second - first,
+        (None, Some(last)) if number > 1 => (last - first) / (number - 1) as f64,
+        (None, Some(_)) => DVec3::ZERO,
+        (None, None) => {
+            return Err(VBAPError::Parse(
+                "<linear_array> needs <second> or <last>".into(),
+            ))
+        }
+    };
+    Ok((0..number).map(|i| first + step * i as f64).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_arrays_and_channels() {
+        let xml = r#"<reproduction_setup>
+            <loudspeaker><position x="0" y="2"/></loudspeaker>
+            <skip number="1"/>
+            <circular_array number="3">
+                <first><position x="-2" y="0"/></first>
+                <last><position x="2" y="0"/></last>
+            </circular_array>
+            <linear_array number="3">
+                <first><position x="-1" y="-2" z="0.5"/></first>
+                <second><position x="0" y="-2" z="0.5"/></second>
+            </linear_array>
+            <loudspeaker model="subwoofer"><position x="0" y="1"/></loudspeaker>
+        </reproduction_setup>"#;
+        let setup = ReproductionSetup::from_xml(xml).unwrap();
+
+        assert_eq!(setup.channels(), [0, 2, 3, 4, 5, 6, 7]);
+        assert_eq!(setup.subwoofer_channels(), [8]);
+
+        // Front, then a half circle from left over the front to right
+        let expected = [
+            DVec3::new(0.0, 2.0, 0.0),
+            DVec3::new(2.0, 0.0, 0.0),
+            DVec3::new(0.0, 2.0, 0.0),
+            DVec3::new(-2.0, 0.0, 0.0),
+            DVec3::new(1.0, -2.0, 0.5),
+            DVec3::new(0.0, -2.0, 0.5),
+            DVec3::new(-1.0, -2.0, 0.5),
+        ];
+        for (position, expected) in setup.positions().iter().zip(expected) {
+            assert_relative_eq!(position.x, expected.x, epsilon = 1e-12);
+            assert_relative_eq!(position.y, expected.y, epsilon = 1e-12);
+            assert_relative_eq!(position.z, expected.z, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_malformed_setups() {
+        for xml in [
+            "<reproduction_setup>",
+            "<asdf/>",
+            "<reproduction_setup><loudspeaker/></reproduction_setup>",
+            r#"<reproduction_setup><loudspeaker><position x="1"/></loudspeaker></reproduction_setup>"#,
+            r#"<reproduction_setup><skip number="-1"/></reproduction_setup>"#,
+            // Huge counts are rejected before anything is allocated
+            r#"<reproduction_setup><linear_array number="100000000000000">
+                <first><position x="0" y="1"/></first><second><position x="1" y="1"/></second>
+                </linear_array></reproduction_setup>"#,
+            r#"<reproduction_setup><skip number="1e300"/></reproduction_setup>"#,
+            r#"<reproduction_setup><linear_array number="2">
+                <first><position x="0" y="1"/></first></linear_array></reproduction_setup>"#,
+        ] {
+            assert!(
+                matches!(ReproductionSetup::from_xml(xml), Err(VBAPError::Parse(_))),
+                "{}",
+                xml
+            );
+        }
+        let over = format!(
+            r#"<reproduction_setup><circular_array number="{}">
+                <first><position x="0" y="1"/></first></circular_array></reproduction_setup>"#,
+            MAX_SPEAKERS + 1
+        );
+        assert!(matches!(
+            ReproductionSetup::from_xml(&over),
+            Err(VBAPError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+//! ZKM Zirkonium speaker setups.
+//!
+//! Zirkonium stores speaker setups as XML property lists. Every `dict` with
+//! `Azimuth` and `Zenith` entries is read as one speaker, in document order,
+//! so setups grouped into rings load the same as flat lists. Zirkonium
+//! measures angles in units of π: azimuth 0 is the front and 0.5 the left
+//! (90°), zenith 0 the horizon and 0.5 straight up. An optional `Radius`
+//! becomes the speaker distance.
+//!
+//! ```text
+//! <plist version="1.0"><dict>
+//!   <key>Speakers</key>
+//!   <array>
+//!     <dict>
+//!       <key>Azimuth</key><real>0.25</real>
+//!       <key>Zenith</key><real>0</real>
+//!       <key>Radius</key><real>1</real>
+//!     </dict>
+//!     ...
+//!   </array>
+//! </dict></plist>
+//! ```
+
+use roxmltree::{Document, Node};
+
+use crate::config::SpeakerConfigBuilder;
+use crate::error::{Result, VBAPError};
+use crate::speaker::Speaker;
+
+/// Parse a Zirkonium speaker setup into a builder.
+///
+/// Returns [`VBAPError::Parse`] if the XML is malformed, contains no
+/// speakers, or a speaker entry is not a number.
+///
+/// # Example
+///
+/// ```
+/// use vbap::formats::zirkonium::parse_speaker_setup;
+///
+/// let xml = r#"<plist version="1.0"><dict><key>Speakers</key><array>
+///     <dict><key>Azimuth</key><real>0.1667</real><key>Zenith</key><real>0</real></dict>
+///     <dict><key>Azimuth</key><real>-0.1667</real><key>Zenith</key><real>0</real></dict>
+/// </array></dict></plist>"#;
+///
+/// let panner = parse_speaker_setup(xml).unwrap().build().unwrap();
+/// assert!((panner.speakers()[0].azimuth() - 30.0).abs() < 0.01);
+/// ```
+pub fn parse_speaker_setup(xml: &str) -> Result<SpeakerConfigBuilder> {
+    let document = Document::parse(xml).map_err(|e| VBAPError::Parse(e.to_string()))?;
+
+    let mut builder = SpeakerConfigBuilder::new();
+    let mut found = false;
+    for dict in document.descendants().filter(|n| n.has_tag_name("dict")) {
+        let (Some(azimuth), Some(zenith)) = (entry(dict, "Azimuth")?, entry(dict, "Zenith")?)
+        else {
+            continue;
+        };
+        let radius = entry(dict, "Radius")?.unwrap_or(1.0);
+        builder = builder.add(Speaker::with_distance(
+            0,
+            azimuth * 180.0,
+            zenith * 180.0,
+            radius,
+        ));
+        found = true;
+    }
+
+    if !found {
+        return Err(VBAPError::Parse("no speakers in the setup".into()));
+    }
+    Ok(builder)
+}
+
+/// Read the number stored under `key` directly in `dict`.
+fn entry(dict: Node, key: &str) -> Result<Option<f64>> {
+    let mut children = dict.children().filter(Node::is_element);
+    while let Some(child) = children.next() {
+        if !child.has_tag_name("key") || child.text() != Some(key) {
+            continue;
+        }
+        let value = children
+            .next()
+            .filter(|v| v.has_tag_name("real") || v.has_tag_name("integer"));
+        return value
+            .and_then(|v| v.text())
+            .and_then(|text| text.trim().parse::<f64>().ok())
+            .filter(|v| v.is_finite())
+            .map(Some)
+            .ok_or_else(|| VBAPError::Parse(format!("{} is not a number", key)));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_rings() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <plist version="1.0"><dict>
+            <key>Name</key><string>Dome</string>
+            <key>Rings</key><array>
+                <dict><key>Speakers</key><array>
+                    <dict><key>Azimuth</key><real>0.25</real><key>Zenith</key><integer>0</integer></dict>
+                    <dict><key>Azimuth</key><real>-0.25</real><key>Zenith</key><integer>0</integer></dict>
+                    <dict><key>Azimuth</key><real>0.75</real><key>Zenith</key><integer>0</integer></dict>
+                    <dict><key>Azimuth</key><real>-0.75</real><key>Zenith</key><integer>0</integer></dict>
+                </array></dict>
+                <dict><key>Speakers</key><array>
+                    <dict><key>Azimuth</key><real>0</real><key>Zenith</key><real>0.5</real>
+                        <key>Radius</key><real>2.5</real></dict>
+                </array></dict>
+            </array>
+            </dict></plist>"#;
+        let config = parse_speaker_setup(xml).unwrap().build_config().unwrap();
+        assert_eq!(config.num_speakers(), 5);
+        assert_relative_eq!(config.speakers()[0].azimuth(), 45.0);
+        assert_relative_eq!(config.speakers()[3].azimuth(), -135.0);
+        assert_relative_eq!(config.speakers()[4].elevation(), 90.0);
+        assert_relative_eq!(config.speakers()[4].distance(), 2.5);
+    }
+
+    #[test]
+    fn test_malformed_setups() {
+        for xml in [
+            "<plist>",
+            "<plist><dict><key>Name</key><string>Empty</string></dict></plist>",
+            "<plist><dict><key>Azimuth</key><string>left</string><key>Zenith</key><real>0</real></dict></plist>",
+        ] {
+            assert!(
+                matches!(parse_speaker_setup(xml), Err(VBAPError::Parse(_))),
+                "{}",
+                xml
+            );
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Speaker gains with channel labels.
+//!
+//! [`VBAPanner::gains`](crate::VBAPanner::gains) returns a [`Gains`] instead of a bare `Vec<f64>`:
+//! it knows which speaker each gain belongs to, so application code can
+//! ask for a channel by [`ChannelLabel`], read levels in dB or print the
+//! active speakers for logging.
+
+use std::fmt;
+
+use crate::speaker::{ChannelLabel, Speaker};
+use crate::util::lin_to_db;
+
+/// Gains of one source, one per speaker of a layout.
+///
+/// # Example
+///
+/// ```
+/// use vbap::{ChannelLabel, VBAPanner};
+///
+/// let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+/// let gains = panner.gains(15.0, 0.0);
+///
+/// assert!(gains.by_label(ChannelLabel::L).unwrap() > 0.0);
+/// assert_eq!(gains.by_label(ChannelLabel::Rs), Some(0.0));
+/// assert_eq!(gains.by_label(ChannelLabel::Ltf), None);
+///
+/// for (speaker, gain) in &gains {
+///     println!("{speaker}: {gain:.3}");
+/// }
+/// println!("{gains}");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Gains<'a> {
+    values: Vec<f64>,
+    speakers: &'a [Speaker],
+}
+
+impl<'a> Gains<'a> {
+    /// Pair gains with the speakers they belong to.
+    ///
+    /// # Panics
+    /// Panics if the lengths differ.
+    pub fn new(values: Vec<f64>, speakers: &'a [Speaker]) -> Self {
+        assert_eq!(
+            values.len(),
+            speakers.len(),
+            "one gain per speaker required"
+        );
+        Self { values, speakers }
+    }
+
+    /// Get the linear gains in speaker order.
+    #[inline]
+    pub fn linear(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Take the linear gains.
+    #[inline]
+    pub fn into_linear(self) -> Vec<f64> {
+        self.values
+    }
+
+    /// Get the gains in dB, negative infinity for silent speakers.
+    pub fn db(&self) -> Vec<f64> {
+        self.values.iter().copied().map(lin_to_db).collect()
+    }
+
+    /// Get the gain of the speaker with a channel label, or `None` if no
+    /// speaker has it.
+    pub fn by_label(&self, label: ChannelLabel) -> Option<f64> {
+        self.speakers
+            .iter()
+            .position(|speaker| speaker.label() == Some(label))
+            .map(|index| self.values[index])
+    }
+
+    /// Get the speakers the gains belong to.
+    #[inline]
+    pub fn speakers(&self) -> &'a [Speaker] {
+        self.speakers
+    }
+
+    /// Get the number of gains.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Check whether there are no gains.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Iterate over (speaker index, gain) pairs.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (usize, f64)> + '_ {
+        self.values.iter().copied().enumerate()
+    }
+}
+
+impl<'g> IntoIterator for &'g Gains<'_> {
+    type Item = (usize, f64);
+    type IntoIter = std::iter::Enumerate<std::iter::Copied<std::slice::Iter<'g, f64>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values.iter().copied().enumerate()
+    }
+}
+
+impl fmt::Display for Gains<'_> {
+    /// Lists the speakers with a nonzero gain, by label where they have one.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (speaker, &gain) in self.speakers.iter().zip(&self.values) {
+            if gain == 0.0 {
+                continue;
+            }
+            if !first {
+                f.write_str(", ")?;
+            }
+            first = false;
+            match speaker.label() {
+                Some(label) => write!(f, "{label}")?,
+                None => write!(f, "{}", speaker.id())?,
+            }
+            write!(f, " {:.1} dB", lin_to_db(gain))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::panner::VBAPanner;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_labels_and_db() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let gains = panner.gains(0.0, 0.0);
+        assert_eq!(gains.len(), 11);
+        assert_relative_eq!(
+            gains.by_label(ChannelLabel::C).unwrap(),
+            1.0,
+            epsilon = 1e-9
+        );
+        assert_eq!(gains.by_label(ChannelLabel::Ltf), Some(0.0));
+        assert_eq!(gains.linear(), panner.compute_gains(0.0, 0.0));
+
+        let db = gains.db();
+        assert_relative_eq!(db[2], 0.0, epsilon = 1e-9);
+        assert_eq!(db[0], f64::NEG_INFINITY);
+        assert_eq!(gains.to_string(), "C 0.0 dB");
+    }
+
+    #[test]
+    fn test_unlabeled_speakers() {
+        let panner = VBAPanner::builder()
+            .add_speaker(45.0, 0.0)
+            .add_labeled_speaker(ChannelLabel::R, -45.0, 0.0)
+            .add_speaker(180.0, 0.0)
+            .build()
+            .unwrap();
+        let gains = panner.gains(0.0, 0.0);
+        assert_eq!(gains.by_label(ChannelLabel::L), None);
+        assert_relative_eq!(
+            gains.by_label(ChannelLabel::R).unwrap(),
+            std::f64::consts::FRAC_1_SQRT_2,
+            epsilon = 1e-9
+        );
+        assert_eq!(gains.to_string(), "0 -3.0 dB, R -3.0 dB");
+
+        let pairs: Vec<(usize, f64)> = gains.iter().collect();
+        assert_eq!(pairs.len(), 3);
+        assert_eq!(pairs[2], (2, 0.0));
+        assert_eq!((&gains).into_iter().count(), 3);
+    }
+}
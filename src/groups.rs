@@ -0,0 +1,397 @@
+//! Groups of physical speakers panned as single logical speakers.
+//!
+//! Cinema surround walls are arrays of several boxes fed the same signal,
+//! and panning between the individual boxes would pull sources toward
+//! whichever seat is nearest. [`GroupedPanner`] pans among the positions
+//! of a logical layout and spreads each logical speaker's gain over its
+//! [`SpeakerGroup`] of physical output channels by a [`Distribution`]
+//! rule.
+
+use crate::error::{Result, VBAPError};
+use crate::panner::VBAPanner;
+
+/// How a group shares its logical speaker's gain among its channels.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Distribution {
+    /// Every channel gets `gain / sqrt(n)`, keeping the group's total power
+    /// that of a single speaker. Suits arrays whose boxes sum
+    /// incoherently in the room, such as cinema surrounds.
+    #[default]
+    EqualPower,
+    /// Every channel gets `gain / n`, keeping the summed amplitude that of
+    /// a single speaker. Suits closely spaced boxes that sum coherently.
+    EqualAmplitude,
+    /// Channel `i` gets `gain * weights[i]`, as given.
+    Weights(Vec<f64>),
+}
+
+/// Physical output channels playing one logical speaker.
+///
+/// # Example
+///
+/// ```
+/// use vbap::groups::{Distribution, SpeakerGroup};
+///
+/// // Six boxes on the left wall
+/// let wall = SpeakerGroup::new(vec![5, 6, 7, 8, 9, 10]);
+/// assert!((wall.weight(0) - 1.0 / 6f64.sqrt()).abs() < 1e-12);
+///
+/// // A main box with a quieter fill
+/// let group = SpeakerGroup::new(vec![0, 1]).with_distribution(Distribution::Weights(vec![1.0, 0.5]));
+/// assert_eq!(group.weight(1), 0.5);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpeakerGroup {
+    channels: Vec<usize>,
+    distribution: Distribution,
+}
+
+impl SpeakerGroup {
+    /// Group output channels with [equal power](Distribution::EqualPower).
+    pub fn new(channels: Vec<usize>) -> Self {
+        Self {
+            channels,
+            distribution: Distribution::default(),
+        }
+    }
+
+    /// A logical speaker played by a single output channel.
+    pub fn single(channel: usize) -> Self {
+        Self::new(vec![channel])
+    }
+
+    /// Set how the gain is shared among the channels.
+    pub fn with_distribution(mut self, distribution: Distribution) -> Self {
+        self.distribution = distribution;
+        self
+    }
+
+    /// Get the output channels of the group.
+    #[inline]
+    pub fn channels(&self) -> &[usize] {
+        &self.channels
+    }
+
+    /// Get how the gain is shared among the channels.
+    #[inline]
+    pub fn distribution(&self) -> &Distribution {
+        &self.distribution
+    }
+
+    /// Get the factor applied to the logical gain on the group's `i`th
+    /// channel.
+    ///
+    /// # Panics
+    /// Panics if `i` is out of range.
+    pub fn weight(&self, i: usize) -> f64 {
+        assert!(i < self.channels.len(), "channel index out of range");
+        let n = self.channels.len() as f64;
+        match &self.distribution {
+            Distribution::EqualPower => 1.0 / n.sqrt(),
+            Distribution::EqualAmplitude => 1.0 / n,
+            Distribution::Weights(weights) => weights[i],
+        }
+    }
+}
+
+/// Panner whose speakers are groups of physical output channels.
+///
+/// The wrapped [`VBAPanner`] describes the logical layout, one speaker per
+/// group; its speaker `i` is played by `groups[i]`. Gains and outputs are
+/// per physical channel.
+///
+/// # Example
+///
+/// ```
+/// use vbap::groups::{GroupedPanner, SpeakerGroup};
+/// use vbap::VBAPanner;
+///
+/// // 5.1 in a small cinema: L, R, C on channels 0 to 2, then three boxes
+/// // per surround wall
+/// let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+/// let grouped = GroupedPanner::new(
+///     panner,
+///     vec![
+///         SpeakerGroup::single(0),
+///         SpeakerGroup::single(1),
+///         SpeakerGroup::single(2),
+///         SpeakerGroup::new(vec![3, 4, 5]),
+///         SpeakerGroup::new(vec![6, 7, 8]),
+///     ],
+/// )
+/// .unwrap();
+/// assert_eq!(grouped.num_outputs(), 9);
+///
+/// // Straight at the left surround, its whole wall plays at equal power
+/// let gains = grouped.compute_gains(110.0, 0.0);
+/// assert!((gains[3] - gains[5]).abs() < 1e-12);
+/// let power: f64 = gains.iter().map(|g| g * g).sum();
+/// assert!((power - 1.0).abs() < 1e-9);
+/// ```
+#[derive(Clone, Debug)]
+pub struct GroupedPanner {
+    panner: VBAPanner,
+    groups: Vec<SpeakerGroup>,
+    /// `(channel, weight)` of each group's channels, by logical speaker.
+    routes: Vec<Vec<(usize, f64)>>,
+    num_outputs: usize,
+}
+
+impl GroupedPanner {
+    /// Play logical speaker `i` of `panner` on `groups[i]`.
+    ///
+    /// The number of outputs is one past the highest channel used; unused
+    /// channels below it stay silent. Returns
+    /// [`VBAPError::InvalidConfiguration`] if there is not one group per
+    /// speaker, a group is empty, a channel is in more than one group, or
+    /// a group's weights do not match its channels.
+    pub fn new(panner: VBAPanner, groups: Vec<SpeakerGroup>) -> Result<Self> {
+        if groups.len() != panner.num_speakers() {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "{} speaker groups for a layout of {} speakers",
+                groups.len(),
+                panner.num_speakers()
+            )));
+        }
+        let mut used: Vec<usize> = Vec::new();
+        for (speaker, group) in groups.iter().enumerate() {
+            if group.channels.is_empty() {
+                return Err(VBAPError::InvalidConfiguration(format!(
+                    "speaker group {} has no channels",
+                    speaker
+                )));
+            }
+            if let Distribution::Weights(weights) = &group.distribution {
+                if weights.len() != group.channels.len() {
+                    return Err(VBAPError::InvalidConfiguration(format!(
+                        "speaker group {} has {} channels but {} weights",
+                        speaker,
+                        group.channels.len(),
+                        weights.len()
+                    )));
+                }
+            }
+            for &channel in &group.channels {
+                if used.contains(&channel) {
+                    return Err(VBAPError::InvalidConfiguration(format!(
+                        "channel {} is in more than one speaker group",
+                        channel
+                    )));
+                }
+                used.push(channel);
+            }
+        }
+
+        let routes = groups
+            .iter()
+            .map(|group| {
+                (0..group.channels.len())
+                    .map(|i| (group.channels[i], group.weight(i)))
+                    .collect()
+            })
+            .collect();
+        let num_outputs = used.iter().max().map_or(0, |&channel| channel + 1);
+        Ok(Self {
+            panner,
+            groups,
+            routes,
+            num_outputs,
+        })
+    }
+
+    /// Get the panner of the logical layout.
+    #[inline]
+    pub fn panner(&self) -> &VBAPanner {
+        &self.panner
+    }
+
+    /// Get the speaker groups, by logical speaker.
+    #[inline]
+    pub fn groups(&self) -> &[SpeakerGroup] {
+        &self.groups
+    }
+
+    /// Get the number of physical output channels.
+    #[inline]
+    pub fn num_outputs(&self) -> usize {
+        self.num_outputs
+    }
+
+    /// Compute output channel gains for a source direction, in the layout's
+    /// [`convention`](crate::SpeakerConfig::convention).
+    pub fn compute_gains(&self, azimuth: f64, elevation: f64) -> Vec<f64> {
+        let mut gains = vec![0.0; self.num_outputs];
+        let mut scratch = vec![0.0; self.groups.len()];
+        self.compute_gains_into(azimuth, elevation, &mut gains, &mut scratch);
+        gains
+    }
+
+    /// Compute output channel gains into a caller-provided buffer, without
+    /// allocating.
+    ///
+    /// `scratch` receives the logical speaker gains, exactly as the
+    /// panner's [`compute_gains_into`](VBAPanner::compute_gains_into)
+    /// computes them, before they are routed to the channels.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_outputs()` or `scratch` is shorter
+    /// than the number of groups.
+    pub fn compute_gains_into(
+        &self,
+        azimuth: f64,
+        elevation: f64,
+        gains: &mut [f64],
+        scratch: &mut [f64],
+    ) {
+        assert!(
+            gains.len() >= self.num_outputs,
+            "gains slice too small: {} < {}",
+            gains.len(),
+            self.num_outputs
+        );
+        self.panner.compute_gains_into(azimuth, elevation, scratch);
+        gains.fill(0.0);
+        for (&gain, routes) in scratch.iter().zip(&self.routes) {
+            if gain == 0.0 {
+                continue;
+            }
+            for &(channel, weight) in routes {
+                gains[channel] += weight * gain;
+            }
+        }
+    }
+
+    /// Spread logical speaker feeds, for example a mixer's outputs over the
+    /// logical layout, onto the physical output channels.
+    ///
+    /// Frames beyond the shortest logical feed are left unchanged.
+    ///
+    /// # Panics
+    /// Panics if there are fewer logical feeds than groups or fewer
+    /// outputs than [`num_outputs`](Self::num_outputs).
+    pub fn distribute<S: AsRef<[f32]>>(&self, logical: &[S], outputs: &mut [&mut [f32]]) {
+        assert!(
+            logical.len() >= self.groups.len(),
+            "expected {} logical feeds, got {}",
+            self.groups.len(),
+            logical.len()
+        );
+        assert!(
+            outputs.len() >= self.num_outputs,
+            "expected {} outputs, got {}",
+            self.num_outputs,
+            outputs.len()
+        );
+        let frames = logical
+            .iter()
+            .map(|feed| feed.as_ref().len())
+            .min()
+            .unwrap_or(0);
+        for output in outputs.iter_mut().take(self.num_outputs) {
+            let end = frames.min(output.len());
+            output[..end].fill(0.0);
+        }
+        for (feed, routes) in logical.iter().zip(&self.routes) {
+            let feed = &feed.as_ref()[..frames];
+            for &(channel, weight) in routes {
+                let weight = weight as f32;
+                for (out, &sample) in outputs[channel].iter_mut().zip(feed) {
+                    *out += weight * sample;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::panner::ElevationPolicy;
+    use approx::assert_relative_eq;
+
+    fn stereo_arrays() -> GroupedPanner {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        GroupedPanner::new(
+            panner,
+            vec![
+                SpeakerGroup::new(vec![0, 2]),
+                SpeakerGroup::new(vec![1, 3, 5]).with_distribution(Distribution::EqualAmplitude),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_groups_share_logical_gain() {
+        let grouped = stereo_arrays();
+        assert_eq!(grouped.num_outputs(), 6);
+
+        let logical = grouped.panner().compute_gains(10.0, 0.0);
+        let gains = grouped.compute_gains(10.0, 0.0);
+        assert_relative_eq!(gains[0], logical[0] / 2f64.sqrt(), epsilon = 1e-12);
+        assert_relative_eq!(gains[2], gains[0], epsilon = 1e-12);
+        assert_relative_eq!(gains[3], logical[1] / 3.0, epsilon = 1e-12);
+        assert_eq!(gains[4], 0.0);
+    }
+
+    #[test]
+    fn test_matches_panner_elevation_policy() {
+        let panner = VBAPanner::builder()
+            .surround_5_1()
+            .build()
+            .unwrap()
+            .with_elevation_policy(ElevationPolicy::Spread);
+        let grouped = GroupedPanner::new(
+            panner.clone(),
+            vec![
+                SpeakerGroup::single(0),
+                SpeakerGroup::single(1),
+                SpeakerGroup::single(2),
+                SpeakerGroup::single(3),
+                SpeakerGroup::single(4),
+            ],
+        )
+        .unwrap();
+        for (azimuth, elevation) in [(20.0, 50.0), (-120.0, 30.0), (0.0, 0.0)] {
+            let expected = panner.compute_gains(azimuth, elevation);
+            let gains = grouped.compute_gains(azimuth, elevation);
+            for (gain, expected) in gains.iter().zip(&expected) {
+                assert_relative_eq!(gain, expected, epsilon = 1e-12);
+            }
+        }
+        // Spreading reaches every speaker
+        assert!(grouped.compute_gains(20.0, 50.0).iter().all(|&g| g > 0.0));
+    }
+
+    #[test]
+    fn test_distribute_feeds() {
+        let grouped = stereo_arrays();
+        let logical = [[1.0f32, 2.0], [3.0, 6.0]];
+        let mut buffers = vec![vec![9.0f32; 2]; 6];
+        let mut outputs: Vec<&mut [f32]> = buffers.iter_mut().map(|b| &mut b[..]).collect();
+        grouped.distribute(&logical, &mut outputs);
+        assert_relative_eq!(buffers[2][1], 2.0 / 2f32.sqrt(), epsilon = 1e-6);
+        assert_eq!(buffers[5], [1.0, 2.0]);
+        assert_eq!(buffers[4], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_invalid_groups() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let cases = [
+            vec![SpeakerGroup::single(0)],
+            vec![SpeakerGroup::single(0), SpeakerGroup::new(vec![])],
+            vec![SpeakerGroup::new(vec![0, 1]), SpeakerGroup::single(1)],
+            vec![
+                SpeakerGroup::single(0),
+                SpeakerGroup::new(vec![1, 2]).with_distribution(Distribution::Weights(vec![1.0])),
+            ],
+        ];
+        for groups in cases {
+            assert!(matches!(
+                GroupedPanner::new(panner.clone(), groups),
+                Err(VBAPError::InvalidConfiguration(_))
+            ));
+        }
+    }
+}
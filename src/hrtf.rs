@@ -0,0 +1,456 @@
+//! Head-related impulse response sets for binaural preview.
+//!
+//! An [`HrirSet`] holds HRIR pairs measured from a grid of directions. It
+//! looks up the pair for any direction, by nearest measurement or
+//! interpolated between the closest three, and builds a
+//! [`BinauralDownmix`] for a speaker layout, so a VBAP render can be
+//! previewed on headphones.
+//!
+//! The crate does not read HRIR files. [`HrirSet::from_arrays`] takes the
+//! measurements as flat arrays, laid out like the variables of a SOFA
+//! `SimpleFreeFieldHRIR` file (`SourcePosition` with its `Type` and
+//! `Units`, `Data.IR`, `Data.Delay` and `Data.SamplingRate`), so data read
+//! with an HDF5 or netCDF library can be passed as is.
+//!
+//! Requires the `hrtf` feature.
+
+use glam::DVec3;
+
+use crate::config::SpeakerConfig;
+use crate::error::{Result, VBAPError};
+use crate::math::{cartesian_to_spherical, spherical_to_cartesian};
+use crate::monitor::BinauralDownmix;
+
+/// One measured HRIR pair.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hrir {
+    /// Azimuth of the measurement in degrees.
+    pub azimuth: f64,
+    /// Elevation of the measurement in degrees.
+    pub elevation: f64,
+    /// Left-ear impulse response.
+    pub left: Vec<f32>,
+    /// Right-ear impulse response.
+    pub right: Vec<f32>,
+}
+
+impl Hrir {
+    /// Create a measurement from a direction and its impulse responses.
+    pub fn new(azimuth: f64, elevation: f64, left: Vec<f32>, right: Vec<f32>) -> Self {
+        Self {
+            azimuth,
+            elevation,
+            left,
+            right,
+        }
+    }
+}
+
+/// How measurement positions are given.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoordinateType {
+    /// Azimuth and elevation in degrees, then distance, with azimuth 0° in
+    /// front and positive to the left.
+    Spherical,
+    /// x to the front, y to the left and z up.
+    Cartesian,
+}
+
+impl CoordinateType {
+    /// Read the coordinate type from the `Type` and `Units` attributes of
+    /// a position variable, such as `"spherical"` with
+    /// `"degree, degree, metre"` or `"cartesian"` with `"metre"`.
+    ///
+    /// # Errors
+    /// Returns [`VBAPError::Parse`] for other types, or spherical angles in
+    /// a unit other than degrees.
+    pub fn from_attributes(kind: &str, units: &str) -> Result<Self> {
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "spherical" => {
+                let angles: Vec<&str> = units
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|unit| !unit.is_empty())
+                    .take(2)
+                    .collect();
+                if angles.len() == 2 && angles.iter().all(|unit| unit.starts_with("degree")) {
+                    Ok(Self::Spherical)
+                } else {
+                    Err(VBAPError::Parse(format!(
+                        "spherical positions must be in degrees, got units '{}'",
+                        units
+                    )))
+                }
+            }
+            "cartesian" => Ok(Self::Cartesian),
+            _ => Err(VBAPError::Parse(format!(
+                "unsupported position type '{}'",
+                kind
+            ))),
+        }
+    }
+
+    /// Get the azimuth and elevation in degrees of a position.
+    fn direction(self, position: [f64; 3]) -> (f64, f64) {
+        match self {
+            Self::Spherical => (position[0], position[1]),
+            // The crate's x points left and y to the front
+            Self::Cartesian => {
+                cartesian_to_spherical(DVec3::new(position[1], position[0], position[2]))
+            }
+        }
+    }
+}
+
+/// A set of HRIR measurements at one sample rate.
+///
+/// Directions use the crate's native convention (azimuth 0° in front,
+/// positive to the left), which is also SOFA's spherical convention.
+///
+/// # Example
+///
+/// ```
+/// use vbap::hrtf::{Hrir, HrirSet};
+/// use vbap::SpeakerConfigBuilder;
+///
+/// let set = HrirSet::new(
+///     48000.0,
+///     vec![
+///         Hrir::new(30.0, 0.0, vec![1.0], vec![0.0, 0.5]),
+///         Hrir::new(-30.0, 0.0, vec![0.0, 0.5], vec![1.0]),
+///     ],
+/// )
+/// .unwrap();
+/// assert_eq!(set.nearest(20.0, 5.0).azimuth, 30.0);
+///
+/// let config = SpeakerConfigBuilder::new().stereo().build_config().unwrap();
+/// let downmix = set.downmix(&config);
+/// assert_eq!(downmix.num_speakers(), 2);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct HrirSet {
+    sample_rate: f64,
+    hrirs: Vec<Hrir>,
+    /// Unit vector of each measurement direction.
+    directions: Vec<DVec3>,
+}
+
+impl HrirSet {
+    /// Create a set from measurements at `sample_rate` Hz.
+    ///
+    /// # Errors
+    /// Returns an error if `sample_rate` is not positive and finite or
+    /// `hrirs` is empty.
+    pub fn new(sample_rate: f64, hrirs: Vec<Hrir>) -> Result<Self> {
+        if !(sample_rate.is_finite() && sample_rate > 0.0) {
+            return Err(VBAPError::InvalidParameter {
+                parameter: "sample_rate",
+                value: sample_rate,
+                min: 0.0,
+                max: f64::INFINITY,
+            });
+        }
+        if hrirs.is_empty() {
+            return Err(VBAPError::InvalidConfiguration(
+                "an HRIR set needs at least one measurement".into(),
+            ));
+        }
+        let directions = hrirs
+            .iter()
+            .map(|hrir| spherical_to_cartesian(hrir.azimuth, hrir.elevation))
+            .collect();
+        Ok(Self {
+            sample_rate,
+            hrirs,
+            directions,
+        })
+    }
+
+    /// Create a set from measurements stored as flat arrays.
+    ///
+    /// `positions` holds one position per measurement in `coordinates`,
+    /// and `data_ir` the impulse responses in M x R x N order: for each
+    /// measurement, the left then the right ear's `num_samples` taps.
+    /// `delays` are broadband delays in samples, added in front of the
+    /// responses; give one `[left, right]` pair per measurement, a single
+    /// pair for all of them, or none. Fractional delays are interpolated
+    /// linearly.
+    ///
+    /// # Errors
+    /// Returns an error if the sample rate is invalid, there are no
+    /// measurements, `data_ir` does not hold two `num_samples` long
+    /// responses per measurement, or a delay is negative or not finite.
+    pub fn from_arrays(
+        sample_rate: f64,
+        positions: &[[f64; 3]],
+        coordinates: CoordinateType,
+        data_ir: &[f64],
+        num_samples: usize,
+        delays: &[[f64; 2]],
+    ) -> Result<Self> {
+        let expected = positions.len() * 2 * num_samples;
+        if data_ir.len() != expected {
+            return Err(VBAPError::Parse(format!(
+                "impulse responses have {} values, expected {} ({} measurements x 2 receivers x {} samples)",
+                data_ir.len(),
+                expected,
+                positions.len(),
+                num_samples
+            )));
+        }
+        if !(delays.len() <= 1 || delays.len() == positions.len()) {
+            return Err(VBAPError::Parse(format!(
+                "{} delays for {} measurements",
+                delays.len(),
+                positions.len()
+            )));
+        }
+        if let Some(&delay) = delays
+            .iter()
+            .flatten()
+            .find(|d| !(d.is_finite() && **d >= 0.0))
+        {
+            return Err(VBAPError::Parse(format!("invalid delay {}", delay)));
+        }
+
+        let hrirs = positions
+            .iter()
+            .enumerate()
+            .map(|(m, &position)| {
+                let start = m * 2 * num_samples;
+                let (left, right) = data_ir[start..start + 2 * num_samples].split_at(num_samples);
+                let [left_delay, right_delay] = match delays {
+                    [] => [0.0; 2],
+                    [delay] => *delay,
+                    _ => delays[m],
+                };
+                let (azimuth, elevation) = coordinates.direction(position);
+                Hrir::new(
+                    azimuth,
+                    elevation,
+                    delayed(left, left_delay),
+                    delayed(right, right_delay),
+                )
+            })
+            .collect();
+        Self::new(sample_rate, hrirs)
+    }
+
+    /// Get the sample rate of the impulse responses.
+    #[inline]
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+
+    /// Get the measurements.
+    #[inline]
+    pub fn hrirs(&self) -> &[Hrir] {
+        &self.hrirs
+    }
+
+    /// Get the measurement closest to a direction.
+    pub fn nearest(&self, azimuth: f64, elevation: f64) -> &Hrir {
+        let target = spherical_to_cartesian(azimuth, elevation);
+        let index = self
+            .directions
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.dot(target).total_cmp(&b.dot(target)))
+            .map_or(0, |(i, _)| i);
+        &self.hrirs[index]
+    }
+
+    /// Get the (left, right) HRIRs for a direction, interpolated between
+    /// the three closest measurements.
+    ///
+    /// Each measurement is weighted by its inverse angular distance, so a
+    /// direction on the measurement grid gets that measurement exactly.
+    /// Responses are mixed in the time domain; on a dense grid the comb
+    /// filtering this causes is small enough for previewing.
+    pub fn interpolated(&self, azimuth: f64, elevation: f64) -> [Vec<f32>; 2] {
+        let target = spherical_to_cartesian(azimuth, elevation);
+        let mut closest: Vec<(usize, f64)> = self
+            .directions
+            .iter()
+            .enumerate()
+            .map(|(i, direction)| (i, direction.angle_between(target)))
+            .collect();
+        closest.sort_by(|a, b| a.1.total_cmp(&b.1));
+        closest.truncate(3);
+
+        if closest[0].1 < 1e-9 {
+            let hrir = &self.hrirs[closest[0].0];
+            return [hrir.left.clone(), hrir.right.clone()];
+        }
+
+        let total: f64 = closest.iter().map(|&(_, angle)| 1.0 / angle).sum();
+        let mut mixed = [Vec::new(), Vec::new()];
+        for &(i, angle) in &closest {
+            let weight = (1.0 / angle / total) as f32;
+            let hrir = &self.hrirs[i];
+            for (out, taps) in mixed.iter_mut().zip([&hrir.left, &hrir.right]) {
+                if out.len() < taps.len() {
+                    out.resize(taps.len(), 0.0);
+                }
+                for (y, &x) in out.iter_mut().zip(taps) {
+                    *y += x * weight;
+                }
+            }
+        }
+        mixed
+    }
+
+    /// Build a binaural downmix that renders each speaker of a layout
+    /// through the HRIRs interpolated for its direction.
+    ///
+    /// Virtual speakers get silent filters. The downmix runs at this set's
+    /// sample rate.
+    pub fn downmix(&self, config: &SpeakerConfig) -> BinauralDownmix {
+        let hrirs = config
+            .speakers()
+            .iter()
+            .map(|speaker| {
+                if speaker.is_virtual() {
+                    [Vec::new(), Vec::new()]
+                } else {
+                    self.interpolated(speaker.azimuth(), speaker.elevation())
+                }
+            })
+            .collect();
+        BinauralDownmix::new(hrirs)
+    }
+}
+
+/// Convert taps to `f32`, delayed by `delay` samples.
+fn delayed(taps: &[f64], delay: f64) -> Vec<f32> {
+    let whole = delay.floor() as usize;
+    let fraction = delay - delay.floor();
+    let mut out = vec![0.0f32; whole + taps.len() + usize::from(fraction > 0.0)];
+    for (n, &x) in taps.iter().enumerate() {
+        out[whole + n] += ((1.0 - fraction) * x) as f32;
+        if fraction > 0.0 {
+            out[whole + n + 1] += (fraction * x) as f32;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpeakerConfigBuilder;
+    use approx::assert_relative_eq;
+
+    /// Measurements every 90° around the horizon, tagged by their first tap.
+    fn horizon() -> HrirSet {
+        let positions = [
+            [0.0, 0.0, 1.2],
+            [90.0, 0.0, 1.2],
+            [180.0, 0.0, 1.2],
+            [-90.0, 0.0, 1.2],
+        ];
+        let data: Vec<f64> = (0..4)
+            .flat_map(|m| [m as f64, 0.0, 10.0 + m as f64, 0.0])
+            .collect();
+        HrirSet::from_arrays(
+            44100.0,
+            &positions,
+            CoordinateType::Spherical,
+            &data,
+            2,
+            &[],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_arrays_layout() {
+        let set = horizon();
+        assert_eq!(set.hrirs().len(), 4);
+        assert_eq!(set.hrirs()[2].left, [2.0, 0.0]);
+        assert_eq!(set.hrirs()[2].right, [12.0, 0.0]);
+        assert_eq!(set.nearest(-100.0, 10.0).azimuth, -90.0);
+
+        let spherical = CoordinateType::Spherical;
+        let one = [[0.0, 0.0, 1.0]];
+        assert!(HrirSet::from_arrays(44100.0, &one, spherical, &[0.0; 3], 2, &[]).is_err());
+        assert!(
+            HrirSet::from_arrays(44100.0, &one, spherical, &[0.0; 4], 2, &[[-1.0, 0.0]]).is_err()
+        );
+        assert!(
+            HrirSet::from_arrays(44100.0, &one, spherical, &[0.0; 4], 2, &[[0.0; 2]; 2]).is_err()
+        );
+        assert!(HrirSet::new(44100.0, Vec::new()).is_err());
+        for rate in [0.0, -48000.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                HrirSet::from_arrays(rate, &one, spherical, &[0.0; 4], 2, &[]),
+                Err(VBAPError::InvalidParameter { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_coordinates_and_delays() {
+        assert_eq!(
+            CoordinateType::from_attributes("spherical", "degree, degree, metre").unwrap(),
+            CoordinateType::Spherical
+        );
+        assert_eq!(
+            CoordinateType::from_attributes("Cartesian", "metre").unwrap(),
+            CoordinateType::Cartesian
+        );
+        assert!(CoordinateType::from_attributes("spherical", "radian, radian, metre").is_err());
+        assert!(CoordinateType::from_attributes("polar", "degree").is_err());
+
+        // Cartesian positions: 1 m to the left, then 2 m in front
+        let positions = [[0.0, 1.0, 0.0], [2.0, 0.0, 0.0]];
+        let data = [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0, 0.0];
+        let set = HrirSet::from_arrays(
+            48000.0,
+            &positions,
+            CoordinateType::Cartesian,
+            &data,
+            2,
+            &[[0.0, 2.0], [1.5, 0.0]],
+        )
+        .unwrap();
+        assert_relative_eq!(set.hrirs()[0].azimuth, 90.0, epsilon = 1e-9);
+        assert_relative_eq!(set.hrirs()[1].azimuth, 0.0, epsilon = 1e-9);
+        assert_eq!(set.hrirs()[0].left, [1.0, 0.0]);
+        assert_eq!(set.hrirs()[0].right, [0.0, 0.0, 1.0, 0.0]);
+        assert_eq!(set.hrirs()[1].left, [0.0, 0.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_interpolated_weights() {
+        let set = horizon();
+        // On the grid: that measurement exactly
+        assert_eq!(
+            set.interpolated(90.0, 0.0),
+            [vec![1.0, 0.0], vec![11.0, 0.0]]
+        );
+
+        // Closer to the front than to the left
+        let [left, _] = set.interpolated(30.0, 0.0);
+        // Weights 1/30, 1/60 and 1/120 (right) normalized
+        let expected =
+            (0.0 / 30.0 + 1.0 / 60.0 + 3.0 / 120.0) / (1.0 / 30.0 + 1.0 / 60.0 + 1.0 / 120.0);
+        assert_relative_eq!(left[0] as f64, expected, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_downmix_uses_speaker_directions() {
+        let set = horizon();
+        let config = SpeakerConfigBuilder::new()
+            .add_speaker(90.0, 0.0)
+            .add_speaker(-90.0, 0.0)
+            .add_speaker(180.0, 0.0)
+            .build_config()
+            .unwrap();
+        let mut downmix = set.downmix(&config);
+
+        let (mut left, mut right) = ([0.0f32; 2], [0.0f32; 2]);
+        downmix.process(&[&[1.0, 0.0], &[0.0; 2], &[0.0; 2]], &mut left, &mut right);
+        assert_eq!(left, [1.0, 0.0]);
+        assert_eq!(right, [11.0, 0.0]);
+    }
+}
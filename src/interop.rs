@@ -0,0 +1,264 @@
+//! Interoperability with the `mint`, `nalgebra` and `ndarray` crates.
+//!
+//! The crate computes with `glam`. [`Direction`] converts the vector types
+//! of other ecosystems to and from the crate's Cartesian frame (x = left,
+//! y = front, z = up), so [`VBAPanner::compute_gains_toward`] and
+//! [`SpeakerConfig::speaker_directions`] take and return them directly.
+//!
+//! Gains for many directions are available as a `directions × speakers`
+//! matrix: an `nalgebra::DMatrix` from `VBAPanner::gain_matrix`, or an
+//! `ndarray::Array2` from `VBAPanner::gain_array` (filled in place by
+//! `VBAPanner::compute_gains_batch_array`, and from a sampled sphere by
+//! `GainField::gain_array`).
+//!
+//! Requires the `mint`, `nalgebra` or `ndarray` feature.
+
+use glam::DVec3;
+
+use crate::config::SpeakerConfig;
+use crate::math::cartesian_to_spherical;
+use crate::panner::VBAPanner;
+
+/// A 3D vector type usable as a direction.
+pub trait Direction: Sized {
+    /// Convert to a `glam` vector in the crate's frame.
+    fn to_dvec3(&self) -> DVec3;
+
+    /// Convert from a unit vector in the crate's frame.
+    fn from_dvec3(direction: DVec3) -> Self;
+}
+
+impl Direction for DVec3 {
+    #[inline]
+    fn to_dvec3(&self) -> DVec3 {
+        *self
+    }
+
+    #[inline]
+    fn from_dvec3(direction: DVec3) -> Self {
+        direction
+    }
+}
+
+#[cfg(feature = "mint")]
+impl Direction for mint::Vector3<f64> {
+    #[inline]
+    fn to_dvec3(&self) -> DVec3 {
+        DVec3::from(*self)
+    }
+
+    #[inline]
+    fn from_dvec3(direction: DVec3) -> Self {
+        direction.into()
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl Direction for nalgebra::Vector3<f64> {
+    #[inline]
+    fn to_dvec3(&self) -> DVec3 {
+        DVec3::new(self.x, self.y, self.z)
+    }
+
+    #[inline]
+    fn from_dvec3(direction: DVec3) -> Self {
+        nalgebra::Vector3::new(direction.x, direction.y, direction.z)
+    }
+}
+
+#[cfg(feature = "nalgebra")]
+impl Direction for nalgebra::Unit<nalgebra::Vector3<f64>> {
+    #[inline]
+    fn to_dvec3(&self) -> DVec3 {
+        self.as_ref().to_dvec3()
+    }
+
+    #[inline]
+    fn from_dvec3(direction: DVec3) -> Self {
+        nalgebra::Unit::new_normalize(nalgebra::Vector3::from_dvec3(direction))
+    }
+}
+
+impl VBAPanner {
+    /// Compute speaker gains for a source in a Cartesian direction.
+    ///
+    /// The direction need not be unit length; a zero vector pans to the
+    /// front. The panner's [`convention`](SpeakerConfig::convention) does not
+    /// apply, since it only concerns angles.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use glam::DVec3;
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder().stereo().build().unwrap();
+    /// let left = DVec3::new(1.0, 3f64.sqrt(), 0.0);
+    /// assert!((panner.compute_gains_toward(&left)[0] - 1.0).abs() < 1e-9);
+    /// ```
+    pub fn compute_gains_toward<D: Direction>(&self, direction: &D) -> Vec<f64> {
+        let mut gains = vec![0.0; self.num_speakers()];
+        self.compute_gains_toward_into(direction, &mut gains);
+        gains
+    }
+
+    /// Compute speaker gains for a source in a Cartesian direction into a
+    /// caller-provided buffer.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_speakers()`.
+    pub fn compute_gains_toward_into<D: Direction>(&self, direction: &D, gains: &mut [f64]) {
+        let (azimuth, elevation) = cartesian_to_spherical(direction.to_dvec3());
+        let (azimuth, elevation) = self.config().convention().from_native(azimuth, elevation);
+        self.compute_gains_into(azimuth, elevation, gains);
+    }
+
+    /// Compute speaker gains for many directions as a
+    /// `directions.len() × num_speakers()` matrix, one row per direction.
+    ///
+    /// See [`compute_gains_batch`](Self::compute_gains_batch).
+    #[cfg(feature = "nalgebra")]
+    pub fn gain_matrix(&self, directions: &[(f64, f64)]) -> nalgebra::DMatrix<f64> {
+        let mut gains = vec![0.0; directions.len() * self.num_speakers()];
+        self.compute_gains_batch(directions, &mut gains);
+        nalgebra::DMatrix::from_row_slice(directions.len(), self.num_speakers(), &gains)
+    }
+
+    /// Compute speaker gains for many directions as a
+    /// `directions.len() × num_speakers()` array, one row per direction.
+    ///
+    /// See [`compute_gains_batch`](Self::compute_gains_batch).
+    #[cfg(feature = "ndarray")]
+    pub fn gain_array(&self, directions: &[(f64, f64)]) -> ndarray::Array2<f64> {
+        let mut gains = ndarray::Array2::zeros((directions.len(), self.num_speakers()));
+        self.compute_gains_batch_array(directions, gains.view_mut());
+        gains
+    }
+
+    /// Compute speaker gains for many directions into a
+    /// `directions.len() × num_speakers()` array, one row per direction.
+    ///
+    /// Arrays in standard (row-major) layout are filled as by
+    /// [`compute_gains_batch`](Self::compute_gains_batch); others, such as
+    /// column-major arrays, row by row.
+    ///
+    /// # Panics
+    /// Panics if `out` does not have that shape.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ndarray::Array2;
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+    /// let directions = [(0.0, 0.0), (110.0, 0.0)];
+    /// let mut gains = Array2::zeros((2, 5));
+    /// panner.compute_gains_batch_array(&directions, gains.view_mut());
+    /// assert!((gains[[1, 3]] - 1.0).abs() < 1e-9);
+    /// ```
+    #[cfg(feature = "ndarray")]
+    pub fn compute_gains_batch_array(
+        &self,
+        directions: &[(f64, f64)],
+        mut out: ndarray::ArrayViewMut2<f64>,
+    ) {
+        let n = self.num_speakers();
+        assert_eq!(
+            out.dim(),
+            (directions.len(), n),
+            "output must hold {} directions x {} speakers",
+            directions.len(),
+            n
+        );
+        if let Some(slice) = out.as_slice_mut() {
+            self.compute_gains_batch(directions, slice);
+            return;
+        }
+        let mut row = vec![0.0; n];
+        for (&(azimuth, elevation), mut target) in directions.iter().zip(out.rows_mut()) {
+            self.compute_gains_into(azimuth, elevation, &mut row);
+            target.assign(&ndarray::ArrayView1::from(&row));
+        }
+    }
+}
+
+impl SpeakerConfig {
+    /// Get the unit direction vector of each speaker.
+    pub fn speaker_directions<D: Direction>(&self) -> Vec<D> {
+        self.speakers()
+            .iter()
+            .map(|speaker| D::from_dvec3(speaker.cartesian()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "mint")]
+    #[test]
+    fn test_mint_directions() {
+        use approx::assert_relative_eq;
+
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let rear = mint::Vector3 {
+            x: 0.0,
+            y: -2.0,
+            z: 0.0,
+        };
+        let gains = panner.compute_gains_toward(&rear);
+        assert_eq!(gains, panner.compute_gains(180.0, 0.0));
+
+        let directions: Vec<mint::Vector3<f64>> = panner.config().speaker_directions();
+        assert_relative_eq!(directions[2].y, 1.0, epsilon = 1e-12);
+    }
+
+    #[cfg(feature = "nalgebra")]
+    #[test]
+    fn test_nalgebra_directions() {
+        use approx::assert_relative_eq;
+        use nalgebra::{Unit, Vector3};
+
+        let panner = VBAPanner::builder()
+            .convention(crate::Convention::Max)
+            .stereo()
+            .build()
+            .unwrap();
+        // Cartesian directions ignore the angle convention
+        let left = Unit::new_normalize(Vector3::new(1.0, 3f64.sqrt(), 0.0));
+        assert_relative_eq!(panner.compute_gains_toward(&left)[0], 1.0, epsilon = 1e-9);
+
+        let directions: Vec<Unit<Vector3<f64>>> = panner.config().speaker_directions();
+        assert_relative_eq!(directions[1].x, -0.5, epsilon = 1e-12);
+
+        let matrix = panner.gain_matrix(&[(0.0, 0.0), (-30.0, 0.0), (30.0, 0.0)]);
+        assert_eq!(matrix.shape(), (3, 2));
+        assert_relative_eq!(matrix[(0, 0)], matrix[(0, 1)], epsilon = 1e-12);
+        assert_relative_eq!(matrix[(1, 0)], 1.0, epsilon = 1e-9);
+    }
+
+    #[cfg(feature = "ndarray")]
+    #[test]
+    fn test_ndarray_batches() {
+        use ndarray::{Array2, ShapeBuilder};
+
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let directions = [(0.0, 0.0), (45.0, 30.0), (-120.0, 10.0)];
+        let rows = panner.gain_array(&directions);
+        assert_eq!(rows.dim(), (3, 11));
+        assert_eq!(rows.row(1).to_vec(), panner.compute_gains(45.0, 30.0));
+
+        // Column-major output is filled row by row
+        let mut columns = Array2::zeros((3, 11).f());
+        panner.compute_gains_batch_array(&directions, columns.view_mut());
+        assert_eq!(columns, rows);
+
+        let field = crate::analysis::sample_sphere(&panner, 30.0);
+        let array = field.gain_array();
+        let width = field.azimuths().len();
+        assert_eq!(array.dim(), (width * field.elevations().len(), 11));
+        assert_eq!(array.row(2 * width + 3).to_vec(), field.gains(3, 2));
+    }
+}
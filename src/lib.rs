@@ -13,6 +13,56 @@
 //! - **Presets**: Common configurations (stereo, 5.1, 7.1, Atmos, etc.)
 //! - **Builder API**: Fluent interface for custom speaker layouts
 //! - **SIMD Optimized**: Uses `glam` for fast vector math
+//! - **Bass Management**: Per-speaker Linkwitz-Riley crossovers feeding the LFE
+//! - **Layout Remapping**: Static upmix/downmix matrices between layouts
+//! - **Layout Metrics**: Gerzon velocity and energy vectors, spread, angular
+//!   error and gain heatmaps over the sphere
+//! - **Mesh Export**: The speaker triangulation as OBJ or PLY for inspection
+//! - **2.5D Rings**: Pairwise panning within stacked horizontal rings,
+//!   crossfaded by elevation
+//! - **Layout Caching**: Deterministic triangulation, layout hashes and binary
+//!   serialization of built layouts
+//! - **Channel Labels**: Gains looked up by channel label and read in dB
+//! - **Source Extent**: Object width and height rendered as a grid of
+//!   virtual sources
+//! - **Speaker Groups**: Arrays of physical speakers panned as one logical
+//!   speaker, as for cinema surround walls
+//! - **Layout Crossfades**: Glitch-free switching between two layouts or
+//!   calibrations mid-show
+//! - **Gain Utilities**: dB conversion, RMS, peak and target normalization
+//!
+//! ## Cargo Features
+//!
+//! The default build only contains the panning core (layouts, panners,
+//! analysis), so embedded and plugin users do not pay for the rest:
+//!
+//! - `render`: multi-source mixer, bass management, elevation cue filters,
+//!   stereo monitoring and the DSP blocks they share
+//! - `trajectory`: source trajectories and procedural motion
+//! - `io`: layout import and export in other tools' formats
+//! - `experimental`: algorithms outside the semver guarantees, see
+//!   `experimental`
+//! - `hrtf`: measured HRIR sets for binaural preview of a layout
+//! - `viz`: SVG plots of layouts, active tuples and gains
+//! - `tracing`: spans and events for config building and tuple switches
+//! - `ffi`: a C API over the panner, see `ffi` and `include/vbap.h`
+//! - `wav`: offline rendering of a mono WAV file along a trajectory to one
+//!   channel per speaker (implies `render` and `trajectory`)
+//! - `cpal`: real-time playback of the mixer on an audio device, see
+//!   `playback` (implies `render`)
+//! - `bevy`: a Bevy plugin positioning mixer sources from entity
+//!   transforms relative to a listener, see `ecs` (implies `render`)
+//! - `osc`: an OSC receiver moving mixer sources from controllers with
+//!   SpatDIF-style `/source/<n>/aed` messages, see `osc` (implies `render`)
+//! - `cli`: the `vbap-cli` tool for gains, trajectory CSVs and layout
+//!   diagnostics (implies `io` and `trajectory`)
+//! - `wasm`: JavaScript bindings for WebAssembly builds, see `wasm`
+//! - `mint`, `nalgebra` and `ndarray`: directions and gain matrices in
+//!   those crates' types, see `interop`
+//! - `dual-band` and `binaural` (imply `render`), `scripting` and `tempo-sync` (imply
+//!   `trajectory`), `shared`, `rayon`, `simd`: see `Cargo.toml`
+//!
+//! [`prelude`] re-exports the commonly used types of the enabled features.
 //!
 //! ## Quick Start
 //!
@@ -67,23 +117,98 @@
 //! - **Azimuth**: 0° = front center, 90° = left, -90° = right, 180° = rear
 //! - **Elevation**: 0° = horizontal, 90° = above, -90° = below
 //!
+//! Layouts and sources from tools with other conventions (Max/MSP,
+//! compass bearings) can be used as is with
+//! [`SpeakerConfigBuilder::convention`].
+//!
 //! ## References
 //!
 //! Based on Ville Pulkki's VBAP algorithm:
 //! - Pulkki, V. (1997). "Virtual Sound Source Positioning Using Vector Base Amplitude Panning"
 //! - Implementation adapted from Ardour DAW's panner code
 
+pub mod analysis;
+#[cfg(feature = "render")]
+pub mod bass;
 pub mod config;
+pub mod convention;
+pub mod crossfade;
+pub mod diagnostics;
+pub mod divergence;
+#[cfg(feature = "render")]
+pub mod dsp;
+#[cfg(feature = "dual-band")]
+pub mod dual_band;
+#[cfg(feature = "bevy")]
+pub mod ecs;
+#[cfg(feature = "render")]
+pub mod elevation;
 pub mod error;
+pub mod exclusion;
+#[cfg(feature = "experimental")]
+pub mod experimental;
+pub mod extent;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod fixed;
+#[cfg(feature = "io")]
+pub mod formats;
+pub mod gains;
+pub mod groups;
+#[cfg(feature = "hrtf")]
+pub mod hrtf;
+#[cfg(any(feature = "mint", feature = "nalgebra", feature = "ndarray"))]
+pub mod interop;
+pub mod listener;
+pub mod mask;
 pub mod math;
+pub mod mesh;
+#[cfg(feature = "render")]
+pub mod mixer;
+#[cfg(feature = "render")]
+pub mod monitor;
+#[cfg(feature = "osc")]
+pub mod osc;
 pub mod panner;
+#[cfg(feature = "cpal")]
+pub mod playback;
+pub mod prelude;
 pub mod presets;
+pub mod random_layout;
+pub mod remap;
+pub mod rings;
+pub mod rng;
+pub mod room;
+#[cfg(feature = "shared")]
+pub mod shared;
 pub mod speaker;
+pub mod stereo;
+#[cfg(feature = "tracing")]
+pub mod trace;
+#[cfg(feature = "trajectory")]
+pub mod trajectory;
+pub mod util;
+#[cfg(feature = "viz")]
+pub mod viz;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "wav")]
+pub mod wav;
 
 // Re-exports for ergonomic API
 pub use config::{
-    Dimension, InverseMatrix, PanningMode, SpeakerConfig, SpeakerConfigBuilder, SpeakerTuple,
+    Dimension, DuplicateSpeakers, InverseMatrix, PanningMode, SpeakerConfig, SpeakerConfigBuilder,
+    SpeakerTuple, SpeakerTuples, Tolerances,
 };
+pub use convention::Convention;
+pub use divergence::CenterDivergence;
 pub use error::{Result, VBAPError};
-pub use panner::VBAPanner;
-pub use speaker::Speaker;
+pub use exclusion::SpeakerExclusions;
+pub use fixed::FixedPanner;
+pub use gains::Gains;
+pub use mask::SpeakerMask;
+pub use panner::{
+    ActiveGains, ElevationPolicy, Normalization, PanningLaw, PanningState, Renormalization,
+    TieBreak, VBAPanner,
+};
+pub use speaker::{ChannelLabel, Speaker};
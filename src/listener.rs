@@ -0,0 +1,331 @@
+//! Off-center listener compensation.
+//!
+//! Presets assume the listener sits at the center of the speaker array.
+//! In real rooms the sweet spot is often elsewhere, so the speakers are seen
+//! from different directions, at different distances, and their sound
+//! arrives at different times. [`ListenerCompensation`] re-derives the
+//! layout from physical speaker positions as seen from the listener, and
+//! computes per-speaker level trims and delays that time- and level-align
+//! the array at the listening position.
+
+use glam::DVec3;
+
+use crate::config::{Dimension, PanningMode, SpeakerConfigBuilder};
+use crate::error::{Result, VBAPError};
+use crate::math::cartesian_to_spherical;
+use crate::panner::VBAPanner;
+
+/// Speed of sound in m/s at 20 °C.
+pub const SPEED_OF_SOUND: f64 = 343.0;
+
+/// Closest a speaker may be to the listener, in meters.
+const MIN_SPEAKER_DISTANCE: f64 = 1e-3;
+
+/// A panner re-derived for a listener away from the array center.
+///
+/// Positions are in meters, in the panner's Cartesian frame (x = left,
+/// y = front, z = up), relative to any fixed room origin. Source directions
+/// passed to [`compute_gains_into`](Self::compute_gains_into) are relative
+/// to the listener.
+///
+/// Each speaker gets a gain trim of `distance / max_distance` (inverse
+/// distance law, the farthest speaker is unity) and a delay of
+/// `(max_distance - distance) / c`, so all speakers arrive at the listener
+/// together and at the same level.
+///
+/// # Example
+///
+/// ```
+/// use glam::DVec3;
+/// use vbap::listener::ListenerCompensation;
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().quad().build().unwrap();
+/// let corners = [
+///     DVec3::new(2.0, 2.0, 0.0),
+///     DVec3::new(-2.0, 2.0, 0.0),
+///     DVec3::new(2.0, -2.0, 0.0),
+///     DVec3::new(-2.0, -2.0, 0.0),
+/// ];
+/// // Listener half a meter forward of the center
+/// let room = ListenerCompensation::new(&panner, &corners)
+///     .unwrap()
+///     .with_listener(DVec3::new(0.0, 0.5, 0.0))
+///     .unwrap();
+///
+/// // The front speakers are closer, so they are delayed and turned down
+/// assert!(room.delays()[0] > 0.0 && room.delays()[2] == 0.0);
+/// assert!(room.distance_gains()[0] < 1.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ListenerCompensation {
+    /// Panner for a centered listener, carrying the user's settings.
+    base: VBAPanner,
+    positions: Vec<DVec3>,
+    listener: DVec3,
+    /// Panner for the speaker directions seen from the listener.
+    panner: VBAPanner,
+    distances: Vec<f64>,
+    gains: Vec<f64>,
+    delays: Vec<f64>,
+}
+
+impl ListenerCompensation {
+    /// Set up compensation for `panner` with physical speaker `positions`.
+    ///
+    /// The listener starts at the origin. Panner settings (normalization,
+    /// frozen, disabled speakers, ...) carry over to the derived panner.
+    ///
+    /// Returns an error if the number of positions does not match the
+    /// layout or a speaker sits on the listener.
+    pub fn new(panner: &VBAPanner, positions: &[DVec3]) -> Result<Self> {
+        let num_speakers = panner.full_layout().num_speakers();
+        if positions.len() != num_speakers {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "got {} speaker positions for {} speakers",
+                positions.len(),
+                num_speakers
+            )));
+        }
+
+        let mut compensation = Self {
+            base: panner.clone(),
+            positions: positions.to_vec(),
+            listener: DVec3::ZERO,
+            panner: panner.clone(),
+            distances: vec![0.0; num_speakers],
+            gains: vec![1.0; num_speakers],
+            delays: vec![0.0; num_speakers],
+        };
+        compensation.set_listener(DVec3::ZERO)?;
+        Ok(compensation)
+    }
+
+    /// Set up compensation from the speaker distances stored in the layout.
+    ///
+    /// Speakers added with
+    /// [`add_speaker_xyz`](crate::SpeakerConfigBuilder::add_speaker_xyz)
+    /// keep their measured distance; others are at 1 m.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::listener::ListenerCompensation;
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder()
+    ///     .add_speaker_xyz(1.0, 2.0, 0.0)
+    ///     .add_speaker_xyz(-1.5, 3.0, 0.0)
+    ///     .build()
+    ///     .unwrap();
+    /// let room = ListenerCompensation::from_speaker_distances(&panner).unwrap();
+    /// assert!(room.delays()[0] > 0.0 && room.delays()[1] == 0.0);
+    /// ```
+    pub fn from_speaker_distances(panner: &VBAPanner) -> Result<Self> {
+        let positions: Vec<DVec3> = panner
+            .full_layout()
+            .speakers()
+            .iter()
+            .map(|s| s.cartesian() * s.distance())
+            .collect();
+        Self::new(panner, &positions)
+    }
+
+    /// Move the listener, see [`set_listener`](Self::set_listener).
+    pub fn with_listener(mut self, listener: DVec3) -> Result<Self> {
+        self.set_listener(listener)?;
+        Ok(self)
+    }
+
+    /// Move the listener and re-derive directions, trims and delays.
+    ///
+    /// The layout is re-triangulated from the new directions. Returns an
+    /// error if a speaker sits on the listener or the directions cannot be
+    /// triangulated; the previous state is kept then.
+    pub fn set_listener(&mut self, listener: DVec3) -> Result<()> {
+        if !listener.is_finite() {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "listener position {} is not finite",
+                listener
+            )));
+        }
+
+        let layout = self.base.full_layout();
+        let planar = layout.mode() == PanningMode::TwoD;
+        let mut directions = Vec::with_capacity(self.positions.len());
+        let mut distances = Vec::with_capacity(self.positions.len());
+        for (index, &position) in self.positions.iter().enumerate() {
+            let offset = position - listener;
+            let distance = offset.length();
+            if distance < MIN_SPEAKER_DISTANCE {
+                return Err(VBAPError::InvalidConfiguration(format!(
+                    "speaker {} is at the listening position",
+                    index
+                )));
+            }
+            let (azimuth, elevation) = cartesian_to_spherical(offset);
+            // Horizontal layouts stay horizontal; height only changes distance
+            directions.push((azimuth, if planar { 0.0 } else { elevation }));
+            distances.push(distance);
+        }
+
+        let mut builder = SpeakerConfigBuilder::new()
+            .add_speakers(&directions)
+            .tolerances(*layout.tolerances())
+            .convention(layout.convention())
+            .dimension(if planar {
+                Dimension::Force2D
+            } else {
+                Dimension::Force3D
+            });
+        if layout.arc_ends().is_some() {
+            builder = builder.open_arc();
+        }
+        let panner = self.base.with_layout(builder.build_config()?)?;
+
+        let max_distance = distances.iter().copied().fold(0.0, f64::max);
+        self.gains = distances.iter().map(|d| d / max_distance).collect();
+        self.delays = distances
+            .iter()
+            .map(|d| (max_distance - d) / SPEED_OF_SOUND)
+            .collect();
+        self.distances = distances;
+        self.panner = panner;
+        self.listener = listener;
+        Ok(())
+    }
+
+    /// Get the listener position in meters.
+    #[inline]
+    pub fn listener(&self) -> DVec3 {
+        self.listener
+    }
+
+    /// Get the physical speaker positions in meters.
+    #[inline]
+    pub fn positions(&self) -> &[DVec3] {
+        &self.positions
+    }
+
+    /// Get the panner for the speaker directions seen from the listener.
+    ///
+    /// Its gains do not include the distance trims.
+    #[inline]
+    pub fn panner(&self) -> &VBAPanner {
+        &self.panner
+    }
+
+    /// Get the distance from the listener to each speaker in meters.
+    #[inline]
+    pub fn distances(&self) -> &[f64] {
+        &self.distances
+    }
+
+    /// Get the per-speaker level trims (linear, at most 1).
+    #[inline]
+    pub fn distance_gains(&self) -> &[f64] {
+        &self.gains
+    }
+
+    /// Get the per-speaker delays in seconds.
+    #[inline]
+    pub fn delays(&self) -> &[f64] {
+        &self.delays
+    }
+
+    /// Get the per-speaker delays in (fractional) samples.
+    pub fn delay_samples(&self, sample_rate: f64) -> Vec<f64> {
+        self.delays.iter().map(|d| d * sample_rate).collect()
+    }
+
+    /// Compute speaker gains for a source direction relative to the
+    /// listener, including the distance trims.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.panner().num_speakers()`.
+    pub fn compute_gains_into(&self, azimuth: f64, elevation: f64, gains: &mut [f64]) {
+        self.panner.compute_gains_into(azimuth, elevation, gains);
+        for (gain, trim) in gains.iter_mut().zip(&self.gains) {
+            *gain *= trim;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::spherical_to_cartesian;
+    use approx::assert_relative_eq;
+
+    fn ring(panner: &VBAPanner, radius: f64) -> Vec<DVec3> {
+        panner
+            .speakers()
+            .iter()
+            .map(|s| s.cartesian() * radius)
+            .collect()
+    }
+
+    #[test]
+    fn test_centered_listener_is_identity() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let room = ListenerCompensation::new(&panner, &ring(&panner, 3.0)).unwrap();
+
+        assert!(room.delays().iter().all(|&d| d.abs() < 1e-12));
+        let mut gains = vec![0.0; panner.num_speakers()];
+        for (azi, ele) in [(0.0, 0.0), (70.0, 20.0), (-150.0, 45.0)] {
+            room.compute_gains_into(azi, ele, &mut gains);
+            for (a, b) in gains.iter().zip(panner.compute_gains(azi, ele)) {
+                assert_relative_eq!(*a, b, epsilon = 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_offset_listener() {
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let positions = ring(&panner, 2.0);
+        let listener = DVec3::new(0.5, 0.3, 0.0);
+        let room = ListenerCompensation::new(&panner, &positions)
+            .unwrap()
+            .with_listener(listener)
+            .unwrap();
+
+        let farthest = (0..positions.len())
+            .max_by(|&a, &b| room.distances()[a].total_cmp(&room.distances()[b]))
+            .unwrap();
+        assert_relative_eq!(room.distance_gains()[farthest], 1.0);
+        assert_relative_eq!(room.delays()[farthest], 0.0);
+
+        for (i, &position) in positions.iter().enumerate() {
+            let distance = (position - listener).length();
+            assert_relative_eq!(room.distances()[i], distance, epsilon = 1e-12);
+            // Arrival times line up
+            assert_relative_eq!(
+                room.delays()[i] + distance / SPEED_OF_SOUND,
+                room.distances()[farthest] / SPEED_OF_SOUND,
+                epsilon = 1e-12
+            );
+
+            // A source in the direction of a speaker plays only from it
+            let (azi, ele) = cartesian_to_spherical(position - listener);
+            let mut gains = vec![0.0; panner.num_speakers()];
+            room.compute_gains_into(azi, ele, &mut gains);
+            assert_relative_eq!(gains[i], room.distance_gains()[i], epsilon = 1e-9);
+        }
+
+        // The effective direction of the front-left speaker moved right
+        let seen = room.panner().speakers()[0].cartesian();
+        assert!(seen.x < spherical_to_cartesian(30.0, 0.0).x);
+    }
+
+    #[test]
+    fn test_invalid_listener() {
+        let panner = VBAPanner::builder().quad().build().unwrap();
+        let positions = ring(&panner, 2.0);
+        assert!(ListenerCompensation::new(&panner, &positions[..3]).is_err());
+
+        let mut room = ListenerCompensation::new(&panner, &positions).unwrap();
+        assert!(room.set_listener(positions[1]).is_err());
+        assert_eq!(room.listener(), DVec3::ZERO);
+    }
+}
@@ -0,0 +1,196 @@
+//! Speaker subsets for routing sources to zones.
+//!
+//! Live mixes often send stems to a zone only ("surrounds only", "no
+//! center"). A [`SpeakerMask`] names such a subset; a panner restricted to
+//! it re-triangulates the remaining speakers so panning inside the zone is
+//! still properly normalized.
+
+use crate::error::{Result, VBAPError};
+use crate::speaker::Speaker;
+
+/// A subset of the speakers of a layout, stored as a bit set.
+///
+/// # Example
+///
+/// ```
+/// use vbap::{SpeakerMask, VBAPanner};
+///
+/// let panner = VBAPanner::builder().surround_7_1().build().unwrap();
+/// let surrounds = SpeakerMask::matching(panner.speakers(), |s| s.azimuth().abs() > 60.0);
+/// let zone = panner.restricted_to(&surrounds).unwrap();
+///
+/// let gains = zone.compute_gains(0.0, 0.0);
+/// assert!(gains[..3].iter().all(|&g| g == 0.0)); // L, R, C are silent
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SpeakerMask {
+    bits: Vec<u64>,
+    num_speakers: usize,
+}
+
+impl SpeakerMask {
+    /// Mask containing all `num_speakers` speakers.
+    pub fn all(num_speakers: usize) -> Self {
+        let mut mask = Self::none(num_speakers);
+        mask.bits.fill(u64::MAX);
+        mask.clear_unused_bits();
+        mask
+    }
+
+    /// Mask containing none of `num_speakers` speakers.
+    pub fn none(num_speakers: usize) -> Self {
+        Self {
+            bits: vec![0; (num_speakers + 63) / 64],
+            num_speakers,
+        }
+    }
+
+    /// Mask containing the given speaker indices.
+    ///
+    /// Returns an error if an index is not below `num_speakers`.
+    pub fn from_indices(num_speakers: usize, indices: &[usize]) -> Result<Self> {
+        let mut mask = Self::none(num_speakers);
+        for &index in indices {
+            if index >= num_speakers {
+                return Err(VBAPError::InvalidSpeakerIndex {
+                    index,
+                    num_speakers,
+                });
+            }
+            mask.insert(index);
+        }
+        Ok(mask)
+    }
+
+    /// Mask containing the speakers for which `predicate` returns true.
+    pub fn matching(speakers: &[Speaker], mut predicate: impl FnMut(&Speaker) -> bool) -> Self {
+        let mut mask = Self::none(speakers.len());
+        for (i, speaker) in speakers.iter().enumerate() {
+            if predicate(speaker) {
+                mask.insert(i);
+            }
+        }
+        mask
+    }
+
+    /// Get the number of speakers in the layout the mask is for.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.num_speakers
+    }
+
+    /// Get the number of speakers in the mask.
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|b| b.count_ones() as usize).sum()
+    }
+
+    /// Check whether a speaker is in the mask.
+    #[inline]
+    pub fn contains(&self, index: usize) -> bool {
+        index < self.num_speakers && self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Add a speaker to the mask.
+    ///
+    /// # Panics
+    /// Panics if `index >= self.num_speakers()`.
+    pub fn insert(&mut self, index: usize) {
+        self.check_index(index);
+        self.bits[index / 64] |= 1 << (index % 64);
+    }
+
+    /// Remove a speaker from the mask.
+    ///
+    /// # Panics
+    /// Panics if `index >= self.num_speakers()`.
+    pub fn remove(&mut self, index: usize) {
+        self.check_index(index);
+        self.bits[index / 64] &= !(1 << (index % 64));
+    }
+
+    /// Iterate over the indices of the speakers in the mask.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.num_speakers).filter(|&i| self.contains(i))
+    }
+
+    /// Mask of the speakers not in this mask.
+    pub fn inverted(&self) -> Self {
+        let mut mask = Self {
+            bits: self.bits.iter().map(|b| !b).collect(),
+            num_speakers: self.num_speakers,
+        };
+        mask.clear_unused_bits();
+        mask
+    }
+
+    /// Mask of the speakers in either mask.
+    ///
+    /// # Panics
+    /// Panics if the masks are for layouts of different sizes.
+    pub fn union(&self, other: &SpeakerMask) -> Self {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Mask of the speakers in both masks.
+    ///
+    /// # Panics
+    /// Panics if the masks are for layouts of different sizes.
+    pub fn intersection(&self, other: &SpeakerMask) -> Self {
+        self.combine(other, |a, b| a & b)
+    }
+
+    fn combine(&self, other: &SpeakerMask, op: impl Fn(u64, u64) -> u64) -> Self {
+        assert_eq!(
+            self.num_speakers, other.num_speakers,
+            "masks are for layouts of different sizes"
+        );
+        Self {
+            bits: self
+                .bits
+                .iter()
+                .zip(&other.bits)
+                .map(|(&a, &b)| op(a, b))
+                .collect(),
+            num_speakers: self.num_speakers,
+        }
+    }
+
+    fn check_index(&self, index: usize) {
+        assert!(
+            index < self.num_speakers,
+            "speaker index {} out of range for {} speakers",
+            index,
+            self.num_speakers
+        );
+    }
+
+    /// Keep bits past the last speaker zero so equality and counts hold.
+    fn clear_unused_bits(&mut self) {
+        let used = self.num_speakers % 64;
+        if used != 0 {
+            if let Some(last) = self.bits.last_mut() {
+                *last &= (1 << used) - 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_operations() {
+        let a = SpeakerMask::from_indices(70, &[0, 5, 69]).unwrap();
+        let b = SpeakerMask::from_indices(70, &[5, 6]).unwrap();
+
+        assert_eq!(a.count(), 3);
+        assert!(a.contains(69) && !a.contains(70));
+        assert_eq!(a.union(&b).iter().collect::<Vec<_>>(), [0, 5, 6, 69]);
+        assert_eq!(a.intersection(&b).iter().collect::<Vec<_>>(), [5]);
+        assert_eq!(a.inverted().count(), 67);
+        assert_eq!(SpeakerMask::all(70).inverted(), SpeakerMask::none(70));
+
+        assert!(SpeakerMask::from_indices(4, &[4]).is_err());
+    }
+}
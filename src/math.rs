@@ -2,7 +2,7 @@
 //!
 //! Uses `glam` for SIMD-optimized vector operations.
 
-use glam::DVec3;
+use glam::{DQuat, DVec3, EulerRot};
 
 /// Convert spherical coordinates (azimuth, elevation in degrees) to Cartesian unit vector.
 ///
@@ -40,14 +40,197 @@ pub fn cartesian_to_spherical(v: DVec3) -> (f64, f64) {
     (azimuth, elevation)
 }
 
-/// Check if two great circle arcs intersect on a unit sphere.
+/// Wrap an azimuth in degrees into the range `(-180, 180]`.
 ///
-/// Arc 1: from a1 to a2
-/// Arc 2: from b1 to b2
+/// -180° becomes 180°, so each direction has exactly one azimuth.
+///
+/// # Example
+///
+/// ```
+/// use vbap::math::wrap_azimuth;
+///
+/// assert_eq!(wrap_azimuth(270.0), -90.0);
+/// assert_eq!(wrap_azimuth(-180.0), 180.0);
+/// assert_eq!(wrap_azimuth(-540.0), 180.0);
+/// ```
+#[inline]
+pub fn wrap_azimuth(azimuth: f64) -> f64 {
+    let wrapped = (azimuth + 180.0).rem_euclid(360.0) - 180.0;
+    if wrapped == -180.0 {
+        180.0
+    } else {
+        wrapped
+    }
+}
+
+/// Clamp an elevation in degrees into the range `[-90, 90]`.
+#[inline]
+pub fn clamp_elevation(elevation: f64) -> f64 {
+    elevation.clamp(-90.0, 90.0)
+}
+
+/// Angle in degrees between two directions given as (azimuth, elevation)
+/// in degrees, along the great circle through them.
+///
+/// Accurate for nearby directions too, where the usual `acos` of the dot
+/// product loses precision.
+///
+/// # Example
+///
+/// ```
+/// use vbap::math::great_circle_distance;
+///
+/// // Across the rear seam rather than the long way round
+/// assert!((great_circle_distance(170.0, 0.0, -170.0, 0.0) - 20.0).abs() < 1e-9);
+/// // All azimuths meet at the zenith
+/// assert!(great_circle_distance(0.0, 90.0, 120.0, 90.0) < 1e-9);
+/// ```
+pub fn great_circle_distance(
+    azimuth_a: f64,
+    elevation_a: f64,
+    azimuth_b: f64,
+    elevation_b: f64,
+) -> f64 {
+    let a = spherical_to_cartesian(azimuth_a, elevation_a);
+    let b = spherical_to_cartesian(azimuth_b, elevation_b);
+    a.cross(b).length().atan2(a.dot(b)).to_degrees()
+}
+
+/// Interpolate between two azimuths in degrees the short way around.
+///
+/// `t` = 0 gives `a` and 1 gives `b`, both wrapped into `(-180, 180]`, so
+/// going from 170° to -170° passes through 180° rather than 0°. Azimuths
+/// exactly opposite each other are interpolated to the left.
+///
+/// # Example
+///
+/// ```
+/// use vbap::math::lerp_azimuth;
+///
+/// assert_eq!(lerp_azimuth(170.0, -170.0, 0.5), 180.0);
+/// assert_eq!(lerp_azimuth(-10.0, 30.0, 0.25), 0.0);
+/// ```
+#[inline]
+pub fn lerp_azimuth(a: f64, b: f64, t: f64) -> f64 {
+    wrap_azimuth(a + wrap_azimuth(b - a) * t)
+}
+
+/// Spherical linear interpolation between two unit direction vectors.
+///
+/// Moves along the shorter great-circle arc at constant angular speed:
+/// `t` = 0 gives `a`, 1 gives `b`, and values outside `[0, 1]` extrapolate
+/// along the same circle. Opposite directions have no single shortest arc;
+/// they are joined through the direction closest to straight up.
+///
+/// # Example
+///
+/// ```
+/// use vbap::math::{cartesian_to_spherical, slerp, spherical_to_cartesian};
+///
+/// let left = spherical_to_cartesian(170.0, 0.0);
+/// let right = spherical_to_cartesian(-170.0, 0.0);
+/// let (azimuth, _) = cartesian_to_spherical(slerp(left, right, 0.5));
+/// assert!((azimuth.abs() - 180.0).abs() < 1e-9);
+/// ```
+pub fn slerp(a: DVec3, b: DVec3, t: f64) -> DVec3 {
+    let cos = a.dot(b).clamp(-1.0, 1.0);
+    let angle = cos.acos();
+    // Perpendicular to `a` in the plane of the arc. Its length is the sine
+    // of the angle, which rounding leaves slightly above 0 for opposite
+    // directions.
+    let rest = b - a * cos;
+    let perpendicular = if rest.length() > 1e-12 {
+        rest.normalize()
+    } else if cos > 0.0 {
+        return a;
+    } else {
+        let up = (DVec3::Z - a * a.z).normalize_or_zero();
+        if up != DVec3::ZERO {
+            up
+        } else {
+            DVec3::X
+        }
+    };
+    let (sin, cos) = (t * angle).sin_cos();
+    a * cos + perpendicular * sin
+}
+
+/// Build a rotation from yaw, pitch and roll in degrees.
+///
+/// In the crate's frame (x = left, y = front, z = up): positive yaw turns
+/// left around the vertical axis, positive pitch tilts the front upwards
+/// and positive roll tilts the top to the left. They are applied in the
+/// usual head-tracker order, yaw then pitch then roll, each about the
+/// already rotated axes.
+///
+/// # Example
+///
+/// ```
+/// use vbap::math::{euler_rotation, rotate_direction};
+///
+/// // Head tracking: sources stay put in the room while the head turns, so
+/// // rotate them by the inverse of the head orientation
+/// let head = euler_rotation(30.0, 0.0, 0.0);
+/// let (azimuth, _) = rotate_direction(head.inverse(), 30.0, 0.0);
+/// assert!(azimuth.abs() < 1e-9);
+/// ```
+#[inline]
+pub fn euler_rotation(yaw: f64, pitch: f64, roll: f64) -> DQuat {
+    // Azimuth grows from y towards x, against the right-hand sense about z
+    DQuat::from_euler(
+        EulerRot::ZXY,
+        -yaw.to_radians(),
+        pitch.to_radians(),
+        roll.to_radians(),
+    )
+}
+
+/// Rotate a direction given as (azimuth, elevation) in degrees.
+///
+/// Returns the rotated (azimuth, elevation). At the poles the azimuth is
+/// 0.
+#[inline]
+pub fn rotate_direction(rotation: DQuat, azimuth: f64, elevation: f64) -> (f64, f64) {
+    cartesian_to_spherical(rotation * spherical_to_cartesian(azimuth, elevation))
+}
+
+/// Solid angle in steradians of the spherical triangle spanned by three
+/// unit vectors (Van Oosterom and Strackee).
+pub(crate) fn solid_angle(a: DVec3, b: DVec3, c: DVec3) -> f64 {
+    let numerator = a.dot(b.cross(c)).abs();
+    let denominator = 1.0 + a.dot(b) + b.dot(c) + c.dot(a);
+    2.0 * numerator.atan2(denominator)
+}
+
+/// Check whether two great-circle arcs on the unit sphere intersect.
+///
+/// Each arc is the shorter path between its endpoints, which must be unit
+/// vectors that are neither equal nor antipodal. Arcs only touching at an
+/// endpoint of either arc (a shared endpoint, or one arc ending on the
+/// other) do not intersect. Neither do arcs on the same great circle, even
+/// if they overlap.
+///
+/// `tolerance` is passed on to [`point_on_arc`] for both candidate crossing
+/// points, and a crossing within `tolerance` radians of an endpoint counts
+/// as touching; [`Tolerances::DEFAULT`](crate::Tolerances::DEFAULT) uses
+/// `1e-6`.
 ///
 /// Based on Pulkki's VBAP implementation.
+///
+/// # Example
+///
+/// ```
+/// use vbap::math::{arcs_intersect, spherical_to_cartesian};
+///
+/// let horizontal = (spherical_to_cartesian(-30.0, 0.0), spherical_to_cartesian(30.0, 0.0));
+/// let vertical = (spherical_to_cartesian(0.0, -30.0), spherical_to_cartesian(0.0, 30.0));
+/// assert!(arcs_intersect(horizontal.0, horizontal.1, vertical.0, vertical.1, 1e-6));
+///
+/// let high = (spherical_to_cartesian(-30.0, 45.0), spherical_to_cartesian(30.0, 45.0));
+/// assert!(!arcs_intersect(horizontal.0, horizontal.1, high.0, high.1, 1e-6));
+/// ```
 #[inline]
-pub(crate) fn lines_intersect(a1: DVec3, a2: DVec3, b1: DVec3, b2: DVec3) -> bool {
+pub fn arcs_intersect(a1: DVec3, a2: DVec3, b1: DVec3, b2: DVec3, tolerance: f64) -> bool {
     // Normal vectors to the planes containing each arc
     let n1 = a1.cross(a2);
     let n2 = b1.cross(b2);
@@ -65,21 +248,44 @@ pub(crate) fn lines_intersect(a1: DVec3, a2: DVec3, b1: DVec3, b2: DVec3) -> boo
     let p1 = int_normalized;
     let p2 = -int_normalized;
 
-    // Check if either intersection point lies on both arcs
-    (point_on_arc(p1, a1, a2) && point_on_arc(p1, b1, b2))
-        || (point_on_arc(p2, a1, a2) && point_on_arc(p2, b1, b2))
+    // Check if either intersection point lies inside both arcs
+    let crosses = |p: DVec3| {
+        point_on_arc(p, a1, a2, tolerance)
+            && point_on_arc(p, b1, b2, tolerance)
+            && [a1, a2, b1, b2]
+                .iter()
+                .all(|&end| end.angle_between(p) > tolerance)
+    };
+    crosses(p1) || crosses(p2)
 }
 
-/// Check if point p lies on the arc from a to b (shorter path on great circle).
+/// Check whether a point lies on the great-circle arc from `a` to `b`.
+///
+/// The arc is the shorter path between the endpoints; all three vectors
+/// must be unit length. The point is on the arc when the angles from it to
+/// both endpoints add up to the arc's length, within `tolerance` radians.
+/// Since that sum grows only quadratically as the point leaves the arc, a
+/// tolerance of `t` admits points up to roughly `sqrt(t * length / 2)`
+/// radians to the side of a short arc, and about `t` radians past either
+/// end.
+///
+/// # Example
+///
+/// ```
+/// use vbap::math::{point_on_arc, spherical_to_cartesian};
+///
+/// let left = spherical_to_cartesian(30.0, 0.0);
+/// let right = spherical_to_cartesian(-30.0, 0.0);
+/// assert!(point_on_arc(spherical_to_cartesian(10.0, 0.0), left, right, 1e-6));
+/// assert!(!point_on_arc(spherical_to_cartesian(40.0, 0.0), left, right, 1e-6));
+/// ```
 #[inline]
-fn point_on_arc(p: DVec3, a: DVec3, b: DVec3) -> bool {
+pub fn point_on_arc(p: DVec3, a: DVec3, b: DVec3, tolerance: f64) -> bool {
     let angle_ab = a.angle_between(b);
     let angle_ap = a.angle_between(p);
     let angle_pb = p.angle_between(b);
 
-    // Point is on arc if sum of angles to endpoints equals the arc angle
-    // (with some tolerance for floating point)
-    (angle_ap + angle_pb - angle_ab).abs() < 1e-6
+    (angle_ap + angle_pb - angle_ab).abs() < tolerance
 }
 
 #[cfg(test)]
@@ -127,4 +333,127 @@ mod tests {
             assert_relative_eq!(ele, ele2, epsilon = 1e-9);
         }
     }
+
+    #[test]
+    fn test_wrapping() {
+        assert_eq!(wrap_azimuth(180.0), 180.0);
+        assert_eq!(wrap_azimuth(-180.0), 180.0);
+        assert_eq!(wrap_azimuth(360.0), 0.0);
+        assert_relative_eq!(wrap_azimuth(-190.0), 170.0);
+        assert_relative_eq!(wrap_azimuth(725.0), 5.0);
+        assert_eq!(clamp_elevation(95.0), 90.0);
+        assert_eq!(clamp_elevation(-120.0), -90.0);
+        assert_eq!(clamp_elevation(30.0), 30.0);
+    }
+
+    #[test]
+    fn test_great_circle_distance() {
+        assert_relative_eq!(
+            great_circle_distance(30.0, 0.0, -30.0, 0.0),
+            60.0,
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(
+            great_circle_distance(0.0, 0.0, 180.0, 0.0),
+            180.0,
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(
+            great_circle_distance(-180.0, 10.0, 180.0, 10.0),
+            0.0,
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(
+            great_circle_distance(90.0, 45.0, 0.0, 90.0),
+            45.0,
+            epsilon = 1e-9
+        );
+        // Far below where acos rounds to 0
+        let tiny = great_circle_distance(0.0, 0.0, 1e-7, 0.0);
+        assert_relative_eq!(tiny, 1e-7, max_relative = 1e-6);
+    }
+
+    #[test]
+    fn test_euler_rotation() {
+        let check = |rotation: DQuat, from: (f64, f64), to: (f64, f64)| {
+            let (azimuth, elevation) = rotate_direction(rotation, from.0, from.1);
+            assert_relative_eq!(azimuth, to.0, epsilon = 1e-9);
+            assert_relative_eq!(elevation, to.1, epsilon = 1e-9);
+        };
+        check(euler_rotation(90.0, 0.0, 0.0), (0.0, 0.0), (90.0, 0.0));
+        check(euler_rotation(0.0, 30.0, 0.0), (0.0, 0.0), (0.0, 30.0));
+        check(euler_rotation(0.0, 0.0, 90.0), (0.0, 90.0), (90.0, 0.0));
+        // Pitch is applied about the turned left axis
+        check(euler_rotation(90.0, 30.0, 0.0), (0.0, 0.0), (90.0, 30.0));
+        check(
+            euler_rotation(0.0, 0.0, 0.0),
+            (-135.0, 20.0),
+            (-135.0, 20.0),
+        );
+    }
+
+    #[test]
+    fn test_lerp_azimuth() {
+        assert_relative_eq!(lerp_azimuth(170.0, -170.0, 0.25), 175.0);
+        assert_relative_eq!(lerp_azimuth(170.0, -170.0, 0.75), -175.0);
+        assert_relative_eq!(lerp_azimuth(-30.0, 30.0, 0.5), 0.0);
+        assert_relative_eq!(lerp_azimuth(0.0, 180.0, 0.5), 90.0);
+        assert_relative_eq!(lerp_azimuth(540.0, 0.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_slerp() {
+        let front = spherical_to_cartesian(0.0, 0.0);
+        let left = spherical_to_cartesian(90.0, 0.0);
+        for (t, azimuth) in [
+            (0.0, 0.0),
+            (1.0 / 3.0, 30.0),
+            (1.0, 90.0),
+            (-1.0 / 3.0, -30.0),
+        ] {
+            let v = slerp(front, left, t);
+            assert_relative_eq!(v.length(), 1.0, epsilon = 1e-12);
+            assert_relative_eq!(cartesian_to_spherical(v).0, azimuth, epsilon = 1e-9);
+        }
+        assert_eq!(slerp(left, left, 0.5), left);
+
+        // Opposite directions pass overhead, or sideways at the poles
+        let back = spherical_to_cartesian(180.0, 0.0);
+        assert_relative_eq!(slerp(front, back, 0.5).z, 1.0, epsilon = 1e-12);
+        let down = -DVec3::Z;
+        assert_relative_eq!(slerp(DVec3::Z, down, 0.5).x, 1.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_arc_tolerance() {
+        let a = spherical_to_cartesian(-30.0, 0.0);
+        let b = spherical_to_cartesian(30.0, 0.0);
+
+        // 0.01° off the arc exceeds the angle sum by about 3e-7 radians
+        let near = spherical_to_cartesian(0.0, 0.01);
+        assert!(point_on_arc(near, a, b, 1e-6));
+        assert!(!point_on_arc(near, a, b, 1e-8));
+
+        // Touching at an endpoint is not crossing
+        let c = spherical_to_cartesian(30.0, 40.0);
+        assert!(!arcs_intersect(a, b, b, c, 1e-6));
+        let front = spherical_to_cartesian(0.0, 0.0);
+        assert!(!arcs_intersect(a, b, front, c, 1e-6));
+        assert!(arcs_intersect(
+            a,
+            b,
+            spherical_to_cartesian(0.0, -10.0),
+            c,
+            1e-6
+        ));
+        // Arcs on one great circle never intersect
+        let d = spherical_to_cartesian(10.0, 0.0);
+        assert!(!arcs_intersect(
+            a,
+            b,
+            d,
+            spherical_to_cartesian(60.0, 0.0),
+            1e-6
+        ));
+    }
 }
@@ -0,0 +1,184 @@
+//! Export of a layout's triangulation for visualization.
+//!
+//! A [`Mesh`] holds the speaker directions and the triplets (or pairs, for
+//! 2D layouts) the panner chooses between. Write it as Wavefront OBJ or
+//! ASCII PLY to inspect the speaker hull in Blender, MeshLab or a debug
+//! viewer and check that the facets look sane.
+//!
+//! Coordinates are the crate's Cartesian frame on the unit sphere: x to
+//! the left, y to the front, z up. Import with Z as the up axis.
+
+use std::fmt::Write;
+
+use glam::DVec3;
+
+use crate::config::SpeakerConfig;
+
+/// Speaker positions and the tuples connecting them.
+///
+/// # Example
+///
+/// ```
+/// use vbap::mesh::Mesh;
+/// use vbap::SpeakerConfigBuilder;
+///
+/// let config = SpeakerConfigBuilder::new().atmos_7_1_4().build_config().unwrap();
+/// let mesh = Mesh::from_config(&config);
+/// assert_eq!(mesh.vertices().len(), 11);
+/// assert_eq!(mesh.triangles().len(), config.tuples().len());
+///
+/// let obj = mesh.to_obj();
+/// assert!(obj.lines().any(|line| line.starts_with("f ")));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mesh {
+    vertices: Vec<DVec3>,
+    triangles: Vec<[usize; 3]>,
+    edges: Vec<[usize; 2]>,
+}
+
+impl Mesh {
+    /// Build the mesh of a layout's triangulation.
+    ///
+    /// Virtual speakers are included, since the panner uses them as
+    /// corners. Triangles are wound counter-clockwise seen from outside, so
+    /// their normals point away from the listener.
+    pub fn from_config(config: &SpeakerConfig) -> Self {
+        let vertices: Vec<DVec3> = config
+            .speakers()
+            .iter()
+            .map(|speaker| speaker.cartesian().normalize())
+            .collect();
+
+        let mut triangles = Vec::new();
+        let mut edges = Vec::new();
+        for tuple in config.tuples().iter() {
+            match *tuple.speaker_indices() {
+                [a, b, c] => {
+                    let normal = (vertices[b] - vertices[a]).cross(vertices[c] - vertices[a]);
+                    if normal.dot(vertices[a] + vertices[b] + vertices[c]) < 0.0 {
+                        triangles.push([a, c, b]);
+                    } else {
+                        triangles.push([a, b, c]);
+                    }
+                }
+                [a, b] => edges.push([a, b]),
+                _ => {}
+            }
+        }
+
+        Self {
+            vertices,
+            triangles,
+            edges,
+        }
+    }
+
+    /// Get the vertex positions, one per speaker.
+    #[inline]
+    pub fn vertices(&self) -> &[DVec3] {
+        &self.vertices
+    }
+
+    /// Get the triangles of a 3D layout, as vertex indices.
+    #[inline]
+    pub fn triangles(&self) -> &[[usize; 3]] {
+        &self.triangles
+    }
+
+    /// Get the speaker pairs of a 2D layout, as vertex indices.
+    #[inline]
+    pub fn edges(&self) -> &[[usize; 2]] {
+        &self.edges
+    }
+
+    /// Write the mesh as Wavefront OBJ.
+    ///
+    /// Triangles become faces and 2D pairs become line elements.
+    pub fn to_obj(&self) -> String {
+        let mut out = String::new();
+        for v in &self.vertices {
+            let _ = writeln!(out, "v {} {} {}", v.x, v.y, v.z);
+        }
+        // OBJ indices are 1-based
+        for [a, b, c] in &self.triangles {
+            let _ = writeln!(out, "f {} {} {}", a + 1, b + 1, c + 1);
+        }
+        for [a, b] in &self.edges {
+            let _ = writeln!(out, "l {} {}", a + 1, b + 1);
+        }
+        out
+    }
+
+    /// Write the mesh as ASCII PLY.
+    ///
+    /// Triangles become faces and 2D pairs become edges.
+    pub fn to_ply(&self) -> String {
+        let mut out = String::from("ply\nformat ascii 1.0\n");
+        let _ = writeln!(out, "element vertex {}", self.vertices.len());
+        out.push_str("property double x\nproperty double y\nproperty double z\n");
+        let _ = writeln!(out, "element face {}", self.triangles.len());
+        out.push_str("property list uchar int vertex_indices\n");
+        let _ = writeln!(out, "element edge {}", self.edges.len());
+        out.push_str("property int vertex1\nproperty int vertex2\nend_header\n");
+
+        for v in &self.vertices {
+            let _ = writeln!(out, "{} {} {}", v.x, v.y, v.z);
+        }
+        for [a, b, c] in &self.triangles {
+            let _ = writeln!(out, "3 {a} {b} {c}");
+        }
+        for [a, b] in &self.edges {
+            let _ = writeln!(out, "{a} {b}");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpeakerConfigBuilder;
+
+    #[test]
+    fn test_triangles_face_outwards() {
+        let config = SpeakerConfigBuilder::new()
+            .add_speaker(0.0, 0.0)
+            .add_speaker(120.0, 0.0)
+            .add_speaker(-120.0, 0.0)
+            .add_speaker(0.0, 90.0)
+            .add_speaker(0.0, -90.0)
+            .build_config()
+            .unwrap();
+        let mesh = Mesh::from_config(&config);
+        assert_eq!(mesh.triangles().len(), 6);
+        assert!(mesh.edges().is_empty());
+
+        let v = mesh.vertices();
+        for &[a, b, c] in mesh.triangles() {
+            let normal = (v[b] - v[a]).cross(v[c] - v[a]);
+            assert!(normal.dot(v[a]) > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_2d_export() {
+        let config = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .build_config()
+            .unwrap();
+        let mesh = Mesh::from_config(&config);
+        assert!(mesh.triangles().is_empty());
+        assert_eq!(mesh.edges().len(), 5);
+
+        let obj = mesh.to_obj();
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 5);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("l ")).count(), 5);
+
+        let ply = mesh.to_ply();
+        assert!(ply.contains("element vertex 5\n"));
+        assert!(ply.contains("element face 0\n"));
+        assert!(ply.contains("element edge 5\n"));
+        assert_eq!(ply.lines().count(), 12 + 5 + 5);
+    }
+}
@@ -0,0 +1,1252 @@
+//! Multi-source mixer.
+//!
+//! Renders any number of mono sources into one output channel per speaker.
+//! Gains are recomputed once per block and ramped linearly across it, so
+//! moving sources do not produce zipper noise.
+//!
+//! Sources also have a distance. A [`DistanceModel`] turns it into
+//! attenuation, and [`Mixer::with_doppler`] delays each source by its
+//! travel time so that approaching and receding sources shift in pitch.
+//! [`Mixer::with_air_absorption`] dulls far sources with a low-pass filter
+//! that follows their distance, and [`Mixer::with_occlusion`] lets each
+//! source be ducked and muffled by an [`Occlusion`], for example behind
+//! walls in a game.
+//!
+//! Output channels can be muted and soloed, and every block is metered:
+//! [`Mixer::levels`] holds each channel's peak and RMS [`Level`], and
+//! [`Mixer::with_meter`] passes them to a callback after every block.
+//!
+//! With the `trajectory` feature, sources can follow a [`Trajectory`]:
+//! [`Mixer::process_at`] moves them to their position at the block's time,
+//! and [`Mixer::render`] renders whole input buffers offline.
+//!
+//! Requires the `render` feature.
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::dsp::{DelayLine, OnePole};
+use crate::panner::{PanningState, VBAPanner};
+#[cfg(feature = "trajectory")]
+use crate::trajectory::Trajectory;
+use crate::util::{db_to_lin, lin_to_db};
+
+/// Identifier of a source in a [`Mixer`].
+pub type SourceId = usize;
+
+/// Speed of sound in air at 20 °C, in meters per second.
+pub const SPEED_OF_SOUND: f64 = 343.0;
+
+/// How a source's gain falls off with its distance.
+///
+/// Distances are in the same unit as `reference`, meters when Doppler is
+/// enabled. Sources closer than `reference` are never boosted.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum DistanceModel {
+    /// No attenuation.
+    #[default]
+    None,
+    /// `reference / (reference + rolloff * (distance - reference))`; a
+    /// rolloff of 1 halves the gain (-6 dB) per doubling of distance.
+    Inverse {
+        /// Distance with unity gain.
+        reference: f64,
+        /// Rolloff factor.
+        rolloff: f64,
+    },
+    /// `(reference / distance)²`, -12 dB per doubling of distance.
+    InverseSquare {
+        /// Distance with unity gain.
+        reference: f64,
+    },
+    /// `reference / distance` with the distance clamped to
+    /// `[reference, max]`, so sources beyond `max` stop getting quieter.
+    Clamped {
+        /// Distance with unity gain.
+        reference: f64,
+        /// Distance beyond which the gain stays constant.
+        max: f64,
+    },
+    /// Gain computed from the distance by a custom curve.
+    Custom(fn(f64) -> f64),
+}
+
+impl DistanceModel {
+    /// Compute the linear gain at `distance`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::mixer::DistanceModel;
+    ///
+    /// let model = DistanceModel::Inverse { reference: 1.0, rolloff: 1.0 };
+    /// assert_eq!(model.gain(0.5), 1.0);
+    /// assert_eq!(model.gain(4.0), 0.25);
+    /// ```
+    pub fn gain(&self, distance: f64) -> f64 {
+        match *self {
+            DistanceModel::None => 1.0,
+            DistanceModel::Inverse { reference, rolloff } => {
+                let excess = (distance - reference).max(0.0);
+                reference / (reference + rolloff.max(0.0) * excess)
+            }
+            DistanceModel::InverseSquare { reference } => {
+                (reference / distance.max(reference)).powi(2)
+            }
+            DistanceModel::Clamped { reference, max } => {
+                reference / distance.clamp(reference, max.max(reference))
+            }
+            DistanceModel::Custom(curve) => curve(distance),
+        }
+    }
+}
+
+/// Frequency at which air absorption is specified and matched, in Hz.
+const AIR_REFERENCE_FREQUENCY: f64 = 10_000.0;
+
+/// Atmospheric attenuation of high frequencies over distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AirAbsorption {
+    /// Attenuation in dB per meter at 10 kHz, growing with the square of
+    /// the frequency.
+    Coefficient(f64),
+    /// Attenuation of air at `temperature` °C and `humidity` percent
+    /// relative humidity at sea level, from ISO 9613-1.
+    Atmosphere {
+        /// Temperature in degrees Celsius.
+        temperature: f64,
+        /// Relative humidity in percent.
+        humidity: f64,
+    },
+}
+
+impl Default for AirAbsorption {
+    /// 20 °C at 50 % relative humidity.
+    fn default() -> Self {
+        AirAbsorption::Atmosphere {
+            temperature: 20.0,
+            humidity: 50.0,
+        }
+    }
+}
+
+impl AirAbsorption {
+    /// Compute the attenuation in dB per meter at `frequency` Hz.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::mixer::AirAbsorption;
+    ///
+    /// // Roughly 5 dB per kilometer at 1 kHz in mild air
+    /// let db = AirAbsorption::default().attenuation(1000.0);
+    /// assert!(db > 0.004 && db < 0.006);
+    /// ```
+    pub fn attenuation(&self, frequency: f64) -> f64 {
+        let f2 = frequency * frequency;
+        match *self {
+            AirAbsorption::Coefficient(db) => {
+                db.max(0.0) * f2 / (AIR_REFERENCE_FREQUENCY * AIR_REFERENCE_FREQUENCY)
+            }
+            AirAbsorption::Atmosphere {
+                temperature,
+                humidity,
+            } => {
+                let t = temperature + 273.15;
+                let t_rel = t / 293.15;
+                // Molar concentration of water vapour in percent
+                let saturation = 10f64.powf(-6.8346 * (273.16 / t).powf(1.261) + 4.6151);
+                let h = humidity.clamp(0.0, 100.0) * saturation;
+                // Relaxation frequencies of oxygen and nitrogen
+                let fr_o = 24.0 + 4.04e4 * h * (0.02 + h) / (0.391 + h);
+                let fr_n = t_rel.powf(-0.5)
+                    * (9.0 + 280.0 * h * (-4.170 * (t_rel.powf(-1.0 / 3.0) - 1.0)).exp());
+                8.686
+                    * f2
+                    * (1.84e-11 * t_rel.sqrt()
+                        + t_rel.powf(-2.5)
+                            * (0.01275 * (-2239.1 / t).exp() / (fr_o + f2 / fr_o)
+                                + 0.1068 * (-3352.0 / t).exp() / (fr_n + f2 / fr_n)))
+            }
+        }
+    }
+}
+
+/// Ducking and muffling of a source behind geometry.
+///
+/// Game engines typically set it from a ray cast between source and
+/// listener each frame. The attenuation applies to any mixer; the low-pass
+/// needs [`Mixer::with_occlusion`].
+///
+/// # Example
+///
+/// ```
+/// use vbap::mixer::Occlusion;
+///
+/// // Behind a wooden door: 6 dB quieter, muffled above 1.5 kHz
+/// let door = Occlusion::new(6.0, 1500.0);
+/// assert!((door.gain() - 0.501).abs() < 1e-3);
+/// assert!(Occlusion::NONE.is_none());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Occlusion {
+    /// Attenuation in dB, at least 0.
+    pub attenuation: f64,
+    /// Cutoff (-3 dB) frequency of the low-pass in Hz; infinite for none.
+    pub cutoff: f64,
+}
+
+impl Occlusion {
+    /// No attenuation or filtering.
+    pub const NONE: Self = Self {
+        attenuation: 0.0,
+        cutoff: f64::INFINITY,
+    };
+
+    /// Create an occlusion of `attenuation` dB (clamped to at least 0) and
+    /// a low-pass at `cutoff` Hz.
+    pub fn new(attenuation: f64, cutoff: f64) -> Self {
+        Self {
+            attenuation: attenuation.max(0.0),
+            cutoff,
+        }
+    }
+
+    /// Get the linear gain of the attenuation.
+    #[inline]
+    pub fn gain(&self) -> f64 {
+        db_to_lin(-self.attenuation.max(0.0))
+    }
+
+    /// Check whether the occlusion neither attenuates nor filters.
+    #[inline]
+    pub fn is_none(&self) -> bool {
+        self.attenuation <= 0.0 && self.cutoff == f64::INFINITY
+    }
+}
+
+impl Default for Occlusion {
+    fn default() -> Self {
+        Self::NONE
+    }
+}
+
+/// Level of an output channel over one block.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Level {
+    /// Largest absolute sample.
+    pub peak: f32,
+    /// Root mean square of the samples.
+    pub rms: f32,
+}
+
+impl Level {
+    /// Measure a block of samples. An empty block is silent.
+    pub fn measure(samples: &[f32]) -> Self {
+        if samples.is_empty() {
+            return Self::default();
+        }
+        let (peak, sum) = samples.iter().fold((0.0f32, 0.0f64), |(peak, sum), &x| {
+            (peak.max(x.abs()), sum + x as f64 * x as f64)
+        });
+        Self {
+            peak,
+            rms: (sum / samples.len() as f64).sqrt() as f32,
+        }
+    }
+
+    /// Get the peak in dBFS, negative infinity for silence.
+    #[inline]
+    pub fn peak_db(&self) -> f64 {
+        lin_to_db(self.peak as f64)
+    }
+
+    /// Get the RMS in dBFS, negative infinity for silence.
+    #[inline]
+    pub fn rms_db(&self) -> f64 {
+        lin_to_db(self.rms as f64)
+    }
+}
+
+/// Callback receiving the output levels after every block.
+type MeterFn = dyn Fn(&[Level]) + Send + Sync;
+
+#[derive(Clone)]
+struct Meter(Arc<MeterFn>);
+
+impl fmt::Debug for Meter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Meter(..)")
+    }
+}
+
+/// Solo and mute state of an output channel.
+#[derive(Clone, Copy, Debug)]
+struct Channel {
+    muted: bool,
+    soloed: bool,
+    /// Gain the channel ended the last block at, 0 or 1 when settled.
+    gain: f64,
+}
+
+impl Channel {
+    /// Gain the channel ramps to, given whether any channel is soloed.
+    /// Mute wins over solo.
+    #[inline]
+    fn target(&self, any_solo: bool) -> f64 {
+        if self.muted || (any_solo && !self.soloed) {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Per-source propagation delay for Doppler.
+#[derive(Clone, Debug)]
+struct Propagation {
+    line: DelayLine,
+    samples_per_meter: f64,
+    /// Delay in samples reached at the end of the previous block.
+    current: Option<f64>,
+}
+
+impl Propagation {
+    fn new(samples_per_meter: f64, max_delay: usize) -> Self {
+        Self {
+            line: DelayLine::new(max_delay),
+            samples_per_meter,
+            current: None,
+        }
+    }
+
+    /// Delay `input` into `output`, ramping the delay towards the travel
+    /// time of `distance` meters.
+    fn process(&mut self, input: &[f32], distance: f64, output: &mut Vec<f32>) {
+        let target = (distance * self.samples_per_meter).min(self.line.max_delay() as f64);
+        let start = self.current.unwrap_or(target);
+        let step = (target - start) / input.len().max(1) as f64;
+
+        output.clear();
+        for (n, &x) in input.iter().enumerate() {
+            let delay = start + step * (n + 1) as f64;
+            output.push(self.line.process_sample(x, delay));
+        }
+        self.current = Some(target);
+    }
+}
+
+/// Per-source air absorption filter.
+#[derive(Clone, Debug)]
+struct Absorber {
+    filter: OnePole,
+    sample_rate: f64,
+    /// Frequency the filter is matched at, below Nyquist.
+    frequency: f64,
+    /// Attenuation at `frequency` in dB per meter.
+    db_per_meter: f64,
+}
+
+impl Absorber {
+    fn new(sample_rate: f64, absorption: &AirAbsorption) -> Self {
+        let frequency = AIR_REFERENCE_FREQUENCY.min(0.45 * sample_rate);
+        Self {
+            filter: OnePole::new(),
+            sample_rate,
+            frequency,
+            db_per_meter: absorption.attenuation(frequency),
+        }
+    }
+
+    /// Filter `buffer` in place for a source `distance` meters away.
+    fn process(&mut self, buffer: &mut [f32], distance: f64) {
+        let gain = db_to_lin(-self.db_per_meter * distance);
+        self.filter
+            .set_gain_at(self.sample_rate, self.frequency, gain);
+        self.filter.process(buffer);
+    }
+}
+
+/// Per-source occlusion low-pass.
+#[derive(Clone, Debug)]
+struct Occluder {
+    filter: OnePole,
+    sample_rate: f64,
+}
+
+impl Occluder {
+    fn new(sample_rate: f64) -> Self {
+        Self {
+            filter: OnePole::new(),
+            sample_rate,
+        }
+    }
+
+    /// Filter `buffer` in place with the low-pass of `occlusion`.
+    fn process(&mut self, buffer: &mut [f32], occlusion: &Occlusion) {
+        // A one-pole cannot reach -3 dB near Nyquist; treat it as open
+        if occlusion.cutoff < 0.45 * self.sample_rate {
+            let cutoff = occlusion.cutoff.max(1.0);
+            self.filter
+                .set_gain_at(self.sample_rate, cutoff, std::f64::consts::FRAC_1_SQRT_2);
+        } else {
+            self.filter.set_gain_at(self.sample_rate, 0.0, 1.0);
+        }
+        self.filter.process(buffer);
+    }
+}
+
+/// Parameters and panning state of one mixer source.
+#[derive(Clone, Debug)]
+pub struct Source {
+    azimuth: f64,
+    elevation: f64,
+    gain: f64,
+    distance: f64,
+    occlusion: Occlusion,
+    state: PanningState,
+    /// Gains applied at the end of the previous block.
+    current_gains: Vec<f64>,
+    /// Gains to reach by the end of the current block.
+    target_gains: Vec<f64>,
+    propagation: Option<Propagation>,
+    absorber: Option<Absorber>,
+    occluder: Option<Occluder>,
+    /// The delayed or filtered input of the current block.
+    processed: Vec<f32>,
+    #[cfg(feature = "trajectory")]
+    trajectory: Option<Trajectory>,
+}
+
+impl Source {
+    fn new(num_speakers: usize) -> Self {
+        Self {
+            azimuth: 0.0,
+            elevation: 0.0,
+            gain: 1.0,
+            distance: 1.0,
+            occlusion: Occlusion::NONE,
+            state: PanningState::new(),
+            current_gains: vec![0.0; num_speakers],
+            target_gains: vec![0.0; num_speakers],
+            propagation: None,
+            absorber: None,
+            occluder: None,
+            processed: Vec::new(),
+            #[cfg(feature = "trajectory")]
+            trajectory: None,
+        }
+    }
+
+    /// Get the azimuth in degrees.
+    #[inline]
+    pub fn azimuth(&self) -> f64 {
+        self.azimuth
+    }
+
+    /// Get the elevation in degrees.
+    #[inline]
+    pub fn elevation(&self) -> f64 {
+        self.elevation
+    }
+
+    /// Get the linear source gain.
+    #[inline]
+    pub fn gain(&self) -> f64 {
+        self.gain
+    }
+
+    /// Get the distance.
+    #[inline]
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+
+    /// Get the occlusion.
+    #[inline]
+    pub fn occlusion(&self) -> Occlusion {
+        self.occlusion
+    }
+
+    /// Get the trajectory the source follows, if any.
+    #[cfg(feature = "trajectory")]
+    #[inline]
+    pub fn trajectory(&self) -> Option<&Trajectory> {
+        self.trajectory.as_ref()
+    }
+
+    /// Compute the gains to reach by the end of the block, and delay and
+    /// filter the block's input if Doppler or air absorption are enabled.
+    fn update(&mut self, panner: &VBAPanner, distance_model: &DistanceModel, input: &[f32]) {
+        panner.compute_gains_with_state(
+            self.azimuth,
+            self.elevation,
+            &mut self.state,
+            &mut self.target_gains,
+        );
+        let gain = self.gain * distance_model.gain(self.distance) * self.occlusion.gain();
+        for g in &mut self.target_gains {
+            *g *= gain;
+        }
+
+        let filtered = self.is_filtered();
+        match &mut self.propagation {
+            Some(propagation) => propagation.process(input, self.distance, &mut self.processed),
+            None if filtered => {
+                self.processed.clear();
+                self.processed.extend_from_slice(input);
+            }
+            None => {}
+        }
+        if let Some(absorber) = &mut self.absorber {
+            absorber.process(&mut self.processed, self.distance);
+        }
+        if let Some(occluder) = &mut self.occluder {
+            occluder.process(&mut self.processed, &self.occlusion);
+        }
+    }
+
+    /// Whether the input is filtered before mixing.
+    #[inline]
+    fn is_filtered(&self) -> bool {
+        self.absorber.is_some() || self.occluder.is_some()
+    }
+
+    /// The signal to mix for this block: the input, or its processed copy.
+    fn signal<'a>(&'a self, input: &'a [f32]) -> &'a [f32] {
+        if self.propagation.is_some() || self.is_filtered() {
+            &self.processed
+        } else {
+            input
+        }
+    }
+}
+
+/// Renders mono sources through a [`VBAPanner`] to speaker outputs.
+///
+/// # Example
+///
+/// ```
+/// use vbap::mixer::Mixer;
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+/// let mut mixer = Mixer::new(panner);
+/// let voice = mixer.add_source();
+/// mixer.set_position(voice, 30.0, 0.0);
+///
+/// let input = vec![0.5f32; 256];
+/// let mut outputs = vec![vec![0.0f32; 256]; 5];
+/// let mut out: Vec<&mut [f32]> = outputs.iter_mut().map(|o| o.as_mut_slice()).collect();
+/// mixer.process(&[&input], &mut out);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Mixer {
+    panner: VBAPanner,
+    sources: Vec<Source>,
+    distance_model: DistanceModel,
+    /// Samples of delay per meter and maximum delay, if Doppler is enabled.
+    doppler: Option<(f64, usize)>,
+    /// Sample rate and absorption, if air absorption is enabled.
+    air: Option<(f64, AirAbsorption)>,
+    /// Sample rate, if occlusion filters are enabled.
+    occlusion: Option<f64>,
+    /// Solo and mute state, by output channel.
+    channels: Vec<Channel>,
+    /// Levels of the last block, by output channel.
+    levels: Vec<Level>,
+    meter: Option<Meter>,
+}
+
+impl Mixer {
+    /// Create a mixer with no sources.
+    pub fn new(panner: VBAPanner) -> Self {
+        let num_outputs = panner.num_speakers();
+        Self {
+            panner,
+            sources: Vec::new(),
+            distance_model: DistanceModel::None,
+            doppler: None,
+            air: None,
+            occlusion: None,
+            channels: vec![
+                Channel {
+                    muted: false,
+                    soloed: false,
+                    gain: 1.0,
+                };
+                num_outputs
+            ],
+            levels: vec![Level::default(); num_outputs],
+            meter: None,
+        }
+    }
+
+    /// Set how source gains fall off with distance.
+    pub fn with_distance_model(mut self, model: DistanceModel) -> Self {
+        self.distance_model = model;
+        self
+    }
+
+    /// Get the distance model.
+    #[inline]
+    pub fn distance_model(&self) -> DistanceModel {
+        self.distance_model
+    }
+
+    /// Delay every source by its distance in meters over the
+    /// [`SPEED_OF_SOUND`], so that moving sources shift in pitch.
+    ///
+    /// Delays are read from a fractional delay line and ramped across each
+    /// block like the gains. Distances beyond `max_distance` meters are
+    /// delayed as if they were at `max_distance`.
+    pub fn with_doppler(mut self, sample_rate: f64, max_distance: f64) -> Self {
+        let samples_per_meter = sample_rate / SPEED_OF_SOUND;
+        let max_delay = (max_distance.max(0.0) * samples_per_meter).ceil() as usize;
+        self.doppler = Some((samples_per_meter, max_delay));
+        for source in &mut self.sources {
+            source.propagation = Some(Propagation::new(samples_per_meter, max_delay));
+        }
+        self
+    }
+
+    /// Check whether Doppler is enabled.
+    #[inline]
+    pub fn has_doppler(&self) -> bool {
+        self.doppler.is_some()
+    }
+
+    /// Low-pass every source according to the air absorption over its
+    /// distance in meters.
+    ///
+    /// Each source runs a one-pole low-pass matched to the absorption at
+    /// 10 kHz (or just below Nyquist at low sample rates), updated once per
+    /// block. Low frequencies pass unchanged; combine with a
+    /// [`DistanceModel`] for overall attenuation.
+    pub fn with_air_absorption(mut self, sample_rate: f64, absorption: AirAbsorption) -> Self {
+        self.air = Some((sample_rate, absorption));
+        for source in &mut self.sources {
+            source.absorber = Some(Absorber::new(sample_rate, &absorption));
+        }
+        self
+    }
+
+    /// Get the air absorption, if enabled.
+    #[inline]
+    pub fn air_absorption(&self) -> Option<AirAbsorption> {
+        self.air.map(|(_, absorption)| absorption)
+    }
+
+    /// Run a one-pole low-pass per source for the cutoff of its
+    /// [`Occlusion`], updated once per block.
+    ///
+    /// Without it, occlusions only attenuate.
+    pub fn with_occlusion(mut self, sample_rate: f64) -> Self {
+        self.occlusion = Some(sample_rate);
+        for source in &mut self.sources {
+            source.occluder = Some(Occluder::new(sample_rate));
+        }
+        self
+    }
+
+    /// Check whether occlusion filters are enabled.
+    #[inline]
+    pub fn has_occlusion(&self) -> bool {
+        self.occlusion.is_some()
+    }
+
+    /// Call `meter` on the audio thread after every block with the
+    /// [`levels`](Self::levels) of the output channels.
+    ///
+    /// The callback must not block; hand the levels to the user interface
+    /// through atomics or a lock-free queue.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::atomic::{AtomicU32, Ordering};
+    /// use std::sync::Arc;
+    /// use vbap::mixer::Mixer;
+    /// use vbap::VBAPanner;
+    ///
+    /// let left_peak = Arc::new(AtomicU32::new(0));
+    /// let meter = Arc::clone(&left_peak);
+    /// let panner = VBAPanner::builder().stereo().build().unwrap();
+    /// let mut mixer = Mixer::new(panner).with_meter(move |levels| {
+    ///     meter.store(levels[0].peak.to_bits(), Ordering::Relaxed);
+    /// });
+    /// let source = mixer.add_source();
+    /// mixer.set_position(source, 30.0, 0.0);
+    ///
+    /// let input = vec![0.5f32; 64];
+    /// let mut outputs = vec![vec![0.0f32; 64]; 2];
+    /// let mut out: Vec<&mut [f32]> = outputs.iter_mut().map(|o| o.as_mut_slice()).collect();
+    /// mixer.process(&[&input], &mut out);
+    /// assert_eq!(f32::from_bits(left_peak.load(Ordering::Relaxed)), 0.5);
+    /// ```
+    pub fn with_meter<F>(mut self, meter: F) -> Self
+    where
+        F: Fn(&[Level]) + Send + Sync + 'static,
+    {
+        self.meter = Some(Meter(Arc::new(meter)));
+        self
+    }
+
+    /// Get the peak and RMS of each output channel over the last block,
+    /// after solo and mute.
+    #[inline]
+    pub fn levels(&self) -> &[Level] {
+        &self.levels
+    }
+
+    /// Mute or unmute an output channel. Fades over the next block.
+    ///
+    /// A muted channel stays silent even when soloed.
+    ///
+    /// # Panics
+    /// Panics if `channel` is not below [`num_outputs`](Self::num_outputs).
+    pub fn set_mute(&mut self, channel: usize, muted: bool) {
+        self.channels[channel].muted = muted;
+    }
+
+    /// Check whether an output channel is muted.
+    ///
+    /// # Panics
+    /// Panics if `channel` is not below [`num_outputs`](Self::num_outputs).
+    #[inline]
+    pub fn is_muted(&self, channel: usize) -> bool {
+        self.channels[channel].muted
+    }
+
+    /// Solo or unsolo an output channel. Fades over the next block.
+    ///
+    /// While any channel is soloed, only soloed channels play.
+    ///
+    /// # Panics
+    /// Panics if `channel` is not below [`num_outputs`](Self::num_outputs).
+    pub fn set_solo(&mut self, channel: usize, soloed: bool) {
+        self.channels[channel].soloed = soloed;
+    }
+
+    /// Check whether an output channel is soloed.
+    ///
+    /// # Panics
+    /// Panics if `channel` is not below [`num_outputs`](Self::num_outputs).
+    #[inline]
+    pub fn is_soloed(&self, channel: usize) -> bool {
+        self.channels[channel].soloed
+    }
+
+    /// Unmute and unsolo every output channel.
+    pub fn clear_solo_mute(&mut self) {
+        for channel in &mut self.channels {
+            channel.muted = false;
+            channel.soloed = false;
+        }
+    }
+
+    /// Get the panner.
+    #[inline]
+    pub fn panner(&self) -> &VBAPanner {
+        &self.panner
+    }
+
+    /// Get the number of output channels (one per speaker).
+    #[inline]
+    pub fn num_outputs(&self) -> usize {
+        self.panner.num_speakers()
+    }
+
+    /// Add a source (front center, unity gain, distance 1) and return its id.
+    pub fn add_source(&mut self) -> SourceId {
+        let mut source = Source::new(self.panner.num_speakers());
+        source.propagation = self
+            .doppler
+            .map(|(samples_per_meter, max_delay)| Propagation::new(samples_per_meter, max_delay));
+        source.absorber = self
+            .air
+            .map(|(sample_rate, absorption)| Absorber::new(sample_rate, &absorption));
+        source.occluder = self.occlusion.map(Occluder::new);
+        self.sources.push(source);
+        self.sources.len() - 1
+    }
+
+    /// Get all sources, indexed by [`SourceId`].
+    #[inline]
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    /// Set a source's direction in degrees. Takes effect at the next block.
+    ///
+    /// # Panics
+    /// Panics if `id` does not refer to a source.
+    pub fn set_position(&mut self, id: SourceId, azimuth: f64, elevation: f64) {
+        let source = &mut self.sources[id];
+        source.azimuth = azimuth;
+        source.elevation = elevation;
+    }
+
+    /// Set a source's linear gain. Takes effect at the next block.
+    ///
+    /// # Panics
+    /// Panics if `id` does not refer to a source.
+    pub fn set_gain(&mut self, id: SourceId, gain: f64) {
+        self.sources[id].gain = gain;
+    }
+
+    /// Set a source's distance (at least 0). Takes effect at the next block.
+    ///
+    /// # Panics
+    /// Panics if `id` does not refer to a source.
+    pub fn set_distance(&mut self, id: SourceId, distance: f64) {
+        self.sources[id].distance = distance.max(0.0);
+    }
+
+    /// Set how much a source is ducked and muffled. Takes effect at the
+    /// next block.
+    ///
+    /// # Panics
+    /// Panics if `id` does not refer to a source.
+    pub fn set_occlusion(&mut self, id: SourceId, occlusion: Occlusion) {
+        self.sources[id].occlusion = occlusion;
+    }
+
+    /// Make a source follow `trajectory` in [`process_at`](Self::process_at)
+    /// and [`render`](Self::render), replacing any previous one.
+    ///
+    /// # Panics
+    /// Panics if `id` does not refer to a source.
+    #[cfg(feature = "trajectory")]
+    pub fn set_trajectory(&mut self, id: SourceId, trajectory: Trajectory) {
+        self.sources[id].trajectory = Some(trajectory);
+    }
+
+    /// Stop a source from following its trajectory. It stays where it is.
+    ///
+    /// # Panics
+    /// Panics if `id` does not refer to a source.
+    #[cfg(feature = "trajectory")]
+    pub fn clear_trajectory(&mut self, id: SourceId) {
+        self.sources[id].trajectory = None;
+    }
+
+    /// Move every source with a trajectory to its position at `time`
+    /// seconds, then render one block like [`process`](Self::process).
+    ///
+    /// Gains reach the new positions at the end of the block, so `time` is
+    /// normally the block's end time. Call this from a real-time callback
+    /// with the transport time, or use [`render`](Self::render) offline.
+    ///
+    /// # Panics
+    /// Panics under the same conditions as [`process`](Self::process).
+    #[cfg(feature = "trajectory")]
+    pub fn process_at(&mut self, time: f64, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) {
+        for source in &mut self.sources {
+            if let Some(trajectory) = &source.trajectory {
+                (source.azimuth, source.elevation) = trajectory.sample(time);
+            }
+        }
+        self.process(inputs, outputs);
+    }
+
+    /// Render complete input buffers offline, starting at time 0.
+    ///
+    /// The inputs are processed with [`process_at`](Self::process_at) in
+    /// blocks of `block_size` samples (at least one), and one output buffer
+    /// per speaker is returned, as long as the inputs.
+    ///
+    /// # Panics
+    /// Panics if the number of inputs does not match the number of sources
+    /// or the inputs differ in length.
+    #[cfg(feature = "trajectory")]
+    pub fn render(
+        &mut self,
+        inputs: &[&[f32]],
+        sample_rate: f64,
+        block_size: usize,
+    ) -> Vec<Vec<f32>> {
+        let len = inputs.first().map_or(0, |input| input.len());
+        assert!(
+            inputs.iter().all(|input| input.len() == len),
+            "expected inputs of equal length"
+        );
+
+        let mut outputs = vec![vec![0.0f32; len]; self.num_outputs()];
+        let block_size = block_size.max(1);
+        let mut start = 0;
+        while start < len {
+            let end = (start + block_size).min(len);
+            let block_inputs: Vec<&[f32]> = inputs.iter().map(|input| &input[start..end]).collect();
+            let mut block_outputs: Vec<&mut [f32]> = outputs
+                .iter_mut()
+                .map(|output| &mut output[start..end])
+                .collect();
+            self.process_at(end as f64 / sample_rate, &block_inputs, &mut block_outputs);
+            start = end;
+        }
+        outputs
+    }
+
+    /// Render one block.
+    ///
+    /// `inputs` holds one mono buffer per source (in [`SourceId`] order),
+    /// `outputs` one buffer per speaker. Outputs are overwritten. Gains ramp
+    /// from the previous block's values to the current ones over the block,
+    /// as do solo and mute changes. The outputs are then metered.
+    ///
+    /// With the `rayon` feature, gains are computed per source and output
+    /// channels are mixed in parallel.
+    ///
+    /// # Panics
+    /// Panics if the number of inputs or outputs does not match, or if any
+    /// buffer is shorter than the first output.
+    pub fn process(&mut self, inputs: &[&[f32]], outputs: &mut [&mut [f32]]) {
+        assert_eq!(
+            inputs.len(),
+            self.sources.len(),
+            "expected one input per source"
+        );
+        assert_eq!(
+            outputs.len(),
+            self.panner.num_speakers(),
+            "expected one output per speaker"
+        );
+
+        let len = outputs.first().map_or(0, |output| output.len());
+        let panner = &self.panner;
+        let distance_model = &self.distance_model;
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            self.sources
+                .par_iter_mut()
+                .zip(inputs.par_iter())
+                .for_each(|(source, input)| source.update(panner, distance_model, &input[..len]));
+        }
+        #[cfg(not(feature = "rayon"))]
+        for (source, input) in self.sources.iter_mut().zip(inputs) {
+            source.update(panner, distance_model, &input[..len]);
+        }
+
+        let sources = &self.sources;
+        let channels = &self.channels;
+        let any_solo = channels.iter().any(|channel| channel.soloed);
+        let mix_channel = |(channel, output): (usize, &mut &mut [f32])| {
+            mix_output_channel(sources, inputs, channel, output);
+            let state = &channels[channel];
+            ramp(output, state.gain, state.target(any_solo));
+        };
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            outputs.par_iter_mut().enumerate().for_each(mix_channel);
+        }
+        #[cfg(not(feature = "rayon"))]
+        outputs.iter_mut().enumerate().for_each(mix_channel);
+
+        for source in &mut self.sources {
+            source.current_gains.copy_from_slice(&source.target_gains);
+        }
+        for channel in &mut self.channels {
+            channel.gain = channel.target(any_solo);
+        }
+
+        for (level, output) in self.levels.iter_mut().zip(outputs.iter()) {
+            *level = Level::measure(output);
+        }
+        if let Some(Meter(meter)) = &self.meter {
+            meter(&self.levels);
+        }
+    }
+}
+
+/// Scale a block by a gain ramping linearly from `start` to `end`.
+fn ramp(output: &mut [f32], start: f64, end: f64) {
+    if start == 1.0 && end == 1.0 {
+        return;
+    }
+    let step = (end - start) / output.len() as f64;
+    for (n, out) in output.iter_mut().enumerate() {
+        *out = (*out as f64 * (start + step * (n + 1) as f64)) as f32;
+    }
+}
+
+/// Sum all sources into one output channel, ramping each source's gain.
+fn mix_output_channel(sources: &[Source], inputs: &[&[f32]], channel: usize, output: &mut [f32]) {
+    output.fill(0.0);
+    let len = output.len();
+    if len == 0 {
+        return;
+    }
+
+    for (source, input) in sources.iter().zip(inputs) {
+        let start = source.current_gains[channel];
+        let end = source.target_gains[channel];
+        if start == 0.0 && end == 0.0 {
+            continue;
+        }
+
+        let step = (end - start) / len as f64;
+        let signal = source.signal(input);
+        for (n, (out, &x)) in output.iter_mut().zip(&signal[..len]).enumerate() {
+            let gain = start + step * (n + 1) as f64;
+            *out += (x as f64 * gain) as f32;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_static_source_reaches_target() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut mixer = Mixer::new(panner);
+        let id = mixer.add_source();
+        mixer.set_position(id, 30.0, 0.0);
+
+        let input = vec![1.0f32; 64];
+        let mut left = vec![0.0f32; 64];
+        let mut right = vec![0.0f32; 64];
+
+        // First block ramps up from silence
+        mixer.process(&[&input], &mut [&mut left, &mut right]);
+        assert!(left[0] < left[63]);
+        assert_relative_eq!(left[63], 1.0, epsilon = 1e-6);
+
+        // Second block is steady
+        mixer.process(&[&input], &mut [&mut left, &mut right]);
+        assert!(left.iter().all(|&s| (s - 1.0).abs() < 1e-6));
+        assert!(right.iter().all(|&s| s.abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_sources_sum() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut mixer = Mixer::new(panner);
+        let a = mixer.add_source();
+        let b = mixer.add_source();
+        mixer.set_position(a, 30.0, 0.0);
+        mixer.set_position(b, -30.0, 0.0);
+        mixer.set_gain(b, 0.5);
+
+        let input_a = vec![1.0f32; 16];
+        let input_b = vec![1.0f32; 16];
+        let mut left = vec![0.0f32; 16];
+        let mut right = vec![0.0f32; 16];
+        for _ in 0..2 {
+            mixer.process(&[&input_a, &input_b], &mut [&mut left, &mut right]);
+        }
+
+        assert_relative_eq!(left[15], 1.0, epsilon = 1e-6);
+        assert_relative_eq!(right[15], 0.5, epsilon = 1e-6);
+    }
+
+    #[test]
+    fn test_distance_models() {
+        let inverse = DistanceModel::Inverse {
+            reference: 2.0,
+            rolloff: 1.0,
+        };
+        assert_eq!(inverse.gain(1.0), 1.0);
+        assert_relative_eq!(inverse.gain(4.0), 0.5);
+        let square = DistanceModel::InverseSquare { reference: 1.0 };
+        assert_relative_eq!(square.gain(2.0), 0.25);
+        let clamped = DistanceModel::Clamped {
+            reference: 1.0,
+            max: 10.0,
+        };
+        assert_relative_eq!(clamped.gain(5.0), 0.2);
+        assert_relative_eq!(clamped.gain(50.0), 0.1);
+        assert_eq!(DistanceModel::Custom(|d| 1.0 - d / 100.0).gain(25.0), 0.75);
+    }
+
+    #[test]
+    fn test_doppler_delays_by_distance() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut mixer = Mixer::new(panner)
+            .with_distance_model(DistanceModel::InverseSquare { reference: 1.0 })
+            .with_doppler(SPEED_OF_SOUND * 10.0, 10.0);
+        let id = mixer.add_source();
+        mixer.set_position(id, 30.0, 0.0);
+        mixer.set_distance(id, 2.0);
+
+        // Steady gains first, then an impulse
+        let mut left = vec![0.0f32; 64];
+        let mut right = vec![0.0f32; 64];
+        mixer.process(&[&[0.0; 64]], &mut [&mut left, &mut right]);
+        let mut impulse = vec![0.0f32; 64];
+        impulse[0] = 1.0;
+        mixer.process(&[&impulse], &mut [&mut left, &mut right]);
+
+        // 2 m at 10 samples per meter, at a quarter of the gain
+        assert_relative_eq!(left[20], 0.25, epsilon = 1e-6);
+        assert_eq!(left.iter().filter(|&&x| x != 0.0).count(), 1);
+
+        // Receding to 3 m ramps the delay to 30 samples over the block, so
+        // the last sample reads the ramp 30 samples back at a ninth of the gain
+        mixer.set_distance(id, 3.0);
+        let ramp: Vec<f32> = (0..64).map(|n| n as f32).collect();
+        mixer.process(&[&ramp], &mut [&mut left, &mut right]);
+        assert_relative_eq!(left[63], 33.0 / 9.0, epsilon = 1e-4);
+    }
+
+    #[test]
+    fn test_air_absorption() {
+        let air = AirAbsorption::Atmosphere {
+            temperature: 20.0,
+            humidity: 50.0,
+        };
+        // Absorption grows with frequency and is stronger in dry air
+        assert!(air.attenuation(8000.0) > 4.0 * air.attenuation(2000.0));
+        let dry = AirAbsorption::Atmosphere {
+            temperature: 20.0,
+            humidity: 10.0,
+        };
+        assert!(dry.attenuation(8000.0) > air.attenuation(8000.0));
+        assert_relative_eq!(AirAbsorption::Coefficient(0.2).attenuation(5000.0), 0.05);
+
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut mixer =
+            Mixer::new(panner).with_air_absorption(48000.0, AirAbsorption::Coefficient(0.1));
+        let near = mixer.add_source();
+        let far = mixer.add_source();
+        mixer.set_position(near, 30.0, 0.0);
+        mixer.set_position(far, -30.0, 0.0);
+        mixer.set_distance(far, 300.0);
+
+        // Alternating samples (Nyquist) are cut far more for the far source,
+        // while DC passes both
+        let hiss: Vec<f32> = (0..4800)
+            .map(|n| if n % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        let mut left = vec![0.0f32; 4800];
+        let mut right = vec![0.0f32; 4800];
+        mixer.process(&[&hiss, &hiss], &mut [&mut left, &mut right]);
+        assert!(left[4799].abs() > 0.9);
+        assert!(right[4799].abs() < 0.1);
+
+        let dc = vec![1.0f32; 4800];
+        mixer.process(&[&dc, &dc], &mut [&mut left, &mut right]);
+        assert_relative_eq!(right[4799], 1.0, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_occlusion() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut mixer = Mixer::new(panner).with_occlusion(48000.0);
+        let open = mixer.add_source();
+        let walled = mixer.add_source();
+        assert!(mixer.has_occlusion());
+        mixer.set_position(open, 30.0, 0.0);
+        mixer.set_position(walled, -30.0, 0.0);
+        mixer.set_occlusion(walled, Occlusion::new(20.0, 500.0));
+        assert_eq!(mixer.sources()[walled].occlusion().attenuation, 20.0);
+
+        // The wall cuts the highs, and the lows by its attenuation
+        let hiss: Vec<f32> = (0..4800)
+            .map(|n| if n % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        let mut left = vec![0.0f32; 4800];
+        let mut right = vec![0.0f32; 4800];
+        mixer.process(&[&hiss, &hiss], &mut [&mut left, &mut right]);
+        assert!(left[4799].abs() > 0.99);
+        assert!(right[4799].abs() < 0.01);
+
+        let dc = vec![1.0f32; 4800];
+        mixer.process(&[&dc, &dc], &mut [&mut left, &mut right]);
+        assert_relative_eq!(right[4799], 0.1, epsilon = 1e-4);
+
+        // Cleared, the source plays unchanged
+        mixer.set_occlusion(walled, Occlusion::NONE);
+        mixer.process(&[&hiss, &hiss], &mut [&mut left, &mut right]);
+        mixer.process(&[&hiss, &hiss], &mut [&mut left, &mut right]);
+        assert_relative_eq!(right[4799], -1.0, epsilon = 1e-4);
+
+        // Without filters, occlusion still attenuates
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut mixer = Mixer::new(panner);
+        let source = mixer.add_source();
+        mixer.set_position(source, 30.0, 0.0);
+        mixer.set_occlusion(source, Occlusion::new(6.0, 100.0));
+        for _ in 0..2 {
+            mixer.process(&[&hiss], &mut [&mut left, &mut right]);
+        }
+        assert_relative_eq!(left[4799], -0.501, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn test_solo_mute_and_levels() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut mixer = Mixer::new(panner);
+        let source = mixer.add_source();
+        mixer.set_position(source, 0.0, 0.0);
+        let input = vec![1.0f32; 64];
+        let mut left = vec![0.0f32; 64];
+        let mut right = vec![0.0f32; 64];
+        mixer.process(&[&input], &mut [&mut left, &mut right]);
+        mixer.process(&[&input], &mut [&mut left, &mut right]);
+        let gain = std::f32::consts::FRAC_1_SQRT_2;
+        assert_relative_eq!(mixer.levels()[1].peak, gain, epsilon = 1e-6);
+        assert_relative_eq!(mixer.levels()[1].rms, gain, epsilon = 1e-6);
+
+        // Muting fades the channel out over one block
+        mixer.set_mute(1, true);
+        assert!(mixer.is_muted(1));
+        mixer.process(&[&input], &mut [&mut left, &mut right]);
+        assert!(right[0] > 0.6 && right[63] == 0.0);
+        mixer.process(&[&input], &mut [&mut left, &mut right]);
+        assert_eq!(mixer.levels()[1], Level::default());
+        assert_relative_eq!(mixer.levels()[0].peak, gain, epsilon = 1e-6);
+
+        // Soloing silences the other channels; mute still wins
+        mixer.set_solo(1, true);
+        for _ in 0..2 {
+            mixer.process(&[&input], &mut [&mut left, &mut right]);
+        }
+        assert!(left.iter().chain(&right).all(|&s| s == 0.0));
+        mixer.set_mute(1, false);
+        for _ in 0..2 {
+            mixer.process(&[&input], &mut [&mut left, &mut right]);
+        }
+        assert_eq!(mixer.levels()[0].peak, 0.0);
+        assert_relative_eq!(right[0], gain, epsilon = 1e-6);
+
+        mixer.clear_solo_mute();
+        assert!(!mixer.is_soloed(1));
+        for _ in 0..2 {
+            mixer.process(&[&input], &mut [&mut left, &mut right]);
+        }
+        assert_relative_eq!(left[0], gain, epsilon = 1e-6);
+    }
+
+    #[cfg(feature = "trajectory")]
+    #[test]
+    fn test_render_follows_trajectory() {
+        use crate::trajectory::{Keyframe, Trajectory};
+
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut mixer = Mixer::new(panner);
+        let id = mixer.add_source();
+        let pan = Trajectory::new(vec![
+            Keyframe::new(0.0, 30.0, 0.0),
+            Keyframe::new(1.0, -30.0, 0.0),
+        ])
+        .unwrap();
+        mixer.set_trajectory(id, pan);
+
+        let input = vec![1.0f32; 1000];
+        let outputs = mixer.render(&[&input], 1000.0, 100);
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].len(), 1000);
+
+        // Left to right, with equal power in the middle
+        assert!(outputs[0][150] > outputs[1][150]);
+        assert_relative_eq!(outputs[0][499], outputs[1][499], epsilon = 1e-6);
+        assert_relative_eq!(outputs[1][999], 1.0, epsilon = 1e-6);
+        assert_eq!(mixer.sources()[id].azimuth(), -30.0);
+    }
+}
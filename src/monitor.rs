@@ -0,0 +1,250 @@
+//! Headphone and stereo monitoring of multichannel renders.
+//!
+//! [`StereoDownmix`] folds speaker feeds down to two channels with fixed
+//! coefficients, for auditioning a mix on a stereo pair. With the
+//! `binaural` feature, `BinauralDownmix` convolves every speaker feed with a
+//! head-related impulse response (HRIR) pair instead, so the layout can be
+//! heard on headphones as virtual speakers around the listener.
+//!
+//! Requires the `render` feature.
+
+use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_2};
+
+use crate::config::SpeakerConfig;
+#[cfg(feature = "binaural")]
+use crate::dsp::Fir;
+
+/// Half-width of the stereo stage speakers are folded into, in degrees.
+const STAGE: f64 = 30.0;
+
+/// Fixed-coefficient fold-down of speaker feeds to stereo.
+///
+/// Speakers are panned across the ±30° stereo stage with a constant-power
+/// law: front speakers keep their place, side and rear speakers go to their
+/// side of the stage (mirrored front to back, so a rear center stays in the
+/// center). Surround and height speakers are attenuated, by -3 dB each by
+/// default, as in the ITU-R BS.775 Lo/Ro downmix.
+///
+/// # Example
+///
+/// ```
+/// use vbap::monitor::StereoDownmix;
+/// use vbap::SpeakerConfigBuilder;
+///
+/// let config = SpeakerConfigBuilder::new().surround_5_1().build_config().unwrap();
+/// let downmix = StereoDownmix::new(&config);
+///
+/// // Left, right, center, left surround, right surround
+/// let c = downmix.coefficients();
+/// assert!((c[0][0] - 1.0).abs() < 1e-12 && c[0][1].abs() < 1e-12);
+/// assert!((c[2][0] - c[2][1]).abs() < 1e-12);
+/// assert!((c[3][0] - 0.5f64.sqrt()).abs() < 1e-12);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct StereoDownmix {
+    /// (left, right) gain of each speaker.
+    coefficients: Vec<[f64; 2]>,
+}
+
+impl StereoDownmix {
+    /// Create the downmix for a layout with -3 dB surround and height gains.
+    pub fn new(config: &SpeakerConfig) -> Self {
+        Self::with_gains(config, FRAC_1_SQRT_2, FRAC_1_SQRT_2)
+    }
+
+    /// Create the downmix with custom linear gains for surround speakers
+    /// (more than 45° off center) and height speakers (more than 20° up).
+    ///
+    /// Virtual speakers get zero coefficients.
+    pub fn with_gains(config: &SpeakerConfig, surround_gain: f64, height_gain: f64) -> Self {
+        let coefficients = config
+            .speakers()
+            .iter()
+            .map(|speaker| {
+                if speaker.is_virtual() {
+                    return [0.0; 2];
+                }
+                let azimuth = speaker.azimuth();
+                // Mirror rear speakers to the front, then clamp to the stage
+                let folded = if azimuth.abs() > 90.0 {
+                    azimuth.signum() * (180.0 - azimuth.abs())
+                } else {
+                    azimuth
+                };
+                let position = (STAGE - folded.clamp(-STAGE, STAGE)) / (2.0 * STAGE);
+                let (sin, cos) = (position * FRAC_PI_2).sin_cos();
+
+                let mut gain = 1.0;
+                if azimuth.abs() > 45.0 {
+                    gain *= surround_gain;
+                }
+                if speaker.elevation() > 20.0 {
+                    gain *= height_gain;
+                }
+                [cos * gain, sin * gain]
+            })
+            .collect();
+        Self { coefficients }
+    }
+
+    /// Create a downmix from explicit (left, right) gains per speaker.
+    pub fn from_coefficients(coefficients: Vec<[f64; 2]>) -> Self {
+        Self { coefficients }
+    }
+
+    /// Get the (left, right) gain of each speaker.
+    #[inline]
+    pub fn coefficients(&self) -> &[[f64; 2]] {
+        &self.coefficients
+    }
+
+    /// Get the number of speakers.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    /// Fold one block of speaker feeds into `left` and `right`, which are
+    /// overwritten.
+    ///
+    /// # Panics
+    /// Panics if `speakers` does not have one channel per speaker, or a
+    /// channel is shorter than `left`.
+    pub fn process(&self, speakers: &[&[f32]], left: &mut [f32], right: &mut [f32]) {
+        assert_eq!(
+            speakers.len(),
+            self.coefficients.len(),
+            "expected {} speaker channels",
+            self.coefficients.len()
+        );
+        left.fill(0.0);
+        right.fill(0.0);
+        let len = left.len().min(right.len());
+        for (channel, &[l, r]) in speakers.iter().zip(&self.coefficients) {
+            if l == 0.0 && r == 0.0 {
+                continue;
+            }
+            for ((out_l, out_r), &x) in left.iter_mut().zip(right.iter_mut()).zip(&channel[..len]) {
+                *out_l += (x as f64 * l) as f32;
+                *out_r += (x as f64 * r) as f32;
+            }
+        }
+    }
+}
+
+/// Binaural fold-down of speaker feeds for headphone monitoring.
+///
+/// Each speaker feed is convolved with the left- and right-ear HRIRs
+/// measured from that speaker's direction, and the results are summed. The
+/// HRIRs are supplied by the caller, one pair per speaker and at the
+/// render's sample rate.
+///
+/// Requires the `binaural` feature.
+///
+/// # Example
+///
+/// ```
+/// use vbap::monitor::BinauralDownmix;
+///
+/// // Toy HRIRs: the far ear hears each speaker later and quieter
+/// let mut binaural = BinauralDownmix::new(vec![
+///     [vec![1.0], vec![0.0, 0.0, 0.5]],
+///     [vec![0.0, 0.0, 0.5], vec![1.0]],
+/// ]);
+/// let (mut left, mut right) = (vec![0.0f32; 4], vec![0.0f32; 4]);
+/// binaural.process(&[&[1.0, 0.0, 0.0, 0.0], &[0.0; 4]], &mut left, &mut right);
+/// assert_eq!(left, [1.0, 0.0, 0.0, 0.0]);
+/// assert_eq!(right, [0.0, 0.0, 0.5, 0.0]);
+/// ```
+#[cfg(feature = "binaural")]
+#[derive(Clone, Debug)]
+pub struct BinauralDownmix {
+    /// Left- and right-ear filter per speaker.
+    filters: Vec<[Fir; 2]>,
+}
+
+#[cfg(feature = "binaural")]
+impl BinauralDownmix {
+    /// Create the downmix from one (left ear, right ear) HRIR pair per
+    /// speaker.
+    pub fn new(hrirs: Vec<[Vec<f32>; 2]>) -> Self {
+        let filters = hrirs
+            .into_iter()
+            .map(|[left, right]| [Fir::new(left), Fir::new(right)])
+            .collect();
+        Self { filters }
+    }
+
+    /// Get the number of speakers.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.filters.len()
+    }
+
+    /// Clear the convolution state.
+    pub fn reset(&mut self) {
+        for filter in self.filters.iter_mut().flatten() {
+            filter.reset();
+        }
+    }
+
+    /// Render one block of speaker feeds to `left` and `right`, which are
+    /// overwritten.
+    ///
+    /// # Panics
+    /// Panics if `speakers` does not have one channel per speaker, or a
+    /// channel is shorter than `left`.
+    pub fn process(&mut self, speakers: &[&[f32]], left: &mut [f32], right: &mut [f32]) {
+        assert_eq!(
+            speakers.len(),
+            self.filters.len(),
+            "expected {} speaker channels",
+            self.filters.len()
+        );
+        left.fill(0.0);
+        right.fill(0.0);
+        let len = left.len().min(right.len());
+        for (channel, [filter_l, filter_r]) in speakers.iter().zip(&mut self.filters) {
+            for ((out_l, out_r), &x) in left.iter_mut().zip(right.iter_mut()).zip(&channel[..len]) {
+                *out_l += filter_l.process_sample(x);
+                *out_r += filter_r.process_sample(x);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpeakerConfigBuilder;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_stereo_fold_down() {
+        let config = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .build_config()
+            .unwrap();
+        let downmix = StereoDownmix::new(&config);
+        let c = downmix.coefficients();
+
+        // Side and rear left speakers go hard left at -3 dB
+        for i in [3, 5] {
+            assert_relative_eq!(c[i][0], FRAC_1_SQRT_2, epsilon = 1e-12);
+            assert_relative_eq!(c[i][1], 0.0, epsilon = 1e-12);
+        }
+        // Right top rear: height and surround, -6 dB on the right
+        assert_relative_eq!(c[10][1], 0.5, epsilon = 1e-12);
+
+        let ones = vec![1.0f32; 8];
+        let feeds: Vec<&[f32]> = (0..config.num_speakers())
+            .map(|_| ones.as_slice())
+            .collect();
+        let (mut left, mut right) = (vec![0.0f32; 8], vec![0.0f32; 8]);
+        downmix.process(&feeds, &mut left, &mut right);
+        // The layout is symmetric
+        assert_relative_eq!(left[7], right[7], epsilon = 1e-6);
+        let expected: f64 = c.iter().map(|[l, _]| l).sum();
+        assert_relative_eq!(left[7] as f64, expected, epsilon = 1e-6);
+    }
+}
@@ -0,0 +1,785 @@
+//! Live control of source positions over OSC.
+//!
+//! [`OscServer`] listens for Open Sound Control packets on a UDP socket, so
+//! controllers such as IanniX, TouchOSC or Max can move the sources of a
+//! [`Mixer`] while it plays. The server thread decodes each packet into an
+//! [`OscControl`]; clone the control into the audio callback and call
+//! [`OscControl::apply`] before mixing each block. It never blocks the
+//! audio thread.
+//!
+//! Sources are numbered from 1, following SpatDIF, and the `/spatdif`
+//! prefix is optional:
+//!
+//! | Address | Arguments |
+//! |---------|-----------|
+//! | `/source/<n>/aed` | azimuth, elevation in degrees, optional distance |
+//! | `/source/<n>/xyz` | x (right), y (front), z (up) |
+//! | `/source/<n>/position` | x, y, z, optionally followed by `"xyz"`, or azimuth, elevation, distance followed by `"aed"` |
+//!
+//! Azimuths follow SpatDIF's navigational convention (clockwise, 90° =
+//! right) unless set with [`OscControl::with_convention`]. Numbers may be
+//! sent as 32- or 64-bit integers or floats. Messages to other addresses,
+//! to sources beyond [`MAX_SOURCES`], or with the wrong arguments, are
+//! ignored. Bundles are applied as soon as
+//! they arrive; their time tags are not scheduled.
+//!
+//! Requires the `osc` feature.
+
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use glam::DVec3;
+
+use crate::convention::Convention;
+use crate::error::{Result, VBAPError};
+use crate::math::cartesian_to_spherical;
+use crate::mixer::{Mixer, SourceId};
+
+/// Largest packet the server receives; longer datagrams are truncated.
+const MAX_PACKET_SIZE: usize = 65_507;
+
+/// Highest source number an address may name. Messages to higher numbers
+/// are ignored, so a stray packet cannot make the control allocate
+/// positions for billions of sources.
+pub const MAX_SOURCES: usize = 4096;
+
+/// Deepest nesting of bundles in a packet.
+const MAX_BUNDLE_DEPTH: usize = 16;
+
+/// How often the server thread checks whether it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// An OSC argument.
+#[derive(Clone, Debug, PartialEq)]
+pub enum OscArg {
+    /// `i`: 32-bit integer.
+    Int(i32),
+    /// `h`: 64-bit integer.
+    Long(i64),
+    /// `f`: 32-bit float.
+    Float(f32),
+    /// `d`: 64-bit float.
+    Double(f64),
+    /// `s`: string.
+    String(String),
+    /// `b`: binary blob.
+    Blob(Vec<u8>),
+    /// `T` or `F`: boolean without data.
+    Bool(bool),
+    /// `N`: nil.
+    Nil,
+    /// `I`: infinitum (impulse).
+    Infinitum,
+}
+
+impl OscArg {
+    /// Get a numeric argument as `f64`, or `None` for other types.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            OscArg::Int(value) => Some(value as f64),
+            OscArg::Long(value) => Some(value as f64),
+            OscArg::Float(value) => Some(value as f64),
+            OscArg::Double(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Get a string argument, or `None` for other types.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            OscArg::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn type_tag(&self) -> u8 {
+        match self {
+            OscArg::Int(_) => b'i',
+            OscArg::Long(_) => b'h',
+            OscArg::Float(_) => b'f',
+            OscArg::Double(_) => b'd',
+            OscArg::String(_) => b's',
+            OscArg::Blob(_) => b'b',
+            OscArg::Bool(true) => b'T',
+            OscArg::Bool(false) => b'F',
+            OscArg::Nil => b'N',
+            OscArg::Infinitum => b'I',
+        }
+    }
+}
+
+/// An OSC message: an address pattern and its arguments.
+///
+/// # Example
+///
+/// ```
+/// use vbap::osc::{OscArg, OscMessage};
+///
+/// let message = OscMessage::new(
+///     "/source/1/aed",
+///     vec![OscArg::Float(90.0), OscArg::Float(0.0), OscArg::Float(2.0)],
+/// );
+/// let packet = message.to_bytes();
+/// assert_eq!(OscMessage::decode_packet(&packet).unwrap(), [message]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct OscMessage {
+    /// Address pattern, such as `/source/1/aed`.
+    pub address: String,
+    /// Arguments in order.
+    pub args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    /// Create a message.
+    pub fn new(address: impl Into<String>, args: Vec<OscArg>) -> Self {
+        Self {
+            address: address.into(),
+            args,
+        }
+    }
+
+    /// Encode the message as an OSC 1.0 packet.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_string(&mut bytes, &self.address);
+        let mut tags = String::from(",");
+        tags.extend(self.args.iter().map(|arg| arg.type_tag() as char));
+        write_string(&mut bytes, &tags);
+        for arg in &self.args {
+            match arg {
+                OscArg::Int(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+                OscArg::Long(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+                OscArg::Float(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+                OscArg::Double(value) => bytes.extend_from_slice(&value.to_be_bytes()),
+                OscArg::String(value) => write_string(&mut bytes, value),
+                OscArg::Blob(data) => {
+                    bytes.extend_from_slice(&(data.len() as i32).to_be_bytes());
+                    bytes.extend_from_slice(data);
+                    pad(&mut bytes);
+                }
+                OscArg::Bool(_) | OscArg::Nil | OscArg::Infinitum => {}
+            }
+        }
+        bytes
+    }
+
+    /// Decode an OSC 1.0 packet, a message or a bundle, into its messages.
+    ///
+    /// Bundles are flattened in order and their time tags are dropped.
+    /// Messages without a type tag string are read as having no arguments.
+    ///
+    /// Returns [`VBAPError::Parse`] if the packet is malformed, uses an
+    /// unsupported type tag, or nests bundles more than 16 deep.
+    pub fn decode_packet(packet: &[u8]) -> Result<Vec<OscMessage>> {
+        let mut messages = Vec::new();
+        decode_into(packet, &mut messages, 0)?;
+        Ok(messages)
+    }
+}
+
+fn decode_into(packet: &[u8], messages: &mut Vec<OscMessage>, depth: usize) -> Result<()> {
+    let mut reader = Reader::new(packet);
+    if packet.starts_with(b"#bundle\0") {
+        if depth >= MAX_BUNDLE_DEPTH {
+            return Err(parse_error(format!(
+                "bundles nested more than {} deep",
+                MAX_BUNDLE_DEPTH
+            )));
+        }
+        reader.take(16)?; // identifier and time tag
+        while !reader.is_empty() {
+            let size = reader.int()?;
+            let size = usize::try_from(size)
+                .map_err(|_| parse_error(format!("negative bundle element size {}", size)))?;
+            decode_into(reader.take(size)?, messages, depth + 1)?;
+        }
+        return Ok(());
+    }
+
+    let address = reader.string()?;
+    if !address.starts_with('/') {
+        return Err(parse_error(format!("invalid address '{}'", address)));
+    }
+    let tags = if reader.is_empty() {
+        String::from(",")
+    } else {
+        reader.string()?
+    };
+    let Some(tags) = tags.strip_prefix(',') else {
+        return Err(parse_error(format!("invalid type tags '{}'", tags)));
+    };
+    let mut args = Vec::with_capacity(tags.len());
+    for tag in tags.bytes() {
+        args.push(match tag {
+            b'i' => OscArg::Int(reader.int()?),
+            b'h' => OscArg::Long(i64::from_be_bytes(reader.array()?)),
+            b'f' => OscArg::Float(f32::from_be_bytes(reader.array()?)),
+            b'd' => OscArg::Double(f64::from_be_bytes(reader.array()?)),
+            b's' => OscArg::String(reader.string()?),
+            b'b' => {
+                let size = reader.int()?;
+                let size = usize::try_from(size)
+                    .map_err(|_| parse_error(format!("negative blob size {}", size)))?;
+                let data = reader.take(size)?.to_vec();
+                reader.skip_padding(size)?;
+                OscArg::Blob(data)
+            }
+            b'T' => OscArg::Bool(true),
+            b'F' => OscArg::Bool(false),
+            b'N' => OscArg::Nil,
+            b'I' => OscArg::Infinitum,
+            _ => {
+                return Err(parse_error(format!(
+                    "unsupported type tag '{}'",
+                    tag as char
+                )))
+            }
+        });
+    }
+    messages.push(OscMessage { address, args });
+    Ok(())
+}
+
+/// Cursor over a packet's 4-byte aligned fields.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.bytes.len() {
+            return Err(parse_error("packet ends early".into()));
+        }
+        let (head, tail) = self.bytes.split_at(len);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Ok(array)
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.array()?))
+    }
+
+    fn skip_padding(&mut self, len: usize) -> Result<()> {
+        self.take((4 - len % 4) % 4).map(|_| ())
+    }
+
+    /// Read a null-terminated, padded string.
+    fn string(&mut self) -> Result<String> {
+        let len = self
+            .bytes
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| parse_error("unterminated string".into()))?;
+        let text = std::str::from_utf8(&self.bytes[..len])
+            .map_err(|_| parse_error("string is not UTF-8".into()))?
+            .to_string();
+        // The terminator is part of the padding
+        self.take(len + 1)?;
+        self.skip_padding(len + 1)?;
+        Ok(text)
+    }
+}
+
+fn write_string(bytes: &mut Vec<u8>, text: &str) {
+    bytes.extend_from_slice(text.as_bytes());
+    bytes.push(0);
+    pad(bytes);
+}
+
+fn pad(bytes: &mut Vec<u8>) {
+    bytes.resize(bytes.len() + (4 - bytes.len() % 4) % 4, 0);
+}
+
+fn parse_error(message: String) -> VBAPError {
+    VBAPError::Parse(format!("OSC: {}", message))
+}
+
+/// Last position received for a source, in the crate's convention.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SourcePosition {
+    /// Azimuth in degrees.
+    pub azimuth: f64,
+    /// Elevation in degrees.
+    pub elevation: f64,
+    /// Distance, or `None` if only a direction has been received.
+    pub distance: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    position: SourcePosition,
+    /// Not yet applied to the mixer.
+    pending: bool,
+}
+
+/// Source positions received over OSC, shared between the receiving thread
+/// and the audio thread.
+///
+/// Cloning shares the positions.
+///
+/// # Example
+///
+/// ```
+/// use vbap::mixer::Mixer;
+/// use vbap::osc::{OscArg, OscControl, OscMessage};
+/// use vbap::VBAPanner;
+///
+/// let mut mixer = Mixer::new(VBAPanner::builder().surround_5_1().build().unwrap());
+/// let source = mixer.add_source();
+///
+/// let control = OscControl::new();
+/// // SpatDIF azimuths are clockwise: 90° is to the right
+/// let message = OscMessage::new("/source/1/aed", vec![OscArg::Float(90.0), OscArg::Float(0.0)]);
+/// control.handle_packet(&message.to_bytes()).unwrap();
+///
+/// // Normally in the audio callback
+/// control.apply(&mut mixer);
+/// assert_eq!(mixer.sources()[source].azimuth(), -90.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct OscControl {
+    slots: Arc<Mutex<Vec<Option<Slot>>>>,
+    convention: Convention,
+}
+
+impl Default for OscControl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OscControl {
+    /// Create a control reading azimuths in SpatDIF's
+    /// [`Navigational`](Convention::Navigational) convention.
+    pub fn new() -> Self {
+        Self {
+            slots: Arc::default(),
+            convention: Convention::Navigational,
+        }
+    }
+
+    /// Set the convention of incoming `aed` azimuths, for controllers that
+    /// do not send SpatDIF angles.
+    pub fn with_convention(mut self, convention: Convention) -> Self {
+        self.convention = convention;
+        self
+    }
+
+    /// Get the convention of incoming `aed` azimuths.
+    #[inline]
+    pub fn convention(&self) -> Convention {
+        self.convention
+    }
+
+    /// Get the last position received for a source, or `None` if there has
+    /// been none.
+    pub fn position(&self, source: SourceId) -> Option<SourcePosition> {
+        let slots = self.slots.lock().ok()?;
+        slots
+            .get(source)
+            .copied()
+            .flatten()
+            .map(|slot| slot.position)
+    }
+
+    /// Decode a packet and store the positions it sets.
+    ///
+    /// Returns the number of position updates, which excludes messages to
+    /// other addresses or with the wrong arguments, and
+    /// [`VBAPError::Parse`] if the packet is malformed.
+    pub fn handle_packet(&self, packet: &[u8]) -> Result<usize> {
+        let messages = OscMessage::decode_packet(packet)?;
+        Ok(messages
+            .iter()
+            .filter(|message| self.handle_message(message))
+            .count())
+    }
+
+    /// Store the position a message sets. Returns `false` if the message
+    /// does not set one.
+    pub fn handle_message(&self, message: &OscMessage) -> bool {
+        let Some((source, command)) = parse_address(&message.address) else {
+            return false;
+        };
+        let Some(update) = self.parse_update(command, &message.args) else {
+            return false;
+        };
+        let Ok(mut slots) = self.slots.lock() else {
+            return false;
+        };
+        if source >= slots.len() {
+            slots.resize(source + 1, None);
+        }
+        let previous = slots[source].map(|slot| slot.position.distance);
+        let position = SourcePosition {
+            distance: update.distance.or(previous.flatten()),
+            ..update
+        };
+        slots[source] = Some(Slot {
+            position,
+            pending: true,
+        });
+        true
+    }
+
+    /// Move the mixer's sources to the positions received since the last
+    /// call, converted to the layout's
+    /// [`convention`](crate::SpeakerConfig::convention).
+    ///
+    /// Returns `false`, leaving the mixer unchanged, if the receiving thread
+    /// is storing positions at that moment; the next block picks them up.
+    /// Positions of sources the mixer does not have yet stay pending.
+    pub fn apply(&self, mixer: &mut Mixer) -> bool {
+        let Ok(mut slots) = self.slots.try_lock() else {
+            return false;
+        };
+        let convention = mixer.panner().config().convention();
+        let num_sources = mixer.sources().len();
+        for (source, slot) in slots.iter_mut().enumerate().take(num_sources) {
+            let Some(slot) = slot.as_mut().filter(|slot| slot.pending) else {
+                continue;
+            };
+            let position = slot.position;
+            let (azimuth, elevation) = convention.from_native(position.azimuth, position.elevation);
+            mixer.set_position(source, azimuth, elevation);
+            if let Some(distance) = position.distance {
+                mixer.set_distance(source, distance);
+            }
+            slot.pending = false;
+        }
+        true
+    }
+
+    fn parse_update(&self, command: &str, args: &[OscArg]) -> Option<SourcePosition> {
+        let (values, kind) = match (command, args.last().and_then(OscArg::as_str)) {
+            ("aed", None) | ("xyz", None) => (args, command),
+            ("position", None) => (args, "xyz"),
+            ("position", Some(kind)) => (&args[..args.len() - 1], kind),
+            _ => return None,
+        };
+        let values: Vec<f64> = values.iter().map(OscArg::as_f64).collect::<Option<_>>()?;
+        if values.iter().any(|value| !value.is_finite()) {
+            return None;
+        }
+        match (kind, values.as_slice()) {
+            ("aed", &[azimuth, elevation]) => Some(self.direction(azimuth, elevation, None)),
+            ("aed", &[azimuth, elevation, distance]) => {
+                Some(self.direction(azimuth, elevation, Some(distance)))
+            }
+            ("xyz", &[x, y, z]) => {
+                // SpatDIF's x points right, the crate's to the left
+                let vector = DVec3::new(-x, y, z);
+                let (azimuth, elevation) = cartesian_to_spherical(vector);
+                Some(SourcePosition {
+                    azimuth,
+                    elevation,
+                    distance: Some(vector.length()),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    fn direction(&self, azimuth: f64, elevation: f64, distance: Option<f64>) -> SourcePosition {
+        let (azimuth, elevation) = self.convention.to_native(azimuth, elevation);
+        SourcePosition {
+            azimuth,
+            elevation,
+            distance,
+        }
+    }
+}
+
+/// Split `[/spatdif]/source/<n>/<command>` into a 0-based source index and
+/// the command, for `n` from 1 to [`MAX_SOURCES`].
+fn parse_address(address: &str) -> Option<(SourceId, &str)> {
+    let address = address.strip_prefix("/spatdif").unwrap_or(address);
+    let rest = address.strip_prefix("/source/")?;
+    let (number, command) = rest.split_once('/')?;
+    let number: usize = number.parse().ok()?;
+    if number > MAX_SOURCES {
+        return None;
+    }
+    Some((number.checked_sub(1)?, command))
+}
+
+/// A UDP socket receiving OSC packets on a background thread.
+///
+/// The thread stops when the server is dropped. Malformed packets are
+/// dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use vbap::osc::{OscControl, OscServer};
+///
+/// let server = OscServer::bind("0.0.0.0:9000", OscControl::new()).unwrap();
+/// let control = server.control().clone();
+/// // Move `control` into the audio callback and call
+/// // `control.apply(&mut mixer)` before each block
+/// ```
+#[derive(Debug)]
+pub struct OscServer {
+    local_addr: SocketAddr,
+    control: OscControl,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl OscServer {
+    /// Bind a UDP socket to `addr` and start receiving into `control`.
+    ///
+    /// Port 0 picks a free port; see [`local_addr`](Self::local_addr).
+    /// Returns [`VBAPError::Network`] if the socket cannot be bound.
+    pub fn bind(addr: impl ToSocketAddrs, control: OscControl) -> Result<Self> {
+        let socket = UdpSocket::bind(addr).map_err(network_error)?;
+        socket
+            .set_read_timeout(Some(POLL_INTERVAL))
+            .map_err(network_error)?;
+        let local_addr = socket.local_addr().map_err(network_error)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = Arc::clone(&stop);
+        let thread_control = control.clone();
+        let thread = std::thread::Builder::new()
+            .name("vbap-osc".into())
+            .spawn(move || {
+                let mut buffer = vec![0; MAX_PACKET_SIZE];
+                while !thread_stop.load(Ordering::Relaxed) {
+                    // Timeouts only wake the loop to check the stop flag
+                    if let Ok(len) = socket.recv(&mut buffer) {
+                        let _ = thread_control.handle_packet(&buffer[..len]);
+                    }
+                }
+            })
+            .map_err(network_error)?;
+        Ok(Self {
+            local_addr,
+            control,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Get the address the socket is bound to.
+    #[inline]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Get the control the server writes positions into.
+    #[inline]
+    pub fn control(&self) -> &OscControl {
+        &self.control
+    }
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn network_error(error: std::io::Error) -> VBAPError {
+    VBAPError::Network(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::panner::VBAPanner;
+    use approx::assert_relative_eq;
+    use std::time::Instant;
+
+    #[test]
+    fn test_encode_decode() {
+        let message = OscMessage::new(
+            "/test",
+            vec![
+                OscArg::Int(-3),
+                OscArg::Long(1 << 40),
+                OscArg::Float(0.5),
+                OscArg::Double(-2.25),
+                OscArg::String("aed".into()),
+                OscArg::Blob(vec![1, 2, 3, 4, 5]),
+                OscArg::Bool(true),
+                OscArg::Nil,
+                OscArg::Infinitum,
+            ],
+        );
+        let bytes = message.to_bytes();
+        assert_eq!(bytes.len() % 4, 0);
+        assert_eq!(OscMessage::decode_packet(&bytes).unwrap()[0], message);
+
+        // A bundle holding the message and a nested bundle holding it again
+        let element = |bytes: &[u8]| [&(bytes.len() as i32).to_be_bytes()[..], bytes].concat();
+        let header = [&b"#bundle\0"[..], &[0, 0, 0, 0, 0, 0, 0, 1]].concat();
+        let inner = [&header[..], &element(&bytes)].concat();
+        let outer = [&header[..], &element(&bytes), &element(&inner)].concat();
+        assert_eq!(
+            OscMessage::decode_packet(&outer).unwrap(),
+            [message.clone(), message]
+        );
+
+        // Without type tags
+        assert_eq!(
+            OscMessage::decode_packet(b"/ping\0\0\0").unwrap(),
+            [OscMessage::new("/ping", Vec::new())]
+        );
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed() {
+        let bytes = OscMessage::new("/a", vec![OscArg::Float(1.0)]).to_bytes();
+        assert!(OscMessage::decode_packet(&bytes[..bytes.len() - 1]).is_err());
+        assert!(OscMessage::decode_packet(b"/a\0\0,x\0\0").is_err());
+        assert!(OscMessage::decode_packet(b"noslash\0").is_err());
+        assert!(OscMessage::decode_packet(b"/a").is_err());
+        assert!(OscMessage::decode_packet(b"#bundle\0\0\0\0\0\0\0\0\0\0\0\0\x10").is_err());
+
+        // Deeply nested bundles are rejected rather than recursed into
+        let header = [&b"#bundle\0"[..], &[0; 8]].concat();
+        let mut packet = OscMessage::new("/a", Vec::new()).to_bytes();
+        for _ in 0..100 {
+            packet = [&header[..], &(packet.len() as i32).to_be_bytes(), &packet].concat();
+        }
+        assert!(OscMessage::decode_packet(&packet).is_err());
+        assert!(matches!(
+            OscMessage::decode_packet(b""),
+            Err(VBAPError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_addresses() {
+        let control = OscControl::new();
+        let send = |address: &str, args: Vec<OscArg>| {
+            control.handle_packet(&OscMessage::new(address, args).to_bytes())
+        };
+        let f = OscArg::Float;
+
+        assert_eq!(send("/source/2/aed", vec![f(90.0), f(10.0), f(3.0)]), Ok(1));
+        let position = control.position(1).unwrap();
+        assert_relative_eq!(position.azimuth, -90.0);
+        assert_relative_eq!(position.elevation, 10.0);
+        assert_eq!(position.distance, Some(3.0));
+
+        // Direction only keeps the distance
+        assert_eq!(
+            send("/spatdif/source/2/aed", vec![f(-45.0), OscArg::Int(0)]),
+            Ok(1)
+        );
+        let position = control.position(1).unwrap();
+        assert_relative_eq!(position.azimuth, 45.0);
+        assert_eq!(position.distance, Some(3.0));
+
+        // 2 m to the right
+        assert_eq!(send("/source/1/xyz", vec![f(2.0), f(0.0), f(0.0)]), Ok(1));
+        let position = control.position(0).unwrap();
+        assert_relative_eq!(position.azimuth, -90.0);
+        assert_relative_eq!(position.distance.unwrap(), 2.0);
+
+        let aed = OscArg::String("aed".into());
+        assert_eq!(
+            send(
+                "/spatdif/source/1/position",
+                vec![f(180.0), f(0.0), f(1.0), aed]
+            ),
+            Ok(1)
+        );
+        assert_relative_eq!(control.position(0).unwrap().azimuth, 180.0);
+        assert_eq!(
+            send("/source/1/position", vec![f(0.0), f(1.0), f(1.0)]),
+            Ok(1)
+        );
+        assert_relative_eq!(control.position(0).unwrap().elevation, 45.0);
+
+        // Ignored
+        assert_eq!(send("/source/0/aed", vec![f(0.0), f(0.0)]), Ok(0));
+        assert_eq!(send("/source/1/gain", vec![f(0.5)]), Ok(0));
+        assert_eq!(send("/source/1/xyz", vec![f(0.0), f(0.0)]), Ok(0));
+        assert_eq!(send("/source/1/aed", vec![f(f32::NAN), f(0.0)]), Ok(0));
+        assert_eq!(send("/listener/1/aed", vec![f(0.0), f(0.0)]), Ok(0));
+        assert_eq!(control.position(2), None);
+
+        // Source numbers beyond the limit are dropped without allocating
+        let huge = format!("/source/{}/aed", 50_000_000_000u64);
+        assert_eq!(send(&huge, vec![f(0.0), f(0.0)]), Ok(0));
+        let over = format!("/source/{}/aed", MAX_SOURCES + 1);
+        assert_eq!(send(&over, vec![f(0.0), f(0.0)]), Ok(0));
+        let last = format!("/source/{}/aed", MAX_SOURCES);
+        assert_eq!(send(&last, vec![f(0.0), f(0.0)]), Ok(1));
+        assert!(control.position(MAX_SOURCES - 1).is_some());
+    }
+
+    #[test]
+    fn test_apply() {
+        let panner = VBAPanner::builder()
+            .convention(Convention::Max)
+            .surround_5_1()
+            .build()
+            .unwrap();
+        let mut mixer = Mixer::new(panner);
+        let first = mixer.add_source();
+
+        let control = OscControl::new().with_convention(Convention::AmbiX);
+        let message = |source: usize, azimuth: f32| {
+            let address = format!("/source/{}/aed", source + 1);
+            OscMessage::new(address, vec![OscArg::Float(azimuth), OscArg::Float(0.0)])
+        };
+        assert!(control.handle_message(&message(first, 30.0)));
+        assert!(control.handle_message(&message(1, 60.0)));
+        assert!(control.apply(&mut mixer));
+        // Sent counter-clockwise, the layout is clockwise
+        assert_relative_eq!(mixer.sources()[first].azimuth(), -30.0);
+
+        // Applied positions are not applied again
+        mixer.set_position(first, 0.0, 0.0);
+        control.apply(&mut mixer);
+        assert_eq!(mixer.sources()[first].azimuth(), 0.0);
+
+        // The second source's position waits until it exists
+        let second = mixer.add_source();
+        control.apply(&mut mixer);
+        assert_relative_eq!(mixer.sources()[second].azimuth(), -60.0);
+    }
+
+    #[test]
+    fn test_server() {
+        let server = OscServer::bind("127.0.0.1:0", OscControl::new()).unwrap();
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.send_to(b"garbage", server.local_addr()).unwrap();
+        let args = vec![OscArg::Double(-30.0), OscArg::Double(0.0)];
+        let message = OscMessage::new("/source/1/aed", args);
+        socket
+            .send_to(&message.to_bytes(), server.local_addr())
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while server.control().position(0).is_none() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_relative_eq!(server.control().position(0).unwrap().azimuth, 30.0);
+    }
+}
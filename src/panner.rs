@@ -3,10 +3,42 @@
 //! This module provides the main `VBAPanner` struct that computes
 //! speaker gains for a given source position.
 
-use crate::config::{InverseMatrix, PanningMode, SpeakerConfig, SpeakerConfigBuilder};
-use crate::math::spherical_to_cartesian;
+use crate::config::{PanningMode, SpeakerConfig, SpeakerConfigBuilder, MAX_SPEAKERS};
+use crate::divergence::CenterDivergence;
+use crate::error::{Result, VBAPError};
+use crate::exclusion::SpeakerExclusions;
+use crate::extent::Extent;
+use crate::gains::Gains;
+use crate::mask::SpeakerMask;
+use crate::math::{cartesian_to_spherical, solid_angle, spherical_to_cartesian};
 use crate::speaker::Speaker;
-use glam::DVec2;
+use glam::{DVec2, DVec3};
+
+#[cfg(feature = "simd")]
+mod simd;
+
+/// How far below zero a tuple gain may be before a direction is considered
+/// outside an open arc.
+pub(crate) const ARC_EDGE_TOLERANCE: f64 = 1e-6;
+
+/// How far below zero a power-normalized gain may be before
+/// [`VBAPanner::compute_gains_strict`] reports the direction as uncovered.
+pub const NEGATIVE_GAIN_TOLERANCE: f64 = 1e-3;
+
+/// Tuples whose smallest gains differ by at most this much are tied, and
+/// the [`TieBreak`] policy picks between them.
+///
+/// A source on an edge shared by two tuples gives both a smallest gain of
+/// (nearly) zero; which one is larger then depends on rounding and can
+/// differ between platforms.
+pub const TIE_TOLERANCE: f64 = 1e-9;
+
+/// Range of the [focus](VBAPanner::with_focus) exponent.
+pub const FOCUS_RANGE: (f64, f64) = (0.1, 10.0);
+
+/// Angle step in degrees of the central differences in
+/// [`VBAPanner::gain_jacobian`].
+const JACOBIAN_STEP: f64 = 1e-4;
 
 /// Vector Base Amplitude Panner.
 ///
@@ -29,7 +61,295 @@ use glam::DVec2;
 /// ```
 #[derive(Clone, Debug)]
 pub struct VBAPanner {
+    /// Triangulation used for panning.
     config: SpeakerConfig,
+    /// Full layout while some speakers are inactive (outside the zone or
+    /// disabled); `config` is then its re-triangulation without them.
+    layout: Option<SpeakerConfig>,
+    /// Speakers a restricted panner may use.
+    zone: SpeakerMask,
+    /// Speakers taken out of the panning at runtime.
+    disabled: SpeakerMask,
+    /// Per-speaker gain overrides that bypass VBAP.
+    frozen: Vec<Option<f64>>,
+    /// Margin before switching away from the previously used tuple.
+    hysteresis: f64,
+    /// Normalization of the selected tuple's gains.
+    normalization: Normalization,
+    /// Whether tuple gains are amplitudes (VBAP) or intensities (VBIP).
+    panning_law: PanningLaw,
+    /// Exponent applied to the tuple gains before normalization.
+    focus: f64,
+    /// How gains are rescaled after constraints.
+    renormalization: Renormalization,
+    /// Choice between tuples that fit a direction equally well.
+    tie_break: TieBreak,
+    /// Treatment of elevated sources on 2D layouts.
+    elevation_policy: ElevationPolicy,
+    /// How much the level of sources spread over several speakers is
+    /// lowered, from 0 (none) to 1.
+    loudness_compensation: f64,
+}
+
+/// Per-source panning memory.
+///
+/// Stores which tuple was used for the previous call so that
+/// [`VBAPanner::compute_gains_with_state`] can try it first and avoid
+/// switching tuples needlessly. Create one per moving source.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PanningState {
+    last_tuple: Option<usize>,
+}
+
+impl PanningState {
+    /// Create an empty state (no previous tuple).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index of the tuple used by the previous call, if any.
+    #[inline]
+    pub fn last_tuple(&self) -> Option<usize> {
+        self.last_tuple
+    }
+
+    /// Forget the previous tuple, e.g. after a source jumps.
+    pub fn reset(&mut self) {
+        self.last_tuple = None;
+    }
+}
+
+/// How the gains of the selected speaker tuple are normalized.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Normalization {
+    /// Sum of squared gains = 1 (constant power), the classic VBAP choice
+    /// for incoherent, mid/high-frequency content.
+    #[default]
+    Power,
+    /// Sum of gains = 1 (constant amplitude), for coherent low-frequency
+    /// content where speaker signals add in phase.
+    Amplitude,
+    /// Loudest speaker gain = 1.
+    MaxGainOne,
+}
+
+impl Normalization {
+    /// Factor that normalizes `raw` gains, or 0 if their level is below `floor`.
+    pub(crate) fn factor(self, raw: &[f64], floor: f64) -> f64 {
+        let level = match self {
+            Normalization::Power => raw.iter().map(|g| g * g).sum::<f64>().sqrt(),
+            Normalization::Amplitude => raw.iter().map(|g| g.abs()).sum(),
+            Normalization::MaxGainOne => raw.iter().fold(0.0, |m: f64, g| m.max(g.abs())),
+        };
+        if level > floor {
+            1.0 / level
+        } else {
+            0.0
+        }
+    }
+}
+
+/// What the gains of the selected speaker tuple represent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PanningLaw {
+    /// Vector base amplitude panning: the gains weight the speaker vectors
+    /// so they sum to the source direction.
+    #[default]
+    Amplitude,
+    /// Vector base intensity panning (VBIP): the gains are the square roots
+    /// of the VBAP gains, so the speakers' intensities rather than their
+    /// amplitudes follow the source. With power normalization the squared
+    /// gains equal the amplitude-normalized VBAP gains. Transitions between
+    /// speakers are softer, which some prefer for diffuse material.
+    Intensity,
+}
+
+/// Everything that turns a tuple's raw gains into normalized ones.
+#[derive(Clone, Copy, Debug)]
+struct GainShape {
+    normalization: Normalization,
+    law: PanningLaw,
+    focus: f64,
+}
+
+impl GainShape {
+    /// Shape one raw tuple gain before normalization. Negative gains keep
+    /// their sign, so they are still recognized and clamped later.
+    #[inline]
+    fn apply(self, gain: f64) -> f64 {
+        let exponent = match self.law {
+            PanningLaw::Amplitude => self.focus,
+            PanningLaw::Intensity => 0.5 * self.focus,
+        };
+        if exponent == 1.0 {
+            gain
+        } else {
+            gain.signum() * gain.abs().powf(exponent)
+        }
+    }
+
+    /// Undo [`apply`](Self::apply), up to the normalization factor.
+    #[inline]
+    fn invert(self, gain: f64) -> f64 {
+        GainShape {
+            focus: 1.0 / self.focus,
+            law: PanningLaw::Amplitude,
+            ..self
+        }
+        .apply(match self.law {
+            PanningLaw::Amplitude => gain,
+            PanningLaw::Intensity => gain.signum() * gain * gain,
+        })
+    }
+}
+
+/// Which tuple to use when several fit a direction equally well.
+///
+/// Tuples are tied when their smallest gains are within [`TIE_TOLERANCE`],
+/// typically because the source sits on an edge they share. Every policy
+/// is deterministic; they only differ in which tuple wins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// The tuple with the lowest index.
+    #[default]
+    LowestIndex,
+    /// The tuple used for the previous call of
+    /// [`VBAPanner::compute_gains_with_state`], then the lowest index.
+    PreviousTuple,
+    /// The tuple spanning the smallest solid angle (arc length for pairs),
+    /// then the lowest index. Smaller tuples localize more sharply.
+    SmallestSolidAngle,
+}
+
+/// How gains are rescaled after constraints (negative-gain clamping, frozen
+/// speakers, open-arc edge clamping) have modified them.
+///
+/// VBAP normalizes the selected tuple before constraints are applied, so any
+/// constraint that removes gain also removes level. This policy restores it
+/// on the speakers still driven by VBAP. Frozen speakers are never rescaled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Renormalization {
+    /// Leave constrained gains as they are.
+    #[default]
+    Off,
+    /// Rescale so the sum of squared gains matches the unconstrained gains.
+    PreserveEnergy,
+    /// Rescale so the sum of gains matches the unconstrained gains.
+    PreserveAmplitude,
+}
+
+/// How a 2D (horizontal-only) layout treats elevated sources.
+///
+/// Pairwise panning only looks at a source's azimuth, so by default a
+/// source straight overhead plays exactly like one at ear height, and at
+/// the zenith itself, where the azimuth is undefined, it falls silent.
+/// 3D layouts are not affected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ElevationPolicy {
+    /// Pan by azimuth alone.
+    #[default]
+    Ignore,
+    /// Scale the gains by the cosine of the elevation, fading sources out
+    /// towards the zenith and nadir.
+    Attenuate,
+    /// Blend the pairwise gains with an even spread over all speakers in
+    /// use, by `1 - cos(elevation)`, so a source overhead surrounds the
+    /// listener. The blend is normalized again.
+    Spread,
+    /// Render height as phantom images, in the manner of Atmos renderers on
+    /// layouts without height speakers: as the source rises, its energy
+    /// moves from its own direction to a pair of images in front of and
+    /// behind the listener at its lateral position, balanced by how far
+    /// front or back it is. A source overhead plays equally from front and
+    /// rear, which reads as elevated rather than as a point on the ring.
+    PhantomHeight,
+}
+
+/// Level of a gain vector, used as the renormalization reference.
+#[derive(Clone, Copy, Debug, Default)]
+struct GainLevel {
+    /// Sum of squared gains.
+    energy: f64,
+    /// Sum of absolute gains.
+    amplitude: f64,
+}
+
+/// Raw (unnormalized) gains of a candidate tuple for one direction.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TupleGains {
+    /// Index of the tuple in [`SpeakerConfig::tuples`].
+    pub tuple_index: usize,
+    gains: [f64; 3],
+    len: usize,
+}
+
+impl TupleGains {
+    /// The raw gains, one per speaker in the tuple.
+    #[inline]
+    pub fn gains(&self) -> &[f64] {
+        &self.gains[..self.len]
+    }
+
+    /// The smallest raw gain; negative if the direction is outside the tuple.
+    #[inline]
+    pub fn min_gain(&self) -> f64 {
+        self.gains()
+            .iter()
+            .copied()
+            .reduce(f64::min)
+            .unwrap_or(f64::NEG_INFINITY)
+    }
+}
+
+/// The speakers a source actually reaches: at most three (index, gain)
+/// pairs.
+///
+/// Iterating yields the pairs in tuple order.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ActiveGains {
+    entries: [(usize, f64); 3],
+    len: usize,
+}
+
+impl ActiveGains {
+    /// Get the (speaker index, gain) pairs.
+    #[inline]
+    pub fn as_slice(&self) -> &[(usize, f64)] {
+        &self.entries[..self.len]
+    }
+
+    /// Get the number of active speakers.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check whether no speaker is active.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterate over the (speaker index, gain) pairs.
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, (usize, f64)> {
+        self.as_slice().iter()
+    }
+
+    #[inline]
+    fn push(&mut self, speaker: usize, gain: f64) {
+        self.entries[self.len] = (speaker, gain);
+        self.len += 1;
+    }
+}
+
+impl IntoIterator for ActiveGains {
+    type Item = (usize, f64);
+    type IntoIter = std::iter::Take<std::array::IntoIter<(usize, f64), 3>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter().take(self.len)
+    }
 }
 
 impl VBAPanner {
@@ -41,8 +361,28 @@ impl VBAPanner {
     }
 
     /// Create a panner from an existing speaker configuration.
+    ///
+    /// Virtual speakers (see [`Speaker::new_virtual`]) are frozen at zero
+    /// gain, so their share of the panning is dropped or, with
+    /// [`Renormalization`], redistributed to the physical speakers.
     pub fn new(config: SpeakerConfig) -> Self {
-        Self { config }
+        let n = config.num_speakers();
+        let frozen = virtual_gains(&config);
+        Self {
+            config,
+            layout: None,
+            zone: SpeakerMask::all(n),
+            disabled: SpeakerMask::none(n),
+            frozen,
+            hysteresis: 0.0,
+            normalization: Normalization::Power,
+            panning_law: PanningLaw::Amplitude,
+            focus: 1.0,
+            renormalization: Renormalization::Off,
+            tie_break: TieBreak::LowestIndex,
+            elevation_policy: ElevationPolicy::Ignore,
+            loudness_compensation: 0.0,
+        }
     }
 
     /// Compute speaker gains for a source at the given position.
@@ -51,9 +391,13 @@ impl VBAPanner {
     /// * `azimuth` - Horizontal angle in degrees (0° = front, 90° = left, -90° = right)
     /// * `elevation` - Vertical angle in degrees (0° = horizontal, 90° = above)
     ///
+    /// Angles are in the layout's [`convention`](SpeakerConfig::convention),
+    /// as for all source directions passed to the panner.
+    ///
     /// # Returns
-    /// A vector of gains, one per speaker. Gains are normalized so that
-    /// the sum of squared gains equals 1.0. Most gains will be 0.0,
+    /// A vector of gains, one per speaker. By default gains are normalized
+    /// so that the sum of squared gains equals 1.0 (see
+    /// [`with_normalization`](Self::with_normalization)). Most gains will be 0.0,
     /// with only 2-3 speakers active (depending on 2D/3D mode).
     pub fn compute_gains(&self, azimuth: f64, elevation: f64) -> Vec<f64> {
         let mut gains = vec![0.0; self.config.num_speakers()];
@@ -61,6 +405,14 @@ impl VBAPanner {
         gains
     }
 
+    /// Compute speaker gains for a source, paired with the speakers.
+    ///
+    /// Same values as [`compute_gains`](Self::compute_gains), wrapped in
+    /// [`Gains`] for lookup by channel label and dB conversion.
+    pub fn gains(&self, azimuth: f64, elevation: f64) -> Gains<'_> {
+        Gains::new(self.compute_gains(azimuth, elevation), self.speakers())
+    }
+
     /// Compute speaker gains into a pre-allocated slice.
     ///
     /// This avoids allocation when called repeatedly.
@@ -79,175 +431,2179 @@ impl VBAPanner {
         // Zero out all gains
         gains.fill(0.0);
 
-        let tuples = self.config.tuples();
-        if tuples.is_empty() {
-            return;
-        }
-
         // Convert source direction to Cartesian
-        let direction = spherical_to_cartesian(azimuth, elevation);
-
-        // Find the best tuple (highest minimum gain)
-        let mut best_tuple_idx = 0;
-        let mut best_min_gain = f64::NEG_INFINITY;
-        let mut best_gains = [0.0f64; 3];
-        let mut best_len = 0usize;
-
-        for (tuple_idx, tuple) in tuples.iter().enumerate() {
-            // Compute candidate gains by multiplying direction with inverse matrix
-            let (candidate_gains, len) = match tuple.inverse_matrix {
-                InverseMatrix::ThreeD(mat) => {
-                    let result = mat * direction;
-                    ([result.x, result.y, result.z], 3)
-                }
-                InverseMatrix::TwoD(mat) => {
-                    let dir_2d = DVec2::new(direction.x, direction.y);
-                    let result = mat * dir_2d;
-                    ([result.x, result.y, 0.0], 2)
-                }
-            };
-
-            // Find minimum gain - we want the tuple where all gains are positive
-            let min_gain = candidate_gains[..len]
-                .iter()
-                .copied()
-                .reduce(f64::min)
-                .unwrap_or(f64::NEG_INFINITY);
+        let direction = self.source_direction(azimuth, elevation);
 
-            if min_gain > best_min_gain {
-                best_min_gain = min_gain;
-                best_tuple_idx = tuple_idx;
-                best_gains = candidate_gains;
-                best_len = len;
-            }
-        }
-
-        // Apply the winning gains
-        let best_tuple = &tuples[best_tuple_idx];
+        let selected = select_tuple(&self.config, direction, self.tie_break, None);
+        self.write_gains(&self.config, selected, direction, gains);
+    }
 
-        // Normalize gains: sqrt(sum of squares) = 1
-        let sum_sq: f64 = best_gains[..best_len].iter().map(|g| g * g).sum();
-        let norm = if sum_sq > 1e-10 {
-            1.0 / sum_sq.sqrt()
-        } else {
-            0.0
-        };
+    /// Compute speaker gains, failing if the layout does not cover the direction.
+    ///
+    /// [`compute_gains_into`](Self::compute_gains_into) clamps negative tuple
+    /// gains to zero. Inside a tuple they are only rounding noise, but when no
+    /// tuple contains the direction (a gap in the layout, or below a
+    /// hemisphere) the clamp silently drops part of the panning. This
+    /// returns [`VBAPError::UncoveredDirection`] when the best tuple's
+    /// smallest power-normalized gain is below `-NEGATIVE_GAIN_TOLERANCE`.
+    /// `gains` is filled with the clamped gains either way. Directions
+    /// outside an open arc are clamped to its end by design and pass.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_speakers()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::{VBAPError, VBAPanner};
+    ///
+    /// let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+    /// let mut gains = vec![0.0; panner.num_speakers()];
+    ///
+    /// assert!(panner.compute_gains_strict(30.0, 20.0, &mut gains).is_ok());
+    /// // Nothing below the horizon
+    /// let err = panner.compute_gains_strict(30.0, -45.0, &mut gains);
+    /// assert!(matches!(err, Err(VBAPError::UncoveredDirection { .. })));
+    /// ```
+    pub fn compute_gains_strict(
+        &self,
+        azimuth: f64,
+        elevation: f64,
+        gains: &mut [f64],
+    ) -> Result<()> {
+        self.compute_gains_into(azimuth, elevation, gains);
 
-        for (&speaker_idx, &gain) in best_tuple
-            .speaker_indices
-            .iter()
-            .zip(&best_gains[..best_len])
-        {
-            gains[speaker_idx] = (gain * norm).max(0.0);
+        match self.min_tuple_gain(azimuth, elevation) {
+            Some(min_gain) if min_gain < -NEGATIVE_GAIN_TOLERANCE => {
+                Err(VBAPError::UncoveredDirection {
+                    azimuth,
+                    elevation,
+                    min_gain,
+                })
+            }
+            _ => Ok(()),
         }
     }
 
-    /// Get the number of speakers in this configuration.
-    #[inline]
-    pub fn num_speakers(&self) -> usize {
-        self.config.num_speakers()
+    /// Get the smallest power-normalized gain of the tuple selected for a
+    /// direction, before negative gains are clamped.
+    ///
+    /// A clearly negative value means no tuple covers the direction. Returns
+    /// `None` for directions outside an open arc, which are clamped to the
+    /// arc's end speaker instead.
+    pub fn min_tuple_gain(&self, azimuth: f64, elevation: f64) -> Option<f64> {
+        let direction = self.source_direction(azimuth, elevation);
+        let selected = select_tuple(&self.config, direction, self.tie_break, None)?;
+        let min_gain = selected.min_gain();
+        if self.config.arc_ends().is_some() && min_gain < -ARC_EDGE_TOLERANCE {
+            return None;
+        }
+        Some(
+            min_gain
+                * Normalization::Power.factor(
+                    selected.gains(),
+                    self.config.tolerances().normalization_floor,
+                ),
+        )
     }
 
-    /// Get the panning mode (2D or 3D).
+    /// Check whether the active speakers cover a direction, see
+    /// [`SpeakerConfig::covers`].
     #[inline]
-    pub fn mode(&self) -> PanningMode {
-        self.config.mode()
+    pub fn covers(&self, azimuth: f64, elevation: f64) -> bool {
+        self.config
+            .covers_direction(self.source_direction(azimuth, elevation))
     }
 
-    /// Get all speakers in the configuration.
-    #[inline]
-    pub fn speakers(&self) -> &[Speaker] {
-        self.config.speakers()
-    }
+    /// Compute only the nonzero speaker gains for a source direction.
+    ///
+    /// Yields `(speaker index, gain)` pairs: the 1–3 speakers of the
+    /// selected tuple, followed by any frozen speakers with a nonzero gain.
+    /// The values match [`compute_gains`](Self::compute_gains), but nothing is
+    /// allocated and no per-speaker vector is filled or scanned, which
+    /// matters for large layouts.
+    ///
+    /// The [`Spread`](ElevationPolicy::Spread) and
+    /// [`PhantomHeight`](ElevationPolicy::PhantomHeight) elevation policies
+    /// are not applied here: they move gain onto speakers outside the
+    /// tuple, so elevated sources on a 2D layout with either policy get the
+    /// unfolded tuple gains. Use [`compute_gains_into`](Self::compute_gains_into)
+    /// for those.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+    /// let mut output = vec![0.0f32; panner.num_speakers()];
+    ///
+    /// for (speaker, gain) in panner.compute_active_gains(45.0, 30.0) {
+    ///     output[speaker] += 0.5 * gain as f32;
+    /// }
+    /// ```
+    pub fn compute_active_gains(
+        &self,
+        azimuth: f64,
+        elevation: f64,
+    ) -> impl Iterator<Item = (usize, f64)> + '_ {
+        let direction = self.source_direction(azimuth, elevation);
 
-    /// Get the underlying speaker configuration.
-    #[inline]
-    pub fn config(&self) -> &SpeakerConfig {
-        &self.config
-    }
-}
+        let mut active = ActiveGains::default();
+        if let Some(selected) = select_tuple(&self.config, direction, self.tie_break, None) {
+            let (tuple_gains, reference) =
+                active_tuple_gains(&self.config, &selected, direction, self.gain_shape());
+            let free = tuple_gains
+                .iter()
+                .filter(|&&(i, _)| !self.is_frozen(i))
+                .map(|&(_, g)| g);
+            let mut scale = self.renormalization_scale(reference, free).unwrap_or(1.0);
+            if self.elevation_policy == ElevationPolicy::Attenuate
+                && self.config.mode() == PanningMode::TwoD
+            {
+                scale *= (1.0 - direction.z * direction.z).max(0.0).sqrt();
+            }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use approx::assert_relative_eq;
+            scale *= self.loudness_scale(
+                tuple_gains
+                    .iter()
+                    .filter(|&&(i, g)| g > 0.0 && !self.is_frozen(i))
+                    .map(|&(_, g)| g),
+            );
 
-    #[test]
-    fn test_stereo_center() {
-        let panner = VBAPanner::builder().stereo().build().unwrap();
-        let gains = panner.compute_gains(0.0, 0.0);
+            for (speaker_idx, gain) in tuple_gains {
+                if gain > 0.0 && !self.is_frozen(speaker_idx) {
+                    active.push(speaker_idx, gain * scale);
+                }
+            }
+        }
 
-        assert_eq!(gains.len(), 2);
-        // Center pan should have equal gains
-        assert_relative_eq!(gains[0], gains[1], epsilon = 0.01);
+        let frozen = self
+            .frozen
+            .iter()
+            .enumerate()
+            .filter_map(|(i, f)| f.filter(|&g| g != 0.0).map(|g| (i, g)));
+        active.into_iter().chain(frozen)
     }
 
-    #[test]
-    fn test_stereo_hard_left() {
-        let panner = VBAPanner::builder().stereo().build().unwrap();
-        // Stereo is at ±30°, so 30° should be hard left
-        let gains = panner.compute_gains(30.0, 0.0);
+    /// Compute the derivatives of the speaker gains with respect to the
+    /// source's azimuth and elevation, per degree.
+    ///
+    /// Entry `i` is `(d gain_i / d azimuth, d gain_i / d elevation)`. The
+    /// tuple selected for the direction is held fixed, so the derivatives
+    /// are those of the current facet, including normalization and the
+    /// panner's other gain stages; speakers outside the facet, and frozen
+    /// speakers, have zero derivatives. They are taken by central
+    /// differences, so on a facet edge a speaker fading in or out gets the
+    /// mean of its one-sided slopes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder().stereo().build().unwrap();
+    /// let jacobian = panner.gain_jacobian(0.0, 0.0);
+    /// // Moving left raises the left gain and lowers the right one
+    /// assert!(jacobian[0].0 > 0.0 && jacobian[1].0 < 0.0);
+    /// ```
+    pub fn gain_jacobian(&self, azimuth: f64, elevation: f64) -> Vec<(f64, f64)> {
+        let n = self.config.num_speakers();
+        let mut jacobian = vec![(0.0, 0.0); n];
+        let direction = self.source_direction(azimuth, elevation);
+        let Some(selected) = select_tuple(&self.config, direction, self.tie_break, None) else {
+            return jacobian;
+        };
 
-        assert_eq!(gains.len(), 2);
-        // Left speaker (index 0) should be louder
-        assert!(gains[0] > gains[1]);
+        let mut plus = vec![0.0; n];
+        let mut minus = vec![0.0; n];
+        let slope =
+            |plus: &[f64], minus: &[f64], i: usize| (plus[i] - minus[i]) / (2.0 * JACOBIAN_STEP);
+        let tuple = selected.tuple_index;
+        self.facet_gains(tuple, azimuth + JACOBIAN_STEP, elevation, &mut plus);
+        self.facet_gains(tuple, azimuth - JACOBIAN_STEP, elevation, &mut minus);
+        for (i, entry) in jacobian.iter_mut().enumerate() {
+            entry.0 = slope(&plus, &minus, i);
+        }
+        self.facet_gains(tuple, azimuth, elevation + JACOBIAN_STEP, &mut plus);
+        self.facet_gains(tuple, azimuth, elevation - JACOBIAN_STEP, &mut minus);
+        for (i, entry) in jacobian.iter_mut().enumerate() {
+            entry.1 = slope(&plus, &minus, i);
+        }
+        jacobian
     }
 
-    #[test]
-    fn test_stereo_hard_right() {
-        let panner = VBAPanner::builder().stereo().build().unwrap();
-        let gains = panner.compute_gains(-30.0, 0.0);
+    /// Estimate the source direction that produced a set of gains.
+    ///
+    /// This inverts the panning: the speakers with nonzero gains make up
+    /// the facet, and undoing the [panning law](Self::with_panning_law)
+    /// and [focus](Self::with_focus) and solving the facet's
+    /// equations gives the direction, in the layout's
+    /// [`convention`](SpeakerConfig::convention). Normalization and overall
+    /// level do not matter. Frozen speakers are ignored. 2D layouts pan
+    /// horizontally, so their estimates have elevation 0.
+    ///
+    /// Gains spread over several facets, for example by an
+    /// [extent](Self::compute_gains_with_extent), give their weighted mean direction.
+    /// Silent gains give the front, (0, 0).
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_speakers()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+    /// let gains = panner.compute_gains(60.0, 20.0);
+    /// let (azimuth, elevation) = panner.estimate_direction(&gains);
+    /// assert!((azimuth - 60.0).abs() < 1e-9 && (elevation - 20.0).abs() < 1e-9);
+    /// ```
+    pub fn estimate_direction(&self, gains: &[f64]) -> (f64, f64) {
+        let speakers = self.config.speakers();
+        assert!(
+            gains.len() >= speakers.len(),
+            "gains slice too small: {} < {}",
+            gains.len(),
+            speakers.len()
+        );
+        let shape = self.gain_shape();
+        let two_d = self.config.mode() == PanningMode::TwoD;
+        let mut direction = DVec3::ZERO;
+        for (i, (speaker, &gain)) in speakers.iter().zip(gains).enumerate() {
+            if gain == 0.0 || self.is_frozen(i) {
+                continue;
+            }
+            let position = if two_d {
+                spherical_to_cartesian(speaker.azimuth(), 0.0)
+            } else {
+                speaker.cartesian()
+            };
+            direction += shape.invert(gain) * position;
+        }
+        let (azimuth, elevation) = cartesian_to_spherical(direction);
+        self.config.convention().from_native(azimuth, elevation)
+    }
 
-        assert_eq!(gains.len(), 2);
-        // Right speaker (index 1) should be louder
-        assert!(gains[1] > gains[0]);
+    /// Compute the gains for a direction on a given tuple, whether or not
+    /// it is the one the direction would select.
+    fn facet_gains(&self, tuple_index: usize, azimuth: f64, elevation: f64, gains: &mut [f64]) {
+        gains.fill(0.0);
+        let direction = self.source_direction(azimuth, elevation);
+        let (raw, len) = self.config.tuples().raw_gains(tuple_index, direction);
+        let selected = TupleGains {
+            tuple_index,
+            gains: raw,
+            len,
+        };
+        self.write_gains(&self.config, Some(selected), direction, gains);
     }
 
-    #[test]
-    fn test_gains_normalized() {
-        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+    /// Compute speaker gains for many directions at once.
+    ///
+    /// `out` is filled as a row-major `directions.len() × num_speakers()`
+    /// matrix: row `i` holds the gains for `directions[i]`.
+    ///
+    /// With the `rayon` feature, rows are computed in parallel.
+    ///
+    /// # Panics
+    /// Panics if `out.len() != directions.len() * self.num_speakers()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder().stereo().build().unwrap();
+    /// let directions = [(30.0, 0.0), (0.0, 0.0), (-30.0, 0.0)];
+    /// let mut out = vec![0.0; directions.len() * panner.num_speakers()];
+    ///
+    /// panner.compute_gains_batch(&directions, &mut out);
+    /// assert!(out[0] > 0.99); // first row: hard left
+    /// ```
+    pub fn compute_gains_batch(&self, directions: &[(f64, f64)], out: &mut [f64]) {
+        let n = self.config.num_speakers();
+        assert_eq!(
+            out.len(),
+            directions.len() * n,
+            "output must hold {} directions x {} speakers",
+            directions.len(),
+            n
+        );
 
-        for azi in [-180, -90, -45, 0, 45, 90, 180] {
-            let gains = panner.compute_gains(azi as f64, 0.0);
-            let sum_sq: f64 = gains.iter().map(|g| g * g).sum();
-            assert_relative_eq!(sum_sq, 1.0, epsilon = 0.01);
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            out.par_chunks_exact_mut(n)
+                .zip(directions.par_iter())
+                .for_each(|(row, &(azimuth, elevation))| {
+                    self.compute_gains_into(azimuth, elevation, row);
+                });
         }
-    }
-
-    #[test]
-    fn test_gains_non_negative() {
-        let panner = VBAPanner::builder().surround_7_1().build().unwrap();
 
-        for azi in (-180..=180).step_by(15) {
-            let gains = panner.compute_gains(azi as f64, 0.0);
-            for g in &gains {
-                assert!(*g >= 0.0, "gain {} at azi {} is negative", g, azi);
-            }
+        #[cfg(not(feature = "rayon"))]
+        for (&(azimuth, elevation), row) in directions.iter().zip(out.chunks_exact_mut(n)) {
+            self.compute_gains_into(azimuth, elevation, row);
         }
     }
 
-    #[test]
-    fn test_compute_gains_into() {
-        let panner = VBAPanner::builder().stereo().build().unwrap();
-        let mut gains = vec![0.0; 2];
-
-        panner.compute_gains_into(15.0, 0.0, &mut gains);
+    /// Compute speaker gains for a source, remembering the chosen tuple.
+    ///
+    /// Behaves like [`compute_gains_into`](Self::compute_gains_into), but uses
+    /// `state` to remember the previously used tuple:
+    ///
+    /// - If the source is still inside that tuple (all raw gains
+    ///   non-negative), it is reused without scanning the others. For smooth
+    ///   trajectories this makes per-sample panning nearly O(1) instead of
+    ///   O(number of tuples).
+    /// - Otherwise all tuples are scanned, and the panner's
+    ///   [hysteresis](Self::with_hysteresis) keeps the previous tuple until
+    ///   another one is better by more than the margin.
+    ///
+    /// Keep one `PanningState` per source.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_speakers()`.
+    pub fn compute_gains_with_state(
+        &self,
+        azimuth: f64,
+        elevation: f64,
+        state: &mut PanningState,
+        gains: &mut [f64],
+    ) {
+        assert!(
+            gains.len() >= self.config.num_speakers(),
+            "gains slice too small: {} < {}",
+            gains.len(),
+            self.config.num_speakers()
+        );
 
-        let sum_sq: f64 = gains.iter().map(|g| g * g).sum();
-        assert_relative_eq!(sum_sq, 1.0, epsilon = 0.01);
-    }
+        gains.fill(0.0);
 
-    #[test]
-    fn test_3d_panning() {
-        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let direction = self.source_direction(azimuth, elevation);
 
-        assert_eq!(panner.mode(), PanningMode::ThreeD);
-        assert_eq!(panner.num_speakers(), 11);
+        let previous = state.last_tuple.and_then(|prev| {
+            let tuple = self.config.tuples().get(prev)?;
+            let (raw, len) = tuple.raw_gains(direction);
+            Some(TupleGains {
+                tuple_index: prev,
+                gains: raw,
+                len,
+            })
+        });
 
-        // Elevated source should activate height speakers
+        let selected = match previous {
+            // Fast path: the source has not left the previous tuple
+            Some(previous) if previous.min_gain() >= 0.0 => Some(previous),
+            _ => {
+                let best = select_tuple(&self.config, direction, self.tie_break, state.last_tuple);
+                match (best, previous) {
+                    (Some(best), Some(previous))
+                        if previous.min_gain() >= best.min_gain() - self.hysteresis =>
+                    {
+                        Some(previous)
+                    }
+                    _ => best,
+                }
+            }
+        };
+
+        let tuple = selected.map(|s| s.tuple_index);
+        #[cfg(feature = "tracing")]
+        if let (Some(from), Some(to)) = (state.last_tuple, tuple) {
+            if from != to {
+                crate::trace::tuple_switch(from, to, azimuth, elevation);
+            }
+        }
+        state.last_tuple = tuple;
+        self.write_gains(&self.config, selected, direction, gains);
+    }
+
+    /// Compute speaker gains for a source that must avoid some speakers.
+    ///
+    /// The panner re-triangulates the layout without the excluded speakers
+    /// (once, lazily; the result is cached in `exclusions`) so the remaining
+    /// speakers still form a properly normalized VBAP panning, instead of
+    /// zeroing gains after the fact. Keep one [`SpeakerExclusions`] per source.
+    ///
+    /// Returns an error if the remaining speakers cannot form a valid layout.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_speakers()`.
+    pub fn compute_gains_excluding(
+        &self,
+        azimuth: f64,
+        elevation: f64,
+        exclusions: &mut SpeakerExclusions,
+        gains: &mut [f64],
+    ) -> Result<()> {
+        assert!(
+            gains.len() >= self.config.num_speakers(),
+            "gains slice too small: {} < {}",
+            gains.len(),
+            self.config.num_speakers()
+        );
+
+        gains.fill(0.0);
+
+        let config = exclusions.config_for(&self.config)?;
+        let direction = self.source_direction(azimuth, elevation);
+
+        let selected = select_tuple(config, direction, self.tie_break, None);
+        self.write_gains(config, selected, direction, gains);
+        Ok(())
+    }
+
+    /// Compute speaker gains with a center divergence applied.
+    ///
+    /// The gains of the full layout and of the phantom layout without the
+    /// center speaker are blended by the divergence and normalized again, so
+    /// frontal sources move smoothly between the physical and a phantom
+    /// center. Keep one [`CenterDivergence`] per source.
+    ///
+    /// Returns an error if the center index is out of range or the layout
+    /// without it cannot be triangulated.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_speakers()`.
+    pub fn compute_gains_diverged(
+        &self,
+        azimuth: f64,
+        elevation: f64,
+        divergence: &mut CenterDivergence,
+        gains: &mut [f64],
+    ) -> Result<()> {
+        let n = self.config.num_speakers();
+        assert!(
+            gains.len() >= n,
+            "gains slice too small: {} < {}",
+            gains.len(),
+            n
+        );
+
+        gains.fill(0.0);
+
+        let direction = self.source_direction(azimuth, elevation);
+        let amount = divergence.divergence();
+        let phantom = divergence.phantom_config_for(&self.config)?;
+
+        for (config, weight) in [(&self.config, 1.0 - amount), (phantom, amount)] {
+            if weight == 0.0 {
+                continue;
+            }
+            if let Some(selected) = select_tuple(config, direction, self.tie_break, None) {
+                let (active, _) =
+                    active_tuple_gains(config, &selected, direction, self.gain_shape());
+                for (speaker_idx, gain) in active {
+                    gains[speaker_idx] += weight * gain;
+                }
+            }
+        }
+
+        let norm = self
+            .normalization
+            .factor(&gains[..n], self.config.tolerances().normalization_floor);
+        for gain in &mut gains[..n] {
+            *gain *= norm;
+        }
+        self.fold_elevation(&self.config, direction, gains);
+        self.compensate_loudness(gains);
+        self.apply_frozen_gains(gains);
+        Ok(())
+    }
+
+    /// Compute speaker gains for a source with a width and height.
+    ///
+    /// The extent is covered with virtual point sources whose gains are
+    /// summed by power per speaker (they stand for decorrelated parts of
+    /// the source) and normalized again. A point extent gives the same gains
+    /// as [`compute_gains_into`](Self::compute_gains_into).
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_speakers()`.
+    pub fn compute_gains_with_extent(
+        &self,
+        azimuth: f64,
+        elevation: f64,
+        extent: Extent,
+        gains: &mut [f64],
+    ) {
+        if extent.is_point() {
+            self.compute_gains_into(azimuth, elevation, gains);
+            return;
+        }
+
+        let n = self.config.num_speakers();
+        assert!(
+            gains.len() >= n,
+            "gains slice too small: {} < {}",
+            gains.len(),
+            n
+        );
+
+        gains.fill(0.0);
+
+        let direction = self.source_direction(azimuth, elevation);
+        extent.for_each_direction(direction, |point| {
+            if let Some(selected) = select_tuple(&self.config, point, self.tie_break, None) {
+                let (active, _) =
+                    active_tuple_gains(&self.config, &selected, point, self.gain_shape());
+                for (speaker_idx, gain) in active {
+                    gains[speaker_idx] += gain * gain;
+                }
+            }
+        });
+        for gain in &mut gains[..n] {
+            *gain = gain.sqrt();
+        }
+
+        let norm = self
+            .normalization
+            .factor(&gains[..n], self.config.tolerances().normalization_floor);
+        for gain in &mut gains[..n] {
+            *gain *= norm;
+        }
+        self.fold_elevation(&self.config, direction, gains);
+        self.compensate_loudness(gains);
+        self.apply_frozen_gains(gains);
+    }
+
+    /// Write the final gains for a selected tuple (and any overrides).
+    fn write_gains(
+        &self,
+        config: &SpeakerConfig,
+        selected: Option<TupleGains>,
+        direction: DVec3,
+        gains: &mut [f64],
+    ) {
+        if let Some(selected) = selected {
+            let (active, reference) =
+                active_tuple_gains(config, &selected, direction, self.gain_shape());
+            for (speaker_idx, gain) in active {
+                gains[speaker_idx] = gain;
+            }
+            self.renormalize(reference, gains);
+        }
+        self.fold_elevation(config, direction, gains);
+        self.compensate_loudness(gains);
+        self.apply_frozen_gains(gains);
+    }
+
+    /// Apply the [`ElevationPolicy`] of a 2D layout to the gains for a
+    /// source `direction`.
+    fn fold_elevation(&self, config: &SpeakerConfig, direction: DVec3, gains: &mut [f64]) {
+        let z = direction.z;
+        if config.mode() != PanningMode::TwoD || z == 0.0 {
+            return;
+        }
+        let cos = (1.0 - z * z).max(0.0).sqrt();
+        match self.elevation_policy {
+            ElevationPolicy::Ignore => {}
+            ElevationPolicy::Attenuate => {
+                for gain in gains.iter_mut() {
+                    *gain *= cos;
+                }
+            }
+            ElevationPolicy::Spread => {
+                // Speakers the layout pans to (excluded ones are in no tuple)
+                let mut used = [0u64; MAX_SPEAKERS / 64];
+                let tuples = config.tuples();
+                for tuple in 0..tuples.len() {
+                    for i in tuples.speaker_indices(tuple) {
+                        used[i / 64] |= 1 << (i % 64);
+                    }
+                }
+                let n = config.num_speakers();
+                let spread = |i: usize| used[i / 64] & (1 << (i % 64)) != 0 && !self.is_frozen(i);
+                let count = (0..n).filter(|&i| spread(i)).count();
+                if count == 0 {
+                    return;
+                }
+
+                let amount = 1.0 - cos;
+                let even = 1.0 / (count as f64).sqrt();
+                for (i, gain) in gains[..n].iter_mut().enumerate() {
+                    *gain = if spread(i) {
+                        (1.0 - amount) * *gain + amount * even
+                    } else {
+                        0.0
+                    };
+                }
+                self.normalize_blend(config, gains);
+            }
+            ElevationPolicy::PhantomHeight => {
+                // Front and rear images at the source's lateral position,
+                // balanced by how far front or back the source is
+                let lift = z * z;
+                let depth = (direction.y * direction.y + lift).sqrt();
+                let front_share = if depth > 0.0 {
+                    0.5 * (1.0 + direction.y / depth)
+                } else {
+                    0.5
+                };
+                let x = direction.x.clamp(-1.0, 1.0);
+                let y = (1.0 - x * x).sqrt();
+
+                for gain in gains.iter_mut() {
+                    *gain *= cos;
+                }
+                for (image, share) in [
+                    (DVec3::new(x, y, 0.0), front_share),
+                    (DVec3::new(x, -y, 0.0), 1.0 - front_share),
+                ] {
+                    if let Some(selected) = select_tuple(config, image, self.tie_break, None) {
+                        let weight = (lift * share).sqrt();
+                        let (active, _) =
+                            active_tuple_gains(config, &selected, image, self.gain_shape());
+                        for (speaker_idx, gain) in active {
+                            gains[speaker_idx] += weight * gain;
+                        }
+                    }
+                }
+                self.normalize_blend(config, gains);
+            }
+        }
+    }
+
+    /// Apply the [loudness compensation](Self::with_loudness_compensation)
+    /// to the VBAP-driven speakers.
+    fn compensate_loudness(&self, gains: &mut [f64]) {
+        let free = gains
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !self.is_frozen(i))
+            .map(|(_, &g)| g);
+        let scale = self.loudness_scale(free);
+        if scale == 1.0 {
+            return;
+        }
+        for (i, gain) in gains.iter_mut().enumerate() {
+            if !self.is_frozen(i) {
+                *gain *= scale;
+            }
+        }
+    }
+
+    /// Loudness compensation factor for the gains of the free speakers.
+    ///
+    /// `(Σg)² / Σg²` is the effective number of active speakers: 1 on a
+    /// speaker, 2 halfway between two and at most 3 inside a triplet,
+    /// varying smoothly in between. Its square root is how much louder the
+    /// speakers sum when they add coherently, which the compensation
+    /// removes in proportion to its amount.
+    fn loudness_scale(&self, free: impl Iterator<Item = f64>) -> f64 {
+        if self.loudness_compensation == 0.0 {
+            return 1.0;
+        }
+        let (amplitude, energy) = free.fold((0.0, 0.0), |(a, e), g| (a + g.abs(), e + g * g));
+        if energy <= 1e-10 {
+            return 1.0;
+        }
+        let speakers = amplitude * amplitude / energy;
+        speakers.powf(-0.5 * self.loudness_compensation)
+    }
+
+    /// Normalize gains blended from several pannings, leaving frozen
+    /// speakers out.
+    fn normalize_blend(&self, config: &SpeakerConfig, gains: &mut [f64]) {
+        let n = config.num_speakers();
+        for (i, gain) in gains[..n].iter_mut().enumerate() {
+            if self.is_frozen(i) {
+                *gain = 0.0;
+            }
+        }
+        let norm = self
+            .normalization
+            .factor(&gains[..n], config.tolerances().normalization_floor);
+        for gain in &mut gains[..n] {
+            *gain *= norm;
+        }
+    }
+
+    /// Restore the level lost to constraints on the VBAP-driven speakers.
+    ///
+    /// Frozen speakers are outside the panning, so they are neither counted
+    /// nor rescaled.
+    fn renormalize(&self, reference: GainLevel, gains: &mut [f64]) {
+        let free = gains
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !self.is_frozen(i))
+            .map(|(_, &g)| g);
+        let Some(scale) = self.renormalization_scale(reference, free) else {
+            return;
+        };
+
+        for (i, gain) in gains.iter_mut().enumerate() {
+            if !self.is_frozen(i) {
+                *gain *= scale;
+            }
+        }
+    }
+
+    /// Scale restoring `reference` for the gains of the free speakers, or
+    /// `None` if no renormalization applies.
+    fn renormalization_scale(
+        &self,
+        reference: GainLevel,
+        free: impl Iterator<Item = f64>,
+    ) -> Option<f64> {
+        match self.renormalization {
+            Renormalization::Off => None,
+            Renormalization::PreserveEnergy => {
+                let energy: f64 = free.map(|g| g * g).sum();
+                (energy > 1e-10).then(|| (reference.energy / energy).sqrt())
+            }
+            Renormalization::PreserveAmplitude => {
+                let amplitude: f64 = free.map(f64::abs).sum();
+                (amplitude > 1e-10).then(|| reference.amplitude / amplitude)
+            }
+        }
+    }
+
+    /// Convert a source direction in the layout's convention to a vector.
+    #[inline]
+    fn source_direction(&self, azimuth: f64, elevation: f64) -> DVec3 {
+        let (azimuth, elevation) = self.config.convention().to_native(azimuth, elevation);
+        spherical_to_cartesian(azimuth, elevation)
+    }
+
+    #[inline]
+    fn is_virtual(&self, speaker: usize) -> bool {
+        self.full_layout().speakers()[speaker].is_virtual()
+    }
+
+    /// Frozen gain of a speaker that is not frozen by the user.
+    fn unfrozen_gain(&self, speaker: usize) -> Option<f64> {
+        self.is_virtual(speaker).then_some(0.0)
+    }
+
+    #[inline]
+    fn is_frozen(&self, speaker: usize) -> bool {
+        matches!(self.frozen.get(speaker), Some(Some(_)))
+    }
+
+    /// Overwrite the gains of frozen speakers with their fixed values.
+    fn apply_frozen_gains(&self, gains: &mut [f64]) {
+        for (gain, frozen) in gains.iter_mut().zip(&self.frozen) {
+            if let Some(value) = frozen {
+                *gain = *value;
+            }
+        }
+    }
+
+    /// Set how ties between equally good tuples are broken.
+    ///
+    /// See [`TieBreak`]. The default is [`TieBreak::LowestIndex`].
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Get the tie-breaking policy.
+    #[inline]
+    pub fn tie_break(&self) -> TieBreak {
+        self.tie_break
+    }
+
+    /// Set the hysteresis margin used by [`compute_gains_with_state`](Self::compute_gains_with_state).
+    ///
+    /// A source sitting on the edge shared by two tuples can otherwise flip
+    /// between them on every call, producing audible flutter during slow
+    /// automation. With a margin, the previous tuple is kept as long as its
+    /// smallest raw gain is within `margin` of the best candidate's.
+    /// A margin of `0.0` (the default) disables hysteresis.
+    pub fn with_hysteresis(mut self, margin: f64) -> Self {
+        self.hysteresis = margin.max(0.0);
+        self
+    }
+
+    /// Get the hysteresis margin.
+    #[inline]
+    pub fn hysteresis(&self) -> f64 {
+        self.hysteresis
+    }
+
+    /// Set how the gains of the selected tuple are normalized.
+    ///
+    /// Defaults to [`Normalization::Power`]. Use
+    /// [`Normalization::Amplitude`] for coherent low-frequency content.
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Get the tuple gain normalization.
+    #[inline]
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    /// Set whether tuple gains are amplitudes (VBAP) or intensities (VBIP).
+    ///
+    /// Defaults to [`PanningLaw::Amplitude`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::{PanningLaw, VBAPanner};
+    ///
+    /// let vbap = VBAPanner::builder().stereo().build().unwrap();
+    /// let vbip = vbap.clone().with_panning_law(PanningLaw::Intensity);
+    ///
+    /// // Between the speakers VBIP leans less towards the nearer one
+    /// let (a, b) = (vbap.compute_gains(20.0, 0.0), vbip.compute_gains(20.0, 0.0));
+    /// assert!(b[1] > a[1]);
+    /// ```
+    pub fn with_panning_law(mut self, law: PanningLaw) -> Self {
+        self.panning_law = law;
+        self
+    }
+
+    /// Get the panning law.
+    #[inline]
+    pub fn panning_law(&self) -> PanningLaw {
+        self.panning_law
+    }
+
+    /// Set the focus exponent applied to the tuple gains before they are
+    /// normalized.
+    ///
+    /// Above 1 the loudest speaker of the tuple takes a larger share, so
+    /// sources snap towards speakers and localize more sharply; below 1
+    /// the share evens out and sources sound broader. Speakers outside the
+    /// tuple stay silent, and a source on a speaker plays from it alone at
+    /// any focus. `1.0` (the default) is plain VBAP. The exponent is
+    /// clamped to [`FOCUS_RANGE`]; NaN resets it to 1.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder().stereo().build().unwrap();
+    /// let sharp = panner.clone().with_focus(3.0);
+    ///
+    /// // 20° is closer to the left speaker, which sharp panning favors more
+    /// let (a, b) = (panner.compute_gains(20.0, 0.0), sharp.compute_gains(20.0, 0.0));
+    /// assert!(b[0] > a[0] && b[1] < a[1]);
+    /// ```
+    pub fn with_focus(mut self, exponent: f64) -> Self {
+        self.focus = if exponent.is_nan() {
+            1.0
+        } else {
+            exponent.clamp(FOCUS_RANGE.0, FOCUS_RANGE.1)
+        };
+        self
+    }
+
+    /// Get the focus exponent.
+    #[inline]
+    pub fn focus(&self) -> f64 {
+        self.focus
+    }
+
+    /// The tuple gain shaping of this panner.
+    #[inline]
+    fn gain_shape(&self) -> GainShape {
+        GainShape {
+            normalization: self.normalization,
+            law: self.panning_law,
+            focus: self.focus,
+        }
+    }
+
+    /// Set the renormalization policy applied after constraints.
+    ///
+    /// See [`Renormalization`]. For example, with
+    /// [`Renormalization::PreserveEnergy`] the energy VBAP would have sent to
+    /// a frozen speaker is redistributed to the other speakers of the tuple.
+    pub fn with_renormalization(mut self, renormalization: Renormalization) -> Self {
+        self.renormalization = renormalization;
+        self
+    }
+
+    /// Get the renormalization policy.
+    #[inline]
+    pub fn renormalization(&self) -> Renormalization {
+        self.renormalization
+    }
+
+    /// Set how a 2D layout treats elevated sources.
+    ///
+    /// See [`ElevationPolicy`]; the default is
+    /// [`ElevationPolicy::Ignore`]. [`compute_active_gains`](Self::compute_active_gains)
+    /// reports at most three speakers, so it only applies
+    /// [`ElevationPolicy::Attenuate`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::{ElevationPolicy, VBAPanner};
+    ///
+    /// let panner = VBAPanner::builder()
+    ///     .surround_5_1()
+    ///     .build()
+    ///     .unwrap()
+    ///     .with_elevation_policy(ElevationPolicy::Spread);
+    ///
+    /// // Overhead, every speaker plays
+    /// let gains = panner.compute_gains(0.0, 90.0);
+    /// assert!(gains.iter().all(|&g| g > 0.4));
+    /// ```
+    pub fn with_elevation_policy(mut self, policy: ElevationPolicy) -> Self {
+        self.elevation_policy = policy;
+        self
+    }
+
+    /// Compensate the loudness of sources spread over several speakers.
+    ///
+    /// Power normalization keeps the summed energy constant, which suits
+    /// diffuse listening. Near the sweet spot and at low frequencies the
+    /// speaker signals add coherently instead, so a source between two
+    /// speakers sounds up to 3 dB louder than one on a speaker, and a
+    /// moving source dips at every speaker position. This lowers the gains
+    /// by `amount` times that coherent gain, computed from the effective
+    /// number of active speakers so it varies smoothly. `0.0` (the default)
+    /// keeps plain power normalization; `1.0` keeps the amplitude sum
+    /// constant. The amount is clamped to `[0, 1]`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder()
+    ///     .stereo()
+    ///     .build()
+    ///     .unwrap()
+    ///     .with_loudness_compensation(1.0);
+    ///
+    /// let on_speaker: f64 = panner.compute_gains(30.0, 0.0).iter().sum();
+    /// let between: f64 = panner.compute_gains(0.0, 0.0).iter().sum();
+    /// assert!((on_speaker - between).abs() < 1e-9);
+    /// ```
+    pub fn with_loudness_compensation(mut self, amount: f64) -> Self {
+        self.loudness_compensation = if amount.is_nan() {
+            0.0
+        } else {
+            amount.clamp(0.0, 1.0)
+        };
+        self
+    }
+
+    /// Get the loudness compensation amount.
+    #[inline]
+    pub fn loudness_compensation(&self) -> f64 {
+        self.loudness_compensation
+    }
+
+    /// Get the elevation policy for 2D layouts.
+    #[inline]
+    pub fn elevation_policy(&self) -> ElevationPolicy {
+        self.elevation_policy
+    }
+
+    /// Freeze a speaker's output at a fixed gain, bypassing VBAP for it.
+    ///
+    /// The other speakers continue to be panned normally. This is useful for
+    /// dedicated effect channels that must hold a level regardless of where
+    /// sources are positioned. Virtual speakers cannot be frozen at another
+    /// gain than zero.
+    pub fn freeze_speaker(&mut self, index: usize, gain: f64) -> Result<()> {
+        self.config.check_speaker_index(index)?;
+        if self.is_virtual(index) && gain != 0.0 {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "speaker {} is virtual and has no output",
+                index
+            )));
+        }
+        self.frozen[index] = Some(gain);
+        Ok(())
+    }
+
+    /// Release a frozen speaker so it is driven by VBAP again.
+    ///
+    /// Virtual speakers stay frozen at zero.
+    pub fn unfreeze_speaker(&mut self, index: usize) -> Result<()> {
+        self.config.check_speaker_index(index)?;
+        self.frozen[index] = self.unfrozen_gain(index);
+        Ok(())
+    }
+
+    /// Release all frozen speakers except virtual ones.
+    pub fn unfreeze_all(&mut self) {
+        self.frozen = virtual_gains(self.full_layout());
+    }
+
+    /// Get the fixed gain of a frozen speaker, or `None` if it is not frozen.
+    #[inline]
+    pub fn frozen_gain(&self, index: usize) -> Option<f64> {
+        self.frozen.get(index).copied().flatten()
+    }
+
+    /// Get the number of speakers in this configuration.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.config.num_speakers()
+    }
+
+    /// Get the panning mode (2D or 3D).
+    #[inline]
+    pub fn mode(&self) -> PanningMode {
+        self.config.mode()
+    }
+
+    /// Get all speakers in the configuration.
+    #[inline]
+    pub fn speakers(&self) -> &[Speaker] {
+        self.config.speakers()
+    }
+
+    /// Get the underlying speaker configuration.
+    #[inline]
+    pub fn config(&self) -> &SpeakerConfig {
+        &self.config
+    }
+
+    /// Move a speaker while it is being dragged, reusing the current triangulation.
+    ///
+    /// See [`SpeakerConfig::moved_speaker_preview`]; angles are in the
+    /// layout's convention. Call [`move_speaker`](Self::move_speaker) when
+    /// the drag ends.
+    pub fn preview_move_speaker(
+        &mut self,
+        index: usize,
+        azimuth: f64,
+        elevation: f64,
+    ) -> Result<()> {
+        let (azimuth, elevation) = self.config.convention().to_native(azimuth, elevation);
+        let config = self
+            .config
+            .moved_speaker_preview(index, azimuth, elevation)?;
+        if let Some(layout) = &mut self.layout {
+            *layout = layout.moved_speaker_preview(index, azimuth, elevation)?;
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    /// Move a speaker and rebuild the triangulation from scratch.
+    ///
+    /// Angles are in the layout's convention.
+    pub fn move_speaker(&mut self, index: usize, azimuth: f64, elevation: f64) -> Result<()> {
+        let (azimuth, elevation) = self.config.convention().to_native(azimuth, elevation);
+        let layout = self
+            .full_layout()
+            .moved_speaker(index, azimuth, elevation)?;
+        self.activate(layout, self.zone.clone(), self.disabled.clone())
+    }
+
+    /// Take a speaker out of the panning, e.g. after an amplifier failure.
+    ///
+    /// The remaining speakers are re-triangulated, so sources that used the
+    /// speaker are picked up by its neighbors with the usual normalization.
+    /// A disabled speaker is also unfrozen, so its gain is always zero.
+    ///
+    /// Returns an error if the index is out of range or the remaining
+    /// speakers cannot be triangulated; the panner is unchanged then.
+    pub fn disable_speaker(&mut self, index: usize) -> Result<()> {
+        self.config.check_speaker_index(index)?;
+        if self.disabled.contains(index) {
+            return Ok(());
+        }
+
+        let mut disabled = self.disabled.clone();
+        disabled.insert(index);
+        self.activate(self.full_layout().clone(), self.zone.clone(), disabled)?;
+        self.frozen[index] = self.unfrozen_gain(index);
+        Ok(())
+    }
+
+    /// Bring a disabled speaker back into the panning.
+    pub fn enable_speaker(&mut self, index: usize) -> Result<()> {
+        self.config.check_speaker_index(index)?;
+        if !self.disabled.contains(index) {
+            return Ok(());
+        }
+
+        let mut disabled = self.disabled.clone();
+        disabled.remove(index);
+        self.activate(self.full_layout().clone(), self.zone.clone(), disabled)
+    }
+
+    /// Check whether a speaker has been disabled.
+    #[inline]
+    pub fn is_speaker_disabled(&self, index: usize) -> bool {
+        self.disabled.contains(index)
+    }
+
+    /// Get the speakers disabled with [`disable_speaker`](Self::disable_speaker).
+    #[inline]
+    pub fn disabled_speakers(&self) -> &SpeakerMask {
+        &self.disabled
+    }
+
+    /// Derive a panner that only uses the speakers in `zone`.
+    ///
+    /// The zone is re-triangulated on its own, so sources are panned
+    /// between zone speakers with the usual normalization. Gains keep one
+    /// entry per speaker of the full layout; speakers outside the zone are
+    /// always zero. Panner settings, frozen and disabled speakers carry over.
+    ///
+    /// Returns an error if the mask is for a different number of speakers
+    /// or the zone cannot be triangulated.
+    pub fn restricted_to(&self, zone: &SpeakerMask) -> Result<VBAPanner> {
+        if zone.num_speakers() != self.num_speakers() {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "mask covers {} speakers, layout has {}",
+                zone.num_speakers(),
+                self.num_speakers()
+            )));
+        }
+
+        let mut panner = self.clone();
+        panner.activate(
+            self.full_layout().clone(),
+            self.zone.intersection(zone),
+            self.disabled.clone(),
+        )?;
+        Ok(panner)
+    }
+
+    /// Get the full layout, including inactive speakers.
+    pub(crate) fn full_layout(&self) -> &SpeakerConfig {
+        self.layout.as_ref().unwrap_or(&self.config)
+    }
+
+    /// Derive a panner for a different layout of the same speakers.
+    ///
+    /// Panner settings, zone, frozen and disabled speakers carry over.
+    pub(crate) fn with_layout(&self, layout: SpeakerConfig) -> Result<VBAPanner> {
+        debug_assert_eq!(layout.num_speakers(), self.num_speakers());
+        let mut panner = self.clone();
+        panner.activate(layout, self.zone.clone(), self.disabled.clone())?;
+        Ok(panner)
+    }
+
+    /// Triangulate `layout` without the speakers outside `zone` or in
+    /// `disabled`, and pan with the result.
+    fn activate(
+        &mut self,
+        layout: SpeakerConfig,
+        zone: SpeakerMask,
+        disabled: SpeakerMask,
+    ) -> Result<()> {
+        let inactive: Vec<usize> = zone.inverted().union(&disabled).iter().collect();
+        if inactive.is_empty() {
+            self.config = layout;
+            self.layout = None;
+        } else {
+            self.config = layout.without_speakers(&inactive)?;
+            self.layout = Some(layout);
+        }
+        self.zone = zone;
+        self.disabled = disabled;
+        Ok(())
+    }
+}
+
+/// Initial frozen gains: zero for virtual speakers, none for the rest.
+fn virtual_gains(config: &SpeakerConfig) -> Vec<Option<f64>> {
+    config
+        .speakers()
+        .iter()
+        .map(|s| s.is_virtual().then_some(0.0))
+        .collect()
+}
+
+/// Find the best tuple (highest minimum gain) for a direction.
+///
+/// Ties within [`TIE_TOLERANCE`] are broken by `tie_break`; `previous` is the
+/// tuple used last time, if known. Returns `None` if the configuration has
+/// no tuples.
+pub(crate) fn select_tuple(
+    config: &SpeakerConfig,
+    direction: DVec3,
+    tie_break: TieBreak,
+    previous: Option<usize>,
+) -> Option<TupleGains> {
+    let mut best: Option<TupleGains> = None;
+
+    let tuples = config.tuples();
+    for tuple_idx in 0..tuples.len() {
+        // Compute candidate gains by multiplying direction with inverse matrix
+        let (gains, len) = tuples.raw_gains(tuple_idx, direction);
+        let candidate = TupleGains {
+            tuple_index: tuple_idx,
+            gains,
+            len,
+        };
+
+        // We want the tuple where all gains are positive
+        let is_better = match &best {
+            None => true,
+            Some(b) if candidate.min_gain() > b.min_gain() + TIE_TOLERANCE => true,
+            Some(b) if candidate.min_gain() >= b.min_gain() - TIE_TOLERANCE => match tie_break {
+                TieBreak::LowestIndex => false,
+                TieBreak::PreviousTuple => previous == Some(tuple_idx),
+                TieBreak::SmallestSolidAngle => {
+                    tuple_size(config, tuple_idx) < tuple_size(config, b.tuple_index)
+                }
+            },
+            Some(_) => false,
+        };
+        if is_better {
+            best = Some(candidate);
+        }
+    }
+
+    best
+}
+
+/// Solid angle (steradians) of a triplet, or arc length (radians) of a pair.
+fn tuple_size(config: &SpeakerConfig, tuple_index: usize) -> f64 {
+    let speakers = config.speakers();
+    let mut vectors = config
+        .tuples()
+        .speaker_indices(tuple_index)
+        .map(|i| speakers[i].cartesian());
+    let (a, b) = (vectors.next().unwrap(), vectors.next().unwrap());
+    match vectors.next() {
+        Some(c) => solid_angle(a, b, c),
+        None => a.angle_between(b),
+    }
+}
+
+/// Final (pre-renormalization) gains of the selected tuple.
+///
+/// Applies the open-arc clamp when the direction is outside the arc.
+fn active_tuple_gains(
+    config: &SpeakerConfig,
+    selected: &TupleGains,
+    direction: DVec3,
+    shape: GainShape,
+) -> (ActiveGains, GainLevel) {
+    let mut active = ActiveGains::default();
+    let reference = match config.arc_ends() {
+        Some(ends) if selected.min_gain() < -ARC_EDGE_TOLERANCE => {
+            clamp_to_arc_end(config, ends, direction, &mut active)
+        }
+        _ => apply_tuple_gains(config, selected, shape, &mut active),
+    };
+    (active, reference)
+}
+
+/// Shape and normalize the winning tuple's gains and store them in `active`.
+///
+/// Returns the level of the normalized gains before negative ones are clamped.
+fn apply_tuple_gains(
+    config: &SpeakerConfig,
+    selected: &TupleGains,
+    shape: GainShape,
+    active: &mut ActiveGains,
+) -> GainLevel {
+    let speaker_indices = config.tuples().speaker_indices(selected.tuple_index);
+    let mut shaped = [0.0; 3];
+    for (shaped, &gain) in shaped.iter_mut().zip(selected.gains()) {
+        *shaped = shape.apply(gain);
+    }
+    let shaped = &shaped[..selected.len];
+    let norm = shape
+        .normalization
+        .factor(shaped, config.tolerances().normalization_floor);
+
+    let mut reference = GainLevel::default();
+    for (speaker_idx, &gain) in speaker_indices.zip(shaped) {
+        let normalized = gain * norm;
+        reference.energy += normalized * normalized;
+        reference.amplitude += normalized.abs();
+        active.push(speaker_idx, normalized.max(0.0));
+    }
+    reference
+}
+
+/// Send a source outside an open arc entirely to the nearest end speaker.
+fn clamp_to_arc_end(
+    config: &SpeakerConfig,
+    ends: [usize; 2],
+    direction: DVec3,
+    active: &mut ActiveGains,
+) -> GainLevel {
+    let speakers = config.speakers();
+    let horizontal = |v: DVec3| DVec2::new(v.x, v.y).normalize_or_zero();
+    let dir = horizontal(direction);
+
+    let nearest = if dir.dot(horizontal(speakers[ends[0]].cartesian()))
+        >= dir.dot(horizontal(speakers[ends[1]].cartesian()))
+    {
+        ends[0]
+    } else {
+        ends[1]
+    };
+    active.push(nearest, 1.0);
+
+    GainLevel {
+        energy: 1.0,
+        amplitude: 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::convention::Convention;
+    use crate::fixed::FixedPanner;
+    use approx::assert_relative_eq;
+
+    fn assert_active_matches_dense(panner: &VBAPanner) {
+        for i in 0..90 {
+            let (azi, ele) = (i as f64 * 4.0 - 180.0, (i % 6) as f64 * 12.0);
+            let mut sparse = vec![0.0; panner.num_speakers()];
+            for (speaker, gain) in panner.compute_active_gains(azi, ele) {
+                assert!(gain != 0.0);
+                sparse[speaker] += gain;
+            }
+            let dense = panner.compute_gains(azi, ele);
+            for (a, b) in sparse.iter().zip(&dense) {
+                assert_relative_eq!(a, b, epsilon = 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stereo_center() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let gains = panner.compute_gains(0.0, 0.0);
+
+        assert_eq!(gains.len(), 2);
+        // Center pan should have equal gains
+        assert_relative_eq!(gains[0], gains[1], epsilon = 0.01);
+    }
+
+    #[test]
+    fn test_stereo_hard_left() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        // Stereo is at ±30°, so 30° should be hard left
+        let gains = panner.compute_gains(30.0, 0.0);
+
+        assert_eq!(gains.len(), 2);
+        // Left speaker (index 0) should be louder
+        assert!(gains[0] > gains[1]);
+    }
+
+    #[test]
+    fn test_stereo_hard_right() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let gains = panner.compute_gains(-30.0, 0.0);
+
+        assert_eq!(gains.len(), 2);
+        // Right speaker (index 1) should be louder
+        assert!(gains[1] > gains[0]);
+    }
+
+    #[test]
+    fn test_gains_normalized() {
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+
+        for azi in [-180, -90, -45, 0, 45, 90, 180] {
+            let gains = panner.compute_gains(azi as f64, 0.0);
+            let sum_sq: f64 = gains.iter().map(|g| g * g).sum();
+            assert_relative_eq!(sum_sq, 1.0, epsilon = 0.01);
+        }
+    }
+
+    #[test]
+    fn test_gains_non_negative() {
+        let panner = VBAPanner::builder().surround_7_1().build().unwrap();
+
+        for azi in (-180..=180).step_by(15) {
+            let gains = panner.compute_gains(azi as f64, 0.0);
+            for g in &gains {
+                assert!(*g >= 0.0, "gain {} at azi {} is negative", g, azi);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compute_gains_into() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut gains = vec![0.0; 2];
+
+        panner.compute_gains_into(15.0, 0.0, &mut gains);
+
+        let sum_sq: f64 = gains.iter().map(|g| g * g).sum();
+        assert_relative_eq!(sum_sq, 1.0, epsilon = 0.01);
+    }
+
+    #[test]
+    fn test_compute_gains_batch() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let directions: Vec<(f64, f64)> = (0..36).map(|i| (i as f64 * 10.0, 20.0)).collect();
+        let mut out = vec![0.0; directions.len() * 11];
+
+        panner.compute_gains_batch(&directions, &mut out);
+
+        for (&(azi, ele), row) in directions.iter().zip(out.chunks(11)) {
+            assert_eq!(row, panner.compute_gains(azi, ele).as_slice());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "output must hold")]
+    fn test_compute_gains_batch_wrong_size() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        panner.compute_gains_batch(&[(0.0, 0.0)], &mut [0.0; 3]);
+    }
+
+    #[test]
+    fn test_3d_panning() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+
+        assert_eq!(panner.mode(), PanningMode::ThreeD);
+        assert_eq!(panner.num_speakers(), 11);
+
+        // Elevated source should activate height speakers
         let gains = panner.compute_gains(45.0, 45.0);
 
         // At least one non-zero gain
         assert!(gains.iter().any(|&g| g > 0.0));
     }
+
+    #[test]
+    fn test_drag_preview_then_release() {
+        let mut panner = VBAPanner::builder().quad().build().unwrap();
+
+        panner.preview_move_speaker(0, 60.0, 0.0).unwrap();
+        let gains = panner.compute_gains(60.0, 0.0);
+        assert_relative_eq!(gains[0], 1.0, epsilon = 1e-9);
+
+        panner.move_speaker(0, 60.0, 0.0).unwrap();
+        let gains = panner.compute_gains(60.0, 0.0);
+        assert_relative_eq!(gains[0], 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_open_arc_edge_clamp() {
+        let panner = VBAPanner::builder().lcr().open_arc().build().unwrap();
+
+        // Beyond the left end: everything goes to L
+        let gains = panner.compute_gains(90.0, 0.0);
+        assert_relative_eq!(gains[0], 1.0, epsilon = 1e-9);
+        assert_relative_eq!(gains[1] + gains[2], 0.0, epsilon = 1e-9);
+
+        // Behind the listener, closer to R
+        let gains = panner.compute_gains(-170.0, 0.0);
+        assert_relative_eq!(gains[2], 1.0, epsilon = 1e-9);
+
+        // Inside the arc the far speaker stays silent
+        let gains = panner.compute_gains(10.0, 0.0);
+        assert_eq!(gains[2], 0.0);
+        assert!(gains[0] > 0.0 && gains[1] > 0.0);
+    }
+
+    #[test]
+    fn test_hysteresis_keeps_previous_tuple() {
+        let panner = VBAPanner::builder()
+            .quad()
+            .build()
+            .unwrap()
+            .with_hysteresis(0.1);
+        let mut state = PanningState::new();
+        let mut gains = vec![0.0; 4];
+
+        // Settle into the front pair, then move just past FL (45°)
+        panner.compute_gains_with_state(40.0, 0.0, &mut state, &mut gains);
+        let front = state.last_tuple();
+        panner.compute_gains_with_state(46.0, 0.0, &mut state, &mut gains);
+        assert_eq!(state.last_tuple(), front);
+
+        // Well past the margin the panner switches
+        panner.compute_gains_with_state(90.0, 0.0, &mut state, &mut gains);
+        assert_ne!(state.last_tuple(), front);
+    }
+
+    #[test]
+    fn test_no_hysteresis_matches_stateless() {
+        let panner = VBAPanner::builder().surround_7_1().build().unwrap();
+        let mut state = PanningState::new();
+        let mut gains = vec![0.0; 7];
+
+        for azi in (-180..=180).step_by(10) {
+            panner.compute_gains_with_state(azi as f64, 0.0, &mut state, &mut gains);
+
+            let expected = panner.compute_gains(azi as f64, 0.0);
+            for (g, e) in gains.iter().zip(&expected) {
+                assert_relative_eq!(*g, *e, epsilon = 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_state_fast_path_stays_valid() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let mut state = PanningState::new();
+        let mut gains = vec![0.0; 11];
+
+        // Slow spiral across several facets
+        for step in 0..720 {
+            let azi = -180.0 + step as f64 * 0.5;
+            let ele = 20.0 + 20.0 * (step as f64 * 0.01).sin();
+            panner.compute_gains_with_state(azi, ele, &mut state, &mut gains);
+
+            // Overlapping facets may keep a different (but valid) tuple than
+            // the full scan would pick
+            let tuple = panner
+                .config()
+                .tuples()
+                .get(state.last_tuple().unwrap())
+                .unwrap();
+            for (i, g) in gains.iter().enumerate() {
+                assert!(*g >= 0.0);
+                assert!(*g == 0.0 || tuple.speaker_indices().contains(&i));
+            }
+            // ...and never one that loses more energy to clamping
+            let sum_sq: f64 = gains.iter().map(|g| g * g).sum();
+            let expected: f64 = panner.compute_gains(azi, ele).iter().map(|g| g * g).sum();
+            assert!(sum_sq >= expected - 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_exclusions_renormalize() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        // Never send this source to the ceiling
+        let mut exclusions = SpeakerExclusions::new(&[7, 8, 9, 10]);
+        let mut gains = vec![0.0; 11];
+
+        panner
+            .compute_gains_excluding(45.0, 60.0, &mut exclusions, &mut gains)
+            .unwrap();
+
+        assert!(gains[7..].iter().all(|&g| g == 0.0));
+        let sum_sq: f64 = gains.iter().map(|g| g * g).sum();
+        assert_relative_eq!(sum_sq, 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_exclusions_too_many() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut exclusions = SpeakerExclusions::new(&[0]);
+        let mut gains = vec![0.0; 2];
+
+        assert!(panner
+            .compute_gains_excluding(0.0, 0.0, &mut exclusions, &mut gains)
+            .is_err());
+    }
+
+    #[test]
+    fn test_renormalization_around_frozen_speaker() {
+        let mut panner = VBAPanner::builder()
+            .surround_5_1()
+            .build()
+            .unwrap()
+            .with_renormalization(Renormalization::PreserveEnergy);
+        panner.freeze_speaker(0, 0.0).unwrap();
+
+        // Between L (30°) and C (0°): L is frozen, so C carries all the energy
+        let gains = panner.compute_gains(15.0, 0.0);
+        assert_eq!(gains[0], 0.0);
+        assert_relative_eq!(gains[2], 1.0, epsilon = 1e-9);
+
+        let panner = panner.with_renormalization(Renormalization::PreserveAmplitude);
+        let gains = panner.compute_gains(15.0, 0.0);
+        assert_relative_eq!(gains[2], 2.0_f64.sqrt(), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_renormalization_off_keeps_clamped_gains() {
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        assert_eq!(panner.renormalization(), Renormalization::Off);
+
+        let mut frozen = panner.clone();
+        frozen.freeze_speaker(0, 0.0).unwrap();
+        let gains = frozen.compute_gains(15.0, 0.0);
+        assert_relative_eq!(gains[2], panner.compute_gains(15.0, 0.0)[2]);
+    }
+
+    #[test]
+    fn test_normalization_modes() {
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        assert_eq!(panner.normalization(), Normalization::Power);
+
+        let amplitude = panner.clone().with_normalization(Normalization::Amplitude);
+        let max_one = panner.with_normalization(Normalization::MaxGainOne);
+        for azi in [-150.0, -60.0, 10.0, 45.0, 100.0] {
+            let gains = amplitude.compute_gains(azi, 0.0);
+            assert_relative_eq!(gains.iter().sum::<f64>(), 1.0, epsilon = 1e-9);
+
+            let gains = max_one.compute_gains(azi, 0.0);
+            assert_relative_eq!(
+                gains.iter().cloned().fold(0.0, f64::max),
+                1.0,
+                epsilon = 1e-9
+            );
+        }
+
+        // Hard on a speaker, all modes agree
+        let gains = amplitude.compute_gains(0.0, 0.0);
+        assert_relative_eq!(gains[2], 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_intensity_panning() {
+        let vbap = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        assert_eq!(vbap.panning_law(), PanningLaw::Amplitude);
+        let amplitude = vbap.clone().with_normalization(Normalization::Amplitude);
+        let vbip = vbap.with_panning_law(PanningLaw::Intensity);
+        for (azi, ele) in [(10.0, 0.0), (-70.0, 20.0), (135.0, 40.0), (30.0, 0.0)] {
+            let gains = vbip.compute_gains(azi, ele);
+            assert_relative_eq!(
+                gains.iter().map(|g| g * g).sum::<f64>(),
+                1.0,
+                epsilon = 1e-9
+            );
+            for (g, a) in gains.iter().zip(amplitude.compute_gains(azi, ele)) {
+                assert_relative_eq!(g * g, a, epsilon = 1e-9);
+            }
+        }
+        assert_active_matches_dense(&vbip);
+    }
+
+    #[test]
+    fn test_gain_jacobian() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        for (azi, ele) in [(10.0, 0.0), (-70.0, 20.0), (135.0, 40.0)] {
+            let gains = panner.compute_gains(azi, ele);
+            let jacobian = panner.gain_jacobian(azi, ele);
+            let h = 1e-3;
+            let (left, right) = (
+                panner.compute_gains(azi + h, ele),
+                panner.compute_gains(azi - h, ele),
+            );
+            let (up, down) = (
+                panner.compute_gains(azi, ele + h),
+                panner.compute_gains(azi, ele - h),
+            );
+            for i in 0..gains.len() {
+                assert_relative_eq!(
+                    jacobian[i].0,
+                    (left[i] - right[i]) / (2.0 * h),
+                    epsilon = 1e-6
+                );
+                assert_relative_eq!(jacobian[i].1, (up[i] - down[i]) / (2.0 * h), epsilon = 1e-6);
+            }
+            // Power normalization keeps the sum of squares constant
+            let power_slope: f64 = gains.iter().zip(&jacobian).map(|(g, d)| g * d.0).sum();
+            assert_relative_eq!(power_slope, 0.0, epsilon = 1e-9);
+        }
+
+        // Stereo tangent law: the slope of g_L - g_R at the center
+        let stereo = VBAPanner::builder().stereo().build().unwrap();
+        let jacobian = stereo.gain_jacobian(0.0, 0.0);
+        let expected = std::f64::consts::FRAC_1_SQRT_2 / 30f64.to_radians().tan()
+            * std::f64::consts::PI
+            / 180.0;
+        assert_relative_eq!(jacobian[0].0, expected, epsilon = 1e-9);
+        assert_relative_eq!(jacobian[1].0, -expected, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_estimate_direction() {
+        let directions = [(10.0, 0.0), (-70.0, 20.0), (135.0, 40.0), (0.0, 90.0)];
+        let atmos = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        for panner in [
+            atmos.clone(),
+            atmos.clone().with_panning_law(PanningLaw::Intensity),
+            atmos.clone().with_focus(3.0),
+            atmos.with_normalization(Normalization::MaxGainOne),
+        ] {
+            for (azi, ele) in directions {
+                let gains = panner.compute_gains(azi, ele);
+                let (est_azi, est_ele) = panner.estimate_direction(&gains);
+                assert_relative_eq!(est_ele, ele, epsilon = 1e-9);
+                if ele < 90.0 {
+                    assert_relative_eq!(est_azi, azi, epsilon = 1e-9);
+                }
+            }
+        }
+
+        // 2D layouts recover the azimuth, in the layout's convention
+        let panner = VBAPanner::builder()
+            .convention(Convention::Max)
+            .surround_5_1()
+            .build()
+            .unwrap();
+        let gains = panner.compute_gains(-60.0, 0.0);
+        let (azi, ele) = panner.estimate_direction(&gains);
+        assert_relative_eq!(azi, -60.0, epsilon = 1e-9);
+        assert_eq!(ele, 0.0);
+        assert_eq!(panner.estimate_direction(&[0.0; 5]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_focus() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        assert_eq!(panner.focus(), 1.0);
+        assert_eq!(panner.clone().with_focus(100.0).focus(), FOCUS_RANGE.1);
+        assert_eq!(panner.clone().with_focus(f64::NAN).focus(), 1.0);
+
+        // Focus 0.5 is VBIP
+        let soft = panner.clone().with_focus(0.5);
+        let vbip = panner.clone().with_panning_law(PanningLaw::Intensity);
+        let sharp = panner.clone().with_focus(4.0);
+        for (azi, ele) in [(10.0, 0.0), (-70.0, 20.0), (135.0, 40.0)] {
+            let (plain, sharp) = (
+                panner.compute_gains(azi, ele),
+                sharp.compute_gains(azi, ele),
+            );
+            for (a, b) in soft
+                .compute_gains(azi, ele)
+                .iter()
+                .zip(vbip.compute_gains(azi, ele))
+            {
+                assert_relative_eq!(*a, b, epsilon = 1e-12);
+            }
+            let loudest = |g: &[f64]| g.iter().cloned().fold(0.0, f64::max);
+            assert!(loudest(&sharp) > loudest(&plain));
+            assert_relative_eq!(
+                sharp.iter().map(|g| g * g).sum::<f64>(),
+                1.0,
+                epsilon = 1e-9
+            );
+        }
+        // On a speaker, focus changes nothing
+        assert_relative_eq!(sharp.compute_gains(30.0, 0.0)[0], 1.0, epsilon = 1e-9);
+        assert_active_matches_dense(&sharp);
+    }
+
+    #[test]
+    fn test_active_gains() {
+        let atmos = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        assert!(atmos.compute_active_gains(20.0, 10.0).count() <= 3);
+        assert_active_matches_dense(&atmos);
+
+        let arc = VBAPanner::builder().lcr().open_arc().build().unwrap();
+        assert_active_matches_dense(&arc);
+
+        let mut constrained = VBAPanner::builder()
+            .surround_7_1()
+            .build()
+            .unwrap()
+            .with_renormalization(Renormalization::PreserveEnergy);
+        constrained.freeze_speaker(2, 0.25).unwrap();
+        constrained.freeze_speaker(5, 0.0).unwrap();
+        assert_active_matches_dense(&constrained);
+    }
+
+    #[test]
+    fn test_center_divergence() {
+        use std::f64::consts::FRAC_1_SQRT_2;
+
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let mut divergence = CenterDivergence::new(2);
+        let mut gains = vec![0.0; panner.num_speakers()];
+
+        // No divergence: plain VBAP
+        panner
+            .compute_gains_diverged(10.0, 0.0, &mut divergence, &mut gains)
+            .unwrap();
+        for (a, b) in gains.iter().zip(panner.compute_gains(10.0, 0.0)) {
+            assert_relative_eq!(*a, b, epsilon = 1e-12);
+        }
+
+        // Full divergence: phantom center between L and R
+        divergence.set_divergence(1.0).unwrap();
+        panner
+            .compute_gains_diverged(0.0, 0.0, &mut divergence, &mut gains)
+            .unwrap();
+        assert_relative_eq!(gains[0], FRAC_1_SQRT_2, epsilon = 1e-9);
+        assert_relative_eq!(gains[1], FRAC_1_SQRT_2, epsilon = 1e-9);
+        assert_eq!(gains[2], 0.0);
+
+        // Halfway: all three front speakers, power preserved
+        divergence.set_divergence(0.5).unwrap();
+        panner
+            .compute_gains_diverged(0.0, 0.0, &mut divergence, &mut gains)
+            .unwrap();
+        assert!(gains[0] > 0.0 && gains[2] > gains[0]);
+        let power: f64 = gains.iter().map(|g| g * g).sum();
+        assert_relative_eq!(power, 1.0, epsilon = 1e-9);
+
+        // Rear sources do not involve the center
+        panner
+            .compute_gains_diverged(150.0, 0.0, &mut divergence, &mut gains)
+            .unwrap();
+        for (a, b) in gains.iter().zip(panner.compute_gains(150.0, 0.0)) {
+            assert_relative_eq!(*a, b, epsilon = 1e-12);
+        }
+
+        let mut bad = CenterDivergence::new(9);
+        assert!(panner
+            .compute_gains_diverged(0.0, 0.0, &mut bad, &mut gains)
+            .is_err());
+    }
+
+    #[test]
+    fn test_extent() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let mut gains = vec![0.0; panner.num_speakers()];
+
+        panner.compute_gains_with_extent(20.0, 0.0, Extent::default(), &mut gains);
+        assert_eq!(gains, panner.compute_gains(20.0, 0.0));
+
+        // Wider sources reach more speakers at the same power
+        let active = |gains: &[f64]| gains.iter().filter(|&&g| g > 1e-9).count();
+        let mut previous = 0;
+        for width in [10.0, 90.0, 180.0, 360.0] {
+            let extent = Extent::new(width, 0.0).unwrap();
+            panner.compute_gains_with_extent(0.0, 0.0, extent, &mut gains);
+            let power: f64 = gains.iter().map(|g| g * g).sum();
+            assert_relative_eq!(power, 1.0, epsilon = 1e-9);
+            assert!(active(&gains) >= previous);
+            previous = active(&gains);
+        }
+        assert_eq!(previous, 7);
+
+        // Height brings in the top speakers, symmetrically
+        let extent = Extent::new(0.0, 60.0).unwrap();
+        panner.compute_gains_with_extent(0.0, 0.0, extent, &mut gains);
+        assert!(gains[7] > 0.0);
+        assert_relative_eq!(gains[7], gains[8], epsilon = 1e-9);
+        assert_relative_eq!(gains[0], gains[1], epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_restricted_to_zone() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let bed = SpeakerMask::matching(panner.speakers(), Speaker::is_horizontal);
+        let zone = panner.restricted_to(&bed).unwrap();
+
+        assert_eq!(zone.num_speakers(), panner.num_speakers());
+        let gains = zone.compute_gains(30.0, 60.0);
+        for (i, &gain) in gains.iter().enumerate() {
+            assert!(bed.contains(i) || gain == 0.0);
+        }
+        let power: f64 = gains.iter().map(|g| g * g).sum();
+        assert_relative_eq!(power, 1.0, epsilon = 1e-9);
+
+        assert!(panner.restricted_to(&SpeakerMask::all(3)).is_err());
+        assert!(panner
+            .restricted_to(&SpeakerMask::from_indices(12, &[0]).unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_disable_speaker() {
+        let mut panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let healthy = panner.compute_gains(0.0, 0.0);
+
+        panner.freeze_speaker(2, 0.5).unwrap();
+        panner.disable_speaker(2).unwrap();
+        assert!(panner.is_speaker_disabled(2));
+        let gains = panner.compute_gains(0.0, 0.0);
+        assert_eq!(gains[2], 0.0);
+        assert_relative_eq!(gains[0], gains[1], epsilon = 1e-9);
+        let power: f64 = gains.iter().map(|g| g * g).sum();
+        assert_relative_eq!(power, 1.0, epsilon = 1e-9);
+
+        // Moving a speaker keeps the disabled one out
+        panner.move_speaker(3, 100.0, 0.0).unwrap();
+        assert_eq!(panner.compute_gains(0.0, 0.0)[2], 0.0);
+        panner.move_speaker(3, 110.0, 0.0).unwrap();
+
+        panner.enable_speaker(2).unwrap();
+        assert_eq!(panner.compute_gains(0.0, 0.0), healthy);
+        assert!(panner.disable_speaker(9).is_err());
+    }
+
+    #[test]
+    fn test_disable_speaker_in_zone() {
+        let panner = VBAPanner::builder().surround_7_1().build().unwrap();
+        let no_sides = SpeakerMask::from_indices(7, &[0, 1, 2, 5, 6]).unwrap();
+        let mut zone = panner.restricted_to(&no_sides).unwrap();
+
+        zone.disable_speaker(5).unwrap();
+        zone.enable_speaker(5).unwrap();
+        let gains = zone.compute_gains(180.0, 0.0);
+        assert!(gains[3] == 0.0 && gains[4] == 0.0);
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let config = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .build_config()
+            .unwrap();
+        let speakers = config.speakers();
+        let tuples = config.tuples();
+
+        // Find two tuples of different size sharing an edge
+        let mut shared = None;
+        'search: for i in 0..tuples.len() {
+            for j in (i + 1)..tuples.len() {
+                let a: Vec<usize> = tuples.speaker_indices(i).collect();
+                let common: Vec<usize> = tuples
+                    .speaker_indices(j)
+                    .filter(|k| a.contains(k))
+                    .collect();
+                if common.len() == 2 && tuple_size(&config, i) != tuple_size(&config, j) {
+                    shared = Some((i, j, common));
+                    break 'search;
+                }
+            }
+        }
+        let (i, j, edge) = shared.unwrap();
+        let midpoint = speakers[edge[0]].cartesian() + speakers[edge[1]].cartesian();
+        let (azimuth, elevation) = crate::math::cartesian_to_spherical(midpoint);
+
+        let chosen = |tie_break: TieBreak, previous: Option<usize>| {
+            let panner = VBAPanner::new(config.clone()).with_tie_break(tie_break);
+            let mut state = PanningState {
+                last_tuple: previous,
+            };
+            let mut gains = vec![0.0; panner.num_speakers()];
+            panner.compute_gains_with_state(azimuth, elevation, &mut state, &mut gains);
+            state.last_tuple().unwrap()
+        };
+
+        let smaller = if tuple_size(&config, i) < tuple_size(&config, j) {
+            i
+        } else {
+            j
+        };
+        assert_eq!(chosen(TieBreak::LowestIndex, None), i);
+        assert_eq!(chosen(TieBreak::SmallestSolidAngle, None), smaller);
+        assert_eq!(chosen(TieBreak::PreviousTuple, Some(j)), j);
+        assert_eq!(chosen(TieBreak::PreviousTuple, None), i);
+    }
+
+    #[test]
+    fn test_strict_reports_uncovered_directions() {
+        let dome = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let mut gains = vec![0.0; dome.num_speakers()];
+
+        for azimuth in [-170.0, -45.0, 0.0, 30.0, 120.0] {
+            assert!(dome.compute_gains_strict(azimuth, 30.0, &mut gains).is_ok());
+            assert!(dome.min_tuple_gain(azimuth, 30.0).unwrap() >= -1e-9);
+        }
+
+        let err = dome
+            .compute_gains_strict(0.0, -60.0, &mut gains)
+            .unwrap_err();
+        match err {
+            VBAPError::UncoveredDirection { min_gain, .. } => assert!(min_gain < -0.1),
+            other => panic!("unexpected error {:?}", other),
+        }
+        // The clamped gains are still written
+        assert_eq!(gains, dome.compute_gains(0.0, -60.0));
+
+        // Open-arc clamping is intended
+        let arc = VBAPanner::builder().lcr().open_arc().build().unwrap();
+        let mut gains = vec![0.0; arc.num_speakers()];
+        assert!(arc.compute_gains_strict(180.0, 0.0, &mut gains).is_ok());
+        assert!(arc.min_tuple_gain(180.0, 0.0).is_none());
+    }
+
+    #[test]
+    fn test_convention() {
+        let native = VBAPanner::builder().atmos_5_1_4().build().unwrap();
+        let max_positions: Vec<(f64, f64)> = native
+            .speakers()
+            .iter()
+            .map(|s| Convention::Max.from_native(s.azimuth(), s.elevation()))
+            .collect();
+        let mut max = VBAPanner::builder()
+            .convention(Convention::Max)
+            .add_speakers(&max_positions)
+            .build()
+            .unwrap();
+        // Presets are always in the crate's convention
+        let preset = VBAPanner::builder()
+            .convention(Convention::Max)
+            .atmos_5_1_4()
+            .build()
+            .unwrap();
+        let fixed = FixedPanner::<16, 32>::from_config(max.config()).unwrap();
+
+        for (azi, ele) in [(20.0, 0.0), (-75.0, 30.0), (160.0, 10.0)] {
+            let expected = native.compute_gains(azi, ele);
+            let (max_azi, max_ele) = Convention::Max.from_native(azi, ele);
+            assert_eq!(max.compute_gains(max_azi, max_ele), expected);
+            assert_eq!(preset.compute_gains(max_azi, max_ele), expected);
+            assert_eq!(&fixed.compute_gains(max_azi, max_ele)[..9], &expected[..]);
+        }
+
+        max.move_speaker(0, -35.0, 0.0).unwrap();
+        assert_eq!(max.speakers()[0].azimuth(), 35.0);
+        max.disable_speaker(2).unwrap();
+        assert_eq!(max.config().convention(), Convention::Max);
+        assert!(max.compute_gains(-35.0, 0.0)[0] > 0.99);
+    }
+
+    #[test]
+    fn test_virtual_speaker_is_silent() {
+        let config = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .add_virtual_speaker(0.0, -90.0)
+            .build_config()
+            .unwrap();
+        let mut panner = VBAPanner::new(config);
+        assert_eq!(panner.frozen_gain(11), Some(0.0));
+
+        panner.unfreeze_all();
+        panner.unfreeze_speaker(11).unwrap();
+        assert!(panner.freeze_speaker(11, 0.5).is_err());
+        let gains = panner.compute_gains(0.0, -80.0);
+        assert_eq!(gains[11], 0.0);
+        // Most of the signal went to the virtual speaker
+        assert!(gains.iter().map(|g| g * g).sum::<f64>() < 0.5);
+
+        let panner = panner.with_renormalization(Renormalization::PreserveEnergy);
+        let gains = panner.compute_gains(0.0, -80.0);
+        assert_eq!(gains[11], 0.0);
+        assert_relative_eq!(
+            gains.iter().map(|g| g * g).sum::<f64>(),
+            1.0,
+            epsilon = 1e-9
+        );
+        assert!(panner
+            .compute_active_gains(0.0, -80.0)
+            .all(|(speaker, _)| speaker != 11));
+    }
+
+    #[test]
+    fn test_frozen_speaker() {
+        let mut panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        panner.freeze_speaker(2, 0.5).unwrap();
+
+        // Center is frozen even when the source is hard left
+        let gains = panner.compute_gains(30.0, 0.0);
+        assert_relative_eq!(gains[0], 1.0, epsilon = 1e-9);
+        assert_relative_eq!(gains[2], 0.5, epsilon = 1e-9);
+        assert_eq!(panner.frozen_gain(2), Some(0.5));
+
+        panner.unfreeze_speaker(2).unwrap();
+        let gains = panner.compute_gains(30.0, 0.0);
+        assert_relative_eq!(gains[2], 0.0, epsilon = 1e-9);
+        assert!(panner.freeze_speaker(9, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_elevation_policy() {
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        assert_eq!(panner.elevation_policy(), ElevationPolicy::Ignore);
+        assert_eq!(
+            panner.compute_gains(0.0, 60.0)[2],
+            panner.compute_gains(0.0, 0.0)[2]
+        );
+        assert!(panner.compute_gains(0.0, 90.0).iter().all(|&g| g == 0.0));
+
+        let attenuated = panner
+            .clone()
+            .with_elevation_policy(ElevationPolicy::Attenuate);
+        assert_relative_eq!(attenuated.compute_gains(0.0, 60.0)[2], 0.5, epsilon = 1e-9);
+        assert_relative_eq!(attenuated.compute_gains(0.0, -60.0)[2], 0.5, epsilon = 1e-9);
+        let active: Vec<_> = attenuated.compute_active_gains(0.0, 60.0).collect();
+        assert_eq!(active.len(), 1);
+        assert_relative_eq!(active[0].1, 0.5, epsilon = 1e-9);
+
+        let mut spread = panner.with_elevation_policy(ElevationPolicy::Spread);
+        assert_eq!(spread.compute_gains(0.0, 0.0)[2], 1.0);
+        let gains = spread.compute_gains(0.0, 60.0);
+        assert_relative_eq!(
+            gains.iter().map(|g| g * g).sum::<f64>(),
+            1.0,
+            epsilon = 1e-9
+        );
+        assert!(gains.iter().all(|&g| g > 0.0));
+        // The active path leaves the spreading to the dense methods
+        let active: Vec<_> = spread.compute_active_gains(0.0, 60.0).collect();
+        assert_eq!(active.len(), 1);
+        assert!(gains.iter().all(|&g| g <= gains[2]));
+        for gain in spread.compute_gains(30.0, 90.0) {
+            assert_relative_eq!(gain, 1.0 / 5f64.sqrt(), epsilon = 1e-9);
+        }
+
+        // Frozen speakers keep their gain and are left out of the spread
+        spread.freeze_speaker(3, 0.0).unwrap();
+        let gains = spread.compute_gains(0.0, 90.0);
+        assert_eq!(gains[3], 0.0);
+        assert_relative_eq!(gains[0], 0.5, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_elevation_policy_ignores_3d() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let spread = panner
+            .clone()
+            .with_elevation_policy(ElevationPolicy::Spread);
+        assert_eq!(
+            spread.compute_gains(20.0, 60.0),
+            panner.compute_gains(20.0, 60.0)
+        );
+    }
+
+    #[test]
+    fn test_phantom_height() {
+        let panner = VBAPanner::builder()
+            .quad()
+            .build()
+            .unwrap()
+            .with_elevation_policy(ElevationPolicy::PhantomHeight);
+        let (front, rear) = ([0, 1], [2, 3]);
+        assert_eq!(panner.compute_gains(45.0, 0.0)[0], 1.0);
+
+        // Overhead plays equally from front and rear
+        for gain in panner.compute_gains(0.0, 90.0) {
+            assert_relative_eq!(gain, 0.5, epsilon = 1e-9);
+        }
+
+        // Rising at the front brings in some rear
+        let gains = panner.compute_gains(0.0, 45.0);
+        assert_relative_eq!(
+            gains.iter().map(|g| g * g).sum::<f64>(),
+            1.0,
+            epsilon = 1e-9
+        );
+        assert!(rear.iter().all(|&i| gains[i] > 0.0));
+        assert!(gains[front[0]] > gains[rear[0]]);
+
+        // Rising at the side stays on that side
+        let gains = panner.compute_gains(90.0, 60.0);
+        assert!(gains[0] > gains[1] && gains[2] > gains[3]);
+        assert_relative_eq!(gains[0], gains[2], epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_loudness_compensation() {
+        use std::f64::consts::FRAC_1_SQRT_2;
+
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        assert_eq!(panner.loudness_compensation(), 0.0);
+        assert_eq!(
+            panner
+                .clone()
+                .with_loudness_compensation(f64::NAN)
+                .loudness_compensation(),
+            0.0
+        );
+
+        // Half compensation: -1.5 dB between two speakers, none on one
+        let half = panner.clone().with_loudness_compensation(0.5);
+        assert_relative_eq!(half.compute_gains(30.0, 0.0)[0], 1.0, epsilon = 1e-9);
+        let center = half.compute_gains(0.0, 0.0);
+        assert_relative_eq!(center[0], FRAC_1_SQRT_2 * 2f64.powf(-0.25), epsilon = 1e-9);
+        let active: Vec<_> = half.compute_active_gains(0.0, 0.0).collect();
+        assert_relative_eq!(active[0].1, center[0], epsilon = 1e-12);
+
+        // Full compensation keeps the amplitude sum while the source moves
+        let full = panner.with_loudness_compensation(2.0);
+        assert_eq!(full.loudness_compensation(), 1.0);
+        for azimuth in [-30.0, -12.0, 0.0, 7.0, 30.0] {
+            let sum: f64 = full.compute_gains(azimuth, 0.0).iter().sum();
+            assert_relative_eq!(sum, 1.0, epsilon = 1e-9);
+        }
+    }
 }
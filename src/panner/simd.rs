@@ -0,0 +1,209 @@
+//! Wide-lane gain computation for per-sample automation.
+
+use glam::DVec3;
+use wide::{f64x4, CmpGt};
+
+use super::{TieBreak, TupleGains, VBAPanner, TIE_TOLERANCE};
+use crate::config::InverseMatrix;
+
+/// Number of directions computed per SIMD pass.
+const LANES: usize = 4;
+
+impl VBAPanner {
+    /// Compute speaker gains for four directions at once using SIMD lanes.
+    ///
+    /// Direction conversion and the search over all speaker tuples run on
+    /// four lanes in parallel; the result is identical to calling
+    /// [`compute_gains_into`](Self::compute_gains_into) for each direction.
+    /// `out` is filled as a row-major `4 × num_speakers()` matrix.
+    ///
+    /// Requires the `simd` feature.
+    ///
+    /// # Panics
+    /// Panics if `out.len() != 4 * self.num_speakers()`.
+    pub fn compute_gains_x4(&self, azimuths: [f64; 4], elevations: [f64; 4], out: &mut [f64]) {
+        let n = self.config.num_speakers();
+        assert_eq!(
+            out.len(),
+            LANES * n,
+            "output must hold {} directions x {} speakers",
+            LANES,
+            n
+        );
+
+        // Only the default tie-break fits a branch-free lane search
+        if self.tie_break != TieBreak::LowestIndex {
+            for (lane, row) in out.chunks_exact_mut(n).enumerate() {
+                self.compute_gains_into(azimuths[lane], elevations[lane], row);
+            }
+            return;
+        }
+
+        let (azi_sin, azi_cos) = f64x4::from(azimuths).to_radians().sin_cos();
+        let (ele_sin, ele_cos) = f64x4::from(elevations).to_radians().sin_cos();
+        let x = ele_cos * azi_sin;
+        let y = ele_cos * azi_cos;
+        let z = ele_sin;
+
+        let mut best_min = f64x4::splat(f64::NEG_INFINITY);
+        let mut best_index = f64x4::splat(-1.0);
+        let mut best = [f64x4::ZERO; 3];
+
+        for (tuple_idx, tuple) in self.config.tuples().iter().enumerate() {
+            let g = match tuple.inverse_matrix() {
+                InverseMatrix::ThreeD(m) => [
+                    m.x_axis.x * x + m.y_axis.x * y + m.z_axis.x * z,
+                    m.x_axis.y * x + m.y_axis.y * y + m.z_axis.y * z,
+                    m.x_axis.z * x + m.y_axis.z * y + m.z_axis.z * z,
+                ],
+                InverseMatrix::TwoD(m) => [
+                    m.x_axis.x * x + m.y_axis.x * y,
+                    m.x_axis.y * x + m.y_axis.y * y,
+                    f64x4::ZERO,
+                ],
+            };
+            let min = match tuple.inverse_matrix() {
+                InverseMatrix::ThreeD(_) => g[0].min(g[1]).min(g[2]),
+                InverseMatrix::TwoD(_) => g[0].min(g[1]),
+            };
+
+            // Same rule as the scalar search: only a candidate better by more
+            // than the tie tolerance wins, so the first of tied ones is kept.
+            let better = min.cmp_gt(best_min + TIE_TOLERANCE);
+            best_min = better.blend(min, best_min);
+            best_index = better.blend(f64x4::splat(tuple_idx as f64), best_index);
+            for (b, g) in best.iter_mut().zip(g) {
+                *b = better.blend(g, *b);
+            }
+        }
+
+        let best_index = best_index.to_array();
+        let best = best.map(f64x4::to_array);
+        let (x, y, z) = (x.to_array(), y.to_array(), z.to_array());
+
+        for (lane, row) in out.chunks_exact_mut(n).enumerate() {
+            row.fill(0.0);
+            let selected = (best_index[lane] >= 0.0).then(|| {
+                let tuple_index = best_index[lane] as usize;
+                TupleGains {
+                    tuple_index,
+                    gains: [best[0][lane], best[1][lane], best[2][lane]],
+                    len: self.config.tuples().speaker_indices(tuple_index).len(),
+                }
+            });
+            let direction = DVec3::new(x[lane], y[lane], z[lane]);
+            self.write_gains(&self.config, selected, direction, row);
+        }
+    }
+
+    /// Compute speaker gains for a run of automation points using SIMD lanes.
+    ///
+    /// Processes `azimuths`/`elevations` four at a time with
+    /// [`compute_gains_x4`](Self::compute_gains_x4); a trailing remainder is
+    /// computed one direction at a time. `out` is filled as a row-major
+    /// `azimuths.len() × num_speakers()` matrix.
+    ///
+    /// Requires the `simd` feature.
+    ///
+    /// # Panics
+    /// Panics if the angle slices differ in length or if
+    /// `out.len() != azimuths.len() * self.num_speakers()`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::VBAPanner;
+    ///
+    /// let panner = VBAPanner::builder().surround_7_1().build().unwrap();
+    /// // One automation point per sample
+    /// let azimuths: Vec<f64> = (0..64).map(|i| i as f64 * 5.0).collect();
+    /// let elevations = vec![0.0; azimuths.len()];
+    /// let mut out = vec![0.0; azimuths.len() * panner.num_speakers()];
+    ///
+    /// panner.compute_gains_wide(&azimuths, &elevations, &mut out);
+    /// ```
+    pub fn compute_gains_wide(&self, azimuths: &[f64], elevations: &[f64], out: &mut [f64]) {
+        let n = self.config.num_speakers();
+        assert_eq!(
+            azimuths.len(),
+            elevations.len(),
+            "azimuths and elevations must have the same length"
+        );
+        assert_eq!(
+            out.len(),
+            azimuths.len() * n,
+            "output must hold {} directions x {} speakers",
+            azimuths.len(),
+            n
+        );
+
+        let mut rows = out.chunks_exact_mut(LANES * n);
+        let mut azi = azimuths.chunks_exact(LANES);
+        let mut ele = elevations.chunks_exact(LANES);
+        for ((rows, azi), ele) in (&mut rows).zip(&mut azi).zip(&mut ele) {
+            self.compute_gains_x4(
+                [azi[0], azi[1], azi[2], azi[3]],
+                [ele[0], ele[1], ele[2], ele[3]],
+                rows,
+            );
+        }
+
+        let tail = azi.remainder().iter().zip(ele.remainder());
+        for ((&azimuth, &elevation), row) in tail.zip(rows.into_remainder().chunks_exact_mut(n)) {
+            self.compute_gains_into(azimuth, elevation, row);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::VBAPanner;
+    use approx::assert_relative_eq;
+
+    fn assert_matches_scalar(panner: &VBAPanner, directions: &[(f64, f64)]) {
+        let n = panner.num_speakers();
+        let azimuths: Vec<f64> = directions.iter().map(|d| d.0).collect();
+        let elevations: Vec<f64> = directions.iter().map(|d| d.1).collect();
+        let mut out = vec![0.0; directions.len() * n];
+
+        panner.compute_gains_wide(&azimuths, &elevations, &mut out);
+
+        for (&(azi, ele), row) in directions.iter().zip(out.chunks_exact(n)) {
+            let expected = panner.compute_gains(azi, ele);
+            for (a, b) in row.iter().zip(&expected) {
+                assert_relative_eq!(a, b, epsilon = 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wide_matches_scalar_2d() {
+        let panner = VBAPanner::builder().surround_7_1().build().unwrap();
+        let directions: Vec<(f64, f64)> = (0..37).map(|i| (i as f64 * 10.0 - 180.0, 0.0)).collect();
+        assert_matches_scalar(&panner, &directions);
+    }
+
+    #[test]
+    fn test_wide_matches_scalar_3d() {
+        let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+        let directions: Vec<(f64, f64)> = (0..50)
+            .map(|i| (i as f64 * 13.0 - 180.0, (i % 7) as f64 * 10.0))
+            .collect();
+        assert_matches_scalar(&panner, &directions);
+    }
+
+    #[test]
+    fn test_wide_open_arc() {
+        let panner = VBAPanner::builder().lcr().open_arc().build().unwrap();
+        assert_matches_scalar(
+            &panner,
+            &[
+                (0.0, 0.0),
+                (60.0, 0.0),
+                (180.0, 0.0),
+                (-45.0, 0.0),
+                (10.0, 0.0),
+            ],
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Real-time playback through a `cpal` output device.
+//!
+//! [`Playback`] opens an output stream, runs a [`Mixer`] in the audio
+//! callback and routes each speaker feed to a device channel through a
+//! [`ChannelMap`]. Before every block the application's fill callback
+//! writes the source signals and may move the sources on the mixer.
+//!
+//! Add the mixer's sources before starting: the callback provides one
+//! input buffer per source that existed when the stream started.
+//!
+//! On Linux, `cpal` needs the ALSA development files (`libasound2-dev` on
+//! Debian and Ubuntu).
+//!
+//! Requires the `cpal` feature.
+
+use std::sync::{Arc, Mutex};
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use crate::error::{Result, VBAPError};
+use crate::mixer::Mixer;
+
+/// Assignment of speakers to device channels.
+///
+/// # Example
+///
+/// ```
+/// use vbap::playback::ChannelMap;
+///
+/// // A 5-speaker layout on an 8-channel interface, skipping outputs 3 and 4
+/// let map = ChannelMap::new(vec![0, 1, 2, 5, 6], 8).unwrap();
+/// assert_eq!(map.device_channel(3), 5);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChannelMap {
+    /// Device channel of each speaker.
+    channels: Vec<usize>,
+    num_device_channels: usize,
+}
+
+impl ChannelMap {
+    /// Map speaker `i` to device channel `channels[i]`.
+    ///
+    /// Returns an error if a channel is not below `num_device_channels` or
+    /// two speakers share a channel.
+    pub fn new(channels: Vec<usize>, num_device_channels: usize) -> Result<Self> {
+        for (speaker, &channel) in channels.iter().enumerate() {
+            if channel >= num_device_channels {
+                return Err(VBAPError::InvalidConfiguration(format!(
+                    "speaker {} is mapped to channel {}, but the device has {} channels",
+                    speaker, channel, num_device_channels
+                )));
+            }
+            if channels[..speaker].contains(&channel) {
+                return Err(VBAPError::InvalidConfiguration(format!(
+                    "speaker {} is mapped to channel {}, which is already in use",
+                    speaker, channel
+                )));
+            }
+        }
+        Ok(Self {
+            channels,
+            num_device_channels,
+        })
+    }
+
+    /// Map speaker `i` to device channel `i`.
+    ///
+    /// Returns an error if the device has fewer channels than there are
+    /// speakers.
+    pub fn identity(num_speakers: usize, num_device_channels: usize) -> Result<Self> {
+        Self::new((0..num_speakers).collect(), num_device_channels)
+    }
+
+    /// Get the device channel of a speaker.
+    ///
+    /// # Panics
+    /// Panics if `speaker` is out of range.
+    #[inline]
+    pub fn device_channel(&self, speaker: usize) -> usize {
+        self.channels[speaker]
+    }
+
+    /// Get the number of mapped speakers.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Get the number of device channels.
+    #[inline]
+    pub fn num_device_channels(&self) -> usize {
+        self.num_device_channels
+    }
+
+    /// Write speaker feeds into an interleaved device buffer.
+    ///
+    /// Channels without a speaker are silenced. Frames beyond the shortest
+    /// speaker feed are left unchanged.
+    ///
+    /// # Panics
+    /// Panics if there are fewer feeds than mapped speakers.
+    pub fn interleave<S: AsRef<[f32]>>(&self, speakers: &[S], data: &mut [f32]) {
+        assert!(
+            speakers.len() >= self.channels.len(),
+            "expected {} speaker feeds, got {}",
+            self.channels.len(),
+            speakers.len()
+        );
+        let frames = speakers
+            .iter()
+            .map(|feed| feed.as_ref().len())
+            .min()
+            .unwrap_or(0);
+        for (frame, out) in data
+            .chunks_exact_mut(self.num_device_channels)
+            .take(frames)
+            .enumerate()
+        {
+            out.fill(0.0);
+            for (feed, &channel) in speakers.iter().zip(&self.channels) {
+                out[channel] = feed.as_ref()[frame];
+            }
+        }
+    }
+}
+
+/// The state the audio callback owns.
+struct Engine<F> {
+    mixer: Mixer,
+    map: ChannelMap,
+    fill: F,
+    inputs: Vec<Vec<f32>>,
+    outputs: Vec<Vec<f32>>,
+}
+
+impl<F: FnMut(&mut Mixer, &mut [&mut [f32]])> Engine<F> {
+    fn new(mixer: Mixer, map: ChannelMap, fill: F) -> Self {
+        let inputs = vec![Vec::new(); mixer.sources().len()];
+        let outputs = vec![Vec::new(); mixer.num_outputs()];
+        Self {
+            mixer,
+            map,
+            fill,
+            inputs,
+            outputs,
+        }
+    }
+
+    /// Render one interleaved device buffer.
+    fn render(&mut self, data: &mut [f32]) {
+        let frames = data.len() / self.map.num_device_channels();
+        // Buffers only reallocate when the device asks for a larger block than
+        // before
+        for buffer in self.inputs.iter_mut().chain(&mut self.outputs) {
+            buffer.resize(frames, 0.0);
+        }
+        let mut inputs: Vec<&mut [f32]> = self.inputs.iter_mut().map(|b| &mut b[..]).collect();
+        for input in inputs.iter_mut() {
+            input.fill(0.0);
+        }
+        (self.fill)(&mut self.mixer, &mut inputs);
+
+        let inputs: Vec<&[f32]> = self.inputs.iter().map(|b| &b[..frames]).collect();
+        let mut outputs: Vec<&mut [f32]> = self.outputs.iter_mut().map(|b| &mut b[..]).collect();
+        self.mixer.process(&inputs, &mut outputs);
+        self.map.interleave(&self.outputs, data);
+    }
+}
+
+/// A running output stream.
+///
+/// The stream plays until the `Playback` is dropped.
+///
+/// # Example
+///
+/// ```no_run
+/// use vbap::mixer::Mixer;
+/// use vbap::playback::Playback;
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+/// let mut mixer = Mixer::new(panner);
+/// let source = mixer.add_source();
+///
+/// // A 220 Hz tone circling the listener every four seconds
+/// let (mut phase, mut azimuth) = (0.0f32, 0.0);
+/// let playback = Playback::start_default(mixer, move |mixer, inputs| {
+///     let block = inputs[source].len() as f64;
+///     azimuth = (azimuth + 90.0 * block / 48000.0) % 360.0;
+///     mixer.set_position(source, azimuth, 0.0);
+///     for sample in inputs[source].iter_mut() {
+///         *sample = 0.2 * phase.sin();
+///         phase = (phase + std::f32::consts::TAU * 220.0 / 48000.0) % std::f32::consts::TAU;
+///     }
+/// })
+/// .unwrap();
+/// std::thread::sleep(std::time::Duration::from_secs(8));
+/// ```
+pub struct Playback {
+    stream: cpal::Stream,
+    config: cpal::StreamConfig,
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl Playback {
+    /// Start playing the mixer on `device`, routed through `map`.
+    ///
+    /// The stream runs at the device's default sample rate with
+    /// `map.num_device_channels()` channels of 32-bit float samples. `fill`
+    /// is called on the audio thread before every block with the mixer and
+    /// one zeroed input buffer per source, as long as the block.
+    ///
+    /// Returns [`VBAPError::InvalidConfiguration`] if `map` does not cover
+    /// the mixer's speakers, and [`VBAPError::Audio`] if the device cannot
+    /// open the stream.
+    pub fn start<F>(device: &cpal::Device, mixer: Mixer, map: ChannelMap, fill: F) -> Result<Self>
+    where
+        F: FnMut(&mut Mixer, &mut [&mut [f32]]) + Send + 'static,
+    {
+        if map.num_speakers() != mixer.num_outputs() {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "channel map has {} speakers, the layout has {}",
+                map.num_speakers(),
+                mixer.num_outputs()
+            )));
+        }
+        let supported = device.default_output_config().map_err(audio_error)?;
+        let config = cpal::StreamConfig {
+            channels: map.num_device_channels() as u16,
+            sample_rate: supported.sample_rate(),
+            buffer_size: cpal::BufferSize::Default,
+        };
+
+        let error = Arc::new(Mutex::new(None));
+        let stream_error = Arc::clone(&error);
+        let mut engine = Engine::new(mixer, map, fill);
+        let stream = device
+            .build_output_stream(
+                &config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| engine.render(data),
+                move |e| {
+                    if let Ok(mut error) = stream_error.lock() {
+                        *error = Some(e.to_string());
+                    }
+                },
+                None,
+            )
+            .map_err(audio_error)?;
+        stream.play().map_err(audio_error)?;
+        Ok(Self {
+            stream,
+            config,
+            error,
+        })
+    }
+
+    /// Start playing the mixer on the default output device, speaker `i`
+    /// on channel `i`.
+    ///
+    /// See [`start`](Self::start).
+    pub fn start_default<F>(mixer: Mixer, fill: F) -> Result<Self>
+    where
+        F: FnMut(&mut Mixer, &mut [&mut [f32]]) + Send + 'static,
+    {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| VBAPError::Audio("no default output device".into()))?;
+        let channels = device
+            .default_output_config()
+            .map_err(audio_error)?
+            .channels() as usize;
+        let map = ChannelMap::identity(mixer.num_outputs(), channels)?;
+        Self::start(&device, mixer, map, fill)
+    }
+
+    /// Get the sample rate of the stream in Hz.
+    #[inline]
+    pub fn sample_rate(&self) -> u32 {
+        self.config.sample_rate.0
+    }
+
+    /// Get the number of device channels of the stream.
+    #[inline]
+    pub fn num_device_channels(&self) -> usize {
+        self.config.channels as usize
+    }
+
+    /// Pause the stream.
+    pub fn pause(&self) -> Result<()> {
+        self.stream.pause().map_err(audio_error)
+    }
+
+    /// Resume a paused stream.
+    pub fn play(&self) -> Result<()> {
+        self.stream.play().map_err(audio_error)
+    }
+
+    /// Take the last error the stream reported, such as a disconnected
+    /// device or an underrun.
+    pub fn take_error(&self) -> Option<VBAPError> {
+        self.error.lock().ok()?.take().map(VBAPError::Audio)
+    }
+}
+
+fn audio_error(error: impl std::fmt::Display) -> VBAPError {
+    VBAPError::Audio(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::panner::VBAPanner;
+
+    #[test]
+    fn test_channel_map() {
+        assert!(ChannelMap::identity(5, 8).is_ok());
+        assert!(ChannelMap::identity(5, 4).is_err());
+        assert!(ChannelMap::new(vec![0, 1, 1], 4).is_err());
+
+        let map = ChannelMap::new(vec![3, 0], 4).unwrap();
+        let mut data = [9.0; 8];
+        map.interleave(&[[1.0, 2.0], [3.0, 4.0]], &mut data);
+        assert_eq!(data, [3.0, 0.0, 0.0, 1.0, 4.0, 0.0, 0.0, 2.0]);
+    }
+
+    #[test]
+    fn test_engine_routes_mixer() {
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let mut mixer = Mixer::new(panner);
+        let source = mixer.add_source();
+        mixer.set_position(source, -30.0, 0.0);
+
+        // Right speaker on device channel 0 of 3
+        let map = ChannelMap::new(vec![2, 0], 3).unwrap();
+        let mut engine = Engine::new(mixer, map, |_: &mut Mixer, inputs: &mut [&mut [f32]]| {
+            inputs[0].fill(1.0);
+        });
+        let mut data = [0.0; 12];
+        engine.render(&mut data);
+        // The first block ramps in; the second is at full gain
+        engine.render(&mut data);
+        assert_eq!(data, [1.0, 0.0, 0.0].repeat(4)[..]);
+    }
+}
@@ -0,0 +1,65 @@
+//! Commonly used types, for glob import.
+//!
+//! ```
+//! use vbap::prelude::*;
+//!
+//! let panner = VBAPanner::builder()
+//!     .convention(Convention::Max)
+//!     .surround_5_1()
+//!     .build()
+//!     .unwrap();
+//! let gains = panner.compute_gains(-30.0, 0.0);
+//! ```
+//!
+//! Types of optional modules are included when their feature is enabled.
+
+pub use crate::config::{
+    Dimension, DuplicateSpeakers, PanningMode, SpeakerConfig, SpeakerConfigBuilder, Tolerances,
+};
+pub use crate::convention::Convention;
+pub use crate::crossfade::ConfigCrossfade;
+pub use crate::divergence::CenterDivergence;
+pub use crate::error::{Result, VBAPError};
+pub use crate::exclusion::SpeakerExclusions;
+pub use crate::extent::Extent;
+pub use crate::fixed::FixedPanner;
+pub use crate::gains::Gains;
+pub use crate::groups::{Distribution, GroupedPanner, SpeakerGroup};
+pub use crate::listener::ListenerCompensation;
+pub use crate::mask::SpeakerMask;
+pub use crate::panner::{
+    ElevationPolicy, Normalization, PanningLaw, PanningState, Renormalization, TieBreak, VBAPanner,
+};
+pub use crate::rings::RingPanner;
+pub use crate::rng::{Rng, SplitMix64};
+pub use crate::room::RoomLayout;
+pub use crate::speaker::{ChannelLabel, Speaker};
+
+#[cfg(feature = "render")]
+pub use crate::bass::BassManager;
+#[cfg(feature = "dual-band")]
+pub use crate::dual_band::DualBandPanner;
+#[cfg(feature = "bevy")]
+pub use crate::ecs::{SpatialEmitter, SpatialListener, SpatialPositions, VbapPlugin};
+#[cfg(feature = "hrtf")]
+pub use crate::hrtf::{CoordinateType, Hrir, HrirSet};
+#[cfg(any(feature = "mint", feature = "nalgebra", feature = "ndarray"))]
+pub use crate::interop::Direction;
+#[cfg(feature = "render")]
+pub use crate::mixer::{AirAbsorption, DistanceModel, Level, Mixer, Occlusion, Source};
+#[cfg(feature = "binaural")]
+pub use crate::monitor::BinauralDownmix;
+#[cfg(feature = "render")]
+pub use crate::monitor::StereoDownmix;
+#[cfg(feature = "osc")]
+pub use crate::osc::{OscControl, OscServer};
+#[cfg(feature = "cpal")]
+pub use crate::playback::{ChannelMap, Playback};
+#[cfg(feature = "shared")]
+pub use crate::shared::SharedPanner;
+#[cfg(feature = "trajectory")]
+pub use crate::trajectory::{Interpolation, Keyframe, Motion, Orbit, SplinePath, Trajectory};
+#[cfg(feature = "viz")]
+pub use crate::viz::{LayoutPlot, View};
+#[cfg(feature = "wav")]
+pub use crate::wav::WavRenderer;
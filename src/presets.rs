@@ -6,6 +6,11 @@
 //! - Negative azimuth = right
 //! - Elevation 0° = horizontal plane
 //! - Positive elevation = above
+//!
+//! The `*_LABELS` constants name the speakers of the presets the builder
+//! offers, in the same order.
+
+use crate::speaker::ChannelLabel::{self, *};
 
 /// Stereo configuration: Left and Right at ±30°.
 pub const STEREO: &[(f64, f64)] = &[
@@ -26,6 +31,12 @@ pub const LCR: &[(f64, f64)] = &[
     (-30.0, 0.0), // R
 ];
 
+/// Channel labels of [`STEREO`] and [`STEREO_WIDE`].
+pub const STEREO_LABELS: &[ChannelLabel] = &[L, R];
+
+/// Channel labels of [`LCR`].
+pub const LCR_LABELS: &[ChannelLabel] = &[L, C, R];
+
 /// Quadraphonic (4.0) configuration.
 pub const QUAD: &[(f64, f64)] = &[
     (45.0, 0.0),   // FL
@@ -34,6 +45,9 @@ pub const QUAD: &[(f64, f64)] = &[
     (-135.0, 0.0), // RR
 ];
 
+/// Channel labels of [`QUAD`].
+pub const QUAD_LABELS: &[ChannelLabel] = &[L, R, Ls, Rs];
+
 /// 5.0 surround configuration (no LFE - LFE is not spatialized).
 ///
 /// Based on ITU-R BS.775-1 recommendation.
@@ -51,6 +65,9 @@ pub const SURROUND_5_0: &[(f64, f64)] = &[
 /// This is the same as SURROUND_5_0.
 pub const SURROUND_5_1: &[(f64, f64)] = SURROUND_5_0;
 
+/// Channel labels of [`SURROUND_5_0`] and [`SURROUND_5_1`].
+pub const SURROUND_5_0_LABELS: &[ChannelLabel] = &[L, R, C, Ls, Rs];
+
 /// 7.0 surround configuration.
 ///
 /// Adds side surrounds to the 5.0 layout.
@@ -67,6 +84,9 @@ pub const SURROUND_7_0: &[(f64, f64)] = &[
 /// 7.1 surround configuration (7.0 layout, LFE handled separately).
 pub const SURROUND_7_1: &[(f64, f64)] = SURROUND_7_0;
 
+/// Channel labels of [`SURROUND_7_0`] and [`SURROUND_7_1`].
+pub const SURROUND_7_0_LABELS: &[ChannelLabel] = &[L, R, C, Lss, Rss, Lrs, Rrs];
+
 /// Dolby Atmos 7.1.4 configuration.
 ///
 /// 7.1 base layer plus 4 overhead speakers.
@@ -86,6 +106,9 @@ pub const ATMOS_7_1_4: &[(f64, f64)] = &[
     (-135.0, 45.0), // Rtr (Right Top Rear)
 ];
 
+/// Channel labels of [`ATMOS_7_1_4`].
+pub const ATMOS_7_1_4_LABELS: &[ChannelLabel] = &[L, R, C, Lss, Rss, Lrs, Rrs, Ltf, Rtf, Ltr, Rtr];
+
 /// Dolby Atmos 5.1.4 configuration.
 ///
 /// 5.1 base layer plus 4 overhead speakers.
@@ -103,6 +126,9 @@ pub const ATMOS_5_1_4: &[(f64, f64)] = &[
     (-135.0, 45.0), // Rtr
 ];
 
+/// Channel labels of [`ATMOS_5_1_4`].
+pub const ATMOS_5_1_4_LABELS: &[ChannelLabel] = &[L, R, C, Ls, Rs, Ltf, Rtf, Ltr, Rtr];
+
 /// Dolby Atmos 9.1.6 configuration.
 ///
 /// Extended 7.1 base with front wide speakers, plus 6 overhead.
@@ -126,6 +152,11 @@ pub const ATMOS_9_1_6: &[(f64, f64)] = &[
     (-150.0, 45.0), // Rtr
 ];
 
+/// Channel labels of [`ATMOS_9_1_6`].
+pub const ATMOS_9_1_6_LABELS: &[ChannelLabel] = &[
+    L, R, C, Lw, Rw, Lss, Rss, Lrs, Rrs, Ltf, Rtf, Ltm, Rtm, Ltr, Rtr,
+];
+
 /// Auro-3D 9.1 configuration.
 ///
 /// 5.1 base plus 4 height speakers at 30° elevation.
@@ -179,6 +210,23 @@ mod tests {
         assert_eq!(ATMOS_7_1_4.len(), 11);
     }
 
+    #[test]
+    fn test_label_lengths() {
+        for (positions, labels) in [
+            (STEREO, STEREO_LABELS),
+            (STEREO_WIDE, STEREO_LABELS),
+            (LCR, LCR_LABELS),
+            (QUAD, QUAD_LABELS),
+            (SURROUND_5_0, SURROUND_5_0_LABELS),
+            (SURROUND_7_0, SURROUND_7_0_LABELS),
+            (ATMOS_7_1_4, ATMOS_7_1_4_LABELS),
+            (ATMOS_5_1_4, ATMOS_5_1_4_LABELS),
+            (ATMOS_9_1_6, ATMOS_9_1_6_LABELS),
+        ] {
+            assert_eq!(positions.len(), labels.len());
+        }
+    }
+
     #[test]
     fn test_atmos_has_elevation() {
         // Atmos configs should have speakers with non-zero elevation
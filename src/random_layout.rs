@@ -0,0 +1,315 @@
+//! Random-but-valid speaker layouts for testing.
+//!
+//! Real rigs are irregular: speakers sit wherever the room allows. The
+//! generator here scatters speakers uniformly over a ring, hemisphere, or
+//! sphere with a minimum angular separation, and only returns layouts that
+//! triangulate, so tests can run against many diverse rigs deterministically.
+//! Ring and sphere layouts additionally cover every direction of their region.
+
+use glam::DVec3;
+
+use crate::config::{fibonacci_sphere, Dimension, SpeakerConfig, SpeakerConfigBuilder};
+use crate::error::{Result, VBAPError};
+use crate::math::spherical_to_cartesian;
+use crate::rng::{Rng, SplitMix64};
+
+/// Dart throws per speaker before a layout attempt is abandoned.
+const MAX_THROWS_PER_SPEAKER: usize = 1000;
+
+/// Layout attempts before giving up on finding one that triangulates.
+const MAX_LAYOUT_ATTEMPTS: usize = 32;
+
+/// Region the speakers of a [`RandomLayout`] are scattered over.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LayoutShape {
+    /// Horizontal ring (2D).
+    Ring,
+    /// Upper hemisphere, elevation 0° to 90°.
+    ///
+    /// Directions below the lowest speakers are not covered.
+    Hemisphere,
+    /// Full sphere.
+    #[default]
+    Sphere,
+}
+
+/// Generator for random speaker layouts with a minimum separation.
+///
+/// The same seed always produces the same layout. The `*_with_rng`
+/// methods take any [`Rng`] instead.
+///
+/// # Example
+///
+/// ```
+/// use vbap::random_layout::{LayoutShape, RandomLayout};
+/// use vbap::VBAPanner;
+///
+/// for seed in 0..8 {
+///     let config = RandomLayout::new(12, LayoutShape::Hemisphere)
+///         .with_seed(seed)
+///         .build_config()
+///         .unwrap();
+///     let panner = VBAPanner::new(config);
+///     let gains = panner.compute_gains(0.0, 45.0);
+///     assert!(gains.iter().any(|&g| g > 0.0));
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RandomLayout {
+    num_speakers: usize,
+    shape: LayoutShape,
+    min_separation: f64,
+    seed: u64,
+}
+
+impl RandomLayout {
+    /// Create a generator for `num_speakers` speakers.
+    ///
+    /// The minimum separation defaults to 5° and the seed to 0.
+    pub fn new(num_speakers: usize, shape: LayoutShape) -> Self {
+        Self {
+            num_speakers,
+            shape,
+            min_separation: 5.0,
+            seed: 0,
+        }
+    }
+
+    /// Set the minimum angle between any two speakers in degrees.
+    pub fn with_min_separation(mut self, degrees: f64) -> Self {
+        self.min_separation = degrees;
+        self
+    }
+
+    /// Set the random seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Get the number of speakers.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.num_speakers
+    }
+
+    /// Get the layout shape.
+    #[inline]
+    pub fn shape(&self) -> LayoutShape {
+        self.shape
+    }
+
+    /// Get the minimum speaker separation in degrees.
+    #[inline]
+    pub fn min_separation(&self) -> f64 {
+        self.min_separation
+    }
+
+    /// Get the random seed.
+    #[inline]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Generate speaker positions as `(azimuth, elevation)` pairs.
+    ///
+    /// Returns an error if the speakers cannot be placed with the requested
+    /// separation, or no attempt yields a layout that triangulates.
+    pub fn positions(&self) -> Result<Vec<(f64, f64)>> {
+        self.positions_with_rng(&mut SplitMix64::new(self.seed))
+    }
+
+    /// Generate speaker positions, drawing random numbers from `rng`
+    /// instead of the seed.
+    pub fn positions_with_rng(&self, rng: &mut impl Rng) -> Result<Vec<(f64, f64)>> {
+        self.generate(rng).map(|(positions, _)| positions)
+    }
+
+    /// Generate a layout and return it as a builder.
+    ///
+    /// Ring layouts are forced to 2D so they stay horizontal.
+    pub fn builder(&self) -> Result<SpeakerConfigBuilder> {
+        Ok(self.with_positions(&self.positions()?))
+    }
+
+    /// Generate and triangulate a layout.
+    pub fn build_config(&self) -> Result<SpeakerConfig> {
+        self.build_config_with_rng(&mut SplitMix64::new(self.seed))
+    }
+
+    /// Generate and triangulate a layout, drawing random numbers from `rng`
+    /// instead of the seed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::random_layout::{LayoutShape, RandomLayout};
+    /// use vbap::rng::SplitMix64;
+    ///
+    /// // One stream for a whole test run: every layout differs
+    /// let mut rng = SplitMix64::new(42);
+    /// let generator = RandomLayout::new(8, LayoutShape::Ring);
+    /// let a = generator.build_config_with_rng(&mut rng).unwrap();
+    /// let b = generator.build_config_with_rng(&mut rng).unwrap();
+    /// assert_ne!(a.speakers()[0].azimuth(), b.speakers()[0].azimuth());
+    /// ```
+    pub fn build_config_with_rng(&self, rng: &mut impl Rng) -> Result<SpeakerConfig> {
+        self.generate(rng).map(|(_, config)| config)
+    }
+
+    fn with_positions(&self, positions: &[(f64, f64)]) -> SpeakerConfigBuilder {
+        let builder = SpeakerConfigBuilder::new().add_speakers(positions);
+        match self.shape {
+            LayoutShape::Ring => builder.dimension(Dimension::Force2D),
+            LayoutShape::Hemisphere | LayoutShape::Sphere => builder.dimension(Dimension::Force3D),
+        }
+    }
+
+    fn generate(&self, rng: &mut impl Rng) -> Result<(Vec<(f64, f64)>, SpeakerConfig)> {
+        if !self.min_separation.is_finite() || self.min_separation < 0.0 {
+            return Err(VBAPError::InvalidParameter {
+                parameter: "min_separation",
+                value: self.min_separation,
+                min: 0.0,
+                max: 180.0,
+            });
+        }
+
+        let mut last_error = None;
+        for _ in 0..MAX_LAYOUT_ATTEMPTS {
+            let positions = self.scatter(rng)?;
+            match self.with_positions(&positions).build_config() {
+                Ok(config) if self.covers_region(&config) => return Ok((positions, config)),
+                Ok(_) => {}
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            VBAPError::InvalidConfiguration("no random layout covers the region".into())
+        }))
+    }
+
+    /// Check that every direction of the shape's region lies inside a tuple.
+    fn covers_region(&self, config: &SpeakerConfig) -> bool {
+        const GRID: usize = 256;
+        let grid: Vec<DVec3> = match self.shape {
+            LayoutShape::Ring => (0..GRID)
+                .map(|i| spherical_to_cartesian(i as f64 * 360.0 / GRID as f64, 0.0))
+                .collect(),
+            LayoutShape::Hemisphere => return true,
+            // Fibonacci sphere for even coverage without pole clustering.
+            LayoutShape::Sphere => fibonacci_sphere(GRID),
+        };
+
+        grid.into_iter().all(|d| config.covers_direction(d))
+    }
+
+    fn scatter(&self, rng: &mut impl Rng) -> Result<Vec<(f64, f64)>> {
+        let min_cos = self.min_separation.to_radians().cos();
+        let mut positions = Vec::with_capacity(self.num_speakers);
+        let mut directions = Vec::with_capacity(self.num_speakers);
+
+        let max_throws = MAX_THROWS_PER_SPEAKER * self.num_speakers.max(1);
+        for _ in 0..max_throws {
+            if positions.len() == self.num_speakers {
+                break;
+            }
+            let azimuth = rng.range(-180.0, 180.0);
+            // Uniform in z gives uniform area on the sphere.
+            let elevation = match self.shape {
+                LayoutShape::Ring => 0.0,
+                LayoutShape::Hemisphere => rng.next_f64().asin().to_degrees(),
+                LayoutShape::Sphere => rng.range(-1.0, 1.0).asin().to_degrees(),
+            };
+            let direction = spherical_to_cartesian(azimuth, elevation);
+            if directions.iter().all(|d| direction.dot(*d) < min_cos) {
+                positions.push((azimuth, elevation));
+                directions.push(direction);
+            }
+        }
+
+        if positions.len() < self.num_speakers {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "could only place {} of {} speakers {}° apart",
+                positions.len(),
+                self.num_speakers,
+                self.min_separation
+            )));
+        }
+        Ok(positions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VBAPanner;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_deterministic_and_separated() {
+        let layout = RandomLayout::new(20, LayoutShape::Sphere)
+            .with_min_separation(20.0)
+            .with_seed(3);
+        let positions = layout.positions().unwrap();
+        assert_eq!(positions, layout.positions().unwrap());
+        assert_eq!(positions.len(), 20);
+
+        for (i, &(az_a, el_a)) in positions.iter().enumerate() {
+            for &(az_b, el_b) in &positions[i + 1..] {
+                let cos =
+                    spherical_to_cartesian(az_a, el_a).dot(spherical_to_cartesian(az_b, el_b));
+                assert!(cos.acos().to_degrees() >= 20.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shapes() {
+        let ring = RandomLayout::new(6, LayoutShape::Ring).positions().unwrap();
+        assert!(ring.iter().all(|&(_, el)| el == 0.0));
+
+        let dome = RandomLayout::new(10, LayoutShape::Hemisphere)
+            .positions()
+            .unwrap();
+        assert!(dome.iter().all(|&(_, el)| (0.0..=90.0).contains(&el)));
+    }
+
+    #[test]
+    fn test_impossible_separation() {
+        let layout = RandomLayout::new(50, LayoutShape::Ring).with_min_separation(30.0);
+        assert!(layout.positions().is_err());
+        assert!(RandomLayout::new(4, LayoutShape::Ring)
+            .with_min_separation(f64::NAN)
+            .positions()
+            .is_err());
+    }
+
+    #[test]
+    fn test_fuzz_gains_power_normalized() {
+        for (seed, shape) in [LayoutShape::Ring, LayoutShape::Sphere]
+            .into_iter()
+            .cycle()
+            .take(16)
+            .enumerate()
+        {
+            let config = RandomLayout::new(8 + seed, shape)
+                .with_seed(seed as u64)
+                .build_config()
+                .unwrap();
+            let panner = VBAPanner::new(config);
+
+            for i in 0..36 {
+                let elevation = if shape == LayoutShape::Ring {
+                    0.0
+                } else {
+                    (i % 7) as f64 * 25.0 - 75.0
+                };
+                let gains = panner.compute_gains(i as f64 * 10.0 - 180.0, elevation);
+                assert!(gains.iter().all(|&g| g >= 0.0));
+                let power: f64 = gains.iter().map(|g| g * g).sum();
+                assert_relative_eq!(power, 1.0, epsilon = 1e-9);
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Static remapping between speaker layouts.
+//!
+//! Content mixed for one layout can be played on another by treating each
+//! speaker of the source layout as a virtual source and panning it into
+//! the target layout. The result is a fixed matrix that upmixes or downmixes
+//! speaker feeds, e.g. from 7.1.4 to 5.1.
+
+use crate::config::SpeakerConfig;
+use crate::panner::VBAPanner;
+
+/// Compute the remapping matrix from `source` to `target` speakers.
+///
+/// `matrix[i][j]` is the gain from source speaker `i` to target speaker
+/// `j`: each row holds the power-normalized VBAP gains of the target layout
+/// for the direction of source speaker `i`. Rows of virtual source
+/// speakers are zero. A speaker present in both layouts maps one to one.
+///
+/// # Example
+///
+/// ```
+/// use vbap::remap::remap_matrix;
+/// use vbap::SpeakerConfigBuilder;
+///
+/// let atmos = SpeakerConfigBuilder::new().atmos_7_1_4().build_config().unwrap();
+/// let surround = SpeakerConfigBuilder::new().surround_5_1().build_config().unwrap();
+///
+/// let matrix = remap_matrix(&atmos, &surround);
+/// assert_eq!(matrix.len(), 11);
+/// assert_eq!(matrix[0].len(), 5);
+/// // The left front speaker exists in both layouts
+/// assert!((matrix[0][0] - 1.0).abs() < 1e-9);
+/// ```
+pub fn remap_matrix(source: &SpeakerConfig, target: &SpeakerConfig) -> Vec<Vec<f64>> {
+    let panner = VBAPanner::new(target.clone());
+    let convention = target.convention();
+    source
+        .speakers()
+        .iter()
+        .map(|speaker| {
+            if speaker.is_virtual() {
+                return vec![0.0; panner.num_speakers()];
+            }
+            // Speaker angles are native; the panner expects the target's convention
+            let (azimuth, elevation) =
+                convention.from_native(speaker.azimuth(), speaker.elevation());
+            panner.compute_gains(azimuth, elevation)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpeakerConfigBuilder;
+    use crate::convention::Convention;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_downmix_7_1_4_to_5_1() {
+        let atmos = SpeakerConfigBuilder::new()
+            .atmos_7_1_4()
+            .build_config()
+            .unwrap();
+        let surround = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .convention(Convention::Max)
+            .build_config()
+            .unwrap();
+        let matrix = remap_matrix(&atmos, &surround);
+
+        for row in &matrix {
+            let power: f64 = row.iter().map(|g| g * g).sum();
+            assert_relative_eq!(power, 1.0, epsilon = 1e-9);
+        }
+        // Left side surround (90°) lands between left (30°) and left surround (110°)
+        let side = &matrix[3];
+        assert!(side[0] > 0.0 && side[3] > 0.0);
+        assert_eq!(side[1], 0.0);
+        assert_eq!(side[4], 0.0);
+    }
+
+    #[test]
+    fn test_same_layout_is_identity() {
+        let config = SpeakerConfigBuilder::new()
+            .surround_7_1()
+            .build_config()
+            .unwrap();
+        let matrix = remap_matrix(&config, &config);
+        for (i, row) in matrix.iter().enumerate() {
+            for (j, &gain) in row.iter().enumerate() {
+                assert_relative_eq!(gain, if i == j { 1.0 } else { 0.0 }, epsilon = 1e-9);
+            }
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! Ring-wise "2.5D" panning for layouts of stacked horizontal rings.
+//!
+//! Many venues hang a main ring at ear height and one or more height rings
+//! above it. 3D triplets on such a layout mix speakers of two rings and
+//! can jump between them as a source moves around. [`RingPanner`] instead
+//! groups the speakers into rings by elevation, pans pairwise within each
+//! ring by azimuth and crossfades between the rings above and below the
+//! source by elevation, which some venues prefer for its stability.
+
+use std::f64::consts::FRAC_PI_2;
+
+use crate::config::{Dimension, SpeakerConfig, SpeakerConfigBuilder};
+use crate::convention::Convention;
+use crate::error::{Result, VBAPError};
+use crate::panner::VBAPanner;
+
+/// Default elevation difference, in degrees, below which speakers belong to
+/// the same ring.
+pub const DEFAULT_RING_TOLERANCE: f64 = 10.0;
+
+/// A horizontal ring of speakers.
+#[derive(Clone, Debug)]
+pub struct Ring {
+    /// Mean elevation of the ring's speakers in degrees.
+    elevation: f64,
+    /// Speaker indices in the full layout.
+    speakers: Vec<usize>,
+    /// Pairwise panner over the ring's azimuths, `None` for a single
+    /// speaker.
+    panner: Option<VBAPanner>,
+}
+
+impl Ring {
+    /// Get the mean elevation of the ring's speakers in degrees.
+    #[inline]
+    pub fn elevation(&self) -> f64 {
+        self.elevation
+    }
+
+    /// Get the indices of the ring's speakers in the layout.
+    #[inline]
+    pub fn speakers(&self) -> &[usize] {
+        &self.speakers
+    }
+
+    /// Add the ring's gains for a native azimuth, scaled by `weight`.
+    fn add_gains(&self, azimuth: f64, weight: f64, gains: &mut [f64]) {
+        match &self.panner {
+            Some(panner) => {
+                // At elevation 0 no elevation policy applies, so the active
+                // gains are the full gains
+                for (speaker, gain) in panner.compute_active_gains(azimuth, 0.0) {
+                    gains[self.speakers[speaker]] += weight * gain;
+                }
+            }
+            None => gains[self.speakers[0]] += weight,
+        }
+    }
+}
+
+/// Panner for layouts of horizontal rings at different elevations.
+///
+/// Within a ring, a source is panned between the two speakers around its
+/// azimuth as on a 2D layout. Between rings, the ring gains are crossfaded
+/// with a constant-power law by elevation; sources above the top ring or
+/// below the bottom ring play on that ring alone. Gains are
+/// power-normalized, and virtual speakers are left out of the rings.
+///
+/// # Example
+///
+/// ```
+/// use vbap::rings::RingPanner;
+/// use vbap::SpeakerConfigBuilder;
+///
+/// let config = SpeakerConfigBuilder::new().atmos_7_1_4().build_config().unwrap();
+/// let panner = RingPanner::from_config(&config).unwrap();
+/// assert_eq!(panner.rings().len(), 2);
+///
+/// // Halfway between the rings, both contribute equally
+/// let gains = panner.compute_gains(0.0, 22.5);
+/// let power: f64 = gains.iter().map(|g| g * g).sum();
+/// assert!((power - 1.0).abs() < 1e-9);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RingPanner {
+    /// Rings sorted by elevation, lowest first.
+    rings: Vec<Ring>,
+    num_speakers: usize,
+    convention: Convention,
+}
+
+impl RingPanner {
+    /// Group a layout's speakers into rings with the
+    /// [default tolerance](DEFAULT_RING_TOLERANCE).
+    pub fn from_config(config: &SpeakerConfig) -> Result<Self> {
+        Self::with_tolerance(config, DEFAULT_RING_TOLERANCE)
+    }
+
+    /// Group a layout's speakers into rings.
+    ///
+    /// Speakers are sorted by elevation and a new ring starts wherever two
+    /// neighbouring elevations differ by more than `tolerance` degrees.
+    /// Returns an error if a ring's speakers cannot be paired, for example
+    /// two speakers at the same azimuth.
+    pub fn with_tolerance(config: &SpeakerConfig, tolerance: f64) -> Result<Self> {
+        if !(tolerance.is_finite() && tolerance >= 0.0) {
+            return Err(VBAPError::InvalidParameter {
+                parameter: "tolerance",
+                value: tolerance,
+                min: 0.0,
+                max: 180.0,
+            });
+        }
+
+        let speakers = config.speakers();
+        let mut order: Vec<usize> = (0..speakers.len())
+            .filter(|&i| !speakers[i].is_virtual())
+            .collect();
+        order.sort_by(|&a, &b| {
+            speakers[a]
+                .elevation()
+                .total_cmp(&speakers[b].elevation())
+                .then(a.cmp(&b))
+        });
+
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for &index in &order {
+            match groups.last_mut() {
+                Some(group)
+                    if speakers[index].elevation()
+                        - speakers[*group.last().unwrap()].elevation()
+                        <= tolerance =>
+                {
+                    group.push(index)
+                }
+                _ => groups.push(vec![index]),
+            }
+        }
+        if groups.is_empty() {
+            return Err(VBAPError::InsufficientSpeakers {
+                provided: 0,
+                required: 1,
+            });
+        }
+
+        let rings = groups
+            .into_iter()
+            .map(|mut indices| {
+                indices.sort_unstable();
+                let elevation = indices
+                    .iter()
+                    .map(|&i| speakers[i].elevation())
+                    .sum::<f64>()
+                    / indices.len() as f64;
+                let panner = if indices.len() > 1 {
+                    let mut builder = SpeakerConfigBuilder::new()
+                        .dimension(Dimension::Force2D)
+                        .tolerances(*config.tolerances());
+                    for &i in &indices {
+                        builder = builder.add_speaker(speakers[i].azimuth(), 0.0);
+                    }
+                    Some(builder.build()?)
+                } else {
+                    None
+                };
+                Ok(Ring {
+                    elevation,
+                    speakers: indices,
+                    panner,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            rings,
+            num_speakers: config.num_speakers(),
+            convention: config.convention(),
+        })
+    }
+
+    /// Get the rings, lowest first.
+    #[inline]
+    pub fn rings(&self) -> &[Ring] {
+        &self.rings
+    }
+
+    /// Get the number of speakers in the layout.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.num_speakers
+    }
+
+    /// Compute speaker gains for a source direction, in the layout's
+    /// [`convention`](SpeakerConfig::convention).
+    pub fn compute_gains(&self, azimuth: f64, elevation: f64) -> Vec<f64> {
+        let mut gains = vec![0.0; self.num_speakers];
+        self.compute_gains_into(azimuth, elevation, &mut gains);
+        gains
+    }
+
+    /// Compute speaker gains into a caller-provided buffer, without
+    /// allocating.
+    ///
+    /// # Panics
+    /// Panics if `gains.len() < self.num_speakers()`.
+    pub fn compute_gains_into(&self, azimuth: f64, elevation: f64, gains: &mut [f64]) {
+        assert!(
+            gains.len() >= self.num_speakers,
+            "gains slice too small: {} < {}",
+            gains.len(),
+            self.num_speakers
+        );
+        gains.fill(0.0);
+        let (azimuth, elevation) = self.convention.to_native(azimuth, elevation);
+
+        // First ring above the source
+        let upper = self
+            .rings
+            .partition_point(|ring| ring.elevation <= elevation);
+        if upper == 0 {
+            self.rings[0].add_gains(azimuth, 1.0, gains);
+        } else if upper == self.rings.len() {
+            self.rings[upper - 1].add_gains(azimuth, 1.0, gains);
+        } else {
+            let (below, above) = (&self.rings[upper - 1], &self.rings[upper]);
+            let t = (elevation - below.elevation) / (above.elevation - below.elevation);
+            let (sin, cos) = (t * FRAC_PI_2).sin_cos();
+            below.add_gains(azimuth, cos, gains);
+            above.add_gains(azimuth, sin, gains);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn two_rings() -> SpeakerConfig {
+        SpeakerConfigBuilder::new()
+            .add_speakers(&[
+                (0.0, 0.0),
+                (90.0, 2.0),
+                (180.0, -2.0),
+                (-90.0, 0.0),
+                (45.0, 40.0),
+                (135.0, 40.0),
+                (-135.0, 40.0),
+                (-45.0, 40.0),
+            ])
+            .build_config()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_ring_grouping() {
+        let panner = RingPanner::from_config(&two_rings()).unwrap();
+        let rings = panner.rings();
+        assert_eq!(rings.len(), 2);
+        assert_eq!(rings[0].speakers(), [0, 1, 2, 3]);
+        assert_relative_eq!(rings[0].elevation(), 0.0);
+        assert_eq!(rings[1].speakers(), [4, 5, 6, 7]);
+
+        // A tight tolerance splits the main ring
+        let split = RingPanner::with_tolerance(&two_rings(), 1.0).unwrap();
+        assert_eq!(split.rings().len(), 4);
+        assert!(RingPanner::with_tolerance(&two_rings(), -1.0).is_err());
+    }
+
+    #[test]
+    fn test_pairwise_within_ring() {
+        let panner = RingPanner::from_config(&two_rings()).unwrap();
+
+        // On the main ring only its speakers play, pairwise
+        let gains = panner.compute_gains(45.0, 0.0);
+        assert_relative_eq!(gains[0], gains[1], epsilon = 1e-9);
+        assert!(gains[4..].iter().all(|&g| g == 0.0));
+
+        // Above the top ring, only the top ring plays
+        let gains = panner.compute_gains(45.0, 80.0);
+        assert_relative_eq!(gains[4], 1.0, epsilon = 1e-9);
+        assert!(gains[..4].iter().all(|&g| g == 0.0));
+    }
+
+    #[test]
+    fn test_crossfade_between_rings() {
+        let panner = RingPanner::from_config(&two_rings()).unwrap();
+        let mut previous = 0.0;
+        for step in 0..=10 {
+            let elevation = 4.0 * step as f64;
+            let gains = panner.compute_gains(0.0, elevation);
+            let power: f64 = gains.iter().map(|g| g * g).sum();
+            assert_relative_eq!(power, 1.0, epsilon = 1e-9);
+
+            let upper: f64 = gains[4..].iter().map(|g| g * g).sum();
+            assert!(upper >= previous);
+            previous = upper;
+        }
+        assert_relative_eq!(previous, 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_single_speaker_ring() {
+        let config = SpeakerConfigBuilder::new()
+            .surround_5_1()
+            .add_speaker(0.0, 90.0)
+            .build_config()
+            .unwrap();
+        let panner = RingPanner::from_config(&config).unwrap();
+        assert_eq!(panner.rings().len(), 2);
+        assert_eq!(panner.rings()[1].speakers(), [5]);
+
+        let gains = panner.compute_gains(120.0, 90.0);
+        assert_relative_eq!(gains[5], 1.0, epsilon = 1e-9);
+    }
+}
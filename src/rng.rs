@@ -0,0 +1,113 @@
+//! Seedable random number generation for the stochastic features.
+//!
+//! Everything random in the crate (the wander of a trajectory `Swarm`,
+//! [`RandomLayout`](crate::random_layout::RandomLayout) scattering)
+//! draws from an [`Rng`]. The built-in [`SplitMix64`] is used when only a
+//! seed is given; pass your own generator to share one stream across
+//! features or to use a generator from another crate.
+
+/// Source of uniformly distributed random bits.
+///
+/// Only [`next_u64`](Self::next_u64) is required. Generators from other
+/// crates can be used through a small wrapper:
+///
+/// ```
+/// use vbap::rng::Rng;
+///
+/// /// Xorshift64, standing in for a generator from another crate.
+/// struct Xorshift(u64);
+///
+/// impl Rng for Xorshift {
+///     fn next_u64(&mut self) -> u64 {
+///         self.0 ^= self.0 << 13;
+///         self.0 ^= self.0 >> 7;
+///         self.0 ^= self.0 << 17;
+///         self.0
+///     }
+/// }
+///
+/// let mut rng = Xorshift(1);
+/// assert!((0.0..1.0).contains(&rng.next_f64()));
+/// ```
+pub trait Rng {
+    /// Next 64 uniformly distributed random bits.
+    fn next_u64(&mut self) -> u64;
+
+    /// Uniform in `[0, 1)`.
+    #[inline]
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// Uniform in `[min, max)`.
+    #[inline]
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+}
+
+impl<R: Rng + ?Sized> Rng for &mut R {
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        (**self).next_u64()
+    }
+}
+
+impl<R: Rng + ?Sized> Rng for Box<R> {
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        (**self).next_u64()
+    }
+}
+
+/// SplitMix64: tiny, fast, and good enough for motion and layout jitter.
+///
+/// The same seed always produces the same sequence, on every platform.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    /// Create a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl Rng for SplitMix64 {
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic_and_in_range() {
+        let mut a = SplitMix64::new(7);
+        let mut b = SplitMix64::new(7);
+        for _ in 0..1000 {
+            let x = a.next_f64();
+            assert_eq!(x, b.next_f64());
+            assert!((0.0..1.0).contains(&x));
+        }
+    }
+
+    #[test]
+    fn test_borrowed_rng_advances_owner() {
+        let mut owned = SplitMix64::new(7);
+        let mut borrowed = &mut owned;
+        let first = Rng::next_u64(&mut borrowed);
+        let mut boxed: Box<dyn Rng> = Box::new(SplitMix64::new(7));
+        assert_eq!(boxed.next_u64(), first);
+        assert_ne!(owned.next_u64(), first);
+    }
+}
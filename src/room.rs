@@ -0,0 +1,166 @@
+//! Speaker layouts measured in room coordinates.
+//!
+//! Installers measure speaker positions with a tape or laser, relative to
+//! some reference point in the room, rather than as angles around the
+//! listener. [`RoomLayout`] collects such measurements and turns them into a
+//! panner for a given listening position.
+
+use glam::DVec3;
+
+use crate::config::{SpeakerConfig, SpeakerConfigBuilder};
+use crate::error::Result;
+use crate::listener::ListenerCompensation;
+use crate::math::cartesian_to_spherical;
+use crate::panner::VBAPanner;
+
+/// Speaker positions in meters, plus the listening position.
+///
+/// Coordinates use the panner's frame: `x` to the left, `y` to the front
+/// and `z` up, relative to any fixed room origin. The listener starts at the
+/// origin.
+///
+/// # Example
+///
+/// ```
+/// use vbap::room::RoomLayout;
+///
+/// // A 4 x 5 m room, origin in the front-right corner at ear height
+/// let room = RoomLayout::new()
+///     .add_speaker(4.0, 5.0, 0.0)
+///     .add_speaker(0.0, 5.0, 0.0)
+///     .add_speaker(4.0, 0.0, 0.0)
+///     .add_speaker(0.0, 0.0, 0.0)
+///     .with_listener(2.0, 2.0, 0.0);
+///
+/// let compensation = room.compensation().unwrap();
+/// // The rear speakers are closer to this listener
+/// assert!(compensation.delays()[2] > 0.0);
+/// assert_eq!(compensation.delays()[0], 0.0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct RoomLayout {
+    positions: Vec<DVec3>,
+    listener: DVec3,
+}
+
+impl RoomLayout {
+    /// Create an empty layout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a speaker at a position in meters.
+    pub fn add_speaker(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.positions.push(DVec3::new(x, y, z));
+        self
+    }
+
+    /// Set the listening position in meters.
+    pub fn with_listener(mut self, x: f64, y: f64, z: f64) -> Self {
+        self.listener = DVec3::new(x, y, z);
+        self
+    }
+
+    /// Get the speaker positions in meters.
+    #[inline]
+    pub fn positions(&self) -> &[DVec3] {
+        &self.positions
+    }
+
+    /// Get the listening position in meters.
+    #[inline]
+    pub fn listener(&self) -> DVec3 {
+        self.listener
+    }
+
+    /// Get each speaker's (azimuth, elevation, distance) as seen from the
+    /// listener, in degrees and meters.
+    pub fn spherical(&self) -> Vec<(f64, f64, f64)> {
+        self.positions
+            .iter()
+            .map(|&position| {
+                let offset = position - self.listener;
+                let (azimuth, elevation) = cartesian_to_spherical(offset);
+                (azimuth, elevation, offset.length())
+            })
+            .collect()
+    }
+
+    /// Create a builder with the speakers as seen from the listener.
+    ///
+    /// Use this to set the dimension, tolerances and so on before building.
+    pub fn builder(&self) -> SpeakerConfigBuilder {
+        self.positions
+            .iter()
+            .fold(SpeakerConfigBuilder::new(), |builder, &position| {
+                let offset = position - self.listener;
+                builder.add_speaker_xyz(offset.x, offset.y, offset.z)
+            })
+    }
+
+    /// Build the speaker configuration as seen from the listener.
+    pub fn build_config(&self) -> Result<SpeakerConfig> {
+        self.builder().build_config()
+    }
+
+    /// Build a panner as seen from the listener.
+    pub fn build(&self) -> Result<VBAPanner> {
+        self.builder().build()
+    }
+
+    /// Build a panner with delay and level compensation for the listener.
+    pub fn compensation(&self) -> Result<ListenerCompensation> {
+        ListenerCompensation::from_speaker_distances(&self.build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VBAPError;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_room_coordinates() {
+        let room = RoomLayout::new()
+            .add_speaker(3.0, 4.0, 0.0)
+            .add_speaker(-3.0, 4.0, 0.0)
+            .add_speaker(0.0, -2.0, 2.0)
+            .with_listener(0.0, 0.0, 0.0);
+
+        let spherical = room.spherical();
+        assert_relative_eq!(spherical[0].0, 36.8699, epsilon = 1e-4);
+        assert_relative_eq!(spherical[0].2, 5.0, epsilon = 1e-12);
+        assert_relative_eq!(spherical[2].0, 180.0, epsilon = 1e-12);
+        assert_relative_eq!(spherical[2].1, 45.0, epsilon = 1e-12);
+
+        let config = room.build_config().unwrap();
+        for (speaker, &(azi, ele, distance)) in config.speakers().iter().zip(&spherical) {
+            assert_relative_eq!(speaker.azimuth(), azi, epsilon = 1e-12);
+            assert_relative_eq!(speaker.elevation(), ele, epsilon = 1e-12);
+            assert_relative_eq!(speaker.distance(), distance, epsilon = 1e-12);
+        }
+        // Distances survive a rebuild
+        let moved = config.moved_speaker(0, 40.0, 0.0).unwrap();
+        assert_relative_eq!(moved.speakers()[0].distance(), 5.0, epsilon = 1e-12);
+
+        let compensation = room.compensation().unwrap();
+        assert_relative_eq!(compensation.distances()[2], 8f64.sqrt(), epsilon = 1e-12);
+        assert_relative_eq!(compensation.distance_gains()[0], 1.0);
+    }
+
+    #[test]
+    fn test_speaker_at_listener() {
+        let room = RoomLayout::new()
+            .add_speaker(1.0, 1.0, 0.0)
+            .add_speaker(-1.0, 1.0, 0.0)
+            .add_speaker(0.0, 0.0, 0.0);
+        assert!(matches!(
+            room.build(),
+            Err(VBAPError::InvalidParameter {
+                parameter: "distance",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,207 @@
+//! Lock-free panner sharing between a control thread and the audio thread.
+//!
+//! Rebuilding a layout triangulates and allocates, which must not happen on
+//! the audio thread. [`SharedPanner`] lets a control thread build the new
+//! [`VBAPanner`] and publish it atomically while the audio thread keeps
+//! computing gains from whichever panner is current.
+
+use std::sync::{Arc, Mutex};
+
+use arc_swap::ArcSwap;
+
+use crate::error::Result;
+use crate::panner::VBAPanner;
+
+/// A [`VBAPanner`] that can be replaced while other threads read it.
+///
+/// Readers ([`compute_gains_into`](Self::compute_gains_into),
+/// [`with`](Self::with)) neither lock nor allocate. Writers
+/// ([`store`](Self::store), [`update`](Self::update)) are meant for a single
+/// control thread; they keep the replaced panner alive until the next swap
+/// so that it is normally freed on the control thread rather than by a
+/// reader.
+///
+/// A new layout may have a different number of speakers. Size gain buffers
+/// for the largest layout you will publish, or check
+/// [`num_speakers`](Self::num_speakers) each block.
+///
+/// Requires the `shared` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use vbap::shared::SharedPanner;
+/// use vbap::VBAPanner;
+///
+/// let shared = Arc::new(SharedPanner::new(
+///     VBAPanner::builder().surround_5_1().build().unwrap(),
+/// ));
+///
+/// // Control thread
+/// let control = Arc::clone(&shared);
+/// std::thread::spawn(move || {
+///     control.store(VBAPanner::builder().surround_7_1().build().unwrap());
+/// })
+/// .join()
+/// .unwrap();
+///
+/// // Audio thread
+/// let mut gains = [0.0; 16];
+/// shared.compute_gains_into(30.0, 0.0, &mut gains);
+/// assert_eq!(shared.num_speakers(), 7);
+/// ```
+#[derive(Debug)]
+pub struct SharedPanner {
+    current: ArcSwap<VBAPanner>,
+    /// Previous panner, dropped by the next writer.
+    retired: Mutex<Option<Arc<VBAPanner>>>,
+}
+
+impl SharedPanner {
+    /// Share a panner.
+    pub fn new(panner: VBAPanner) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(panner),
+            retired: Mutex::new(None),
+        }
+    }
+
+    /// Compute speaker gains with the current panner.
+    ///
+    /// Entries past the current panner's speaker count are left untouched.
+    ///
+    /// # Panics
+    /// Panics if `gains` is shorter than the current panner's speaker count.
+    #[inline]
+    pub fn compute_gains_into(&self, azimuth: f64, elevation: f64, gains: &mut [f64]) {
+        self.current
+            .load()
+            .compute_gains_into(azimuth, elevation, gains);
+    }
+
+    /// Run `f` with the current panner.
+    ///
+    /// Use this to make several calls against the same layout, e.g. a whole
+    /// block. A swap during `f` is seen by the next call.
+    #[inline]
+    pub fn with<R>(&self, f: impl FnOnce(&VBAPanner) -> R) -> R {
+        f(&self.current.load())
+    }
+
+    /// Get the number of speakers of the current panner.
+    #[inline]
+    pub fn num_speakers(&self) -> usize {
+        self.current.load().num_speakers()
+    }
+
+    /// Get a reference-counted handle to the current panner.
+    ///
+    /// Dropping the last handle frees the panner, so avoid holding one on
+    /// the audio thread.
+    pub fn snapshot(&self) -> Arc<VBAPanner> {
+        self.current.load_full()
+    }
+
+    /// Publish a new panner.
+    pub fn store(&self, panner: VBAPanner) {
+        self.retire(self.current.swap(Arc::new(panner)));
+    }
+
+    /// Publish a panner derived from the current one.
+    ///
+    /// `f` runs on the calling thread and may take as long as it needs. If it
+    /// fails, the current panner is kept and the error returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::shared::SharedPanner;
+    /// use vbap::VBAPanner;
+    ///
+    /// let shared = SharedPanner::new(VBAPanner::builder().surround_5_1().build().unwrap());
+    /// shared
+    ///     .update(|panner| {
+    ///         let mut panner = panner.clone();
+    ///         panner.move_speaker(0, 35.0, 0.0)?;
+    ///         Ok(panner)
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(shared.snapshot().speakers()[0].azimuth(), 35.0);
+    /// ```
+    pub fn update(&self, f: impl FnOnce(&VBAPanner) -> Result<VBAPanner>) -> Result<()> {
+        let panner = f(&self.current.load())?;
+        self.store(panner);
+        Ok(())
+    }
+
+    fn retire(&self, previous: Arc<VBAPanner>) {
+        let mut retired = self.retired.lock().unwrap_or_else(|e| e.into_inner());
+        // Drops the panner retired by the previous swap
+        *retired = Some(previous);
+    }
+}
+
+impl From<VBAPanner> for SharedPanner {
+    fn from(panner: VBAPanner) -> Self {
+        Self::new(panner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_swap_while_reading() {
+        let layouts = [
+            VBAPanner::builder().surround_5_1().build().unwrap(),
+            VBAPanner::builder().surround_7_1().build().unwrap(),
+            VBAPanner::builder().atmos_7_1_4().build().unwrap(),
+        ];
+        let shared = Arc::new(SharedPanner::new(layouts[0].clone()));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let reader = {
+            let shared = Arc::clone(&shared);
+            let done = Arc::clone(&done);
+            std::thread::spawn(move || {
+                let mut gains = [0.0; 16];
+                let mut reads = 0;
+                while !done.load(Ordering::Relaxed) || reads == 0 {
+                    shared.with(|panner| {
+                        gains.fill(0.0);
+                        panner.compute_gains_into(-45.0, 10.0, &mut gains);
+                        let power: f64 = gains.iter().map(|g| g * g).sum();
+                        assert!((power - 1.0).abs() < 1e-9);
+                    });
+                    reads += 1;
+                }
+            })
+        };
+
+        for i in 0..300 {
+            shared.store(layouts[i % layouts.len()].clone());
+        }
+        done.store(true, Ordering::Relaxed);
+        reader.join().unwrap();
+
+        assert_eq!(shared.num_speakers(), layouts[299 % 3].num_speakers());
+    }
+
+    #[test]
+    fn test_failed_update_keeps_panner() {
+        let shared = SharedPanner::new(VBAPanner::builder().stereo().build().unwrap());
+        let before = shared.snapshot();
+
+        assert!(shared
+            .update(|panner| {
+                let mut panner = panner.clone();
+                panner.move_speaker(7, 0.0, 0.0)?;
+                Ok(panner)
+            })
+            .is_err());
+        assert!(Arc::ptr_eq(&before, &shared.snapshot()));
+    }
+}
@@ -1,8 +1,110 @@
 //! Speaker position representation.
 
+use std::fmt;
+
 use crate::math::spherical_to_cartesian;
 use glam::DVec3;
 
+/// Standard channel name of a speaker in a channel-based format.
+///
+/// Displayed as the usual abbreviation ("L", "Lss", "Ltf", ...). The
+/// builder presets label their speakers; custom layouts can use
+/// [`SpeakerConfigBuilder::add_labeled_speaker`](crate::SpeakerConfigBuilder::add_labeled_speaker)
+/// or [`Speaker::with_label`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ChannelLabel {
+    /// Left.
+    L,
+    /// Right.
+    R,
+    /// Center.
+    C,
+    /// Low-frequency effects.
+    Lfe,
+    /// Left surround.
+    Ls,
+    /// Right surround.
+    Rs,
+    /// Left side surround.
+    Lss,
+    /// Right side surround.
+    Rss,
+    /// Left rear surround.
+    Lrs,
+    /// Right rear surround.
+    Rrs,
+    /// Left wide.
+    Lw,
+    /// Right wide.
+    Rw,
+    /// Left top front.
+    Ltf,
+    /// Right top front.
+    Rtf,
+    /// Left top middle.
+    Ltm,
+    /// Right top middle.
+    Rtm,
+    /// Left top rear.
+    Ltr,
+    /// Right top rear.
+    Rtr,
+}
+
+impl ChannelLabel {
+    /// All labels, in declaration order.
+    pub const ALL: [ChannelLabel; 18] = [
+        ChannelLabel::L,
+        ChannelLabel::R,
+        ChannelLabel::C,
+        ChannelLabel::Lfe,
+        ChannelLabel::Ls,
+        ChannelLabel::Rs,
+        ChannelLabel::Lss,
+        ChannelLabel::Rss,
+        ChannelLabel::Lrs,
+        ChannelLabel::Rrs,
+        ChannelLabel::Lw,
+        ChannelLabel::Rw,
+        ChannelLabel::Ltf,
+        ChannelLabel::Rtf,
+        ChannelLabel::Ltm,
+        ChannelLabel::Rtm,
+        ChannelLabel::Ltr,
+        ChannelLabel::Rtr,
+    ];
+
+    /// Get the usual abbreviation.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChannelLabel::L => "L",
+            ChannelLabel::R => "R",
+            ChannelLabel::C => "C",
+            ChannelLabel::Lfe => "LFE",
+            ChannelLabel::Ls => "Ls",
+            ChannelLabel::Rs => "Rs",
+            ChannelLabel::Lss => "Lss",
+            ChannelLabel::Rss => "Rss",
+            ChannelLabel::Lrs => "Lrs",
+            ChannelLabel::Rrs => "Rrs",
+            ChannelLabel::Lw => "Lw",
+            ChannelLabel::Rw => "Rw",
+            ChannelLabel::Ltf => "Ltf",
+            ChannelLabel::Rtf => "Rtf",
+            ChannelLabel::Ltm => "Ltm",
+            ChannelLabel::Rtm => "Rtm",
+            ChannelLabel::Ltr => "Ltr",
+            ChannelLabel::Rtr => "Rtr",
+        }
+    }
+}
+
+impl fmt::Display for ChannelLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// A speaker at a specific position in 3D space.
 ///
 /// Positions are defined using spherical coordinates (azimuth, elevation)
@@ -30,6 +132,12 @@ pub struct Speaker {
 
     /// Cached Cartesian coordinates (unit vector on sphere).
     cartesian: DVec3,
+
+    /// Imaginary speaker that only helps the triangulation.
+    is_virtual: bool,
+
+    /// Channel name, if the speaker has one.
+    label: Option<ChannelLabel>,
 }
 
 impl Speaker {
@@ -49,6 +157,45 @@ impl Speaker {
             elevation,
             distance,
             cartesian,
+            is_virtual: false,
+            label: None,
+        }
+    }
+
+    /// Create a virtual (imaginary) speaker.
+    ///
+    /// Virtual speakers take part in the triangulation so that directions
+    /// without a physical speaker nearby can still be panned, but their own
+    /// gain is discarded. See
+    /// [`SpeakerConfig::suggest_virtual_speakers`](crate::SpeakerConfig::suggest_virtual_speakers).
+    pub fn new_virtual(id: usize, azimuth: f64, elevation: f64) -> Self {
+        Self {
+            is_virtual: true,
+            ..Self::new(id, azimuth, elevation)
+        }
+    }
+
+    /// Give the speaker a channel label.
+    pub fn with_label(mut self, label: ChannelLabel) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// Copy the speaker to a new index and direction, keeping its distance,
+    /// label and whether it is virtual.
+    pub(crate) fn relocated(&self, id: usize, azimuth: f64, elevation: f64) -> Self {
+        Self {
+            is_virtual: self.is_virtual,
+            label: self.label,
+            ..Self::with_distance(id, azimuth, elevation, self.distance)
+        }
+    }
+
+    /// Copy the speaker with a new distance.
+    pub(crate) fn at_distance(&self, distance: f64) -> Self {
+        Self {
+            distance,
+            ..self.clone()
         }
     }
 
@@ -82,6 +229,18 @@ impl Speaker {
         self.cartesian
     }
 
+    /// Check whether this is a virtual speaker, whose gain is discarded.
+    #[inline]
+    pub fn is_virtual(&self) -> bool {
+        self.is_virtual
+    }
+
+    /// Get the channel label, if the speaker has one.
+    #[inline]
+    pub fn label(&self) -> Option<ChannelLabel> {
+        self.label
+    }
+
     /// Check if this speaker is in the horizontal plane (elevation ≈ 0).
     #[inline]
     pub fn is_horizontal(&self) -> bool {
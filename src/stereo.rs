@@ -0,0 +1,192 @@
+//! DAW-style stereo panning with classic pan laws.
+//!
+//! VBAP on a speaker pair is a constant-power (-3 dB center) pan. Mixing
+//! engines often need the other classic laws too, so two-speaker layouts can
+//! be served by [`StereoPanner`] instead.
+
+use crate::config::SpeakerConfig;
+use crate::error::{Result, VBAPError};
+use std::f64::consts::FRAC_PI_2;
+
+/// Stereo pan law, named by its attenuation at the center.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PanLaw {
+    /// -3 dB center: sin/cos constant-power law (same as VBAP).
+    #[default]
+    Minus3dB,
+    /// -4.5 dB center: compromise between constant power and constant gain.
+    Minus4_5dB,
+    /// -6 dB center: linear constant-gain law.
+    Minus6dB,
+}
+
+impl PanLaw {
+    /// (left, right) gains for `t` from 0 (hard left) to 1 (hard right).
+    fn gains(self, t: f64) -> [f64; 2] {
+        let (sin, cos) = (t * FRAC_PI_2).sin_cos();
+        match self {
+            PanLaw::Minus3dB => [cos, sin],
+            PanLaw::Minus4_5dB => [((1.0 - t) * cos).sqrt(), (t * sin).sqrt()],
+            PanLaw::Minus6dB => [1.0 - t, t],
+        }
+    }
+}
+
+/// Two-speaker panner with a selectable [`PanLaw`].
+///
+/// Gains are returned in speaker order, so a `StereoPanner` built from a
+/// configuration can replace a [`VBAPanner`](crate::VBAPanner) on the same
+/// layout.
+///
+/// # Example
+///
+/// ```
+/// use vbap::stereo::{PanLaw, StereoPanner};
+///
+/// let panner = StereoPanner::new(PanLaw::Minus6dB);
+/// let [left, right] = panner.pan(0.0);
+/// assert!((left - 0.5).abs() < 1e-12 && (right - 0.5).abs() < 1e-12);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StereoPanner {
+    law: PanLaw,
+    /// Azimuths of the left and right speakers.
+    azimuths: [f64; 2],
+    /// Speaker indices of the left and right speakers.
+    indices: [usize; 2],
+}
+
+impl StereoPanner {
+    /// Standard stereo pair at ±30° (speaker 0 left, speaker 1 right).
+    pub fn new(law: PanLaw) -> Self {
+        Self {
+            law,
+            azimuths: [30.0, -30.0],
+            indices: [0, 1],
+        }
+    }
+
+    /// Stereo panner for a two-speaker layout.
+    ///
+    /// Returns an error unless the layout has exactly two speakers at
+    /// different azimuths.
+    pub fn from_config(config: &SpeakerConfig, law: PanLaw) -> Result<Self> {
+        let [a, b] = config.speakers() else {
+            return Err(VBAPError::InvalidConfiguration(format!(
+                "stereo panning needs exactly 2 speakers, got {}",
+                config.num_speakers()
+            )));
+        };
+        if a.azimuth() == b.azimuth() {
+            return Err(VBAPError::InvalidConfiguration(
+                "stereo speakers must have different azimuths".into(),
+            ));
+        }
+
+        // Larger azimuth is further left
+        let (left, right) = if a.azimuth() > b.azimuth() {
+            (0, 1)
+        } else {
+            (1, 0)
+        };
+        let speakers = config.speakers();
+        Ok(Self {
+            law,
+            azimuths: [speakers[left].azimuth(), speakers[right].azimuth()],
+            indices: [left, right],
+        })
+    }
+
+    /// Get the pan law.
+    #[inline]
+    pub fn law(&self) -> PanLaw {
+        self.law
+    }
+
+    /// Set the pan law.
+    pub fn with_law(mut self, law: PanLaw) -> Self {
+        self.law = law;
+        self
+    }
+
+    /// Gains in speaker order for a DAW pan position, from -1 (hard left)
+    /// through 0 (center) to 1 (hard right).
+    pub fn pan(&self, position: f64) -> [f64; 2] {
+        let t = (position.clamp(-1.0, 1.0) + 1.0) / 2.0;
+        let [left, right] = self.law.gains(t);
+
+        let mut gains = [0.0; 2];
+        gains[self.indices[0]] = left;
+        gains[self.indices[1]] = right;
+        gains
+    }
+
+    /// Gains in speaker order for a source azimuth in degrees.
+    ///
+    /// The azimuth is mapped linearly between the two speakers and clamped
+    /// to them; elevation is ignored.
+    pub fn compute_gains(&self, azimuth: f64, _elevation: f64) -> [f64; 2] {
+        let [left, right] = self.azimuths;
+        let center = (left + right) / 2.0;
+        let half_width = (left - right) / 2.0;
+        self.pan((center - azimuth) / half_width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpeakerConfigBuilder;
+    use crate::util::lin_to_db as db;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_center_attenuation() {
+        for (law, expected) in [
+            (PanLaw::Minus3dB, -3.01),
+            (PanLaw::Minus4_5dB, -4.52),
+            (PanLaw::Minus6dB, -6.02),
+        ] {
+            let [left, right] = StereoPanner::new(law).pan(0.0);
+            assert_relative_eq!(left, right, epsilon = 1e-12);
+            assert_relative_eq!(db(left), expected, epsilon = 0.01);
+        }
+    }
+
+    #[test]
+    fn test_extremes_and_azimuth() {
+        let panner = StereoPanner::new(PanLaw::Minus4_5dB);
+        assert_eq!(panner.pan(-1.0), [1.0, 0.0]);
+        assert_eq!(panner.pan(5.0), [0.0, 1.0]);
+        assert_eq!(panner.compute_gains(30.0, 0.0), [1.0, 0.0]);
+        assert_eq!(panner.compute_gains(-90.0, 0.0), [0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_from_config_speaker_order() {
+        // Right speaker first
+        let config = SpeakerConfigBuilder::new()
+            .add_speaker(-45.0, 0.0)
+            .add_speaker(45.0, 0.0)
+            .build_config()
+            .unwrap();
+        let panner = StereoPanner::from_config(&config, PanLaw::Minus6dB).unwrap();
+        assert_eq!(panner.pan(-1.0), [0.0, 1.0]);
+        assert_eq!(panner.compute_gains(-45.0, 0.0), [1.0, 0.0]);
+
+        let lcr = SpeakerConfigBuilder::new().lcr().build_config().unwrap();
+        assert!(StereoPanner::from_config(&lcr, PanLaw::Minus3dB).is_err());
+    }
+
+    #[test]
+    fn test_minus_3db_matches_vbap_endpoints() {
+        let vbap = crate::VBAPanner::builder().stereo().build().unwrap();
+        let stereo = StereoPanner::new(PanLaw::Minus3dB);
+        for azi in [-30.0, 0.0, 30.0] {
+            let expected = vbap.compute_gains(azi, 0.0);
+            let gains = stereo.compute_gains(azi, 0.0);
+            assert_relative_eq!(gains[0], expected[0], epsilon = 1e-9);
+            assert_relative_eq!(gains[1], expected[1], epsilon = 1e-9);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! `tracing` instrumentation of layout building and tuple selection.
+//!
+//! Building a configuration runs in a `build_config` span and ends with a
+//! debug event describing the triangulation. Tuple switches of stateful
+//! panning ([`VBAPanner::compute_gains_with_state`]) are debug events on
+//! the `vbap::switch` target. A moving source can switch many times per
+//! second on the audio thread, so switch events are rate limited to one per
+//! [`SWITCH_EVENT_INTERVAL_MS`] across all sources, each carrying the number
+//! of switches suppressed since the previous one.
+//!
+//! Requires the `tracing` feature.
+//!
+//! [`VBAPanner::compute_gains_with_state`]: crate::panner::VBAPanner::compute_gains_with_state
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Minimum time between two tuple switch events, in milliseconds.
+pub const SWITCH_EVENT_INTERVAL_MS: u64 = 100;
+
+/// Time of the last switch event, in milliseconds since the Unix epoch.
+static LAST_SWITCH_EVENT: AtomicU64 = AtomicU64::new(0);
+/// Switches not reported since the last switch event.
+static SUPPRESSED_SWITCHES: AtomicU64 = AtomicU64::new(0);
+
+/// Report that a source moved from tuple `from` to tuple `to`.
+pub(crate) fn tuple_switch(from: usize, to: usize, azimuth: f64, elevation: f64) {
+    if !tracing::enabled!(target: "vbap::switch", tracing::Level::DEBUG) {
+        return;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    if let Some(suppressed) = admit(&LAST_SWITCH_EVENT, &SUPPRESSED_SWITCHES, now) {
+        tracing::debug!(
+            target: "vbap::switch",
+            from,
+            to,
+            azimuth,
+            elevation,
+            suppressed,
+            "tuple switch"
+        );
+    }
+}
+
+/// Decide whether an event at `now` may be emitted.
+///
+/// Returns the number of events suppressed since the last emitted one, or
+/// `None` (counting this event as suppressed) if it comes too soon.
+fn admit(last: &AtomicU64, suppressed: &AtomicU64, now: u64) -> Option<u64> {
+    let previous = last.load(Ordering::Relaxed);
+    let due = now.saturating_sub(previous) >= SWITCH_EVENT_INTERVAL_MS;
+    if due
+        && last
+            .compare_exchange(previous, now, Ordering::Relaxed, Ordering::Relaxed)
+            .is_ok()
+    {
+        Some(suppressed.swap(0, Ordering::Relaxed))
+    } else {
+        suppressed.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit() {
+        let last = AtomicU64::new(0);
+        let suppressed = AtomicU64::new(0);
+
+        assert_eq!(admit(&last, &suppressed, 1_000), Some(0));
+        assert_eq!(admit(&last, &suppressed, 1_010), None);
+        assert_eq!(admit(&last, &suppressed, 1_099), None);
+        assert_eq!(admit(&last, &suppressed, 1_100), Some(2));
+        assert_eq!(admit(&last, &suppressed, 1_300), Some(0));
+    }
+}
@@ -0,0 +1,190 @@
+//! Parametric trajectory generators.
+
+use glam::DVec3;
+
+use super::{Interpolation, Keyframe, Trajectory};
+use crate::math::{cartesian_to_spherical, wrap_azimuth};
+use crate::rng::Rng;
+
+impl Trajectory {
+    /// Circle the listener at a constant `elevation`, starting in front.
+    ///
+    /// `speed` is in degrees per second; positive speeds turn towards the
+    /// left. The circle is sampled into `num_keyframes` (at least 2) evenly
+    /// spaced keyframes from 0 to `duration` seconds; keep them less than
+    /// 180° of travel apart.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::trajectory::Trajectory;
+    ///
+    /// // One turn every 4 seconds, at ear level
+    /// let circle = Trajectory::circle(0.0, 90.0, 4.0, 17);
+    /// assert!((circle.sample(1.0).0 - 90.0).abs() < 1e-9);
+    /// ```
+    pub fn circle(elevation: f64, speed: f64, duration: f64, num_keyframes: usize) -> Trajectory {
+        Self::spiral(elevation, elevation, speed, duration, num_keyframes)
+    }
+
+    /// Circle the listener like [`circle`](Self::circle) while the
+    /// elevation moves steadily from `start_elevation` to `end_elevation`.
+    pub fn spiral(
+        start_elevation: f64,
+        end_elevation: f64,
+        speed: f64,
+        duration: f64,
+        num_keyframes: usize,
+    ) -> Trajectory {
+        sampled(duration, num_keyframes, |t| {
+            let progress = if duration > 0.0 { t / duration } else { 1.0 };
+            (
+                wrap_azimuth(speed * t),
+                start_elevation + (end_elevation - start_elevation) * progress,
+            )
+        })
+    }
+
+    /// Lissajous figure around the front: azimuth swings ±`width` degrees
+    /// at `azimuth_rate` Hz and elevation ±`height` degrees at
+    /// `elevation_rate` Hz, both starting in front.
+    pub fn lissajous(
+        width: f64,
+        height: f64,
+        azimuth_rate: f64,
+        elevation_rate: f64,
+        duration: f64,
+        num_keyframes: usize,
+    ) -> Trajectory {
+        sampled(duration, num_keyframes, |t| {
+            let phase = std::f64::consts::TAU * t;
+            (
+                width * (azimuth_rate * phase).sin(),
+                height * (elevation_rate * phase).sin(),
+            )
+        })
+        .with_interpolation(Interpolation::Spline)
+    }
+
+    /// Figure-eight around the front, ±`width` degrees wide and ±`height`
+    /// degrees high, completed every `period` seconds.
+    ///
+    /// This is a [`lissajous`](Self::lissajous) figure with the elevation
+    /// moving twice as fast as the azimuth.
+    pub fn figure_eight(
+        width: f64,
+        height: f64,
+        period: f64,
+        duration: f64,
+        num_keyframes: usize,
+    ) -> Trajectory {
+        let rate = if period > 0.0 { 1.0 / period } else { 0.0 };
+        Self::lissajous(width, height, rate, 2.0 * rate, duration, num_keyframes)
+    }
+
+    /// Random walk on the sphere, starting in front.
+    ///
+    /// Each of the `num_keyframes` (at least 2) keyframes, evenly spaced from
+    /// 0 to `duration` seconds, lies `step` degrees from the previous one in
+    /// a random direction drawn from `rng`. The walk moves along great
+    /// circles between keyframes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::rng::SplitMix64;
+    /// use vbap::trajectory::Trajectory;
+    ///
+    /// let walk = Trajectory::random_walk(&mut SplitMix64::new(3), 10.0, 5.0, 51);
+    /// assert_eq!(walk.keyframes().len(), 51);
+    /// ```
+    pub fn random_walk<R: Rng>(
+        rng: &mut R,
+        step: f64,
+        duration: f64,
+        num_keyframes: usize,
+    ) -> Trajectory {
+        let (sin, cos) = step.to_radians().sin_cos();
+        let mut position = DVec3::Y;
+        sampled(duration, num_keyframes, |t| {
+            if t > 0.0 {
+                // Random tangent direction, redrawn in the rare degenerate case
+                let tangent = loop {
+                    let v = DVec3::new(
+                        rng.range(-1.0, 1.0),
+                        rng.range(-1.0, 1.0),
+                        rng.range(-1.0, 1.0),
+                    );
+                    if let Some(tangent) = (v - position * v.dot(position)).try_normalize() {
+                        break tangent;
+                    }
+                };
+                position = (position * cos + tangent * sin).normalize();
+            }
+            cartesian_to_spherical(position)
+        })
+        .with_interpolation(Interpolation::Slerp)
+    }
+}
+
+/// Sample `f(t)` at `num_keyframes` (at least 2) evenly spaced times from 0
+/// to `duration`.
+fn sampled(
+    duration: f64,
+    num_keyframes: usize,
+    mut f: impl FnMut(f64) -> (f64, f64),
+) -> Trajectory {
+    let count = num_keyframes.max(2);
+    let keyframes = (0..count)
+        .map(|i| {
+            let t = duration * i as f64 / (count - 1) as f64;
+            let (azimuth, elevation) = f(t);
+            Keyframe::new(t, azimuth, elevation)
+        })
+        .collect();
+    Trajectory::from_sorted(keyframes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::spherical_to_cartesian;
+    use crate::rng::SplitMix64;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_spiral() {
+        let spiral = Trajectory::spiral(0.0, 60.0, -90.0, 4.0, 33);
+        assert_eq!(spiral.duration(), 4.0);
+        let (azimuth, elevation) = spiral.sample(2.0);
+        assert_relative_eq!(azimuth, 180.0, epsilon = 1e-9);
+        assert_relative_eq!(elevation, 30.0, epsilon = 1e-9);
+        // Turning right
+        assert_relative_eq!(spiral.sample(0.5).0, -45.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_figure_eight() {
+        let eight = Trajectory::figure_eight(60.0, 20.0, 2.0, 2.0, 65);
+        // Crosses the front twice per period, at the start and halfway
+        for t in [0.0, 1.0, 2.0] {
+            let (azimuth, elevation) = eight.sample(t);
+            assert_relative_eq!(azimuth, 0.0, epsilon = 1e-9);
+            assert_relative_eq!(elevation, 0.0, epsilon = 1e-9);
+        }
+        assert_relative_eq!(eight.sample(0.5).0, 60.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_random_walk_steps() {
+        let walk = Trajectory::random_walk(&mut SplitMix64::new(11), 15.0, 10.0, 101);
+        let same = Trajectory::random_walk(&mut SplitMix64::new(11), 15.0, 10.0, 101);
+        assert_eq!(walk, same);
+
+        for pair in walk.keyframes().windows(2) {
+            let a = spherical_to_cartesian(pair[0].azimuth, pair[0].elevation);
+            let b = spherical_to_cartesian(pair[1].azimuth, pair[1].elevation);
+            assert_relative_eq!(a.angle_between(b).to_degrees(), 15.0, epsilon = 1e-6);
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! Source trajectories.
+//!
+//! A [`Trajectory`] is a list of keyframes (time, azimuth, elevation) that can
+//! be sampled at any time to drive a moving source, moving linearly, along
+//! great circles or on a spline between keyframes (see [`Interpolation`]).
+//! Generators such as [`Trajectory::circle`], [`Trajectory::figure_eight`]
+//! and [`Trajectory::random_walk`] build common paths.
+//! With the `render` feature, `Mixer` sources can follow trajectories.
+//! [`SplinePath`] builds smooth constant-speed paths from control points,
+//! [`Recorder`] captures live position updates for later playback,
+//! [`Motion`] implementors such as [`Orbit`] describe composable procedural
+//! movement, and [`Swarm`] flocks a group of sources. With the `scripting`
+//! feature, [`ScriptedMotion`] computes positions from user scripts.
+//! [`TimecodeChase`] slaves playback to SMPTE timecode, and with the
+//! `tempo-sync` feature `BeatSynced` locks periodic motion to a musical beat
+//! grid.
+//!
+//! Requires the `trajectory` feature.
+
+mod generate;
+mod motion;
+mod path;
+mod record;
+mod retime;
+#[cfg(feature = "scripting")]
+mod script;
+mod swarm;
+#[cfg(feature = "tempo-sync")]
+mod sync;
+mod timecode;
+
+pub use motion::{Motion, Orbit};
+pub use path::{PathKind, SplinePath};
+pub use record::Recorder;
+pub use retime::Easing;
+#[cfg(feature = "scripting")]
+pub use script::ScriptedMotion;
+pub use swarm::{Swarm, SwarmParams};
+#[cfg(feature = "tempo-sync")]
+pub use sync::{BeatClock, BeatSynced, FixedTempo};
+pub use timecode::{FrameRate, MtcDecoder, Timecode, TimecodeChase};
+
+use glam::DVec3;
+
+use crate::error::{Result, VBAPError};
+use crate::math::{self, cartesian_to_spherical, lerp_azimuth, spherical_to_cartesian};
+
+/// A source position at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Keyframe {
+    /// Time in seconds.
+    pub time: f64,
+    /// Azimuth in degrees.
+    pub azimuth: f64,
+    /// Elevation in degrees.
+    pub elevation: f64,
+}
+
+impl Keyframe {
+    /// Create a keyframe.
+    pub fn new(time: f64, azimuth: f64, elevation: f64) -> Self {
+        Self {
+            time,
+            azimuth,
+            elevation,
+        }
+    }
+}
+
+/// How a [`Trajectory`] moves between keyframes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// Azimuth and elevation change linearly. Azimuth takes the shortest way
+    /// around the circle, so moving from 170° to -170° crosses the rear
+    /// instead of sweeping through the front.
+    #[default]
+    Linear,
+    /// Constant-speed movement along the great circle between keyframes.
+    /// Unlike [`Linear`](Self::Linear), paths over the top of the sphere
+    /// cross the zenith instead of circling around it.
+    Slerp,
+    /// Catmull-Rom spline through the keyframes on the sphere, with tangents
+    /// scaled by the keyframe timing, so the velocity has no corners at
+    /// keyframes.
+    Spline,
+}
+
+/// A keyframed source movement.
+///
+/// Between keyframes the position follows the trajectory's
+/// [`Interpolation`], linear by default.
+///
+/// # Example
+///
+/// ```
+/// use vbap::trajectory::{Keyframe, Trajectory};
+///
+/// let trajectory = Trajectory::new(vec![
+///     Keyframe::new(0.0, 170.0, 0.0),
+///     Keyframe::new(1.0, -170.0, 0.0),
+/// ])
+/// .unwrap();
+///
+/// let (azimuth, _) = trajectory.sample(0.5);
+/// assert!((azimuth.abs() - 180.0).abs() < 1e-9);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trajectory {
+    /// Keyframes sorted by time.
+    keyframes: Vec<Keyframe>,
+    interpolation: Interpolation,
+}
+
+impl Trajectory {
+    /// Create a trajectory from keyframes (sorted by time if needed).
+    ///
+    /// Returns an error if there are no keyframes or a value is not finite.
+    pub fn new(mut keyframes: Vec<Keyframe>) -> Result<Self> {
+        if keyframes.is_empty() {
+            return Err(VBAPError::InvalidTrajectory(
+                "a trajectory needs at least one keyframe".into(),
+            ));
+        }
+        if let Some(k) = keyframes
+            .iter()
+            .find(|k| !(k.time.is_finite() && k.azimuth.is_finite() && k.elevation.is_finite()))
+        {
+            return Err(VBAPError::InvalidTrajectory(format!(
+                "keyframe values must be finite: {:?}",
+                k
+            )));
+        }
+
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(Self::from_sorted(keyframes))
+    }
+
+    /// Parse a trajectory from CSV text with one `time,azimuth,elevation`
+    /// row per keyframe.
+    ///
+    /// Blank lines, `#` comments and a header row are skipped. Returns
+    /// [`VBAPError::Parse`] for a malformed row.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use vbap::trajectory::Trajectory;
+    ///
+    /// let csv = "time,azimuth,elevation\n0,0,0\n2.5,90,15\n";
+    /// let trajectory = Trajectory::from_csv(csv).unwrap();
+    /// assert_eq!(trajectory.end_time(), 2.5);
+    /// ```
+    pub fn from_csv(text: &str) -> Result<Self> {
+        let mut keyframes = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            let values: Vec<f64> = fields.iter().filter_map(|f| f.parse().ok()).collect();
+            if values.is_empty() && keyframes.is_empty() {
+                // Header
+                continue;
+            }
+            if fields.len() != 3 || values.len() != 3 {
+                return Err(VBAPError::Parse(format!(
+                    "line {}: expected time,azimuth,elevation, got '{}'",
+                    index + 1,
+                    line
+                )));
+            }
+            keyframes.push(Keyframe::new(values[0], values[1], values[2]));
+        }
+        Self::new(keyframes)
+    }
+
+    /// Wrap keyframes already sorted by time, interpolating linearly.
+    fn from_sorted(keyframes: Vec<Keyframe>) -> Self {
+        Self {
+            keyframes,
+            interpolation: Interpolation::Linear,
+        }
+    }
+
+    /// Replace the keyframes (sorted by time), keeping the interpolation.
+    fn with_keyframes(&self, keyframes: Vec<Keyframe>) -> Self {
+        Self {
+            keyframes,
+            interpolation: self.interpolation,
+        }
+    }
+
+    /// Set how positions are interpolated between keyframes.
+    pub fn with_interpolation(mut self, interpolation: Interpolation) -> Self {
+        self.interpolation = interpolation;
+        self
+    }
+
+    /// Get the interpolation between keyframes.
+    #[inline]
+    pub fn interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Get the keyframes, sorted by time.
+    #[inline]
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Time of the first keyframe in seconds.
+    #[inline]
+    pub fn start_time(&self) -> f64 {
+        self.keyframes[0].time
+    }
+
+    /// Time of the last keyframe in seconds.
+    #[inline]
+    pub fn end_time(&self) -> f64 {
+        self.keyframes[self.keyframes.len() - 1].time
+    }
+
+    /// Duration between the first and last keyframe in seconds.
+    #[inline]
+    pub fn duration(&self) -> f64 {
+        self.end_time() - self.start_time()
+    }
+
+    /// Sample the (azimuth, elevation) at time `t` in seconds.
+    ///
+    /// Times before the first or after the last keyframe hold the first or
+    /// last position.
+    pub fn sample(&self, t: f64) -> (f64, f64) {
+        let keys = &self.keyframes;
+        let next = keys.partition_point(|k| k.time <= t);
+
+        if next == 0 {
+            return (keys[0].azimuth, keys[0].elevation);
+        }
+        if next == keys.len() {
+            let last = keys[keys.len() - 1];
+            return (last.azimuth, last.elevation);
+        }
+
+        match self.interpolation {
+            Interpolation::Spline => {
+                self.spline(next - 1, fraction(&keys[next - 1], &keys[next], t))
+            }
+            _ => self.between(&keys[next - 1], &keys[next], t),
+        }
+    }
+
+    /// Position at time `t` between two keyframes, ignoring the neighbors a
+    /// spline would take into account.
+    fn between(&self, a: &Keyframe, b: &Keyframe, t: f64) -> (f64, f64) {
+        let frac = fraction(a, b, t);
+        match self.interpolation {
+            Interpolation::Slerp => slerp(a, b, frac),
+            Interpolation::Linear | Interpolation::Spline => interpolate(a, b, frac),
+        }
+    }
+
+    /// Position at fraction `frac` of the cubic Hermite segment from
+    /// keyframe `i` to `i + 1`, projected onto the sphere.
+    fn spline(&self, i: usize, frac: f64) -> (f64, f64) {
+        let keys = &self.keyframes;
+        let point = |k: &Keyframe| spherical_to_cartesian(k.azimuth, k.elevation);
+        // Catmull-Rom tangent (per second) at keyframe `j`, one-sided at the ends
+        let tangent = |j: usize| {
+            let (before, after) = (
+                &keys[j.saturating_sub(1)],
+                &keys[(j + 1).min(keys.len() - 1)],
+            );
+            let span = after.time - before.time;
+            if span > 0.0 {
+                (point(after) - point(before)) / span
+            } else {
+                DVec3::ZERO
+            }
+        };
+
+        let (a, b) = (&keys[i], &keys[i + 1]);
+        let span = b.time - a.time;
+        let (s2, s3) = (frac * frac, frac * frac * frac);
+        let position = point(a) * (2.0 * s3 - 3.0 * s2 + 1.0)
+            + tangent(i) * span * (s3 - 2.0 * s2 + frac)
+            + point(b) * (3.0 * s2 - 2.0 * s3)
+            + tangent(i + 1) * span * (s3 - s2);
+        if position.length_squared() < 1e-12 {
+            return interpolate(a, b, frac);
+        }
+        cartesian_to_spherical(position)
+    }
+}
+
+/// Fraction of the way from keyframe `a` to `b` at time `t`.
+fn fraction(a: &Keyframe, b: &Keyframe, t: f64) -> f64 {
+    let span = b.time - a.time;
+    if span > 0.0 {
+        (t - a.time) / span
+    } else {
+        1.0
+    }
+}
+
+/// Position at fraction `frac` of the straight segment between two keyframes.
+fn interpolate(a: &Keyframe, b: &Keyframe, frac: f64) -> (f64, f64) {
+    let azimuth = lerp_azimuth(a.azimuth, b.azimuth, frac);
+    let elevation = a.elevation + (b.elevation - a.elevation) * frac;
+    (azimuth, elevation)
+}
+
+/// Position at fraction `frac` of the great circle arc between two keyframes.
+///
+/// Falls back to [`interpolate`] for coincident or opposite directions,
+/// where the arc is undefined.
+fn slerp(a: &Keyframe, b: &Keyframe, frac: f64) -> (f64, f64) {
+    let (p, q) = (
+        spherical_to_cartesian(a.azimuth, a.elevation),
+        spherical_to_cartesian(b.azimuth, b.elevation),
+    );
+    if p.cross(q).length() < 1e-9 {
+        return interpolate(a, b, frac);
+    }
+    cartesian_to_spherical(math::slerp(p, q, frac))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_empty_trajectory() {
+        assert!(matches!(
+            Trajectory::new(vec![]),
+            Err(VBAPError::InvalidTrajectory(_))
+        ));
+        assert!(Trajectory::new(vec![Keyframe::new(f64::NAN, 0.0, 0.0)]).is_err());
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let csv = "# fly-over\ntime, azimuth, elevation\n\n2,90,0\n0, -30, 10\n";
+        let trajectory = Trajectory::from_csv(csv).unwrap();
+        assert_eq!(
+            trajectory.keyframes(),
+            [
+                Keyframe::new(0.0, -30.0, 10.0),
+                Keyframe::new(2.0, 90.0, 0.0)
+            ]
+        );
+
+        assert!(matches!(
+            Trajectory::from_csv("0,0,0\n1,45\n"),
+            Err(VBAPError::Parse(_))
+        ));
+        assert!(Trajectory::from_csv("0,0,0\nt,a,e\n").is_err());
+        assert!(matches!(
+            Trajectory::from_csv("time,azimuth,elevation\n"),
+            Err(VBAPError::InvalidTrajectory(_))
+        ));
+    }
+
+    #[test]
+    fn test_sample_linear() {
+        let trajectory = Trajectory::new(vec![
+            Keyframe::new(1.0, 90.0, 20.0),
+            Keyframe::new(0.0, 0.0, 0.0),
+        ])
+        .unwrap();
+
+        assert_eq!(trajectory.duration(), 1.0);
+        let (azi, ele) = trajectory.sample(0.25);
+        assert_relative_eq!(azi, 22.5, epsilon = 1e-9);
+        assert_relative_eq!(ele, 5.0, epsilon = 1e-9);
+
+        // Held outside the keyframe range
+        assert_eq!(trajectory.sample(-1.0), (0.0, 0.0));
+        assert_eq!(trajectory.sample(2.0), (90.0, 20.0));
+    }
+
+    #[test]
+    fn test_sample_slerp_crosses_zenith() {
+        let keyframes = vec![
+            Keyframe::new(0.0, 0.0, 60.0),
+            Keyframe::new(1.0, 180.0, 60.0),
+        ];
+        let linear = Trajectory::new(keyframes.clone()).unwrap();
+        let slerp = linear.clone().with_interpolation(Interpolation::Slerp);
+
+        assert_relative_eq!(linear.sample(0.5).1, 60.0, epsilon = 1e-9);
+        assert_relative_eq!(slerp.sample(0.5).1, 90.0, epsilon = 1e-9);
+        // Constant angular speed: a quarter of the 60° arc
+        assert_relative_eq!(slerp.sample(0.25).1, 75.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_sample_spline() {
+        let trajectory = Trajectory::new(vec![
+            Keyframe::new(0.0, 0.0, 0.0),
+            Keyframe::new(1.0, 40.0, 0.0),
+            Keyframe::new(2.0, 80.0, 0.0),
+            Keyframe::new(3.0, 80.0, 40.0),
+        ])
+        .unwrap()
+        .with_interpolation(Interpolation::Spline);
+
+        // Passes through the keyframes
+        for k in trajectory.keyframes() {
+            let (azi, ele) = trajectory.sample(k.time);
+            assert_relative_eq!(azi, k.azimuth, epsilon = 1e-9);
+            assert_relative_eq!(ele, k.elevation, epsilon = 1e-9);
+        }
+        // Rounds the corner at the third keyframe instead of turning sharply
+        let (azi, ele) = trajectory.sample(1.9);
+        assert!(azi < 80.0 && ele < 0.0, "({}, {})", azi, ele);
+
+        // Retiming keeps the interpolation
+        assert_eq!(
+            trajectory.scaled(2.0).interpolation(),
+            Interpolation::Spline
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Composable source motion.
+
+use glam::DVec3;
+
+use super::{Keyframe, Trajectory};
+use crate::math::{cartesian_to_spherical, spherical_to_cartesian};
+
+/// Anything that yields a position over time.
+///
+/// Positions are listener-relative cartesian coordinates (x = left,
+/// y = front, z = up). Motions compose: an [`Orbit`] can circle around any
+/// other motion, including another orbit.
+pub trait Motion {
+    /// Position at time `t` in seconds.
+    fn position(&self, t: f64) -> DVec3;
+
+    /// (azimuth, elevation) as seen from the listener at time `t`.
+    fn direction(&self, t: f64) -> (f64, f64) {
+        cartesian_to_spherical(self.position(t))
+    }
+
+    /// Sample the motion into a trajectory of `num_keyframes` (at least 2)
+    /// evenly spaced keyframes from `start` to `start + duration`.
+    fn to_trajectory(&self, start: f64, duration: f64, num_keyframes: usize) -> Trajectory {
+        let count = num_keyframes.max(2);
+        let keyframes = (0..count)
+            .map(|i| {
+                let t = start + duration * i as f64 / (count - 1) as f64;
+                let (azimuth, elevation) = self.direction(t);
+                Keyframe::new(t, azimuth, elevation)
+            })
+            .collect();
+        Trajectory::from_sorted(keyframes)
+    }
+}
+
+/// A fixed position.
+impl Motion for DVec3 {
+    fn position(&self, _t: f64) -> DVec3 {
+        *self
+    }
+}
+
+/// Keyframed directions on the unit sphere.
+impl Motion for Trajectory {
+    fn position(&self, t: f64) -> DVec3 {
+        let (azimuth, elevation) = self.sample(t);
+        spherical_to_cartesian(azimuth, elevation)
+    }
+
+    fn direction(&self, t: f64) -> (f64, f64) {
+        self.sample(t)
+    }
+}
+
+impl<M: Motion + ?Sized> Motion for &M {
+    fn position(&self, t: f64) -> DVec3 {
+        (**self).position(t)
+    }
+}
+
+impl<M: Motion + ?Sized> Motion for Box<M> {
+    fn position(&self, t: f64) -> DVec3 {
+        (**self).position(t)
+    }
+}
+
+/// Circular motion around a center that may itself be moving.
+///
+/// The angle advances by `speed` degrees per second around `axis`. With
+/// the default vertical axis the orbit runs in the horizontal plane, phase 0
+/// is in front of the center, and positive speeds turn towards the left
+/// (increasing azimuth).
+///
+/// # Example
+///
+/// ```
+/// use glam::DVec3;
+/// use vbap::trajectory::{Motion, Orbit};
+///
+/// // A bee circling a source that itself circles the listener
+/// let source = Orbit::around_listener(2.0, 30.0);
+/// let bee = Orbit::new(&source, 0.2, 720.0);
+///
+/// let offset = bee.position(1.5) - source.position(1.5);
+/// assert!((offset.length() - 0.2).abs() < 1e-9);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Orbit<C> {
+    center: C,
+    radius: f64,
+    speed: f64,
+    axis: DVec3,
+    phase: f64,
+}
+
+impl Orbit<DVec3> {
+    /// Orbit the listener at `radius` with `speed` degrees per second.
+    pub fn around_listener(radius: f64, speed: f64) -> Self {
+        Self::new(DVec3::ZERO, radius, speed)
+    }
+}
+
+impl<C: Motion> Orbit<C> {
+    /// Orbit `center` at `radius` with `speed` degrees per second.
+    pub fn new(center: C, radius: f64, speed: f64) -> Self {
+        Self {
+            center,
+            radius,
+            speed,
+            axis: DVec3::Z,
+            phase: 0.0,
+        }
+    }
+
+    /// Set the rotation axis (normalized; a zero axis keeps the current one).
+    pub fn with_axis(mut self, axis: DVec3) -> Self {
+        if let Some(axis) = axis.try_normalize() {
+            self.axis = axis;
+        }
+        self
+    }
+
+    /// Set the starting angle in degrees.
+    pub fn with_phase(mut self, phase: f64) -> Self {
+        self.phase = phase;
+        self
+    }
+
+    /// Get the center motion.
+    #[inline]
+    pub fn center(&self) -> &C {
+        &self.center
+    }
+
+    /// Get the orbit radius.
+    #[inline]
+    pub fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    /// Get the angular speed in degrees per second.
+    #[inline]
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Get the (normalized) rotation axis.
+    #[inline]
+    pub fn axis(&self) -> DVec3 {
+        self.axis
+    }
+
+    /// Get the starting angle in degrees.
+    #[inline]
+    pub fn phase(&self) -> f64 {
+        self.phase
+    }
+
+    /// Time for one full revolution in seconds (infinite if not moving).
+    #[inline]
+    pub fn period(&self) -> f64 {
+        360.0 / self.speed.abs()
+    }
+}
+
+impl<C: Motion> Motion for Orbit<C> {
+    fn position(&self, t: f64) -> DVec3 {
+        // Orbit plane basis: "front" projected onto the plane, falling back to
+        // "up" when the axis points to the front.
+        let front = DVec3::Y.reject_from_normalized(self.axis);
+        let u = front
+            .try_normalize()
+            .unwrap_or_else(|| DVec3::Z.reject_from_normalized(self.axis).normalize());
+        let v = u.cross(self.axis);
+
+        let (sin, cos) = (self.phase + self.speed * t).to_radians().sin_cos();
+        self.center.position(t) + self.radius * (cos * u + sin * v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_orbit_listener_horizontal() {
+        let orbit = Orbit::around_listener(1.0, 90.0);
+
+        let (azi, ele) = orbit.direction(0.0);
+        assert_relative_eq!(azi, 0.0, epsilon = 1e-9);
+        assert_relative_eq!(ele, 0.0, epsilon = 1e-9);
+        // Positive speed turns left
+        assert_relative_eq!(orbit.direction(1.0).0, 90.0, epsilon = 1e-9);
+        assert_relative_eq!(orbit.position(0.37).length(), 1.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_orbit_axis_and_phase() {
+        // Vertical orbit through the zenith, starting overhead
+        let orbit = Orbit::around_listener(1.0, 45.0)
+            .with_axis(DVec3::X)
+            .with_phase(90.0);
+        let (_, ele) = orbit.direction(0.0);
+        assert_relative_eq!(ele.abs(), 90.0, epsilon = 1e-9);
+
+        // Axis pointing to the front falls back to an up-based plane
+        let front_axis = Orbit::around_listener(1.0, 10.0).with_axis(DVec3::Y);
+        assert!(front_axis.position(0.0).abs_diff_eq(DVec3::Z, 1e-9));
+    }
+
+    #[test]
+    fn test_nested_orbit() {
+        let source = Orbit::around_listener(3.0, 20.0);
+        let satellite = Orbit::new(&source, 0.5, 200.0).with_phase(30.0);
+
+        for t in [0.0, 0.4, 2.5] {
+            let offset = satellite.position(t) - source.position(t);
+            assert_relative_eq!(offset.length(), 0.5, epsilon = 1e-9);
+        }
+
+        let trajectory = satellite.to_trajectory(0.0, 2.0, 21);
+        assert_eq!(trajectory.keyframes().len(), 21);
+        assert_eq!(trajectory.duration(), 2.0);
+    }
+}
@@ -0,0 +1,288 @@
+//! Spline paths with arc-length parameterization.
+
+use glam::DVec3;
+
+use super::{Keyframe, Trajectory};
+use crate::error::{Result, VBAPError};
+use crate::math::{cartesian_to_spherical, spherical_to_cartesian};
+
+/// Number of arc-length table entries per spline segment.
+const SAMPLES_PER_SEGMENT: usize = 32;
+
+/// Spline interpolation type of a [`SplinePath`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathKind {
+    /// Catmull-Rom spline passing through every control point.
+    CatmullRom,
+    /// Chained cubic Bézier segments: point, handle, handle, point, ...
+    Bezier,
+}
+
+/// A spline path through control points.
+///
+/// Paths are either drawn on the unit sphere (from azimuth/elevation pairs,
+/// with every evaluated point projected back onto the sphere) or in free xyz
+/// space, where the distance from the listener varies along the path.
+///
+/// Positions are looked up by arc length, so sampling at evenly spaced
+/// fractions gives constant-speed motion regardless of how unevenly the
+/// control points are spaced.
+///
+/// # Example
+///
+/// ```
+/// use vbap::trajectory::SplinePath;
+///
+/// let path = SplinePath::catmull_rom(&[(0.0, 0.0), (20.0, 0.0), (90.0, 0.0)]).unwrap();
+/// let (azimuth, _) = path.direction_at(0.5);
+/// assert!((azimuth - 45.0).abs() < 1.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SplinePath {
+    kind: PathKind,
+    points: Vec<DVec3>,
+    on_sphere: bool,
+    /// Cumulative arc length at evenly spaced spline parameters.
+    lengths: Vec<f64>,
+}
+
+impl SplinePath {
+    /// Catmull-Rom path on the unit sphere through (azimuth, elevation) points.
+    pub fn catmull_rom(points: &[(f64, f64)]) -> Result<Self> {
+        Self::new(PathKind::CatmullRom, sphere_points(points), true)
+    }
+
+    /// Catmull-Rom path through xyz points.
+    pub fn catmull_rom_xyz(points: &[DVec3]) -> Result<Self> {
+        Self::new(PathKind::CatmullRom, points.to_vec(), false)
+    }
+
+    /// Bézier path on the unit sphere from (azimuth, elevation) control points.
+    ///
+    /// Requires `3 * n + 1` points for `n` cubic segments.
+    pub fn bezier(points: &[(f64, f64)]) -> Result<Self> {
+        Self::new(PathKind::Bezier, sphere_points(points), true)
+    }
+
+    /// Bézier path from xyz control points.
+    ///
+    /// Requires `3 * n + 1` points for `n` cubic segments.
+    pub fn bezier_xyz(points: &[DVec3]) -> Result<Self> {
+        Self::new(PathKind::Bezier, points.to_vec(), false)
+    }
+
+    fn new(kind: PathKind, points: Vec<DVec3>, on_sphere: bool) -> Result<Self> {
+        match kind {
+            PathKind::CatmullRom if points.len() < 2 => {
+                return Err(VBAPError::InvalidTrajectory(format!(
+                    "a Catmull-Rom path needs at least 2 points, got {}",
+                    points.len()
+                )));
+            }
+            PathKind::Bezier if points.len() < 4 || (points.len() - 1) % 3 != 0 => {
+                return Err(VBAPError::InvalidTrajectory(format!(
+                    "a Bézier path needs 3n+1 control points, got {}",
+                    points.len()
+                )));
+            }
+            _ => {}
+        }
+        if points.iter().any(|p| !p.is_finite()) {
+            return Err(VBAPError::InvalidTrajectory(
+                "path control points must be finite".into(),
+            ));
+        }
+
+        let mut path = Self {
+            kind,
+            points,
+            on_sphere,
+            lengths: Vec::new(),
+        };
+        path.build_length_table();
+        Ok(path)
+    }
+
+    /// Get the spline type.
+    #[inline]
+    pub fn kind(&self) -> PathKind {
+        self.kind
+    }
+
+    /// Total arc length (radians for paths on the unit sphere).
+    #[inline]
+    pub fn length(&self) -> f64 {
+        self.lengths[self.lengths.len() - 1]
+    }
+
+    /// Position at `fraction` (0 to 1) of the path's arc length.
+    pub fn position_at(&self, fraction: f64) -> DVec3 {
+        self.position_at_length(fraction.clamp(0.0, 1.0) * self.length())
+    }
+
+    /// Position at arc length `s` from the start of the path.
+    pub fn position_at_length(&self, s: f64) -> DVec3 {
+        let s = s.clamp(0.0, self.length());
+        let i = self.lengths.partition_point(|&l| l < s).max(1);
+        let (l0, l1) = (self.lengths[i - 1], self.lengths[i]);
+        let frac = if l1 > l0 { (s - l0) / (l1 - l0) } else { 0.0 };
+        self.evaluate((i - 1) as f64 + frac)
+    }
+
+    /// (azimuth, elevation) at `fraction` (0 to 1) of the path's arc length.
+    pub fn direction_at(&self, fraction: f64) -> (f64, f64) {
+        cartesian_to_spherical(self.position_at(fraction))
+    }
+
+    /// Convert to a constant-speed trajectory lasting `duration` seconds.
+    ///
+    /// The path is sampled at `num_keyframes` (at least 2) evenly spaced
+    /// arc-length positions.
+    pub fn to_trajectory(&self, duration: f64, num_keyframes: usize) -> Trajectory {
+        let count = num_keyframes.max(2);
+        let keyframes = (0..count)
+            .map(|i| {
+                let t = i as f64 / (count - 1) as f64;
+                let (azimuth, elevation) = self.direction_at(t);
+                Keyframe::new(t * duration, azimuth, elevation)
+            })
+            .collect();
+        Trajectory::from_sorted(keyframes)
+    }
+
+    fn num_segments(&self) -> usize {
+        match self.kind {
+            PathKind::CatmullRom => self.points.len() - 1,
+            PathKind::Bezier => (self.points.len() - 1) / 3,
+        }
+    }
+
+    /// Evaluate at table position `u` (in units of table samples).
+    fn evaluate(&self, u: f64) -> DVec3 {
+        let u = u / SAMPLES_PER_SEGMENT as f64;
+        let segment = (u.floor().max(0.0) as usize).min(self.num_segments() - 1);
+        let t = (u - segment as f64).clamp(0.0, 1.0);
+
+        let p = &self.points;
+        let point = match self.kind {
+            PathKind::CatmullRom => {
+                let p1 = p[segment];
+                let p2 = p[segment + 1];
+                let p0 = if segment > 0 { p[segment - 1] } else { p1 };
+                let p3 = p.get(segment + 2).copied().unwrap_or(p2);
+                catmull_rom_point(p0, p1, p2, p3, t)
+            }
+            PathKind::Bezier => {
+                let i = segment * 3;
+                bezier_point(p[i], p[i + 1], p[i + 2], p[i + 3], t)
+            }
+        };
+
+        if self.on_sphere {
+            point.normalize_or_zero()
+        } else {
+            point
+        }
+    }
+
+    fn build_length_table(&mut self) {
+        let samples = self.num_segments() * SAMPLES_PER_SEGMENT;
+        let mut lengths = Vec::with_capacity(samples + 1);
+        let mut total = 0.0;
+        let mut prev = self.evaluate(0.0);
+        lengths.push(0.0);
+
+        for i in 1..=samples {
+            let point = self.evaluate(i as f64);
+            total += if self.on_sphere {
+                prev.angle_between(point)
+            } else {
+                prev.distance(point)
+            };
+            lengths.push(total);
+            prev = point;
+        }
+        self.lengths = lengths;
+    }
+}
+
+fn sphere_points(points: &[(f64, f64)]) -> Vec<DVec3> {
+    points
+        .iter()
+        .map(|&(azi, ele)| spherical_to_cartesian(azi, ele))
+        .collect()
+}
+
+fn catmull_rom_point(p0: DVec3, p1: DVec3, p2: DVec3, p3: DVec3, t: f64) -> DVec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+fn bezier_point(p0: DVec3, p1: DVec3, p2: DVec3, p3: DVec3, t: f64) -> DVec3 {
+    let u = 1.0 - t;
+    p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_control_point_counts() {
+        assert!(SplinePath::catmull_rom(&[(0.0, 0.0)]).is_err());
+        assert!(SplinePath::bezier(&[(0.0, 0.0), (10.0, 0.0), (20.0, 0.0)]).is_err());
+        assert!(SplinePath::bezier(&[(0.0, 0.0); 4]).is_ok());
+    }
+
+    #[test]
+    fn test_catmull_rom_endpoints_and_length() {
+        let path =
+            SplinePath::catmull_rom(&[(0.0, 0.0), (30.0, 0.0), (60.0, 0.0), (90.0, 0.0)]).unwrap();
+
+        assert_relative_eq!(path.direction_at(0.0).0, 0.0, epsilon = 1e-9);
+        assert_relative_eq!(path.direction_at(1.0).0, 90.0, epsilon = 1e-9);
+        assert_relative_eq!(path.length(), std::f64::consts::FRAC_PI_2, epsilon = 1e-3);
+        // Stays on the horizon and on the unit sphere
+        assert_relative_eq!(path.position_at(0.3).length(), 1.0, epsilon = 1e-9);
+        assert_relative_eq!(path.direction_at(0.3).1, 0.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_constant_speed() {
+        // Control points bunched near the start
+        let path =
+            SplinePath::catmull_rom(&[(0.0, 0.0), (10.0, 0.0), (30.0, 0.0), (90.0, 0.0)]).unwrap();
+        let trajectory = path.to_trajectory(1.0, 11);
+
+        let keys = trajectory.keyframes();
+        let steps: Vec<f64> = keys
+            .windows(2)
+            .map(|w| {
+                spherical_to_cartesian(w[0].azimuth, w[0].elevation)
+                    .angle_between(spherical_to_cartesian(w[1].azimuth, w[1].elevation))
+            })
+            .collect();
+        for step in &steps {
+            assert_relative_eq!(*step, path.length() / 10.0, epsilon = 1e-3);
+        }
+    }
+
+    #[test]
+    fn test_bezier_xyz_distance() {
+        let path = SplinePath::bezier_xyz(&[
+            DVec3::new(0.0, 1.0, 0.0),
+            DVec3::new(0.0, 2.0, 0.0),
+            DVec3::new(0.0, 3.0, 0.0),
+            DVec3::new(0.0, 4.0, 0.0),
+        ])
+        .unwrap();
+
+        assert_relative_eq!(path.length(), 3.0, epsilon = 1e-9);
+        assert_relative_eq!(path.position_at(0.5).length(), 2.5, epsilon = 1e-9);
+    }
+}
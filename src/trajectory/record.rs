@@ -0,0 +1,203 @@
+//! Recording live position updates into trajectories.
+
+use super::{Keyframe, Trajectory};
+use crate::error::Result;
+use crate::math::great_circle_distance;
+
+/// Captures live position updates (from OSC, MIDI, a UI, ...) into a
+/// [`Trajectory`] for record-then-playback workflows.
+///
+/// Timestamps can be snapped to a grid; several updates landing on the same
+/// grid slot keep only the latest position. Updates older than the last
+/// recorded one are ignored.
+///
+/// # Example
+///
+/// ```
+/// use vbap::trajectory::Recorder;
+///
+/// let mut recorder = Recorder::new().with_quantization(0.01);
+/// for i in 0..=100 {
+///     let t = i as f64 * 0.01;
+///     recorder.record(t, t * 90.0, 0.0);
+/// }
+///
+/// // A straight sweep reduces to its two end points
+/// let trajectory = recorder.finish(0.5).unwrap();
+/// assert_eq!(trajectory.keyframes().len(), 2);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Recorder {
+    keyframes: Vec<Keyframe>,
+    /// Timestamp grid in seconds; 0 disables quantization.
+    quantum: f64,
+}
+
+impl Recorder {
+    /// Create an empty recorder without timestamp quantization.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Snap recorded timestamps to multiples of `step` seconds.
+    ///
+    /// A step of 0 (or less) disables quantization.
+    pub fn with_quantization(mut self, step: f64) -> Self {
+        self.quantum = step.max(0.0);
+        self
+    }
+
+    /// Get the timestamp grid in seconds (0 if disabled).
+    #[inline]
+    pub fn quantization(&self) -> f64 {
+        self.quantum
+    }
+
+    /// Record a position update at `time` seconds.
+    ///
+    /// Non-finite values and updates older than the last recorded one are
+    /// ignored.
+    pub fn record(&mut self, time: f64, azimuth: f64, elevation: f64) {
+        if !(time.is_finite() && azimuth.is_finite() && elevation.is_finite()) {
+            return;
+        }
+        let time = if self.quantum > 0.0 {
+            (time / self.quantum).round() * self.quantum
+        } else {
+            time
+        };
+        let keyframe = Keyframe::new(time, azimuth, elevation);
+
+        match self.keyframes.last_mut() {
+            Some(last) if time < last.time => {}
+            Some(last) if time == last.time => *last = keyframe,
+            _ => self.keyframes.push(keyframe),
+        }
+    }
+
+    /// Get the keyframes recorded so far.
+    #[inline]
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Discard everything recorded so far.
+    pub fn clear(&mut self) {
+        self.keyframes.clear();
+    }
+
+    /// Finish recording, simplifying the result with
+    /// [`Trajectory::simplified`].
+    ///
+    /// Returns an error if nothing was recorded. The recorder is left empty.
+    pub fn finish(&mut self, tolerance_deg: f64) -> Result<Trajectory> {
+        let trajectory = Trajectory::new(std::mem::take(&mut self.keyframes))?;
+        Ok(trajectory.simplified(tolerance_deg))
+    }
+}
+
+impl Trajectory {
+    /// Drop keyframes that playback can reproduce within `tolerance_deg`.
+    ///
+    /// Uses Douglas–Peucker on the sphere: a keyframe is kept if the angle
+    /// between it and the position interpolated at its time from the
+    /// surrounding kept keyframes exceeds the tolerance. Measuring at the
+    /// keyframe's own time (rather than the nearest point of the segment)
+    /// also preserves pauses and speed changes.
+    pub fn simplified(&self, tolerance_deg: f64) -> Trajectory {
+        let keys = &self.keyframes;
+        if keys.len() <= 2 {
+            return self.clone();
+        }
+
+        let tolerance = tolerance_deg.max(0.0);
+        let mut keep = vec![false; keys.len()];
+        keep[0] = true;
+        keep[keys.len() - 1] = true;
+
+        let mut stack = vec![(0, keys.len() - 1)];
+        while let Some((first, last)) = stack.pop() {
+            let mut worst = (0, tolerance);
+            for (i, key) in keys.iter().enumerate().take(last).skip(first + 1) {
+                let (azimuth, elevation) = self.between(&keys[first], &keys[last], key.time);
+                let error = great_circle_distance(azimuth, elevation, key.azimuth, key.elevation);
+                if error > worst.1 {
+                    worst = (i, error);
+                }
+            }
+
+            if worst.0 != 0 {
+                keep[worst.0] = true;
+                stack.push((first, worst.0));
+                stack.push((worst.0, last));
+            }
+        }
+
+        let keyframes = keys
+            .iter()
+            .zip(&keep)
+            .filter(|&(_, &k)| k)
+            .map(|(k, _)| *k)
+            .collect();
+        self.with_keyframes(keyframes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_quantization() {
+        let mut recorder = Recorder::new().with_quantization(0.1);
+        recorder.record(0.01, 0.0, 0.0);
+        recorder.record(0.04, 10.0, 0.0); // same slot, replaces
+        recorder.record(0.12, 20.0, 0.0);
+        recorder.record(0.05, 30.0, 0.0); // out of order, ignored
+        recorder.record(f64::NAN, 0.0, 0.0);
+
+        let keys = recorder.keyframes();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0], Keyframe::new(0.0, 10.0, 0.0));
+        assert_relative_eq!(keys[1].time, 0.1, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_finish_empty() {
+        assert!(Recorder::new().finish(1.0).is_err());
+    }
+
+    #[test]
+    fn test_simplify_keeps_corners_and_pauses() {
+        let mut recorder = Recorder::new();
+        // Sweep 0 -> 90 in 1 s, hold for 1 s, then rise to 45° elevation
+        for i in 0..=30 {
+            let t = i as f64 / 10.0;
+            let (azi, ele) = match t {
+                t if t <= 1.0 => (t * 90.0, 0.0),
+                t if t <= 2.0 => (90.0, 0.0),
+                t => (90.0, (t - 2.0) * 45.0),
+            };
+            recorder.record(t, azi, ele);
+        }
+
+        let trajectory = recorder.finish(0.1).unwrap();
+        let times: Vec<f64> = trajectory.keyframes().iter().map(|k| k.time).collect();
+        assert_eq!(times.len(), 4);
+        assert_relative_eq!(times[1], 1.0, epsilon = 1e-9);
+        assert_relative_eq!(times[2], 2.0, epsilon = 1e-9);
+        assert!(recorder.keyframes().is_empty());
+    }
+
+    #[test]
+    fn test_simplify_across_rear() {
+        let trajectory = Trajectory::new(vec![
+            Keyframe::new(0.0, 170.0, 0.0),
+            Keyframe::new(1.0, 180.0, 0.0),
+            Keyframe::new(2.0, -170.0, 0.0),
+        ])
+        .unwrap();
+        assert_eq!(trajectory.simplified(0.01).keyframes().len(), 2);
+    }
+}
@@ -0,0 +1,172 @@
+//! Retiming operations for reusing captured movements at different tempos.
+
+use super::{Keyframe, Trajectory};
+
+/// Time-remapping curve for [`Trajectory::eased`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Easing {
+    /// Constant speed.
+    #[default]
+    Linear,
+    /// Start slowly and accelerate (cubic).
+    EaseIn,
+    /// Start quickly and decelerate (cubic).
+    EaseOut,
+    /// Accelerate, then decelerate (smoothstep).
+    EaseInOut,
+}
+
+impl Easing {
+    /// Map normalized time `t` in `[0, 1]` to normalized progress in `[0, 1]`.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+impl Trajectory {
+    /// Scale the duration by `factor`, keeping the start time.
+    ///
+    /// A factor of 2 plays the movement at half speed. Non-positive factors
+    /// collapse all keyframes onto the start time.
+    pub fn scaled(&self, factor: f64) -> Trajectory {
+        let start = self.start_time();
+        let factor = factor.max(0.0);
+        self.map_times(|t| start + (t - start) * factor)
+    }
+
+    /// Stretch or squeeze the movement to last exactly `duration` seconds.
+    pub fn with_duration(&self, duration: f64) -> Trajectory {
+        let current = self.duration();
+        if current > 0.0 {
+            self.scaled(duration / current)
+        } else {
+            self.clone()
+        }
+    }
+
+    /// Play the movement backwards over the same time span.
+    pub fn reversed(&self) -> Trajectory {
+        let (start, end) = (self.start_time(), self.end_time());
+        self.map_times(|t| start + end - t)
+    }
+
+    /// Repeat the movement `count` times back to back.
+    ///
+    /// Each repetition starts where the previous one ended, so the movement
+    /// jumps back to its first position at the seams.
+    pub fn looped(&self, count: usize) -> Trajectory {
+        self.repeat(count, |_, trajectory| trajectory.clone())
+    }
+
+    /// Repeat the movement `count` times, alternating forwards and backwards.
+    pub fn ping_pong(&self, count: usize) -> Trajectory {
+        let backwards = self.reversed();
+        self.repeat(count, |i, trajectory| {
+            if i % 2 == 0 {
+                trajectory.clone()
+            } else {
+                backwards.clone()
+            }
+        })
+    }
+
+    /// Apply an easing curve to the movement's timing.
+    ///
+    /// The result is resampled to `num_keyframes` evenly spaced keyframes
+    /// (at least 2) over the original time span, so easing also works on
+    /// trajectories with only a few keyframes.
+    pub fn eased(&self, easing: Easing, num_keyframes: usize) -> Trajectory {
+        let (start, duration) = (self.start_time(), self.duration());
+        let count = num_keyframes.max(2);
+
+        let keyframes = (0..count)
+            .map(|i| {
+                let t = i as f64 / (count - 1) as f64;
+                let (azimuth, elevation) = self.sample(start + easing.apply(t) * duration);
+                Keyframe::new(start + t * duration, azimuth, elevation)
+            })
+            .collect();
+        self.with_keyframes(keyframes)
+    }
+
+    fn map_times(&self, f: impl Fn(f64) -> f64) -> Trajectory {
+        let mut keyframes: Vec<Keyframe> = self
+            .keyframes
+            .iter()
+            .map(|k| Keyframe::new(f(k.time), k.azimuth, k.elevation))
+            .collect();
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        self.with_keyframes(keyframes)
+    }
+
+    fn repeat(&self, count: usize, pass: impl Fn(usize, &Trajectory) -> Trajectory) -> Trajectory {
+        let duration = self.duration();
+        let keyframes = (0..count.max(1))
+            .flat_map(|i| {
+                let offset = i as f64 * duration;
+                pass(i, self)
+                    .keyframes
+                    .into_iter()
+                    .map(move |k| Keyframe::new(k.time + offset, k.azimuth, k.elevation))
+            })
+            .collect();
+        self.with_keyframes(keyframes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn sweep() -> Trajectory {
+        Trajectory::new(vec![
+            Keyframe::new(0.0, 0.0, 0.0),
+            Keyframe::new(2.0, 90.0, 0.0),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_scaled_and_with_duration() {
+        let slow = sweep().scaled(2.0);
+        assert_eq!(slow.duration(), 4.0);
+        assert_relative_eq!(slow.sample(2.0).0, 45.0, epsilon = 1e-9);
+
+        assert_eq!(sweep().with_duration(1.0).duration(), 1.0);
+    }
+
+    #[test]
+    fn test_reversed() {
+        let back = sweep().reversed();
+        assert_eq!(back.sample(0.0).0, 90.0);
+        assert_eq!(back.sample(2.0).0, 0.0);
+    }
+
+    #[test]
+    fn test_loop_and_ping_pong() {
+        let looped = sweep().looped(3);
+        assert_eq!(looped.duration(), 6.0);
+        assert_relative_eq!(looped.sample(3.0).0, 45.0, epsilon = 1e-9);
+
+        let ping_pong = sweep().ping_pong(2);
+        assert_eq!(ping_pong.duration(), 4.0);
+        assert_relative_eq!(ping_pong.sample(3.0).0, 45.0, epsilon = 1e-9);
+        assert_relative_eq!(ping_pong.sample(4.0).0, 0.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_eased() {
+        let eased = sweep().eased(Easing::EaseIn, 33);
+        assert_eq!(eased.duration(), 2.0);
+        // Halfway through time, an ease-in has covered 1/8 of the distance
+        assert_relative_eq!(eased.sample(1.0).0, 90.0 / 8.0, epsilon = 1e-9);
+        assert_relative_eq!(eased.sample(2.0).0, 90.0, epsilon = 1e-9);
+    }
+}
@@ -0,0 +1,192 @@
+//! User scripts for custom source motion.
+
+use glam::DVec3;
+use rhai::{CallFnOptions, Dynamic, Engine, Scope, AST};
+
+use crate::error::{Result, VBAPError};
+use crate::math::{cartesian_to_spherical, spherical_to_cartesian};
+
+/// Operation budget per script call, so a runaway loop cannot stall the host.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// Source positions computed by a sandboxed [Rhai](https://rhai.rs) script.
+///
+/// The script must define `fn position(t, index)` returning
+/// `[azimuth, elevation]` or `[azimuth, elevation, distance]` for source
+/// `index` at time `t` seconds. Top-level statements run once when the
+/// script is loaded; the variables they define are visible to `position`.
+///
+/// Scripts have no file, network or process access, `eval` is disabled,
+/// and each call is bounded in operations, call depth and data size.
+///
+/// Requires the `scripting` feature.
+///
+/// # Example
+///
+/// ```
+/// use vbap::trajectory::ScriptedMotion;
+///
+/// let mut motion = ScriptedMotion::new(
+///     r#"
+///     let speed = 90.0;
+///     fn position(t, index) {
+///         [speed * t + 180.0 * index, 10.0]
+///     }
+///     "#,
+/// )
+/// .unwrap();
+///
+/// let mut directions = [(0.0, 0.0); 2];
+/// motion.directions_into(1.0, &mut directions).unwrap();
+/// let (azimuth, elevation) = directions[1];
+/// assert!((azimuth + 90.0).abs() < 1e-9 && (elevation - 10.0).abs() < 1e-9);
+/// ```
+pub struct ScriptedMotion {
+    engine: Engine,
+    ast: AST,
+    scope: Scope<'static>,
+}
+
+impl ScriptedMotion {
+    /// Compile a script and run its top-level statements.
+    ///
+    /// Returns an error if the script does not parse, its top-level
+    /// statements fail, or it does not define `position(t, index)`.
+    pub fn new(script: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(4096)
+            .set_max_array_size(1024)
+            .set_max_map_size(256)
+            .disable_symbol("eval");
+
+        let ast = engine.compile(script).map_err(script_error)?;
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == "position" && f.params.len() == 2)
+        {
+            return Err(VBAPError::Script(
+                "script must define fn position(t, index)".into(),
+            ));
+        }
+
+        let mut scope = Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(script_error)?;
+
+        Ok(Self { engine, ast, scope })
+    }
+
+    /// Listener-relative cartesian position of source `index` at time `t`.
+    pub fn position(&mut self, t: f64, index: usize) -> Result<DVec3> {
+        let result: Dynamic = self
+            .engine
+            .call_fn_with_options(
+                CallFnOptions::new().eval_ast(false),
+                &mut self.scope,
+                &self.ast,
+                "position",
+                (t, index as rhai::INT),
+            )
+            .map_err(script_error)?;
+
+        let values = result
+            .into_array()
+            .map_err(|ty| VBAPError::Script(format!("position returned {}, not an array", ty)))?
+            .into_iter()
+            .map(|v| {
+                v.as_float()
+                    .or_else(|_| v.as_int().map(|i| i as f64))
+                    .map_err(|ty| VBAPError::Script(format!("position returned a {}", ty)))
+            })
+            .collect::<Result<Vec<f64>>>()?;
+
+        let (azimuth, elevation, distance) = match values[..] {
+            [azimuth, elevation] => (azimuth, elevation, 1.0),
+            [azimuth, elevation, distance] => (azimuth, elevation, distance),
+            _ => {
+                return Err(VBAPError::Script(format!(
+                    "position must return 2 or 3 numbers, got {}",
+                    values.len()
+                )))
+            }
+        };
+        if !(azimuth.is_finite() && elevation.is_finite() && distance.is_finite()) {
+            return Err(VBAPError::Script(
+                "position returned a non-finite value".into(),
+            ));
+        }
+
+        Ok(spherical_to_cartesian(azimuth, elevation) * distance)
+    }
+
+    /// (azimuth, elevation) of source `index` at time `t`.
+    pub fn direction(&mut self, t: f64, index: usize) -> Result<(f64, f64)> {
+        Ok(cartesian_to_spherical(self.position(t, index)?))
+    }
+
+    /// Compute the directions of sources `0..out.len()` for one control tick.
+    ///
+    /// The output can be passed straight to
+    /// [`VBAPanner::compute_gains_batch`](crate::VBAPanner::compute_gains_batch).
+    pub fn directions_into(&mut self, t: f64, out: &mut [(f64, f64)]) -> Result<()> {
+        for (index, direction) in out.iter_mut().enumerate() {
+            *direction = self.direction(t, index)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ScriptedMotion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ScriptedMotion").finish_non_exhaustive()
+    }
+}
+
+fn script_error(err: impl std::fmt::Display) -> VBAPError {
+    VBAPError::Script(err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_missing_position_fn() {
+        assert!(matches!(
+            ScriptedMotion::new("fn other(t) { [0.0, 0.0] }"),
+            Err(VBAPError::Script(_))
+        ));
+        assert!(ScriptedMotion::new("fn position(t, i) {").is_err());
+    }
+
+    #[test]
+    fn test_distance_and_int_values() {
+        let mut motion = ScriptedMotion::new("fn position(t, i) { [90, 0, 2.0] }").unwrap();
+        let position = motion.position(0.0, 0).unwrap();
+        assert_relative_eq!(position.x, 2.0, epsilon = 1e-12);
+        assert_relative_eq!(position.length(), 2.0, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_bad_return_value() {
+        let mut motion = ScriptedMotion::new(r#"fn position(t, i) { "left" }"#).unwrap();
+        assert!(motion.direction(0.0, 0).is_err());
+
+        let mut motion = ScriptedMotion::new("fn position(t, i) { [1.0] }").unwrap();
+        assert!(motion.direction(0.0, 0).is_err());
+    }
+
+    #[test]
+    fn test_sandbox_limits() {
+        let mut runaway = ScriptedMotion::new("fn position(t, i) { loop {} }").unwrap();
+        assert!(runaway.direction(0.0, 0).is_err());
+
+        assert!(ScriptedMotion::new(r#"eval("1"); fn position(t, i) { [0, 0] }"#).is_err());
+    }
+}
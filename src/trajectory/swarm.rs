@@ -0,0 +1,313 @@
+//! Boids-style flocking of a group of sources.
+
+use glam::DVec3;
+
+use crate::math::{cartesian_to_spherical, spherical_to_cartesian};
+use crate::rng::{Rng, SplitMix64};
+
+/// Steering weights and limits for a [`Swarm`].
+///
+/// Angles are in degrees and speeds in degrees per second along the sphere.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SwarmParams {
+    /// Agents closer than this steer away from each other.
+    pub separation_radius: f64,
+    /// Agents within this angle count as neighbors.
+    pub neighbor_radius: f64,
+    /// Weight of steering away from close neighbors.
+    pub separation: f64,
+    /// Weight of matching the neighbors' heading.
+    pub alignment: f64,
+    /// Weight of moving towards the neighbors' center.
+    pub cohesion: f64,
+    /// Amount of random steering.
+    pub wander: f64,
+    /// Maximum agent speed.
+    pub max_speed: f64,
+}
+
+impl Default for SwarmParams {
+    fn default() -> Self {
+        Self {
+            separation_radius: 10.0,
+            neighbor_radius: 40.0,
+            separation: 1.5,
+            alignment: 1.0,
+            cohesion: 1.0,
+            wander: 0.5,
+            max_speed: 60.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Agent {
+    position: DVec3,
+    /// Tangent velocity in radians per second.
+    velocity: DVec3,
+}
+
+/// Coordinated motion of a group of sources on the sphere.
+///
+/// Each agent follows the classic boids rules (separation, alignment,
+/// cohesion) plus some random wander, and is steered back whenever it
+/// leaves the bounding cap. Agent directions are kept in a slice ready for
+/// [`VBAPanner::compute_gains_batch`](crate::VBAPanner::compute_gains_batch).
+///
+/// The wander and scattering draw from `R`, a [`SplitMix64`] seeded by
+/// [`new`](Swarm::new) unless another generator is passed to
+/// [`with_rng`](Swarm::with_rng).
+///
+/// # Example
+///
+/// ```
+/// use vbap::trajectory::Swarm;
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+/// let mut swarm = Swarm::new(16, 42).with_bounds(0.0, 30.0, 60.0);
+/// let mut gains = vec![0.0; swarm.len() * panner.num_speakers()];
+///
+/// for _ in 0..100 {
+///     swarm.step(0.01);
+///     panner.compute_gains_batch(swarm.directions(), &mut gains);
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Swarm<R = SplitMix64> {
+    agents: Vec<Agent>,
+    directions: Vec<(f64, f64)>,
+    params: SwarmParams,
+    /// Center and angular radius (radians) of the bounding cap.
+    bounds: (DVec3, f64),
+    rng: R,
+}
+
+impl Swarm {
+    /// Create `num_agents` agents scattered over the whole sphere.
+    ///
+    /// The same `seed` always produces the same motion.
+    pub fn new(num_agents: usize, seed: u64) -> Self {
+        Self::with_rng(num_agents, SplitMix64::new(seed))
+    }
+}
+
+impl<R: Rng> Swarm<R> {
+    /// Create `num_agents` agents scattered over the whole sphere, drawing
+    /// random numbers from `rng`.
+    pub fn with_rng(num_agents: usize, rng: R) -> Self {
+        let mut swarm = Self {
+            agents: vec![
+                Agent {
+                    position: DVec3::Y,
+                    velocity: DVec3::ZERO,
+                };
+                num_agents
+            ],
+            directions: vec![(0.0, 0.0); num_agents],
+            params: SwarmParams::default(),
+            bounds: (DVec3::Y, std::f64::consts::PI),
+            rng,
+        };
+        swarm.scatter();
+        swarm
+    }
+
+    /// Set the steering parameters.
+    pub fn with_params(mut self, params: SwarmParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Keep agents within `radius` degrees of (`azimuth`, `elevation`).
+    ///
+    /// Agents are re-scattered inside the new bounds.
+    pub fn with_bounds(mut self, azimuth: f64, elevation: f64, radius: f64) -> Self {
+        self.bounds = (
+            spherical_to_cartesian(azimuth, elevation),
+            radius.clamp(0.0, 180.0).to_radians(),
+        );
+        self.scatter();
+        self
+    }
+
+    /// Get the steering parameters.
+    #[inline]
+    pub fn params(&self) -> &SwarmParams {
+        &self.params
+    }
+
+    /// Get the number of agents.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.agents.len()
+    }
+
+    /// Check whether the swarm has no agents.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /// Get the (azimuth, elevation) of every agent.
+    #[inline]
+    pub fn directions(&self) -> &[(f64, f64)] {
+        &self.directions
+    }
+
+    /// Advance the simulation by `dt` seconds.
+    pub fn step(&mut self, dt: f64) {
+        let p = self.params;
+        let neighbor_cos = p.neighbor_radius.to_radians().cos();
+        let separation_cos = p.separation_radius.to_radians().cos();
+        let max_speed = p.max_speed.to_radians();
+        let (center, radius) = self.bounds;
+
+        let mut accelerations = Vec::with_capacity(self.agents.len());
+        for (i, agent) in self.agents.iter().enumerate() {
+            let mut separation = DVec3::ZERO;
+            let mut heading = DVec3::ZERO;
+            let mut centroid = DVec3::ZERO;
+            let mut neighbors = 0;
+
+            for (j, other) in self.agents.iter().enumerate() {
+                let cos = agent.position.dot(other.position);
+                if i == j || cos < neighbor_cos {
+                    continue;
+                }
+                neighbors += 1;
+                heading += other.velocity;
+                centroid += other.position;
+                if cos > separation_cos {
+                    let away = agent.position - other.position;
+                    separation += away / away.length_squared().max(1e-6);
+                }
+            }
+
+            let mut acceleration = separation * p.separation * max_speed * 0.01;
+            if neighbors > 0 {
+                let n = neighbors as f64;
+                acceleration += (heading / n - agent.velocity) * p.alignment;
+                acceleration += (centroid / n - agent.position) * p.cohesion * max_speed;
+            }
+
+            let jitter = DVec3::new(
+                self.rng.range(-1.0, 1.0),
+                self.rng.range(-1.0, 1.0),
+                self.rng.range(-1.0, 1.0),
+            );
+            acceleration += jitter * p.wander * max_speed;
+
+            // Steer back into the bounding cap
+            let outside = agent.position.angle_between(center) - radius;
+            if outside > 0.0 {
+                acceleration += (center - agent.position) * (outside + 0.1) * max_speed * 10.0;
+            }
+
+            accelerations.push(acceleration);
+        }
+
+        for ((agent, acceleration), direction) in self
+            .agents
+            .iter_mut()
+            .zip(accelerations)
+            .zip(&mut self.directions)
+        {
+            let mut velocity = tangent(agent.position, agent.velocity + acceleration * dt);
+            let speed = velocity.length();
+            if speed > max_speed {
+                velocity *= max_speed / speed;
+            }
+
+            let position = (agent.position + velocity * dt)
+                .try_normalize()
+                .unwrap_or(agent.position);
+            agent.velocity = tangent(position, velocity);
+            agent.position = position;
+            *direction = cartesian_to_spherical(position);
+        }
+    }
+
+    /// Place all agents uniformly at random inside the bounds.
+    fn scatter(&mut self) {
+        let (center, radius) = self.bounds;
+        let u = center.any_orthonormal_vector();
+        let v = center.cross(u);
+        let min_cos = radius.cos();
+
+        for (agent, direction) in self.agents.iter_mut().zip(&mut self.directions) {
+            let cos = self.rng.range(min_cos, 1.0);
+            let sin = (1.0 - cos * cos).max(0.0).sqrt();
+            let (s, c) = self.rng.range(0.0, std::f64::consts::TAU).sin_cos();
+            agent.position = (center * cos + (u * c + v * s) * sin).normalize();
+            agent.velocity = DVec3::ZERO;
+            *direction = cartesian_to_spherical(agent.position);
+        }
+    }
+}
+
+/// Component of `v` tangent to the unit sphere at `position`.
+fn tangent(position: DVec3, v: DVec3) -> DVec3 {
+    v - position * v.dot(position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        let mut a = Swarm::new(8, 1);
+        let mut b = Swarm::new(8, 1);
+        for _ in 0..50 {
+            a.step(0.02);
+            b.step(0.02);
+        }
+        assert_eq!(a.directions(), b.directions());
+        assert_ne!(a.directions(), Swarm::new(8, 2).directions());
+
+        let mut shared = SplitMix64::new(1);
+        let mut c = Swarm::with_rng(8, &mut shared);
+        for _ in 0..50 {
+            c.step(0.02);
+        }
+        assert_eq!(a.directions(), c.directions());
+    }
+
+    #[test]
+    fn test_stays_in_bounds() {
+        let mut swarm = Swarm::new(12, 3).with_bounds(90.0, 20.0, 30.0);
+        let center = spherical_to_cartesian(90.0, 20.0);
+
+        for _ in 0..2000 {
+            swarm.step(0.01);
+            for &(azi, ele) in swarm.directions() {
+                let angle = spherical_to_cartesian(azi, ele)
+                    .angle_between(center)
+                    .to_degrees();
+                assert!(angle < 40.0, "agent strayed {} degrees", angle);
+            }
+        }
+    }
+
+    #[test]
+    fn test_agents_move_and_separate() {
+        let mut swarm = Swarm::new(10, 5).with_bounds(0.0, 0.0, 45.0);
+        let start = swarm.directions().to_vec();
+        for _ in 0..200 {
+            swarm.step(0.01);
+        }
+        assert_ne!(swarm.directions(), &start[..]);
+
+        let positions: Vec<DVec3> = swarm
+            .directions()
+            .iter()
+            .map(|&(azi, ele)| spherical_to_cartesian(azi, ele))
+            .collect();
+        for (i, a) in positions.iter().enumerate() {
+            for b in &positions[i + 1..] {
+                assert!(a.angle_between(*b) > 1e-3);
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Tempo-synced periodic motion for live-electronics setups.
+//!
+//! Periodic motions (orbits and other loops) can be locked to a musical
+//! beat grid such as an Ableton Link session. The crate includes no Link
+//! binding, which is why the feature is `tempo-sync` rather than `link`:
+//! hosts wrap their Link binding's session state in a [`BeatClock`] (its
+//! `beat_at_time` is exactly what is needed), so the motion follows the
+//! session's tempo and phase. [`FixedTempo`] covers offline rendering.
+//!
+//! Requires the `tempo-sync` feature.
+
+use glam::DVec3;
+
+use super::Motion;
+
+/// A source of musical time.
+pub trait BeatClock {
+    /// Beat position at host time `t` seconds.
+    fn beat_at(&self, t: f64) -> f64;
+}
+
+impl<C: BeatClock + ?Sized> BeatClock for &C {
+    fn beat_at(&self, t: f64) -> f64 {
+        (**self).beat_at(t)
+    }
+}
+
+/// A constant tempo, for offline rendering or when no session is running.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedTempo {
+    /// Tempo in beats per minute.
+    pub bpm: f64,
+    /// Beat position at time 0.
+    pub beat_at_zero: f64,
+}
+
+impl FixedTempo {
+    /// Create a clock at `bpm` with beat 0 at time 0.
+    pub fn new(bpm: f64) -> Self {
+        Self {
+            bpm,
+            beat_at_zero: 0.0,
+        }
+    }
+}
+
+impl BeatClock for FixedTempo {
+    fn beat_at(&self, t: f64) -> f64 {
+        self.beat_at_zero + t * self.bpm / 60.0
+    }
+}
+
+/// A periodic motion whose cycle is locked to the beat grid.
+///
+/// One cycle of `motion` (lasting `cycle` seconds in its own time) is
+/// stretched over `beats` beats, starting on multiples of `beats`. Tempo
+/// changes and phase jumps of the clock are followed immediately. A cycle
+/// that is zero, negative or not finite, such as the
+/// [`period`](super::Orbit::period) of an orbit with speed 0, holds the
+/// motion at its start.
+///
+/// Requires the `tempo-sync` feature.
+///
+/// # Example
+///
+/// ```
+/// use vbap::trajectory::{BeatSynced, FixedTempo, Motion, Orbit};
+///
+/// // One full circle per 4/4 bar at 120 BPM (2 seconds)
+/// let orbit = Orbit::around_listener(1.0, 90.0);
+/// let synced = BeatSynced::new(orbit.clone(), orbit.period(), FixedTempo::new(120.0), 4.0);
+///
+/// let (azimuth, _) = synced.direction(0.5); // beat 1 of the bar
+/// assert!((azimuth - 90.0).abs() < 1e-9);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BeatSynced<M, C> {
+    motion: M,
+    cycle: f64,
+    clock: C,
+    beats: f64,
+}
+
+impl<M: Motion, C: BeatClock> BeatSynced<M, C> {
+    /// Lock a `motion` with a `cycle`-second period to `beats` beats of `clock`.
+    pub fn new(motion: M, cycle: f64, clock: C, beats: f64) -> Self {
+        let cycle = if cycle.is_finite() && cycle > 0.0 {
+            cycle
+        } else {
+            0.0
+        };
+        Self {
+            motion,
+            cycle,
+            clock,
+            beats,
+        }
+    }
+
+    /// Get the beat clock.
+    #[inline]
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Get the beat clock for updating (e.g. a new tempo).
+    #[inline]
+    pub fn clock_mut(&mut self) -> &mut C {
+        &mut self.clock
+    }
+
+    /// Get the number of beats per motion cycle.
+    #[inline]
+    pub fn beats(&self) -> f64 {
+        self.beats
+    }
+
+    /// Phase within the current cycle at time `t`, from 0 to 1.
+    pub fn phase(&self, t: f64) -> f64 {
+        if self.beats > 0.0 {
+            (self.clock.beat_at(t) / self.beats).rem_euclid(1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl<M: Motion, C: BeatClock> Motion for BeatSynced<M, C> {
+    fn position(&self, t: f64) -> DVec3 {
+        self.motion.position(self.phase(t) * self.cycle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectory::Orbit;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_follows_tempo_change() {
+        let orbit = Orbit::around_listener(1.0, 45.0);
+        let mut synced = BeatSynced::new(orbit.clone(), orbit.period(), FixedTempo::new(60.0), 4.0);
+
+        // Half a bar at 60 BPM: half a circle
+        assert_relative_eq!(synced.direction(2.0).0.abs(), 180.0, epsilon = 1e-9);
+
+        synced.clock_mut().bpm = 120.0;
+        // Bar lines repeat
+        assert_relative_eq!(synced.phase(2.0), 0.0, epsilon = 1e-12);
+        assert_relative_eq!(synced.direction(0.5).0, 90.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_clock_phase_offset() {
+        let clock = FixedTempo {
+            bpm: 120.0,
+            beat_at_zero: 3.0,
+        };
+        let synced = BeatSynced::new(DVec3::Y, 1.0, clock, 4.0);
+        assert_relative_eq!(synced.phase(0.0), 0.75, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_stationary_orbit() {
+        let orbit = Orbit::around_listener(1.0, 0.0);
+        assert!(orbit.period().is_infinite());
+        let synced = BeatSynced::new(orbit.clone(), orbit.period(), FixedTempo::new(120.0), 4.0);
+        for t in [0.0, 0.7, 3.0] {
+            let (azimuth, elevation) = synced.direction(t);
+            assert!(azimuth.is_finite() && elevation.is_finite());
+            assert!(synced.position(t).abs_diff_eq(orbit.position(0.0), 1e-12));
+        }
+        let zero = BeatSynced::new(orbit.clone(), 0.0, FixedTempo::new(120.0), 4.0);
+        assert!(zero.position(1.3).is_finite());
+    }
+}
@@ -0,0 +1,356 @@
+//! SMPTE timecode (LTC/MTC) chasing for trajectory playback.
+//!
+//! The playback system owns the clock: [`MtcDecoder`] assembles MIDI time
+//! code, and [`TimecodeChase`] maps incoming timecode (from MTC or an
+//! external LTC reader) to a trajectory time.
+
+use std::fmt;
+
+use crate::error::{Result, VBAPError};
+
+/// SMPTE frame rate, as encoded in MIDI time code.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameRate {
+    /// 24 fps (film).
+    Fps24,
+    /// 25 fps (PAL).
+    #[default]
+    Fps25,
+    /// 29.97 fps drop-frame (NTSC).
+    Fps2997Drop,
+    /// 30 fps non-drop.
+    Fps30,
+}
+
+impl FrameRate {
+    /// Frames per second.
+    pub fn fps(self) -> f64 {
+        match self {
+            FrameRate::Fps24 => 24.0,
+            FrameRate::Fps25 => 25.0,
+            FrameRate::Fps2997Drop => 30000.0 / 1001.0,
+            FrameRate::Fps30 => 30.0,
+        }
+    }
+
+    /// Frame labels per second (30 for drop-frame).
+    fn nominal(self) -> u32 {
+        match self {
+            FrameRate::Fps24 => 24,
+            FrameRate::Fps25 => 25,
+            FrameRate::Fps2997Drop | FrameRate::Fps30 => 30,
+        }
+    }
+
+    /// Rate from the 2-bit MTC rate code.
+    fn from_mtc(code: u8) -> Self {
+        match code & 0x03 {
+            0 => FrameRate::Fps24,
+            1 => FrameRate::Fps25,
+            2 => FrameRate::Fps2997Drop,
+            _ => FrameRate::Fps30,
+        }
+    }
+}
+
+/// A SMPTE timecode address.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timecode {
+    /// Hours (0–23).
+    pub hours: u8,
+    /// Minutes (0–59).
+    pub minutes: u8,
+    /// Seconds (0–59).
+    pub seconds: u8,
+    /// Frames (0 to frame rate - 1).
+    pub frames: u8,
+    /// Frame rate.
+    pub rate: FrameRate,
+}
+
+impl Timecode {
+    /// Create a timecode, validating each field.
+    pub fn new(hours: u8, minutes: u8, seconds: u8, frames: u8, rate: FrameRate) -> Result<Self> {
+        let invalid = |what: &str| {
+            Err(VBAPError::InvalidConfiguration(format!(
+                "invalid timecode {:02}:{:02}:{:02}:{:02}: {}",
+                hours, minutes, seconds, frames, what
+            )))
+        };
+        if hours > 23 || minutes > 59 || seconds > 59 {
+            return invalid("field out of range");
+        }
+        if u32::from(frames) >= rate.nominal() {
+            return invalid("frame out of range");
+        }
+        // Drop-frame skips frames 0 and 1 at the start of most minutes
+        if rate == FrameRate::Fps2997Drop && seconds == 0 && frames < 2 && minutes % 10 != 0 {
+            return invalid("dropped frame");
+        }
+        Ok(Self {
+            hours,
+            minutes,
+            seconds,
+            frames,
+            rate,
+        })
+    }
+
+    /// Parse `HH:MM:SS:FF` (or `HH:MM:SS;FF` for drop-frame).
+    pub fn parse(text: &str, rate: FrameRate) -> Result<Self> {
+        let fields: Vec<&str> = text.trim().split([':', ';']).collect();
+        let parsed: Option<Vec<u8>> = fields.iter().map(|f| f.parse().ok()).collect();
+        match parsed.as_deref() {
+            Some(&[h, m, s, f]) => Self::new(h, m, s, f, rate),
+            _ => Err(VBAPError::InvalidConfiguration(format!(
+                "cannot parse timecode {:?}",
+                text
+            ))),
+        }
+    }
+
+    /// Frame count since 00:00:00:00.
+    pub fn frame_number(&self) -> u64 {
+        let nominal = u64::from(self.rate.nominal());
+        let total_minutes = 60 * u64::from(self.hours) + u64::from(self.minutes);
+        let labels =
+            (total_minutes * 60 + u64::from(self.seconds)) * nominal + u64::from(self.frames);
+        match self.rate {
+            FrameRate::Fps2997Drop => labels - 2 * (total_minutes - total_minutes / 10),
+            _ => labels,
+        }
+    }
+
+    /// Wall-clock seconds since 00:00:00:00.
+    pub fn to_seconds(&self) -> f64 {
+        self.frame_number() as f64 / self.rate.fps()
+    }
+
+    /// The timecode of the frame containing `seconds` (wrapping at 24 h).
+    pub fn from_seconds(seconds: f64, rate: FrameRate) -> Self {
+        let mut frame = (seconds.max(0.0) * rate.fps() + 1e-6).floor() as u64;
+        if rate == FrameRate::Fps2997Drop {
+            // Re-insert the dropped frame labels
+            let (tens, rest) = (frame / 17982, frame % 17982);
+            frame += 18 * tens + if rest < 2 { 0 } else { 2 * ((rest - 2) / 1798) };
+        }
+
+        let nominal = u64::from(rate.nominal());
+        Self {
+            hours: ((frame / (nominal * 3600)) % 24) as u8,
+            minutes: ((frame / (nominal * 60)) % 60) as u8,
+            seconds: ((frame / nominal) % 60) as u8,
+            frames: (frame % nominal) as u8,
+            rate,
+        }
+    }
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = if self.rate == FrameRate::Fps2997Drop {
+            ';'
+        } else {
+            ':'
+        };
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours, self.minutes, self.seconds, separator, self.frames
+        )
+    }
+}
+
+/// Assembles MIDI time code from quarter-frame and full-frame messages.
+#[derive(Clone, Debug, Default)]
+pub struct MtcDecoder {
+    pieces: [u8; 8],
+    /// Bit set of the pieces received since the last complete timecode.
+    received: u8,
+}
+
+impl MtcDecoder {
+    /// Create a decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed the data byte of a quarter-frame message (status `0xF1`).
+    ///
+    /// Returns the current timecode once all eight pieces have arrived. The
+    /// two frames the pieces took to transmit are compensated for.
+    pub fn quarter_frame(&mut self, data: u8) -> Option<Timecode> {
+        let piece = usize::from((data >> 4) & 0x07);
+        self.pieces[piece] = data & 0x0F;
+        self.received |= 1 << piece;
+
+        if piece != 7 || self.received != 0xFF {
+            return None;
+        }
+        self.received = 0;
+
+        let p = &self.pieces;
+        let rate = FrameRate::from_mtc(p[7] >> 1);
+        let timecode = Timecode {
+            frames: p[0] | (p[1] & 0x01) << 4,
+            seconds: p[2] | (p[3] & 0x03) << 4,
+            minutes: p[4] | (p[5] & 0x03) << 4,
+            hours: p[6] | (p[7] & 0x01) << 4,
+            rate,
+        };
+        Some(Timecode::from_seconds(
+            (timecode.frame_number() + 2) as f64 / rate.fps(),
+            rate,
+        ))
+    }
+
+    /// Decode a full-frame SysEx message (`F0 7F <dev> 01 01 hh mm ss ff F7`).
+    ///
+    /// Also resets quarter-frame assembly, as sent after a locate.
+    pub fn full_frame(&mut self, sysex: &[u8]) -> Option<Timecode> {
+        match *sysex {
+            [0xF0, 0x7F, _, 0x01, 0x01, hr, mn, sc, fr, 0xF7] => {
+                self.received = 0;
+                Timecode::new(
+                    hr & 0x1F,
+                    mn & 0x3F,
+                    sc & 0x3F,
+                    fr & 0x1F,
+                    FrameRate::from_mtc(hr >> 5),
+                )
+                .ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Chases external timecode to drive trajectory playback.
+///
+/// Trajectory time 0 corresponds to the `start` timecode. Between timecode
+/// updates (and through short dropouts) playback freewheels; once no
+/// timecode has arrived for longer than the freewheel time, playback stops.
+///
+/// # Example
+///
+/// ```
+/// use vbap::trajectory::{FrameRate, Keyframe, Timecode, TimecodeChase, Trajectory};
+///
+/// let cue = Trajectory::new(vec![
+///     Keyframe::new(0.0, 0.0, 0.0),
+///     Keyframe::new(10.0, 90.0, 0.0),
+/// ])
+/// .unwrap();
+///
+/// let start = Timecode::parse("01:00:00:00", FrameRate::Fps25).unwrap();
+/// let mut chase = TimecodeChase::new(start);
+///
+/// let t = chase.update(Timecode::parse("01:00:05:00", FrameRate::Fps25).unwrap());
+/// assert_eq!(cue.sample(t).0, 45.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct TimecodeChase {
+    start: f64,
+    freewheel: f64,
+    position: Option<f64>,
+    since_update: f64,
+}
+
+impl TimecodeChase {
+    /// Chase timecode, with trajectory time 0 at `start`.
+    pub fn new(start: Timecode) -> Self {
+        Self {
+            start: start.to_seconds(),
+            freewheel: 0.5,
+            position: None,
+            since_update: 0.0,
+        }
+    }
+
+    /// Set how long playback continues without timecode (default 0.5 s).
+    pub fn with_freewheel(mut self, seconds: f64) -> Self {
+        self.freewheel = seconds.max(0.0);
+        self
+    }
+
+    /// Locate to a received timecode; returns the trajectory time.
+    pub fn update(&mut self, timecode: Timecode) -> f64 {
+        let position = timecode.to_seconds() - self.start;
+        self.position = Some(position);
+        self.since_update = 0.0;
+        position
+    }
+
+    /// Advance by `dt` seconds without new timecode.
+    ///
+    /// Returns the extrapolated trajectory time, or `None` once stopped.
+    pub fn advance(&mut self, dt: f64) -> Option<f64> {
+        self.since_update += dt;
+        if self.since_update > self.freewheel {
+            self.position = None;
+        }
+        if let Some(position) = &mut self.position {
+            *position += dt;
+        }
+        self.position
+    }
+
+    /// Current trajectory time, or `None` if not locked to timecode.
+    #[inline]
+    pub fn position(&self) -> Option<f64> {
+        self.position
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_drop_frame_round_trip() {
+        let rate = FrameRate::Fps2997Drop;
+        // First frame after the drop at minute 1
+        let tc = Timecode::parse("00:01:00;02", rate).unwrap();
+        assert_eq!(tc.frame_number(), 1800);
+        assert_eq!(Timecode::from_seconds(tc.to_seconds(), rate), tc);
+        assert!(Timecode::parse("00:01:00;00", rate).is_err());
+        assert!(Timecode::parse("00:10:00;00", rate).is_ok());
+
+        for frame in [0u64, 1799, 1800, 17981, 17982, 107892] {
+            let tc = Timecode::from_seconds(frame as f64 / rate.fps(), rate);
+            assert_eq!(tc.frame_number(), frame);
+        }
+        assert_eq!(tc.to_string(), "00:01:00;02");
+    }
+
+    #[test]
+    fn test_mtc_quarter_frames() {
+        let mut decoder = MtcDecoder::new();
+        // 01:02:03:04 at 25 fps
+        let pieces = [4u8, 0, 3, 0, 2, 0, 1, 1 << 1];
+        let mut result = None;
+        for (i, value) in pieces.iter().enumerate() {
+            result = decoder.quarter_frame((i as u8) << 4 | value);
+        }
+        let tc = result.unwrap();
+        assert_eq!(tc.rate, FrameRate::Fps25);
+        assert_eq!((tc.hours, tc.minutes, tc.seconds, tc.frames), (1, 2, 3, 6));
+
+        let full = [0xF0, 0x7F, 0x7F, 0x01, 0x01, 0x20 | 10, 0, 0, 0, 0xF7];
+        let tc = decoder.full_frame(&full).unwrap();
+        assert_eq!(tc.hours, 10);
+        assert_eq!(tc.rate, FrameRate::Fps25);
+    }
+
+    #[test]
+    fn test_chase_freewheel() {
+        let start = Timecode::new(1, 0, 0, 0, FrameRate::Fps24).unwrap();
+        let mut chase = TimecodeChase::new(start).with_freewheel(0.2);
+        assert_eq!(chase.advance(0.1), None);
+
+        chase.update(Timecode::new(1, 0, 2, 12, FrameRate::Fps24).unwrap());
+        assert_relative_eq!(chase.advance(0.1).unwrap(), 2.6, epsilon = 1e-9);
+        assert_eq!(chase.advance(0.15), None);
+    }
+}
@@ -0,0 +1,100 @@
+//! Level conversions and gain vector helpers.
+//!
+//! Small functions that code around a panner keeps needing: dB conversion,
+//! the RMS and peak of a gain vector, and scaling gains to a target level.
+
+use crate::panner::Normalization;
+
+/// Convert a linear gain to dB.
+///
+/// The sign is ignored, and a gain of 0 gives negative infinity.
+#[inline]
+pub fn lin_to_db(gain: f64) -> f64 {
+    20.0 * gain.abs().log10()
+}
+
+/// Convert a level in dB to a linear gain.
+///
+/// Negative infinity gives 0.
+#[inline]
+pub fn db_to_lin(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+/// Get the root mean square of gains, 0 for an empty slice.
+pub fn rms(gains: &[f64]) -> f64 {
+    if gains.is_empty() {
+        return 0.0;
+    }
+    (gains.iter().map(|g| g * g).sum::<f64>() / gains.len() as f64).sqrt()
+}
+
+/// Get the largest absolute gain, 0 for an empty slice.
+pub fn peak(gains: &[f64]) -> f64 {
+    gains.iter().fold(0.0, |peak: f64, g| peak.max(g.abs()))
+}
+
+/// Scale gains in place so that their level, measured as `normalization`
+/// does, equals the linear `target`.
+///
+/// Silent gains are left unchanged. Use [`db_to_lin`] for a target in dB.
+///
+/// # Example
+///
+/// ```
+/// use vbap::util::{db_to_lin, normalize, peak};
+/// use vbap::Normalization;
+///
+/// let mut gains = [0.2, 0.4, 0.0];
+/// normalize(&mut gains, Normalization::MaxGainOne, db_to_lin(-6.0));
+/// assert!((peak(&gains) - db_to_lin(-6.0)).abs() < 1e-12);
+/// ```
+pub fn normalize(gains: &mut [f64], normalization: Normalization, target: f64) {
+    let factor = normalization.factor(gains, 0.0);
+    if factor > 0.0 {
+        for gain in gains {
+            *gain *= factor * target;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_db_conversion() {
+        assert_relative_eq!(lin_to_db(0.5), -6.0206, epsilon = 1e-4);
+        assert_relative_eq!(lin_to_db(-2.0), lin_to_db(2.0));
+        assert_eq!(lin_to_db(0.0), f64::NEG_INFINITY);
+        assert_eq!(db_to_lin(f64::NEG_INFINITY), 0.0);
+        for db in [-60.0, -3.0, 0.0, 12.0] {
+            assert_relative_eq!(lin_to_db(db_to_lin(db)), db, epsilon = 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_rms_and_peak() {
+        assert_relative_eq!(rms(&[1.0, -1.0, 1.0, -1.0]), 1.0);
+        assert_relative_eq!(rms(&[3.0, 4.0]), (12.5f64).sqrt());
+        assert_eq!(rms(&[]), 0.0);
+        assert_eq!(peak(&[0.5, -0.8, 0.1]), 0.8);
+        assert_eq!(peak(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_normalize() {
+        let mut gains = [3.0, 4.0];
+        normalize(&mut gains, Normalization::Power, 1.0);
+        assert_relative_eq!(gains[0], 0.6);
+        assert_relative_eq!(gains[1], 0.8);
+
+        normalize(&mut gains, Normalization::Amplitude, 0.5);
+        assert_relative_eq!(gains.iter().sum::<f64>(), 0.5);
+
+        let mut silent = [0.0; 3];
+        normalize(&mut silent, Normalization::Power, 1.0);
+        assert_eq!(silent, [0.0; 3]);
+    }
+}
@@ -0,0 +1,268 @@
+//! SVG plots of layouts and gains.
+//!
+//! [`LayoutPlot`] draws a speaker layout from above and from the side,
+//! with the panning tuples, the selected tuple and each speaker colored by
+//! its gain for a source direction. Attach the picture to a bug report
+//! about the wrong speakers being active.
+//!
+//! Requires the `viz` feature.
+
+use std::fmt::Write;
+
+use glam::DVec3;
+
+use crate::math::spherical_to_cartesian;
+use crate::panner::VBAPanner;
+
+/// Projection used for a plot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum View {
+    /// Seen from above, front at the top and left on the left.
+    #[default]
+    Top,
+    /// Seen from the right, front to the right and up at the top.
+    Side,
+}
+
+impl View {
+    /// Project a unit direction to plot coordinates in [-1, 1], y down.
+    fn project(self, v: DVec3) -> (f64, f64) {
+        match self {
+            View::Top => (-v.x, -v.y),
+            View::Side => (v.y, -v.z),
+        }
+    }
+}
+
+/// An SVG plot of a panner's layout and gains.
+///
+/// # Example
+///
+/// ```
+/// use vbap::viz::{LayoutPlot, View};
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().atmos_7_1_4().build().unwrap();
+/// let svg = LayoutPlot::new(&panner).with_source(45.0, 30.0).render(View::Top);
+/// assert!(svg.starts_with("<svg"));
+///
+/// // Both views next to each other
+/// let svg = LayoutPlot::new(&panner).with_source(45.0, 30.0).to_svg();
+/// assert!(svg.contains("Side"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct LayoutPlot<'a> {
+    panner: &'a VBAPanner,
+    source: Option<(f64, f64)>,
+    size: f64,
+}
+
+impl<'a> LayoutPlot<'a> {
+    /// Plot a panner's layout, 400 pixels per view.
+    pub fn new(panner: &'a VBAPanner) -> Self {
+        Self {
+            panner,
+            source: None,
+            size: 400.0,
+        }
+    }
+
+    /// Show a source direction, in the panner's convention, and color the
+    /// speakers by their gains for it.
+    pub fn with_source(mut self, azimuth: f64, elevation: f64) -> Self {
+        self.source = Some((azimuth, elevation));
+        self
+    }
+
+    /// Set the width and height of each view in pixels.
+    pub fn with_size(mut self, size: f64) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Render one view as a standalone SVG document.
+    pub fn render(&self, view: View) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{s}" height="{s}" viewBox="0 0 {s} {s}">"#,
+            s = self.size
+        );
+        self.draw(&mut out, view, 0.0);
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Render the top and side views next to each other as one SVG
+    /// document.
+    pub fn to_svg(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{s}" viewBox="0 0 {w} {s}">"#,
+            w = 2.0 * self.size,
+            s = self.size
+        );
+        self.draw(&mut out, View::Top, 0.0);
+        self.draw(&mut out, View::Side, self.size);
+        out.push_str("</svg>\n");
+        out
+    }
+
+    /// Draw one view into a group offset `x` pixels to the right.
+    fn draw(&self, out: &mut String, view: View, x: f64) {
+        let half = self.size / 2.0;
+        let radius = half * 0.8;
+        let point = |v: DVec3| {
+            let (px, py) = view.project(v);
+            (half + px * radius, half + py * radius)
+        };
+
+        let speakers: Vec<DVec3> = self
+            .panner
+            .speakers()
+            .iter()
+            .map(|speaker| speaker.cartesian().normalize())
+            .collect();
+        let gains = self
+            .source
+            .map(|(azimuth, elevation)| self.panner.compute_gains(azimuth, elevation));
+
+        let _ = write!(
+            out,
+            r#"<g transform="translate({x} 0)" font-family="sans-serif" font-size="{}">"#,
+            self.size / 40.0
+        );
+        let title = match view {
+            View::Top => "Top",
+            View::Side => "Side",
+        };
+        let _ = write!(
+            out,
+            r#"<text x="8" y="{}">{title}</text>"#,
+            self.size / 20.0
+        );
+        let _ = write!(
+            out,
+            r##"<circle cx="{half}" cy="{half}" r="{radius}" fill="none" stroke="#bbbbbb"/>"##
+        );
+        let _ = write!(
+            out,
+            r##"<circle cx="{half}" cy="{half}" r="3" fill="#333333"/>"##
+        );
+
+        // Tuples, with the one carrying the source filled in
+        for tuple in self.panner.config().tuples().iter() {
+            let indices = tuple.speaker_indices();
+            let active = gains
+                .as_ref()
+                .is_some_and(|gains| indices.iter().all(|&i| gains[i] > 0.0));
+            let points: Vec<String> = indices
+                .iter()
+                .map(|&i| {
+                    let (px, py) = point(speakers[i]);
+                    format!("{px:.2},{py:.2}")
+                })
+                .collect();
+            let (fill, stroke) = if active {
+                ("#ff7f0e55", "#ff7f0e")
+            } else {
+                ("none", "#999999")
+            };
+            let _ = write!(
+                out,
+                r#"<polygon points="{}" fill="{fill}" stroke="{stroke}"/>"#,
+                points.join(" ")
+            );
+        }
+
+        // Speakers, colored by gain
+        for (i, (&v, speaker)) in speakers.iter().zip(self.panner.speakers()).enumerate() {
+            let (px, py) = point(v);
+            let gain = gains.as_ref().map_or(0.0, |gains| gains[i]);
+            let dash = if speaker.is_virtual() {
+                r#" stroke-dasharray="2 2""#
+            } else {
+                ""
+            };
+            let _ = write!(
+                out,
+                r#"<circle class="speaker" cx="{px:.2}" cy="{py:.2}" r="{:.2}" fill="{}" stroke="black"{dash}><title>{i}: {:.1}/{:.1} gain {gain:.3}</title></circle>"#,
+                self.size / 40.0,
+                heat(gain),
+                speaker.azimuth(),
+                speaker.elevation(),
+            );
+            let _ = write!(
+                out,
+                r#"<text x="{:.2}" y="{:.2}">{i}</text>"#,
+                px + self.size / 30.0,
+                py
+            );
+        }
+
+        if let Some((azimuth, elevation)) = self.source {
+            let (azimuth, elevation) = self
+                .panner
+                .config()
+                .convention()
+                .to_native(azimuth, elevation);
+            let (px, py) = point(spherical_to_cartesian(azimuth, elevation));
+            let arm = self.size / 50.0;
+            let _ = write!(
+                out,
+                r##"<path d="M{:.2} {:.2}L{:.2} {:.2}M{:.2} {:.2}L{:.2} {:.2}" stroke="#1f77b4" stroke-width="2"/>"##,
+                px - arm,
+                py - arm,
+                px + arm,
+                py + arm,
+                px - arm,
+                py + arm,
+                px + arm,
+                py - arm
+            );
+        }
+        out.push_str("</g>");
+    }
+}
+
+/// Color for a gain, from light gray at 0 to red at 1.
+fn heat(gain: f64) -> String {
+    let t = gain.clamp(0.0, 1.0);
+    let lerp = |a: f64, b: f64| (a + (b - a) * t).round() as u8;
+    format!(
+        "rgb({},{},{})",
+        lerp(230.0, 214.0),
+        lerp(230.0, 39.0),
+        lerp(230.0, 40.0)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_tuple_highlighted() {
+        let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+        let svg = LayoutPlot::new(&panner)
+            .with_source(15.0, 0.0)
+            .render(View::Top);
+
+        assert_eq!(svg.matches(r#"class="speaker""#).count(), 5);
+        assert_eq!(svg.matches("#ff7f0e55").count(), 1);
+        // Speakers are colored by their gains
+        assert!(svg.contains(&heat(panner.compute_gains(15.0, 0.0)[0])));
+        assert!(svg.ends_with("</svg>\n"));
+
+        let plain = LayoutPlot::new(&panner).render(View::Side);
+        assert!(!plain.contains("#ff7f0e55"));
+    }
+
+    #[test]
+    fn test_heat_scale() {
+        assert_eq!(heat(0.0), "rgb(230,230,230)");
+        assert_eq!(heat(1.0), "rgb(214,39,40)");
+        assert_eq!(heat(2.0), heat(1.0));
+    }
+}
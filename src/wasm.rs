@@ -0,0 +1,199 @@
+//! JavaScript bindings for WebAssembly.
+//!
+//! Exposes the builder, the presets and gain computation through
+//! `wasm-bindgen`, so Web Audio applications can pan client-side. Build for
+//! the web with
+//!
+//! ```text
+//! cargo rustc --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/vbap.wasm
+//! ```
+//!
+//! and use it from JavaScript:
+//!
+//! ```text
+//! import init, { Panner, PannerBuilder } from "./pkg/vbap.js";
+//!
+//! await init();
+//! const surround = Panner.preset("5.1");
+//! const quad = new PannerBuilder()
+//!     .addSpeaker(45, 0).addSpeaker(-45, 0)
+//!     .addSpeaker(135, 0).addSpeaker(-135, 0)
+//!     .build();
+//!
+//! const gains = new Float64Array(quad.numSpeakers);
+//! quad.computeGainsInto(20, 0, gains); // no allocation per frame
+//! ```
+//!
+//! Errors are thrown as JavaScript `Error`s.
+//!
+//! Requires the `wasm` feature.
+
+use wasm_bindgen::prelude::*;
+
+use crate::config::{SpeakerConfigBuilder, PRESET_NAMES};
+use crate::panner::VBAPanner;
+
+/// A built panner.
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct Panner {
+    inner: VBAPanner,
+}
+
+#[wasm_bindgen]
+impl Panner {
+    /// Create a panner for a preset layout by name, such as `"stereo"`,
+    /// `"5.1"` or `"7.1.4"`.
+    pub fn preset(name: &str) -> Result<Panner, JsError> {
+        PannerBuilder::new().preset(name)?.build()
+    }
+
+    /// Get the names accepted by [`preset`](Self::preset).
+    #[wasm_bindgen(js_name = presetNames)]
+    pub fn preset_names() -> Vec<String> {
+        PRESET_NAMES.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// Get the number of speakers.
+    #[wasm_bindgen(getter, js_name = numSpeakers)]
+    pub fn num_speakers(&self) -> usize {
+        self.inner.num_speakers()
+    }
+
+    /// Compute speaker gains for a source direction in degrees, as a new
+    /// `Float64Array`.
+    #[wasm_bindgen(js_name = computeGains)]
+    pub fn compute_gains(&self, azimuth: f64, elevation: f64) -> Vec<f64> {
+        self.inner.compute_gains(azimuth, elevation)
+    }
+
+    /// Compute speaker gains into an existing `Float64Array`.
+    #[wasm_bindgen(js_name = computeGainsInto)]
+    pub fn compute_gains_into(
+        &self,
+        azimuth: f64,
+        elevation: f64,
+        gains: &mut [f64],
+    ) -> Result<(), JsError> {
+        let n = self.inner.num_speakers();
+        if gains.len() < n {
+            return Err(JsError::new(&format!(
+                "gains array too small: {} < {}",
+                gains.len(),
+                n
+            )));
+        }
+        self.inner.compute_gains_into(azimuth, elevation, gains);
+        Ok(())
+    }
+
+    /// Get the speaker directions as `[azimuth, elevation, ...]` pairs in
+    /// degrees.
+    #[wasm_bindgen(js_name = speakerDirections)]
+    pub fn speaker_directions(&self) -> Vec<f64> {
+        self.inner
+            .config()
+            .speakers()
+            .iter()
+            .flat_map(|speaker| [speaker.azimuth(), speaker.elevation()])
+            .collect()
+    }
+}
+
+impl From<VBAPanner> for Panner {
+    fn from(inner: VBAPanner) -> Self {
+        Self { inner }
+    }
+}
+
+impl From<Panner> for VBAPanner {
+    fn from(panner: Panner) -> Self {
+        panner.inner
+    }
+}
+
+/// Builder for custom layouts.
+///
+/// Like [`SpeakerConfigBuilder`], each method consumes the builder and
+/// returns it, so calls chain.
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct PannerBuilder {
+    inner: SpeakerConfigBuilder,
+}
+
+#[wasm_bindgen]
+impl PannerBuilder {
+    /// Create an empty builder.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> PannerBuilder {
+        Self::default()
+    }
+
+    /// Add a speaker at an azimuth and elevation in degrees.
+    #[wasm_bindgen(js_name = addSpeaker)]
+    pub fn add_speaker(self, azimuth: f64, elevation: f64) -> PannerBuilder {
+        Self {
+            inner: self.inner.add_speaker(azimuth, elevation),
+        }
+    }
+
+    /// Add the speakers of a preset layout by name.
+    pub fn preset(self, name: &str) -> Result<PannerBuilder, JsError> {
+        Ok(Self {
+            inner: self.inner.preset(name)?,
+        })
+    }
+
+    /// Leave the widest gap of a horizontal layout unpanned, for
+    /// front-only arcs.
+    #[wasm_bindgen(js_name = openArc)]
+    pub fn open_arc(self) -> PannerBuilder {
+        Self {
+            inner: self.inner.open_arc(),
+        }
+    }
+
+    /// Triangulate the layout and create the panner.
+    pub fn build(self) -> Result<Panner, JsError> {
+        Ok(self.inner.build()?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `JsError` needs a JavaScript host, so only the success paths run
+    // natively.
+    #[test]
+    fn test_builder_and_gains() {
+        let panner = PannerBuilder::new()
+            .add_speaker(30.0, 0.0)
+            .add_speaker(-30.0, 0.0)
+            .add_speaker(0.0, 0.0)
+            .open_arc()
+            .build()
+            .unwrap();
+        assert_eq!(panner.num_speakers(), 3);
+
+        let mut gains = [0.0; 3];
+        panner.compute_gains_into(0.0, 0.0, &mut gains).unwrap();
+        assert!((gains[2] - 1.0).abs() < 1e-9);
+        assert_eq!(panner.compute_gains(0.0, 0.0), gains);
+        assert_eq!(
+            panner.speaker_directions(),
+            [30.0, 0.0, -30.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn test_presets() {
+        for name in Panner::preset_names() {
+            assert!(Panner::preset(&name).unwrap().num_speakers() >= 2);
+        }
+        let panner: VBAPanner = Panner::preset("7.1.4").unwrap().into();
+        assert_eq!(panner.num_speakers(), 11);
+    }
+}
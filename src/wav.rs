@@ -0,0 +1,236 @@
+//! Offline rendering of mono WAV files.
+//!
+//! [`WavRenderer`] pans a mono recording along a [`Trajectory`] and writes
+//! one channel per speaker, in speaker order, as a 32-bit float WAV file at
+//! the input's sample rate. Rendering goes through a [`Mixer`], so gains
+//! ramp smoothly across blocks just as in a real-time host.
+//!
+//! Requires the `wav` feature.
+
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+use crate::error::{Result, VBAPError};
+use crate::mixer::Mixer;
+use crate::panner::VBAPanner;
+use crate::trajectory::Trajectory;
+
+/// Default number of samples between gain updates.
+const DEFAULT_BLOCK_SIZE: usize = 256;
+
+/// Renders a mono WAV file through a layout.
+///
+/// # Example
+///
+/// ```no_run
+/// use vbap::trajectory::{Keyframe, Trajectory};
+/// use vbap::wav::WavRenderer;
+/// use vbap::VBAPanner;
+///
+/// let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+/// // Front to rear left over four seconds
+/// let trajectory = Trajectory::new(vec![
+///     Keyframe::new(0.0, 0.0, 0.0),
+///     Keyframe::new(4.0, 110.0, 0.0),
+/// ])
+/// .unwrap();
+///
+/// WavRenderer::new(panner)
+///     .render_file("voice.wav", "voice_5_1.wav", &trajectory)
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct WavRenderer {
+    panner: VBAPanner,
+    block_size: usize,
+}
+
+impl WavRenderer {
+    /// Create a renderer for a panner's layout.
+    pub fn new(panner: VBAPanner) -> Self {
+        Self {
+            panner,
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+
+    /// Set the number of samples between gain updates (default 256, at
+    /// least 1).
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    /// Get the number of samples between gain updates.
+    #[inline]
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    /// Get the panner.
+    #[inline]
+    pub fn panner(&self) -> &VBAPanner {
+        &self.panner
+    }
+
+    /// Render WAV data from `input` to `output`.
+    ///
+    /// The input may be integer or float PCM but must be mono; the output
+    /// is as long as the input. The source follows `trajectory` from time 0
+    /// and holds its last position after the trajectory ends.
+    ///
+    /// Returns [`VBAPError::Audio`] if the input cannot be decoded, is not
+    /// mono, or the output cannot be written.
+    pub fn render<R: Read, W: Write + Seek>(
+        &self,
+        input: R,
+        output: W,
+        trajectory: &Trajectory,
+    ) -> Result<()> {
+        let reader = WavReader::new(input).map_err(audio_error)?;
+        let spec = reader.spec();
+        if spec.channels != 1 {
+            return Err(VBAPError::Audio(format!(
+                "expected a mono input, got {} channels",
+                spec.channels
+            )));
+        }
+        let samples = read_samples(reader)?;
+
+        let mut mixer = Mixer::new(self.panner.clone());
+        let source = mixer.add_source();
+        mixer.set_trajectory(source, trajectory.clone());
+        let channels = mixer.render(&[&samples], spec.sample_rate as f64, self.block_size);
+
+        let spec = WavSpec {
+            channels: channels.len() as u16,
+            sample_rate: spec.sample_rate,
+            bits_per_sample: 32,
+            sample_format: SampleFormat::Float,
+        };
+        let mut writer = WavWriter::new(output, spec).map_err(audio_error)?;
+        for frame in 0..samples.len() {
+            for channel in &channels {
+                writer.write_sample(channel[frame]).map_err(audio_error)?;
+            }
+        }
+        writer.finalize().map_err(audio_error)
+    }
+
+    /// Render the WAV file at `input` to a new file at `output`.
+    ///
+    /// See [`render`](Self::render).
+    pub fn render_file(
+        &self,
+        input: impl AsRef<Path>,
+        output: impl AsRef<Path>,
+        trajectory: &Trajectory,
+    ) -> Result<()> {
+        let reader = std::fs::File::open(input.as_ref())
+            .map_err(|e| VBAPError::Audio(format!("{}: {}", input.as_ref().display(), e)))?;
+        let writer = std::fs::File::create(output.as_ref())
+            .map_err(|e| VBAPError::Audio(format!("{}: {}", output.as_ref().display(), e)))?;
+        self.render(
+            std::io::BufReader::new(reader),
+            std::io::BufWriter::new(writer),
+            trajectory,
+        )
+    }
+}
+
+/// Decode all samples to `f32` in `[-1, 1]`.
+fn read_samples<R: Read>(mut reader: WavReader<R>) -> Result<Vec<f32>> {
+    let spec = reader.spec();
+    match spec.sample_format {
+        SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<std::result::Result<_, _>>()
+            .map_err(audio_error),
+        SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 * scale))
+                .collect::<std::result::Result<_, _>>()
+                .map_err(audio_error)
+        }
+    }
+}
+
+fn audio_error(error: hound::Error) -> VBAPError {
+    VBAPError::Audio(error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::trajectory::Keyframe;
+    use std::io::Cursor;
+
+    fn wav(spec: WavSpec, samples: &[i32]) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        let mut writer = WavWriter::new(&mut bytes, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn test_render_integer_input() {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let input = wav(spec, &[16384; 100]);
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let trajectory = Trajectory::new(vec![Keyframe::new(0.0, 30.0, 0.0)]).unwrap();
+
+        let mut output = Cursor::new(Vec::new());
+        WavRenderer::new(panner)
+            .with_block_size(10)
+            .render(Cursor::new(input), &mut output, &trajectory)
+            .unwrap();
+
+        output.set_position(0);
+        let mut reader = WavReader::new(output).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.spec().sample_rate, 8000);
+        let samples: Vec<f32> = reader.samples().map(|s| s.unwrap()).collect();
+        assert_eq!(samples.len(), 200);
+        // After the first block's ramp, all of it is on the left speaker
+        assert_eq!(&samples[198..], [0.5, 0.0]);
+    }
+
+    #[test]
+    fn test_rejects_stereo_input() {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let input = wav(spec, &[0; 4]);
+        let panner = VBAPanner::builder().stereo().build().unwrap();
+        let trajectory = Trajectory::new(vec![Keyframe::new(0.0, 0.0, 0.0)]).unwrap();
+        let result = WavRenderer::new(panner).render(
+            Cursor::new(input),
+            Cursor::new(Vec::new()),
+            &trajectory,
+        );
+        assert!(matches!(result, Err(VBAPError::Audio(_))));
+        assert!(matches!(
+            WavRenderer::new(VBAPanner::builder().stereo().build().unwrap()).render(
+                Cursor::new(b"not a wav file".to_vec()),
+                Cursor::new(Vec::new()),
+                &trajectory,
+            ),
+            Err(VBAPError::Audio(_))
+        ));
+    }
+}
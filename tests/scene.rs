@@ -0,0 +1,121 @@
+//! End-to-end render of a full scene: a 7.1.4 rig, 32 moving sources
+//! (orbits and a swarm) mixed offline for several seconds.
+
+use std::f64::consts::TAU;
+
+use glam::DVec3;
+use vbap::mixer::Mixer;
+use vbap::trajectory::{Motion, Orbit, Swarm};
+use vbap::VBAPanner;
+
+const SAMPLE_RATE: f64 = 48000.0;
+const BLOCK: usize = 256;
+const SECONDS: usize = 4;
+const NUM_ORBITS: usize = 16;
+const NUM_SOURCES: usize = 32;
+const AMPLITUDE: f64 = 1.0 / NUM_SOURCES as f64;
+
+#[test]
+fn test_render_7_1_4_scene() {
+    let panner = VBAPanner::builder()
+        .atmos_7_1_4()
+        .build()
+        .unwrap()
+        .with_hysteresis(0.05);
+    let num_outputs = panner.num_speakers();
+    let mut mixer = Mixer::new(panner);
+    let ids: Vec<_> = (0..NUM_SOURCES).map(|_| mixer.add_source()).collect();
+
+    // Half the sources circle at different speeds and heights, the rest
+    // flock over the front. The rig has no speakers below the horizon, so
+    // everything stays above it
+    let orbits: Vec<_> = (0..NUM_ORBITS)
+        .map(|i| {
+            let height = (i % 4) as f64 * 0.6;
+            Orbit::new(DVec3::new(0.0, 0.0, height), 2.0, 30.0 + 15.0 * i as f64)
+                .with_phase(i as f64 * 360.0 / NUM_ORBITS as f64)
+        })
+        .collect();
+    let mut swarm = Swarm::new(NUM_SOURCES - NUM_ORBITS, 7).with_bounds(0.0, 45.0, 45.0);
+
+    // One sine per source at distinct frequencies, so cross terms average out
+    let frequencies: Vec<f64> = (0..NUM_SOURCES).map(|i| 110.0 + 20.0 * i as f64).collect();
+    let mut inputs = vec![vec![0.0f32; BLOCK]; NUM_SOURCES];
+    let mut outputs = vec![vec![0.0f32; BLOCK]; num_outputs];
+
+    let num_blocks = SECONDS * SAMPLE_RATE as usize / BLOCK;
+    let dt = BLOCK as f64 / SAMPLE_RATE;
+    let mut input_energy = 0.0;
+    let mut output_energy = vec![0.0; num_outputs];
+    let mut peak = 0.0f32;
+    let mut max_step = 0.0f32;
+    let mut previous = vec![0.0f32; num_outputs];
+
+    for block in 0..num_blocks {
+        let time = block as f64 * dt;
+        for (i, orbit) in orbits.iter().enumerate() {
+            let (azimuth, elevation) = orbit.direction(time);
+            mixer.set_position(ids[i], azimuth, elevation);
+        }
+        swarm.step(dt);
+        for (i, &(azimuth, elevation)) in swarm.directions().iter().enumerate() {
+            mixer.set_position(ids[NUM_ORBITS + i], azimuth, elevation);
+        }
+
+        for (input, frequency) in inputs.iter_mut().zip(&frequencies) {
+            for (n, sample) in input.iter_mut().enumerate() {
+                let t = (block * BLOCK + n) as f64 / SAMPLE_RATE;
+                *sample = (AMPLITUDE * (TAU * frequency * t).sin()) as f32;
+                // The first block fades in from silence
+                if block > 0 {
+                    input_energy += (*sample as f64).powi(2);
+                }
+            }
+        }
+
+        let input_refs: Vec<&[f32]> = inputs.iter().map(Vec::as_slice).collect();
+        let mut output_refs: Vec<&mut [f32]> = outputs.iter_mut().map(Vec::as_mut_slice).collect();
+        mixer.process(&input_refs, &mut output_refs);
+
+        for (channel, output) in outputs.iter().enumerate() {
+            for &sample in output {
+                assert!(
+                    sample.is_finite(),
+                    "non-finite sample in channel {}",
+                    channel
+                );
+                peak = peak.max(sample.abs());
+                max_step = max_step.max((sample - previous[channel]).abs());
+                previous[channel] = sample;
+                if block > 0 {
+                    output_energy[channel] += (sample as f64).powi(2);
+                }
+            }
+        }
+    }
+
+    // Every gain is at most 1, so the mix can never exceed the input sum
+    assert!(peak <= 1.0, "peak {}", peak);
+    // Ramped gains keep the output free of clicks: no sample jumps further
+    // than the sines themselves allow
+    assert!(max_step < 0.1, "largest sample step {}", max_step);
+
+    // Power normalization preserves the scene's total energy
+    let total: f64 = output_energy.iter().sum();
+    assert!(
+        (total / input_energy - 1.0).abs() < 0.05,
+        "output/input energy ratio {}",
+        total / input_energy
+    );
+
+    // The orbits sweep every speaker, including the height layer
+    for (channel, energy) in output_energy.iter().enumerate() {
+        assert!(
+            *energy > total * 1e-3,
+            "channel {} is silent ({:.3e} of {:.3e})",
+            channel,
+            energy,
+            total
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! Offline render of a tone flying around a 5.1 rig, checked through the
+//! WAV files it produces.
+
+use std::f32::consts::TAU;
+use std::io::Cursor;
+
+use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+use vbap::trajectory::{Keyframe, Trajectory};
+use vbap::wav::WavRenderer;
+use vbap::VBAPanner;
+
+const SAMPLE_RATE: u32 = 48000;
+const SECONDS: u32 = 2;
+
+/// RMS of one channel between two times in seconds.
+fn rms(frames: &[Vec<f32>], channel: usize, start: f64, end: f64) -> f32 {
+    let range = (start * SAMPLE_RATE as f64) as usize..(end * SAMPLE_RATE as f64) as usize;
+    let len = range.len() as f32;
+    (frames[range]
+        .iter()
+        .map(|f| f[channel] * f[channel])
+        .sum::<f32>()
+        / len)
+        .sqrt()
+}
+
+#[test]
+fn test_render_5_1_flyover() {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: SAMPLE_RATE,
+        bits_per_sample: 24,
+        sample_format: SampleFormat::Int,
+    };
+    let mut input = Cursor::new(Vec::new());
+    let mut writer = WavWriter::new(&mut input, spec).unwrap();
+    for i in 0..SAMPLE_RATE * SECONDS {
+        let sample = (TAU * 440.0 * i as f32 / SAMPLE_RATE as f32).sin() * 0.5;
+        writer.write_sample((sample * 8388607.0) as i32).unwrap();
+    }
+    writer.finalize().unwrap();
+    input.set_position(0);
+
+    // Left front (30°) to left surround (110°) over the first second, then
+    // held there
+    let trajectory = Trajectory::new(vec![
+        Keyframe::new(0.0, 30.0, 0.0),
+        Keyframe::new(1.0, 110.0, 0.0),
+    ])
+    .unwrap();
+    let panner = VBAPanner::builder().surround_5_1().build().unwrap();
+    let mut output = Cursor::new(Vec::new());
+    WavRenderer::new(panner)
+        .render(input, &mut output, &trajectory)
+        .unwrap();
+
+    output.set_position(0);
+    let mut reader = WavReader::new(output).unwrap();
+    assert_eq!(reader.spec().channels, 5);
+    assert_eq!(reader.spec().sample_format, SampleFormat::Float);
+    let samples: Vec<f32> = reader.samples().map(|s| s.unwrap()).collect();
+    let frames: Vec<Vec<f32>> = samples.chunks(5).map(<[f32]>::to_vec).collect();
+    assert_eq!(frames.len(), (SAMPLE_RATE * SECONDS) as usize);
+
+    let tone = 0.5 / 2f32.sqrt();
+    // Start: all on L
+    assert!((rms(&frames, 0, 0.01, 0.02) - tone).abs() < 0.01);
+    assert!(rms(&frames, 3, 0.01, 0.02) < 0.01);
+    // Halfway (70°): shared between L and Ls
+    let (l, ls) = (rms(&frames, 0, 0.49, 0.51), rms(&frames, 3, 0.49, 0.51));
+    assert!(l > 0.1 && ls > 0.1);
+    assert!(((l * l + ls * ls).sqrt() - tone).abs() < 0.01);
+    // End: all on Ls, nothing on the right
+    assert!((rms(&frames, 3, 1.5, 2.0) - tone).abs() < 0.01);
+    assert!(rms(&frames, 0, 1.5, 2.0) < 1e-6);
+    for channel in [1, 2, 4] {
+        assert!(rms(&frames, channel, 0.0, 2.0) < 1e-6);
+    }
+}